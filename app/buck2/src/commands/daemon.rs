@@ -28,6 +28,7 @@ use buck2_common::legacy_configs::init::DaemonStartupConfig;
 use buck2_common::memory;
 use buck2_core::buck2_env;
 use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::file_name::FileName;
 use buck2_core::logging::LogConfigurationReloadHandle;
 use buck2_server::builtin_docs::docs::docs_command;
 use buck2_server::daemon::daemon_tcp::create_listener;
@@ -60,6 +61,9 @@ enum DaemonError {
     PidFileMismatch(PathBuf, u32, u32),
 }
 
+/// Written and then renamed to `buckd.info`.
+const BUCKD_INFO_TMP: &FileName = FileName::unchecked_new("buckd.info.tmp");
+
 /// Start or run buck daemon.
 ///
 /// This is an internal command, not intended to be used directly.
@@ -123,7 +127,8 @@ impl BuckdServerDependencies for BuckdServerDependenciesImpl {
         req: buck2_cli_proto::ProfileRequest,
     ) -> anyhow::Result<buck2_cli_proto::ProfileResponse> {
         match req.profile_opts.as_ref().expect("Missing profile opts") {
-            buck2_cli_proto::profile_request::ProfileOpts::TargetProfile(_) => {
+            buck2_cli_proto::profile_request::ProfileOpts::TargetProfile(_)
+            | buck2_cli_proto::profile_request::ProfileOpts::AnalysisBench(_) => {
                 profile_command(ctx, partial_result_dispatcher, req).await
             }
             buck2_cli_proto::profile_request::ProfileOpts::BxlProfile(_) => {
@@ -155,9 +160,39 @@ pub(crate) fn init_listener() -> anyhow::Result<(std::net::TcpListener, Connecti
 pub(crate) fn write_process_info(
     daemon_dir: &DaemonDir,
     process_info: &DaemonProcessInfo,
+    permissions: u32,
 ) -> anyhow::Result<()> {
-    let file = File::create(daemon_dir.buckd_info())?;
+    // Write to a fresh file and rename it into place: a file left over from a previous daemon
+    // keeps its old mode when truncated, and readers never observe a partially written file.
+    let path = daemon_dir.buckd_info();
+    let tmp_path = daemon_dir.path.join(BUCKD_INFO_TMP);
+    if fs_util::try_exists(&tmp_path)? {
+        fs_util::remove_file(&tmp_path)?;
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(permissions);
+    }
+    #[cfg(not(unix))]
+    let _ = permissions;
+    let file = options
+        .open(&tmp_path)
+        .with_context(|| format!("Error creating `{}`", tmp_path))?;
+    // The mode passed at creation is subject to the umask, so set it explicitly too. Nothing has
+    // been written yet, so the file never holds the auth token with a wider mode.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(permissions))?;
+    }
     serde_json::to_writer(&file, &process_info)?;
+    drop(file);
+
+    fs_util::rename(&tmp_path, &path)?;
     Ok(())
 }
 
@@ -228,6 +263,9 @@ impl DaemonCommand {
         let stderr = File::create(stderr_path)?;
 
         let auth_token = gen_auth_token();
+        let endpoint_permissions = server_init_ctx
+            .daemon_startup_config
+            .endpoint_permissions()?;
 
         let (listener, process_info, endpoint) = if !self.dont_daemonize {
             // We must create stdout/stderr before creating a listener,
@@ -251,7 +289,7 @@ impl DaemonCommand {
 
             // TODO(nga): this code is executed after server daemonization,
             //   so client has to retry to read it. Fix it.
-            write_process_info(&daemon_dir, &process_info)?;
+            write_process_info(&daemon_dir, &process_info, endpoint_permissions)?;
 
             tracing::info!("Daemonized.");
 
@@ -272,7 +310,7 @@ impl DaemonCommand {
                 auth_token,
             };
 
-            write_process_info(&daemon_dir, &process_info)?;
+            write_process_info(&daemon_dir, &process_info, endpoint_permissions)?;
 
             (listener, process_info, endpoint)
         };
@@ -543,9 +581,11 @@ mod tests {
     use buck2_cli_proto::PingRequest;
     use buck2_client_ctx::daemon::client::connect::new_daemon_api_client;
     use buck2_client_ctx::daemon_constraints::gen_daemon_constraints;
+    use buck2_common::daemon_dir::DaemonDir;
     use buck2_common::invocation_paths::InvocationPaths;
     use buck2_common::invocation_roots::InvocationRoots;
     use buck2_common::legacy_configs::init::DaemonStartupConfig;
+    use buck2_core::fs::fs_util;
     use buck2_core::fs::paths::file_name::FileNameBuf;
     use buck2_core::fs::project::ProjectRootTemp;
     use buck2_core::logging::LogConfigurationReloadHandle;
@@ -558,7 +598,9 @@ mod tests {
     use rand::SeedableRng;
     use tokio::runtime::Handle;
 
+    use crate::commands::daemon::write_process_info;
     use crate::commands::daemon::BuckdServerDependenciesImpl;
+    use crate::commands::daemon::BUCKD_INFO_TMP;
 
     // `fbinit_tokio` is not on crates, so we cannot use `#[fbinit::test]`.
     #[tokio::test]
@@ -666,4 +708,38 @@ mod tests {
             .expect("handle join failed")
             .expect("daemon returned error");
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_process_info_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let project_root = ProjectRootTemp::new().unwrap();
+        let daemon_dir = DaemonDir {
+            path: project_root.path().root().to_buf(),
+        };
+        let process_info = DaemonProcessInfo {
+            endpoint: "tcp:1234".to_owned(),
+            pid: 1,
+            version: "13.17.19".to_owned(),
+            auth_token: "abc".to_owned(),
+        };
+
+        // A leftover world-readable file must not keep its mode.
+        std::fs::write(daemon_dir.buckd_info(), "stale").unwrap();
+        std::fs::set_permissions(
+            daemon_dir.buckd_info(),
+            std::fs::Permissions::from_mode(0o644),
+        )
+        .unwrap();
+
+        write_process_info(&daemon_dir, &process_info, 0o600).unwrap();
+
+        let metadata = std::fs::metadata(daemon_dir.buckd_info()).unwrap();
+        assert_eq!(0o600, metadata.permissions().mode() & 0o777);
+        let written: DaemonProcessInfo =
+            serde_json::from_slice(&std::fs::read(daemon_dir.buckd_info()).unwrap()).unwrap();
+        assert_eq!(process_info, written);
+        assert!(!fs_util::try_exists(daemon_dir.path.join(BUCKD_INFO_TMP)).unwrap());
+    }
 }
@@ -18,9 +18,13 @@ use buck2_client::args::expand_argfiles_with_context;
 use buck2_client::commands::build::BuildCommand;
 use buck2_client::commands::bxl::BxlCommand;
 use buck2_client::commands::clean::CleanCommand;
+use buck2_client::commands::completion::CompletionCommand;
 use buck2_client::commands::ctargets::ConfiguredTargetsCommand;
 use buck2_client::commands::debug::DebugCommand;
+use buck2_client::commands::fetch_cells::FetchCellsCommand;
+use buck2_client::commands::fetch_toolchains::FetchToolchainsCommand;
 use buck2_client::commands::help_env::HelpEnvCommand;
+use buck2_client::commands::impact::ImpactCommand;
 use buck2_client::commands::init::InitCommand;
 use buck2_client::commands::install::InstallCommand;
 use buck2_client::commands::kill::KillCommand;
@@ -265,6 +269,7 @@ pub(crate) enum CommandKind {
     Build(BuildCommand),
     Bxl(BxlCommand),
     HelpEnv(HelpEnvCommand),
+    Impact(ImpactCommand),
     Test(TestCommand),
     Cquery(CqueryCommand),
     Init(InitCommand),
@@ -292,6 +297,9 @@ pub(crate) enum CommandKind {
     #[clap(hide(true))] // @oss-enable
     Rage(RageCommand),
     Clean(CleanCommand),
+    Completion(CompletionCommand),
+    FetchCells(FetchCellsCommand),
+    FetchToolchains(FetchToolchainsCommand),
     #[clap(subcommand)]
     Log(LogCommand),
     Lsp(LspCommand),
@@ -375,10 +383,14 @@ impl CommandKind {
             CommandKind::Test(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::Cquery(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::HelpEnv(cmd) => cmd.exec(matches, command_ctx),
+            CommandKind::Impact(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::Kill(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::Killall(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::Clean(cmd) => cmd.exec(matches, command_ctx),
+            CommandKind::FetchCells(cmd) => cmd.exec(matches, command_ctx),
+            CommandKind::FetchToolchains(cmd) => cmd.exec(matches, command_ctx).into(),
             CommandKind::Root(cmd) => cmd.exec(matches, command_ctx).into(),
+            CommandKind::Completion(cmd) => cmd.exec(matches, command_ctx).into(),
             CommandKind::Query(cmd) => {
                 buck2_client_ctx::eprintln!(
                     "WARNING: \"buck2 query\" is an alias for \"buck2 uquery\". Consider using \"buck2 cquery\" or \"buck2 uquery\" explicitly."
@@ -41,7 +41,12 @@ pub(crate) fn start_in_process_daemon(
         .await
         .with_context(|| "Error locking buckd lifecycle.lock")?;
 
-        kill_command_impl(&lifecycle_lock, "A command with `--no-buckd` is invoked").await
+        kill_command_impl(
+            &lifecycle_lock,
+            "A command with `--no-buckd` is invoked",
+            None,
+        )
+        .await
     })?;
 
     let daemon_startup_config = daemon_startup_config.clone();
@@ -57,6 +57,7 @@ use derive_more::Display;
 use dupe::Dupe;
 use gazebo::prelude::*;
 use host_sharing::HostSharingRequirements;
+use host_sharing::ResourceClass;
 use host_sharing::WeightClass;
 use indexmap::indexmap;
 use indexmap::IndexSet;
@@ -161,6 +162,7 @@ pub(crate) struct UnregisteredRunAction {
     pub(crate) executor_preference: ExecutorPreference,
     pub(crate) always_print_stderr: bool,
     pub(crate) weight: WeightClass,
+    pub(crate) resource_class: Option<ResourceClass>,
     pub(crate) low_pass_filter: bool,
     pub(crate) dep_files: RunActionDepFiles,
     pub(crate) metadata_param: Option<MetadataParameter>,
@@ -425,6 +427,7 @@ impl RunAction {
                 .collect(),
             ctx.fs(),
             ctx.digest_config(),
+            ctx.merged_directory_cache(),
         )?;
 
         Ok(PreparedRunAction {
@@ -600,6 +603,10 @@ impl Action for RunAction {
             "executor_preference".to_owned() => self.inner.executor_preference.to_string(),
             "always_print_stderr".to_owned() => self.inner.always_print_stderr.to_string(),
             "weight".to_owned() => self.inner.weight.to_string(),
+            "resource_class".to_owned() => match &self.inner.resource_class {
+                None => "None".to_owned(),
+                Some(x) => x.to_string(),
+            },
             "dep_files".to_owned() => self.inner.dep_files.to_string(),
             "metadata_param".to_owned() => match &self.inner.metadata_param {
                 None => "None".to_owned(),
@@ -644,6 +651,7 @@ impl IncrementalActionExecutable for RunAction {
             .with_prefetch_lossy_stderr(true)
             .with_executor_preference(self.inner.executor_preference)
             .with_host_sharing_requirements(host_sharing_requirements)
+            .with_resource_class(self.inner.resource_class)
             .with_low_pass_filter(self.inner.low_pass_filter)
             .with_outputs_cleanup(!self.inner.no_outputs_cleanup)
             .with_local_environment_inheritance(EnvironmentInheritance::local_command_exclusions())
@@ -48,6 +48,7 @@ use chrono::Utc;
 use dupe::Dupe;
 use dupe::OptionDupedExt;
 use either::Either;
+use host_sharing::ResourceClass;
 use host_sharing::WeightClass;
 use host_sharing::WeightPercentage;
 use indexmap::indexset;
@@ -605,6 +606,9 @@ fn analysis_actions_methods_actions(builder: &mut MethodsBuilder) {
     ///   event stream, and must be unique for a given target
     /// * `weight`: used to note how heavy the command is and will typically be set to a higher
     ///   value to indicate that less such commands should be run in parallel (if running locally)
+    /// * `resource_class`: `"cpu"` or `"io"`, the resource the command is mostly bound by. When
+    ///   `build.io_threads` is set, commands of each class run locally against separate budgets.
+    ///   If unset, buck2 classifies the command from its category and previous executions
     /// * `no_outputs_cleanup`: if this flag is set then Buck2 won't clean the outputs of a previous
     ///   build that might be present on a disk; in which case, command from arguments should be
     ///   responsible for the cleanup (that is useful, for example, when an action is supporting
@@ -656,6 +660,7 @@ fn analysis_actions_methods_actions(builder: &mut MethodsBuilder) {
         #[starlark(require = named, default = false)] always_print_stderr: bool,
         #[starlark(require = named)] weight: Option<i32>,
         #[starlark(require = named)] weight_percentage: Option<i32>,
+        #[starlark(require = named)] resource_class: Option<&str>,
         #[starlark(require = named)] dep_files: Option<SmallMap<&'v str, &'v ArtifactTag>>,
         #[starlark(require = named)] metadata_env_var: Option<String>,
         #[starlark(require = named)] metadata_path: Option<String>,
@@ -808,6 +813,10 @@ fn analysis_actions_methods_actions(builder: &mut MethodsBuilder) {
             }
         }
 
+        let resource_class = resource_class
+            .map(|c| c.parse::<ResourceClass>())
+            .transpose()?;
+
         let category = Category::try_from(category)?;
         let identifier = identifier.into_option();
 
@@ -840,6 +849,7 @@ fn analysis_actions_methods_actions(builder: &mut MethodsBuilder) {
             executor_preference,
             always_print_stderr,
             weight,
+            resource_class,
             low_pass_filter,
             dep_files: dep_files_configuration,
             metadata_param,
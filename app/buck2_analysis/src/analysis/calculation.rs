@@ -368,8 +368,31 @@ pub async fn profile_analysis(
     })
 }
 
-fn all_deps(node: ConfiguredTargetNode) -> LabelIndexedSet<ConfiguredTargetNode> {
-    let mut stack = vec![node];
+/// Run the analysis of `target` and return how long it took. The analysis of `target` itself
+/// does not go through DICE, so it is rerun on each call, but the analysis of its dependencies is.
+pub async fn benchmark_analysis(
+    ctx: &DiceComputations,
+    target: &ConfiguredTargetLabel,
+) -> anyhow::Result<Duration> {
+    let target_node = ctx
+        .get_configured_target_node(target)
+        .await?
+        .require_compatible()?;
+    let target = match target_node.forward_target() {
+        None => target_node.label(),
+        Some(forward) => forward.label(),
+    };
+    let now = Instant::now();
+    get_analysis_result(ctx, target, &StarlarkProfileModeOrInstrumentation::None)
+        .await?
+        .require_compatible()?;
+    Ok(now.elapsed())
+}
+
+fn all_deps(
+    nodes: impl IntoIterator<Item = ConfiguredTargetNode>,
+) -> LabelIndexedSet<ConfiguredTargetNode> {
+    let mut stack: Vec<_> = nodes.into_iter().collect();
     let mut visited = LabelIndexedSet::new();
     while let Some(node) = stack.pop() {
         if visited.insert(node.dupe()) {
@@ -382,6 +405,15 @@ fn all_deps(node: ConfiguredTargetNode) -> LabelIndexedSet<ConfiguredTargetNode>
 pub async fn profile_analysis_recursively(
     ctx: &DiceComputations,
     target: &ConfiguredTargetLabel,
+) -> anyhow::Result<StarlarkProfileDataAndStats> {
+    profile_analyses_recursively(ctx, [target]).await
+}
+
+/// Merged analysis profile of the given targets and all their deps, each analysis being
+/// counted once.
+pub async fn profile_analyses_recursively(
+    ctx: &DiceComputations,
+    targets: impl IntoIterator<Item = &ConfiguredTargetLabel>,
 ) -> anyhow::Result<StarlarkProfileDataAndStats> {
     // Self check.
     let profile_mode = ctx.get_profile_mode_for_intermediate_analysis().await?;
@@ -392,12 +424,16 @@ pub async fn profile_analysis_recursively(
         return Err(ProfileAnalysisError::RecursiveProfileConfiguredIncorrectly.into());
     }
 
-    let node = ctx
-        .get_configured_target_node(target)
-        .await?
-        .require_compatible()?;
+    let mut nodes = Vec::new();
+    for target in targets {
+        nodes.push(
+            ctx.get_configured_target_node(target)
+                .await?
+                .require_compatible()?,
+        );
+    }
 
-    let all_deps = all_deps(node);
+    let all_deps = all_deps(nodes);
 
     let mut futures = all_deps
         .iter()
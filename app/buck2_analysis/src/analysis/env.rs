@@ -20,6 +20,7 @@ use buck2_build_api::interpreter::rule_defs::context::AnalysisContext;
 use buck2_build_api::interpreter::rule_defs::provider::builtin::template_placeholder_info::FrozenTemplatePlaceholderInfo;
 use buck2_build_api::interpreter::rule_defs::provider::collection::FrozenProviderCollectionValue;
 use buck2_build_api::interpreter::rule_defs::provider::collection::ProviderCollection;
+use buck2_common::dice::data::HasCaseSensitivity;
 use buck2_core::base_deferred_key::BaseDeferredKey;
 use buck2_core::execution_types::execution::ExecutionPlatformResolution;
 use buck2_core::provider::label::ConfiguredProvidersLabel;
@@ -265,10 +266,17 @@ async fn run_analysis_with_env_underlying(
         )
     };
 
-    let registry = AnalysisRegistry::new_from_owner(
+    let mut registry = AnalysisRegistry::new_from_owner(
         BaseDeferredKey::TargetLabel(node.label().dupe()),
         analysis_env.execution_platform.dupe(),
     )?;
+    if dice
+        .global_data()
+        .get_case_sensitivity()
+        .outputs_case_insensitive
+    {
+        registry.set_case_insensitive_outputs();
+    }
 
     let mut profiler_opt = profile_mode
         .profile_mode()
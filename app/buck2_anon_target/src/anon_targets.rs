@@ -36,6 +36,7 @@ use buck2_build_api::interpreter::rule_defs::plugins::AnalysisPlugins;
 use buck2_build_api::interpreter::rule_defs::provider::collection::FrozenProviderCollectionValue;
 use buck2_build_api::interpreter::rule_defs::provider::collection::ProviderCollection;
 use buck2_build_api::keep_going;
+use buck2_common::dice::data::HasCaseSensitivity;
 use buck2_configured::nodes::calculation::find_execution_platform_by_configuration;
 use buck2_core::base_deferred_key::BaseDeferredKey;
 use buck2_core::base_deferred_key::BaseDeferredKeyDyn;
@@ -385,10 +386,17 @@ impl AnonTargetKey {
                         }
                         let attributes = env.heap().alloc(AllocStruct(resolved_attrs));
 
-                        let registry = AnalysisRegistry::new_from_owner(
+                        let mut registry = AnalysisRegistry::new_from_owner(
                             BaseDeferredKey::AnonTarget(self.0.dupe()),
                             exec_resolution,
                         )?;
+                        if dice
+                            .global_data()
+                            .get_case_sensitivity()
+                            .outputs_case_insensitive
+                        {
+                            registry.set_case_insensitive_outputs();
+                        }
 
                         let ctx = env.heap().alloc_typed(AnalysisContext::new(
                             eval.heap(),
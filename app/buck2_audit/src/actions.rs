@@ -0,0 +1,42 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use async_trait::async_trait;
+use buck2_client_ctx::common::CommonCommandOptions;
+
+use crate::AuditSubcommand;
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "audit-actions",
+    about = "List the actions registered by the analysis of the given targets, without running them",
+    long_about = "List the actions registered by the analysis of the given targets, without running them.\n\
+    Inputs and outputs are listed with the digest of their content when it is known: for source \
+    files, and for build artifacts present on disk from a previous build."
+)]
+pub struct AuditActionsCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(
+        name = "TARGET_PATTERNS",
+        help = "Patterns to analyze. The actions of the targets matching these patterns will be listed"
+    )]
+    pub patterns: Vec<String>,
+
+    #[clap(long = "json", help = "Output in JSON format")]
+    pub json: bool,
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditActionsCommand {
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
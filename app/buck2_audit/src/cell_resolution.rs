@@ -0,0 +1,42 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use async_trait::async_trait;
+use buck2_client_ctx::common::CommonCommandOptions;
+
+use crate::AuditSubcommand;
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "audit-cell-resolution",
+    about = "Explain how paths and labels resolve to cells: the cell alias rewrites applied, \
+    the cell root, and the buckconfig files read for the cell, in include order."
+)]
+pub struct AuditCellResolutionCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(long = "json", help = "Output in JSON format")]
+    pub json: bool,
+
+    #[clap(
+        name = "PATHS_OR_LABELS",
+        help = "Paths (relative to the working directory) or labels (like `cell//pkg:target`, \
+        with the cell alias resolved in the working directory cell) to explain.",
+        required = true
+    )]
+    pub inputs: Vec<String>,
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditCellResolutionCommand {
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
@@ -89,6 +89,24 @@ pub struct AuditConfigCommand {
     #[clap(long = "value", default_value = "resolved", possible_values=&["resolved", "raw", "both"])]
     pub value_style: ValueStyle,
 
+    /// Instead of printing the configuration, print the keys whose resolved value differs
+    /// between this invocation and an invocation with the given `--diff-config` and
+    /// `--diff-config-file` arguments (and no other config arguments).
+    #[clap(long)]
+    pub diff: bool,
+
+    /// A `section.key=value` config argument for the configuration to compare against.
+    #[clap(
+        long = "diff-config",
+        requires = "diff",
+        value_name = "SECTION.OPTION=VALUE"
+    )]
+    pub diff_config_values: Vec<String>,
+
+    /// A config file for the configuration to compare against.
+    #[clap(long = "diff-config-file", requires = "diff", value_name = "PATH")]
+    pub diff_config_files: Vec<String>,
+
     /// config section/key specs of the form `section` or `section.key`.
     /// If any specs are provided, only values matching a spec will be printed
     /// (section headers will be printed only for sections with a key matching the spec).
@@ -0,0 +1,51 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use async_trait::async_trait;
+use buck2_client_ctx::common::CommonCommandOptions;
+
+use crate::AuditSubcommand;
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "audit-dep-path",
+    about = "Print the shortest configured dependency path from a target to another, with the \
+    attributes carrying each edge"
+)]
+pub struct AuditDepPathCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(name = "FROM", help = "Target pattern of the targets to start from.")]
+    pub from: String,
+
+    #[clap(
+        name = "TO",
+        help = "Target pattern of the targets to reach, in any configuration."
+    )]
+    pub to: String,
+
+    #[clap(
+        long,
+        help = "Print every path from a target matching `FROM` to a target matching `TO`, \
+        instead of only the shortest one. The number of paths can grow exponentially with the \
+        depth of the graph"
+    )]
+    pub all: bool,
+
+    #[clap(long, help = "Output in JSON format")]
+    pub json: bool,
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditDepPathCommand {
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
@@ -23,6 +23,11 @@ pub struct AuditExecutionPlatformResolutionCommand {
 
     #[clap(name = "TARGET_PATTERNS", help = "Patterns to analyze")]
     pub patterns: Vec<String>,
+
+    /// Also walk the transitive deps of each target, and explain every dep whose execution
+    /// platform differs from the one of the target depending on it.
+    #[clap(long, short = 'r')]
+    pub recursive: bool,
 }
 
 #[async_trait]
@@ -24,36 +24,48 @@ use buck2_client_ctx::exit_result::ExitResult;
 use buck2_client_ctx::streaming::StreamingCommand;
 use classpath::AuditClasspathCommand;
 
+use crate::actions::AuditActionsCommand;
 use crate::analysis_queries::AuditAnalysisQueriesCommand;
 use crate::cell::AuditCellCommand;
+use crate::cell_resolution::AuditCellResolutionCommand;
 use crate::config::AuditConfigCommand;
 use crate::configurations::AuditConfigurationsCommand;
 use crate::deferred_materializer::DeferredMaterializerCommand;
 use crate::dep_files::AuditDepFilesCommand;
+use crate::dep_path::AuditDepPathCommand;
 use crate::execution_platform_resolution::AuditExecutionPlatformResolutionCommand;
 use crate::includes::AuditIncludesCommand;
 use crate::output::command::AuditOutputCommand;
 use crate::output::parse::AuditParseCommand;
+use crate::package_errors::AuditPackageErrorsCommand;
 use crate::package_values::PackageValuesCommand;
 use crate::prelude::AuditPreludeCommand;
 use crate::providers::AuditProvidersCommand;
+use crate::sbom::AuditSbomCommand;
+use crate::select::AuditSelectCommand;
 use crate::starlark::StarlarkCommand;
 use crate::subtargets::AuditSubtargetsCommand;
 use crate::visibility::AuditVisibilityCommand;
 
+pub mod actions;
 pub mod analysis_queries;
 pub mod cell;
+pub mod cell_resolution;
 pub mod classpath;
 pub mod config;
 pub mod configurations;
 pub mod deferred_materializer;
 pub mod dep_files;
+pub mod dep_path;
 pub mod execution_platform_resolution;
 pub mod includes;
 pub mod output;
+pub mod package_errors;
 pub mod package_values;
 pub mod prelude;
 pub mod providers;
+pub mod sbom;
+pub mod select;
 pub mod starlark;
 pub mod subtargets;
 pub mod visibility;
@@ -62,6 +74,7 @@ pub mod visibility;
 #[clap(name = "audit", about = "Perform lower level queries")]
 pub enum AuditCommand {
     Cell(AuditCellCommand),
+    CellResolution(AuditCellResolutionCommand),
     Classpath(AuditClasspathCommand),
     Config(AuditConfigCommand),
     Configurations(AuditConfigurationsCommand),
@@ -70,6 +83,7 @@ pub enum AuditCommand {
     Providers(AuditProvidersCommand),
     Subtargets(AuditSubtargetsCommand),
     AnalysisQueries(AuditAnalysisQueriesCommand),
+    Actions(AuditActionsCommand),
     ExecutionPlatformResolution(AuditExecutionPlatformResolutionCommand),
     Visibility(AuditVisibilityCommand),
     #[clap(subcommand)]
@@ -79,6 +93,10 @@ pub enum AuditCommand {
     Output(AuditOutputCommand),
     Parse(AuditParseCommand),
     PackageValues(PackageValuesCommand),
+    Select(AuditSelectCommand),
+    Sbom(AuditSbomCommand),
+    DepPath(AuditDepPathCommand),
+    PackageErrors(AuditPackageErrorsCommand),
 }
 
 /// `buck2 audit` subcommands have a somewhat unique approach to make it really easy to
@@ -98,6 +116,7 @@ impl AuditCommand {
     fn as_subcommand(&self) -> &dyn AuditSubcommand {
         match self {
             AuditCommand::Cell(cmd) => cmd,
+            AuditCommand::CellResolution(cmd) => cmd,
             AuditCommand::Classpath(cmd) => cmd,
             AuditCommand::Config(cmd) => cmd,
             AuditCommand::Configurations(cmd) => cmd,
@@ -106,6 +125,7 @@ impl AuditCommand {
             AuditCommand::Providers(cmd) => cmd,
             AuditCommand::Subtargets(cmd) => cmd,
             AuditCommand::AnalysisQueries(cmd) => cmd,
+            AuditCommand::Actions(cmd) => cmd,
             AuditCommand::ExecutionPlatformResolution(cmd) => cmd,
             AuditCommand::Starlark(cmd) => cmd,
             AuditCommand::DepFiles(cmd) => cmd,
@@ -114,6 +134,10 @@ impl AuditCommand {
             AuditCommand::Output(cmd) => cmd,
             AuditCommand::Parse(cmd) => cmd,
             AuditCommand::PackageValues(cmd) => cmd,
+            AuditCommand::Select(cmd) => cmd,
+            AuditCommand::Sbom(cmd) => cmd,
+            AuditCommand::DepPath(cmd) => cmd,
+            AuditCommand::PackageErrors(cmd) => cmd,
         }
     }
 }
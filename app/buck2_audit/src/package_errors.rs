@@ -0,0 +1,40 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use async_trait::async_trait;
+use buck2_client_ctx::common::CommonCommandOptions;
+
+use crate::AuditSubcommand;
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "audit-package-errors",
+    about = "Load every package matching the pattern(s) and report all the packages which fail \
+    to load, with the file and line of their errors, instead of stopping at the first one"
+)]
+pub struct AuditPackageErrorsCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(
+        name = "TARGET_PATTERNS",
+        help = "Target pattern(s) whose packages to load, e.g. `//foo/...`."
+    )]
+    pub patterns: Vec<String>,
+
+    #[clap(long = "json", help = "Output in JSON format")]
+    pub json: bool,
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditPackageErrorsCommand {
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
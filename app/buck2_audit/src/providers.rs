@@ -28,22 +28,27 @@ pub struct AuditProvidersCommand {
     )]
     pub patterns: Vec<String>,
 
-    #[clap(long, conflicts_with_all=&["list", "print-debug"])]
+    #[clap(long, conflicts_with_all=&["list", "print-debug", "json"])]
     pub quiet: bool,
 
     #[clap(
         long,
         short = 'l',
-        help = "List the available providers", conflicts_with_all=&["print-debug", "quiet"]
+        help = "List the available providers", conflicts_with_all=&["print-debug", "quiet", "json"]
     )]
     pub list: bool,
 
     #[clap(
         long = "print-debug",
         help = "Print the providers using debug format (very verbose)",
-        conflicts_with_all=&["list", "quiet"]
+        conflicts_with_all=&["list", "quiet", "json"]
     )]
     pub print_debug: bool,
+
+    /// Print the providers as JSON, following the schema documented in
+    /// `docs/users/audit_providers_json.md`.
+    #[clap(long, conflicts_with_all=&["list", "quiet", "print-debug"])]
+    pub json: bool,
 }
 
 #[async_trait]
@@ -0,0 +1,77 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use async_trait::async_trait;
+use buck2_client_ctx::common::CommonCommandOptions;
+use dupe::Dupe;
+
+use crate::AuditSubcommand;
+
+#[derive(
+    Debug,
+    Dupe,
+    Clone,
+    Copy,
+    serde::Serialize,
+    serde::Deserialize,
+    clap::ArgEnum
+)]
+#[clap(rename_all = "snake_case")]
+pub enum SbomFormat {
+    /// SPDX 2.3, as JSON.
+    Spdx,
+    /// CycloneDX 1.5, as JSON.
+    Cyclonedx,
+}
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "audit-sbom",
+    about = "Print a software bill of materials of the configured target(s): every target in \
+    their configured dependency graph, with its license and version"
+)]
+pub struct AuditSbomCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(name = "TARGET_PATTERNS", help = "Target pattern(s) to analyze.")]
+    pub patterns: Vec<String>,
+
+    #[clap(long, ignore_case = true, arg_enum, default_value = "spdx")]
+    pub format: SbomFormat,
+
+    #[clap(
+        long,
+        default_value = "licenses",
+        help = "Attribute holding the licenses of a target, either SPDX license identifiers or \
+        license files"
+    )]
+    pub license_attr: String,
+
+    #[clap(
+        long,
+        default_value = "version",
+        help = "Attribute holding the version of a target"
+    )]
+    pub version_attr: String,
+
+    #[clap(
+        long,
+        help = "Also include execution deps, such as compilers, which are used to build the \
+        targets but are not part of their outputs"
+    )]
+    pub include_exec_deps: bool,
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditSbomCommand {
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
@@ -23,6 +23,14 @@ pub struct AuditVisibilityCommand {
 
     #[clap(name = "TARGET_PATTERNS", help = "Target pattern(s) to analyze.")]
     pub patterns: Vec<String>,
+
+    #[clap(
+        long,
+        value_name = "TARGET",
+        help = "Instead of verifying transitive deps, explain whether the specified target(s) \
+        may depend on TARGET, listing the `visibility` and `within_view` patterns which matched"
+    )]
+    pub explain: Option<String>,
 }
 
 #[async_trait]
@@ -0,0 +1,276 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::io::Write;
+
+use async_trait::async_trait;
+use buck2_artifact::artifact::provide_outputs::ProvideActionKey;
+use buck2_audit::actions::AuditActionsCommand;
+use buck2_build_api::actions::artifact::get_artifact_fs::GetArtifactFs;
+use buck2_build_api::actions::calculation::ActionCalculation;
+use buck2_build_api::actions::RegisteredAction;
+use buck2_build_api::analysis::calculation::RuleAnalysisCalculation;
+use buck2_build_api::artifact_groups::ArtifactGroup;
+use buck2_cli_proto::ClientContext;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::dice::file_ops::HasFileOps;
+use buck2_common::file_ops::FileDigest;
+use buck2_common::file_ops::FileDigestConfig;
+use buck2_common::file_ops::FileOps;
+use buck2_common::file_ops::RawPathMetadata;
+use buck2_common::pattern::resolve::resolve_target_patterns;
+use buck2_core::cells::CellResolver;
+use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::pattern::PackageSpec;
+use buck2_core::target::label::TargetLabel;
+use buck2_execute::digest_config::DigestConfig;
+use buck2_execute::digest_config::HasDigestConfig;
+use buck2_node::nodes::frontend::TargetGraphCalculation;
+use buck2_node::target_calculation::ConfiguredTargetCalculation;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use buck2_server_ctx::pattern::target_platform_from_client_context;
+use dice::DiceComputations;
+use dupe::Dupe;
+use gazebo::prelude::*;
+use gazebo::variants::VariantName;
+
+use crate::AuditSubcommand;
+
+/// A path read or written by an action, with the digest of its content if it is known without
+/// running anything.
+#[derive(serde::Serialize)]
+struct PathDigest {
+    path: String,
+    digest: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct ActionSummary {
+    key: String,
+    kind: &'static str,
+    category: String,
+    identifier: Option<String>,
+    inputs: Vec<PathDigest>,
+    /// Inputs which are not artifacts, e.g. transitive set projections.
+    other_inputs: usize,
+    outputs: Vec<PathDigest>,
+}
+
+/// The digest of a source file, as known to the file ops.
+async fn source_digest(
+    ctx: &DiceComputations,
+    cells: &CellResolver,
+    path: &ProjectRelativePath,
+) -> anyhow::Result<Option<String>> {
+    let cell_path = cells.get_cell_path(path)?;
+    match ctx
+        .file_ops()
+        .read_path_metadata_if_exists(cell_path.as_ref())
+        .await?
+    {
+        Some(RawPathMetadata::File(metadata)) => Ok(Some(metadata.digest.to_string())),
+        _ => Ok(None),
+    }
+}
+
+/// The digest of the file currently at the path of a build artifact, if it was built (and
+/// materialized) by a previous build. Directories are not digested.
+fn built_digest(
+    artifact_fs: &ArtifactFs,
+    digest_config: DigestConfig,
+    path: &ProjectRelativePath,
+) -> anyhow::Result<Option<String>> {
+    let path = artifact_fs.fs().resolve(path);
+    match fs_util::symlink_metadata_if_exists(&path)? {
+        Some(metadata) if metadata.is_file() => Ok(Some(
+            FileDigest::from_file(
+                &path,
+                FileDigestConfig::build(digest_config.cas_digest_config()),
+            )?
+            .to_string(),
+        )),
+        _ => Ok(None),
+    }
+}
+
+async fn summarize_action(
+    ctx: &DiceComputations,
+    cells: &CellResolver,
+    artifact_fs: &ArtifactFs,
+    digest_config: DigestConfig,
+    action: &RegisteredAction,
+) -> anyhow::Result<ActionSummary> {
+    let mut inputs = Vec::new();
+    let mut other_inputs = 0;
+    for input in action.inputs()?.iter() {
+        match input {
+            ArtifactGroup::Artifact(artifact) => {
+                let path = artifact.get_path().resolve(artifact_fs)?;
+                let digest = if artifact.is_source() {
+                    source_digest(ctx, cells, &path).await?
+                } else {
+                    built_digest(artifact_fs, digest_config, &path)?
+                };
+                inputs.push(PathDigest {
+                    path: path.to_string(),
+                    digest,
+                });
+            }
+            ArtifactGroup::TransitiveSetProjection(_) | ArtifactGroup::Promise(_) => {
+                other_inputs += 1;
+            }
+        }
+    }
+
+    let outputs = action.outputs()?.try_map(|output| {
+        let path = artifact_fs.resolve_build(output.get_path());
+        anyhow::Ok(PathDigest {
+            digest: built_digest(artifact_fs, digest_config, &path)?,
+            path: path.to_string(),
+        })
+    })?;
+
+    Ok(ActionSummary {
+        key: action.key().to_string(),
+        kind: action.kind().variant_name(),
+        category: action.category().as_str().to_owned(),
+        identifier: action.identifier().map(|s| s.to_owned()),
+        inputs,
+        other_inputs,
+        outputs,
+    })
+}
+
+fn write_paths(stdout: &mut impl Write, name: &str, paths: &[PathDigest]) -> anyhow::Result<()> {
+    writeln!(stdout, "    {}: {}", name, paths.len())?;
+    for path in paths {
+        match &path.digest {
+            Some(digest) => writeln!(stdout, "      {} {}", path.path, digest)?,
+            None => writeln!(stdout, "      {}", path.path)?,
+        }
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditActionsCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, mut ctx| {
+                let cells = ctx.get_cell_resolver().await?;
+                let artifact_fs = ctx.get_artifact_fs().await?;
+                let digest_config = ctx.global_data().get_digest_config();
+
+                let global_target_platform =
+                    target_platform_from_client_context(&client_ctx, server_ctx, &mut ctx).await?;
+
+                let parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &mut ctx,
+                    &self
+                        .patterns
+                        .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
+                    server_ctx.working_dir(),
+                )
+                .await?;
+                let resolved_pattern =
+                    resolve_target_patterns(&cells, &parsed_patterns, &ctx.file_ops()).await?;
+
+                let mut json_output = serde_json::Map::new();
+                let mut stdout = stdout.as_writer();
+
+                for (package, spec) in resolved_pattern.specs {
+                    let targets: Vec<_> = match spec {
+                        PackageSpec::Targets(targets) => {
+                            targets.into_map(|(target, TargetPatternExtra)| target)
+                        }
+                        PackageSpec::All => {
+                            let interpreter_results =
+                                ctx.get_interpreter_results(package.dupe()).await?;
+                            interpreter_results
+                                .targets()
+                                .keys()
+                                .map(|target| target.to_owned())
+                                .collect()
+                        }
+                    };
+
+                    for target in targets {
+                        let label = TargetLabel::new(package.dupe(), target.as_ref());
+                        let configured_target = ctx
+                            .get_configured_target(&label, global_target_platform.as_ref())
+                            .await?;
+                        let analysis = ctx
+                            .get_analysis_result(&configured_target)
+                            .await?
+                            .require_compatible()?;
+
+                        let action_keys: Vec<_> = analysis
+                            .iter_deferreds()
+                            .filter_map(|entry| {
+                                provider::request_value::<ProvideActionKey>(entry.as_complex())
+                            })
+                            .collect();
+                        let actions = futures::future::try_join_all(
+                            action_keys.iter().map(|key| ctx.get_action(&key.0)),
+                        )
+                        .await?;
+
+                        let mut summaries = Vec::with_capacity(actions.len());
+                        for action in &actions {
+                            summaries.push(
+                                summarize_action(&ctx, &cells, &artifact_fs, digest_config, action)
+                                    .await?,
+                            );
+                        }
+
+                        if self.json {
+                            json_output.insert(
+                                configured_target.to_string(),
+                                serde_json::to_value(&summaries)?,
+                            );
+                        } else {
+                            writeln!(stdout, "{}:", configured_target)?;
+                            for action in &summaries {
+                                writeln!(stdout, "  {}", action.key)?;
+                                writeln!(stdout, "    kind: {}", action.kind)?;
+                                writeln!(stdout, "    category: {}", action.category)?;
+                                if let Some(identifier) = &action.identifier {
+                                    writeln!(stdout, "    identifier: {}", identifier)?;
+                                }
+                                write_paths(&mut stdout, "inputs", &action.inputs)?;
+                                if action.other_inputs != 0 {
+                                    writeln!(stdout, "    other inputs: {}", action.other_inputs)?;
+                                }
+                                write_paths(&mut stdout, "outputs", &action.outputs)?;
+                            }
+                        }
+                    }
+                }
+
+                if self.json {
+                    serde_json::to_writer_pretty(&mut stdout, &json_output)?;
+                    writeln!(stdout)?;
+                }
+
+                Ok(())
+            })
+            .await
+    }
+}
@@ -0,0 +1,183 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::io::Write;
+use std::path::Path;
+
+use async_trait::async_trait;
+use buck2_audit::cell_resolution::AuditCellResolutionCommand;
+use buck2_cli_proto::ClientContext;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::legacy_configs::dice::HasLegacyConfigs;
+use buck2_common::legacy_configs::LegacyBuckConfigs;
+use buck2_core::cells::cell_path::CellPath;
+use buck2_core::cells::name::CellName;
+use buck2_core::cells::paths::CellRelativePathBuf;
+use buck2_core::cells::CellResolver;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+
+use crate::AuditSubcommand;
+
+/// An alias rewrite: `alias` as written, resolved in the cell `in_cell`, names the cell `cell`.
+#[derive(serde::Serialize)]
+struct AliasResolution {
+    alias: String,
+    in_cell: String,
+    cell: String,
+}
+
+#[derive(serde::Serialize)]
+struct ConfigFile {
+    path: String,
+    include_depth: usize,
+}
+
+#[derive(serde::Serialize)]
+struct CellResolution {
+    input: String,
+    alias: Option<AliasResolution>,
+    cell: String,
+    cell_root: String,
+    cell_path: String,
+    project_path: String,
+    buckconfigs: Vec<ConfigFile>,
+}
+
+fn resolve_input(
+    input: &str,
+    cells: &CellResolver,
+    configs: &LegacyBuckConfigs,
+    fs: &ProjectRoot,
+    working_dir_cell: CellName,
+    working_dir: &AbsNormPath,
+) -> anyhow::Result<CellResolution> {
+    let (alias, cell_path) = match input.split_once("//") {
+        Some((alias, rest)) => {
+            let cell = cells
+                .get(working_dir_cell)?
+                .cell_alias_resolver()
+                .resolve(alias)?;
+            // Drop the target name and any pattern suffix, leaving the package path.
+            let package = rest.split_once(':').map_or(rest, |(package, _)| package);
+            let package = package.trim_end_matches("...").trim_end_matches('/');
+            (
+                Some(AliasResolution {
+                    alias: alias.to_owned(),
+                    in_cell: working_dir_cell.to_string(),
+                    cell: cell.to_string(),
+                }),
+                CellPath::new(cell, CellRelativePathBuf::try_from(package.to_owned())?),
+            )
+        }
+        None => {
+            let abs_path = fs_util::canonicalize(working_dir.as_abs_path().join(Path::new(input)))?;
+            let project_path = fs.relativize(&abs_path)?;
+            (None, cells.get_cell_path(&project_path)?)
+        }
+    };
+
+    let cell = cells.get(cell_path.cell())?;
+    let buckconfigs = configs
+        .get(cell_path.cell())?
+        .files()
+        .iter()
+        .map(|f| ConfigFile {
+            path: f.path.clone(),
+            include_depth: f.include_depth,
+        })
+        .collect();
+
+    Ok(CellResolution {
+        input: input.to_owned(),
+        alias,
+        cell: cell_path.cell().to_string(),
+        cell_root: cell.path().to_string(),
+        project_path: cells.resolve_path(cell_path.as_ref())?.to_string(),
+        cell_path: cell_path.to_string(),
+        buckconfigs,
+    })
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditCellResolutionCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        _client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, ctx| {
+                let cells = ctx.get_cell_resolver().await?;
+                let configs = ctx.get_legacy_configs().await?;
+                let fs = server_ctx.project_root();
+                let working_dir_cell = cells.find(server_ctx.working_dir())?;
+                let working_dir = server_ctx.working_dir_abs().path();
+
+                let resolutions = self
+                    .inputs
+                    .iter()
+                    .map(|input| {
+                        resolve_input(input, &cells, &configs, fs, working_dir_cell, working_dir)
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+
+                let mut stdout = stdout.as_writer();
+                if self.json {
+                    writeln!(stdout, "{}", serde_json::to_string_pretty(&resolutions)?)?;
+                    return Ok(());
+                }
+
+                for r in resolutions {
+                    writeln!(stdout, "{}", r.input)?;
+                    if let Some(alias) = &r.alias {
+                        if alias.alias == alias.cell {
+                            writeln!(
+                                stdout,
+                                "  alias `{}` in cell `{}` names cell `{}`",
+                                alias.alias, alias.in_cell, alias.cell
+                            )?;
+                        } else {
+                            writeln!(
+                                stdout,
+                                "  alias `{}` in cell `{}` rewritten to cell `{}`",
+                                alias.alias, alias.in_cell, alias.cell
+                            )?;
+                        }
+                    }
+                    writeln!(stdout, "  cell: {}", r.cell)?;
+                    writeln!(stdout, "  cell root: {}", r.cell_root)?;
+                    writeln!(stdout, "  cell path: {}", r.cell_path)?;
+                    writeln!(stdout, "  project path: {}", r.project_path)?;
+                    writeln!(stdout, "  buckconfigs:")?;
+                    for file in &r.buckconfigs {
+                        writeln!(
+                            stdout,
+                            "    {}{}{}",
+                            "  ".repeat(file.include_depth),
+                            if file.include_depth > 0 {
+                                "included: "
+                            } else {
+                                ""
+                            },
+                            file.path
+                        )?;
+                    }
+                }
+
+                Ok(())
+            })
+            .await
+    }
+}
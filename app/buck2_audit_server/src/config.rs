@@ -7,8 +7,10 @@
  * of this source tree.
  */
 
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::io::Write;
+use std::path::Path;
 
 use async_trait::async_trait;
 use buck2_audit::config::AuditConfigCommand;
@@ -17,9 +19,12 @@ use buck2_audit::config::OutputFormat;
 use buck2_audit::config::ValueStyle;
 use buck2_cli_proto::ClientContext;
 use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::legacy_configs::cells::BuckConfigBasedCells;
 use buck2_common::legacy_configs::dice::HasLegacyConfigs;
 use buck2_common::legacy_configs::LegacyBuckConfigLocation;
 use buck2_common::legacy_configs::LegacyBuckConfigValue;
+use buck2_common::legacy_configs::LegacyBuckConfigs;
+use buck2_common::legacy_configs::LegacyConfigCmdArg;
 use buck2_core::cells::name::CellName;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::ctx::ServerCommandDiceContext;
@@ -92,6 +97,127 @@ fn print_value(
     Ok(())
 }
 
+/// A key whose resolved value differs between two configurations.
+struct ConfigDiff {
+    cell: CellName,
+    spec: String,
+    current: Option<String>,
+    other: Option<String>,
+}
+
+/// Parse the configuration that an invocation with only the `--diff-config`
+/// and `--diff-config-file` arguments would see.
+fn parse_diff_configs(
+    command: &AuditConfigCommand,
+    server_ctx: &dyn ServerCommandContextTrait,
+) -> anyhow::Result<LegacyBuckConfigs> {
+    let mut config_args = Vec::new();
+    for file in &command.diff_config_files {
+        // Same as the client does for `--config-file`: cell-relative paths are resolved
+        // by the config parser, other paths relative to the working directory.
+        if file.contains("//") {
+            config_args.push(LegacyConfigCmdArg::file(file)?);
+        } else {
+            let path = server_ctx.working_dir_abs().resolve(Path::new(file));
+            config_args.push(LegacyConfigCmdArg::file(&path.to_string_lossy())?);
+        }
+    }
+    for value in &command.diff_config_values {
+        config_args.push(LegacyConfigCmdArg::flag(value)?);
+    }
+    Ok(BuckConfigBasedCells::parse_with_config_args(
+        server_ctx.project_root(),
+        &config_args,
+        server_ctx.working_dir(),
+    )?
+    .configs_by_name)
+}
+
+fn diff_configs(
+    current: &LegacyBuckConfigs,
+    other: &LegacyBuckConfigs,
+    filter: &dyn Fn(CellName, &str, &str) -> Option<String>,
+) -> Vec<ConfigDiff> {
+    let cells: BTreeSet<CellName> = current
+        .iter()
+        .chain(other.iter())
+        .map(|(cell, _)| cell)
+        .collect();
+
+    let mut diffs = Vec::new();
+    for cell in cells {
+        let current_config = current.get(cell).ok();
+        let other_config = other.get(cell).ok();
+        let sections: BTreeSet<&String> = current_config
+            .into_iter()
+            .chain(other_config)
+            .flat_map(|config| config.sections())
+            .collect();
+        for section in sections {
+            let current_section = current_config.and_then(|c| c.get_section(section));
+            let other_section = other_config.and_then(|c| c.get_section(section));
+            let keys: BTreeSet<&String> = current_section
+                .into_iter()
+                .chain(other_section)
+                .flat_map(|s| s.keys())
+                .collect();
+            for key in keys {
+                let Some(spec) = filter(cell, section, key) else {
+                    continue;
+                };
+                let current_value = current_section
+                    .and_then(|s| s.get(key))
+                    .map(|v| v.as_str().to_owned());
+                let other_value = other_section
+                    .and_then(|s| s.get(key))
+                    .map(|v| v.as_str().to_owned());
+                if current_value != other_value {
+                    diffs.push(ConfigDiff {
+                        cell,
+                        spec,
+                        current: current_value,
+                        other: other_value,
+                    });
+                }
+            }
+        }
+    }
+    diffs
+}
+
+fn print_diffs(
+    writer: &mut impl Write,
+    diffs: &[ConfigDiff],
+    format: OutputFormat,
+) -> anyhow::Result<()> {
+    match format {
+        OutputFormat::Json => writeln!(
+            writer,
+            "{}",
+            json!(diffs.map(|d| json!({
+                "cell": d.cell.as_str(),
+                "key": d.spec,
+                "current": d.current,
+                "other": d.other,
+            })))
+        )?,
+        OutputFormat::Simple => {
+            for d in diffs {
+                writeln!(writer, "{}//{}", d.cell, d.spec)?;
+                match &d.current {
+                    Some(v) => writeln!(writer, "  - {}", v)?,
+                    None => writeln!(writer, "  - (unset)")?,
+                }
+                match &d.other {
+                    Some(v) => writeln!(writer, "  + {}", v)?,
+                    None => writeln!(writer, "  + (unset)")?,
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 #[async_trait]
 impl AuditSubcommand for AuditConfigCommand {
     async fn server_execute(
@@ -131,7 +257,8 @@ impl AuditSubcommand for AuditConfigCommand {
 
                 let filter = move |cell: CellName, section: &str, key: &str| {
                     if specs.is_empty() {
-                        if cell == resolved_relevant_cell {
+                        // When diffing without an explicit cell, show differences in all cells.
+                        if cell == resolved_relevant_cell || (self.diff && self.cell.is_none()) {
                             Some(format!("{}.{}", section, key))
                         } else {
                             None
@@ -155,6 +282,12 @@ impl AuditSubcommand for AuditConfigCommand {
 
                 let mut stdout = stdout.as_writer();
 
+                if self.diff {
+                    let other_config = parse_diff_configs(self, server_ctx)?;
+                    let diffs = diff_configs(&config, &other_config, &filter);
+                    return print_diffs(&mut stdout, &diffs, self.output_format());
+                }
+
                 match self.output_format() {
                     OutputFormat::Json => writeln!(
                         &mut stdout,
@@ -0,0 +1,535 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::hash::Hash;
+use std::io::Write;
+
+use async_trait::async_trait;
+use buck2_audit::dep_path::AuditDepPathCommand;
+use buck2_cli_proto::ClientContext;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::plugins::PluginKind;
+use buck2_core::provider::label::ConfiguredProvidersLabel;
+use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
+use buck2_core::target::label::TargetLabel;
+use buck2_node::attrs::configured_traversal::ConfiguredAttrTraversal;
+use buck2_node::attrs::inspect_options::AttrInspectOptions;
+use buck2_node::load_patterns::load_patterns;
+use buck2_node::load_patterns::MissingTargetBehavior;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_node::nodes::configured_frontend::ConfiguredTargetNodeCalculation;
+use buck2_node::target_calculation::ConfiguredTargetCalculation;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use buck2_server_ctx::pattern::target_platform_from_client_context;
+use dice::DiceComputations;
+use dupe::Dupe;
+use serde_json::json;
+
+use crate::AuditSubcommand;
+
+#[derive(Debug, buck2_error::Error)]
+enum AuditDepPathError {
+    #[error("No target matching `{0}` is reachable from `{1}`")]
+    #[buck2(user)]
+    NoPath(String, String),
+}
+
+/// A dependency, with the attributes of the dependent which carry it.
+type Edge<N> = (N, Vec<String>);
+
+/// Collects the deps carried by each attribute of a node.
+#[derive(Default)]
+struct EdgeCollector {
+    attr: String,
+    deps: HashMap<ConfiguredTargetLabel, Vec<String>>,
+    plugin_deps: HashMap<TargetLabel, Vec<String>>,
+    configuration_deps: Vec<Edge<TargetLabel>>,
+}
+
+impl EdgeCollector {
+    fn add(attrs: &mut Vec<String>, attr: String) {
+        if !attrs.contains(&attr) {
+            attrs.push(attr);
+        }
+    }
+
+    fn add_dep(&mut self, dep: &ConfiguredProvidersLabel, kind: &str) {
+        let attr = format!("{}{}", self.attr, kind);
+        Self::add(self.deps.entry(dep.target().dupe()).or_default(), attr);
+    }
+
+    fn add_configuration_dep(&mut self, dep: &TargetLabel, attr: String) {
+        match self.configuration_deps.iter_mut().find(|(d, _)| d == dep) {
+            Some((_, attrs)) => Self::add(attrs, attr),
+            None => self.configuration_deps.push((dep.dupe(), vec![attr])),
+        }
+    }
+}
+
+impl ConfiguredAttrTraversal for EdgeCollector {
+    fn dep(&mut self, dep: &ConfiguredProvidersLabel) -> anyhow::Result<()> {
+        self.add_dep(dep, "");
+        Ok(())
+    }
+
+    fn exec_dep(&mut self, dep: &ConfiguredProvidersLabel) -> anyhow::Result<()> {
+        self.add_dep(dep, " (exec)");
+        Ok(())
+    }
+
+    fn toolchain_dep(&mut self, dep: &ConfiguredProvidersLabel) -> anyhow::Result<()> {
+        self.add_dep(dep, " (toolchain)");
+        Ok(())
+    }
+
+    fn configuration_dep(&mut self, dep: &TargetLabel) -> anyhow::Result<()> {
+        let attr = format!("{} (configuration)", self.attr);
+        self.add_configuration_dep(dep, attr);
+        Ok(())
+    }
+
+    fn plugin_dep(&mut self, dep: &TargetLabel, _kind: &PluginKind) -> anyhow::Result<()> {
+        let attr = format!("{} (plugin)", self.attr);
+        Self::add(self.plugin_deps.entry(dep.dupe()).or_default(), attr);
+        Ok(())
+    }
+}
+
+/// The deps of a node with the attributes carrying them. Besides the deps of `deps()`, this
+/// includes the configuration deps of its attributes and `select()` keys, and names the plugin
+/// kind of the plugins it uses.
+async fn node_edges(
+    ctx: &DiceComputations,
+    node: &ConfiguredTargetNode,
+) -> anyhow::Result<Vec<Edge<ConfiguredTargetNode>>> {
+    let mut collector = EdgeCollector::default();
+    for attr in node.attrs_where(AttrInspectOptions::All, |attr| attr.0.may_have_deps) {
+        collector.attr = attr.name.to_owned();
+        attr.traverse(node.label().pkg(), &mut collector)?;
+    }
+    for (attr, resolutions) in node.select_resolutions(AttrInspectOptions::All)? {
+        for branch in resolutions
+            .iter()
+            .flat_map(|resolution| &resolution.branches)
+        {
+            if let Some(key) = &branch.key {
+                collector.add_configuration_dep(key, format!("{} (select)", attr));
+            }
+        }
+    }
+
+    let mut edges: Vec<Edge<ConfiguredTargetNode>> = Vec::new();
+    for dep in node.deps() {
+        let mut attrs = collector
+            .deps
+            .get(dep.label())
+            .or_else(|| collector.plugin_deps.get(dep.label().unconfigured()))
+            .cloned()
+            .unwrap_or_default();
+        // Plugins used by the node are exec deps which no attribute of it carries.
+        for kind in node.uses_plugins() {
+            if node
+                .plugin_lists()
+                .iter_for_kind(kind)
+                .any(|(target, _)| target == dep.label().unconfigured())
+            {
+                EdgeCollector::add(&mut attrs, format!("{} (plugin)", kind.as_str()));
+            }
+        }
+        match edges.iter_mut().find(|(d, _)| d == dep) {
+            Some((_, existing)) => {
+                for attr in attrs {
+                    EdgeCollector::add(existing, attr);
+                }
+            }
+            None => edges.push((dep.dupe(), attrs)),
+        }
+    }
+    for (dep, attrs) in collector.configuration_deps {
+        let dep = ctx.get_configured_target(&dep, None).await?;
+        let dep = ctx
+            .get_configured_target_node(&dep)
+            .await?
+            .require_compatible()?;
+        edges.push((dep, attrs));
+    }
+    Ok(edges)
+}
+
+/// The edges of the graph reachable from the roots, explored breadth first without going past
+/// targets. Unless `all`, exploration stops at the first target found, which is enough to find
+/// a shortest path.
+async fn explore<N, F>(
+    roots: &[N],
+    is_target: impl Fn(&N) -> bool,
+    all: bool,
+    edges: impl Fn(N) -> F,
+) -> anyhow::Result<HashMap<N, Vec<Edge<N>>>>
+where
+    N: Clone + Eq + Hash,
+    F: Future<Output = anyhow::Result<Vec<Edge<N>>>>,
+{
+    let mut graph = HashMap::new();
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+    for root in roots {
+        if seen.insert(root.clone()) {
+            queue.push_back(root.clone());
+        }
+    }
+    while let Some(node) = queue.pop_front() {
+        if is_target(&node) {
+            if all {
+                continue;
+            }
+            break;
+        }
+        let node_edges = edges(node.clone()).await?;
+        for (dep, _) in &node_edges {
+            if seen.insert(dep.clone()) {
+                queue.push_back(dep.clone());
+            }
+        }
+        graph.insert(node, node_edges);
+    }
+    Ok(graph)
+}
+
+/// Breadth first search of the shortest path from one of the roots to a target.
+fn shortest_path<N: Clone + Eq + Hash>(
+    graph: &HashMap<N, Vec<Edge<N>>>,
+    roots: &[N],
+    is_target: impl Fn(&N) -> bool,
+) -> Option<Vec<N>> {
+    let mut parents: HashMap<N, Option<N>> = HashMap::new();
+    let mut queue = VecDeque::new();
+    for root in roots {
+        if !parents.contains_key(root) {
+            parents.insert(root.clone(), None);
+            queue.push_back(root.clone());
+        }
+    }
+
+    while let Some(node) = queue.pop_front() {
+        if is_target(&node) {
+            let mut path = vec![node];
+            while let Some(Some(parent)) = parents.get(path.last()?) {
+                path.push(parent.clone());
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for (dep, _) in graph.get(&node).into_iter().flatten() {
+            if !parents.contains_key(dep) {
+                parents.insert(dep.clone(), Some(node.clone()));
+                queue.push_back(dep.clone());
+            }
+        }
+    }
+    None
+}
+
+/// Every path from a root to a target which doesn't go through another target, roots in order
+/// and deps in declaration order.
+fn all_paths<N: Clone + Eq + Hash>(
+    graph: &HashMap<N, Vec<Edge<N>>>,
+    roots: &[N],
+    is_target: impl Fn(&N) -> bool,
+) -> Vec<Vec<N>> {
+    struct Search<'a, N, F> {
+        graph: &'a HashMap<N, Vec<Edge<N>>>,
+        is_target: F,
+        // Whether a target is reachable from a node, so that dead ends are only explored once.
+        reaches: HashMap<N, bool>,
+        path: Vec<N>,
+        paths: Vec<Vec<N>>,
+    }
+
+    impl<N: Clone + Eq + Hash, F: Fn(&N) -> bool> Search<'_, N, F> {
+        fn visit(&mut self, node: &N) -> bool {
+            if self.reaches.get(node) == Some(&false) || self.path.contains(node) {
+                return false;
+            }
+            self.path.push(node.clone());
+            let mut reaches = false;
+            if (self.is_target)(node) {
+                self.paths.push(self.path.clone());
+                reaches = true;
+            } else {
+                for (dep, _) in self.graph.get(node).into_iter().flatten() {
+                    reaches |= self.visit(dep);
+                }
+            }
+            self.path.pop();
+            self.reaches.insert(node.clone(), reaches);
+            reaches
+        }
+    }
+
+    let mut search = Search {
+        graph,
+        is_target,
+        reaches: HashMap::new(),
+        path: Vec::new(),
+        paths: Vec::new(),
+    };
+    let mut seen = HashSet::new();
+    for root in roots {
+        if seen.insert(root.clone()) {
+            search.visit(root);
+        }
+    }
+    search.paths
+}
+
+/// The attributes carrying the edge from `node` to `dep`.
+fn edge_attrs<'a, N: Eq + Hash>(
+    graph: &'a HashMap<N, Vec<Edge<N>>>,
+    node: &N,
+    dep: &N,
+) -> &'a [String] {
+    graph
+        .get(node)
+        .and_then(|edges| edges.iter().find(|(d, _)| d == dep))
+        .map_or(&[], |(_, attrs)| attrs.as_slice())
+}
+
+fn path_to_json(
+    graph: &HashMap<ConfiguredTargetNode, Vec<Edge<ConfiguredTargetNode>>>,
+    path: &[ConfiguredTargetNode],
+) -> serde_json::Value {
+    let mut steps = vec![json!({ "target": path[0].label().to_string() })];
+    for edge in path.windows(2) {
+        steps.push(json!({
+            "target": edge[1].label().to_string(),
+            "attrs": edge_attrs(graph, &edge[0], &edge[1]),
+        }));
+    }
+    json!(steps)
+}
+
+fn write_path(
+    out: &mut impl Write,
+    graph: &HashMap<ConfiguredTargetNode, Vec<Edge<ConfiguredTargetNode>>>,
+    path: &[ConfiguredTargetNode],
+) -> anyhow::Result<()> {
+    writeln!(out, "{}", path[0].label())?;
+    for edge in path.windows(2) {
+        writeln!(
+            out,
+            "  -> {}  [{}]",
+            edge[1].label(),
+            edge_attrs(graph, &edge[0], &edge[1]).join(", ")
+        )?;
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditDepPathCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, mut ctx| {
+                let from = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &mut ctx,
+                    &[buck2_data::TargetPattern {
+                        value: self.from.clone(),
+                    }],
+                    server_ctx.working_dir(),
+                )
+                .await?;
+                let to = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &mut ctx,
+                    &[buck2_data::TargetPattern {
+                        value: self.to.clone(),
+                    }],
+                    server_ctx.working_dir(),
+                )
+                .await?;
+                let loaded_patterns =
+                    load_patterns(&ctx, from, MissingTargetBehavior::Fail).await?;
+                let target_platform =
+                    target_platform_from_client_context(&client_ctx, server_ctx, &mut ctx).await?;
+
+                let mut roots = Vec::new();
+                for (_, targets) in loaded_patterns.into_iter() {
+                    for (_, node) in targets? {
+                        let configured_target = ctx
+                            .get_configured_target(node.label(), target_platform.as_ref())
+                            .await?;
+                        roots.push(
+                            ctx.get_configured_target_node(&configured_target)
+                                .await?
+                                .require_compatible()?,
+                        );
+                    }
+                }
+
+                let is_target = |node: &ConfiguredTargetNode| {
+                    to.iter().any(|p| p.matches(node.label().unconfigured()))
+                };
+                let ctx = &ctx;
+                let graph = explore(&roots, is_target, self.all, |node| async move {
+                    node_edges(ctx, &node).await
+                })
+                .await?;
+                let paths: Vec<_> = if self.all {
+                    all_paths(&graph, &roots, is_target)
+                } else {
+                    shortest_path(&graph, &roots, is_target)
+                        .into_iter()
+                        .collect()
+                };
+                if paths.is_empty() {
+                    return Err(
+                        AuditDepPathError::NoPath(self.to.clone(), self.from.clone()).into(),
+                    );
+                }
+
+                let mut stdout = stdout.as_writer();
+                if self.json {
+                    let paths: Vec<_> = paths
+                        .iter()
+                        .map(|path| path_to_json(&graph, path))
+                        .collect();
+                    serde_json::to_writer_pretty(&mut stdout, &paths)?;
+                    writeln!(stdout)?;
+                } else {
+                    for (i, path) in paths.iter().enumerate() {
+                        if i != 0 {
+                            writeln!(stdout)?;
+                        }
+                        write_path(&mut stdout, &graph, path)?;
+                    }
+                }
+                Ok(())
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::dep_path::all_paths;
+    use crate::dep_path::edge_attrs;
+    use crate::dep_path::explore;
+    use crate::dep_path::shortest_path;
+    use crate::dep_path::Edge;
+
+    /// `a -> b -> d -> e`, `a -> c -> d`, `a -> e`, `b -> f`, and `d -> e -> g`.
+    fn graph() -> HashMap<&'static str, Vec<Edge<&'static str>>> {
+        let edge = |dep, attr: &str| (dep, vec![attr.to_owned()]);
+        HashMap::from([
+            (
+                "a",
+                vec![edge("b", "deps"), edge("c", "deps"), edge("e", "srcs")],
+            ),
+            ("b", vec![edge("d", "deps"), edge("f", "deps")]),
+            ("c", vec![edge("d", "compatible_with (configuration)")]),
+            ("d", vec![edge("e", "deps (exec)")]),
+            ("e", vec![edge("g", "deps")]),
+        ])
+    }
+
+    fn explore_graph(
+        roots: &[&'static str],
+        target: &'static str,
+        all: bool,
+    ) -> HashMap<&'static str, Vec<Edge<&'static str>>> {
+        let graph = graph();
+        futures::executor::block_on(explore(
+            roots,
+            |node| *node == target,
+            all,
+            |node| futures::future::ready(Ok(graph.get(&node).cloned().unwrap_or_default())),
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_explore_stops_at_targets() {
+        let graph = explore_graph(&["a"], "e", true);
+        // `e` is a target, so `g` is never reached.
+        let mut explored: Vec<_> = graph.keys().copied().collect();
+        explored.sort();
+        assert_eq!(vec!["a", "b", "c", "d", "f"], explored);
+
+        // Exploration stops when the first target is reached, so `d` and `f` are not explored.
+        let graph = explore_graph(&["a"], "e", false);
+        let mut explored: Vec<_> = graph.keys().copied().collect();
+        explored.sort();
+        assert_eq!(vec!["a", "b", "c"], explored);
+    }
+
+    #[test]
+    fn test_shortest_path() {
+        let graph = explore_graph(&["a"], "d", false);
+        assert_eq!(
+            Some(vec!["a", "b", "d"]),
+            shortest_path(&graph, &["a"], |node| *node == "d")
+        );
+        let graph = explore_graph(&["c", "b"], "e", false);
+        assert_eq!(
+            Some(vec!["c", "d", "e"]),
+            shortest_path(&graph, &["c", "b"], |node| *node == "e")
+        );
+        let graph = explore_graph(&["f"], "a", false);
+        assert_eq!(None, shortest_path(&graph, &["f"], |node| *node == "a"));
+    }
+
+    #[test]
+    fn test_all_paths() {
+        let graph = explore_graph(&["a"], "e", true);
+        assert_eq!(
+            vec![
+                vec!["a", "b", "d", "e"],
+                vec!["a", "c", "d", "e"],
+                vec!["a", "e"],
+            ],
+            all_paths(&graph, &["a"], |node| *node == "e")
+        );
+        let graph = explore_graph(&["b", "c", "b"], "d", true);
+        assert_eq!(
+            vec![vec!["b", "d"], vec!["c", "d"]],
+            all_paths(&graph, &["b", "c", "b"], |node| *node == "d")
+        );
+        // A root which is a target is a path on its own.
+        let graph = explore_graph(&["e"], "e", true);
+        assert_eq!(
+            vec![vec!["e"]],
+            all_paths(&graph, &["e"], |node| *node == "e")
+        );
+        let graph = explore_graph(&["f"], "a", true);
+        assert!(all_paths(&graph, &["f"], |node| *node == "a").is_empty());
+    }
+
+    #[test]
+    fn test_edge_attrs() {
+        let graph = graph();
+        assert_eq!(
+            ["compatible_with (configuration)".to_owned()],
+            edge_attrs(&graph, &"c", &"d")
+        );
+        assert!(edge_attrs(&graph, &"a", &"g").is_empty());
+    }
+}
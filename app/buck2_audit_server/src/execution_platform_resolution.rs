@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::collections::HashSet;
 use std::io::Write;
 
 use async_trait::async_trait;
@@ -20,6 +21,7 @@ use buck2_core::pattern::ParsedPattern;
 use buck2_core::target::label::TargetLabel;
 use buck2_node::load_patterns::load_patterns;
 use buck2_node::load_patterns::MissingTargetBehavior;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
 use buck2_node::nodes::configured_frontend::ConfiguredTargetNodeCalculation;
 use buck2_node::target_calculation::ConfiguredTargetCalculation;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
@@ -27,6 +29,7 @@ use buck2_server_ctx::ctx::ServerCommandDiceContext;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
 use buck2_server_ctx::pattern::target_platform_from_client_context;
 use buck2_server_ctx::pattern::PatternParser;
+use dupe::Dupe;
 use indent_write::io::IndentWriter;
 
 use crate::AuditSubcommand;
@@ -120,6 +123,9 @@ impl AuditSubcommand for AuditExecutionPlatformResolutionCommand {
                         }
                         Err(e) => writeln!(stdout, "{}", e)?,
                     }
+                    if self.recursive {
+                        write_execution_platform_changes(&mut stdout, &configured_node)?;
+                    }
                 }
 
                 Ok(())
@@ -127,3 +133,81 @@ impl AuditSubcommand for AuditExecutionPlatformResolutionCommand {
         .await
     }
 }
+
+fn execution_platform_id(node: &ConfiguredTargetNode) -> Option<String> {
+    node.execution_platform_resolution()
+        .platform()
+        .ok()
+        .map(|p| p.id())
+}
+
+/// Walk the transitive deps of `root`, and explain every dep whose execution platform differs
+/// from the one of the target depending on it.
+fn write_execution_platform_changes(
+    stdout: &mut impl Write,
+    root: &ConfiguredTargetNode,
+) -> anyhow::Result<()> {
+    writeln!(stdout, "  Deps with a different execution platform:")?;
+
+    let mut visited = HashSet::new();
+    let mut stack = vec![root.dupe()];
+    visited.insert(root.label().dupe());
+
+    while let Some(node) = stack.pop() {
+        let platform = execution_platform_id(&node);
+
+        let toolchain_deps: HashSet<_> = node.toolchain_deps().map(|d| d.label()).collect();
+        let deps = node
+            .target_deps()
+            .map(|d| {
+                let kind = if toolchain_deps.contains(d.label()) {
+                    "toolchain"
+                } else {
+                    "target"
+                };
+                (kind, d)
+            })
+            .chain(node.exec_deps().map(|d| ("exec", d)));
+
+        for (kind, dep) in deps {
+            let dep_platform = execution_platform_id(dep);
+            if dep_platform != platform {
+                writeln!(
+                    stdout,
+                    "    {} ({} dep of {})",
+                    dep.label(),
+                    kind,
+                    node.label()
+                )?;
+                writeln!(
+                    stdout,
+                    "      Execution platform: {} (was {})",
+                    dep_platform.as_deref().unwrap_or("<none>"),
+                    platform.as_deref().unwrap_or("<none>")
+                )?;
+                let resolution = dep.execution_platform_resolution();
+                match resolution
+                    .skipped()
+                    .iter()
+                    .find(|(id, _)| Some(id) == platform.as_ref())
+                {
+                    Some((id, reason)) => {
+                        writeln!(stdout, "      Skipped {}", id)?;
+                        writeln!(IndentWriter::new("        ", &mut *stdout), "{:#}", reason)?;
+                    }
+                    None if dep_platform.is_some() && platform.is_some() => writeln!(
+                        stdout,
+                        "      Resolved to a compatible platform of higher priority"
+                    )?,
+                    None => {}
+                }
+            }
+
+            if visited.insert(dep.label().dupe()) {
+                stack.push(dep.dupe());
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -19,19 +19,25 @@ use buck2_cli_proto::ClientContext;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
 
+mod actions;
 mod analysis_queries;
 mod cell;
+mod cell_resolution;
 mod classpath;
 mod config;
 mod configurations;
 pub mod deferred_materializer;
 mod dep_files;
+mod dep_path;
 mod execution_platform_resolution;
 mod includes;
 pub mod output;
+mod package_errors;
 mod package_values;
 mod prelude;
 mod providers;
+mod sbom;
+mod select;
 pub mod server;
 mod starlark;
 mod subtargets;
@@ -81,6 +87,7 @@ impl AuditCommandExt for AuditCommand {
     fn as_subcommand(&self) -> &dyn AuditSubcommand {
         match self {
             AuditCommand::Cell(cmd) => cmd,
+            AuditCommand::CellResolution(cmd) => cmd,
             AuditCommand::Classpath(cmd) => cmd,
             AuditCommand::Config(cmd) => cmd,
             AuditCommand::Configurations(cmd) => cmd,
@@ -89,6 +96,7 @@ impl AuditCommandExt for AuditCommand {
             AuditCommand::Providers(cmd) => cmd,
             AuditCommand::Subtargets(cmd) => cmd,
             AuditCommand::AnalysisQueries(cmd) => cmd,
+            AuditCommand::Actions(cmd) => cmd,
             AuditCommand::ExecutionPlatformResolution(cmd) => cmd,
             AuditCommand::Starlark(cmd) => cmd,
             AuditCommand::DepFiles(cmd) => cmd,
@@ -97,6 +105,10 @@ impl AuditCommandExt for AuditCommand {
             AuditCommand::Output(cmd) => cmd,
             AuditCommand::Parse(cmd) => cmd,
             AuditCommand::PackageValues(cmd) => cmd,
+            AuditCommand::Select(cmd) => cmd,
+            AuditCommand::Sbom(cmd) => cmd,
+            AuditCommand::DepPath(cmd) => cmd,
+            AuditCommand::PackageErrors(cmd) => cmd,
         }
     }
 }
@@ -0,0 +1,188 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::io::Write;
+
+use async_trait::async_trait;
+use buck2_audit::package_errors::AuditPackageErrorsCommand;
+use buck2_cli_proto::ClientContext;
+use buck2_core::package::PackageLabel;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_node::load_patterns::load_patterns;
+use buck2_node::load_patterns::MissingTargetBehavior;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use gazebo::prelude::SliceExt;
+use serde_json::json;
+
+use crate::AuditSubcommand;
+
+#[derive(buck2_error::Error, Debug)]
+enum PackageErrorsError {
+    #[error("{0} package(s) failed to load")]
+    #[buck2(user)]
+    PackagesFailed(usize),
+}
+
+/// A package which failed to load.
+struct PackageError {
+    package: PackageLabel,
+    /// Where the error is, as `(file, line)`, if the error has a location.
+    location: Option<(String, u32)>,
+    message: String,
+}
+
+/// Find the location of an error in its message: Starlark errors point to the line which failed,
+/// e.g. ` --> foo/BUCK:3:5`.
+fn error_location(message: &str) -> Option<(String, u32)> {
+    message.lines().find_map(|line| {
+        let location = line.trim_start().strip_prefix("--> ")?;
+        let (location, column) = location.rsplit_once(':')?;
+        column.parse::<u32>().ok()?;
+        let (file, line) = location.rsplit_once(':')?;
+        if file.is_empty() {
+            return None;
+        }
+        Some((file.to_owned(), line.parse().ok()?))
+    })
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditPackageErrorsCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        _client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, mut ctx| {
+                let parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &mut ctx,
+                    &self
+                        .patterns
+                        .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
+                    server_ctx.working_dir(),
+                )
+                .await?;
+                // Missing targets don't prevent the packages from loading, so they aren't
+                // reported here.
+                let loaded_patterns =
+                    load_patterns(&ctx, parsed_patterns, MissingTargetBehavior::Warn).await?;
+
+                let mut loaded = 0;
+                let mut errors = Vec::new();
+                for (package, result) in loaded_patterns.iter() {
+                    match result {
+                        Ok(_) => loaded += 1,
+                        Err(e) => {
+                            let message = format!("{:#}", e);
+                            errors.push(PackageError {
+                                package,
+                                location: error_location(&message),
+                                message,
+                            });
+                        }
+                    }
+                }
+
+                let mut stdout = stdout.as_writer();
+                if self.json {
+                    let errors: Vec<_> = errors
+                        .iter()
+                        .map(|error| {
+                            json!({
+                                "package": error.package.to_string(),
+                                "file": error.location.as_ref().map(|(file, _)| file),
+                                "line": error.location.as_ref().map(|(_, line)| line),
+                                "message": error.message,
+                            })
+                        })
+                        .collect();
+                    serde_json::to_writer_pretty(
+                        &mut stdout,
+                        &json!({
+                            "loaded": loaded,
+                            "errors": errors,
+                        }),
+                    )?;
+                    writeln!(stdout)?;
+                } else {
+                    for error in &errors {
+                        match &error.location {
+                            Some((file, line)) => {
+                                writeln!(stdout, "{} ({}:{}):", error.package, file, line)?
+                            }
+                            None => writeln!(stdout, "{}:", error.package)?,
+                        }
+                        for line in error.message.lines() {
+                            writeln!(stdout, "  {}", line)?;
+                        }
+                    }
+                    writeln!(
+                        stdout,
+                        "{} package(s) loaded, {} failed",
+                        loaded,
+                        errors.len()
+                    )?;
+                }
+
+                if !errors.is_empty() {
+                    return Err(PackageErrorsError::PackagesFailed(errors.len()).into());
+                }
+                Ok(())
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(file: &str, line: u32) -> Option<(String, u32)> {
+        Some((file.to_owned(), line))
+    }
+
+    #[test]
+    fn test_error_location_accepted() {
+        assert_eq!(location("foo/BUCK", 3), error_location("--> foo/BUCK:3:5"));
+        assert_eq!(
+            location("foo/BUCK", 12),
+            error_location("error: Variable `x` not found\n   --> foo/BUCK:12:1\n    |\n")
+        );
+        assert_eq!(
+            location("C:\\repo\\foo\\BUCK", 7),
+            error_location(" --> C:\\repo\\foo\\BUCK:7:2")
+        );
+        // The first location is the one which failed.
+        assert_eq!(
+            location("foo/BUCK", 1),
+            error_location(" --> foo/BUCK:1:1\n --> foo/defs.bzl:2:3")
+        );
+    }
+
+    #[test]
+    fn test_error_location_rejected() {
+        assert_eq!(None, error_location("error: Package failed to load"));
+        assert_eq!(None, error_location(""));
+        // No column.
+        assert_eq!(None, error_location("--> foo/BUCK:3"));
+        // Not numbers.
+        assert_eq!(None, error_location("--> foo/BUCK:x:5"));
+        assert_eq!(None, error_location("--> foo/BUCK:3:x"));
+        // No file.
+        assert_eq!(None, error_location("--> :3:5"));
+        // The arrow must start the line and be followed by a space.
+        assert_eq!(None, error_location("see --> foo/BUCK:3:5"));
+        assert_eq!(None, error_location("-->foo/BUCK:3:5"));
+    }
+}
@@ -7,12 +7,15 @@
  * of this source tree.
  */
 
+use std::collections::BTreeMap;
 use std::io::Write;
 
 use async_trait::async_trait;
 use buck2_audit::providers::AuditProvidersCommand;
+use buck2_build_api::actions::artifact::get_artifact_fs::GetArtifactFs;
 use buck2_build_api::analysis::calculation::RuleAnalysisCalculation;
 use buck2_build_api::interpreter::rule_defs::provider::collection::FrozenProviderCollectionValue;
+use buck2_build_api::interpreter::rule_defs::provider::json::ProvidersJson;
 use buck2_cli_proto::ClientContext;
 use buck2_common::dice::cells::HasCellResolver;
 use buck2_common::dice::file_ops::HasFileOps;
@@ -121,13 +124,24 @@ async fn server_execute_with_dice(
     let mut stdout = stdout.as_writer();
     let mut stderr = server_ctx.stderr()?;
 
+    let artifact_fs = ctx.get_artifact_fs().await?;
+    let mut json_results = BTreeMap::new();
+
     let mut at_least_one_error = false;
     while let Some((target, result)) = futs.next().await {
         match result {
             Ok(v) => {
                 let v: FrozenProviderCollectionValue = v.require_compatible()?;
 
-                if command.quiet {
+                if command.json {
+                    json_results.insert(
+                        target.to_string(),
+                        serde_json::to_value(ProvidersJson {
+                            collection: v.provider_collection(),
+                            artifact_fs: &artifact_fs,
+                        })?,
+                    );
+                } else if command.quiet {
                     writeln!(&mut stdout, "{}", target)?
                 } else if command.list {
                     let mut provider_names = v.provider_collection().provider_names();
@@ -172,6 +186,11 @@ async fn server_execute_with_dice(
         }
     }
 
+    if command.json {
+        serde_json::to_writer_pretty(&mut stdout, &json_results)?;
+        writeln!(&mut stdout)?;
+    }
+
     stdout.flush()?;
     stderr.flush()?;
 
@@ -0,0 +1,519 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use async_trait::async_trait;
+use buck2_audit::sbom::AuditSbomCommand;
+use buck2_audit::sbom::SbomFormat;
+use buck2_cli_proto::ClientContext;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
+use buck2_node::attrs::fmt_context::AttrFmtContext;
+use buck2_node::attrs::inspect_options::AttrInspectOptions;
+use buck2_node::attrs::serialize::AttrSerializeWithContextExt;
+use buck2_node::load_patterns::load_patterns;
+use buck2_node::load_patterns::MissingTargetBehavior;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_node::nodes::configured_frontend::ConfiguredTargetNodeCalculation;
+use buck2_node::nodes::unconfigured::RuleKind;
+use buck2_node::target_calculation::ConfiguredTargetCalculation;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use buck2_server_ctx::pattern::target_platform_from_client_context;
+use dupe::Dupe;
+use gazebo::prelude::SliceExt;
+use serde_json::json;
+
+use crate::AuditSubcommand;
+
+/// A target of the SBOM, with the metadata read from its attributes.
+struct SbomPackage {
+    label: ConfiguredTargetLabel,
+    rule_type: String,
+    version: Option<String>,
+    licenses: Vec<String>,
+    deps: Vec<usize>,
+}
+
+/// Collect the strings of an attribute value: a string, or the strings of a list.
+fn attr_strings(value: serde_json::Value, out: &mut Vec<String>) {
+    match value {
+        serde_json::Value::String(s) => out.push(s),
+        serde_json::Value::Array(values) => {
+            for value in values {
+                attr_strings(value, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn read_attr(node: &ConfiguredTargetNode, attr: &str) -> anyhow::Result<Vec<String>> {
+    let mut strings = Vec::new();
+    if let Some(attr) = node.get(attr, AttrInspectOptions::All) {
+        let fmt_ctx = AttrFmtContext {
+            package: Some(node.label().pkg()),
+        };
+        attr_strings(
+            serde_json::to_value(attr.value.as_serialize(&fmt_ctx))?,
+            &mut strings,
+        );
+    }
+    Ok(strings)
+}
+
+/// Collects the targets in the configured dependency graph of some roots, in a stable order.
+struct SbomCollector<'a> {
+    cmd: &'a AuditSbomCommand,
+    packages: Vec<SbomPackage>,
+    indices: HashMap<ConfiguredTargetLabel, usize>,
+    queue: Vec<ConfiguredTargetNode>,
+}
+
+impl<'a> SbomCollector<'a> {
+    /// Returns the packages and the indices of the roots.
+    fn collect(
+        cmd: &'a AuditSbomCommand,
+        roots: &[ConfiguredTargetNode],
+    ) -> anyhow::Result<(Vec<SbomPackage>, Vec<usize>)> {
+        let mut this = SbomCollector {
+            cmd,
+            packages: Vec::new(),
+            indices: HashMap::new(),
+            queue: Vec::new(),
+        };
+        let roots = roots
+            .iter()
+            .map(|root| this.visit(root))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        // Breadth first, so that direct deps come first.
+        let mut next = 0;
+        while next < this.queue.len() {
+            let node = this.queue[next].dupe();
+            let index = this.indices[node.label()];
+            let deps: Vec<&ConfiguredTargetNode> = if cmd.include_exec_deps {
+                node.deps().collect()
+            } else {
+                node.target_deps()
+                    .filter(|dep| dep.rule_kind() != RuleKind::Toolchain)
+                    .collect()
+            };
+            for dep in deps {
+                let dep_index = this.visit(dep)?;
+                if !this.packages[index].deps.contains(&dep_index) {
+                    this.packages[index].deps.push(dep_index);
+                }
+            }
+            next += 1;
+        }
+        Ok((this.packages, roots))
+    }
+
+    fn visit(&mut self, node: &ConfiguredTargetNode) -> anyhow::Result<usize> {
+        if let Some(index) = self.indices.get(node.label()) {
+            return Ok(*index);
+        }
+        let index = self.packages.len();
+        self.indices.insert(node.label().dupe(), index);
+        self.packages.push(SbomPackage {
+            label: node.label().dupe(),
+            rule_type: node.rule_type().name().to_owned(),
+            version: read_attr(node, &self.cmd.version_attr)?.into_iter().next(),
+            licenses: read_attr(node, &self.cmd.license_attr)?,
+            deps: Vec::new(),
+        });
+        self.queue.push(node.dupe());
+        Ok(index)
+    }
+}
+
+/// SPDX license expressions can only be built from identifiers, not from license files.
+fn spdx_license_expression(licenses: &[String]) -> Option<String> {
+    if licenses.is_empty()
+        || !licenses.iter().all(|l| {
+            l.bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'.' || b == b'+')
+        })
+    {
+        return None;
+    }
+    Some(licenses.join(" AND "))
+}
+
+fn spdx_document(
+    packages: &[SbomPackage],
+    roots: &[usize],
+    trace_id: &str,
+    created: &str,
+) -> serde_json::Value {
+    let spdx_id = |index: usize| format!("SPDXRef-Package-{}", index);
+    let spdx_packages = packages.iter().enumerate().map(|(index, package)| {
+        let mut spdx_package = json!({
+            "SPDXID": spdx_id(index),
+            "name": package.label.unconfigured().to_string(),
+            "downloadLocation": "NOASSERTION",
+            "filesAnalyzed": false,
+            "licenseDeclared": spdx_license_expression(&package.licenses)
+                .unwrap_or_else(|| "NOASSERTION".to_owned()),
+            "comment": format!("{} ({})", package.label, package.rule_type),
+        });
+        if let Some(version) = &package.version {
+            spdx_package["versionInfo"] = json!(version);
+        }
+        if !package.licenses.is_empty() && spdx_license_expression(&package.licenses).is_none() {
+            spdx_package["licenseComments"] = json!(package.licenses.join(", "));
+        }
+        spdx_package
+    });
+    let describes = roots.iter().map(|root| {
+        json!({
+            "spdxElementId": "SPDXRef-DOCUMENT",
+            "relationshipType": "DESCRIBES",
+            "relatedSpdxElement": spdx_id(*root),
+        })
+    });
+    let depends_on = packages.iter().enumerate().flat_map(|(index, package)| {
+        package.deps.iter().map(move |dep| {
+            json!({
+                "spdxElementId": spdx_id(index),
+                "relationshipType": "DEPENDS_ON",
+                "relatedSpdxElement": spdx_id(*dep),
+            })
+        })
+    });
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": roots
+            .iter()
+            .map(|root| packages[*root].label.unconfigured().to_string())
+            .collect::<Vec<_>>()
+            .join(" "),
+        "documentNamespace": format!("https://buck2.build/spdx/{}", trace_id),
+        "creationInfo": {
+            "created": created,
+            "creators": ["Tool: buck2"],
+        },
+        "packages": spdx_packages.collect::<Vec<_>>(),
+        "relationships": describes.chain(depends_on).collect::<Vec<_>>(),
+    })
+}
+
+fn cyclonedx_document(
+    packages: &[SbomPackage],
+    roots: &[usize],
+    trace_id: &str,
+    created: &str,
+) -> serde_json::Value {
+    let bom_ref = |index: usize| packages[index].label.to_string();
+    let components = packages.iter().enumerate().map(|(index, package)| {
+        let mut component = json!({
+            "type": "library",
+            "bom-ref": bom_ref(index),
+            "name": package.label.unconfigured().to_string(),
+            "properties": [{"name": "buck2:rule_type", "value": package.rule_type}],
+        });
+        if let Some(version) = &package.version {
+            component["version"] = json!(version);
+        }
+        if !package.licenses.is_empty() {
+            component["licenses"] = match spdx_license_expression(&package.licenses) {
+                Some(expression) => json!([{ "expression": expression }]),
+                None => package
+                    .licenses
+                    .iter()
+                    .map(|license| json!({ "license": { "name": license } }))
+                    .collect(),
+            };
+        }
+        component
+    });
+    let dependencies = packages.iter().enumerate().map(|(index, package)| {
+        json!({
+            "ref": bom_ref(index),
+            "dependsOn": package.deps.map(|dep| bom_ref(*dep)),
+        })
+    });
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "serialNumber": format!("urn:uuid:{}", trace_id),
+        "version": 1,
+        "metadata": {
+            "timestamp": created,
+            "tools": [{ "name": "buck2" }],
+            "properties": roots
+                .iter()
+                .map(|root| json!({ "name": "buck2:root", "value": bom_ref(*root) }))
+                .collect::<Vec<_>>(),
+        },
+        "components": components.collect::<Vec<_>>(),
+        "dependencies": dependencies.collect::<Vec<_>>(),
+    })
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditSbomCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, mut ctx| {
+                let parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &mut ctx,
+                    &self
+                        .patterns
+                        .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
+                    server_ctx.working_dir(),
+                )
+                .await?;
+                let loaded_patterns =
+                    load_patterns(&ctx, parsed_patterns, MissingTargetBehavior::Fail).await?;
+                let target_platform =
+                    target_platform_from_client_context(&client_ctx, server_ctx, &mut ctx).await?;
+
+                let mut roots = Vec::new();
+                for (_, targets) in loaded_patterns.into_iter() {
+                    for (_, node) in targets? {
+                        let configured_target = ctx
+                            .get_configured_target(node.label(), target_platform.as_ref())
+                            .await?;
+                        roots.push(
+                            ctx.get_configured_target_node(&configured_target)
+                                .await?
+                                .require_compatible()?,
+                        );
+                    }
+                }
+
+                let (packages, roots) = SbomCollector::collect(self, &roots)?;
+                let trace_id = server_ctx.events().trace_id().to_string();
+                let created = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+                let document = match self.format {
+                    SbomFormat::Spdx => spdx_document(&packages, &roots, &trace_id, &created),
+                    SbomFormat::Cyclonedx => {
+                        cyclonedx_document(&packages, &roots, &trace_id, &created)
+                    }
+                };
+
+                let mut stdout = stdout.as_writer();
+                serde_json::to_writer_pretty(&mut stdout, &document)?;
+                writeln!(stdout)?;
+                Ok(())
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
+    use serde_json::json;
+
+    use crate::sbom::attr_strings;
+    use crate::sbom::cyclonedx_document;
+    use crate::sbom::spdx_document;
+    use crate::sbom::spdx_license_expression;
+    use crate::sbom::SbomPackage;
+
+    const TRACE_ID: &str = "00000000-0000-0000-0000-000000000000";
+    const CREATED: &str = "2024-01-01T00:00:00Z";
+
+    fn label(label: &str) -> ConfiguredTargetLabel {
+        ConfiguredTargetLabel::testing_parse(label, ConfigurationData::testing_new())
+    }
+
+    /// `root//app:app` depends on `zlib` and `foo`, and `foo` on `zlib`.
+    fn packages() -> Vec<SbomPackage> {
+        vec![
+            SbomPackage {
+                label: label("root//app:app"),
+                rule_type: "cxx_binary".to_owned(),
+                version: None,
+                licenses: Vec::new(),
+                deps: vec![1, 2],
+            },
+            SbomPackage {
+                label: label("root//third-party/zlib:zlib"),
+                rule_type: "prebuilt_cxx_library".to_owned(),
+                version: Some("1.3".to_owned()),
+                licenses: vec!["Zlib".to_owned()],
+                deps: Vec::new(),
+            },
+            SbomPackage {
+                label: label("root//third-party/foo:foo"),
+                rule_type: "rust_library".to_owned(),
+                version: Some("0.2.0".to_owned()),
+                licenses: vec!["MIT".to_owned(), "third-party/foo/COPYING".to_owned()],
+                deps: vec![1],
+            },
+        ]
+    }
+
+    #[test]
+    fn test_spdx_document() {
+        let packages = packages();
+        let [app, zlib, foo] = [0, 1, 2].map(|i| packages[i].label.to_string());
+        assert_eq!(
+            json!({
+                "spdxVersion": "SPDX-2.3",
+                "dataLicense": "CC0-1.0",
+                "SPDXID": "SPDXRef-DOCUMENT",
+                "name": "root//app:app",
+                "documentNamespace": format!("https://buck2.build/spdx/{}", TRACE_ID),
+                "creationInfo": {
+                    "created": CREATED,
+                    "creators": ["Tool: buck2"],
+                },
+                "packages": [
+                    {
+                        "SPDXID": "SPDXRef-Package-0",
+                        "name": "root//app:app",
+                        "downloadLocation": "NOASSERTION",
+                        "filesAnalyzed": false,
+                        "licenseDeclared": "NOASSERTION",
+                        "comment": format!("{} (cxx_binary)", app),
+                    },
+                    {
+                        "SPDXID": "SPDXRef-Package-1",
+                        "name": "root//third-party/zlib:zlib",
+                        "downloadLocation": "NOASSERTION",
+                        "filesAnalyzed": false,
+                        "licenseDeclared": "Zlib",
+                        "comment": format!("{} (prebuilt_cxx_library)", zlib),
+                        "versionInfo": "1.3",
+                    },
+                    {
+                        "SPDXID": "SPDXRef-Package-2",
+                        "name": "root//third-party/foo:foo",
+                        "downloadLocation": "NOASSERTION",
+                        "filesAnalyzed": false,
+                        "licenseDeclared": "NOASSERTION",
+                        "comment": format!("{} (rust_library)", foo),
+                        "versionInfo": "0.2.0",
+                        "licenseComments": "MIT, third-party/foo/COPYING",
+                    },
+                ],
+                "relationships": [
+                    {
+                        "spdxElementId": "SPDXRef-DOCUMENT",
+                        "relationshipType": "DESCRIBES",
+                        "relatedSpdxElement": "SPDXRef-Package-0",
+                    },
+                    {
+                        "spdxElementId": "SPDXRef-Package-0",
+                        "relationshipType": "DEPENDS_ON",
+                        "relatedSpdxElement": "SPDXRef-Package-1",
+                    },
+                    {
+                        "spdxElementId": "SPDXRef-Package-0",
+                        "relationshipType": "DEPENDS_ON",
+                        "relatedSpdxElement": "SPDXRef-Package-2",
+                    },
+                    {
+                        "spdxElementId": "SPDXRef-Package-2",
+                        "relationshipType": "DEPENDS_ON",
+                        "relatedSpdxElement": "SPDXRef-Package-1",
+                    },
+                ],
+            }),
+            spdx_document(&packages, &[0], TRACE_ID, CREATED)
+        );
+    }
+
+    #[test]
+    fn test_cyclonedx_document() {
+        let packages = packages();
+        let [app, zlib, foo] = [0, 1, 2].map(|i| packages[i].label.to_string());
+        assert_eq!(
+            json!({
+                "bomFormat": "CycloneDX",
+                "specVersion": "1.5",
+                "serialNumber": format!("urn:uuid:{}", TRACE_ID),
+                "version": 1,
+                "metadata": {
+                    "timestamp": CREATED,
+                    "tools": [{ "name": "buck2" }],
+                    "properties": [{ "name": "buck2:root", "value": app }],
+                },
+                "components": [
+                    {
+                        "type": "library",
+                        "bom-ref": app,
+                        "name": "root//app:app",
+                        "properties": [{ "name": "buck2:rule_type", "value": "cxx_binary" }],
+                    },
+                    {
+                        "type": "library",
+                        "bom-ref": zlib,
+                        "name": "root//third-party/zlib:zlib",
+                        "properties": [
+                            { "name": "buck2:rule_type", "value": "prebuilt_cxx_library" }
+                        ],
+                        "version": "1.3",
+                        "licenses": [{ "expression": "Zlib" }],
+                    },
+                    {
+                        "type": "library",
+                        "bom-ref": foo,
+                        "name": "root//third-party/foo:foo",
+                        "properties": [{ "name": "buck2:rule_type", "value": "rust_library" }],
+                        "version": "0.2.0",
+                        "licenses": [
+                            { "license": { "name": "MIT" } },
+                            { "license": { "name": "third-party/foo/COPYING" } },
+                        ],
+                    },
+                ],
+                "dependencies": [
+                    { "ref": app, "dependsOn": [zlib, foo] },
+                    { "ref": zlib, "dependsOn": [] },
+                    { "ref": foo, "dependsOn": [zlib] },
+                ],
+            }),
+            cyclonedx_document(&packages, &[0], TRACE_ID, CREATED)
+        );
+    }
+
+    #[test]
+    fn test_spdx_license_expression() {
+        assert_eq!(None, spdx_license_expression(&[]));
+        assert_eq!(
+            Some("MIT AND Apache-2.0".to_owned()),
+            spdx_license_expression(&["MIT".to_owned(), "Apache-2.0".to_owned()])
+        );
+        assert_eq!(
+            Some("GPL-2.0+".to_owned()),
+            spdx_license_expression(&["GPL-2.0+".to_owned()])
+        );
+        assert_eq!(
+            None,
+            spdx_license_expression(&["MIT".to_owned(), "foo/LICENSE".to_owned()])
+        );
+    }
+
+    #[test]
+    fn test_attr_strings() {
+        let mut strings = Vec::new();
+        attr_strings(json!(["MIT", ["Zlib"], 1, null]), &mut strings);
+        attr_strings(json!("BSD-3-Clause"), &mut strings);
+        attr_strings(json!({"a": "b"}), &mut strings);
+        assert_eq!(vec!["MIT", "Zlib", "BSD-3-Clause"], strings);
+    }
+}
@@ -0,0 +1,124 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::io::Write;
+
+use async_trait::async_trait;
+use buck2_audit::select::AuditSelectCommand;
+use buck2_cli_proto::ClientContext;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_node::attrs::coerced_attr::SelectBranchResolution;
+use buck2_node::attrs::display::AttrDisplayWithContextExt;
+use buck2_node::attrs::fmt_context::AttrFmtContext;
+use buck2_node::attrs::inspect_options::AttrInspectOptions;
+use buck2_node::load_patterns::load_patterns;
+use buck2_node::load_patterns::MissingTargetBehavior;
+use buck2_node::nodes::configured_frontend::ConfiguredTargetNodeCalculation;
+use buck2_node::target_calculation::ConfiguredTargetCalculation;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use buck2_server_ctx::pattern::target_platform_from_client_context;
+use gazebo::prelude::SliceExt;
+
+use crate::AuditSubcommand;
+
+fn write_branch(
+    stdout: &mut dyn Write,
+    branch: &SelectBranchResolution,
+    fmt_ctx: &AttrFmtContext,
+) -> anyhow::Result<()> {
+    let marker = if branch.selected { "*" } else { " " };
+    match &branch.key {
+        Some(key) => writeln!(stdout, "      {} {}", marker, key)?,
+        None => writeln!(stdout, "      {} DEFAULT", marker)?,
+    }
+    match &branch.matched {
+        Some(setting) => {
+            writeln!(stdout, "          matched by:")?;
+            for (key, value) in &setting.constraints {
+                writeln!(stdout, "            {} = {}", key, value)?;
+            }
+            for (key, value) in &setting.buckconfigs {
+                writeln!(stdout, "            {} = {}", key, value)?;
+            }
+        }
+        None if branch.key.is_some() => writeln!(stdout, "          not matched")?,
+        None => {}
+    }
+    match &branch.value {
+        Ok(value) => writeln!(stdout, "          value: {}", value.as_display(fmt_ctx))?,
+        Err(e) => writeln!(stdout, "          value: <error: {:#}>", e)?,
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditSelectCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, mut ctx| {
+                let parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &mut ctx,
+                    &self
+                        .patterns
+                        .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
+                    server_ctx.working_dir(),
+                )
+                .await?;
+                let loaded_patterns =
+                    load_patterns(&ctx, parsed_patterns, MissingTargetBehavior::Fail).await?;
+                let target_platform =
+                    target_platform_from_client_context(&client_ctx, server_ctx, &mut ctx).await?;
+
+                let mut stdout = stdout.as_writer();
+
+                for (_, targets) in loaded_patterns.into_iter() {
+                    for (_, node) in targets? {
+                        let configured_target = ctx
+                            .get_configured_target(node.label(), target_platform.as_ref())
+                            .await?;
+                        let configured_node =
+                            ctx.get_configured_target_node(&configured_target).await?;
+                        let configured_node = configured_node.require_compatible()?;
+                        let fmt_ctx = AttrFmtContext {
+                            package: Some(configured_node.label().pkg()),
+                        };
+
+                        writeln!(stdout, "{}:", configured_target)?;
+                        for (attr, resolutions) in
+                            configured_node.select_resolutions(AttrInspectOptions::All)?
+                        {
+                            if !self.attributes.is_empty()
+                                && !self.attributes.iter().any(|a| a == attr)
+                            {
+                                continue;
+                            }
+                            writeln!(stdout, "  {}:", attr)?;
+                            for (i, resolution) in resolutions.iter().enumerate() {
+                                writeln!(stdout, "    select #{}", i + 1)?;
+                                for branch in &resolution.branches {
+                                    write_branch(&mut stdout, branch, &fmt_ctx)?;
+                                }
+                            }
+                        }
+                    }
+                }
+
+                Ok(())
+            })
+            .await
+    }
+}
@@ -7,10 +7,15 @@
  * of this source tree.
  */
 
+use std::fmt::Display;
+use std::io::Write;
+
 use async_trait::async_trait;
 use buck2_audit::visibility::AuditVisibilityCommand;
 use buck2_cli_proto::ClientContext;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::target::label::TargetLabel;
 use buck2_node::load_patterns::load_patterns;
 use buck2_node::load_patterns::MissingTargetBehavior;
 use buck2_node::nodes::lookup::TargetNodeLookup;
@@ -24,6 +29,7 @@ use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::ctx::ServerCommandDiceContext;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
 use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use dice::DiceComputations;
 use dice::DiceTransaction;
 use dupe::Dupe;
 use gazebo::prelude::SliceExt;
@@ -36,6 +42,91 @@ enum VisibilityCommandError {
         "Internal Error: The dependency `{0}` of the target `{1}` was not found during the traversal."
     )]
     DepNodeNotFound(String, String),
+    #[error("`{0}` is not visible to {1} of the specified target(s)")]
+    #[buck2(user)]
+    NotVisible(String, usize),
+}
+
+fn write_matches(
+    stdout: &mut dyn Write,
+    attr: &str,
+    owner: &TargetLabel,
+    spec: &dyn Display,
+    matches: &[String],
+) -> anyhow::Result<()> {
+    writeln!(stdout, "  `{}` of `{}`: {}", attr, owner, spec)?;
+    if matches.is_empty() {
+        writeln!(stdout, "    no pattern matched")?;
+    }
+    for pattern in matches {
+        writeln!(stdout, "    matched by `{}`", pattern)?;
+    }
+    Ok(())
+}
+
+/// Explain whether `target` may depend on `dep`: the dep's `visibility` must match the target,
+/// and the target's `within_view` must match the dep. Returns whether the dep is visible.
+fn explain_visibility(
+    stdout: &mut dyn Write,
+    target: &TargetNode,
+    dep: &TargetNode,
+) -> anyhow::Result<bool> {
+    writeln!(stdout, "{} -> {}:", target.label(), dep.label())?;
+    if target.label().pkg() == dep.label().pkg() {
+        writeln!(stdout, "  visible: targets are in the same package")?;
+        return Ok(true);
+    }
+
+    let visibility = dep.visibility()?;
+    let visibility_matches = visibility.0.matching_patterns(target.label());
+    write_matches(
+        stdout,
+        "visibility",
+        dep.label(),
+        visibility,
+        &visibility_matches,
+    )?;
+
+    let within_view = target.within_view()?;
+    let within_view_matches = within_view.0.matching_patterns(dep.label());
+    write_matches(
+        stdout,
+        "within_view",
+        target.label(),
+        within_view,
+        &within_view_matches,
+    )?;
+
+    let visible = !visibility_matches.is_empty() && !within_view_matches.is_empty();
+    writeln!(
+        stdout,
+        "  {}",
+        if visible { "visible" } else { "not visible" }
+    )?;
+    Ok(visible)
+}
+
+async fn load_target_nodes(
+    ctx: &mut DiceComputations,
+    patterns: &[String],
+    working_dir: &ProjectRelativePath,
+) -> anyhow::Result<TargetSet<TargetNode>> {
+    let parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
+        ctx,
+        &patterns.map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
+        working_dir,
+    )
+    .await?;
+
+    let parsed_target_patterns =
+        load_patterns(ctx, parsed_patterns, MissingTargetBehavior::Fail).await?;
+
+    let mut nodes = TargetSet::<TargetNode>::new();
+    for (_package, result) in parsed_target_patterns.iter() {
+        let res = result.as_ref().map_err(Dupe::dupe)?;
+        nodes.extend(res.values());
+    }
+    Ok(nodes)
 }
 
 async fn verify_visibility(
@@ -99,6 +190,14 @@ async fn verify_visibility(
 
     for err in &visibility_errors {
         buck2_client_ctx::eprintln!("{}", err)?;
+        let VisibilityError::NotVisibleTo(dep, _) = err;
+        if let Some(dep) = delegate.targets.get(dep) {
+            buck2_client_ctx::eprintln!(
+                "  visibility of `{}`: {}",
+                dep.label(),
+                dep.visibility()?
+            )?;
+        }
     }
 
     if !visibility_errors.is_empty() {
@@ -114,31 +213,42 @@ impl AuditSubcommand for AuditVisibilityCommand {
     async fn server_execute(
         &self,
         server_ctx: &dyn ServerCommandContextTrait,
-        _stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
         _client_ctx: ClientContext,
     ) -> anyhow::Result<()> {
         server_ctx
             .with_dice_ctx(async move |server_ctx, mut ctx| {
-                let parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
-                    &mut ctx,
-                    &self
-                        .patterns
-                        .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
-                    server_ctx.working_dir(),
-                )
-                .await?;
-
-                let parsed_target_patterns =
-                    load_patterns(&ctx, parsed_patterns, MissingTargetBehavior::Fail).await?;
-
-                let mut nodes = TargetSet::<TargetNode>::new();
-                for (_package, result) in parsed_target_patterns.iter() {
-                    let res = result.as_ref().map_err(Dupe::dupe)?;
-                    nodes.extend(res.values());
+                let nodes =
+                    load_target_nodes(&mut ctx, &self.patterns, server_ctx.working_dir()).await?;
+
+                match &self.explain {
+                    None => verify_visibility(ctx, nodes).await,
+                    Some(dep) => {
+                        let deps = load_target_nodes(
+                            &mut ctx,
+                            std::slice::from_ref(dep),
+                            server_ctx.working_dir(),
+                        )
+                        .await?;
+                        let mut stdout = stdout.as_writer();
+                        for dep in deps.iter() {
+                            let mut not_visible = 0;
+                            for target in nodes.iter() {
+                                if !explain_visibility(&mut stdout, target, dep)? {
+                                    not_visible += 1;
+                                }
+                            }
+                            if not_visible != 0 {
+                                return Err(VisibilityCommandError::NotVisible(
+                                    dep.label().to_string(),
+                                    not_visible,
+                                )
+                                .into());
+                            }
+                        }
+                        Ok(())
+                    }
                 }
-
-                verify_visibility(ctx, nodes).await?;
-                Ok(())
             })
             .await
     }
@@ -7,6 +7,9 @@
  * of this source tree.
  */
 
+use std::any::Any;
+use std::fmt;
+use std::fmt::Write;
 use std::iter::zip;
 use std::sync::Arc;
 use std::time::Instant;
@@ -16,6 +19,8 @@ use async_trait::async_trait;
 use buck2_artifact::actions::key::ActionKey;
 use buck2_artifact::artifact::build_artifact::BuildArtifact;
 use buck2_build_signals::NodeDuration;
+use buck2_common::dice::cycles::CycleAdapterDescriptor;
+use buck2_common::dice::cycles::CycleGuard;
 use buck2_common::events::HasEvents;
 use buck2_data::ActionErrorDiagnostics;
 use buck2_data::ActionSubErrors;
@@ -31,6 +36,7 @@ use buck2_execute::output_size::OutputSize;
 use buck2_futures::cancellation::CancellationContext;
 use buck2_interpreter::error::BuckStarlarkError;
 use buck2_interpreter::print_handler::EventDispatcherPrintHandler;
+use buck2_util::cycle_detector::CycleDescriptor;
 use derive_more::Display;
 use dice::DiceComputations;
 use dice::Key;
@@ -54,6 +60,7 @@ use crate::actions::execute::action_executor::HasActionExecutor;
 use crate::actions::key::ActionKeyExt;
 use crate::actions::RegisteredAction;
 use crate::artifact_groups::calculation::ensure_artifact_group_staged;
+use crate::artifact_groups::ArtifactGroup;
 use crate::deferred::calculation::DeferredCalculation;
 use crate::keep_going;
 use crate::starlark::values::type_repr::StarlarkTypeRepr;
@@ -102,8 +109,15 @@ async fn build_action_no_redirect(
             .map(|v| ensure_artifact_group_staged(ctx, v))
             .collect();
 
-        let ready_inputs: Vec<_> =
-            tokio::task::unconstrained(keep_going::try_join_all(ctx, ensure_futs)).await?;
+        let ready_inputs: Vec<_> = match ActionCycleDescriptor::guard_this(
+            ctx,
+            tokio::task::unconstrained(keep_going::try_join_all(ctx, ensure_futs)),
+        )
+        .await?
+        {
+            Ok(ready_inputs) => ready_inputs?,
+            Err(cycle) => return Err(describe_action_cycle(ctx, &cycle).await?.into()),
+        };
 
         let mut results = IndexMap::with_capacity(inputs.len());
         for (artifact, ready) in zip(inputs.iter(), ready_inputs) {
@@ -172,8 +186,8 @@ async fn build_action_no_redirect(
         let error_diagnostics = match execute_result {
             Ok((outputs, meta)) => {
                 output_size = outputs.calc_output_count_and_bytes().bytes;
-                action_result = Ok(outputs);
                 execution_kind = Some(meta.execution_kind.as_enum());
+                action_result = Ok(outputs.with_execution_kind(meta.execution_kind.as_enum()));
                 wall_time = Some(meta.timing.wall_time);
                 error = None;
 
@@ -427,6 +441,99 @@ impl Key for BuildKey {
     }
 }
 
+#[derive(Debug, buck2_error::Error)]
+#[buck2(user)]
+enum ActionCycleError {
+    #[error("Action cycle detected (`->` means \"depends on\"):\n{0}")]
+    Cycle(String),
+}
+
+/// Actions forming a cycle, each depending on the next one, and the last one on the first one.
+#[derive(Debug, Clone)]
+pub struct ActionCycle(Arc<Vec<ActionKey>>);
+
+impl fmt::Display for ActionCycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for key in self.0.iter() {
+            write!(f, "{} -> ", key)?;
+        }
+        match self.0.first() {
+            Some(first) => write!(f, "{}", first),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Detects cycles between actions, that is, between `BuildKey`s. Cycles going through keys that
+/// are not tracked here (such as transitive set projections) are left to DICE.
+#[derive(Debug)]
+pub struct ActionCycleDescriptor;
+
+impl CycleDescriptor for ActionCycleDescriptor {
+    type Key = ActionKey;
+    type Error = ActionCycle;
+
+    fn cycle_error(cycle: Vec<&Self::Key>) -> Self::Error {
+        ActionCycle(Arc::new(cycle.into_iter().map(|k| k.dupe()).collect()))
+    }
+}
+
+impl CycleAdapterDescriptor for ActionCycleDescriptor {
+    fn to_key(key: &dyn Any) -> Option<Self::Key> {
+        key.downcast_ref::<BuildKey>().map(|k| k.0.dupe())
+    }
+}
+
+fn display_action(action: &RegisteredAction) -> String {
+    let mut s = format!("{} ({}", action.owner(), action.category());
+    if let Some(identifier) = action.identifier() {
+        write!(s, " {}", identifier).unwrap();
+    }
+    s.push(')');
+    if let Some(location) = action.declaration_location() {
+        write!(s, ", output declared at {}", location).unwrap();
+    }
+    s
+}
+
+/// Describe the actions of a cycle, along with the inputs creating each edge.
+async fn describe_action_cycle(
+    ctx: &DiceComputations,
+    cycle: &ActionCycle,
+) -> anyhow::Result<ActionCycleError> {
+    let actions = future::try_join_all(
+        cycle
+            .0
+            .iter()
+            .map(|key| ActionCalculation::get_action(ctx, key)),
+    )
+    .await?;
+
+    let mut s = String::new();
+    for (i, action) in actions.iter().enumerate() {
+        let next = &actions[(i + 1) % actions.len()];
+        writeln!(s, "  {} ->", display_action(action)).unwrap();
+
+        let mut found = false;
+        for input in action.inputs()?.iter() {
+            if let ArtifactGroup::Artifact(artifact) = input {
+                if artifact.action_key() == Some(next.key()) {
+                    writeln!(s, "    input `{}` is an output of", artifact).unwrap();
+                    found = true;
+                }
+            }
+        }
+        if !found {
+            writeln!(s, "    through an indirect input, on").unwrap();
+        }
+    }
+    // Point back at the first action in the cycle.
+    if let Some(first) = actions.first() {
+        writeln!(s, "  {}", display_action(first)).unwrap();
+    }
+    Ok(ActionCycleError::Cycle(s))
+}
+
 async fn command_execution_report_to_proto(
     report: &CommandExecutionReport,
     allow_omit_details: bool,
@@ -20,6 +20,9 @@ use buck2_common::events::HasEvents;
 use buck2_common::http::HasHttpClient;
 use buck2_common::io::IoProvider;
 use buck2_common::liveliness_observer::NoopLivelinessObserver;
+use buck2_core::directory::DirectoryEntry;
+use buck2_core::directory::DirectoryIterator;
+use buck2_core::directory::FingerprintedDirectory;
 use buck2_core::execution_types::executor_config::CommandExecutorConfig;
 use buck2_core::fs::artifact_path_resolver::ArtifactFs;
 use buck2_core::fs::buck_out_path::BuckOutPath;
@@ -28,6 +31,7 @@ use buck2_execute::artifact::fs::ExecutorFs;
 use buck2_execute::artifact_value::ArtifactValue;
 use buck2_execute::digest_config::DigestConfig;
 use buck2_execute::digest_config::HasDigestConfig;
+use buck2_execute::directory::ActionDirectoryMember;
 use buck2_execute::execute::action_digest_and_blobs::ActionDigestAndBlobs;
 use buck2_execute::execute::blocking::BlockingExecutor;
 use buck2_execute::execute::blocking::HasBlockingExecutor;
@@ -41,6 +45,8 @@ use buck2_execute::execute::command_executor::CommandExecutor;
 use buck2_execute::execute::dep_file_digest::DepFileDigest;
 use buck2_execute::execute::kind::CommandExecutionKind;
 use buck2_execute::execute::manager::CommandExecutionManager;
+use buck2_execute::execute::merged_directory_cache::HasMergedDirectoryCache;
+use buck2_execute::execute::merged_directory_cache::MergedDirectoryCache;
 use buck2_execute::execute::prepared::PreparedAction;
 use buck2_execute::execute::prepared::PreparedCommand;
 use buck2_execute::execute::request::CommandExecutionRequest;
@@ -103,6 +109,10 @@ impl OutputSize for ActionOutputs {
 #[derivative(PartialEq, Eq)]
 struct ActionOutputsData {
     outputs: IndexMap<BuckOutPath, ArtifactValue>,
+    /// How the action was executed. This is not compared, so that an action which produced the
+    /// same outputs in a different way does not invalidate its dependents.
+    #[derivative(PartialEq = "ignore")]
+    execution_kind: Option<buck2_data::ActionExecutionKind>,
 }
 
 /// Metadata associated with the execution of this action.
@@ -190,7 +200,22 @@ impl ActionExecutionKind {
 
 impl ActionOutputs {
     pub fn new(outputs: IndexMap<BuckOutPath, ArtifactValue>) -> Self {
-        Self(Arc::new(ActionOutputsData { outputs }))
+        Self(Arc::new(ActionOutputsData {
+            outputs,
+            execution_kind: None,
+        }))
+    }
+
+    /// Record how the action which produced these outputs was executed.
+    pub fn with_execution_kind(self, execution_kind: buck2_data::ActionExecutionKind) -> Self {
+        let outputs = match Arc::try_unwrap(self.0) {
+            Ok(data) => data.outputs,
+            Err(data) => data.outputs.clone(),
+        };
+        Self(Arc::new(ActionOutputsData {
+            outputs,
+            execution_kind: Some(execution_kind),
+        }))
     }
 
     pub fn from_single(artifact: BuckOutPath, value: ArtifactValue) -> Self {
@@ -208,6 +233,12 @@ impl ActionOutputs {
     pub fn values(&self) -> impl Iterator<Item = &ArtifactValue> {
         self.0.outputs.values()
     }
+
+    /// How the action was executed, when these outputs were produced by executing it. This is
+    /// the execution which produced the outputs, which may have happened in an earlier build.
+    pub fn execution_kind(&self) -> Option<buck2_data::ActionExecutionKind> {
+        self.0.execution_kind
+    }
 }
 
 /// Executes 'Actions'
@@ -255,6 +286,7 @@ impl HasActionExecutor for DiceComputations {
         let io_provider = self.global_data().get_io_provider();
         let http_client = self.per_transaction_data().get_http_client();
         let mergebase = self.per_transaction_data().get_mergebase();
+        let merged_directory_cache = self.per_transaction_data().get_merged_directory_cache();
 
         Ok(Arc::new(BuckActionExecutor::new(
             CommandExecutor::new(
@@ -274,6 +306,7 @@ impl HasActionExecutor for DiceComputations {
             io_provider,
             http_client,
             mergebase,
+            merged_directory_cache,
         )))
     }
 }
@@ -289,6 +322,7 @@ pub struct BuckActionExecutor {
     io_provider: Arc<dyn IoProvider>,
     http_client: HttpClient,
     mergebase: Mergebase,
+    merged_directory_cache: Arc<MergedDirectoryCache>,
 }
 
 impl BuckActionExecutor {
@@ -303,6 +337,7 @@ impl BuckActionExecutor {
         io_provider: Arc<dyn IoProvider>,
         http_client: HttpClient,
         mergebase: Mergebase,
+        merged_directory_cache: Arc<MergedDirectoryCache>,
     ) -> Self {
         Self {
             command_executor,
@@ -315,6 +350,7 @@ impl BuckActionExecutor {
             io_provider,
             http_client,
             mergebase,
+            merged_directory_cache,
         }
     }
 }
@@ -328,6 +364,44 @@ struct BuckActionExecutionContext<'a> {
     cancellations: &'a CancellationContext<'a>,
 }
 
+impl BuckActionExecutionContext<'_> {
+    fn log_action_details(&self, request: &CommandExecutionRequest) {
+        let mut inputs = Vec::new();
+        for (path, entry) in request
+            .paths()
+            .input_directory()
+            .fingerprinted_ordered_walk()
+            .with_paths()
+        {
+            if let DirectoryEntry::Leaf(ActionDirectoryMember::File(metadata)) = entry {
+                inputs.push(buck2_data::ActionInputFile {
+                    path: path.to_string(),
+                    digest: metadata.digest.data().to_string(),
+                });
+            }
+        }
+        self.executor
+            .events
+            .instant_event(buck2_data::ActionCommandDetails {
+                key: Some(self.action.key().as_proto()),
+                name: Some(buck2_data::ActionName {
+                    category: self.action.category().as_str().to_owned(),
+                    identifier: self.action.identifier().unwrap_or("").to_owned(),
+                }),
+                argv: request.all_args_vec(),
+                env: request
+                    .env()
+                    .iter()
+                    .map(|(key, value)| buck2_data::EnvironmentEntry {
+                        key: key.clone(),
+                        value: value.clone(),
+                    })
+                    .collect(),
+                inputs,
+            });
+    }
+}
+
 #[async_trait]
 impl ActionExecutionCtx for BuckActionExecutionContext<'_> {
     fn target(&self) -> ActionExecutionTarget<'_> {
@@ -374,6 +448,10 @@ impl ActionExecutionCtx for BuckActionExecutionContext<'_> {
         self.executor.digest_config
     }
 
+    fn merged_directory_cache(&self) -> &MergedDirectoryCache {
+        &self.executor.merged_directory_cache
+    }
+
     fn run_action_knobs(&self) -> RunActionKnobs {
         self.executor.run_action_knobs
     }
@@ -390,6 +468,9 @@ impl ActionExecutionCtx for BuckActionExecutionContext<'_> {
         &mut self,
         request: &CommandExecutionRequest,
     ) -> anyhow::Result<PreparedAction> {
+        if self.executor.run_action_knobs.log_action_details {
+            self.log_action_details(request);
+        }
         self.executor
             .command_executor
             .prepare_action(request, self.digest_config())
@@ -790,6 +871,7 @@ mod tests {
                 .unwrap()
                 .build(),
             Default::default(),
+            Arc::new(MergedDirectoryCache::testing_new()),
         );
 
         #[derive(Debug, Allocative)]
@@ -861,6 +943,7 @@ mod tests {
                             .collect(),
                         ctx.fs(),
                         ctx.digest_config(),
+                        ctx.merged_directory_cache(),
                     )?,
                     SortedVectorMap::new(),
                 );
@@ -928,6 +1011,7 @@ mod tests {
                 ran: Default::default(),
             }),
             CommandExecutorConfig::testing_local(),
+            None,
         );
         let res = with_dispatcher_async(
             EventDispatcher::null(),
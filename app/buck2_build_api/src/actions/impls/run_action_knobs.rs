@@ -24,6 +24,10 @@ pub struct RunActionKnobs {
     /// for network actions (download_file, cas_artifact). Used to support offline
     /// builds.
     pub use_network_action_output_cache: bool,
+
+    /// Log the full command of every action, including its inputs, for
+    /// `buck2 debug action-diff`.
+    pub log_action_details: bool,
 }
 
 pub trait HasRunActionKnobs {
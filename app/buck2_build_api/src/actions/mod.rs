@@ -48,6 +48,7 @@ use buck2_execute::execute::blocking::BlockingExecutor;
 use buck2_execute::execute::cache_uploader::CacheUploadResult;
 use buck2_execute::execute::cache_uploader::DepFileEntry;
 use buck2_execute::execute::manager::CommandExecutionManager;
+use buck2_execute::execute::merged_directory_cache::MergedDirectoryCache;
 use buck2_execute::execute::prepared::PreparedAction;
 use buck2_execute::execute::request::CommandExecutionRequest;
 use buck2_execute::execute::result::CommandExecutionResult;
@@ -62,6 +63,7 @@ use dupe::Dupe;
 use indexmap::indexmap;
 use indexmap::IndexMap;
 use indexmap::IndexSet;
+use starlark::codemap::FileSpan;
 use starlark::values::OwnedFrozenValue;
 use static_assertions::_core::ops::Deref;
 
@@ -260,6 +262,9 @@ pub trait ActionExecutionCtx: Send + Sync {
 
     fn digest_config(&self) -> DigestConfig;
 
+    /// Merged directories of transitive set projections, shared by all actions in the daemon.
+    fn merged_directory_cache(&self) -> &MergedDirectoryCache;
+
     /// Obtain per-command knobs for RunAction.
     fn run_action_knobs(&self) -> RunActionKnobs;
 
@@ -285,6 +290,10 @@ pub enum ActionErrors {
         "Multiple artifacts and/or metadata files are declared at conflicting output locations. Output path `{0}` conflicts with the following output paths: {1:?}."
     )]
     ConflictingOutputPaths(ForwardRelativePathBuf, Vec<String>),
+    #[error(
+        "Output path `{0}` differs only in case from output path `{1}`, so they would overwrite each other on a case-insensitive filesystem."
+    )]
+    CaseInsensitiveConflictingOutputPath(ForwardRelativePathBuf, ForwardRelativePathBuf),
     #[error(
         "Action category `{0}` contains duplicate identifier `{1}`; category-identifier pairs must be unique within a rule"
     )]
@@ -306,6 +315,9 @@ pub struct RegisteredAction {
     action: Box<dyn Action>,
     #[derivative(Hash = "ignore", PartialEq = "ignore")]
     executor_config: Arc<CommandExecutorConfig>,
+    /// Where the first output of this action was declared, if known.
+    #[derivative(Hash = "ignore", PartialEq = "ignore")]
+    declaration_location: Option<FileSpan>,
 }
 
 impl TrivialDeferred for Arc<RegisteredAction> {
@@ -330,11 +342,13 @@ impl RegisteredAction {
         key: ActionKey,
         action: Box<dyn Action>,
         executor_config: Arc<CommandExecutorConfig>,
+        declaration_location: Option<FileSpan>,
     ) -> Self {
         Self {
             key,
             action,
             executor_config,
+            declaration_location,
         }
     }
 
@@ -367,6 +381,10 @@ impl RegisteredAction {
     pub fn identifier(&self) -> Option<&str> {
         self.action.identifier()
     }
+
+    pub fn declaration_location(&self) -> Option<&FileSpan> {
+        self.declaration_location.as_ref()
+    }
 }
 
 impl Deref for RegisteredAction {
@@ -384,6 +402,7 @@ struct ActionToBeRegistered {
     inputs: IndexSet<ArtifactGroup>,
     outputs: IndexSet<BuildArtifact>,
     action: Box<dyn UnregisteredAction>,
+    declaration_location: Option<FileSpan>,
 }
 
 impl ActionToBeRegistered {
@@ -391,11 +410,13 @@ impl ActionToBeRegistered {
         inputs: IndexSet<ArtifactGroup>,
         outputs: IndexSet<BuildArtifact>,
         a: A,
+        declaration_location: Option<FileSpan>,
     ) -> Self {
         Self {
             inputs,
             outputs,
             action: Box::new(a),
+            declaration_location,
         }
     }
 
@@ -24,6 +24,7 @@ use buck2_core::cells::CellResolver;
 use buck2_core::fs::artifact_path_resolver::ArtifactFs;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_core::provider::label::ConfiguredProvidersLabel;
 use buck2_core::target::label::TargetLabel;
 use buck2_execute::artifact::fs::ExecutorFs;
@@ -43,6 +44,7 @@ use serde::Serializer;
 
 use crate::actions::RegisteredAction;
 use crate::analysis::AnalysisResult;
+use crate::artifact_groups::ArtifactGroup;
 use crate::artifact_groups::TransitiveSetProjectionKey;
 use crate::interpreter::rule_defs::provider::collection::FrozenProviderCollectionValue;
 
@@ -219,6 +221,33 @@ pub struct ActionData {
 }
 
 impl ActionData {
+    pub fn action(&self) -> &Arc<RegisteredAction> {
+        &self.action
+    }
+
+    /// The paths of the outputs of this action.
+    pub fn output_paths(&self) -> anyhow::Result<Vec<ProjectRelativePathBuf>> {
+        Ok(self
+            .action
+            .action()
+            .outputs()?
+            .iter()
+            .map(|output| self.fs.resolve_build(output.get_path()))
+            .collect())
+    }
+
+    /// The paths of the artifacts this action takes directly as inputs. This does not include the
+    /// inputs coming from transitive sets.
+    pub fn input_paths(&self) -> anyhow::Result<Vec<ProjectRelativePathBuf>> {
+        let mut paths = Vec::new();
+        for input in self.action.action().inputs()?.iter() {
+            if let ArtifactGroup::Artifact(artifact) = input {
+                paths.push(artifact.get_path().resolve(&self.fs)?);
+            }
+        }
+        Ok(paths)
+    }
+
     fn attrs(&self) -> IndexMap<String, String> {
         let mut attrs = self.action.action().aquery_attributes(&ExecutorFs::new(
             &self.fs,
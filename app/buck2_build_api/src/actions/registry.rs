@@ -27,6 +27,7 @@ use buck2_core::directory::DirectoryIterator;
 use buck2_core::directory::NoDigest;
 use buck2_core::execution_types::execution::ExecutionPlatformResolution;
 use buck2_core::fs::buck_out_path::BuckOutPath;
+use buck2_core::fs::case_insensitive::CaseInsensitivePaths;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
 use buck2_execute::execute::request::OutputType;
@@ -56,6 +57,8 @@ pub struct ActionsRegistry {
     )>,
     execution_platform: ExecutionPlatformResolution,
     claimed_output_paths: DirectoryBuilder<Option<FileSpan>, NoDigest>,
+    /// Only set when outputs are written to a case-insensitive filesystem.
+    case_insensitive_output_paths: Option<CaseInsensitivePaths>,
 }
 
 impl ActionsRegistry {
@@ -67,9 +70,16 @@ impl ActionsRegistry {
             pending: Default::default(),
             execution_platform,
             claimed_output_paths: DirectoryBuilder::empty(),
+            case_insensitive_output_paths: None,
         }
     }
 
+    /// Reject output paths that differ only in case, which collide when outputs are written to a
+    /// case-insensitive filesystem.
+    pub fn set_case_insensitive_outputs(&mut self) {
+        self.case_insensitive_output_paths = Some(CaseInsensitivePaths::new());
+    }
+
     pub fn set_action_key(&mut self, action_key: Arc<str>) {
         self.action_key = Some(action_key);
     }
@@ -98,7 +108,20 @@ impl ActionsRegistry {
             .claimed_output_paths
             .insert(path, DirectoryEntry::Leaf(declaration_location))
         {
-            Ok(None) => Ok(()),
+            Ok(None) => {
+                if let Some(case_insensitive_output_paths) = &mut self.case_insensitive_output_paths
+                {
+                    if let Some(conflict) = case_insensitive_output_paths.insert(path) {
+                        return Err(anyhow::anyhow!(
+                            ActionErrors::CaseInsensitiveConflictingOutputPath(
+                                path.to_owned(),
+                                conflict,
+                            )
+                        ));
+                    }
+                }
+                Ok(())
+            }
             Ok(Some(conflict)) => match conflict {
                 DirectoryEntry::Leaf(location) => {
                     Err(anyhow::anyhow!(ActionErrors::ConflictingOutputPath(
@@ -188,10 +211,16 @@ impl ActionsRegistry {
                 .dupe();
             bound_outputs.insert(bound);
         }
+        let declaration_location = bound_outputs.first().and_then(|output| {
+            match directory::find(&self.claimed_output_paths, output.get_path().path()) {
+                Ok(Some(DirectoryEntry::Leaf(location))) => location.clone(),
+                _ => None,
+            }
+        });
         let id = reserved.data().deferred_key().id();
         self.pending.push((
             reserved,
-            ActionToBeRegistered::new(inputs, bound_outputs, action),
+            ActionToBeRegistered::new(inputs, bound_outputs, action, declaration_location),
         ));
 
         Ok(id)
@@ -216,6 +245,7 @@ impl ActionsRegistry {
             let starlark_data = analysis_value_fetcher.get(deferred_id)?;
             let error_handler = analysis_value_fetcher.get_error_handler(deferred_id)?;
             let action_key = ActionKey::new(key.data().dupe());
+            let declaration_location = a.declaration_location.clone();
             let action = a.register(starlark_data, error_handler)?;
             match (action.category(), action.identifier()) {
                 (category, Some(identifier)) => {
@@ -250,6 +280,7 @@ impl ActionsRegistry {
                     action_key,
                     action,
                     (*self.execution_platform.executor_config()?).dupe(),
+                    declaration_location,
                 )),
             );
         }
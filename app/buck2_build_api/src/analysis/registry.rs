@@ -104,6 +104,10 @@ impl<'v> AnalysisRegistry<'v> {
         })
     }
 
+    pub fn set_case_insensitive_outputs(&mut self) {
+        self.actions.set_case_insensitive_outputs();
+    }
+
     pub(crate) fn set_action_key(&mut self, action_key: Arc<str>) {
         self.actions.set_action_key(action_key);
     }
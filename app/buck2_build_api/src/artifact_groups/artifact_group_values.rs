@@ -24,10 +24,13 @@ use buck2_execute::directory::insert_artifact;
 use buck2_execute::directory::ActionDirectoryBuilder;
 use buck2_execute::directory::ActionSharedDirectory;
 use buck2_execute::directory::INTERNER;
+use buck2_execute::execute::merged_directory_cache::SharedDirectoryKey;
 use dupe::Dupe;
 use smallvec::smallvec;
 use smallvec::SmallVec;
 
+use crate::artifact_groups::TransitiveSetProjectionKey;
+
 /// The [`ArtifactValue`]s for an [`crate::artifact_groups::ArtifactGroup`].
 #[derive(Clone, Dupe, Allocative)]
 pub struct ArtifactGroupValues(pub(super) Arc<ArtifactGroupValuesData>);
@@ -36,6 +39,7 @@ impl ArtifactGroupValues {
     /// Create a new instance of ArtifactGroupValues for a TransitiveSetProjection. This expects
     /// that all the children *will* have a Directory.
     pub fn new(
+        key: &TransitiveSetProjectionKey,
         values: SmallVec<[(Artifact, ArtifactValue); 1]>,
         children: Vec<Self>,
         artifact_fs: &ArtifactFs,
@@ -54,7 +58,7 @@ impl ArtifactGroupValues {
             // NOTE: Technically, we could fall back to iterating the artifacts in the
             // ArtifactGroupValues here, but we *do* rely on the fact that TransitiveSetProjections
             // produce intermediate directories, so if they don't, it is preferable to report it.
-            let child_dir = child
+            let (_, child_dir) = child
                 .0
                 .directory
                 .as_ref()
@@ -72,7 +76,7 @@ impl ArtifactGroupValues {
         Ok(Self(Arc::new(ArtifactGroupValuesData {
             values,
             children,
-            directory: Some(directory),
+            directory: Some((SharedDirectoryKey::new(key.dupe()), directory)),
         })))
     }
 
@@ -89,7 +93,7 @@ impl ArtifactGroupValues {
         builder: &mut ActionDirectoryBuilder,
         artifact_fs: &ArtifactFs,
     ) -> anyhow::Result<()> {
-        if let Some(d) = self.0.directory.as_ref() {
+        if let Some((_, d)) = self.0.directory.as_ref() {
             builder.merge(d.to_builder())?;
             return Ok(());
         }
@@ -123,8 +127,8 @@ pub struct ArtifactGroupValuesData {
     pub(super) values: SmallVec<[(Artifact, ArtifactValue); 1]>,
     pub(super) children: Vec<ArtifactGroupValues>,
     /// If set, a precomputed directory represented the union of all values in this
-    /// ArtifactGroupValuesData.
-    pub(super) directory: Option<ActionSharedDirectory>,
+    /// ArtifactGroupValuesData, along with the key of the projection it was computed for.
+    pub(super) directory: Option<(SharedDirectoryKey, ActionSharedDirectory)>,
 }
 
 /// An opaque identifier for the identity of a ArtifactGroupValue. There is no operation on this
@@ -236,6 +240,10 @@ impl ArtifactGroupValuesDyn for ArtifactGroupValues {
     ) -> anyhow::Result<()> {
         self.add_to_directory(builder, artifact_fs)
     }
+
+    fn directory(&self) -> Option<(&SharedDirectoryKey, &ActionSharedDirectory)> {
+        self.0.directory.as_ref().map(|(key, dir)| (key, dir))
+    }
 }
 
 #[cfg(test)]
@@ -440,8 +440,9 @@ impl Key for EnsureTransitiveSetProjectionKey {
         (move || {
             let digest_config = ctx.global_data().get_digest_config();
 
-            let values = ArtifactGroupValues::new(values, children, &artifact_fs, digest_config)
-                .context("Failed to construct ArtifactGroupValues")?;
+            let values =
+                ArtifactGroupValues::new(&self.0, values, children, &artifact_fs, digest_config)
+                    .context("Failed to construct ArtifactGroupValues")?;
 
             Ok(values)
         })()
@@ -16,11 +16,15 @@ use crate::interpreter::rule_defs::context::get_artifact_from_anon_target_analys
 
 pub mod registry;
 
+use std::collections::hash_map::DefaultHasher;
 use std::hash::Hash;
+use std::hash::Hasher;
 
 use allocative::Allocative;
 pub use artifact_group_values::ArtifactGroupValues;
 use buck2_artifact::artifact::artifact_type::Artifact;
+use buck2_execute::execute::merged_directory_cache::SharedDirectoryKeyDyn;
+use cmp_any::PartialEqAny;
 use derive_more::Display;
 use dice::DiceComputations;
 use dupe::Dupe;
@@ -105,3 +109,15 @@ pub struct TransitiveSetProjectionKey {
     pub key: TransitiveSetKey,
     pub projection: usize,
 }
+
+impl SharedDirectoryKeyDyn for TransitiveSetProjectionKey {
+    fn eq_token(&self) -> PartialEqAny {
+        PartialEqAny::new(self)
+    }
+
+    fn hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        Hash::hash(self, &mut hasher);
+        hasher.finish()
+    }
+}
@@ -12,6 +12,7 @@ use std::collections::HashMap;
 use std::fmt::Debug;
 use std::fmt::Formatter;
 use std::sync::Arc;
+use std::time::Duration;
 
 use allocative::Allocative;
 use anyhow::Context;
@@ -38,6 +39,7 @@ use futures::stream::StreamExt;
 use futures::FutureExt;
 use itertools::Itertools;
 use tokio::sync::Mutex;
+use tokio::sync::MutexGuard;
 
 use crate::actions::artifact::get_artifact_fs::GetArtifactFs;
 use crate::actions::artifact::materializer::ArtifactMaterializer;
@@ -70,6 +72,14 @@ pub struct ConfiguredBuildTargetResultGen<T> {
     pub target_rule_type_name: Option<String>,
     pub configured_graph_size: Option<buck2_error::Result<MaybeCompatible<u64>>>,
     pub errors: Vec<buck2_error::Error>,
+    /// Whether the build stopped (because it timed out) before all outputs were built.
+    pub unfinished: bool,
+}
+
+#[derive(Debug, buck2_error::Error)]
+enum BuildTimeoutError {
+    #[error("Build timed out after {0:?}, unfinished targets are reported as cancelled")]
+    TimedOut(Duration),
 }
 
 pub type ConfiguredBuildTargetResult =
@@ -80,12 +90,24 @@ pub struct BuildTargetResult {
     /// Errors that could not be associated with a specific configured target. These errors may be
     /// associated with a providers label, or might not be associated with any target at all.
     pub other_errors: BTreeMap<Option<ProvidersLabel>, Vec<buck2_error::Error>>,
+    /// Whether the build stopped because it timed out.
+    pub timed_out: bool,
 }
 
 impl BuildTargetResult {
     pub async fn collect_stream(
+        stream: impl Stream<Item = BuildEvent> + Unpin,
+        fail_fast: bool,
+    ) -> anyhow::Result<Self> {
+        Self::collect_stream_with_timeout(stream, fail_fast, None).await
+    }
+
+    /// Like `collect_stream`, but stops building once `timeout` has passed. The targets that were
+    /// still being built are then marked as unfinished, and a timeout error is reported.
+    pub async fn collect_stream_with_timeout(
         mut stream: impl Stream<Item = BuildEvent> + Unpin,
         fail_fast: bool,
+        timeout: Option<Duration>,
     ) -> anyhow::Result<Self> {
         // Create a map of labels to outputs, but retain the expected index of each output.
         let mut res = HashMap::<
@@ -93,8 +115,25 @@ impl BuildTargetResult {
             Option<ConfiguredBuildTargetResultGen<(usize, buck2_error::Result<ProviderArtifacts>)>>,
         >::new();
         let mut other_errors = BTreeMap::<_, Vec<_>>::new();
-
-        while let Some(event) = stream.next().await {
+        // The number of outputs of each prepared target, to tell which ones are unfinished.
+        let mut num_outputs = HashMap::<ConfiguredProvidersLabel, usize>::new();
+        let deadline = timeout.map(|timeout| tokio::time::Instant::now() + timeout);
+        let mut timed_out = false;
+
+        loop {
+            let event = match deadline {
+                None => stream.next().await,
+                Some(deadline) => match tokio::time::timeout_at(deadline, stream.next()).await {
+                    Ok(event) => event,
+                    Err(_) => {
+                        timed_out = true;
+                        break;
+                    }
+                },
+            };
+            let Some(event) = event else {
+                break;
+            };
             let ConfiguredBuildEvent { variant, label } = match event {
                 BuildEvent::Configured(variant) => variant,
                 BuildEvent::OtherError { label: target, err } => {
@@ -109,7 +148,9 @@ impl BuildTargetResult {
                 ConfiguredBuildEventVariant::Prepared {
                     run_args,
                     target_rule_type_name,
+                    num_outputs: n,
                 } => {
+                    num_outputs.insert((*label).clone(), n);
                     res.entry((*label).clone())
                         .or_insert(Some(ConfiguredBuildTargetResultGen {
                             outputs: Vec::new(),
@@ -117,6 +158,7 @@ impl BuildTargetResult {
                             target_rule_type_name: Some(target_rule_type_name),
                             configured_graph_size: None,
                             errors: Vec::new(),
+                            unfinished: false,
                         }));
                 }
                 ConfiguredBuildEventVariant::Output { index, output } => {
@@ -150,6 +192,7 @@ impl BuildTargetResult {
                             target_rule_type_name: None,
                             configured_graph_size: None,
                             errors: Vec::new(),
+                            unfinished: false,
                         }))
                         .as_mut()
                         .unwrap()
@@ -162,6 +205,25 @@ impl BuildTargetResult {
             }
         }
 
+        if let Some(timeout) = timeout.filter(|_| timed_out) {
+            for (label, result) in res.iter_mut() {
+                let Some(result) = result else { continue };
+                let built = result
+                    .outputs
+                    .iter()
+                    .map(|(index, _)| *index)
+                    .unique()
+                    .count();
+                if built < num_outputs.get(label).copied().unwrap_or_default() {
+                    result.unfinished = true;
+                }
+            }
+            other_errors
+                .entry(None)
+                .or_default()
+                .push(anyhow::Error::from(BuildTimeoutError::TimedOut(timeout)).into());
+        }
+
         // Sort our outputs within each individual BuildTargetResult, then return those.
         // Also, turn our HashMap into a BTreeMap.
         let res = res
@@ -174,6 +236,7 @@ impl BuildTargetResult {
                         target_rule_type_name,
                         configured_graph_size,
                         errors,
+                        unfinished,
                     } = result;
 
                     // No need for a stable sort: the indices are unique (see below).
@@ -193,6 +256,7 @@ impl BuildTargetResult {
                         target_rule_type_name,
                         configured_graph_size,
                         errors,
+                        unfinished,
                     }
                 });
 
@@ -203,6 +267,7 @@ impl BuildTargetResult {
         Ok(Self {
             configured: res,
             other_errors,
+            timed_out,
         })
     }
 }
@@ -212,6 +277,8 @@ enum ConfiguredBuildEventVariant {
     Prepared {
         run_args: Option<Vec<String>>,
         target_rule_type_name: String,
+        /// The number of `Output` events that will follow for this target.
+        num_outputs: usize,
     },
     Output {
         output: buck2_error::Result<ProviderArtifacts>,
@@ -396,6 +463,7 @@ async fn build_configured_label_inner<'a>(
         ));
     }
 
+    let num_outputs = outputs.len();
     let outputs = outputs
         .into_iter()
         .enumerate()
@@ -430,6 +498,7 @@ async fn build_configured_label_inner<'a>(
         variant: ConfiguredBuildEventVariant::Prepared {
             run_args,
             target_rule_type_name,
+            num_outputs,
         },
     }))
     .chain(outputs);
@@ -598,3 +667,114 @@ impl HasCreateUnhashedSymlinkLock for UserComputationData {
             .dupe()
     }
 }
+
+/// Held while the graph sizes recorded by graph budgets in buck-out are updated, so that
+/// concurrent builds don't overwrite each other's sizes.
+#[derive(Clone, Dupe, Default)]
+pub struct GraphBudgetsLock(Arc<Mutex<()>>);
+
+impl GraphBudgetsLock {
+    pub async fn lock(&self) -> MutexGuard<'_, ()> {
+        self.0.lock().await
+    }
+}
+
+pub trait HasGraphBudgetsLock {
+    fn set_graph_budgets_lock(&mut self, lock: GraphBudgetsLock);
+
+    fn get_graph_budgets_lock(&self) -> anyhow::Result<GraphBudgetsLock>;
+}
+
+impl HasGraphBudgetsLock for UserComputationData {
+    fn set_graph_budgets_lock(&mut self, lock: GraphBudgetsLock) {
+        self.data.set(lock);
+    }
+
+    fn get_graph_budgets_lock(&self) -> anyhow::Result<GraphBudgetsLock> {
+        Ok(self.data.get::<GraphBudgetsLock>()?.dupe())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::provider::label::ProvidersName;
+    use buck2_core::target::label::TargetLabel;
+
+    use super::*;
+
+    fn prepared(target: &str, num_outputs: usize) -> BuildEvent {
+        BuildEvent::Configured(ConfiguredBuildEvent {
+            label: Arc::new(ConfiguredProvidersLabel::new(
+                TargetLabel::testing_parse(target).configure(ConfigurationData::testing_new()),
+                ProvidersName::Default,
+            )),
+            variant: ConfiguredBuildEventVariant::Prepared {
+                run_args: None,
+                target_rule_type_name: "genrule".to_owned(),
+                num_outputs,
+            },
+        })
+    }
+
+    #[tokio::test]
+    async fn test_collect_stream_with_timeout() -> anyhow::Result<()> {
+        // `root//:a` never finishes building its output, `root//:b` has nothing to build.
+        let stream = futures::stream::iter([prepared("root//:a", 1), prepared("root//:b", 0)])
+            .chain(futures::stream::pending());
+
+        let result = BuildTargetResult::collect_stream_with_timeout(
+            stream,
+            false,
+            Some(Duration::from_millis(10)),
+        )
+        .await?;
+
+        let unfinished = result
+            .configured
+            .iter()
+            .map(|(label, result)| {
+                (
+                    label.target().unconfigured().to_string(),
+                    result.as_ref().unwrap().unfinished,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            unfinished,
+            vec![
+                ("root//:a".to_owned(), true),
+                ("root//:b".to_owned(), false)
+            ]
+        );
+        assert!(result.timed_out);
+        assert_eq!(result.other_errors.get(&None).map(|e| e.len()), Some(1));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_collect_stream_without_timeout() -> anyhow::Result<()> {
+        let stream = futures::stream::iter([prepared("root//:b", 0)]);
+
+        let result = BuildTargetResult::collect_stream_with_timeout(
+            stream,
+            false,
+            Some(Duration::from_secs(60)),
+        )
+        .await?;
+
+        assert!(
+            !result
+                .configured
+                .values()
+                .next()
+                .unwrap()
+                .as_ref()
+                .unwrap()
+                .unfinished
+        );
+        assert!(!result.timed_out);
+        assert!(result.other_errors.is_empty());
+        Ok(())
+    }
+}
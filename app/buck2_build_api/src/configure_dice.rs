@@ -10,10 +10,14 @@
 use std::sync::Arc;
 
 use buck2_common::dice::cells::SetCellResolver;
+use buck2_common::dice::data::SetCaseSensitivity;
 use buck2_common::dice::data::SetIoProvider;
+use buck2_common::dice::file_ops::FollowedExternalSymlinks;
+use buck2_common::dice::file_ops::SetFollowedExternalSymlinks;
 use buck2_common::io::IoProvider;
 use buck2_common::legacy_configs::dice::SetLegacyConfigs;
 use buck2_common::legacy_configs::LegacyBuckConfig;
+use buck2_core::fs::case_insensitive::FilesystemCaseSensitivity;
 use buck2_execute::digest_config::DigestConfig;
 use buck2_execute::digest_config::SetDigestConfig;
 use dice::DetectCycles;
@@ -25,6 +29,7 @@ use dice::WhichDice;
 pub async fn configure_dice_for_buck(
     io: Arc<dyn IoProvider>,
     digest_config: DigestConfig,
+    case_sensitivity: FilesystemCaseSensitivity,
     root_config: Option<&LegacyBuckConfig>,
     detect_cycles: Option<DetectCycles>,
     which_dice: Option<WhichDice>,
@@ -56,6 +61,8 @@ pub async fn configure_dice_for_buck(
     };
     dice.set_io_provider(io);
     dice.set_digest_config(digest_config);
+    dice.set_case_sensitivity(case_sensitivity);
+    dice.set_followed_external_symlinks(Arc::new(FollowedExternalSymlinks::new()));
 
     let dice = dice.build(detect_cycles);
     let mut dice_ctx = dice.updater();
@@ -9,6 +9,7 @@
 
 use std::iter::empty;
 use std::iter::once;
+use std::time::Duration;
 
 use allocative::Allocative;
 use anyhow::Context as _;
@@ -98,6 +99,27 @@ pub struct ExternalRunnerTestInfoGen<V> {
     /// should be ignored when executing tests even if those are passed as required from test runner.
     #[provider(field_type = DictType<String, Option<StarlarkConfiguredProvidersLabel>>)]
     local_resources: V,
+
+    /// Names of the environment variables of the buck2 daemon this test inherits when it runs
+    /// locally. Other variables are not passed to the test, only those set in `env` are.
+    /// If omitted, a default allowlist is used.
+    #[provider(field_type = Option<Vec<String>>)]
+    env_allowlist: V,
+
+    /// Maximum time in seconds a run of this test may take. Runs exceeding it time out, even if
+    /// the test executor allows longer.
+    #[provider(field_type = NoneOr<f64>)]
+    timeout_seconds: V,
+
+    /// Maximum memory in MiB a run of this test may use. It is passed to the test executor to
+    /// enforce when running locally, and as platform properties when running on RE.
+    #[provider(field_type = NoneOr<i32>)]
+    max_memory_mebibytes: V,
+
+    /// Types of `local_resources` which are set up for every run of this test, in addition to
+    /// those the test executor requires.
+    #[provider(field_type = Option<Vec<String>>)]
+    required_local_resources: V,
 }
 
 // NOTE: All the methods here unwrap because we validate at freeze time.
@@ -152,6 +174,39 @@ impl FrozenExternalRunnerTestInfo {
         unwrap_all(iter_local_resources(self.local_resources.to_value())).collect()
     }
 
+    /// `None` if the test did not declare an allowlist and uses the default one.
+    pub fn env_allowlist(&self) -> Option<impl Iterator<Item = &str>> {
+        let env_allowlist = self.env_allowlist.to_value();
+        if env_allowlist.is_none() {
+            return None;
+        }
+        Some(unwrap_all(iter_opt_str_list(
+            env_allowlist,
+            "env_allowlist",
+        )))
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        NoneOr::<f64>::unpack_value(self.timeout_seconds.to_value())
+            .unwrap()
+            .into_option()
+            .and_then(|timeout| Duration::try_from_secs_f64(timeout).ok())
+    }
+
+    pub fn max_memory_bytes(&self) -> Option<u64> {
+        NoneOr::<i32>::unpack_value(self.max_memory_mebibytes.to_value())
+            .unwrap()
+            .into_option()
+            .map(|mebibytes| mebibytes as u64 * 1024 * 1024)
+    }
+
+    pub fn required_local_resources(&self) -> impl Iterator<Item = &str> {
+        unwrap_all(iter_opt_str_list(
+            self.required_local_resources.to_value(),
+            "required_local_resources",
+        ))
+    }
+
     pub fn visit_artifacts(
         &self,
         visitor: &mut dyn CommandLineArtifactVisitor,
@@ -426,10 +481,54 @@ where
     check_all(iter_opt_str_list(info.contacts.to_value(), "contacts"))?;
     check_all(iter_executor_overrides(info.executor_overrides.to_value()))?;
     check_all(iter_local_resources(info.local_resources.to_value()))?;
+    check_all(iter_opt_str_list(
+        info.env_allowlist.to_value(),
+        "env_allowlist",
+    ))?;
     NoneOr::<bool>::unpack_value(info.use_project_relative_paths.to_value())
         .context("`use_project_relative_paths` must be a bool if provided")?;
     NoneOr::<bool>::unpack_value(info.run_from_project_root.to_value())
         .context("`run_from_project_root` must be a bool if provided")?;
+    if let Some(timeout) = NoneOr::<f64>::unpack_value(info.timeout_seconds.to_value())
+        .context("`timeout_seconds` must be a number if provided")?
+        .into_option()
+    {
+        match Duration::try_from_secs_f64(timeout) {
+            Ok(duration) if !duration.is_zero() => {}
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "`timeout_seconds` must be a positive duration, got `{}`",
+                    timeout
+                ));
+            }
+        }
+    }
+    if let Some(memory) = NoneOr::<i32>::unpack_value(info.max_memory_mebibytes.to_value())
+        .context("`max_memory_mebibytes` must be an int if provided")?
+        .into_option()
+    {
+        if memory <= 0 {
+            return Err(anyhow::anyhow!(
+                "`max_memory_mebibytes` must be positive, got `{}`",
+                memory
+            ));
+        }
+    }
+    let local_resources = iter_local_resources(info.local_resources.to_value())
+        .map(|r| r.map(|(key, _)| key))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    for required in iter_opt_str_list(
+        info.required_local_resources.to_value(),
+        "required_local_resources",
+    ) {
+        let required = required?;
+        if !local_resources.contains(&required) {
+            return Err(anyhow::anyhow!(
+                "`required_local_resources` contains `{}` which is not a key of `local_resources`",
+                required
+            ));
+        }
+    }
     unpack_opt_executor(info.default_executor.to_value()).context("Invalid `default_executor`")?;
     info.test_type
         .to_value()
@@ -452,6 +551,10 @@ fn external_runner_test_info_creator(globals: &mut GlobalsBuilder) {
         #[starlark(default = NoneType)] default_executor: Value<'v>,
         #[starlark(default = NoneType)] executor_overrides: Value<'v>,
         #[starlark(default = NoneType)] local_resources: Value<'v>,
+        #[starlark(default = NoneType)] env_allowlist: Value<'v>,
+        #[starlark(default = NoneType)] timeout_seconds: Value<'v>,
+        #[starlark(default = NoneType)] max_memory_mebibytes: Value<'v>,
+        #[starlark(default = NoneType)] required_local_resources: Value<'v>,
     ) -> anyhow::Result<ExternalRunnerTestInfo<'v>> {
         let res = ExternalRunnerTestInfo {
             test_type: r#type,
@@ -464,6 +567,10 @@ fn external_runner_test_info_creator(globals: &mut GlobalsBuilder) {
             default_executor,
             executor_overrides,
             local_resources,
+            env_allowlist,
+            timeout_seconds,
+            max_memory_mebibytes,
+            required_local_resources,
         };
         validate_external_runner_test_info(&res)?;
         Ok(res)
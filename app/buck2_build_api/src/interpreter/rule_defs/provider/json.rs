@@ -0,0 +1,159 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+use serde::ser::SerializeMap;
+use serde::Serialize;
+use serde::Serializer;
+use starlark::values::dict::DictRef;
+use starlark::values::list::ListRef;
+use starlark::values::record::Record;
+use starlark::values::structs::StructRef;
+use starlark::values::tuple::TupleRef;
+use starlark::values::UnpackValue;
+use starlark::values::Value;
+
+use crate::interpreter::rule_defs::artifact::ValueAsArtifactLike;
+use crate::interpreter::rule_defs::provider::collection::FrozenProviderCollection;
+use crate::interpreter::rule_defs::provider::ProviderLike;
+use crate::interpreter::rule_defs::provider::ValueAsProviderLike;
+
+/// Serializes the providers of a target as a map from provider name to fields. The format is
+/// documented in `docs/users/audit_providers_json.md`.
+pub struct ProvidersJson<'a> {
+    pub collection: &'a FrozenProviderCollection,
+    pub artifact_fs: &'a ArtifactFs,
+}
+
+impl Serialize for ProvidersJson<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut ids = self.collection.provider_ids();
+        ids.sort_by(|a, b| a.name.cmp(&b.name));
+        serializer.collect_map(ids.into_iter().filter_map(|id| {
+            let provider = self
+                .collection
+                .get_provider_raw(id)?
+                .to_value()
+                .as_provider()?;
+            Some((
+                &id.name,
+                ProviderFieldsJson {
+                    provider,
+                    artifact_fs: self.artifact_fs,
+                },
+            ))
+        }))
+    }
+}
+
+struct ProviderFieldsJson<'a, 'v> {
+    provider: &'v dyn ProviderLike<'v>,
+    artifact_fs: &'a ArtifactFs,
+}
+
+impl Serialize for ProviderFieldsJson<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_map(self.provider.items().into_iter().map(|(k, v)| {
+            (
+                k,
+                ValueJson {
+                    value: v,
+                    artifact_fs: self.artifact_fs,
+                },
+            )
+        }))
+    }
+}
+
+/// A provider field. Unlike `write_json`, this accepts any value: artifacts are written as
+/// `{"artifact": path}`, nested providers as `{"provider": name, "fields": {...}}`, and values
+/// with no JSON equivalent (functions, transitive sets, ...) as `{"repr": repr}`.
+struct ValueJson<'a, 'v> {
+    value: Value<'v>,
+    artifact_fs: &'a ArtifactFs,
+}
+
+impl<'a, 'v> ValueJson<'a, 'v> {
+    fn with_value(&self, value: Value<'v>) -> Self {
+        Self {
+            value,
+            artifact_fs: self.artifact_fs,
+        }
+    }
+
+    fn serialize_tagged<S, T>(serializer: S, tag: &str, value: &T) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: Serialize + ?Sized,
+    {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(tag, value)?;
+        map.end()
+    }
+}
+
+impl Serialize for ValueJson<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let value = self.value;
+        if value.is_none() {
+            serializer.serialize_none()
+        } else if let Some(x) = value.unpack_bool() {
+            serializer.serialize_bool(x)
+        } else if let Some(x) = value.unpack_str() {
+            serializer.serialize_str(x)
+        } else if let Some(x) = i64::unpack_value(value) {
+            serializer.serialize_i64(x)
+        } else if let Some(x) = ListRef::from_value(value) {
+            serializer.collect_seq(x.iter().map(|v| self.with_value(v)))
+        } else if let Some(x) = TupleRef::from_value(value) {
+            serializer.collect_seq(x.iter().map(|v| self.with_value(v)))
+        } else if let Some(x) = DictRef::from_value(value) {
+            serializer.collect_map(x.iter().map(|(k, v)| {
+                let k = k.unpack_str().map_or_else(|| k.to_repr(), |k| k.to_owned());
+                (k, self.with_value(v))
+            }))
+        } else if let Some(x) = StructRef::from_value(value) {
+            serializer.collect_map(x.iter().map(|(k, v)| (k, self.with_value(v))))
+        } else if let Some(x) = Record::from_value(value) {
+            serializer.collect_map(x.iter().map(|(k, v)| (k, self.with_value(v))))
+        } else if let Some(x) = ValueAsArtifactLike::unpack_value(value) {
+            match x
+                .0
+                .get_bound_artifact()
+                .and_then(|a| a.get_path().resolve(self.artifact_fs))
+            {
+                Ok(path) => Self::serialize_tagged(serializer, "artifact", path.as_str()),
+                // Not bound to an action, e.g. an unresolved promise artifact.
+                Err(_) => Self::serialize_tagged(serializer, "repr", &value.to_repr()),
+            }
+        } else if let Some(x) = value.as_provider() {
+            let mut map = serializer.serialize_map(Some(2))?;
+            map.serialize_entry("provider", &x.id().name)?;
+            map.serialize_entry(
+                "fields",
+                &ProviderFieldsJson {
+                    provider: x,
+                    artifact_fs: self.artifact_fs,
+                },
+            )?;
+            map.end()
+        } else {
+            Self::serialize_tagged(serializer, "repr", &value.to_repr())
+        }
+    }
+}
@@ -75,6 +75,7 @@ pub mod collection;
 pub mod dependency;
 pub(crate) mod doc;
 pub mod execution_platform;
+pub mod json;
 pub mod registration;
 pub mod test_provider;
 pub(crate) mod ty;
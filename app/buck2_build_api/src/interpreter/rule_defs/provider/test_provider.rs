@@ -87,6 +87,12 @@ impl TestProvider for FrozenExternalRunnerTestInfo {
             contacts: self.contacts().map(|l| l.to_owned()).collect(),
             oncall: self.contacts().exactly_one().ok().map(str::to_owned),
             working_dir_cell,
+            timeout: self.timeout(),
+            max_memory_bytes: self.max_memory_bytes(),
+            required_local_resources: self
+                .required_local_resources()
+                .map(|r| r.to_owned())
+                .collect(),
         };
 
         async move { executor.external_runner_spec(spec).await }.boxed()
@@ -14,8 +14,9 @@ use std::sync::Arc;
 
 use allocative::Allocative;
 use anyhow::Context;
-use buck2_core::bzl::ImportPath;
 use buck2_interpreter::build_context::starlark_path_from_build_context;
+use buck2_interpreter::paths::module::OwnedStarlarkModulePath;
+use buck2_interpreter::paths::module::StarlarkModulePath;
 use buck2_interpreter::paths::path::StarlarkPath;
 use derive_more::Display;
 use dupe::Dupe;
@@ -51,8 +52,8 @@ use crate::interpreter::rule_defs::transitive_set::TransitiveSetError;
 
 #[derive(Debug, buck2_error::Error)]
 enum TransitiveSetDefinitionError {
-    #[error("`transitive_set()` can only be used in `bzl` files")]
-    TransitiveSetOnlyInBzl,
+    #[error("`transitive_set()` can only be used in `bzl` and `bxl` files")]
+    TransitiveSetOnlyInBzlOrBxl,
 }
 
 #[derive(Debug, Clone, Dupe, Copy, Trace, Freeze, PartialEq, Allocative)]
@@ -89,7 +90,7 @@ pub struct TransitiveSetProjectionSpec<V> {
 #[derive(Debug, Clone, Display, Allocative, Hash)]
 #[display(fmt = "{}", "name")]
 struct TransitiveSetId {
-    module_id: ImportPath,
+    module_id: OwnedStarlarkModulePath,
     name: String,
 }
 
@@ -109,7 +110,7 @@ pub struct TransitiveSetDefinition<'v> {
     pub(crate) exported: std::cell::OnceCell<TransitiveSetDefinitionExported>,
 
     /// The module id where this `TransitiveSetDefinition` is created and assigned
-    module_id: ImportPath,
+    module_id: OwnedStarlarkModulePath,
 
     operations: TransitiveSetOperationsGen<Value<'v>>,
 }
@@ -193,7 +194,7 @@ impl<'v> Serialize for TransitiveSetDefinition<'v> {
 }
 
 impl<'v> TransitiveSetDefinition<'v> {
-    fn new(module_id: ImportPath, operations: TransitiveSetOperations<'v>) -> Self {
+    fn new(module_id: OwnedStarlarkModulePath, operations: TransitiveSetOperations<'v>) -> Self {
         Self {
             exported: std::cell::OnceCell::new(),
             module_id,
@@ -298,7 +299,8 @@ impl fmt::Debug for FrozenTransitiveSetDefinition {
         write!(
             f,
             "TransitiveSetDefinition({} declared in {})",
-            self.exported.id.name, self.exported.id.module_id
+            self.exported.id.name,
+            self.exported.id.module_id.path()
         )
     }
 }
@@ -488,8 +490,11 @@ pub fn register_transitive_set(builder: &mut GlobalsBuilder) {
         let starlark_path: StarlarkPath = starlark_path_from_build_context(eval)?;
         Ok(TransitiveSetDefinition::new(
             match starlark_path {
-                StarlarkPath::LoadFile(import_path) => import_path.clone(),
-                _ => return Err(TransitiveSetDefinitionError::TransitiveSetOnlyInBzl.into()),
+                StarlarkPath::LoadFile(import_path) => {
+                    StarlarkModulePath::LoadFile(import_path).to_owned()
+                }
+                StarlarkPath::BxlFile(bxl_path) => StarlarkModulePath::BxlFile(bxl_path).to_owned(),
+                _ => return Err(TransitiveSetDefinitionError::TransitiveSetOnlyInBzlOrBxl.into()),
             },
             TransitiveSetOperations {
                 projections,
@@ -73,6 +73,8 @@ use buck2_execute::execute::blocking::testing::DummyBlockingExecutor;
 use buck2_execute::execute::blocking::SetBlockingExecutor;
 use buck2_execute::execute::cache_uploader::NoOpCacheUploader;
 use buck2_execute::execute::kind::CommandExecutionKind;
+use buck2_execute::execute::merged_directory_cache::MergedDirectoryCache;
+use buck2_execute::execute::merged_directory_cache::SetMergedDirectoryCache;
 use buck2_execute::execute::output::CommandStdStreams;
 use buck2_execute::execute::prepared::NoOpCommandOptionalExecutor;
 use buck2_execute::execute::request::CommandExecutionOutput;
@@ -134,6 +136,7 @@ fn registered_action(
         build_artifact.key().dupe(),
         action,
         CommandExecutorConfig::testing_local(),
+        None,
     );
     Arc::new(registered_action)
 }
@@ -202,6 +205,7 @@ async fn make_default_dice_state(
     extra.set_re_client(ManagedRemoteExecutionClient::testing_new_dummy());
     extra.set_http_client(HttpClientBuilder::https_with_system_roots()?.build());
     extra.set_mergebase(Default::default());
+    extra.set_merged_directory_cache(Arc::new(MergedDirectoryCache::testing_new()));
     extra.data.set(EventDispatcher::null());
     extra.data.set(RunActionKnobs::default());
     extra.spawner = Arc::new(BuckSpawner::current_runtime().unwrap());
@@ -134,6 +134,45 @@ fn claiming_conflicting_path() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn claiming_paths_differing_only_in_case() -> anyhow::Result<()> {
+    let target = ConfiguredTargetLabel::testing_parse(
+        "cell//pkg:my_target",
+        ConfigurationData::testing_new(),
+    );
+    let upper = ForwardRelativePathBuf::unchecked_new("foo/Bar.h".into());
+    let lower = ForwardRelativePathBuf::unchecked_new("foo/bar.h".into());
+
+    // Outputs on a case-sensitive filesystem do not collide.
+    let mut actions = ActionsRegistry::new(
+        BaseDeferredKey::TargetLabel(target.dupe()),
+        ExecutionPlatformResolution::unspecified(),
+    );
+    actions.claim_output_path(&upper, None)?;
+    actions.claim_output_path(&lower, None)?;
+
+    let mut actions = ActionsRegistry::new(
+        BaseDeferredKey::TargetLabel(target.dupe()),
+        ExecutionPlatformResolution::unspecified(),
+    );
+    actions.set_case_insensitive_outputs();
+    actions.claim_output_path(&upper, None)?;
+    assert_matches!(
+        actions.claim_output_path(&lower, None),
+        Err(e) => {
+            assert_matches!(
+                e.downcast_ref::<ActionErrors>(),
+                Some(ActionErrors::CaseInsensitiveConflictingOutputPath(inserted, existing)) => {
+                    assert_eq!(inserted, &lower);
+                    assert_eq!(existing, &upper);
+                }
+            );
+        }
+    );
+
+    Ok(())
+}
+
 #[test]
 fn register_actions() -> anyhow::Result<()> {
     let base = BaseDeferredKey::TargetLabel(ConfiguredTargetLabel::testing_parse(
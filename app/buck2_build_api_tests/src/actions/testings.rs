@@ -149,6 +149,7 @@ impl PristineActionExecutable for SimpleAction {
                     .collect(),
                 ctx.fs(),
                 ctx.digest_config(),
+                ctx.merged_directory_cache(),
             )?,
             sorted_vector_map![],
         );
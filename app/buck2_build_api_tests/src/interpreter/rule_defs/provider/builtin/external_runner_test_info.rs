@@ -34,6 +34,11 @@ fn test_construction() -> anyhow::Result<()> {
             ExternalRunnerTestInfo(type = "foo", labels = ("foo",))
             ExternalRunnerTestInfo(type = "foo", use_project_relative_paths = True)
             ExternalRunnerTestInfo(type = "foo", run_from_project_root = True)
+            ExternalRunnerTestInfo(type = "foo", env_allowlist = ["HOME"])
+            ExternalRunnerTestInfo(type = "foo", env_allowlist = [])
+            ExternalRunnerTestInfo(type = "foo", timeout_seconds = 30)
+            ExternalRunnerTestInfo(type = "foo", timeout_seconds = 0.5, max_memory_mebibytes = 512)
+            ExternalRunnerTestInfo(type = "foo", local_resources = {"db": None}, required_local_resources = ["db"])
         "#
     );
     let mut tester = tester();
@@ -74,6 +79,66 @@ fn test_validation() -> anyhow::Result<()> {
         "`command`",
     );
 
+    tester.run_starlark_bzl_test_expecting_error(
+        indoc!(
+            r#"
+        def test():
+            ExternalRunnerTestInfo(type = "foo", env_allowlist = [1])
+        "#
+        ),
+        "`env_allowlist`",
+    );
+
+    tester.run_starlark_bzl_test_expecting_error(
+        indoc!(
+            r#"
+        def test():
+            ExternalRunnerTestInfo(type = "foo", timeout_seconds = 0)
+        "#
+        ),
+        "`timeout_seconds`",
+    );
+
+    tester.run_starlark_bzl_test_expecting_error(
+        indoc!(
+            r#"
+        def test():
+            ExternalRunnerTestInfo(type = "foo", timeout_seconds = 1e30)
+        "#
+        ),
+        "`timeout_seconds`",
+    );
+
+    tester.run_starlark_bzl_test_expecting_error(
+        indoc!(
+            r#"
+        def test():
+            ExternalRunnerTestInfo(type = "foo", timeout_seconds = -1.5)
+        "#
+        ),
+        "`timeout_seconds`",
+    );
+
+    tester.run_starlark_bzl_test_expecting_error(
+        indoc!(
+            r#"
+        def test():
+            ExternalRunnerTestInfo(type = "foo", max_memory_mebibytes = "1G")
+        "#
+        ),
+        "`max_memory_mebibytes`",
+    );
+
+    tester.run_starlark_bzl_test_expecting_error(
+        indoc!(
+            r#"
+        def test():
+            ExternalRunnerTestInfo(type = "foo", required_local_resources = ["ios_simulator"])
+        "#
+        ),
+        "`required_local_resources`",
+    );
+
     tester.run_starlark_bzl_test_expecting_error(
         indoc!(
             r#"
@@ -10,7 +10,9 @@
 use buck2_build_api::interpreter::rule_defs::register_rule_defs;
 use buck2_build_api::interpreter::rule_defs::transitive_set::globals::register_transitive_set_types;
 use buck2_build_api::interpreter::rule_defs::transitive_set::transitive_set_definition::register_transitive_set;
+use buck2_common::package_listing::listing::PackageListing;
 use buck2_core::bzl::ImportPath;
+use buck2_interpreter::paths::bxl::BxlFilePath;
 use buck2_interpreter_for_build::interpreter::testing::expect_error;
 use buck2_interpreter_for_build::interpreter::testing::Tester;
 use indoc::indoc;
@@ -75,6 +77,44 @@ fn test_hash_transitive_set() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_define_transitive_set_in_bxl() -> anyhow::Result<()> {
+    let tester = transitive_set_tester();
+    tester.eval_bxl_file(
+        &BxlFilePath::testing_new("root", "test/script.bxl"),
+        indoc!(
+            r#"
+            FooSet = transitive_set()
+            assert_eq("FooSet", str(FooSet))
+            assert_eq("Value(TransitiveSetDefinition(FooSet declared in root//test/script.bxl))", debug(FooSet))
+            dict = {FooSet: 1}
+            "#
+        ),
+    )?;
+
+    Ok(())
+}
+
+#[test]
+fn test_define_transitive_set_in_build_file() -> anyhow::Result<()> {
+    let tester = transitive_set_tester();
+    let err = tester
+        .eval_build_file(
+            &Tester::build_file_path(),
+            "FooSet = transitive_set()",
+            PackageListing::testing_empty(),
+        )
+        .err()
+        .expect("transitive_set() should fail in a build file");
+    assert!(
+        format!("{:#}", err).contains("can only be used in `bzl` and `bxl` files"),
+        "unexpected error: {:#}",
+        err
+    );
+
+    Ok(())
+}
+
 #[test]
 fn test_define_transitive_set_projections() -> anyhow::Result<()> {
     let mut tester = transitive_set_tester();
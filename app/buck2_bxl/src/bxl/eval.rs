@@ -11,6 +11,7 @@ use std::cell::RefCell;
 use std::sync::Arc;
 
 use anyhow::Context;
+use buck2_analysis::analysis::calculation::profile_analyses_recursively;
 use buck2_artifact::artifact::build_artifact::BuildArtifact;
 use buck2_build_api::actions::artifact::get_artifact_fs::GetArtifactFs;
 use buck2_build_api::bxl::result::BxlResult;
@@ -195,7 +196,7 @@ async fn eval_bxl_inner(
 
     let global_target_platform = key.global_target_platform().clone();
 
-    let (bxl_result, materializations) = with_starlark_eval_provider(
+    let (bxl_result, materializations, analysed_targets) = with_starlark_eval_provider(
         ctx,
         &mut profiler,
         format!("bxl:{}", key),
@@ -261,6 +262,7 @@ async fn eval_bxl_inner(
                 return Err(anyhow::anyhow!(NotAValidReturnType(result.get_type())));
             }
 
+            let analysed_targets = bxl_ctx.as_ref().data.analysed_targets.take();
             let (actions, ensured_artifacts, materializations) = BxlContext::take_state(bxl_ctx)?;
             std::mem::drop(eval);
 
@@ -314,12 +316,23 @@ async fn eval_bxl_inner(
                 .visit_frozen_module(Some(&frozen_module))
                 .context("Profiler heap visitation failed")?;
 
-            Ok((bxl_result, materializations))
+            Ok((bxl_result, materializations, analysed_targets))
         },
     )
     .await?;
 
-    let profile_data = profiler_opt.map(|p| p.finish()).transpose()?;
+    let profile_data = match profiler_opt.map(|p| p.finish()).transpose()? {
+        Some(bxl_profile_data) if !analysed_targets.is_empty() => {
+            // Analyses triggered by the BXL are profiled too, include them in its profile.
+            let analysis_profile_data =
+                profile_analyses_recursively(ctx, &analysed_targets).await?;
+            Some(StarlarkProfileDataAndStats::merge([
+                &bxl_profile_data,
+                &analysis_profile_data,
+            ])?)
+        }
+        profile_data => profile_data,
+    };
     Ok((bxl_result, profile_data, materializations))
 }
 
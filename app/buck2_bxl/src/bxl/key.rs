@@ -246,3 +246,56 @@ impl BaseDeferredKeyDyn for BxlDynamicKeyData {
         &self.execution_platform_resolution
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::Hash;
+    use std::hash::Hasher;
+    use std::sync::Arc;
+
+    use buck2_build_api::bxl::types::BxlFunctionLabel;
+    use buck2_core::target::label::TargetLabel;
+    use buck2_interpreter::paths::bxl::BxlFilePath;
+
+    use crate::bxl::key::BxlKey;
+    use crate::bxl::starlark_defs::cli_args::CliArgValue;
+
+    fn key(args: &[(&str, &str)], platform: Option<&str>) -> BxlKey {
+        BxlKey::new(
+            BxlFunctionLabel {
+                bxl_path: BxlFilePath::testing_new("root", "ide/lsp.bxl"),
+                name: "main".to_owned(),
+            },
+            Arc::new(
+                args.iter()
+                    .map(|(k, v)| ((*k).to_owned(), CliArgValue::String((*v).to_owned())))
+                    .collect(),
+            ),
+            platform.map(TargetLabel::testing_parse),
+            false,
+        )
+    }
+
+    fn hash(key: &BxlKey) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// The result of a BXL function is cached in DICE under its key, so invocations with the same
+    /// function, CLI args and target platform must share it.
+    #[test]
+    fn test_bxl_key_identifies_invocation() {
+        let a = key(&[("file", "foo.cpp")], None);
+        assert_eq!(a, key(&[("file", "foo.cpp")], None));
+        assert_eq!(hash(&a), hash(&key(&[("file", "foo.cpp")], None)));
+
+        assert_ne!(a, key(&[("file", "bar.cpp")], None));
+        assert_ne!(a, key(&[], None));
+        assert_ne!(
+            a,
+            key(&[("file", "foo.cpp")], Some("root//platforms:linux"))
+        );
+    }
+}
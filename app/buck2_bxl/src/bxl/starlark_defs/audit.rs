@@ -12,10 +12,15 @@ use anyhow::Context as _;
 use buck2_build_api::audit_cell::audit_cell;
 use buck2_build_api::audit_output::audit_output;
 use buck2_build_api::audit_output::AuditOutputResult;
+use buck2_common::legacy_configs::dice::HasLegacyConfigs;
 use buck2_core::cells::CellResolver;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_core::pattern::parse_package::parse_package;
 use buck2_core::target::label::TargetLabel;
+use buck2_interpreter::types::target_label::StarlarkConfiguredTargetLabel;
 use buck2_interpreter::types::target_label::StarlarkTargetLabel;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_node::package_values_calculation::PACKAGE_VALUES_CALCULATION;
 use derivative::Derivative;
 use derive_more::Display;
 use dupe::Dupe;
@@ -26,8 +31,10 @@ use starlark::environment::MethodsBuilder;
 use starlark::environment::MethodsStatic;
 use starlark::starlark_module;
 use starlark::values::dict::AllocDict;
+use starlark::values::list::AllocList;
 use starlark::values::list_or_tuple::UnpackListOrTuple;
 use starlark::values::starlark_value;
+use starlark::values::structs::AllocStruct;
 use starlark::values::AllocValue;
 use starlark::values::Heap;
 use starlark::values::NoSerialize;
@@ -36,8 +43,12 @@ use starlark::values::Trace;
 use starlark::values::Value;
 use starlark::StarlarkDocs;
 
+use crate::bxl::starlark_defs::cli_args::JsonCliArgValueData;
 use crate::bxl::starlark_defs::context::BxlContext;
 use crate::bxl::starlark_defs::nodes::action::StarlarkAction;
+use crate::bxl::starlark_defs::target_list_expr::filter_incompatible;
+use crate::bxl::starlark_defs::target_list_expr::ConfiguredTargetListExprArg;
+use crate::bxl::starlark_defs::target_list_expr::TargetListExpr;
 use crate::bxl::value_as_starlark_target_label::ValueAsStarlarkTargetLabel;
 
 #[derive(
@@ -192,4 +203,165 @@ fn audit_methods(builder: &mut MethodsBuilder) {
             result.into_iter().map(|(k, v)| (k, v.to_string())),
         ))
     }
+
+    /// Returns the value of the buckconfig property `section.key`, as resolved by buck2 with
+    /// all the config files and command line overrides applied, or None if it is not set.
+    ///
+    /// The config is read from the given `cell` alias, which defaults to the cell of the BXL
+    /// script. This is the same as `buck2 audit config section.key`.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _impl_audit_config(ctx):
+    ///     ctx.output.print(ctx.audit().config("buildfile", "name", cell = "root"))
+    /// ```
+    fn config<'v>(
+        this: &StarlarkAuditCtx<'v>,
+        #[starlark(require = pos)] section: &str,
+        #[starlark(require = pos)] key: &str,
+        #[starlark(require = named)] cell: Option<&str>,
+    ) -> anyhow::Result<Option<String>> {
+        let cell_name = match cell {
+            None => this.ctx.data.cell_name,
+            Some(cell) => this
+                .cell_resolver
+                .get(this.ctx.data.cell_name)?
+                .cell_alias_resolver()
+                .resolve(cell)?,
+        };
+
+        this.ctx.async_ctx.borrow_mut().via(|ctx| {
+            async move {
+                Ok(ctx
+                    .get_legacy_config_property(cell_name, section, key)
+                    .await?
+                    .map(|v| v.to_string()))
+            }
+            .boxed_local()
+        })
+    }
+
+    /// Returns the execution platform resolution of the given configured targets, as a dict of
+    /// configured target label to a struct with the fields:
+    /// * `platform` - the label of the execution platform, or None if no platform is compatible.
+    /// * `configuration` - the configuration of the execution platform, or None.
+    /// * `exec_deps` - the configured labels of the execution deps.
+    /// * `toolchain_deps` - the configured labels of the toolchain deps.
+    /// * `skipped` - a dict of the platforms skipped during resolution to the reason they were
+    ///   skipped.
+    ///
+    /// Incompatible targets are skipped. This is the same as
+    /// `buck2 audit execution-platform-resolution`.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _impl_audit_exec_platform(ctx):
+    ///     result = ctx.audit().execution_platform_resolution("//foo:bar")
+    ///     for label, resolution in result.items():
+    ///         ctx.output.print(label, resolution.platform)
+    /// ```
+    fn execution_platform_resolution<'v>(
+        this: &StarlarkAuditCtx<'v>,
+        #[starlark(require = pos)] targets: ConfiguredTargetListExprArg<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        let nodes = this.ctx.via_dice(|mut dice, ctx| {
+            dice.via(|dice| {
+                async move {
+                    filter_incompatible(
+                        TargetListExpr::<'v, ConfiguredTargetNode>::unpack(
+                            targets,
+                            &this.global_target_platform,
+                            ctx,
+                            dice,
+                        )
+                        .await?
+                        .get(dice)
+                        .await?,
+                        ctx,
+                    )
+                }
+                .boxed_local()
+            })
+        })?;
+
+        let resolutions = nodes.iter().map(|node| {
+            let resolution = node.execution_platform_resolution();
+            let platform = resolution.platform().ok();
+            (
+                StarlarkConfiguredTargetLabel::new(node.label().dupe()),
+                AllocStruct([
+                    ("platform", heap.alloc(platform.map(|p| p.id()))),
+                    (
+                        "configuration",
+                        heap.alloc(platform.map(|p| p.cfg().to_string())),
+                    ),
+                    ("exec_deps", heap.alloc(configured_labels(node.exec_deps()))),
+                    (
+                        "toolchain_deps",
+                        heap.alloc(configured_labels(node.toolchain_deps())),
+                    ),
+                    (
+                        "skipped",
+                        heap.alloc(AllocDict(
+                            resolution
+                                .skipped()
+                                .iter()
+                                .map(|(label, reason)| (label.as_str(), format!("{:#}", reason))),
+                        )),
+                    ),
+                ]),
+            )
+        });
+
+        Ok(heap.alloc(AllocDict(resolutions)))
+    }
+
+    /// Returns the PACKAGE values of the given `package` (e.g. `"root//foo/bar"`), as a dict of
+    /// value name to value. These are the values after inheritance from the parent PACKAGE files
+    /// is applied. This is the same as `buck2 audit package-values`.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _impl_audit_package_values(ctx):
+    ///     values = ctx.audit().package_values("root//foo/bar")
+    ///     ctx.output.print(values.get("team.oncall"))
+    /// ```
+    fn package_values<'v>(
+        this: &StarlarkAuditCtx<'v>,
+        #[starlark(require = pos)] package: &str,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        let package = parse_package(
+            package,
+            this.cell_resolver
+                .get(this.ctx.data.cell_name)?
+                .cell_alias_resolver(),
+        )?;
+
+        let package_values = this.ctx.async_ctx.borrow_mut().via(|ctx| {
+            async move {
+                PACKAGE_VALUES_CALCULATION
+                    .get()?
+                    .package_values(ctx, package)
+                    .await
+            }
+            .boxed_local()
+        })?;
+
+        Ok(
+            heap.alloc(AllocDict(package_values.iter().map(|(key, value)| {
+                (
+                    key.as_str(),
+                    JsonCliArgValueData::from_serde_value(value).as_starlark(heap),
+                )
+            }))),
+        )
+    }
+}
+
+fn configured_labels<'a>(
+    nodes: impl Iterator<Item = &'a ConfiguredTargetNode>,
+) -> AllocList<impl IntoIterator<Item = StarlarkConfiguredTargetLabel>> {
+    AllocList(nodes.map(|node| StarlarkConfiguredTargetLabel::new(node.label().dupe())))
 }
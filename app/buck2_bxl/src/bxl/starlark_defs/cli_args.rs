@@ -17,6 +17,7 @@ use std::sync::Arc;
 
 use allocative::Allocative;
 use anyhow::Context as _;
+use buck2_common::legacy_configs::dice::HasLegacyConfigs;
 use buck2_core::pattern::lex_target_pattern;
 use buck2_core::pattern::pattern_type::ProvidersPatternExtra;
 use buck2_core::pattern::pattern_type::TargetPatternExtra;
@@ -64,6 +65,9 @@ use crate::bxl::eval::CliResolutionCtx;
 pub(crate) struct CliArgs {
     /// The default value. If None, the value is not optional and must be provided by the user
     pub(crate) default: Option<Arc<CliArgValue>>,
+    /// The buckconfig `(section, key)` whose value is used when the arg is not given on the
+    /// command line. Takes precedence over `default`.
+    default_config: Option<(String, String)>,
     /// Documentation for what the attribute actually means
     doc: String,
     /// The coercer to take this parameter's value from Starlark value -> an
@@ -86,6 +90,7 @@ impl CliArgs {
         doc: &str,
         coercer: CliArgType,
         short: Option<Value<'v>>,
+        default_config: Option<&str>,
     ) -> anyhow::Result<Self> {
         let default = match default {
             None => None,
@@ -109,9 +114,31 @@ impl CliArgs {
             },
         };
 
+        let default_config = match default_config {
+            None => None,
+            Some(config) => match config.split_once('.') {
+                Some((section, key)) if !section.is_empty() && !key.is_empty() => {
+                    Some((section.to_owned(), key.to_owned()))
+                }
+                _ => return Err(CliArgError::InvalidDefaultConfig(config.to_owned()).into()),
+            },
+        };
+
+        // Surface the config key in the generated `--help`.
+        let doc = match &default_config {
+            None => doc.to_owned(),
+            Some((section, key)) if doc.is_empty() => {
+                format!("[default from buckconfig: {}.{}]", section, key)
+            }
+            Some((section, key)) => {
+                format!("{} [default from buckconfig: {}.{}]", doc, section, key)
+            }
+        };
+
         Ok(Self {
             default,
-            doc: doc.to_owned(),
+            default_config,
+            doc,
             coercer,
             short,
         })
@@ -123,7 +150,7 @@ impl CliArgs {
             arg = arg.short(short);
         }
 
-        if self.default.is_some() {
+        if self.default.is_some() || self.default_config.is_some() {
             arg = arg.required(false);
         }
 
@@ -135,6 +162,33 @@ impl CliArgs {
         clap: ArgAccessor<'a>,
         ctx: &CliResolutionCtx<'a>,
     ) -> anyhow::Result<CliArgValue> {
+        if clap.value_of().is_none() {
+            if let Some((section, key)) = &self.default_config {
+                if let Some(value) = ctx
+                    .dice
+                    .get_legacy_config_property(ctx.relative_dir.cell_name(), section, key)
+                    .await?
+                {
+                    // Lists are read from buckconfig as comma or whitespace separated values.
+                    let values = if matches!(self.coercer, CliArgType::List(_)) {
+                        value
+                            .split(|c: char| c == ',' || c.is_whitespace())
+                            .filter(|v| !v.is_empty())
+                            .collect()
+                    } else {
+                        vec![value.trim()]
+                    };
+                    return self
+                        .coercer
+                        .parse_clap(ArgAccessor::Literals(values), ctx)
+                        .await?
+                        .with_context(|| {
+                            format!("Empty value for buckconfig `{}.{}`", section, key)
+                        });
+                }
+            }
+        }
+
         Ok(match self.coercer.parse_clap(clap, ctx).await? {
             None => (**self.default.as_ref().ok_or(CliArgError::MissingCliArg)?).clone(),
             Some(v) => v,
@@ -173,7 +227,7 @@ pub(crate) enum JsonCliArgValueData {
 }
 
 impl JsonCliArgValueData {
-    fn from_serde_value(val: &serde_json::Value) -> Self {
+    pub(crate) fn from_serde_value(val: &serde_json::Value) -> Self {
         match val {
             serde_json::Value::Null => JsonCliArgValueData::None,
             serde_json::Value::Bool(x) => JsonCliArgValueData::Bool(*x),
@@ -379,6 +433,8 @@ pub(crate) enum CliArgError {
     DefinedBothKebabAndSnakeCase(String),
     #[error("Expecting json object. Got: `{0}`")]
     NotAJsonObject(String),
+    #[error("Expected `default_config` to be of the form `section.key`, got `{0}`")]
+    InvalidDefaultConfig(String),
 }
 
 impl CliArgType {
@@ -535,14 +591,21 @@ impl CliArgType {
                     r.map(Some)
                 })?,
                 CliArgType::String => clap.value_of().map(|s| CliArgValue::String(s.to_owned())),
-                CliArgType::Enumeration(_variants) => {
-                    // the validators should have already made sure the strings are only of the variants
-                    clap.value_of().map(|s| CliArgValue::String(s.to_owned()))
-                }
+                CliArgType::Enumeration(variants) => match clap.value_of() {
+                    None => None,
+                    // the validators have already checked values from the command line, but not
+                    // values from buckconfig
+                    Some(s) if variants.contains(s) => Some(CliArgValue::String(s.to_owned())),
+                    Some(s) => {
+                        return Err(
+                            CliArgError::DefaultValueTypeError(self.dupe(), s.to_owned()).into(),
+                        );
+                    }
+                },
                 CliArgType::List(inner) => match clap.values_of() {
                     None => None,
-                    Some(values) => Some(CliArgValue::List(
-                        futures::future::join_all(values.map(async move |v| try {
+                    Some(values) => {
+                        let items = futures::future::join_all(values.map(async move |v| try {
                             inner
                                 .parse_clap(ArgAccessor::Literal(v), ctx)
                                 .await?
@@ -550,8 +613,22 @@ impl CliArgType {
                         }))
                         .await
                         .into_iter()
-                        .collect::<anyhow::Result<_>>()?,
-                    )),
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                        // A repeated target expression is the union of the targets of each
+                        // expression, rather than a list of lists.
+                        let items = match &**inner {
+                            CliArgType::TargetExpr | CliArgType::SubTargetExpr => items
+                                .into_iter()
+                                .flat_map(|item| match item {
+                                    CliArgValue::List(targets) => targets,
+                                    item => vec![item],
+                                })
+                                .unique()
+                                .collect(),
+                            _ => items,
+                        };
+                        Some(CliArgValue::List(items))
+                    }
                 },
                 CliArgType::Option(inner) => Some(if clap.value_of().is_some() {
                     inner
@@ -660,8 +737,9 @@ pub(crate) fn cli_args_module(registry: &mut GlobalsBuilder) {
         default: Option<Value<'v>>,
         #[starlark(default = "")] doc: &str,
         #[starlark(require = named)] short: Option<Value<'v>>,
+        #[starlark(require = named)] default_config: Option<&str>,
     ) -> anyhow::Result<CliArgs> {
-        CliArgs::new(default, doc, CliArgType::string(), short)
+        CliArgs::new(default, doc, CliArgType::string(), short, default_config)
     }
 
     fn list<'v>(
@@ -669,33 +747,43 @@ pub(crate) fn cli_args_module(registry: &mut GlobalsBuilder) {
         default: Option<Value<'v>>,
         #[starlark(default = "")] doc: &str,
         #[starlark(require = named)] short: Option<Value<'v>>,
+        #[starlark(require = named)] default_config: Option<&str>,
     ) -> anyhow::Result<CliArgs> {
         let coercer = CliArgType::list(inner.coercer.dupe());
-        CliArgs::new(default, doc, coercer, short)
+        CliArgs::new(default, doc, coercer, short, default_config)
     }
 
     fn bool<'v>(
         #[starlark(default = false)] default: Value<'v>,
         #[starlark(default = "")] doc: &str,
         #[starlark(require = named)] short: Option<Value<'v>>,
+        #[starlark(require = named)] default_config: Option<&str>,
     ) -> anyhow::Result<CliArgs> {
-        CliArgs::new(Some(default), doc, CliArgType::bool(), short)
+        CliArgs::new(
+            Some(default),
+            doc,
+            CliArgType::bool(),
+            short,
+            default_config,
+        )
     }
 
     fn int<'v>(
         default: Option<Value<'v>>,
         #[starlark(default = "")] doc: &str,
         #[starlark(require = named)] short: Option<Value<'v>>,
+        #[starlark(require = named)] default_config: Option<&str>,
     ) -> anyhow::Result<CliArgs> {
-        CliArgs::new(default, doc, CliArgType::int(), short)
+        CliArgs::new(default, doc, CliArgType::int(), short, default_config)
     }
 
     fn float<'v>(
         default: Option<Value<'v>>,
         #[starlark(default = "")] doc: &str,
         #[starlark(require = named)] short: Option<Value<'v>>,
+        #[starlark(require = named)] default_config: Option<&str>,
     ) -> anyhow::Result<CliArgs> {
-        CliArgs::new(default, doc, CliArgType::float(), short)
+        CliArgs::new(default, doc, CliArgType::float(), short, default_config)
     }
 
     fn option<'v>(
@@ -703,9 +791,10 @@ pub(crate) fn cli_args_module(registry: &mut GlobalsBuilder) {
         #[starlark(default = "")] doc: &str,
         #[starlark(default = NoneType)] default: Value<'v>,
         #[starlark(require = named)] short: Option<Value<'v>>,
+        #[starlark(require = named)] default_config: Option<&str>,
     ) -> anyhow::Result<CliArgs> {
         let coercer = CliArgType::option(inner.coercer.dupe());
-        CliArgs::new(Some(default), doc, coercer, short)
+        CliArgs::new(Some(default), doc, coercer, short, default_config)
     }
 
     fn r#enum<'v>(
@@ -713,6 +802,7 @@ pub(crate) fn cli_args_module(registry: &mut GlobalsBuilder) {
         default: Option<Value<'v>>,
         #[starlark(default = "")] doc: &str,
         #[starlark(require = named)] short: Option<Value<'v>>,
+        #[starlark(require = named)] default_config: Option<&str>,
     ) -> anyhow::Result<CliArgs> {
         // Value seems to usually be a `[String]`, listing the possible values of the
         // enumeration. Unfortunately, for things like `exported_lang_preprocessor_flags`
@@ -722,42 +812,54 @@ pub(crate) fn cli_args_module(registry: &mut GlobalsBuilder) {
             doc,
             CliArgType::enumeration(variants.into_iter().collect()),
             short,
+            default_config,
         )
     }
 
     fn target_label<'v>(
         #[starlark(default = "")] doc: &str,
         #[starlark(require = named)] short: Option<Value<'v>>,
+        #[starlark(require = named)] default_config: Option<&str>,
     ) -> anyhow::Result<CliArgs> {
-        CliArgs::new(None, doc, CliArgType::target_label(), short)
+        CliArgs::new(None, doc, CliArgType::target_label(), short, default_config)
     }
 
     fn sub_target<'v>(
         #[starlark(default = "")] doc: &str,
         #[starlark(require = named)] short: Option<Value<'v>>,
+        #[starlark(require = named)] default_config: Option<&str>,
     ) -> anyhow::Result<CliArgs> {
-        CliArgs::new(None, doc, CliArgType::sub_target(), short)
+        CliArgs::new(None, doc, CliArgType::sub_target(), short, default_config)
     }
 
     fn target_expr<'v>(
         #[starlark(default = "")] doc: &str,
         #[starlark(require = named)] short: Option<Value<'v>>,
+        #[starlark(require = named)] default_config: Option<&str>,
     ) -> anyhow::Result<CliArgs> {
-        CliArgs::new(None, doc, CliArgType::target_expr(), short)
+        CliArgs::new(None, doc, CliArgType::target_expr(), short, default_config)
     }
 
     fn sub_target_expr<'v>(
         #[starlark(default = "")] doc: &str,
         #[starlark(require = named)] short: Option<Value<'v>>,
+        #[starlark(require = named)] default_config: Option<&str>,
     ) -> anyhow::Result<CliArgs> {
-        CliArgs::new(None, doc, CliArgType::sub_target_expr(), short)
+        CliArgs::new(
+            None,
+            doc,
+            CliArgType::sub_target_expr(),
+            short,
+            default_config,
+        )
     }
 
     fn json<'v>(
         #[starlark(default = "")] doc: &str,
         #[starlark(require = named)] short: Option<Value<'v>>,
+        #[starlark(require = named)] default_config: Option<&str>,
     ) -> anyhow::Result<CliArgs> {
-        CliArgs::new(None, doc, CliArgType::json(), short)
+        CliArgs::new(None, doc, CliArgType::json(), short, default_config)
     }
 }
 
@@ -916,6 +1018,8 @@ pub(crate) enum ArgAccessor<'a> {
         arg: &'a str,
     },
     Literal(&'a str),
+    /// Values from outside the command line, e.g. buckconfig.
+    Literals(Vec<&'a str>),
 }
 
 #[allow(deprecated)] // TODO(nga): fix.
@@ -924,13 +1028,19 @@ impl<'a> ArgAccessor<'a> {
         match self {
             ArgAccessor::Clap { clap, arg } => clap.value_of(arg),
             ArgAccessor::Literal(s) => Some(s),
+            ArgAccessor::Literals(v) => v.first().copied(),
         }
     }
 
     fn values_of(&self) -> Option<impl Iterator<Item = &str>> {
         match self {
             ArgAccessor::Clap { clap, arg } => clap.values_of(arg).map(itertools::Either::Left),
-            ArgAccessor::Literal(s) => Some(itertools::Either::Right(std::iter::once(*s))),
+            ArgAccessor::Literal(s) => Some(itertools::Either::Right(itertools::Either::Left(
+                std::iter::once(*s),
+            ))),
+            ArgAccessor::Literals(v) => Some(itertools::Either::Right(itertools::Either::Right(
+                v.iter().copied(),
+            ))),
         }
     }
 }
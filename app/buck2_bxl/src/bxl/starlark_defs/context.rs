@@ -52,6 +52,7 @@ use buck2_core::pattern::query_file_literal::parse_query_file_literal;
 use buck2_core::pattern::ParsedPattern;
 use buck2_core::provider::label::ConfiguredProvidersLabel;
 use buck2_core::provider::label::ProvidersLabel;
+use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
 use buck2_core::target::label::TargetLabel;
 use buck2_events::dispatch::console_message;
 use buck2_events::dispatch::with_dispatcher_async;
@@ -84,6 +85,7 @@ use starlark::environment::MethodsStatic;
 use starlark::environment::Module;
 use starlark::eval::Evaluator;
 use starlark::starlark_module;
+use starlark::values::list_or_tuple::UnpackListOrTuple;
 use starlark::values::none::NoneOr;
 use starlark::values::none::NoneType;
 use starlark::values::starlark_value;
@@ -235,6 +237,12 @@ pub(crate) struct BxlContextNoDice<'v> {
     pub(crate) project_fs: ProjectRoot,
     #[derivative(Debug = "ignore")]
     pub(crate) artifact_fs: ArtifactFs,
+    /// Targets analysed or built by this BXL, recorded to profile their analysis along with the
+    /// BXL when profiling.
+    #[trace(unsafe_ignore)]
+    #[derivative(Debug = "ignore")]
+    #[allocative(skip)]
+    pub(crate) analysed_targets: RefCell<Vec<ConfiguredTargetLabel>>,
 }
 
 impl<'v> BxlContext<'v> {
@@ -303,6 +311,7 @@ impl<'v> BxlContext<'v> {
                 context_type,
                 project_fs,
                 artifact_fs,
+                analysed_targets: RefCell::new(Vec::new()),
             },
         })
     }
@@ -350,6 +359,7 @@ impl<'v> BxlContext<'v> {
                 context_type: BxlContextType::Dynamic(dynamic_data),
                 project_fs,
                 artifact_fs,
+                analysed_targets: RefCell::new(Vec::new()),
             },
         })
     }
@@ -896,13 +906,32 @@ fn context_methods(builder: &mut MethodsBuilder) {
     /// configuration used to configured any unconfigured target nodes.
     ///
     /// The `target_platform` is a target label, or a string that is a target label.
+    ///
+    /// An optional `target_universe` (a list of target patterns) sets the universe used by `eval`
+    /// queries on the returned ctx. Several `cqueryctx`s with different universes can be used in
+    /// the same script, e.g.:
+    /// ```text
+    /// def _impl(ctx):
+    ///     app = ctx.cquery(target_universe = ["root//app/..."])
+    ///     lib = ctx.cquery(target_universe = ["root//lib/..."])
+    ///     ctx.output.print(app.eval("deps(root//common:util)"))
+    ///     ctx.output.print(lib.eval("deps(root//common:util)"))
+    /// ```
     fn cquery<'v>(
         this: &'v BxlContext<'v>,
         // TODO(nga): parameter should be either positional or named, not both.
         #[starlark(default = ValueAsStarlarkTargetLabel::NONE)]
         target_platform: ValueAsStarlarkTargetLabel<'v>,
+        #[starlark(require = named, default = NoneOr::None)] target_universe: NoneOr<
+            UnpackListOrTuple<String>,
+        >,
     ) -> anyhow::Result<StarlarkCQueryCtx<'v>> {
-        StarlarkCQueryCtx::new(this, target_platform, &this.data.global_target_platform)
+        StarlarkCQueryCtx::new(
+            this,
+            target_platform,
+            &this.data.global_target_platform,
+            target_universe.into_option().map(|v| v.items),
+        )
     }
 
     /// Returns the `aqueryctx` that holds all the aquery functions.
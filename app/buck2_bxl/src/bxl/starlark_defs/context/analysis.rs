@@ -12,6 +12,7 @@ use buck2_core::configuration::compatibility::IncompatiblePlatformReason;
 use buck2_core::configuration::compatibility::MaybeCompatible;
 use buck2_core::provider::label::ConfiguredProvidersLabel;
 use dice::DiceComputations;
+use dupe::Dupe;
 use either::Either;
 use gazebo::prelude::*;
 
@@ -27,6 +28,10 @@ pub(crate) async fn analysis<'v>(
 ) -> anyhow::Result<
     Either<Option<StarlarkAnalysisResult>, Vec<(ConfiguredProvidersLabel, StarlarkAnalysisResult)>>,
 > {
+    ctx.analysed_targets
+        .borrow_mut()
+        .extend(expr.labels().map(|label| label.target().dupe()));
+
     let analysis = futures::future::join_all(expr.labels().map(async move |label| {
         let maybe_result = dice.get_analysis_result(label.target()).await?;
 
@@ -211,6 +211,10 @@ pub(crate) fn build<'v>(
             )
             .await?;
 
+            ctx.analysed_targets
+                .borrow_mut()
+                .extend(build_spec.labels().map(|label| label.target().dupe()));
+
             let stream = dice
                 .compute_many(build_spec.labels().unique().map(|target| {
                     let target = target.clone();
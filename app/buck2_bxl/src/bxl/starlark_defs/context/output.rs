@@ -24,6 +24,7 @@ use buck2_build_api::interpreter::rule_defs::cmd_args::SimpleCommandLineArtifact
 use buck2_build_api::interpreter::rule_defs::cmd_args::StarlarkCommandLineInputs;
 use buck2_core::fs::artifact_path_resolver::ArtifactFs;
 use buck2_core::fs::project::ProjectRoot;
+use buck2_events::dispatch::instant_event;
 use buck2_execute::path::artifact_path::ArtifactPath;
 use buck2_interpreter::error::BuckStarlarkError;
 use derivative::Derivative;
@@ -162,49 +163,39 @@ fn output_stream_methods(builder: &mut MethodsBuilder) {
         #[starlark(args)] args: UnpackTuple<Value<'v>>,
         #[starlark(default = " ")] sep: &'v str,
     ) -> anyhow::Result<NoneType> {
-        let mut first = true;
-        let mut write = |d: &dyn Display| -> anyhow::Result<()> {
-            if !first {
-                write!(this.sink.borrow_mut(), "{}{}", sep, d)?;
-            } else {
-                write!(this.sink.borrow_mut(), "{}", d)?;
-                first = false;
-            }
-            Ok(())
-        };
+        write_output_args(this, args, sep, this.sink.borrow_mut().deref_mut())?;
 
-        for arg in args {
-            if let Some(ensured) = <&EnsuredArtifact>::unpack_value(arg) {
-                let path = get_artifact_path_display(
-                    ensured.get_artifact_path(),
-                    ensured.abs(),
-                    &this.project_fs,
-                    &this.artifact_fs,
-                )?;
-                write(&path)?;
-            } else if let Some(ensured) = <&EnsuredArtifactGroup>::unpack_value(arg) {
-                this.async_ctx.borrow_mut().via(|dice| {
-                    ensured
-                        .visit_artifact_path_without_associated_deduped(
-                            |artifact_path, abs| {
-                                let path = get_artifact_path_display(
-                                    artifact_path,
-                                    abs,
-                                    &this.project_fs,
-                                    &this.artifact_fs,
-                                )?;
-                                write(&path)
-                            },
-                            dice,
-                        )
-                        .boxed_local()
-                })?;
-            } else {
-                write(&arg.to_str())?;
-            }
-        }
+        Ok(NoneType)
+    }
 
-        writeln!(this.sink.borrow_mut())?;
+    /// Streams results to stdout as soon as they are produced, rather than at the end of the bxl
+    /// script like `print()`. Accepts an optional separator that defaults to " ".
+    ///
+    /// This is meant for long running scripts whose consumers, such as IDE integrations, want
+    /// early results. Each call is sent to the client immediately, and is written to stdout
+    /// before any of the outputs of `print()`. Unlike `print()`, streamed outputs are not cached:
+    /// they are only emitted when the script actually runs, not when its result is cached.
+    ///
+    /// Ensured artifacts are printed as their paths, but are only materialized once the script
+    /// has finished.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _impl_stream(ctx):
+    ///     for target in ctx.uquery().eval("//..."):
+    ///         ctx.output.stream(target.label)
+    /// ```
+    fn stream<'v>(
+        this: &'v OutputStream<'v>,
+        #[starlark(args)] args: UnpackTuple<Value<'v>>,
+        #[starlark(default = " ")] sep: &'v str,
+    ) -> anyhow::Result<NoneType> {
+        let mut data = Vec::new();
+        write_output_args(this, args, sep, &mut data)?;
+
+        instant_event(buck2_data::StreamingOutput {
+            data: String::from_utf8(data)?,
+        });
 
         Ok(NoneType)
     }
@@ -441,6 +432,157 @@ fn output_stream_methods(builder: &mut MethodsBuilder) {
             Err(anyhow::anyhow!(incorrect_parameter_type_error(artifacts)))
         }
     }
+
+    /// Same as `ensure_multiple`, but returns a `Dict` mapping each artifact to its path, which
+    /// is absolute unless `abs = False`. Useful to write manifest files rather than printing paths
+    /// one by one. Accepts a list of artifacts, or the result of `ctx.build()` (or one of its
+    /// values or `artifacts()`), in which case the keys are the built artifacts.
+    ///
+    /// As with `ensure`, the artifacts are materialized at the end of the bxl invocation, with
+    /// the progress shown in the console.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _impl_ensure_paths(ctx):
+    ///     paths = ctx.output.ensure_paths(ctx.build(ctx.cli_args.targets))
+    ///     manifest = {artifact.short_path: path for artifact, path in paths.items()}
+    ///     ctx.output.print_json(manifest)
+    /// ```
+    fn ensure_paths<'v>(
+        this: &'v OutputStream<'v>,
+        artifacts: Value<'v>,
+        #[starlark(require = named, default = true)] abs: bool,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        let ensured: Vec<(Value<'v>, EnsuredArtifact)> = if artifacts.is_none() {
+            Vec::new()
+        } else if let Some(list) = ListRef::from_value(artifacts) {
+            list.iter()
+                .map(|value| {
+                    let artifact = EnsuredArtifactArg::unpack_value(value)
+                        .ok_or_else(|| anyhow::anyhow!(incorrect_parameter_type_error(artifacts)))?
+                        .into_ensured_artifact();
+                    populate_ensured_artifacts(
+                        this,
+                        EnsuredArtifactOrGroup::Artifact(artifact.clone()),
+                    )?;
+                    Ok((value, artifact))
+                })
+                .collect::<anyhow::Result<_>>()?
+        } else {
+            let build_results: Vec<&StarlarkBxlBuildResult> = if let Some(artifact_gen) =
+                <&StarlarkProvidersArtifactIterable>::unpack_value(artifacts)
+            {
+                vec![
+                    artifact_gen
+                        .0
+                        .downcast_ref::<StarlarkBxlBuildResult>()
+                        .unwrap(),
+                ]
+            } else if let Some(bxl_build_result) =
+                <&StarlarkBxlBuildResult>::unpack_value(artifacts)
+            {
+                vec![bxl_build_result]
+            } else if let Some(build_result_dict) = <DictRef>::unpack_value(artifacts) {
+                build_result_dict
+                    .values()
+                    .map(|value| {
+                        <&StarlarkBxlBuildResult>::unpack_value(value).ok_or_else(|| {
+                            anyhow::anyhow!(incorrect_parameter_type_error(artifacts))
+                        })
+                    })
+                    .collect::<anyhow::Result<_>>()?
+            } else {
+                return Err(anyhow::anyhow!(incorrect_parameter_type_error(artifacts)));
+            };
+            let mut ensured = Vec::new();
+            for build_result in build_results {
+                for artifact in get_artifacts_from_bxl_build_result(build_result, this)? {
+                    let value = match &artifact {
+                        EnsuredArtifact::Artifact { artifact, .. } => heap.alloc(artifact.dupe()),
+                        EnsuredArtifact::DeclaredArtifact { artifact, .. } => {
+                            heap.alloc(artifact.dupe())
+                        }
+                    };
+                    ensured.push((value, artifact));
+                }
+            }
+            ensured
+        };
+
+        Ok(heap.alloc(Dict::new(
+            ensured
+                .into_iter()
+                .map(|(value, artifact)| {
+                    let path = get_artifact_path_display(
+                        artifact.get_artifact_path(),
+                        abs,
+                        &this.project_fs,
+                        &this.artifact_fs,
+                    )?;
+                    Ok((
+                        value.get_hashed().map_err(BuckStarlarkError::new)?,
+                        heap.alloc(path),
+                    ))
+                })
+                .collect::<anyhow::Result<_>>()?,
+        )))
+    }
+}
+
+/// Writes `args` separated by `sep` followed by a newline, printing ensured artifacts as their
+/// paths.
+fn write_output_args<'v>(
+    this: &'v OutputStream<'v>,
+    args: UnpackTuple<Value<'v>>,
+    sep: &str,
+    sink: &mut dyn Write,
+) -> anyhow::Result<()> {
+    let mut first = true;
+    let mut write = |d: &dyn Display| -> anyhow::Result<()> {
+        if !first {
+            write!(sink, "{}{}", sep, d)?;
+        } else {
+            write!(sink, "{}", d)?;
+            first = false;
+        }
+        Ok(())
+    };
+
+    for arg in args {
+        if let Some(ensured) = <&EnsuredArtifact>::unpack_value(arg) {
+            let path = get_artifact_path_display(
+                ensured.get_artifact_path(),
+                ensured.abs(),
+                &this.project_fs,
+                &this.artifact_fs,
+            )?;
+            write(&path)?;
+        } else if let Some(ensured) = <&EnsuredArtifactGroup>::unpack_value(arg) {
+            this.async_ctx.borrow_mut().via(|dice| {
+                ensured
+                    .visit_artifact_path_without_associated_deduped(
+                        |artifact_path, abs| {
+                            let path = get_artifact_path_display(
+                                artifact_path,
+                                abs,
+                                &this.project_fs,
+                                &this.artifact_fs,
+                            )?;
+                            write(&path)
+                        },
+                        dice,
+                    )
+                    .boxed_local()
+            })?;
+        } else {
+            write(&arg.to_str())?;
+        }
+    }
+
+    writeln!(sink)?;
+
+    Ok(())
 }
 
 pub(crate) fn get_cmd_line_inputs<'v>(
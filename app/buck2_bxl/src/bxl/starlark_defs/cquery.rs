@@ -69,6 +69,9 @@ pub(crate) struct StarlarkCQueryCtx<'v> {
     ctx: &'v BxlContext<'v>,
     #[derivative(Debug = "ignore")]
     target_platform: Option<TargetLabel>,
+    /// Target universe used by `eval` when the call doesn't specify one.
+    #[derivative(Debug = "ignore")]
+    target_universe: Option<Vec<String>>,
 }
 
 #[starlark_value(type = "cqueryctx", StarlarkTypeRepr, UnpackValue)]
@@ -103,6 +106,7 @@ impl<'v> StarlarkCQueryCtx<'v> {
         ctx: &'v BxlContext<'v>,
         global_target_platform: ValueAsStarlarkTargetLabel<'v>,
         default_target_platform: &Option<TargetLabel>,
+        target_universe: Option<Vec<String>>,
     ) -> anyhow::Result<StarlarkCQueryCtx<'v>> {
         let target_platform = global_target_platform.parse_target_platforms(
             &ctx.data.target_alias_resolver,
@@ -114,10 +118,23 @@ impl<'v> StarlarkCQueryCtx<'v> {
         Ok(Self {
             ctx,
             target_platform,
+            target_universe,
         })
     }
 }
 
+/// The target universe for an `eval` call: the one passed to `eval`, or the one the `cqueryctx`
+/// was created with.
+fn eval_target_universe(
+    ctx_target_universe: Option<&[String]>,
+    target_universe: NoneOr<UnpackListOrTuple<String>>,
+) -> Option<Vec<String>> {
+    match target_universe {
+        NoneOr::None => ctx_target_universe.map(|v| v.to_vec()),
+        NoneOr::Other(target_universe) => Some(target_universe.items),
+    }
+}
+
 /// The context for performing `cquery` operations in bxl. The functions offered on this ctx are
 /// the same behaviour as the query functions available within cquery command.
 ///
@@ -638,6 +655,8 @@ fn cquery_methods(builder: &mut MethodsBuilder) {
     ///     result2 = ctx.cquery().eval("inputs(%s)", query_args = ["cell//path/to/file:target"])
     ///     ctx.output.print(result2)
     /// ```
+    ///
+    /// `target_universe` defaults to the universe the `cqueryctx` was created with, if any.
     fn eval<'v>(
         this: &StarlarkCQueryCtx<'v>,
         query: &'v str,
@@ -649,6 +668,8 @@ fn cquery_methods(builder: &mut MethodsBuilder) {
             NoneOr::None => Vec::new(),
             NoneOr::Other(query_args) => query_args.into_strings(),
         };
+        let target_universe =
+            eval_target_universe(this.target_universe.as_deref(), target_universe);
 
         this.ctx.via_dice(|mut dice, ctx| {
             dice.via(|dice| {
@@ -663,7 +684,7 @@ fn cquery_methods(builder: &mut MethodsBuilder) {
                                 query,
                                 &query_args,
                                 this.target_platform.dupe(),
-                                target_universe.into_option().as_ref().map(|v| &v.items[..]),
+                                target_universe.as_deref(),
                             )
                             .await?,
                         eval,
@@ -713,3 +734,47 @@ fn cquery_methods(builder: &mut MethodsBuilder) {
             .map(StarlarkFileSet::from)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use starlark::values::list_or_tuple::UnpackListOrTuple;
+    use starlark::values::none::NoneOr;
+
+    use super::eval_target_universe;
+
+    fn universe(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(|p| (*p).to_owned()).collect()
+    }
+
+    #[test]
+    fn test_eval_target_universe() {
+        let ctx_universe = universe(&["root//app/..."]);
+
+        // No universe anywhere: the query's own literals are the universe.
+        assert_eq!(None, eval_target_universe(None, NoneOr::None));
+
+        // The ctx universe is used when `eval` doesn't specify one.
+        assert_eq!(
+            Some(ctx_universe.clone()),
+            eval_target_universe(Some(&ctx_universe), NoneOr::None)
+        );
+
+        // A universe passed to `eval` overrides the ctx universe.
+        assert_eq!(
+            Some(universe(&["root//lib/..."])),
+            eval_target_universe(
+                Some(&ctx_universe),
+                NoneOr::Other(UnpackListOrTuple {
+                    items: universe(&["root//lib/..."]),
+                }),
+            )
+        );
+        assert_eq!(
+            Some(Vec::new()),
+            eval_target_universe(
+                Some(&ctx_universe),
+                NoneOr::Other(UnpackListOrTuple { items: Vec::new() }),
+            )
+        );
+    }
+}
@@ -265,8 +265,21 @@ async fn ensure_artifacts(
         buck2_build_api::bxl::result::BxlResult::BuildsArtifacts {
             built, artifacts, ..
         } => {
+            let built_count: usize = built
+                .iter()
+                .map(|res| match res {
+                    BxlBuildResult::Built(ConfiguredBuildTargetResult { outputs, .. }) => outputs
+                        .iter()
+                        .map(|res| res.as_ref().map_or(0, |artifacts| artifacts.values.len()))
+                        .sum(),
+                    BxlBuildResult::None => 0,
+                })
+                .sum();
+            let start = BxlEnsureArtifactsStart {
+                artifact_count: (built_count + artifacts.len()) as u64,
+            };
             get_dispatcher()
-                .span_async(BxlEnsureArtifactsStart {}, async move {
+                .span_async(start, async move {
                     (
                         ensure_artifacts_inner(ctx, materialization_ctx, built, artifacts).await,
                         BxlEnsureArtifactsEnd {},
@@ -14,12 +14,16 @@ use serde::Serialize;
 pub enum NewGenericRequest {
     Materialize(MaterializeRequest),
     DebugEval(DebugEvalRequest),
+    PinReOutputs(PinReOutputsRequest),
+    FetchCells(FetchCellsRequest),
 }
 
 #[derive(Serialize, Deserialize)]
 pub enum NewGenericResponse {
     Materialize(MaterializeResponse),
     DebugEval(DebugEvalResponse),
+    PinReOutputs(PinReOutputsResponse),
+    FetchCells(FetchCellsResponse),
 }
 
 #[derive(Serialize, Deserialize)]
@@ -38,3 +42,48 @@ pub struct DebugEvalRequest {
 
 #[derive(Serialize, Deserialize)]
 pub struct DebugEvalResponse {}
+
+#[derive(Serialize, Deserialize)]
+pub struct PinReAction {
+    /// The action digest, as `HASH:SIZE`.
+    pub digest: String,
+    /// The RE use case the action ran with.
+    pub use_case: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PinReOutputsRequest {
+    /// Actions whose results, and the outputs of those results, to pin.
+    pub actions: Vec<PinReAction>,
+    /// CAS blobs to pin, as `HASH:SIZE`.
+    pub blobs: Vec<String>,
+    /// The RE use case to pin the blobs with.
+    pub use_case: String,
+    /// How long to extend the TTL of the blobs by, in seconds.
+    pub ttl_s: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PinReOutputsResponse {
+    pub actions_pinned: u64,
+    pub actions_missing: u64,
+    pub blobs_pinned: u64,
+    pub blobs_missing: u64,
+    /// Until when the pinned blobs were requested to be kept, as an RFC 3339 timestamp.
+    pub earliest_expiration: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct FetchCellsRequest {}
+
+#[derive(Serialize, Deserialize)]
+pub struct FetchedCell {
+    pub alias: String,
+    /// The root of the cell, relative to the project root.
+    pub path: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct FetchCellsResponse {
+    pub cells: Vec<FetchedCell>,
+}
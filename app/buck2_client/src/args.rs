@@ -41,6 +41,8 @@ enum ArgExpansionError {
     PythonExecutionFailed { source: io::Error, cmd: Command },
     #[error("Unable to read line from stdin")]
     StdinReadError { source: anyhow::Error },
+    #[error("Cyclic argfile inclusion: {cycle}")]
+    CyclicInclusion { cycle: String },
 }
 
 /// Log that a relative flag file was not found in CWD, but was found, and used, from the cell root
@@ -74,9 +76,51 @@ enum ArgFile {
     Stdin,
 }
 
+impl ArgFile {
+    fn path(&self) -> Option<&AbsNormPathBuf> {
+        match self {
+            ArgFile::PythonExecutable(path, _) | ArgFile::Path(path) => Some(path),
+            ArgFile::Stdin => None,
+        }
+    }
+}
+
+/// Whether a conditional section header in an argfile (e.g. `[windows]`) applies to the
+/// current host. Returns `None` if the line is not a recognised section header.
+fn section_applies(line: &str) -> Option<bool> {
+    match line.trim() {
+        "[all]" => Some(true),
+        "[windows]" => Some(cfg!(windows)),
+        "[unix]" => Some(cfg!(unix)),
+        "[linux]" => Some(cfg!(target_os = "linux")),
+        "[macos]" => Some(cfg!(target_os = "macos")),
+        _ => None,
+    }
+}
+
+/// Drops lines belonging to conditional sections that do not apply to the current host.
+/// Lines before the first section header, and after an `[all]` header, always apply.
+fn filter_conditional_sections(lines: Vec<String>) -> Vec<String> {
+    let mut enabled = true;
+    lines
+        .into_iter()
+        .filter(|line| match section_applies(line) {
+            Some(applies) => {
+                enabled = applies;
+                false
+            }
+            None => enabled,
+        })
+        .collect()
+}
+
 // Expands any argfiles passed as command line parameters. There are
 // two ways to do: `@argfile` or `--flagfile PATH`.
 //
+// Argfiles may themselves include other argfiles, and may contain
+// platform-conditional sections (`[windows]`, `[unix]`, `[linux]`, `[macos]`,
+// with `[all]` returning to unconditional lines). Cyclic inclusion is an error.
+//
 // Caveats:
 //  - `--` and `--flagfile` cannot be values of other options
 //  - `--flagfile=X` is _not_ supported, you need to pass
@@ -89,6 +133,14 @@ enum ArgFile {
 pub fn expand_argfiles_with_context(
     args: Vec<String>,
     context: &mut ImmediateConfigContext,
+) -> anyhow::Result<Vec<String>> {
+    expand_argfiles_with_stack(args, context, &mut Vec::new())
+}
+
+fn expand_argfiles_with_stack(
+    args: Vec<String>,
+    context: &mut ImmediateConfigContext,
+    stack: &mut Vec<AbsNormPathBuf>,
 ) -> anyhow::Result<Vec<String>> {
     let mut expanded_args = Vec::new();
     let mut arg_iterator = args.into_iter();
@@ -105,8 +157,7 @@ pub fn expand_argfiles_with_context(
                     Some(val) => val,
                     None => return Err(anyhow::anyhow!(ArgExpansionError::MissingFlagFilePath)),
                 };
-                // TODO: We want to detect cyclic inclusion
-                let expanded_flagfile_args = resolve_and_expand_argfile(&flagfile, context)?;
+                let expanded_flagfile_args = resolve_and_expand_argfile(&flagfile, context, stack)?;
                 expanded_args.extend(expanded_flagfile_args);
             }
             next_arg if next_arg.starts_with('@') => {
@@ -116,8 +167,7 @@ pub fn expand_argfiles_with_context(
                         ArgExpansionError::MissingFlagFilePathInArgfile
                     ));
                 }
-                // TODO: We want to detect cyclic inclusion
-                let expanded_flagfile_args = resolve_and_expand_argfile(flagfile, context)?;
+                let expanded_flagfile_args = resolve_and_expand_argfile(flagfile, context, stack)?;
                 expanded_args.extend(expanded_flagfile_args);
             }
             _ => expanded_args.push(next_arg),
@@ -132,11 +182,29 @@ pub fn expand_argfiles_with_context(
 fn resolve_and_expand_argfile(
     path: &str,
     context: &mut ImmediateConfigContext,
+    stack: &mut Vec<AbsNormPathBuf>,
 ) -> anyhow::Result<Vec<String>> {
     let flagfile = resolve_flagfile(path, context)
         .with_context(|| format!("Error resolving flagfile `{}`", path))?;
+    let flagfile_path = flagfile.path().cloned();
+    if let Some(flagfile_path) = &flagfile_path {
+        if let Some(start) = stack.iter().position(|p| p == flagfile_path) {
+            let cycle = stack[start..]
+                .iter()
+                .chain(std::iter::once(flagfile_path))
+                .map(|p| format!("`{}`", p.display()))
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(ArgExpansionError::CyclicInclusion { cycle }.into());
+        }
+        stack.push(flagfile_path.clone());
+    }
     let flagfile_lines = expand_argfile_contents(&flagfile)?;
-    expand_argfiles_with_context(flagfile_lines, context)
+    let expanded = expand_argfiles_with_stack(flagfile_lines, context, stack);
+    if flagfile_path.is_some() {
+        stack.pop();
+    }
+    expanded
 }
 
 fn expand_argfile_contents(flagfile: &ArgFile) -> anyhow::Result<Vec<String>> {
@@ -159,7 +227,7 @@ fn expand_argfile_contents(flagfile: &ArgFile) -> anyhow::Result<Vec<String>> {
                 }
                 lines.push(line);
             }
-            Ok(lines)
+            Ok(filter_conditional_sections(lines))
         }
         ArgFile::PythonExecutable(path, flag) => {
             let mut cmd = background_command(if is_open_source() {
@@ -302,4 +370,47 @@ mod tests {
             expand_argfiles_with_context(vec!["@bar/arg1.txt".to_owned()], &mut context).unwrap();
         assert_eq!(res, vec!["--magic".to_owned()]);
     }
+
+    #[test]
+    fn test_conditional_sections() {
+        let lines = ["a", "[windows]", "b", "[unix]", "c", "[all]", "d"]
+            .map(ToOwned::to_owned)
+            .to_vec();
+        let expected = if cfg!(windows) {
+            vec!["a", "b", "d"]
+        } else {
+            vec!["a", "c", "d"]
+        };
+        assert_eq!(expected, filter_conditional_sections(lines));
+    }
+
+    #[test]
+    fn test_cyclic_inclusion() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let root = AbsPath::new(tempdir.path()).unwrap();
+        fs_util::write(root.join("a.txt"), "--a\n@b.txt").unwrap();
+        fs_util::write(root.join("b.txt"), "--b\n@a.txt").unwrap();
+        fs_util::write(root.join(".buckconfig"), "[repositories]\nroot = .").unwrap();
+        let cwd =
+            WorkingDir::unchecked_new(AbsNormPathBuf::new(root.canonicalize().unwrap()).unwrap());
+        let mut context = ImmediateConfigContext::new(&cwd);
+        let err = expand_argfiles_with_context(vec!["@a.txt".to_owned()], &mut context)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Cyclic argfile inclusion"), "{}", err);
+    }
+
+    #[test]
+    fn test_diamond_inclusion_is_not_a_cycle() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let root = AbsPath::new(tempdir.path()).unwrap();
+        fs_util::write(root.join("a.txt"), "@common.txt\n@common.txt").unwrap();
+        fs_util::write(root.join("common.txt"), "--common").unwrap();
+        fs_util::write(root.join(".buckconfig"), "[repositories]\nroot = .").unwrap();
+        let cwd =
+            WorkingDir::unchecked_new(AbsNormPathBuf::new(root.canonicalize().unwrap()).unwrap());
+        let mut context = ImmediateConfigContext::new(&cwd);
+        let res = expand_argfiles_with_context(vec!["@a.txt".to_owned()], &mut context).unwrap();
+        assert_eq!(res, vec!["--common".to_owned(), "--common".to_owned()]);
+    }
 }
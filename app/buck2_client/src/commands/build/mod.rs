@@ -28,6 +28,7 @@ use buck2_client_ctx::common::CommonOutputOptions;
 use buck2_client_ctx::common::PrintOutputsFormat;
 use buck2_client_ctx::daemon::client::BuckdClientConnector;
 use buck2_client_ctx::daemon::client::NoPartialResultHandler;
+use buck2_client_ctx::exit_result::ExitCode;
 use buck2_client_ctx::exit_result::ExitResult;
 use buck2_client_ctx::final_console::FinalConsole;
 use buck2_client_ctx::output_destination_arg::OutputDestinationArg;
@@ -229,6 +230,13 @@ impl StreamingCommand for BuildCommand {
                     build_opts: Some(self.build_opts.to_proto()),
                     final_artifact_materializations: self.materializations.to_proto() as i32,
                     target_universe: self.target_universe,
+                    timeout: self
+                        .common_opts
+                        .event_log_opts
+                        .timeout()
+                        .map(prost_types::Duration::try_from)
+                        .transpose()
+                        .context("Invalid --timeout")?,
                     output_hashes_file: self
                         .output_hashes_file
                         .map(|p| {
@@ -300,6 +308,8 @@ impl StreamingCommand for BuildCommand {
             }
 
             ExitResult::success()
+        } else if response.timed_out {
+            ExitResult::status(ExitCode::Timeout)
         } else {
             ExitResult::from_errors(&response.errors)
         };
@@ -318,6 +328,10 @@ impl StreamingCommand for BuildCommand {
     fn common_opts(&self) -> &CommonBuildConfigurationOptions {
         &self.common_opts.config_opts
     }
+
+    fn daemon_handles_timeout(&self) -> bool {
+        true
+    }
 }
 
 pub(crate) fn print_build_succeeded(
@@ -0,0 +1,168 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_common::argv::Argv;
+use buck2_common::argv::SanitizedArgv;
+
+#[derive(Debug, Clone, Copy, clap::ArgEnum)]
+#[clap(rename_all = "snake_case")]
+enum Shell {
+    Bash,
+}
+
+/// Bash completion for buck2.
+///
+/// Target patterns containing a `:` are completed by asking the daemon for the targets in the
+/// package (`buck2 targets <package>:`). The query is bounded by `BUCK2_COMPLETION_TIMEOUT`
+/// seconds (default 2) so a cold daemon never blocks the shell, and results are cached per
+/// package for the lifetime of the shell session. Failed, timed out and empty queries are not
+/// cached, so they are retried on the next completion.
+const BASH_COMPLETION: &str = r#"# buck2 bash completion
+declare -gA __buck2_targets_cache
+
+# Stores the targets in package $1 in the cache. This must not run in a subshell (e.g. `$(...)`),
+# or the cache is lost.
+__buck2_cache_targets() {
+    local pkg="$1"
+    if [[ -z "${__buck2_targets_cache[$pkg]+set}" ]]; then
+        local targets
+        targets="$(timeout "${BUCK2_COMPLETION_TIMEOUT:-2}" \
+            buck2 targets "$pkg:" 2>/dev/null)" || return 1
+        [[ -n "$targets" ]] || return 1
+        __buck2_targets_cache[$pkg]="$targets"
+    fi
+}
+
+_buck2() {
+    local cur
+    if declare -F _get_comp_words_by_ref >/dev/null; then
+        _get_comp_words_by_ref -n : cur
+    else
+        cur="${COMP_WORDS[COMP_CWORD]}"
+    fi
+
+    if [[ "$COMP_CWORD" -eq 1 ]]; then
+        local cmds
+        cmds="$(buck2 --help 2>/dev/null | awk '/^SUBCOMMANDS:/ {s=1; next} s && /^    [a-z]/ {print $1}')"
+        COMPREPLY=($(compgen -W "$cmds" -- "$cur"))
+        return 0
+    fi
+
+    if [[ "$cur" == *:* ]]; then
+        local pkg="${cur%%:*}"
+        __buck2_cache_targets "$pkg" || return 0
+        local targets="${__buck2_targets_cache[$pkg]}"
+        if [[ "$pkg" == //* ]]; then
+            # `buck2 targets` prints fully qualified labels; match the cell-relative form typed.
+            targets="$(printf '%s\n' "$targets" | sed -e 's|^[^/]*//|//|')"
+        fi
+        COMPREPLY=($(compgen -W "$targets" -- "$cur"))
+        if declare -F __ltrim_colon_completions >/dev/null; then
+            __ltrim_colon_completions "$cur"
+        fi
+        return 0
+    fi
+
+    return 0
+}
+
+complete -o default -F _buck2 buck2
+"#;
+
+#[derive(Debug, clap::Parser)]
+#[clap(
+    about = "Print a shell completion script",
+    long_about = "Print a shell completion script.\n\n\
+        To enable completion in the current bash session, run `source <(buck2 completion bash)`."
+)]
+pub struct CompletionCommand {
+    #[clap(arg_enum, help = "The shell to print the completion script for")]
+    shell: Shell,
+}
+
+impl CompletionCommand {
+    pub fn exec(
+        self,
+        _matches: &clap::ArgMatches,
+        _ctx: ClientCommandContext<'_>,
+    ) -> anyhow::Result<()> {
+        let script = match self.shell {
+            Shell::Bash => BASH_COMPLETION,
+        };
+        buck2_client_ctx::print!("{}", script)?;
+        Ok(())
+    }
+
+    pub fn sanitize_argv(&self, argv: Argv) -> SanitizedArgv {
+        argv.no_need_to_sanitize()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+    use std::process::Command;
+
+    use super::*;
+
+    /// Completes targets in `//foo` three times against a fake `buck2` that fails, then prints
+    /// `stub_output`, then fails again.
+    fn query_targets_three_times(stub_output: &str) -> anyhow::Result<String> {
+        let dir = tempfile::tempdir()?;
+        let stub = dir.path().join("buck2");
+        std::fs::write(
+            &stub,
+            format!(
+                "#!/bin/sh\n\
+                 n=$(cat \"$0.calls\" 2>/dev/null || echo 0)\n\
+                 echo $((n + 1)) > \"$0.calls\"\n\
+                 [ \"$n\" = 1 ] || exit 1\n\
+                 printf '{}'\n",
+                stub_output
+            ),
+        )?;
+        std::fs::set_permissions(&stub, std::fs::Permissions::from_mode(0o755))?;
+
+        let script = format!(
+            "{}\n\
+             for i in 1 2 3; do\n\
+                 __buck2_cache_targets //foo\n\
+                 echo \"$i: ${{__buck2_targets_cache[//foo]}}\"\n\
+             done\n",
+            BASH_COMPLETION
+        );
+        let path = format!(
+            "{}:{}",
+            dir.path().display(),
+            std::env::var("PATH").unwrap_or_default()
+        );
+        let output = Command::new("bash")
+            .arg("-c")
+            .arg(script)
+            .env("PATH", path)
+            .output()?;
+        Ok(String::from_utf8(output.stdout)?)
+    }
+
+    #[test]
+    fn test_failed_queries_are_retried_and_results_cached() -> anyhow::Result<()> {
+        assert_eq!(
+            query_targets_three_times("root//foo:bar\\n")?,
+            "1: \n2: root//foo:bar\n3: root//foo:bar\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_results_are_not_cached() -> anyhow::Result<()> {
+        assert_eq!(query_targets_three_times("")?, "1: \n2: \n3: \n");
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_event_log::file_names::find_log_by_trace_id;
+use buck2_event_log::read::EventLogPathBuf;
+use buck2_event_log::stream_value::StreamValue;
+use buck2_event_observer::display;
+use buck2_event_observer::display::TargetDisplayOptions;
+use buck2_wrapper_common::invocation_id::TraceId;
+use futures::TryStreamExt;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+enum ActionDiffError {
+    #[error("Log not found locally by trace id `{0}`")]
+    LogNotFound(TraceId),
+    #[error(
+        "No action details in the event log of `{0}`, run the build with `-c buck2.log_action_details=true`"
+    )]
+    NoActionDetails(String),
+}
+
+/// Shows how the actions of two builds differ: their arguments, environment variables and
+/// inputs.
+///
+/// Only actions which ran in a build are in its event log, so this is mostly useful to compare
+/// an action that ran again with the previous time it ran. Both builds must have been run with
+/// `-c buck2.log_action_details=true`.
+#[derive(Debug, clap::Parser)]
+pub struct ActionDiffCommand {
+    /// Trace id, or path to the event log, of the first build.
+    #[clap(long, value_name = "TRACE_ID_OR_PATH")]
+    base: String,
+
+    /// Trace id, or path to the event log, of the second build.
+    #[clap(long, value_name = "TRACE_ID_OR_PATH")]
+    target: String,
+
+    /// Only compare actions whose identity, as shown by `buck2 log what-ran`, contains this
+    /// string, such as a target label.
+    #[clap(value_name = "SUBSTRING")]
+    action: Option<String>,
+}
+
+/// The command of an action, as logged.
+#[derive(PartialEq)]
+struct ActionDetails {
+    argv: Vec<String>,
+    env: BTreeMap<String, String>,
+    inputs: BTreeMap<String, String>,
+}
+
+impl ActionDiffCommand {
+    pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
+        let Self {
+            base,
+            target,
+            action,
+        } = self;
+        let (base_actions, target_actions) = ctx.with_runtime(async move |ctx| {
+            let base_actions = read_actions(&ctx, &base, action.as_deref()).await?;
+            let target_actions = read_actions(&ctx, &target, action.as_deref()).await?;
+            anyhow::Ok((base_actions, target_actions))
+        })?;
+
+        let identities: BTreeSet<&String> =
+            base_actions.keys().chain(target_actions.keys()).collect();
+        let mut unchanged = 0;
+        for identity in identities {
+            match (base_actions.get(identity), target_actions.get(identity)) {
+                (Some(_), None) => buck2_client_ctx::println!("- {}", identity)?,
+                (None, Some(_)) => buck2_client_ctx::println!("+ {}", identity)?,
+                (Some(base), Some(target)) if base == target => unchanged += 1,
+                (Some(base), Some(target)) => {
+                    buck2_client_ctx::println!("~ {}", identity)?;
+                    print_diff(base, target)?;
+                }
+                (None, None) => {}
+            }
+        }
+        buck2_client_ctx::eprintln!("{} action(s) unchanged", unchanged)?;
+        ExitResult::success()
+    }
+}
+
+fn print_diff(base: &ActionDetails, target: &ActionDetails) -> anyhow::Result<()> {
+    if base.argv != target.argv {
+        buck2_client_ctx::println!("  args:")?;
+        // Arguments are compared as sets, which is what matters for most changes to flags.
+        let base_args: BTreeSet<&String> = base.argv.iter().collect();
+        let target_args: BTreeSet<&String> = target.argv.iter().collect();
+        for arg in &base.argv {
+            if !target_args.contains(arg) {
+                buck2_client_ctx::println!("    - {}", arg)?;
+            }
+        }
+        for arg in &target.argv {
+            if !base_args.contains(arg) {
+                buck2_client_ctx::println!("    + {}", arg)?;
+            }
+        }
+        if base_args == target_args {
+            buck2_client_ctx::println!("    (reordered)")?;
+        }
+    }
+    print_map_diff("env", &base.env, &target.env)?;
+    print_map_diff("inputs", &base.inputs, &target.inputs)?;
+    Ok(())
+}
+
+fn print_map_diff(
+    title: &str,
+    base: &BTreeMap<String, String>,
+    target: &BTreeMap<String, String>,
+) -> anyhow::Result<()> {
+    if base == target {
+        return Ok(());
+    }
+    buck2_client_ctx::println!("  {}:", title)?;
+    let keys: BTreeSet<&String> = base.keys().chain(target.keys()).collect();
+    for key in keys {
+        match (base.get(key), target.get(key)) {
+            (Some(b), None) => buck2_client_ctx::println!("    - {}={}", key, b)?,
+            (None, Some(t)) => buck2_client_ctx::println!("    + {}={}", key, t)?,
+            (Some(b), Some(t)) if b != t => {
+                buck2_client_ctx::println!("    ~ {}: {} -> {}", key, b, t)?
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// The actions of a build, by identity. If an action ran more than once, the last run is kept.
+async fn read_actions(
+    ctx: &ClientCommandContext<'_>,
+    log: &str,
+    action_filter: Option<&str>,
+) -> anyhow::Result<BTreeMap<String, ActionDetails>> {
+    let log_path = match log.parse::<TraceId>() {
+        Ok(trace_id) => find_log_by_trace_id(&ctx.paths()?.log_dir(), &trace_id)?
+            .ok_or(ActionDiffError::LogNotFound(trace_id))?,
+        Err(_) => EventLogPathBuf::infer(ctx.working_dir.resolve(Path::new(log)))?,
+    };
+    let (_invocation, mut events) = log_path.unpack_stream().await?;
+
+    let mut any_details = false;
+    let mut actions = BTreeMap::new();
+    while let Some(event) = events.try_next().await? {
+        let event = match event {
+            StreamValue::Event(event) => event,
+            StreamValue::Result(..) | StreamValue::PartialResult(..) => continue,
+        };
+        let details = match event.data {
+            Some(buck2_data::buck_event::Data::Instant(buck2_data::InstantEvent {
+                data: Some(buck2_data::instant_event::Data::ActionCommandDetails(details)),
+            })) => details,
+            _ => continue,
+        };
+        any_details = true;
+
+        let identity = display::display_action_identity(
+            details.key.as_ref(),
+            details.name.as_ref(),
+            TargetDisplayOptions::for_log(),
+        )?;
+        if action_filter.map_or(false, |filter| !identity.contains(filter)) {
+            continue;
+        }
+        actions.insert(
+            identity,
+            ActionDetails {
+                argv: details.argv,
+                env: details.env.into_iter().map(|e| (e.key, e.value)).collect(),
+                inputs: details
+                    .inputs
+                    .into_iter()
+                    .map(|i| (i.path, i.digest))
+                    .collect(),
+            },
+        );
+    }
+
+    if !any_details {
+        return Err(ActionDiffError::NoActionDetails(log.to_owned()).into());
+    }
+    Ok(actions)
+}
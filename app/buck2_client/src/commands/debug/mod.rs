@@ -22,20 +22,26 @@ use heap_dump::HeapDumpCommand;
 use internal_version::InternalVersionCommand;
 use materialize::MaterializeCommand;
 
+use crate::commands::debug::action_diff::ActionDiffCommand;
 use crate::commands::debug::allocative::AllocativeCommand;
 use crate::commands::debug::daemon_dir::DaemonDirCommand;
 use crate::commands::debug::eval::EvalCommand;
 use crate::commands::debug::exe::ExeCommand;
 use crate::commands::debug::log_perf::LogPerfCommand;
+use crate::commands::debug::net_check::NetCheckCommand;
 use crate::commands::debug::paranoid::ParanoidCommand;
 use crate::commands::debug::persist_event_logs::PersistEventLogsCommand;
+use crate::commands::debug::pin_re_outputs::PinReOutputsCommand;
+use crate::commands::debug::replay_action::ReplayActionCommand;
 use crate::commands::debug::segfault::SegfaultCommand;
 use crate::commands::debug::set_log_filter::SetLogFilterCommand;
 use crate::commands::debug::trace_io::TraceIoCommand;
+use crate::commands::debug::upload_diagnostics::UploadDiagnosticsCommand;
 use crate::commands::debug::upload_re_logs::UploadReLogsCommand;
 use crate::commands::log::debug_replay::DebugReplayCommand;
 use crate::commands::log::debug_what_ran::DebugWhatRanCommand;
 
+mod action_diff;
 mod allocative;
 mod allocator_stats;
 mod chrome_trace;
@@ -50,11 +56,15 @@ mod heap_dump;
 mod internal_version;
 mod log_perf;
 mod materialize;
+mod net_check;
 mod paranoid;
 mod persist_event_logs;
+mod pin_re_outputs;
+mod replay_action;
 mod segfault;
 mod set_log_filter;
 mod trace_io;
+mod upload_diagnostics;
 pub(crate) mod upload_re_logs;
 
 #[derive(Debug, clap::Parser)]
@@ -83,6 +93,7 @@ pub enum DebugCommand {
     Materialize(MaterializeCommand),
     // Upload RE logs given an RE session ID
     UploadReLogs(UploadReLogsCommand),
+    UploadDiagnostics(UploadDiagnosticsCommand),
     /// Validates that Buck2 and disk agree on the state of files.
     FileStatus(FileStatusCommand),
     /// Shows the commands that buck ran
@@ -103,6 +114,10 @@ pub enum DebugCommand {
     #[clap(subcommand)]
     Paranoid(ParanoidCommand),
     Eval(EvalCommand),
+    NetCheck(NetCheckCommand),
+    ReplayAction(ReplayActionCommand),
+    ActionDiff(ActionDiffCommand),
+    PinReOutputs(PinReOutputsCommand),
 }
 
 impl DebugCommand {
@@ -121,6 +136,7 @@ impl DebugCommand {
             DebugCommand::WhatRan(cmd) => cmd.exec(matches, ctx),
             DebugCommand::Materialize(cmd) => cmd.exec(matches, ctx),
             DebugCommand::UploadReLogs(cmd) => cmd.exec(matches, ctx),
+            DebugCommand::UploadDiagnostics(cmd) => cmd.exec(matches, ctx),
             DebugCommand::DaemonDir(cmd) => cmd.exec(matches, ctx),
             DebugCommand::Exe(cmd) => cmd.exec(matches, ctx),
             DebugCommand::Allocative(cmd) => cmd.exec(matches, ctx),
@@ -131,6 +147,10 @@ impl DebugCommand {
             DebugCommand::PersistEventLogs(cmd) => cmd.exec(matches, ctx),
             DebugCommand::Paranoid(cmd) => cmd.exec(matches, ctx),
             DebugCommand::Eval(cmd) => cmd.exec(matches, ctx),
+            DebugCommand::NetCheck(cmd) => cmd.exec(matches, ctx),
+            DebugCommand::ReplayAction(cmd) => cmd.exec(matches, ctx),
+            DebugCommand::ActionDiff(cmd) => cmd.exec(matches, ctx),
+            DebugCommand::PinReOutputs(cmd) => cmd.exec(matches, ctx),
         }
     }
 
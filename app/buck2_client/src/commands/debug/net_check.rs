@@ -0,0 +1,67 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::time::Duration;
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_http::HttpClientBuilder;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Validate network settings (proxy and CA bundle) and check connectivity.
+///
+/// Uses the same `http.*` buckconfigs and proxy environment variables as the daemon.
+#[derive(Debug, clap::Parser)]
+pub struct NetCheckCommand {
+    /// URLs to send a HEAD request to through the configured proxy.
+    #[clap(value_name = "URL")]
+    urls: Vec<String>,
+}
+
+impl NetCheckCommand {
+    pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
+        let http_config = ctx.immediate_config.daemon_startup_config()?.http.clone();
+        ctx.with_runtime(async move |_ctx| {
+            let proxy_config = http_config.proxy_config().with_env_fallback()?;
+            let display = |v: &Option<String>| v.clone().unwrap_or_else(|| "<none>".to_owned());
+            buck2_client_ctx::println!("https_proxy: {}", display(&proxy_config.https_proxy))?;
+            buck2_client_ctx::println!("http_proxy: {}", display(&proxy_config.http_proxy))?;
+            buck2_client_ctx::println!("no_proxy: {}", display(&proxy_config.no_proxy))?;
+            buck2_client_ctx::println!("ca_bundle: {}", display(&http_config.ca_bundle))?;
+
+            let mut builder = HttpClientBuilder::oss(&proxy_config)?;
+            if let Some(ca_bundle) = &http_config.ca_bundle {
+                builder.with_ca_bundle(ca_bundle)?;
+            }
+            builder.with_connect_timeout(Some(CONNECT_TIMEOUT));
+            let client = builder.build();
+            buck2_client_ctx::println!("settings: ok")?;
+
+            let mut failed = false;
+            for url in &self.urls {
+                match client.head(url).await {
+                    Ok(response) => {
+                        buck2_client_ctx::println!("{}: HTTP {}", url, response.status())?;
+                    }
+                    Err(e) => {
+                        failed = true;
+                        buck2_client_ctx::println!("{}: {:#}", url, anyhow::Error::from(e))?;
+                    }
+                }
+            }
+
+            if failed {
+                ExitResult::bail("Some URLs could not be reached")
+            } else {
+                ExitResult::success()
+            }
+        })
+    }
+}
@@ -21,6 +21,8 @@ use prost::Message;
 /// interacts with. In particular, Buck2 will always race local and remote execution (including
 /// cache queries), eagerly download outputs, and not cancel local executions until it successfully
 /// downloads outputs from RE.
+///
+/// Paranoid mode can also be enabled permanently, and tuned, in the `[paranoid]` buckconfig section.
 #[derive(Debug, clap::Parser)]
 pub enum ParanoidCommand {
     Enable(EnableParanoidCommand),
@@ -0,0 +1,257 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use buck2_cli_proto::new_generic::NewGenericRequest;
+use buck2_cli_proto::new_generic::NewGenericResponse;
+use buck2_cli_proto::new_generic::PinReAction;
+use buck2_cli_proto::new_generic::PinReOutputsRequest;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::CommonBuildConfigurationOptions;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_client_ctx::common::CommonConsoleOptions;
+use buck2_client_ctx::common::CommonDaemonCommandOptions;
+use buck2_client_ctx::daemon::client::BuckdClientConnector;
+use buck2_client_ctx::exit_result::ExitCode;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::path_arg::PathArg;
+use buck2_client_ctx::streaming::StreamingCommand;
+use buck2_data::command_execution_kind::Command;
+use buck2_event_log::stream_value::StreamValue;
+use futures::TryStreamExt;
+use thiserror::Error;
+
+use crate::commands::log::options::EventLogOptions;
+
+#[derive(Debug, Error)]
+enum PinReOutputsError {
+    #[error(
+        "Nothing to pin: no action ran on RE or hit the action cache in the selected invocation"
+    )]
+    NoRemoteActions,
+    #[error(
+        "Nothing to pin: no file artifact digests in `{0}`, was it written with `-c build_report.include_artifacts=true`?"
+    )]
+    NoArtifacts(String),
+    #[error("Unexpected response from the daemon")]
+    UnexpectedResponse,
+}
+
+/// Extends the TTL of the outputs of a build in RE, so that release artifacts can still be
+/// fetched from the CAS and the action cache weeks later.
+///
+/// The actions of a build are read from its event log: their results, and the blobs those
+/// reference, including the files of output directories, are pinned. Alternatively, the file
+/// artifacts of a build report written with `-c build_report.include_artifacts=true` are pinned.
+/// Run this periodically for as long as the build must be kept.
+#[derive(Debug, clap::Parser)]
+pub struct PinReOutputsCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(flatten)]
+    event_log: EventLogOptions,
+
+    /// Pin the artifacts of this build report rather than the actions of an event log.
+    #[clap(long, value_name = "PATH", conflicts_with = "event_log")]
+    build_report: Option<PathArg>,
+
+    /// The RE use case to pin artifacts of a build report with, and actions whose use case is
+    /// not in the event log.
+    #[clap(long, default_value = "buck2-default")]
+    use_case: String,
+
+    /// How long to extend the TTL of the outputs by.
+    #[clap(long, default_value = "30days")]
+    ttl: humantime::Duration,
+}
+
+#[async_trait]
+impl StreamingCommand for PinReOutputsCommand {
+    const COMMAND_NAME: &'static str = "pin-re-outputs";
+
+    async fn exec_impl(
+        self,
+        buckd: &mut BuckdClientConnector,
+        matches: &clap::ArgMatches,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        let ttl_s = Duration::from(self.ttl).as_secs();
+        let req = match &self.build_report {
+            Some(build_report) => {
+                let digests = read_build_report_digests(build_report, ctx)?;
+                if digests.directories > 0 {
+                    buck2_client_ctx::eprintln!(
+                        "Skipping {} directory artifact(s): their files can only be pinned from the event log of the build",
+                        digests.directories
+                    )?;
+                }
+                PinReOutputsRequest {
+                    actions: Vec::new(),
+                    blobs: digests.files,
+                    use_case: self.use_case.clone(),
+                    ttl_s,
+                }
+            }
+            None => PinReOutputsRequest {
+                actions: read_remote_actions(&self.event_log, &self.use_case, ctx).await?,
+                blobs: Vec::new(),
+                use_case: self.use_case.clone(),
+                ttl_s,
+            },
+        };
+
+        let context = ctx.client_context(matches, &self)?;
+        let resp = buckd
+            .with_flushing()
+            .new_generic(
+                context,
+                NewGenericRequest::PinReOutputs(req),
+                ctx.stdin()
+                    .console_interaction_stream(&self.common_opts.console_opts),
+            )
+            .await??;
+        let NewGenericResponse::PinReOutputs(resp) = resp else {
+            return ExitResult::err(PinReOutputsError::UnexpectedResponse.into());
+        };
+
+        buck2_client_ctx::println!(
+            "Pinned {} action result(s) and {} blob(s)",
+            resp.actions_pinned,
+            resp.blobs_pinned
+        )?;
+        if let Some(earliest_expiration) = &resp.earliest_expiration {
+            buck2_client_ctx::println!("Pinned blobs are kept until {}", earliest_expiration)?;
+        }
+        if resp.actions_missing > 0 || resp.blobs_missing > 0 {
+            buck2_client_ctx::eprintln!(
+                "{} action result(s) and {} blob(s) were no longer in RE",
+                resp.actions_missing,
+                resp.blobs_missing
+            )?;
+            return ExitResult::status(ExitCode::UnknownFailure);
+        }
+        ExitResult::success()
+    }
+
+    fn console_opts(&self) -> &CommonConsoleOptions {
+        &self.common_opts.console_opts
+    }
+
+    fn event_log_opts(&self) -> &CommonDaemonCommandOptions {
+        &self.common_opts.event_log_opts
+    }
+
+    fn common_opts(&self) -> &CommonBuildConfigurationOptions {
+        &self.common_opts.config_opts
+    }
+}
+
+/// The actions of an invocation which ran on RE or hit the action cache, with their use case.
+async fn read_remote_actions(
+    event_log: &EventLogOptions,
+    default_use_case: &str,
+    ctx: &ClientCommandContext<'_>,
+) -> anyhow::Result<Vec<PinReAction>> {
+    let log_path = event_log.get(ctx).await?;
+    let (_invocation, mut events) = log_path.unpack_stream().await?;
+
+    // By digest, since the same action can appear more than once.
+    let mut actions = BTreeMap::new();
+    while let Some(event) = events.try_next().await? {
+        let event = match event {
+            StreamValue::Event(event) => event,
+            StreamValue::Result(..) | StreamValue::PartialResult(..) => continue,
+        };
+        let action = match event.data {
+            Some(buck2_data::buck_event::Data::SpanEnd(buck2_data::SpanEndEvent {
+                data: Some(buck2_data::span_end_event::Data::ActionExecution(action)),
+                ..
+            })) => action,
+            _ => continue,
+        };
+        for command in action.commands {
+            let command = command
+                .details
+                .and_then(|d| d.command_kind)
+                .and_then(|k| k.command);
+            if let Some(Command::RemoteCommand(remote)) = command {
+                let use_case = remote
+                    .details
+                    .map(|d| d.use_case)
+                    .filter(|use_case| !use_case.is_empty())
+                    .unwrap_or_else(|| default_use_case.to_owned());
+                actions.insert(remote.action_digest, use_case);
+            }
+        }
+    }
+
+    if actions.is_empty() {
+        return Err(PinReOutputsError::NoRemoteActions.into());
+    }
+    Ok(actions
+        .into_iter()
+        .map(|(digest, use_case)| PinReAction { digest, use_case })
+        .collect())
+}
+
+struct BuildReportDigests {
+    files: Vec<String>,
+    /// Number of directory artifacts. Their digest is not that of a blob in the CAS, so the files
+    /// in them can't be found.
+    directories: usize,
+}
+
+/// The digests of the file artifacts of a build report.
+fn read_build_report_digests(
+    build_report: &PathArg,
+    ctx: &ClientCommandContext<'_>,
+) -> anyhow::Result<BuildReportDigests> {
+    let path = build_report.resolve(&ctx.working_dir);
+    let report: serde_json::Value = serde_json::from_slice(&std::fs::read(&path)?)?;
+
+    let mut digests = Vec::new();
+    let mut directories = 0;
+    let entries = report["results"]
+        .as_object()
+        .into_iter()
+        .flat_map(|r| r.values());
+    for entry in entries {
+        let configured = entry["configured"]
+            .as_object()
+            .into_iter()
+            .flat_map(|c| c.values());
+        for configured in configured {
+            let artifacts = configured["artifacts"]
+                .as_object()
+                .into_iter()
+                .flat_map(|a| a.values());
+            for artifact in artifacts {
+                if artifact["is_directory"].as_bool() == Some(true) {
+                    directories += 1;
+                } else if let Some(digest) = artifact["digest"].as_str() {
+                    digests.push(digest.to_owned());
+                }
+            }
+        }
+    }
+
+    if digests.is_empty() {
+        return Err(PinReOutputsError::NoArtifacts(path.display().to_string()).into());
+    }
+    digests.sort();
+    digests.dedup();
+    Ok(BuildReportDigests {
+        files: digests,
+        directories,
+    })
+}
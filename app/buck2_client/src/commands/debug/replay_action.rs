@@ -0,0 +1,168 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_data::command_execution_kind::Command;
+use buck2_event_log::stream_value::StreamValue;
+use buck2_event_observer::display;
+use buck2_event_observer::display::TargetDisplayOptions;
+use buck2_event_observer::what_ran::command_to_string;
+use futures::TryStreamExt;
+use thiserror::Error;
+
+use crate::commands::log::options::EventLogOptions;
+
+#[derive(Debug, Error)]
+enum ReplayActionError {
+    #[error("No failed action found in the selected invocation")]
+    NoFailedAction,
+    #[error("No action matching `{0}` found in the selected invocation")]
+    NoMatchingAction(String),
+    #[error(
+        "`{0}` ran on RE, so its command is not in the event log. Download it with: frecli cas download-action {1}"
+    )]
+    RemoteCommand(String, String),
+    #[error("`{0}` did not run a command that can be replayed")]
+    NoCommand(String),
+}
+
+/// Prints or re-runs the command of an action from a previous invocation.
+///
+/// By default, this picks the last action that failed. The command runs from the project root,
+/// with the environment variables that were set for the action. Its inputs are whatever is
+/// currently in `buck-out`, so rebuild them first if they may have changed.
+#[derive(Debug, clap::Parser)]
+pub struct ReplayActionCommand {
+    #[clap(flatten)]
+    event_log: EventLogOptions,
+
+    /// Pick the last action whose identity, as shown by `buck2 log what-ran`, contains this
+    /// string, rather than the last failed action.
+    #[clap(long, value_name = "SUBSTRING")]
+    action: Option<String>,
+
+    /// Run the command instead of printing it.
+    #[clap(long)]
+    run: bool,
+}
+
+impl ReplayActionCommand {
+    pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
+        let Self {
+            event_log,
+            action,
+            run,
+        } = self;
+        let project_root = ctx.paths()?.project_root().root().as_abs_path().to_owned();
+
+        let (identity, command) = ctx.with_runtime(async move |ctx| {
+            find_command(&ctx, &event_log, action.as_deref()).await
+        })?;
+
+        let (argv, env) = match command {
+            Command::LocalCommand(c) => (c.argv, c.env),
+            Command::WorkerCommand(c) => {
+                let mut argv = c.fallback_exe;
+                argv.extend(c.argv);
+                (argv, c.env)
+            }
+            Command::RemoteCommand(c) => {
+                return ExitResult::err(
+                    ReplayActionError::RemoteCommand(identity, c.action_digest).into(),
+                );
+            }
+            Command::OmittedLocalCommand(..) | Command::WorkerInitCommand(..) => {
+                return ExitResult::err(ReplayActionError::NoCommand(identity).into());
+            }
+        };
+
+        buck2_client_ctx::eprintln!("Replaying: {}", identity)?;
+
+        if !run {
+            buck2_client_ctx::println!(
+                "cd {} && {}",
+                shlex::quote(&project_root.to_string()),
+                command_to_string(&buck2_data::LocalCommand {
+                    argv,
+                    env,
+                    ..Default::default()
+                })
+            )?;
+            return ExitResult::success();
+        }
+
+        if argv.is_empty() {
+            return ExitResult::err(ReplayActionError::NoCommand(identity).into());
+        }
+
+        ExitResult::exec(
+            argv[0].clone(),
+            argv,
+            Some(project_root),
+            env.into_iter().map(|e| (e.key, e.value)).collect(),
+        )
+    }
+}
+
+/// Find the last action matching `action`, or the last failed action, and the last command it
+/// ran.
+async fn find_command(
+    ctx: &ClientCommandContext<'_>,
+    event_log: &EventLogOptions,
+    action_filter: Option<&str>,
+) -> anyhow::Result<(String, Command)> {
+    let log_path = event_log.get(ctx).await?;
+    let (_invocation, mut events) = log_path.unpack_stream().await?;
+
+    let mut found = None;
+    while let Some(event) = events.try_next().await? {
+        let event = match event {
+            StreamValue::Event(event) => event,
+            StreamValue::Result(..) | StreamValue::PartialResult(..) => continue,
+        };
+        let action = match &event.data {
+            Some(buck2_data::buck_event::Data::SpanEnd(buck2_data::SpanEndEvent {
+                data: Some(buck2_data::span_end_event::Data::ActionExecution(action)),
+                ..
+            })) => action,
+            _ => continue,
+        };
+
+        let identity = display::display_action_identity(
+            action.key.as_ref(),
+            action.name.as_ref(),
+            TargetDisplayOptions::for_log(),
+        )?;
+        let matches = match action_filter {
+            Some(action_filter) => identity.contains(action_filter),
+            None => action.failed,
+        };
+        if matches {
+            let command = action
+                .commands
+                .last()
+                .and_then(|c| c.details.as_ref())
+                .and_then(|d| d.command_kind.as_ref())
+                .and_then(|k| k.command.clone());
+            found = Some((identity, command));
+        }
+    }
+
+    match found {
+        Some((identity, Some(command))) => Ok((identity, command)),
+        Some((identity, None)) => Err(ReplayActionError::NoCommand(identity).into()),
+        None => Err(match action_filter {
+            Some(action_filter) => {
+                ReplayActionError::NoMatchingAction(action_filter.to_owned()).into()
+            }
+            None => ReplayActionError::NoFailedAction.into(),
+        }),
+    }
+}
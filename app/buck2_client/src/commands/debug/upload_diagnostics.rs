@@ -0,0 +1,77 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::diagnostics::DiagnosticArtifact;
+use buck2_client_ctx::diagnostics::DiagnosticContents;
+use buck2_client_ctx::diagnostics::DiagnosticKind;
+use buck2_client_ctx::diagnostics::DiagnosticsDestination;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::manifold::Bucket;
+use buck2_client_ctx::manifold::ManifoldClient;
+use buck2_client_ctx::path_arg::PathArg;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+
+/// Uploads a diagnostic artifact, such as a heap dump, or archives it in a local directory.
+///
+/// The artifact is named and kept for as long as the artifacts of the same kind collected by
+/// `buck2 rage`.
+#[derive(Debug, clap::Parser)]
+pub struct UploadDiagnosticsCommand {
+    /// The kind of the artifact.
+    #[clap(long, arg_enum)]
+    kind: DiagnosticKind,
+
+    /// What the artifact is about, such as a trace id or an RE session id.
+    #[clap(long)]
+    id: String,
+
+    /// Archive the artifact in a subdirectory of this directory named after its kind, rather than
+    /// uploading it. Artifacts of the same kind which are older than its retention are removed.
+    #[clap(long, value_name = "DIR")]
+    output_dir: Option<PathArg>,
+
+    #[clap(long)]
+    allow_vpnless: bool,
+
+    /// The file or directory to upload. Directories are uploaded as a tarball.
+    #[clap(value_name = "PATH")]
+    path: PathArg,
+}
+
+impl UploadDiagnosticsCommand {
+    pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
+        let path = self.path.resolve(&ctx.working_dir);
+        let contents = if path.is_dir() {
+            DiagnosticContents::Directory(path)
+        } else {
+            DiagnosticContents::File(path)
+        };
+        let artifact = DiagnosticArtifact::new(self.kind, self.id, contents);
+
+        let destination = match self.output_dir {
+            Some(output_dir) => DiagnosticsDestination::Directory(AbsNormPathBuf::new(
+                output_dir.resolve(&ctx.working_dir).into_path_buf(),
+            )?),
+            None => {
+                buck2_core::facebook_only();
+                DiagnosticsDestination::Manifold {
+                    client: ManifoldClient::new(self.allow_vpnless)?,
+                    bucket: Bucket::RAGE_DUMPS,
+                }
+            }
+        };
+
+        ctx.with_runtime(async move |_ctx| {
+            let location = destination.store(&artifact).await?;
+            buck2_client_ctx::println!("{}", location)?;
+            ExitResult::success()
+        })
+    }
+}
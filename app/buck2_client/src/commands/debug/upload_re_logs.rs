@@ -7,15 +7,15 @@
  * of this source tree.
  */
 
-use async_compression::tokio::bufread::ZstdEncoder;
 use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::diagnostics::DiagnosticArtifact;
+use buck2_client_ctx::diagnostics::DiagnosticKind;
+use buck2_client_ctx::diagnostics::DiagnosticsDestination;
 use buck2_client_ctx::exit_result::ExitResult;
 use buck2_client_ctx::manifold::Bucket;
 use buck2_client_ctx::manifold::ManifoldClient;
-use buck2_core::fs::async_fs_util;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
-use tokio::io::BufReader;
 
 #[derive(Debug, clap::Parser)]
 #[clap(about = "upload RE logs")]
@@ -33,38 +33,31 @@ impl UploadReLogsCommand {
 
         // TODO: This should receive the path from the caller.
         ctx.with_runtime(async move |ctx| {
-            let manifold = ManifoldClient::new(self.allow_vpnless)?;
-            let re_logs_dir = ctx.paths()?.re_logs_dir();
-            upload_re_logs(
-                &manifold,
-                Bucket::RE_LOGS,
-                &re_logs_dir,
-                &self.session_id,
-                &format!("flat/{}.log.zst", &self.session_id),
-            )
-            .await?;
+            let destination = DiagnosticsDestination::Manifold {
+                client: ManifoldClient::new(self.allow_vpnless)?,
+                bucket: Bucket::RE_LOGS,
+            };
+            let artifact = re_logs_artifact(&ctx.paths()?.re_logs_dir(), &self.session_id)?;
+            // The RE logs bucket is keyed by session id alone.
+            destination
+                .store_as(&artifact, &format!("{}.log.zst", &self.session_id))
+                .await?;
             ExitResult::success()
         })
     }
 }
 
-pub(crate) async fn upload_re_logs(
-    manifold: &ManifoldClient,
-    bucket: Bucket,
+/// The logs of the RE client for a session.
+pub(crate) fn re_logs_artifact(
     re_logs_dir: &AbsNormPath,
     session_id: &str,
-    bucket_path: &str,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<DiagnosticArtifact> {
     let logs_path = re_logs_dir
         .join(ForwardRelativePath::new(session_id)?)
         .join(ForwardRelativePath::new("REClientFolly.log")?);
-    let file = async_fs_util::open(&logs_path).await?;
-    let mut encoder =
-        ZstdEncoder::with_quality(BufReader::new(file), async_compression::Level::Default);
-
-    manifold
-        .read_and_upload(bucket, bucket_path, Default::default(), &mut encoder)
-        .await?;
-
-    Ok(())
+    Ok(DiagnosticArtifact::file(
+        DiagnosticKind::ReLogs,
+        session_id,
+        logs_path.as_abs_path(),
+    ))
 }
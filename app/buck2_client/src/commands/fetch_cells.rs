@@ -0,0 +1,101 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use async_trait::async_trait;
+use buck2_cli_proto::new_generic::FetchCellsRequest;
+use buck2_cli_proto::new_generic::NewGenericRequest;
+use buck2_cli_proto::new_generic::NewGenericResponse;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::CommonBuildConfigurationOptions;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_client_ctx::common::CommonConsoleOptions;
+use buck2_client_ctx::common::CommonDaemonCommandOptions;
+use buck2_client_ctx::daemon::client::BuckdClientConnector;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::streaming::StreamingCommand;
+use buck2_common::legacy_configs::cells::BuckConfigBasedCells;
+use buck2_common::legacy_configs::external_cells::lock_external_cells;
+use buck2_common::legacy_configs::external_cells::ExternalCellLockStatus;
+use buck2_common::legacy_configs::external_cells::EXTERNAL_CELLS_LOCKFILE;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+enum FetchCellsError {
+    #[error("Unexpected response from the daemon")]
+    UnexpectedResponse,
+}
+
+/// Lock the external cells declared in the root `.buckconfig` in `cells.lock`, and fetch
+/// those which are not available locally yet, so that later commands can run without network
+/// access.
+#[derive(Debug, clap::Parser)]
+#[clap(name = "fetch-cells")]
+pub struct FetchCellsCommand {
+    /// Resolve the `rev` of all git cells again and update their commits, even if they are
+    /// already locked, e.g. to pick up new commits of a branch.
+    #[clap(long)]
+    update: bool,
+
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+}
+
+#[async_trait]
+impl StreamingCommand for FetchCellsCommand {
+    const COMMAND_NAME: &'static str = "fetch-cells";
+
+    async fn exec_impl(
+        self,
+        buckd: &mut BuckdClientConnector,
+        matches: &clap::ArgMatches,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        // The lockfile is updated before the daemon reads the configs for this command, so that
+        // it resolves the cells to the commits locked here.
+        let project_root = ctx.paths()?.project_root().clone();
+        let cells = BuckConfigBasedCells::parse(&project_root)?;
+        let root_config = cells.configs_by_name.get(cells.cell_resolver.root_cell())?;
+        for (cell, status) in lock_external_cells(&project_root, root_config, self.update).await? {
+            if status == ExternalCellLockStatus::Locked {
+                buck2_client_ctx::eprintln!("Locked {} in {}", cell, EXTERNAL_CELLS_LOCKFILE)?;
+            }
+        }
+
+        let context = ctx.client_context(matches, &self)?;
+        let resp = buckd
+            .with_flushing()
+            .new_generic(
+                context,
+                NewGenericRequest::FetchCells(FetchCellsRequest {}),
+                ctx.stdin()
+                    .console_interaction_stream(&self.common_opts.console_opts),
+            )
+            .await??;
+        let NewGenericResponse::FetchCells(resp) = resp else {
+            return ExitResult::err(FetchCellsError::UnexpectedResponse.into());
+        };
+
+        for cell in resp.cells {
+            buck2_client_ctx::println!("{} {}", cell.alias, cell.path)?;
+        }
+        ExitResult::success()
+    }
+
+    fn console_opts(&self) -> &CommonConsoleOptions {
+        &self.common_opts.console_opts
+    }
+
+    fn event_log_opts(&self) -> &CommonDaemonCommandOptions {
+        &self.common_opts.event_log_opts
+    }
+
+    fn common_opts(&self) -> &CommonBuildConfigurationOptions {
+        &self.common_opts.config_opts
+    }
+}
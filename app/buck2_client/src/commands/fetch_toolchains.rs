@@ -0,0 +1,62 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_common::argv::Argv;
+use buck2_common::argv::SanitizedArgv;
+use buck2_common::legacy_configs::cells::BuckConfigBasedCells;
+use buck2_common::legacy_configs::toolchain_downloads::includes_toolchains_lockfile;
+use buck2_common::legacy_configs::toolchain_downloads::lock_toolchain_downloads;
+use buck2_common::legacy_configs::toolchain_downloads::ToolchainLockStatus;
+use buck2_common::legacy_configs::toolchain_downloads::TOOLCHAINS_LOCKFILE;
+
+/// Download the toolchains declared in the `[toolchain_downloads]` section of the root
+/// `.buckconfig`, and record their hashes in `toolchains.lock`.
+///
+/// Toolchains already in the lockfile for the same URL are not downloaded again.
+#[derive(Debug, clap::Parser)]
+#[clap(name = "fetch-toolchains")]
+pub struct FetchToolchainsCommand {
+    /// Download all toolchains again and update their hashes, even if they are already locked.
+    #[clap(long)]
+    update: bool,
+}
+
+impl FetchToolchainsCommand {
+    pub fn exec(
+        self,
+        _matches: &clap::ArgMatches,
+        ctx: ClientCommandContext<'_>,
+    ) -> anyhow::Result<()> {
+        let paths = ctx.paths()?;
+        let cells = BuckConfigBasedCells::parse(paths.project_root())?;
+        let root_config = cells.configs_by_name.get(cells.cell_resolver.root_cell())?;
+
+        let statuses = lock_toolchain_downloads(paths.project_root(), root_config, self.update)?;
+        for (name, status) in statuses {
+            let status = match status {
+                ToolchainLockStatus::Unchanged => "unchanged",
+                ToolchainLockStatus::Locked => "locked",
+            };
+            buck2_client_ctx::println!("{} {}", name, status)?;
+        }
+
+        if !includes_toolchains_lockfile(root_config) {
+            buck2_client_ctx::eprintln!(
+                "Add `<?file:{}>` to the root `.buckconfig` to use the locked toolchains.",
+                TOOLCHAINS_LOCKFILE
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn sanitize_argv(&self, argv: Argv) -> SanitizedArgv {
+        argv.no_need_to_sanitize()
+    }
+}
@@ -0,0 +1,279 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use async_trait::async_trait;
+use buck2_cli_proto::QueryOutputFormat;
+use buck2_cli_proto::UqueryRequest;
+use buck2_cli_proto::UqueryResponse;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::CommonBuildConfigurationOptions;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_client_ctx::common::CommonConsoleOptions;
+use buck2_client_ctx::common::CommonDaemonCommandOptions;
+use buck2_client_ctx::daemon::client::BuckdClientConnector;
+use buck2_client_ctx::daemon::client::StdoutPartialResultHandler;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::streaming::StreamingCommand;
+use buck2_common::vcs::detect_vcs;
+use buck2_common::vcs::VcsError;
+use gazebo::prelude::*;
+
+#[derive(Debug, thiserror::Error)]
+enum ImpactError {
+    #[error("Changed file `{0}` contains both `'` and `\"`, which cannot be quoted in a query")]
+    UnquotablePath(String),
+}
+
+/// List the targets affected by the files changed since a source control revision.
+///
+/// Asks source control (hg or git) for the files changed between the revision and the working
+/// copy, and prints the targets of the universe depending on the targets affected by these
+/// files, on the unconfigured target graph. A target is affected by a file if the file is one of
+/// its inputs, or if the file is its build file or a `.bzl` file its build file loads, directly
+/// or not.
+///
+/// Examples:
+///
+/// `buck2 impact --modified-since main`
+///
+/// `buck2 impact --modified-since .~1 --universe //app/... --depth 2 --json`
+#[derive(Debug, clap::Parser)]
+#[clap(name = "impact")]
+pub struct ImpactCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    /// The revision to compare the working copy against.
+    #[clap(long, value_name = "REV")]
+    modified_since: String,
+
+    /// Patterns of the targets which may be affected. Defaults to `//...`.
+    #[clap(long, value_name = "PATTERN")]
+    universe: Vec<String>,
+
+    /// Maximum number of dependency edges between an affected target and a changed file owner.
+    /// Unlimited by default.
+    #[clap(long, value_name = "N")]
+    depth: Option<u64>,
+
+    /// Print the targets as a JSON list.
+    #[clap(long)]
+    json: bool,
+}
+
+/// Quote a path as a query literal. The query language has no escape sequences, so like the
+/// query parser does when printing literals, the path is quoted with a quote it does not contain.
+fn quote(path: &str) -> anyhow::Result<String> {
+    if !path.contains('"') {
+        Ok(format!("\"{}\"", path))
+    } else if !path.contains('\'') {
+        Ok(format!("'{}'", path))
+    } else {
+        Err(ImpactError::UnquotablePath(path.to_owned()).into())
+    }
+}
+
+impl ImpactCommand {
+    fn query(&self, changed_files: &[String]) -> anyhow::Result<String> {
+        let universe = if self.universe.is_empty() {
+            "set(//...)".to_owned()
+        } else {
+            format!("set({})", self.universe.join(" "))
+        };
+        let files = format!("set({})", changed_files.try_map(|f| quote(f))?.join(" "));
+        // Targets owning the changed files, and targets defined in changed build files or in
+        // build files loading changed `.bzl` files.
+        let affected = format!(
+            "owner({files}) + targets_in_buildfile(rbuildfiles(allbuildfiles({universe}), {files}))"
+        );
+        Ok(match self.depth {
+            Some(depth) => format!("rdeps({universe}, {affected}, {depth})"),
+            None => format!("rdeps({universe}, {affected})"),
+        })
+    }
+}
+
+#[async_trait]
+impl StreamingCommand for ImpactCommand {
+    const COMMAND_NAME: &'static str = "impact";
+
+    async fn exec_impl(
+        self,
+        buckd: &mut BuckdClientConnector,
+        matches: &clap::ArgMatches,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        let project_root = ctx.paths()?.project_root().root().to_owned();
+        let vcs = detect_vcs(&project_root).ok_or(VcsError::NoRepository)?;
+        let changed_files = vcs
+            .changed_files(&self.modified_since)
+            .await?
+            .into_map(|f| project_root.as_path().join(f).display().to_string());
+        if changed_files.is_empty() {
+            if self.json {
+                buck2_client_ctx::println!("[]")?;
+            }
+            return ExitResult::success();
+        }
+
+        let unstable_output_format = if self.json {
+            QueryOutputFormat::Json
+        } else {
+            QueryOutputFormat::Default
+        } as i32;
+        let context = ctx.client_context(matches, &self)?;
+
+        let UqueryResponse {} = buckd
+            .with_flushing()
+            .uquery(
+                UqueryRequest {
+                    query: self.query(&changed_files)?,
+                    query_args: Vec::new(),
+                    context: Some(context),
+                    output_attributes: Vec::new(),
+                    unstable_output_format,
+                    graph_options: None,
+                },
+                ctx.stdin()
+                    .console_interaction_stream(&self.common_opts.console_opts),
+                &mut StdoutPartialResultHandler,
+            )
+            .await??;
+
+        ExitResult::success()
+    }
+
+    fn console_opts(&self) -> &CommonConsoleOptions {
+        &self.common_opts.console_opts
+    }
+
+    fn event_log_opts(&self) -> &CommonDaemonCommandOptions {
+        &self.common_opts.event_log_opts
+    }
+
+    fn common_opts(&self) -> &CommonBuildConfigurationOptions {
+        &self.common_opts.config_opts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_query_parser::parse_expr;
+    use buck2_query_parser::Expr;
+    use buck2_query_parser::SpannedExpr;
+    use clap::Parser;
+
+    use crate::commands::impact::ImpactCommand;
+
+    /// The name of the function called by `expr`, and its arguments.
+    fn call<'a>(expr: &'a SpannedExpr<'a>) -> (&'a str, &'a [SpannedExpr<'a>]) {
+        match &expr.value {
+            Expr::Function {
+                function_name,
+                args,
+            } => (function_name.fragment(), args),
+            _ => panic!("expected a function call, got `{}`", expr.value),
+        }
+    }
+
+    /// The literals of a `set()`.
+    fn set<'a>(expr: &'a SpannedExpr<'a>) -> Vec<&'a str> {
+        match &expr.value {
+            Expr::Set(words) => words.iter().map(|w| w.fragment()).collect(),
+            _ => panic!("expected a set, got `{}`", expr.value),
+        }
+    }
+
+    #[test]
+    fn test_query() -> anyhow::Result<()> {
+        let command = ImpactCommand::parse_from(["impact", "--modified-since", "main"]);
+        assert_eq!(
+            "rdeps(set(//...), owner(set(\"/repo/a.txt\" \"/repo/b/c.txt\")) + \
+             targets_in_buildfile(rbuildfiles(allbuildfiles(set(//...)), \
+             set(\"/repo/a.txt\" \"/repo/b/c.txt\"))))",
+            command.query(&["/repo/a.txt".to_owned(), "/repo/b/c.txt".to_owned()])?
+        );
+
+        let command = ImpactCommand::parse_from([
+            "impact",
+            "--modified-since",
+            "main",
+            "--universe",
+            "//app/...",
+            "--universe",
+            "//lib/...",
+            "--depth",
+            "2",
+        ]);
+        let query = command.query(&["/repo/a.txt".to_owned()])?;
+        let parsed = parse_expr(&query)?;
+        let (name, args) = call(&parsed);
+        assert_eq!("rdeps", name);
+        assert_eq!(3, args.len());
+        assert_eq!(vec!["//app/...", "//lib/..."], set(&args[0]));
+        assert_eq!("2", args[2].value.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_build_files() -> anyhow::Result<()> {
+        let command = ImpactCommand::parse_from(["impact", "--modified-since", "main"]);
+        let query = command.query(&["/repo/a/BUCK".to_owned(), "/repo/defs.bzl".to_owned()])?;
+        let parsed = parse_expr(&query)?;
+        let (_, args) = call(&parsed);
+        let Expr::BinaryOpSequence(owner, rest) = &args[1].value else {
+            panic!("expected a union, got `{}`", args[1].value);
+        };
+        let (name, owner_args) = call(owner);
+        assert_eq!("owner", name);
+        assert_eq!(vec!["/repo/a/BUCK", "/repo/defs.bzl"], set(&owner_args[0]));
+
+        // Build files and `.bzl` files also affect the targets of the build files loading them.
+        assert_eq!(1, rest.len());
+        let (name, targets_args) = call(&rest[0].1);
+        assert_eq!("targets_in_buildfile", name);
+        let (name, rbuildfiles_args) = call(&targets_args[0]);
+        assert_eq!("rbuildfiles", name);
+        let (name, universe) = call(&rbuildfiles_args[0]);
+        assert_eq!("allbuildfiles", name);
+        assert_eq!(vec!["//..."], set(&universe[0]));
+        assert_eq!(
+            vec!["/repo/a/BUCK", "/repo/defs.bzl"],
+            set(&rbuildfiles_args[1])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_quoting() -> anyhow::Result<()> {
+        let command = ImpactCommand::parse_from(["impact", "--modified-since", "main"]);
+        let files = [
+            "/repo/with space.txt",
+            r#"/repo/double"quote.txt"#,
+            "/repo/single'quote.txt",
+            r"C:\repo\back\slash.txt",
+            "/repo/paren).txt",
+        ];
+        let query = command.query(&files.map(|f| f.to_owned()))?;
+        let parsed = parse_expr(&query)?;
+        let (_, args) = call(&parsed);
+        let Expr::BinaryOpSequence(owner, _) = &args[1].value else {
+            panic!("expected a union, got `{}`", args[1].value);
+        };
+        // Every path is parsed back as a single literal, unchanged.
+        assert_eq!(files.to_vec(), set(&call(owner).1[0]));
+
+        assert!(
+            command
+                .query(&[r#"/repo/both'"quotes.txt"#.to_owned()])
+                .is_err()
+        );
+        Ok(())
+    }
+}
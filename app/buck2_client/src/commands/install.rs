@@ -38,6 +38,13 @@ pub struct InstallCommand {
     )]
     installer_debug: bool,
 
+    #[clap(
+        long,
+        help = "Only push the files which changed since the last install to the same device, and restart or hot-reload the app instead of reinstalling it. \
+        Installers which cannot do this for the app do a full install"
+    )]
+    incremental: bool,
+
     #[clap(flatten)]
     android_install_opts: AndroidInstallOptions,
 
@@ -185,6 +192,7 @@ impl StreamingCommand for InstallCommand {
                     build_opts: Some(self.build_opts.to_proto()),
                     installer_run_args: extra_run_args,
                     installer_debug: self.installer_debug,
+                    incremental: self.incremental,
                 },
                 ctx.stdin()
                     .console_interaction_stream(&self.common_opts.console_opts),
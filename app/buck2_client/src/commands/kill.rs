@@ -27,7 +27,21 @@ use buck2_common::argv::SanitizedArgv;
 ///
 /// `buck2 clean` kills the buck2 daemon and also deletes the buck2 state files.
 #[derive(Debug, clap::Parser)]
-pub struct KillCommand {}
+pub struct KillCommand {
+    /// Stop accepting new commands, wait for in-flight commands to finish and flush daemon state
+    /// before exiting, instead of interrupting running commands.
+    #[clap(long)]
+    drain: bool,
+
+    /// How long `--drain` waits for in-flight commands before shutting down anyway.
+    #[clap(
+        long,
+        value_name = "DURATION",
+        default_value = "5m",
+        requires = "drain"
+    )]
+    drain_timeout: humantime::Duration,
+}
 
 impl KillCommand {
     pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
@@ -41,7 +55,12 @@ impl KillCommand {
             .await
             .with_context(|| "Error locking buckd lifecycle.lock")?;
 
-            kill_command_impl(&lifecycle_lock, "`buck kill` was invoked").await
+            let drain = if self.drain {
+                Some(self.drain_timeout.into())
+            } else {
+                None
+            };
+            kill_command_impl(&lifecycle_lock, "`buck kill` was invoked", drain).await
         })
     }
 
@@ -53,6 +72,7 @@ impl KillCommand {
 pub async fn kill_command_impl(
     lifecycle_lock: &BuckdLifecycleLock,
     reason: &str,
+    drain: Option<Duration>,
 ) -> anyhow::Result<()> {
     let process = match BuckdProcessInfo::load(lifecycle_lock.daemon_dir()) {
         Ok(p) => p,
@@ -69,10 +89,16 @@ pub async fn kill_command_impl(
     .await;
 
     let response = match buckd {
-        Ok(Ok(mut buckd)) => {
-            buck2_client_ctx::eprintln!("killing buckd server")?;
-            Some(buckd.kill(reason).await?)
-        }
+        Ok(Ok(mut buckd)) => match drain {
+            Some(timeout) => {
+                buck2_client_ctx::eprintln!("draining buckd server")?;
+                Some(buckd.drain_and_kill(reason, timeout).await?)
+            }
+            None => {
+                buck2_client_ctx::eprintln!("killing buckd server")?;
+                Some(buckd.kill(reason).await?)
+            }
+        },
         Ok(Err(e)) => {
             // No time out: we just errored out. This is likely indicative that there is no
             // buckd (i.e. our connection got rejected), so let's check for this and then
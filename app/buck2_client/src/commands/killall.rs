@@ -9,18 +9,52 @@
 
 use buck2_client_ctx::client_ctx::ClientCommandContext;
 use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::path_arg::PathArg;
 use buck2_common::argv::Argv;
 use buck2_common::argv::SanitizedArgv;
 use buck2_wrapper_common::is_buck2::WhoIsAsking;
+use buck2_wrapper_common::KillallOptions;
 
+/// Kill buck2 processes on the machine.
+///
+/// The processes are listed before they are killed. Daemons which are running a command (that
+/// is, with a buck2 client running in their repository with the same isolation dir) are not
+/// killed, nor are their clients, unless `--force` is passed.
 #[derive(Debug, clap::Parser)]
 #[clap(about = "Kill all buck2 processes on the machine")]
-pub struct KillallCommand {}
+pub struct KillallCommand {
+    /// Only kill processes running for this repository, or a directory below it.
+    #[clap(long, value_name = "PATH")]
+    repo: Option<PathArg>,
+
+    /// Only kill processes with this isolation dir.
+    #[clap(long, value_name = "NAME")]
+    isolation_dir: Option<String>,
+
+    /// Also kill daemons which are running a command, and their clients.
+    #[clap(long)]
+    force: bool,
+
+    /// Only list the processes which would be killed.
+    #[clap(long)]
+    dry_run: bool,
+}
 
 impl KillallCommand {
     pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
+        let repo = self.repo.map(|repo| {
+            let repo = repo.resolve(&ctx.working_dir);
+            // Working directories of processes are canonical.
+            std::fs::canonicalize(&repo).unwrap_or_else(|_| repo.into_path_buf())
+        });
+        let options = KillallOptions {
+            repo,
+            isolation_dir: self.isolation_dir,
+            force: self.force,
+            dry_run: self.dry_run,
+        };
         ctx.instant_command("killall", async move |_ctx| {
-            buck2_wrapper_common::killall(WhoIsAsking::Buck2, |s| {
+            buck2_wrapper_common::killall_with_options(WhoIsAsking::Buck2, &options, |s| {
                 let _ignored = buck2_client_ctx::eprintln!("{}", s);
             })
             .then_some(())
@@ -12,10 +12,13 @@ pub(crate) mod debug_replay;
 pub(crate) mod debug_what_ran;
 pub(crate) mod options;
 pub(crate) mod path_log;
+mod recent;
 mod replay;
 mod show_log;
 mod show_user_log;
+mod stats;
 mod summary;
+mod test_env;
 mod what_cmd;
 mod what_failed;
 mod what_materialized;
@@ -91,6 +94,9 @@ pub enum LogCommand {
     Replay(replay::ReplayCommand),
     ShowUser(show_user_log::ShowUserLogCommand),
     Summary(summary::SummaryCommand),
+    Stats(stats::StatsCommand),
+    Recent(recent::RecentCommand),
+    TestEnv(test_env::TestEnvCommand),
 }
 
 impl LogCommand {
@@ -108,6 +114,9 @@ impl LogCommand {
             Self::Replay(cmd) => cmd.exec(matches, ctx),
             Self::ShowUser(cmd) => cmd.exec(matches, ctx),
             Self::Summary(cmd) => cmd.exec(matches, ctx),
+            Self::Stats(cmd) => cmd.exec(matches, ctx),
+            Self::Recent(cmd) => cmd.exec(matches, ctx),
+            Self::TestEnv(cmd) => cmd.exec(matches, ctx),
         }
     }
 
@@ -0,0 +1,134 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::str::FromStr;
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::invocation_history::InvocationHistory;
+use buck2_client_ctx::invocation_history::InvocationHistoryEntry;
+use chrono::Local;
+use chrono::TimeZone;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+enum RecentFilterError {
+    #[error(
+        "Invalid filter `{0}`, expected `KEY=VALUE` with key one of `command`, `result`, `config_hash`, `trace_id`, or `args`"
+    )]
+    InvalidFilter(String),
+    #[error("Invalid result `{0}` in filter, expected `success`, `failure` or `unknown`")]
+    InvalidResult(String),
+}
+
+#[derive(Debug, Clone)]
+enum RecentFilter {
+    Command(String),
+    Result(Option<bool>),
+    ConfigHash(String),
+    TraceId(String),
+    Args(String),
+}
+
+impl FromStr for RecentFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (key, value) = s
+            .split_once('=')
+            .ok_or_else(|| RecentFilterError::InvalidFilter(s.to_owned()))?;
+        let value = value.to_owned();
+        Ok(match key {
+            "command" => RecentFilter::Command(value),
+            "result" => RecentFilter::Result(match value.as_str() {
+                "success" => Some(true),
+                "failure" => Some(false),
+                "unknown" => None,
+                _ => return Err(RecentFilterError::InvalidResult(value).into()),
+            }),
+            "config_hash" => RecentFilter::ConfigHash(value),
+            "trace_id" => RecentFilter::TraceId(value),
+            "args" => RecentFilter::Args(value),
+            _ => return Err(RecentFilterError::InvalidFilter(s.to_owned()).into()),
+        })
+    }
+}
+
+impl RecentFilter {
+    fn matches(&self, entry: &InvocationHistoryEntry) -> bool {
+        match self {
+            RecentFilter::Command(command) => entry.command_name == *command,
+            RecentFilter::Result(success) => entry.success == *success,
+            RecentFilter::ConfigHash(hash) => entry.config_hash.as_ref() == Some(hash),
+            RecentFilter::TraceId(trace_id) => entry.trace_id.starts_with(trace_id.as_str()),
+            RecentFilter::Args(args) => entry.cli_args.join(" ").contains(args.as_str()),
+        }
+    }
+}
+
+/// List recent invocations of buck2 in this project, most recent last.
+///
+/// This reads an index which buck2 maintains as commands finish, so it is fast, and keeps
+/// more invocations than the event logs.
+#[derive(Debug, clap::Parser)]
+pub struct RecentCommand {
+    /// Number of invocations to show.
+    #[clap(long, short = 'n', default_value = "10")]
+    count: usize,
+
+    /// Only show invocations matching this filter: `command=NAME`,
+    /// `result=success|failure|unknown`, `config_hash=HASH`, `trace_id=PREFIX`, or
+    /// `args=SUBSTRING` (of the command line). Can be repeated, in which case invocations
+    /// must match all the filters.
+    #[clap(long, value_name = "KEY=VALUE")]
+    filter: Vec<RecentFilter>,
+
+    /// Print one JSON object per invocation.
+    #[clap(long)]
+    json: bool,
+}
+
+impl RecentCommand {
+    pub(crate) fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext) -> ExitResult {
+        let history = InvocationHistory::new(ctx.paths()?.invocation_history_path());
+        let entries = history
+            .read()?
+            .into_iter()
+            .filter(|entry| self.filter.iter().all(|filter| filter.matches(entry)))
+            .collect::<Vec<_>>();
+
+        for entry in &entries[entries.len().saturating_sub(self.count)..] {
+            if self.json {
+                buck2_client_ctx::println!("{}", serde_json::to_string(entry)?)?;
+            } else {
+                let start_time = Local
+                    .timestamp_millis_opt(entry.start_time_ms as i64)
+                    .single()
+                    .map_or_else(String::new, |t| t.format("%Y-%m-%d %H:%M:%S").to_string());
+                let duration = entry
+                    .duration_ms
+                    .map_or_else(String::new, |d| format!("{:.1}s", d as f64 / 1000.0));
+                let result = match entry.success {
+                    Some(true) => "success",
+                    Some(false) => "failure",
+                    None => "unknown",
+                };
+                buck2_client_ctx::println!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    start_time,
+                    duration,
+                    result,
+                    entry.trace_id,
+                    entry.cli_args.join(" ")
+                )?;
+            }
+        }
+        ExitResult::success()
+    }
+}
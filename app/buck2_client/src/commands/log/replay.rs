@@ -15,6 +15,7 @@ use buck2_client_ctx::exit_result::ExitCode;
 use buck2_client_ctx::exit_result::ExitResult;
 use buck2_client_ctx::replayer::Replayer;
 use buck2_client_ctx::signal_handler::with_simple_sigint_handler;
+use buck2_client_ctx::signal_handler::HardCancelHandle;
 use buck2_client_ctx::subscribers::get::get_console_with_root;
 
 use crate::commands::log::options::EventLogOptions;
@@ -100,7 +101,8 @@ impl ReplayCommand {
                 ExitResult::success()
             };
 
-            with_simple_sigint_handler(work)
+            // Replaying doesn't run anything on the daemon, so there is nothing to hard cancel.
+            with_simple_sigint_handler(work, HardCancelHandle::default())
                 .await
                 .unwrap_or_else(|| ExitResult::status(ExitCode::SignalInterrupt))
         })
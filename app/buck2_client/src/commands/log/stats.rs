@@ -0,0 +1,136 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_data::ActionExecutionKind;
+use buck2_event_log::file_names::get_local_logs;
+use buck2_event_log::read::EventLogPathBuf;
+use buck2_event_log::stream_value::StreamValue;
+use tokio_stream::StreamExt;
+
+#[derive(Default)]
+struct CategoryStats {
+    actions: u64,
+    cache_hits: u64,
+    total_duration: Duration,
+    total_output_bytes: u64,
+}
+
+impl CategoryStats {
+    fn add(&mut self, end: &buck2_data::ActionExecutionEnd) {
+        self.actions += 1;
+        match ActionExecutionKind::from_i32(end.execution_kind) {
+            Some(
+                ActionExecutionKind::ActionCache
+                | ActionExecutionKind::RemoteDepFileCache
+                | ActionExecutionKind::LocalDepFile,
+            ) => self.cache_hits += 1,
+            _ => {}
+        }
+        if let Some(wall_time) = end
+            .wall_time
+            .clone()
+            .and_then(|d| Duration::try_from(d).ok())
+        {
+            self.total_duration += wall_time;
+        }
+        self.total_output_bytes += end.output_size;
+    }
+
+    fn average_duration(&self) -> Duration {
+        self.total_duration / (self.actions.max(1) as u32)
+    }
+}
+
+/// Show statistics about actions across recent commands, read from the local event logs.
+#[derive(Debug, clap::Parser)]
+pub struct StatsCommand {
+    /// Break down statistics by action category.
+    #[clap(long)]
+    by_category: bool,
+
+    /// Number of recent event logs to read.
+    #[clap(long, value_name = "NUMBER", default_value = "10")]
+    recent: usize,
+}
+
+impl StatsCommand {
+    pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
+        ctx.with_runtime(async move |ctx| {
+            let logs = get_local_logs(&ctx.paths()?.log_dir())?;
+            let logs = &logs[logs.len().saturating_sub(self.recent)..];
+
+            let mut stats: HashMap<String, CategoryStats> = HashMap::new();
+            for log in logs {
+                if let Err(e) = self.read_log(log, &mut stats).await {
+                    buck2_client_ctx::eprintln!(
+                        "Skipping unreadable log `{}`: {:#}",
+                        log.path().display(),
+                        e
+                    )?;
+                }
+            }
+
+            buck2_client_ctx::eprintln!("Showing statistics from {} logs", logs.len())?;
+
+            let mut stats = stats.into_iter().collect::<Vec<_>>();
+            stats.sort_by(|(_, a), (_, b)| b.total_duration.cmp(&a.total_duration));
+
+            buck2_client_ctx::println!(
+                "category\tactions\ttotal_duration\tavg_duration\tcache_hit_rate\tavg_output_bytes"
+            )?;
+            for (category, s) in stats {
+                buck2_client_ctx::println!(
+                    "{}\t{}\t{:.3}s\t{:.3}s\t{:.1}%\t{}",
+                    category,
+                    s.actions,
+                    s.total_duration.as_secs_f64(),
+                    s.average_duration().as_secs_f64(),
+                    100.0 * s.cache_hits as f64 / s.actions.max(1) as f64,
+                    s.total_output_bytes / s.actions.max(1),
+                )?;
+            }
+
+            anyhow::Ok(())
+        })?;
+
+        ExitResult::success()
+    }
+
+    async fn read_log(
+        &self,
+        log: &EventLogPathBuf,
+        stats: &mut HashMap<String, CategoryStats>,
+    ) -> anyhow::Result<()> {
+        let (_invocation, mut events) = log.unpack_stream().await?;
+        while let Some(event) = events.try_next().await? {
+            let event = match event {
+                StreamValue::Event(event) => event,
+                StreamValue::Result(..) | StreamValue::PartialResult(..) => continue,
+            };
+            if let Some(buck2_data::buck_event::Data::SpanEnd(end)) = &event.data {
+                if let Some(buck2_data::span_end_event::Data::ActionExecution(end)) = &end.data {
+                    let key = if self.by_category {
+                        end.name
+                            .as_ref()
+                            .map_or_else(|| "<unknown>".to_owned(), |n| n.category.clone())
+                    } else {
+                        "total".to_owned()
+                    };
+                    stats.entry(key).or_default().add(end);
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_event_log::stream_value::StreamValue;
+use buck2_event_observer::display;
+use buck2_event_observer::display::TargetDisplayOptions;
+use tokio_stream::StreamExt;
+
+use crate::commands::log::options::EventLogOptions;
+
+/// Show which environment variables of the daemon tests inherited in a selected invocation.
+///
+/// This produces tab-delimited output with a line per test target that ran locally: the target,
+/// whether it declared the variables it inherits (`declared`) or used the default allowlist
+/// (`default`), and the variables it inherited.
+///
+/// Tests using the default allowlist may depend on the environment of the machine they run on.
+/// Set `env_allowlist` in their `ExternalRunnerTestInfo` to make that explicit.
+#[derive(Debug, clap::Parser)]
+pub struct TestEnvCommand {
+    #[clap(flatten)]
+    event_log: EventLogOptions,
+
+    /// Only show tests using the default allowlist.
+    #[clap(long)]
+    undeclared: bool,
+}
+
+impl TestEnvCommand {
+    pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
+        let Self {
+            event_log,
+            undeclared,
+        } = self;
+
+        ctx.with_runtime(async move |ctx| {
+            let log_path = event_log.get(&ctx).await?;
+
+            let (invocation, mut events) = log_path.unpack_stream().await?;
+            buck2_client_ctx::eprintln!(
+                "Showing test environments from: {}",
+                invocation.display_command_line()
+            )?;
+
+            // Tests run several times, so merge what each of their runs inherited.
+            let mut tests = BTreeMap::<String, (bool, BTreeSet<String>)>::new();
+
+            while let Some(event) = events.try_next().await? {
+                let test_end = match event {
+                    StreamValue::Event(event) => match event.data {
+                        Some(buck2_data::buck_event::Data::SpanEnd(buck2_data::SpanEndEvent {
+                            data: Some(buck2_data::span_end_event::Data::TestEnd(test_end)),
+                            ..
+                        })) => test_end,
+                        _ => continue,
+                    },
+                    _ => continue,
+                };
+
+                if test_end.inherited_env.is_empty() {
+                    continue;
+                }
+                let target = match test_end.suite.and_then(|s| s.target_label) {
+                    Some(target) => display::display_configured_target_label(
+                        &target,
+                        TargetDisplayOptions::for_log(),
+                    )?,
+                    None => continue,
+                };
+
+                let (declared, inherited_env) = tests.entry(target).or_default();
+                *declared = test_end.env_allowlist_declared;
+                inherited_env.extend(test_end.inherited_env);
+            }
+
+            for (target, (declared, inherited_env)) in tests {
+                if declared && undeclared {
+                    continue;
+                }
+                buck2_client_ctx::println!(
+                    "{}\t{}\t{}",
+                    target,
+                    if declared { "declared" } else { "default" },
+                    inherited_env.into_iter().collect::<Vec<_>>().join(",")
+                )?;
+            }
+
+            anyhow::Ok(())
+        })?;
+
+        ExitResult::success()
+    }
+}
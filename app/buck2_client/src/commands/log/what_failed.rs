@@ -13,13 +13,18 @@ use buck2_client_ctx::exit_result::ExitResult;
 use crate::commands::log::what_ran::WhatRanCommand;
 use crate::commands::log::what_ran::WhatRanCommandCommon;
 
-/// Outputs every command that failed in the selected invocation.
+/// Outputs every command that failed in the selected invocation, including failed test runs,
+/// along with their stderr.
 ///
 /// Look at the help for what-ran to understand the output format.
 #[derive(Debug, clap::Parser)]
 pub struct WhatFailedCommand {
     #[clap(flatten)]
     pub common: WhatRanCommandCommon,
+
+    /// Do not show the stderr of failed commands.
+    #[clap(long)]
+    pub no_std_err: bool,
 }
 
 impl WhatFailedCommand {
@@ -28,7 +33,7 @@ impl WhatFailedCommand {
             common: self.common,
             failed: true,
             incomplete: false,
-            show_std_err: false,
+            show_std_err: !self.no_std_err,
             omit_empty_std_err: false,
         }
         .exec(matches, ctx)
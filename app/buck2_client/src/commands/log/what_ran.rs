@@ -304,11 +304,30 @@ fn should_emit_finished_action(
         Some(buck2_data::span_end_event::Data::ActionExecution(action)) => {
             action.failed || !options.failed
         }
+        Some(buck2_data::span_end_event::Data::TestDiscovery(buck2_data::TestDiscoveryEnd {
+            command_report,
+            ..
+        }))
+        | Some(buck2_data::span_end_event::Data::TestEnd(buck2_data::TestRunEnd {
+            command_report,
+            ..
+        })) => command_failed(command_report.as_ref()) || !options.failed,
         _ => !options.failed, // This is dead code (this span can only be ActionExecution End given
                               // its ID must match an ActionExecution start).
     }
 }
 
+fn command_failed(command: Option<&buck2_data::CommandExecution>) -> bool {
+    matches!(
+        command.and_then(|c| c.status.as_ref()),
+        Some(
+            buck2_data::command_execution::Status::Failure(..)
+                | buck2_data::command_execution::Status::Timeout(..)
+                | buck2_data::command_execution::Status::Error(..)
+        )
+    )
+}
+
 fn should_emit_unfinished_action(options: &WhatRanCommandOptions) -> bool {
     !options.failed // We don't know if it failed or not.
 }
@@ -11,9 +11,13 @@ pub mod build;
 pub mod bxl;
 pub mod clean;
 pub mod clean_stale;
+pub mod completion;
 pub mod ctargets;
 pub mod debug;
+pub mod fetch_cells;
+pub mod fetch_toolchains;
 pub mod help_env;
+pub mod impact;
 pub mod init;
 pub mod install;
 pub mod kill;
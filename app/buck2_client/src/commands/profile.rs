@@ -14,6 +14,7 @@ use async_trait::async_trait;
 use buck2_cli_proto::profile_request::ProfileOpts;
 use buck2_cli_proto::profile_request::Profiler;
 use buck2_cli_proto::target_profile::Action;
+use buck2_cli_proto::AnalysisBench;
 use buck2_cli_proto::BxlProfile;
 use buck2_cli_proto::ProfileRequest;
 use buck2_cli_proto::ProfileResponse;
@@ -33,6 +34,7 @@ use buck2_common::argv::Argv;
 use buck2_common::argv::SanitizedArgv;
 use dupe::Dupe;
 use gazebo::prelude::VecExt;
+use thiserror::Error;
 
 use super::bxl::BxlCommandOptions;
 
@@ -47,6 +49,9 @@ pub enum ProfileCommand {
 
     #[clap(about = "Profile BXL script")]
     Bxl(BxlProfileOptions),
+
+    #[clap(about = "Benchmark analysis")]
+    AnalysisBench(AnalysisBenchCommand),
 }
 
 pub enum ProfileOptionsType {
@@ -62,7 +67,7 @@ pub enum ProfileOptionsType {
 impl ProfileCommand {
     pub fn exec(self, matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
         let submatches = matches.subcommand().expect("subcommand not found").1;
-        match self {
+        let subcommand = match self {
             Self::Analysis(opts) => ProfileSubcommand {
                 opts: ProfileOptionsType::BuckProfileOptions {
                     opts: opts.buck_opts,
@@ -83,8 +88,9 @@ impl ProfileCommand {
                 },
                 profile_common_opts: opts.profile_common_opts,
             },
-        }
-        .exec(submatches, ctx)
+            Self::AnalysisBench(cmd) => return cmd.exec(submatches, ctx),
+        };
+        subcommand.exec(submatches, ctx)
     }
 
     pub fn sanitize_argv(&self, argv: Argv) -> SanitizedArgv {
@@ -279,3 +285,194 @@ impl StreamingCommand for ProfileSubcommand {
         &self.profile_common_opts.common_opts.config_opts
     }
 }
+
+#[derive(Debug, Error)]
+enum AnalysisBenchError {
+    #[error("`--iterations` must be at least 1")]
+    NoIterations,
+}
+
+/// Repeatedly analyze targets and report how long their analysis takes.
+///
+/// Each target is first analyzed `--warmup` times, which also analyzes its dependencies. Its
+/// analysis is then rerun `--iterations` times, bypassing the cache of analysis results, and
+/// timed. Dependencies are not reanalyzed, and no actions are run.
+#[derive(Debug, clap::Parser)]
+pub struct AnalysisBenchCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(value_name = "TARGET_PATTERNS", required = true)]
+    target_patterns: Vec<String>,
+
+    /// Number of timed analyses of each target.
+    #[clap(long, value_name = "N", default_value = "10")]
+    iterations: u32,
+
+    /// Number of analyses of each target to run before the timed ones.
+    #[clap(long, value_name = "N", default_value = "1")]
+    warmup: u32,
+
+    /// Print the timings as JSON, including every sample.
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(Debug, PartialEq, serde::Serialize)]
+struct AnalysisBenchStats {
+    target: String,
+    min_ms: f64,
+    median_ms: f64,
+    mean_ms: f64,
+    max_ms: f64,
+    stddev_ms: f64,
+    samples_ms: Vec<f64>,
+}
+
+impl AnalysisBenchStats {
+    fn new(target: String, samples: &[Duration]) -> Self {
+        let samples_ms: Vec<f64> = samples.iter().map(|d| d.as_secs_f64() * 1000.0).collect();
+        let mut sorted = samples_ms.clone();
+        sorted.sort_by(f64::total_cmp);
+
+        let n = sorted.len();
+        let (min_ms, max_ms) = match (sorted.first(), sorted.last()) {
+            (Some(min), Some(max)) => (*min, *max),
+            _ => (0.0, 0.0),
+        };
+        let median_ms = match n {
+            0 => 0.0,
+            n if n % 2 == 0 => (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0,
+            n => sorted[n / 2],
+        };
+        let mean_ms = if n == 0 {
+            0.0
+        } else {
+            sorted.iter().sum::<f64>() / n as f64
+        };
+        // Sample standard deviation.
+        let stddev_ms = if n < 2 {
+            0.0
+        } else {
+            (sorted.iter().map(|s| (s - mean_ms).powi(2)).sum::<f64>() / (n - 1) as f64).sqrt()
+        };
+
+        Self {
+            target,
+            min_ms,
+            median_ms,
+            mean_ms,
+            max_ms,
+            stddev_ms,
+            samples_ms,
+        }
+    }
+}
+
+#[async_trait]
+impl StreamingCommand for AnalysisBenchCommand {
+    const COMMAND_NAME: &'static str = "profile-analysis-bench";
+
+    async fn exec_impl(
+        self,
+        buckd: &mut BuckdClientConnector,
+        matches: &clap::ArgMatches,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        if self.iterations == 0 {
+            return ExitResult::err(AnalysisBenchError::NoIterations.into());
+        }
+
+        let context = ctx.client_context(matches, &self)?;
+        let console_opts = ctx.stdin().console_interaction_stream(self.console_opts());
+
+        let response = buckd
+            .with_flushing()
+            .profile(
+                ProfileRequest {
+                    context: Some(context),
+                    profile_opts: Some(ProfileOpts::AnalysisBench(AnalysisBench {
+                        target_patterns: self
+                            .target_patterns
+                            .iter()
+                            .map(|value| buck2_data::TargetPattern {
+                                value: value.clone(),
+                            })
+                            .collect(),
+                        iterations: self.iterations,
+                        warmup: self.warmup,
+                    })),
+                    ..Default::default()
+                },
+                console_opts,
+                &mut NoPartialResultHandler,
+            )
+            .await??;
+
+        let mut stats = Vec::with_capacity(response.analysis_bench.len());
+        for target in response.analysis_bench {
+            let samples = target
+                .samples
+                .into_iter()
+                .map(Duration::try_from)
+                .collect::<Result<Vec<_>, _>>()
+                .context("Invalid analysis duration")?;
+            stats.push(AnalysisBenchStats::new(target.target, &samples));
+        }
+
+        if self.json {
+            buck2_client_ctx::println!("{}", serde_json::to_string_pretty(&stats)?)?;
+        } else {
+            buck2_client_ctx::println!(
+                "{:>10} {:>10} {:>10} {:>10} {:>10}  TARGET",
+                "MIN_MS",
+                "MEDIAN_MS",
+                "MEAN_MS",
+                "MAX_MS",
+                "STDDEV_MS"
+            )?;
+            for s in &stats {
+                buck2_client_ctx::println!(
+                    "{:>10.3} {:>10.3} {:>10.3} {:>10.3} {:>10.3}  {}",
+                    s.min_ms,
+                    s.median_ms,
+                    s.mean_ms,
+                    s.max_ms,
+                    s.stddev_ms,
+                    s.target
+                )?;
+            }
+        }
+
+        ExitResult::success()
+    }
+
+    fn console_opts(&self) -> &CommonConsoleOptions {
+        &self.common_opts.console_opts
+    }
+
+    fn event_log_opts(&self) -> &CommonDaemonCommandOptions {
+        &self.common_opts.event_log_opts
+    }
+
+    fn common_opts(&self) -> &CommonBuildConfigurationOptions {
+        &self.common_opts.config_opts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analysis_bench_stats() {
+        let stats =
+            AnalysisBenchStats::new("t".to_owned(), &[4, 1, 3, 2].map(Duration::from_millis));
+        assert_eq!(stats.min_ms, 1.0);
+        assert_eq!(stats.max_ms, 4.0);
+        assert_eq!(stats.median_ms, 2.5);
+        assert_eq!(stats.mean_ms, 2.5);
+        assert!((stats.stddev_ms - (5.0f64 / 3.0).sqrt()).abs() < 1e-9);
+        assert_eq!(stats.samples_ms, vec![4.0, 1.0, 3.0, 2.0]);
+    }
+}
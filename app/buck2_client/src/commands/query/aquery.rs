@@ -7,7 +7,11 @@
  * of this source tree.
  */
 
+use std::collections::HashMap;
+use std::time::Duration;
+
 use async_trait::async_trait;
+use buck2_cli_proto::AqueryHistoricalCost;
 use buck2_cli_proto::AqueryRequest;
 use buck2_cli_proto::AqueryResponse;
 use buck2_client_ctx::client_ctx::ClientCommandContext;
@@ -19,6 +23,13 @@ use buck2_client_ctx::daemon::client::BuckdClientConnector;
 use buck2_client_ctx::daemon::client::StdoutPartialResultHandler;
 use buck2_client_ctx::exit_result::ExitResult;
 use buck2_client_ctx::streaming::StreamingCommand;
+use buck2_common::invocation_paths::InvocationPaths;
+use buck2_data::ActionExecutionKind;
+use buck2_event_log::file_names::retrieve_all_logs;
+use buck2_event_log::stream_value::StreamValue;
+use buck2_event_observer::display;
+use buck2_event_observer::display::TargetDisplayOptions;
+use tokio_stream::StreamExt;
 
 use crate::commands::query::common::CommonQueryOptions;
 
@@ -43,6 +54,13 @@ use crate::commands::query::common::CommonQueryOptions;
 ///
 /// Currently, aquery interacts poorly with dynamic outputs. It may return incorrect results or otherwise
 /// behave unexpectedly.
+///
+/// Performance investigation:
+///
+/// With `--historical-costs`, each action is annotated with its mean duration and last execution
+/// kind as recorded in recent event logs, when it was executed recently.
+///
+/// `buck2 aquery 'deps(//java/com/example/app:amazing)' --historical-costs --output-format=json`
 #[derive(Debug, clap::Parser)]
 #[clap(name = "aquery")]
 pub struct AqueryCommand {
@@ -51,6 +69,19 @@ pub struct AqueryCommand {
 
     #[clap(flatten)]
     query_common: CommonQueryOptions,
+
+    /// Annotate actions with their duration and execution kind recorded in recent event logs.
+    #[clap(long)]
+    historical_costs: bool,
+
+    /// Number of recent event logs to scan for `--historical-costs`.
+    #[clap(
+        long,
+        value_name = "N",
+        default_value = "10",
+        requires = "historical-costs"
+    )]
+    historical_costs_logs: usize,
 }
 
 #[async_trait]
@@ -67,6 +98,11 @@ impl StreamingCommand for AqueryCommand {
         let unstable_output_format = self.query_common.output_format() as i32;
         let output_attributes = self.query_common.attributes.get()?;
         let context = ctx.client_context(matches, &self)?;
+        let historical_costs = if self.historical_costs {
+            load_historical_costs(ctx.paths()?, self.historical_costs_logs).await?
+        } else {
+            Vec::new()
+        };
 
         let AqueryResponse {} = buckd
             .with_flushing()
@@ -77,6 +113,8 @@ impl StreamingCommand for AqueryCommand {
                     context: Some(context),
                     output_attributes,
                     unstable_output_format,
+                    graph_options: Some(self.query_common.graph_options()),
+                    historical_costs,
                 },
                 ctx.stdin()
                     .console_interaction_stream(&self.common_opts.console_opts),
@@ -99,3 +137,89 @@ impl StreamingCommand for AqueryCommand {
         &self.common_opts.config_opts
     }
 }
+
+#[derive(Default)]
+struct CostAccumulator {
+    total: Duration,
+    samples: u32,
+    last_execution_kind: Option<String>,
+}
+
+/// Collect the successful action executions of the `max_logs` most recent event logs, keyed
+/// by owner, category and identifier.
+async fn load_historical_costs(
+    paths: &InvocationPaths,
+    max_logs: usize,
+) -> anyhow::Result<Vec<AqueryHistoricalCost>> {
+    let mut logs = retrieve_all_logs(paths)?;
+    // Newest first, so the first execution kind seen is the last one.
+    logs.reverse();
+
+    let mut costs: HashMap<(String, String, String), CostAccumulator> = HashMap::new();
+    for log in logs.into_iter().take(max_logs) {
+        // Logs of other versions or of commands still running may not be readable, they are
+        // just not accounted for.
+        let Ok((_invocation, mut events)) = log.unpack_stream().await else {
+            continue;
+        };
+        while let Ok(Some(event)) = events.try_next().await {
+            let StreamValue::Event(event) = event else {
+                continue;
+            };
+            let Some(buck2_data::buck_event::Data::SpanEnd(end)) = &event.data else {
+                continue;
+            };
+            let Some(buck2_data::span_end_event::Data::ActionExecution(action)) = &end.data else {
+                continue;
+            };
+            if action.failed {
+                continue;
+            }
+            let (Some(key), Some(name), Some(wall_time)) =
+                (&action.key, &action.name, &action.wall_time)
+            else {
+                continue;
+            };
+            let Ok(owner) = display::display_action_key(key, TargetDisplayOptions::for_log())
+            else {
+                continue;
+            };
+            let Ok(wall_time) = Duration::try_from(wall_time.clone()) else {
+                continue;
+            };
+
+            let cost = costs
+                .entry((owner, name.category.clone(), name.identifier.clone()))
+                .or_default();
+            cost.total += wall_time;
+            cost.samples += 1;
+            if cost.last_execution_kind.is_none() {
+                cost.last_execution_kind = Some(execution_kind_name(action.execution_kind));
+            }
+        }
+    }
+
+    Ok(costs
+        .into_iter()
+        .map(
+            |((owner, category, identifier), cost)| AqueryHistoricalCost {
+                owner,
+                category,
+                identifier,
+                duration_us: (cost.total / cost.samples).as_micros() as u64,
+                last_execution_kind: cost.last_execution_kind.unwrap_or_default(),
+                samples: cost.samples,
+            },
+        )
+        .collect())
+}
+
+fn execution_kind_name(kind: i32) -> String {
+    match ActionExecutionKind::from_i32(kind) {
+        Some(kind) => kind
+            .as_str_name()
+            .trim_start_matches("ACTION_EXECUTION_KIND_")
+            .to_ascii_lowercase(),
+        None => "unknown".to_owned(),
+    }
+}
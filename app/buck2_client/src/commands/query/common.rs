@@ -7,6 +7,8 @@
  * of this source tree.
  */
 
+use buck2_cli_proto::query_graph_options;
+use buck2_cli_proto::QueryGraphOptions;
 use buck2_cli_proto::QueryOutputFormat;
 use buck2_client_ctx::query_args::CommonAttributeArgs;
 use buck2_query_parser::placeholder::QUERY_PERCENT_SS_PLACEHOLDER;
@@ -25,6 +27,22 @@ enum QueryOutputFormatArg {
     Dot,
     Json,
     DotCompact,
+    Jsonl,
+    Mermaid,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Dupe,
+    clap::ArgEnum,
+    serde::Serialize,
+    serde::Deserialize
+)]
+#[clap(rename_all = "snake_case")]
+enum GraphClusterArg {
+    Package,
+    RuleType,
 }
 
 /// Args common to all the query commands
@@ -46,6 +64,9 @@ pub(crate) struct CommonQueryOptions {
     #[clap(long, help = "Output in a more compact format than Graphviz Dot")]
     dot_compact: bool,
 
+    #[clap(long, help = "Output as a Mermaid flowchart")]
+    mermaid: bool,
+
     #[clap(
         long,
         ignore_case = true,
@@ -53,13 +74,25 @@ pub(crate) struct CommonQueryOptions {
         long_help = "Output format (default: list). \n
            dot -  dot graph format. \n
            dot_compact - compact alternative to dot format. \n
-           json - JSON format.
+           json - JSON format. \n
+           jsonl - one JSON value per line, written as soon as it is computed. \n
+           mermaid - Mermaid flowchart format.
          ",
-        value_name = "dot|dot_compact|json",
+        value_name = "dot|dot_compact|json|jsonl|mermaid",
         arg_enum
     )]
     output_format: Option<QueryOutputFormatArg>,
 
+    /// For graph output (dot, dot_compact and mermaid), draw the nodes in clusters of the same
+    /// package or rule type.
+    #[clap(long, ignore_case = true, value_name = "package|rule_type", arg_enum)]
+    graph_cluster: Option<GraphClusterArg>,
+
+    /// For graph output (dot, dot_compact and mermaid), only include the nodes within this many
+    /// edges of the roots of the graph (the nodes no other node of the result depends on).
+    #[clap(long, value_name = "DEPTH")]
+    graph_max_depth: Option<u32>,
+
     #[clap(
         name = "QUERY_ARGS",
         help = "list of literals for a multi-query (one containing `%s` or `%Ss`)"
@@ -87,6 +120,8 @@ impl CommonQueryOptions {
             Some(QueryOutputFormatArg::Json) => QueryOutputFormat::Json,
             Some(QueryOutputFormatArg::Dot) => QueryOutputFormat::Dot,
             Some(QueryOutputFormatArg::DotCompact) => QueryOutputFormat::DotCompact,
+            Some(QueryOutputFormatArg::Jsonl) => QueryOutputFormat::Jsonl,
+            Some(QueryOutputFormatArg::Mermaid) => QueryOutputFormat::Mermaid,
             None => {
                 if self.json {
                     QueryOutputFormat::Json
@@ -94,6 +129,8 @@ impl CommonQueryOptions {
                     QueryOutputFormat::Dot
                 } else if self.dot_compact {
                     QueryOutputFormat::DotCompact
+                } else if self.mermaid {
+                    QueryOutputFormat::Mermaid
                 } else {
                     QueryOutputFormat::Default
                 }
@@ -101,6 +138,17 @@ impl CommonQueryOptions {
         }
     }
 
+    pub fn graph_options(&self) -> QueryGraphOptions {
+        QueryGraphOptions {
+            cluster: match self.graph_cluster {
+                None => query_graph_options::Cluster::NoCluster,
+                Some(GraphClusterArg::Package) => query_graph_options::Cluster::Package,
+                Some(GraphClusterArg::RuleType) => query_graph_options::Cluster::RuleType,
+            } as i32,
+            max_depth: self.graph_max_depth,
+        }
+    }
+
     pub fn get_query(&self) -> (String, Vec<String>) {
         if self.query.contains(QUERY_PERCENT_SS_PLACEHOLDER) {
             let replacement = Self::args_as_set(&self.query_args);
@@ -18,7 +18,11 @@ use buck2_client_ctx::common::CommonDaemonCommandOptions;
 use buck2_client_ctx::daemon::client::BuckdClientConnector;
 use buck2_client_ctx::daemon::client::StdoutPartialResultHandler;
 use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::path_arg::PathArg;
 use buck2_client_ctx::streaming::StreamingCommand;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::file_name::FileName;
 
 use crate::commands::query::common::CommonQueryOptions;
 
@@ -33,7 +37,8 @@ use crate::commands::query::common::CommonQueryOptions;
 /// provided, any literals will resolve to all matching targets within the universe (which
 /// includes the targets passed as the universe and all transitive deps of them).
 /// When not provided, we implicitly set the universe to be rooted at every target literal
-/// in the `cquery`.
+/// in the `cquery`. A large universe can be read from a file with `--target-universe-file`,
+/// and saved with `--save-target-universe NAME` to be reused as `--target-universe-name NAME`.
 ///
 /// Run `buck2 docs cquery` for more documentation about the functions available in cquery
 /// expressions.
@@ -68,6 +73,21 @@ pub struct CqueryCommand {
     )]
     target_universe: Vec<String>,
 
+    /// File with the targets at which to root the queryable universe, one pattern per line.
+    /// Empty lines and lines starting with `#` are ignored. Added to `--target-universe`.
+    #[clap(long, value_name = "PATH")]
+    target_universe_file: Option<PathArg>,
+
+    /// Add the universe previously saved with `--save-target-universe NAME` to the target universe.
+    #[clap(long, value_name = "NAME")]
+    target_universe_name: Option<String>,
+
+    /// Save the target universe of this query under NAME, for later queries to use with
+    /// `--target-universe-name NAME`. Relative patterns are saved as is, so they are
+    /// interpreted relative to the working directory of the query using them.
+    #[clap(long, value_name = "NAME")]
+    save_target_universe: Option<String>,
+
     #[clap(
         long,
         help = "Show the providers of the query result instead of the attributes and labels"
@@ -89,6 +109,60 @@ pub struct CqueryCommand {
     correct_owner: bool,
 }
 
+#[derive(Debug, thiserror::Error)]
+enum CqueryCommandError {
+    #[error(
+        "Target universe `{0}` was not saved, save it with `--save-target-universe {0}` first"
+    )]
+    UniverseNotSaved(String),
+}
+
+/// Parse a target universe file: one pattern per line, ignoring empty lines and `#` comments.
+fn parse_target_universe_file(contents: &str) -> impl Iterator<Item = String> + '_ {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_owned)
+}
+
+impl CqueryCommand {
+    fn saved_target_universe_path(
+        ctx: &ClientCommandContext<'_>,
+        name: &str,
+    ) -> anyhow::Result<AbsNormPathBuf> {
+        Ok(ctx
+            .paths()?
+            .target_universes_dir()
+            .join(FileName::new(name)?))
+    }
+
+    /// The target universe from `--target-universe`, `--target-universe-file` and
+    /// `--target-universe-name`, saved if `--save-target-universe` is passed.
+    fn target_universe(&self, ctx: &ClientCommandContext<'_>) -> anyhow::Result<Vec<String>> {
+        let mut target_universe = self.target_universe.clone();
+        if let Some(file) = &self.target_universe_file {
+            let contents = fs_util::read_to_string(file.resolve(&ctx.working_dir))?;
+            target_universe.extend(parse_target_universe_file(&contents));
+        }
+        if let Some(name) = &self.target_universe_name {
+            let contents =
+                fs_util::read_to_string_if_exists(Self::saved_target_universe_path(ctx, name)?)?
+                    .ok_or_else(|| CqueryCommandError::UniverseNotSaved(name.clone()))?;
+            target_universe.extend(parse_target_universe_file(&contents));
+        }
+        if let Some(name) = &self.save_target_universe {
+            fs_util::create_dir_all(ctx.paths()?.target_universes_dir())?;
+            let contents: String = target_universe
+                .iter()
+                .map(|pattern| format!("{}\n", pattern))
+                .collect();
+            fs_util::write(Self::saved_target_universe_path(ctx, name)?, contents)?;
+        }
+        Ok(target_universe)
+    }
+}
+
 #[async_trait]
 impl StreamingCommand for CqueryCommand {
     const COMMAND_NAME: &'static str = "cquery";
@@ -103,6 +177,7 @@ impl StreamingCommand for CqueryCommand {
         let unstable_output_format = self.query_common.output_format() as i32;
         let output_attributes = self.query_common.attributes.get()?;
         let context = ctx.client_context(matches, &self)?;
+        let target_universe = self.target_universe(ctx)?;
 
         let correct_owner = match (self.correct_owner, self.deprecated_owner) {
             (true, false) => true,
@@ -123,9 +198,10 @@ impl StreamingCommand for CqueryCommand {
                     query_args,
                     context: Some(context),
                     output_attributes,
-                    target_universe: self.target_universe,
+                    target_universe,
                     show_providers: self.show_providers,
                     unstable_output_format,
+                    graph_options: Some(self.query_common.graph_options()),
                     correct_owner,
                 },
                 ctx.stdin()
@@ -149,3 +225,17 @@ impl StreamingCommand for CqueryCommand {
         &self.common_opts.config_opts
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::commands::query::cquery::parse_target_universe_file;
+
+    #[test]
+    fn test_parse_target_universe_file() {
+        let contents = "# The universe\n//foo/...\n\n  //bar:baz  \n# //qux/...\n";
+        assert_eq!(
+            vec!["//foo/...", "//bar:baz"],
+            parse_target_universe_file(contents).collect::<Vec<_>>()
+        );
+    }
+}
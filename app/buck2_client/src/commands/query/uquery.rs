@@ -85,6 +85,7 @@ impl StreamingCommand for UqueryCommand {
                     context: Some(context),
                     output_attributes,
                     unstable_output_format,
+                    graph_options: Some(self.query_common.graph_options()),
                 },
                 ctx.stdin()
                     .console_interaction_stream(&self.common_opts.console_opts),
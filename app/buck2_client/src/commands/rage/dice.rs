@@ -7,38 +7,31 @@
  * of this source tree.
  */
 
-use std::path::Path;
-
 use anyhow::Context;
 use buck2_cli_proto::unstable_dice_dump_request::DiceDumpFormat;
 use buck2_cli_proto::UnstableDiceDumpRequest;
 use buck2_client_ctx::daemon::client::connect::BootstrapBuckdClient;
 use buck2_client_ctx::daemon::client::BuckdClientConnector;
-use buck2_client_ctx::manifold::Bucket;
-use buck2_client_ctx::manifold::ManifoldClient;
+use buck2_client_ctx::diagnostics::DiagnosticArtifact;
+use buck2_client_ctx::diagnostics::DiagnosticContents;
+use buck2_client_ctx::diagnostics::DiagnosticKind;
+use buck2_client_ctx::diagnostics::DiagnosticsDestination;
 use buck2_core::fs::fs_util::create_dir_all;
 use buck2_core::fs::fs_util::remove_all;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::paths::abs_path::AbsPathBuf;
-use buck2_util::process::async_background_command;
-
-use crate::commands::rage::manifold::manifold_leads;
 
 pub async fn upload_dice_dump(
     buckd: BootstrapBuckdClient,
     buck_out_dice: AbsNormPathBuf,
-    manifold: &ManifoldClient,
+    diagnostics: &DiagnosticsDestination,
     manifold_id: &String,
 ) -> anyhow::Result<String> {
     let buckd = buckd.with_subscribers(Default::default());
-    let manifold_bucket = Bucket::RAGE_DUMPS;
-    let manifold_filename = format!("flat/{}_dice-dump.tar", manifold_id);
     let this_dump_folder_name = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S").to_string();
     DiceDump::new(buck_out_dice, &this_dump_folder_name)
-        .upload(buckd, manifold, manifold_bucket, &manifold_filename)
-        .await?;
-
-    Ok(manifold_leads(&manifold_bucket, manifold_filename))
+        .upload(buckd, diagnostics, manifold_id)
+        .await
 }
 
 struct DiceDump {
@@ -59,10 +52,9 @@ impl DiceDump {
     async fn upload(
         &self,
         mut buckd: BuckdClientConnector<'_>,
-        manifold: &ManifoldClient,
-        manifold_bucket: Bucket,
-        manifold_filename: &str,
-    ) -> anyhow::Result<()> {
+        diagnostics: &DiagnosticsDestination,
+        manifold_id: &str,
+    ) -> anyhow::Result<String> {
         create_dir_all(&self.buck_out_dice).with_context(|| {
             format!(
                 "Failed to create directory `{}`, no DICE dump will be created",
@@ -84,47 +76,20 @@ impl DiceDump {
                 )
             })?;
 
-        // create DICE dump name using the old command being rage on and the trace id of this rage command.
-        upload_to_manifold(
-            &self.dump_folder,
-            manifold,
-            manifold_bucket,
-            manifold_filename,
-        )
-        .await
-        .with_context(|| "Failed during manifold upload!")?;
-
-        Ok(())
-    }
-}
-
-async fn upload_to_manifold(
-    dump_folder: &Path,
-    manifold: &ManifoldClient,
-    manifold_bucket: Bucket,
-    manifold_filename: &str,
-) -> anyhow::Result<()> {
-    if !cfg!(target_os = "windows") {
+        if cfg!(target_os = "windows") {
+            return Ok("DICE dumps are not uploaded on Windows".to_owned());
+        }
         buck2_core::facebook_only();
 
-        let tar = async_background_command("tar")
-            .arg("-c")
-            .arg(dump_folder)
-            .stdin(std::process::Stdio::null())
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::null())
-            .spawn()?;
-
-        manifold
-            .read_and_upload(
-                manifold_bucket,
-                manifold_filename,
-                Default::default(),
-                &mut tar.stdout.unwrap(),
-            )
-            .await?;
+        diagnostics
+            .store(&DiagnosticArtifact::new(
+                DiagnosticKind::DiceDump,
+                manifold_id,
+                DiagnosticContents::Directory(self.dump_folder.clone()),
+            ))
+            .await
+            .with_context(|| "Failed during manifold upload!")
     }
-    Ok(())
 }
 
 impl Drop for DiceDump {
@@ -14,20 +14,21 @@ use buck2_audit::AuditCommand;
 use buck2_cli_proto::ClientContext;
 use buck2_client_ctx::command_outcome::CommandOutcome;
 use buck2_client_ctx::daemon::client::connect::BootstrapBuckdClient;
+use buck2_client_ctx::diagnostics::DiagnosticArtifact;
+use buck2_client_ctx::diagnostics::DiagnosticKind;
+use buck2_client_ctx::diagnostics::DiagnosticsDestination;
 use buck2_client_ctx::events_ctx::PartialResultCtx;
 use buck2_client_ctx::events_ctx::PartialResultHandler;
-use buck2_client_ctx::manifold::ManifoldClient;
 use buck2_client_ctx::subscribers::subscriber::EventSubscriber;
 use futures::future::BoxFuture;
 use futures::future::Shared;
 
-use crate::commands::rage::manifold::buf_to_manifold;
 use crate::commands::rage::MaterializerRageUploadData;
 
 pub async fn upload_materializer_data(
     buckd: Shared<BoxFuture<'_, buck2_error::Result<BootstrapBuckdClient>>>,
     client_context: &ClientContext,
-    manifold: &ManifoldClient,
+    diagnostics: &DiagnosticsDestination,
     manifold_id: &String,
     materializer_data: MaterializerRageUploadData,
 ) -> anyhow::Result<String> {
@@ -66,8 +67,13 @@ pub async fn upload_materializer_data(
         CommandOutcome::Failure(..) => return Err(anyhow::anyhow!("Command failed")),
     }
 
-    let manifold_filename = format!("flat/{}_materializer_{}", manifold_id, materializer_data);
-    buf_to_manifold(manifold, &capture.buf, manifold_filename).await
+    let kind = match materializer_data {
+        MaterializerRageUploadData::State => DiagnosticKind::MaterializerState,
+        MaterializerRageUploadData::Fsck => DiagnosticKind::MaterializerFsck,
+    };
+    diagnostics
+        .store(&DiagnosticArtifact::bytes(kind, manifold_id, capture.buf))
+        .await
 }
 
 /// Receive StdoutBytes, just capture them.
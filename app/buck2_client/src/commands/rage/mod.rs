@@ -9,7 +9,6 @@
 
 mod build_info;
 mod dice;
-mod manifold;
 mod materializer;
 mod source_control;
 mod system_info;
@@ -25,6 +24,9 @@ use std::time::SystemTime;
 use anyhow::Context;
 use buck2_client_ctx::client_ctx::ClientCommandContext;
 use buck2_client_ctx::daemon::client::connect::BuckdProcessInfo;
+use buck2_client_ctx::diagnostics::DiagnosticArtifact;
+use buck2_client_ctx::diagnostics::DiagnosticKind;
+use buck2_client_ctx::diagnostics::DiagnosticsDestination;
 use buck2_client_ctx::exit_result::ExitResult;
 use buck2_client_ctx::manifold::Bucket;
 use buck2_client_ctx::manifold::ManifoldClient;
@@ -51,8 +53,6 @@ use derive_more::Display;
 use dupe::Dupe;
 use futures::future::FutureExt;
 use futures::future::LocalBoxFuture;
-use manifold::file_to_manifold;
-use manifold::manifold_leads;
 use serde::Serialize;
 use thiserror::Error;
 use tokio::io::AsyncBufRead;
@@ -126,7 +126,10 @@ impl RageCommand {
         let client_ctx = ctx.empty_client_context("rage")?;
 
         // Don't fail the rage if you can't figure out whether to do vpnless.
-        let manifold = ManifoldClient::new(ctx.allow_vpnless_for_logging().unwrap_or_default())?;
+        let diagnostics = DiagnosticsDestination::Manifold {
+            client: ManifoldClient::new(ctx.allow_vpnless_for_logging().unwrap_or_default())?,
+            bucket: Bucket::RAGE_DUMPS,
+        };
 
         let rage_id = TraceId::new();
         let mut manifold_id = format!("{}", rage_id);
@@ -162,7 +165,7 @@ impl RageCommand {
         buck2_client_ctx::eprintln!("Collecting debug info...")?;
 
         let thread_dump = self.section("Thread dump", || {
-            thread_dump::upload_thread_dump(&info, &manifold, &manifold_id)
+            thread_dump::upload_thread_dump(&info, &diagnostics, &manifold_id)
         });
         let build_info_command = self.skippable_section(
             "Associated invocation info",
@@ -180,18 +183,23 @@ impl RageCommand {
 
         let system_info_command = self.section("System info", system_info::get);
         let daemon_stderr_command = self.section("Daemon stderr", || {
-            upload_daemon_stderr(stderr_path, &manifold, &manifold_id)
+            upload_daemon_stderr(stderr_path, &diagnostics, &manifold_id)
         });
         let hg_snapshot_id_command = self.section("Source control", source_control::get_info);
         let dice_dump_command = self.section("Dice dump", || async {
-            dice::upload_dice_dump(buckd.clone().await?, dice_dump_dir, &manifold, &manifold_id)
-                .await
+            dice::upload_dice_dump(
+                buckd.clone().await?,
+                dice_dump_dir,
+                &diagnostics,
+                &manifold_id,
+            )
+            .await
         });
         let materializer_state = self.section("Materializer state", || {
             materializer::upload_materializer_data(
                 buckd.clone(),
                 &client_ctx,
-                &manifold,
+                &diagnostics,
                 &manifold_id,
                 MaterializerRageUploadData::State,
             )
@@ -200,7 +208,7 @@ impl RageCommand {
             materializer::upload_materializer_data(
                 buckd.clone(),
                 &client_ctx,
-                &manifold,
+                &diagnostics,
                 &manifold_id,
                 MaterializerRageUploadData::Fsck,
             )
@@ -209,14 +217,14 @@ impl RageCommand {
             "Event log upload",
             selected_invocation
                 .as_ref()
-                .map(|path| || upload_event_logs(path, &manifold, &manifold_id)),
+                .map(|path| || upload_event_logs(path, &diagnostics, &manifold_id)),
         );
 
         let re_logs_command = self.skippable_section(
             "RE logs upload",
             build_info
                 .get_field(|o| o.re_session_id.clone())
-                .map(|id| || upload_re_logs_impl(&manifold, &re_logs_dir, id)),
+                .map(|id| || upload_re_logs_impl(&diagnostics, &re_logs_dir, id)),
         );
 
         let (
@@ -493,32 +501,42 @@ fn insert_if_some<D>(data: &mut HashMap<String, D>, key: &str, value: Option<D>)
 
 async fn upload_daemon_stderr(
     path: AbsNormPathBuf,
-    manifold: &ManifoldClient,
+    diagnostics: &DiagnosticsDestination,
     manifold_id: &str,
 ) -> anyhow::Result<String> {
-    file_to_manifold(manifold, &path, format!("flat/{}.stderr", manifold_id)).await
+    diagnostics
+        .store(&DiagnosticArtifact::file(
+            DiagnosticKind::DaemonStderr,
+            manifold_id,
+            path.as_abs_path(),
+        ))
+        .await
 }
 
 async fn upload_event_logs(
     path: &EventLogPathBuf,
-    manifold: &ManifoldClient,
+    diagnostics: &DiagnosticsDestination,
     manifold_id: &str,
 ) -> anyhow::Result<String> {
-    let filename = format!("flat/{}-event_log{}", manifold_id, path.extension());
-    file_to_manifold(manifold, path.path(), filename).await
+    diagnostics
+        .store(
+            &DiagnosticArtifact::file(DiagnosticKind::EventLog, manifold_id, path.path())
+                .with_extension(path.extension()),
+        )
+        .await
 }
 
 async fn upload_re_logs_impl(
-    manifold: &ManifoldClient,
+    diagnostics: &DiagnosticsDestination,
     re_logs_dir: &AbsNormPath,
     re_session_id: String,
 ) -> anyhow::Result<String> {
-    let bucket = Bucket::RAGE_DUMPS;
-    let filename = format!("flat/{}-re_logs.zst", &re_session_id);
-    upload_re_logs::upload_re_logs(manifold, bucket, re_logs_dir, &re_session_id, &filename)
-        .await?;
-
-    Ok(manifold_leads(&bucket, filename))
+    diagnostics
+        .store(&upload_re_logs::re_logs_artifact(
+            re_logs_dir,
+            &re_session_id,
+        )?)
+        .await
 }
 
 async fn dispatch_result_event(
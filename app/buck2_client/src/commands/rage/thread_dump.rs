@@ -9,14 +9,14 @@
 
 use anyhow::Context;
 use buck2_client_ctx::daemon::client::connect::BuckdProcessInfo;
-use buck2_client_ctx::manifold::ManifoldClient;
+use buck2_client_ctx::diagnostics::DiagnosticArtifact;
+use buck2_client_ctx::diagnostics::DiagnosticKind;
+use buck2_client_ctx::diagnostics::DiagnosticsDestination;
 use buck2_util::process::async_background_command;
 
-use crate::commands::rage::manifold::buf_to_manifold;
-
 pub async fn upload_thread_dump(
     buckd: &buck2_error::Result<BuckdProcessInfo<'_>>,
-    manifold: &ManifoldClient,
+    diagnostics: &DiagnosticsDestination,
     manifold_id: &String,
 ) -> anyhow::Result<String> {
     let buckd_pid = buckd.as_ref().map_err(|e| e.clone())?.pid();
@@ -36,8 +36,13 @@ pub async fn upload_thread_dump(
         .await?;
 
     if command.status.success() {
-        let manifold_filename = format!("flat/{}_thread_dump", manifold_id);
-        buf_to_manifold(manifold, &command.stdout, manifold_filename).await
+        diagnostics
+            .store(&DiagnosticArtifact::bytes(
+                DiagnosticKind::ThreadDump,
+                manifold_id,
+                command.stdout,
+            ))
+            .await
     } else {
         let stderr = &command.stderr;
         Ok(String::from_utf8_lossy(stderr).to_string())
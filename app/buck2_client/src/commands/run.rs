@@ -116,6 +116,7 @@ impl StreamingCommand for RunCommand {
                     final_artifact_materializations: Materializations::Materialize as i32,
                     target_universe: Vec::new(),
                     output_hashes_file: None,
+                    timeout: None,
                 },
                 ctx.stdin()
                     .console_interaction_stream(&self.common_opts.console_opts),
@@ -18,16 +18,33 @@ use buck2_client_ctx::subscribers::stdout_stderr_forwarder::StdoutStderrForwarde
 use buck2_common::argv::Argv;
 use buck2_common::argv::SanitizedArgv;
 use buck2_common::daemon_dir::DaemonDir;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_event_log::file_names::get_local_logs;
+use buck2_event_log::read::EventLogSummary;
+use buck2_event_observer::humanized::HumanizedBytes;
+use chrono::DateTime;
+use chrono::Local;
 use chrono::NaiveDateTime;
 use humantime::format_duration;
 use walkdir::WalkDir;
 
+/// Number of recent invocations listed per daemon by `buck2 status --all`.
+const RECENT_INVOCATIONS: usize = 3;
+
 #[derive(Debug, thiserror::Error)]
 enum StatusError {
     #[error("Incorrect seconds/nanos argument")]
     NativeDateTime,
 }
 
+#[derive(Debug, Clone, Copy, clap::ArgEnum)]
+#[clap(rename_all = "snake_case")]
+enum StatusFormat {
+    Json,
+    Human,
+}
+
 #[derive(Debug, clap::Parser)]
 #[clap(about = "Buckd status")]
 pub struct StatusCommand {
@@ -35,6 +52,18 @@ pub struct StatusCommand {
     snapshot: bool,
     #[clap(long, help = "Enable printing status for all running buckd")]
     all: bool,
+    #[clap(
+        long,
+        help = "Include the commands running on the daemon and the commands waiting for them to finish."
+    )]
+    queue: bool,
+    #[clap(
+        long,
+        arg_enum,
+        default_value = "json",
+        help = "Output format. `human` lists each daemon with its pid, memory usage, active command and recent invocations."
+    )]
+    format: StatusFormat,
 }
 
 impl StatusCommand {
@@ -61,20 +90,36 @@ impl StatusCommand {
                     }
                 }
 
-                let mut statuses = Vec::new();
+                let mut summaries = Vec::new();
                 for dir in daemon_dirs {
                     if let Ok(bootstrap_client) = establish_connection_existing(&dir).await {
-                        statuses.push(process_status(
-                            bootstrap_client
-                                .with_subscribers(vec![Box::new(StdoutStderrForwarder)])
-                                .with_flushing()
-                                .status(self.snapshot)
-                                .await?,
-                        )?);
+                        // Always request a snapshot: it carries the daemon's memory usage.
+                        let status = bootstrap_client
+                            .with_subscribers(vec![Box::new(StdoutStderrForwarder)])
+                            .with_flushing()
+                            .status(true)
+                            .await?;
+                        summaries.push(DaemonSummary::collect(status).await);
                     }
                 }
 
-                buck2_client_ctx::println!("{}", serde_json::to_string_pretty(&statuses)?)?;
+                match self.format {
+                    StatusFormat::Json => {
+                        let statuses = summaries
+                            .into_iter()
+                            .map(|summary| summary.to_json(self.snapshot, self.queue))
+                            .collect::<anyhow::Result<Vec<_>>>()?;
+                        buck2_client_ctx::println!("{}", serde_json::to_string_pretty(&statuses)?)?;
+                    }
+                    StatusFormat::Human => {
+                        if summaries.is_empty() {
+                            buck2_client_ctx::eprintln!("no buckd running")?;
+                        }
+                        for summary in &summaries {
+                            summary.print_human()?;
+                        }
+                    }
+                }
             } else {
                 match ctx
                     .connect_buckd(BuckdConnectOptions::existing_only_no_console())
@@ -84,14 +129,22 @@ impl StatusCommand {
                         buck2_client_ctx::eprintln!("no buckd running")?;
                         // Should this be an error?
                     }
-                    Ok(mut client) => {
-                        let json_status =
-                            process_status(client.with_flushing().status(self.snapshot).await?)?;
-                        buck2_client_ctx::println!(
-                            "{}",
-                            serde_json::to_string_pretty(&json_status)?
-                        )?;
-                    }
+                    Ok(mut client) => match self.format {
+                        StatusFormat::Json => {
+                            let json_status = process_status(
+                                client.with_flushing().status(self.snapshot).await?,
+                                self.queue,
+                            )?;
+                            buck2_client_ctx::println!(
+                                "{}",
+                                serde_json::to_string_pretty(&json_status)?
+                            )?;
+                        }
+                        StatusFormat::Human => {
+                            let status = client.with_flushing().status(true).await?;
+                            DaemonSummary::collect(status).await.print_human()?;
+                        }
+                    },
                 }
             }
 
@@ -116,7 +169,7 @@ fn duration_to_string(duration: Duration) -> String {
     format_duration(duration).to_string()
 }
 
-fn process_status(status: StatusResponse) -> anyhow::Result<serde_json::Value> {
+fn process_status(status: StatusResponse, queue: bool) -> anyhow::Result<serde_json::Value> {
     let timestamp = match status.start_time {
         None => "unknown".to_owned(),
         Some(timestamp) => timestamp_to_string(timestamp.seconds as u64, timestamp.nanos as u32)?,
@@ -129,7 +182,7 @@ fn process_status(status: StatusResponse) -> anyhow::Result<serde_json::Value> {
         }
     };
 
-    Ok(serde_json::json!({
+    let mut json_status = serde_json::json!({
         "start_time": timestamp,
         "uptime": uptime,
         "process_info": serde_json::to_value(status.process_info)?,
@@ -139,7 +192,131 @@ fn process_status(status: StatusResponse) -> anyhow::Result<serde_json::Value> {
         "isolation_dir": status.isolation_dir,
         "forkserver_pid": serde_json::to_value(status.forkserver_pid)?,
         "supports_vpnless": status.supports_vpnless.unwrap_or_default(),
-    }))
+    });
+    if queue {
+        json_status["command_queue"] = serde_json::to_value(status.command_queue)?;
+    }
+    Ok(json_status)
+}
+
+/// Status of one daemon, together with the invocations it ran most recently.
+struct DaemonSummary {
+    status: StatusResponse,
+    /// Newest first.
+    recent_invocations: Vec<EventLogSummary>,
+}
+
+impl DaemonSummary {
+    async fn collect(status: StatusResponse) -> Self {
+        let recent_invocations = match recent_invocations(&status).await {
+            Ok(recent_invocations) => recent_invocations,
+            Err(e) => {
+                tracing::debug!("Failed to read recent invocations: {:#}", e);
+                Vec::new()
+            }
+        };
+        DaemonSummary {
+            status,
+            recent_invocations,
+        }
+    }
+
+    fn rss(&self) -> Option<u64> {
+        self.status.snapshot.as_ref().and_then(|s| s.buck2_rss)
+    }
+
+    fn active_commands(&self) -> impl Iterator<Item = &str> {
+        self.status
+            .command_queue
+            .iter()
+            .filter(|c| !c.waiting)
+            .map(|c| c.argv.as_str())
+    }
+
+    fn to_json(mut self, snapshot: bool, queue: bool) -> anyhow::Result<serde_json::Value> {
+        let rss = self.rss();
+        let active_commands = self
+            .active_commands()
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+        let recent_invocations = self
+            .recent_invocations
+            .iter()
+            .map(|invocation| {
+                let timestamp: DateTime<Local> = invocation.timestamp.into();
+                serde_json::json!({
+                    "trace_id": invocation.trace_id.to_string(),
+                    "timestamp": timestamp.to_rfc3339(),
+                    "command": invocation.invocation.display_command_line(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if !snapshot {
+            self.status.snapshot = None;
+        }
+        let mut json_status = process_status(self.status, queue)?;
+        json_status["rss"] = serde_json::to_value(rss)?;
+        json_status["active_commands"] = serde_json::to_value(active_commands)?;
+        json_status["recent_invocations"] = serde_json::Value::Array(recent_invocations);
+        Ok(json_status)
+    }
+
+    fn print_human(&self) -> anyhow::Result<()> {
+        let pid = self.status.process_info.as_ref().map(|p| p.pid);
+        let uptime = self.status.uptime.as_ref().map(|uptime| {
+            duration_to_string(Duration::new(uptime.seconds as u64, uptime.nanos as u32))
+        });
+        buck2_client_ctx::println!(
+            "{} (isolation dir `{}`)",
+            self.status.project_root,
+            self.status.isolation_dir
+        )?;
+        buck2_client_ctx::println!(
+            "  pid: {}, rss: {}, uptime: {}",
+            pid.map_or_else(|| "unknown".to_owned(), |pid| pid.to_string()),
+            self.rss().map_or_else(
+                || "unknown".to_owned(),
+                |rss| HumanizedBytes::new(rss).to_string()
+            ),
+            uptime.as_deref().unwrap_or("unknown"),
+        )?;
+        let mut active_commands = self.active_commands().peekable();
+        if active_commands.peek().is_none() {
+            buck2_client_ctx::println!("  active: none")?;
+        }
+        for command in active_commands {
+            buck2_client_ctx::println!("  active: {}", command)?;
+        }
+        for invocation in &self.recent_invocations {
+            let timestamp: DateTime<Local> = invocation.timestamp.into();
+            buck2_client_ctx::println!(
+                "  recent: {}  {}",
+                timestamp.format("%Y-%m-%d %H:%M:%S"),
+                invocation.invocation.display_command_line()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The most recent invocations logged in the daemon's `buck-out`, newest first.
+async fn recent_invocations(status: &StatusResponse) -> anyhow::Result<Vec<EventLogSummary>> {
+    let log_dir = AbsNormPathBuf::from(status.project_root.clone())?
+        .join(ForwardRelativePath::new("buck-out")?)
+        .join(ForwardRelativePath::new(&status.isolation_dir)?)
+        .join(ForwardRelativePath::new("log")?);
+    let mut summaries = Vec::new();
+    for log in get_local_logs(&log_dir)?
+        .into_iter()
+        .rev()
+        .take(RECENT_INVOCATIONS)
+    {
+        if let Ok(summary) = log.get_summary().await {
+            summaries.push(summary);
+        }
+    }
+    Ok(summaries)
 }
 
 #[cfg(test)]
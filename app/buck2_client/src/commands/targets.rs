@@ -37,6 +37,14 @@ enum TargetsError {
     /// Clap should report it, but if we missed something, this is a fallback.
     #[error("Flags are mutually exclusive")]
     IncompatibleArguments,
+    #[error(
+        "`--output-format jsonl` streams targets, so it cannot be used with `--show-target-hash`"
+    )]
+    JsonlWithTargetHash,
+    #[error(
+        "`--output-format target_hashes` requires `--show-target-hash` or `--show-unconfigured-target-hash`"
+    )]
+    TargetHashesWithoutHash,
 }
 
 // Use non-camel case so the possible values match buck1's
@@ -49,6 +57,16 @@ enum TargetHashFileMode {
     None,
 }
 
+#[derive(Debug, clap::ArgEnum, Clone, Copy, Dupe)]
+#[clap(rename_all = "snake_case")]
+enum TargetsOutputFormatArg {
+    Text,
+    Json,
+    Jsonl,
+    Stats,
+    TargetHashes,
+}
+
 #[derive(Debug, clap::ArgEnum, Clone, Dupe)]
 enum TargetHashGraphType {
     None,
@@ -91,6 +109,20 @@ pub struct TargetsCommand {
     #[clap(long)]
     stats: bool,
 
+    /// Output format, an alternative to `--json`, `--json-lines` and `--stats`.
+    /// `jsonl` implies `--streaming`: each target is written as soon as its package is loaded.
+    /// `target_hashes` prints a JSON object whose `targets` key maps each target to its hash, and
+    /// whose `errors` key maps each package which failed to load to its error. It requires
+    /// `--show-target-hash` or `--show-unconfigured-target-hash`.
+    #[clap(
+        long,
+        arg_enum,
+        ignore_case = true,
+        value_name = "text|json|jsonl|stats|target_hashes",
+        conflicts_with_all = &["json", "json-lines", "stats"]
+    )]
+    output_format: Option<TargetsOutputFormatArg>,
+
     /// Print the fully-qualified build target for the specified aliases
     #[clap(long, alias = "resolvealias")]
     resolve_alias: bool,
@@ -166,6 +198,25 @@ pub struct TargetsCommand {
     #[clap(long, requires = "streaming")]
     imports: bool,
 
+    /// Resolve the `select()`s in attributes, by configuring each target for the target
+    /// platform, so that attributes show the values the targets are built with. Targets
+    /// incompatible with the platform show their unresolved attributes.
+    #[clap(long, requires = "streaming")]
+    resolve_attributes: bool,
+
+    /// Only output the targets of packages which changed since the export that wrote this
+    /// manifest (with `--write-manifest`), and a `buck.removed` entry for each package of the
+    /// manifest which no longer exists. Packages whose build file, `PACKAGE` files, loaded
+    /// `.bzl` files and file names did not change are not loaded at all, unless a buckconfig
+    /// changed. Use the same patterns as that export.
+    #[clap(long, value_name = "PATH", requires = "streaming")]
+    previous_manifest: Option<PathArg>,
+
+    /// Write a manifest of this export, recording a fingerprint of the targets of each package,
+    /// to be passed to `--previous-manifest` by the next export.
+    #[clap(long, value_name = "PATH", requires = "streaming")]
+    write_manifest: Option<PathArg>,
+
     /// Show the package values. Produces an additional attribute representing all the package values
     /// for the package containing the target.
     #[clap(long, conflicts_with = "package-values-regex")]
@@ -195,7 +246,28 @@ pub struct TargetsCommand {
 impl TargetsCommand {
     #[allow(clippy::if_same_then_else)]
     fn output_format(&self) -> anyhow::Result<OutputFormat> {
-        if self.json {
+        if let Some(output_format) = self.output_format {
+            if self.json || self.json_lines || self.stats {
+                return Err(TargetsError::IncompatibleArguments.into());
+            }
+            Ok(match output_format {
+                TargetsOutputFormatArg::Text => OutputFormat::Text,
+                TargetsOutputFormatArg::Json => OutputFormat::Json,
+                TargetsOutputFormatArg::Jsonl => {
+                    if self.show_target_hash {
+                        return Err(TargetsError::JsonlWithTargetHash.into());
+                    }
+                    OutputFormat::JsonLines
+                }
+                TargetsOutputFormatArg::Stats => OutputFormat::Stats,
+                TargetsOutputFormatArg::TargetHashes => {
+                    if !self.show_target_hash && !self.show_unconfigured_target_hash {
+                        return Err(TargetsError::TargetHashesWithoutHash.into());
+                    }
+                    OutputFormat::TargetHashes
+                }
+            })
+        } else if self.json {
             if self.json_lines || self.stats {
                 return Err(TargetsError::IncompatibleArguments.into());
             }
@@ -311,10 +383,22 @@ impl StreamingCommand for TargetsCommand {
                     include_default_attributes: self.include_defaults,
                     target_hash_recursive: self.target_hash_recursive,
                     keep_going: self.keep_going,
-                    streaming: self.streaming,
+                    streaming: self.streaming
+                        || matches!(self.output_format, Some(TargetsOutputFormatArg::Jsonl)),
                     cached: !self.no_cache,
                     imports: self.imports,
                     package_values,
+                    resolve_attributes: self.resolve_attributes,
+                    previous_manifest: self
+                        .previous_manifest
+                        .as_ref()
+                        .map(|x| x.resolve(&ctx.working_dir).into_string())
+                        .transpose()?,
+                    write_manifest: self
+                        .write_manifest
+                        .as_ref()
+                        .map(|x| x.resolve(&ctx.working_dir).into_string())
+                        .transpose()?,
                 })
             }),
             output: self
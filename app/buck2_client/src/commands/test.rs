@@ -7,9 +7,14 @@
  * of this source tree.
  */
 
+use std::collections::BTreeMap;
+
 use anyhow::Context;
 use async_trait::async_trait;
+use buck2_cli_proto::test_impact_request::RecordedTestDeps;
+use buck2_cli_proto::test_response::TestImpactReport;
 use buck2_cli_proto::CounterWithExamples;
+use buck2_cli_proto::TestImpactRequest;
 use buck2_cli_proto::TestRequest;
 use buck2_cli_proto::TestSessionOptions;
 use buck2_client_ctx::client_ctx::ClientCommandContext;
@@ -28,6 +33,8 @@ use buck2_client_ctx::stdio::eprint_line;
 use buck2_client_ctx::streaming::StreamingCommand;
 use buck2_client_ctx::subscribers::superconsole::test::span_from_build_failure_count;
 use buck2_client_ctx::subscribers::superconsole::test::TestCounterColumn;
+use buck2_common::vcs::detect_vcs;
+use buck2_common::vcs::VcsError;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::working_dir::WorkingDir;
 use gazebo::prelude::*;
@@ -65,6 +72,71 @@ fn print_error_counter(
     }
     Ok(())
 }
+
+fn print_quarantined_counter(
+    console: &FinalConsole,
+    counter: &CounterWithExamples,
+) -> anyhow::Result<()> {
+    if counter.count > 0 {
+        console.print_warning(&format!(
+            "{} TESTS QUARANTINED (known flaky, failures ignored)",
+            counter.count
+        ))?;
+        for test_name in &counter.example_tests {
+            console.print_warning(&format!("  ~ {}", test_name))?;
+        }
+        if counter.count > counter.max {
+            console.print_warning(&format!(
+                "  ...and {} more not shown...",
+                counter.count - counter.max
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+fn print_impact_report(console: &FinalConsole, report: &TestImpactReport) -> anyhow::Result<()> {
+    console.print_stderr(&format!(
+        "Test impact analysis against `{}`: {} changed files, {} tests selected, {} tests skipped",
+        report.revision, report.changed_files, report.impacted, report.skipped
+    ))?;
+    if report.all_impacted {
+        console.print_warning(
+            "Changed files affect every target (e.g. `.bzl` files or buckconfigs), all tests were selected",
+        )?;
+    }
+    Ok(())
+}
+
+fn write_impact_report(
+    report: &TestImpactReport,
+    path_arg: &PathArg,
+    working_dir: &WorkingDir,
+) -> anyhow::Result<()> {
+    let json = serde_json::json!({
+        "revision": report.revision,
+        "changed_files": report.changed_files,
+        "impacted": report.impacted,
+        "skipped": report.skipped,
+        "all_impacted": report.all_impacted,
+    });
+    fs_util::write(
+        path_arg.resolve(working_dir),
+        serde_json::to_string_pretty(&json)?,
+    )
+    .context("Failed to write test impact report to path")
+}
+
+/// Parse recorded test dependencies: a JSON object from test target labels to the files, relative
+/// to the project root, each test depends on.
+fn parse_recorded_deps(json: &str) -> anyhow::Result<Vec<RecordedTestDeps>> {
+    let deps: BTreeMap<String, Vec<String>> = serde_json::from_str(json)?;
+    Ok(deps
+        .into_iter()
+        .map(|(target, files)| RecordedTestDeps { target, files })
+        .collect())
+}
+
 #[derive(Debug, clap::Parser)]
 #[clap(name = "test", about = "Build and test the specified targets")]
 pub struct TestCommand {
@@ -90,6 +162,15 @@ If include patterns are present, regardless of whether exclude patterns are pres
     )]
     include: Vec<String>,
 
+    #[clap(
+        long = "label-expr",
+        value_name = "EXPR",
+        conflicts_with_all = &["include", "exclude"],
+        help = "Boolean expression over labels selecting which tests to run, e.g. `unit & !(slow | flaky)`. \
+Supports `&`, `|`, `!` and parentheses. Cannot be combined with `--include` or `--exclude`."
+    )]
+    label_expr: Option<String>,
+
     #[clap(
         long = "always-exclude",
         alias = "always_exclude",
@@ -103,6 +184,25 @@ If include patterns are present, regardless of whether exclude patterns are pres
     )]
     build_filtered_targets: bool, // TODO(bobyf) this flag should always override the buckconfig option when we use it
 
+    /// Only run the tests which may be affected by the files changed between this source control
+    /// revision and the working copy.
+    ///
+    /// A test is affected if a changed file is an input or build file of the test or of one of its
+    /// transitive dependencies, or a source file its test info refers to. Changes to `.bzl` files,
+    /// `PACKAGE` files or buckconfigs select every test.
+    #[clap(long, value_name = "REV")]
+    impacted_by: Option<String>,
+
+    /// Write a JSON report of how many tests test impact analysis ran and skipped to this path.
+    #[clap(long, value_name = "PATH", requires = "impacted-by")]
+    impact_report: Option<PathArg>,
+
+    /// Dependencies of tests recorded in earlier runs that the target graph does not know about,
+    /// e.g. files read at runtime: a JSON object from test target labels to lists of files relative
+    /// to the project root. A test is also affected if one of its recorded files changed.
+    #[clap(long, value_name = "PATH", requires = "impacted-by")]
+    impact_recorded_deps: Option<PathArg>,
+
     /// This option does nothing. It is here to keep compatibility with Buck1 and ci
     #[allow(unused)] // for v1 compat
     #[clap(long = "deep")]
@@ -168,6 +268,26 @@ impl StreamingCommand for TestCommand {
         matches: &clap::ArgMatches,
         ctx: &mut ClientCommandContext<'_>,
     ) -> ExitResult {
+        let impact = match &self.impacted_by {
+            Some(revision) => {
+                let project_root = ctx.paths()?.project_root().root().to_owned();
+                let vcs = detect_vcs(&project_root).ok_or(VcsError::NoRepository)?;
+                let recorded_deps = match &self.impact_recorded_deps {
+                    Some(path) => parse_recorded_deps(
+                        &fs_util::read_to_string(path.resolve(&ctx.working_dir))
+                            .context("Failed to read recorded test dependencies")?,
+                    )
+                    .context("Failed to parse recorded test dependencies")?,
+                    None => Vec::new(),
+                };
+                Some(TestImpactRequest {
+                    revision: revision.clone(),
+                    changed_files: vcs.changed_files(revision).await?,
+                    recorded_deps,
+                })
+            }
+            None => None,
+        };
         let context = ctx.client_context(matches, &self)?;
         let response = buckd
             .with_flushing()
@@ -181,10 +301,12 @@ impl StreamingCommand for TestCommand {
                     excluded_labels: self.exclude,
                     included_labels: self.include,
                     always_exclude: self.always_exclude,
+                    label_expression: self.label_expr.unwrap_or_default(),
                     build_filtered_targets: self.build_filtered_targets,
                     // we don't currently have a different flag for this, so just use the build one.
                     concurrency: self.build_opts.num_threads.unwrap_or(0),
                     build_opts: Some(self.build_opts.to_proto()),
+                    impact,
                     session_options: Some(TestSessionOptions {
                         allow_re: self.unstable_allow_compatible_tests_on_re
                             || self.unstable_allow_all_tests_on_re,
@@ -211,6 +333,10 @@ impl StreamingCommand for TestCommand {
         let failed = statuses.failed.as_ref().context("Missing `failed`")?;
         let fatals = statuses.fatals.as_ref().context("Missing `fatals`")?;
         let skipped = statuses.skipped.as_ref().context("Missing `skipped`")?;
+        let quarantined = statuses
+            .quarantined
+            .as_ref()
+            .context("Missing `quarantined`")?;
 
         let console = self.common_opts.console_opts.final_console();
         print_build_result(&console, &response.errors)?;
@@ -244,10 +370,18 @@ impl StreamingCommand for TestCommand {
         print_error_counter(&console, listing_failed, "LISTINGS FAILED", "⚠")?;
         print_error_counter(&console, failed, "TESTS FAILED", "✗")?;
         print_error_counter(&console, fatals, "TESTS FATALS", "⚠")?;
-        if passed.count + failed.count + fatals.count + skipped.count == 0 {
+        print_quarantined_counter(&console, quarantined)?;
+        if passed.count + failed.count + fatals.count + skipped.count + quarantined.count == 0 {
             console.print_warning("NO TESTS RAN")?;
         }
 
+        if let Some(report) = &response.impact_report {
+            print_impact_report(&console, report)?;
+            if let Some(path) = &self.impact_report {
+                write_impact_report(report, path, &ctx.working_dir)?;
+            }
+        }
+
         let info_messages = response.executor_info_messages;
         for message in info_messages {
             console.print_stderr(message.as_str())?;
@@ -293,3 +427,51 @@ impl StreamingCommand for TestCommand {
         &self.common_opts.config_opts
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use buck2_cli_proto::test_impact_request::RecordedTestDeps;
+    use clap::Parser;
+
+    use crate::commands::test::parse_recorded_deps;
+    use crate::commands::test::TestCommand;
+
+    #[test]
+    fn test_labels_is_include() {
+        let command = TestCommand::parse_from(["test", "--labels", "foo", "!bar"]);
+        assert_eq!(vec!["foo".to_owned(), "!bar".to_owned()], command.include);
+        assert_eq!(None, command.label_expr);
+    }
+
+    #[test]
+    fn test_parse_recorded_deps() {
+        let deps =
+            parse_recorded_deps(r#"{"root//b:test": ["b/data.txt"], "root//a:test": []}"#).unwrap();
+        assert_eq!(
+            vec![
+                RecordedTestDeps {
+                    target: "root//a:test".to_owned(),
+                    files: Vec::new(),
+                },
+                RecordedTestDeps {
+                    target: "root//b:test".to_owned(),
+                    files: vec!["b/data.txt".to_owned()],
+                },
+            ],
+            deps
+        );
+        assert!(parse_recorded_deps(r#"["b/data.txt"]"#).is_err());
+    }
+
+    #[test]
+    fn test_label_expr() {
+        let command = TestCommand::parse_from(["test", "--label-expr", "unit & !slow", "//:t"]);
+        assert_eq!(Some("unit & !slow".to_owned()), command.label_expr);
+        assert!(command.include.is_empty());
+
+        assert!(
+            TestCommand::try_parse_from(["test", "--label-expr", "unit", "--include", "foo"])
+                .is_err()
+        );
+    }
+}
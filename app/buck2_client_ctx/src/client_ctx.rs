@@ -14,6 +14,7 @@ use buck2_cli_proto::client_context::HostArchOverride as GrpcHostArchOverride;
 use buck2_cli_proto::client_context::HostPlatformOverride as GrpcHostPlatformOverride;
 use buck2_cli_proto::ClientContext;
 use buck2_common::argv::Argv;
+use buck2_common::compression::CompressionConfig;
 use buck2_common::invocation_paths::InvocationPaths;
 use buck2_core::error::buck2_hard_error_env;
 use buck2_core::fs::working_dir::WorkingDir;
@@ -161,6 +162,11 @@ impl<'a> ClientCommandContext<'a> {
             _ => None,
         };
 
+        let parent_action = match std::env::var("BUCK2_ACTION_KEY") {
+            Ok(parent_action) => Some(parent_action),
+            _ => None,
+        };
+
         Ok(ClientContext {
             working_dir: self
                 .working_dir
@@ -181,6 +187,7 @@ impl<'a> ClientCommandContext<'a> {
             trace_id: format!("{}", self.trace_id),
             reuse_current_config: false,
             daemon_uuid,
+            parent_action,
             sanitized_argv: Vec::new(),
             argfiles: Vec::new(),
             buck2_hard_error: buck2_hard_error_env()?.unwrap_or_default().to_owned(),
@@ -204,4 +211,17 @@ impl<'a> ClientCommandContext<'a> {
             .daemon_startup_config()?
             .allow_vpnless_for_logging)
     }
+
+    pub fn event_log_compression(&self) -> anyhow::Result<CompressionConfig> {
+        self.immediate_config
+            .daemon_startup_config()?
+            .event_log_compression()
+    }
+
+    pub fn record_vcs_revision(&self) -> anyhow::Result<bool> {
+        Ok(self
+            .immediate_config
+            .daemon_startup_config()?
+            .record_vcs_revision)
+    }
 }
@@ -129,6 +129,13 @@ pub struct CommonDaemonCommandOptions {
     /// regarding the stability of the format.
     #[clap(long, value_name = "PATH")]
     pub(crate) unstable_write_invocation_record: Option<PathArg>,
+
+    /// Cancel the command if it takes longer than this (e.g. `30m`), and exit with a dedicated
+    /// exit code. The event log is finalized as for a Ctrl-C. `buck2 build` still writes its
+    /// build report, in which the targets that did not finish are marked as `CANCELED`.
+    #[clap(long, value_name = "DURATION")]
+    #[serde(skip)]
+    pub(crate) timeout: Option<humantime::Duration>,
 }
 
 impl CommonDaemonCommandOptions {
@@ -138,9 +145,14 @@ impl CommonDaemonCommandOptions {
             no_event_log: false,
             write_build_id: None,
             unstable_write_invocation_record: None,
+            timeout: None,
         };
         &DEFAULT
     }
+
+    pub fn timeout(&self) -> Option<std::time::Duration> {
+        self.timeout.map(|t| *t)
+    }
 }
 
 /// Defines options for config and configuration related things. Any command that involves the build graph should include these options.
@@ -439,6 +451,12 @@ pub struct CommonBuildOptions {
     /// Materializes inputs for failed actions which ran on RE
     #[clap(long)]
     materialize_failed_inputs: bool,
+
+    /// Run local actions at a reduced CPU and IO priority, and by default only half as many of
+    /// them concurrently, so that a background build doesn't slow down interactive use of the
+    /// machine.
+    #[clap(long)]
+    low_priority: bool,
 }
 
 impl CommonBuildOptions {
@@ -482,6 +500,7 @@ impl CommonBuildOptions {
             skip_missing_targets: self.skip_missing_targets,
             skip_incompatible_targets: self.skip_incompatible_targets,
             materialize_failed_inputs: self.materialize_failed_inputs,
+            low_priority: self.low_priority,
         }
     }
 }
@@ -23,6 +23,7 @@ use buck2_common::client_utils::get_channel_tcp;
 use buck2_common::client_utils::get_channel_uds;
 use buck2_common::daemon_dir::DaemonDir;
 use buck2_common::invocation_paths::InvocationPaths;
+use buck2_common::legacy_configs::init::is_endpoint_shared;
 use buck2_common::legacy_configs::init::DaemonStartupConfig;
 use buck2_core::buck2_env;
 use buck2_util::process::async_background_command;
@@ -508,7 +509,16 @@ impl BootstrapBuckdClient {
     }
 
     pub async fn kill(&mut self, reason: &str) -> anyhow::Result<kill::KillResponse> {
-        kill::kill(&mut self.client, &self.info, reason).await
+        kill::kill(&mut self.client, &self.info, reason, None).await
+    }
+
+    /// Like `kill`, but lets in-flight commands finish (up to `timeout`) first.
+    pub async fn drain_and_kill(
+        &mut self,
+        reason: &str,
+        timeout: Duration,
+    ) -> anyhow::Result<kill::KillResponse> {
+        kill::kill(&mut self.client, &self.info, reason, Some(timeout)).await
     }
 
     async fn kill_for_constraints_mismatch(&mut self) -> anyhow::Result<kill::KillResponse> {
@@ -731,6 +741,8 @@ impl<'a> BuckdProcessInfo<'a> {
         let location = daemon_dir.buckd_info();
         let file = File::open(&location)
             .with_context(|| format!("Trying to open buckd info, `{}`", location.display()))?;
+        #[cfg(unix)]
+        check_daemon_owner(&file, &location)?;
         let reader = BufReader::new(file);
         let info =serde_json::from_reader(reader).with_context(|| {
             format!(
@@ -810,6 +822,51 @@ enum BuckdConnectError {
     },
     #[error("Error connecting to the daemon, daemon stderr follows:\n{stderr}")]
     ConnectError { stderr: String },
+    #[error(
+        "The buck2 daemon described by `{path}` belongs to uid {owner}, but this command runs as uid {current}. \
+        Daemons are only shared with other users when their owner sets `buck2.endpoint_permissions` to let them read it: \
+        run buck2 as uid {owner}, or use a different `--isolation-dir`."
+    )]
+    DaemonOwnedByOtherUser {
+        path: String,
+        owner: u32,
+        current: u32,
+    },
+}
+
+/// Reject daemons started by another user, rather than failing later with a confusing
+/// authentication or permission error, unless their owner shared them.
+#[cfg(unix)]
+fn check_daemon_owner(file: &File, location: &std::path::Path) -> anyhow::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = file.metadata()?;
+    check_daemon_access(
+        location,
+        metadata.uid(),
+        metadata.mode(),
+        nix::unistd::geteuid().as_raw(),
+    )
+}
+
+/// Whether the user `current` may use the daemon described by a file owned by `owner` with mode
+/// `mode`, which is set from `buck2.endpoint_permissions`.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn check_daemon_access(
+    location: &std::path::Path,
+    owner: u32,
+    mode: u32,
+    current: u32,
+) -> anyhow::Result<()> {
+    if owner != current && !is_endpoint_shared(mode) {
+        return Err(BuckdConnectError::DaemonOwnedByOtherUser {
+            path: location.display().to_string(),
+            owner,
+            current,
+        }
+        .into());
+    }
+    Ok(())
 }
 
 fn daemon_connect_error(paths: &InvocationPaths) -> BuckdConnectError {
@@ -973,4 +1030,14 @@ mod tests {
         req.daemon_startup_config.daemon_buster = Some("1".to_owned());
         assert!(!req.satisfied(&daemon));
     }
+
+    #[test]
+    fn test_check_daemon_access() {
+        let location = std::path::Path::new("buckd.info");
+        assert!(check_daemon_access(location, 1000, 0o100600, 1000).is_ok());
+        assert!(check_daemon_access(location, 1000, 0o100600, 1001).is_err());
+        // Shared by the owner with `buck2.endpoint_permissions`.
+        assert!(check_daemon_access(location, 1000, 0o100640, 1001).is_ok());
+        assert!(check_daemon_access(location, 1000, 0o100604, 1001).is_ok());
+    }
 }
@@ -52,6 +52,7 @@ pub async fn kill(
     client: &mut DaemonApiClient<InterceptedService<Channel, BuckAddAuthTokenInterceptor>>,
     info: &DaemonProcessInfo,
     reason: &str,
+    drain: Option<Duration>,
 ) -> anyhow::Result<KillResponse> {
     let pid = info.pid;
     let pid: u32 = pid
@@ -61,12 +62,25 @@ pub async fn kill(
 
     tracing::debug!("Killing daemon with PID {}", pid);
 
+    // When draining, the request timeout bounds how long the daemon waits for in-flight commands,
+    // after which it shuts down gracefully as usual.
+    let graceful_shutdown_timeout = match drain {
+        Some(drain) => {
+            crate::eprintln!(
+                "Waiting up to {} for in-flight commands to finish",
+                humantime::format_duration(drain)
+            )?;
+            drain + GRACEFUL_SHUTDOWN_TIMEOUT
+        }
+        None => GRACEFUL_SHUTDOWN_TIMEOUT,
+    };
     let request_fut = client.kill(Request::new(KillRequest {
         reason: reason.to_owned(),
-        timeout: Some(GRACEFUL_SHUTDOWN_TIMEOUT.try_into()?),
+        timeout: Some(drain.unwrap_or(GRACEFUL_SHUTDOWN_TIMEOUT).try_into()?),
         callers,
+        drain: drain.is_some(),
     }));
-    let time_to_kill = GRACEFUL_SHUTDOWN_TIMEOUT + FORCE_SHUTDOWN_TIMEOUT;
+    let time_to_kill = graceful_shutdown_timeout + FORCE_SHUTDOWN_TIMEOUT;
     let time_req_sent = Instant::now();
     // First we send a Kill request
     match tokio::time::timeout(KILL_REQUEST_TIMEOUT, request_fut).await {
@@ -76,7 +90,7 @@ pub async fn kill(
                     if !kill::process_exists(pid)? {
                         return Ok(KillResponse { pid });
                     }
-                    if time_req_sent.elapsed() > GRACEFUL_SHUTDOWN_TIMEOUT {
+                    if time_req_sent.elapsed() > graceful_shutdown_timeout {
                         crate::eprintln!(
                             "Timed out waiting for graceful shutdown of buck2 daemon pid {}",
                             pid
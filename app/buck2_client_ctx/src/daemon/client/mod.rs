@@ -21,6 +21,7 @@ use buck2_common::daemon_dir::DaemonDir;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_event_log::stream_value::StreamValue;
+use buck2_wrapper_common::invocation_id::TraceId;
 use fs4::FileExt;
 use futures::future::BoxFuture;
 use futures::pin_mut;
@@ -77,6 +78,35 @@ impl<'a> BuckdClientConnector<'a> {
             .iter()
             .filter_map(|s| s.as_error_observer())
     }
+
+    /// Returns a handle that can forcibly cancel the command with this trace id, even after
+    /// this connector (and the command's request) has been dropped.
+    pub fn hard_canceller(&self, trace_id: TraceId) -> HardCanceller {
+        HardCanceller {
+            client: self.client.client.clone(),
+            trace_id,
+        }
+    }
+}
+
+/// Forcibly cancels a command on the daemon, see [`BuckdClientConnector::hard_canceller`].
+#[derive(Clone)]
+pub struct HardCanceller {
+    client: DaemonApiClient<InterceptedService<Channel, BuckAddAuthTokenInterceptor>>,
+    trace_id: TraceId,
+}
+
+impl HardCanceller {
+    /// Returns whether the command was still running on the daemon.
+    pub async fn cancel(mut self) -> anyhow::Result<bool> {
+        let response = self
+            .client
+            .cancel_command(Request::new(CancelCommandRequest {
+                trace_id: self.trace_id.to_string(),
+            }))
+            .await?;
+        Ok(response.into_inner().found)
+    }
 }
 
 pub struct BuckdLifecycleLock {
@@ -0,0 +1,415 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Diagnostic artifacts: the files collected to debug buck2, such as event logs, RE logs, DICE
+//! dumps or heap dumps.
+//!
+//! The kind of an artifact determines the name it is stored under and how long it is kept, so
+//! that artifacts are stored the same way whichever subsystem produced them, and whether they
+//! are uploaded or archived in a local directory. The kinds `buck2 rage` uploaded before keep
+//! the names and retention their consumers rely on.
+
+use std::io::Cursor;
+use std::process::Stdio;
+use std::time::SystemTime;
+
+use anyhow::Context;
+use async_compression::tokio::bufread::ZstdEncoder;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::abs_path::AbsPath;
+use buck2_core::fs::paths::abs_path::AbsPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_util::process::async_background_command;
+use dupe::Dupe;
+use tokio::io::AsyncRead;
+use tokio::io::BufReader;
+use tokio::process::Child;
+
+use crate::manifold::Bucket;
+use crate::manifold::ManifoldClient;
+use crate::manifold::Ttl;
+
+#[derive(Debug, thiserror::Error)]
+enum DiagnosticsError {
+    #[error("Failed to archive `{0}`: tar exited with {1}")]
+    TarFailed(String, std::process::ExitStatus),
+}
+
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq, clap::ArgEnum)]
+pub enum DiagnosticKind {
+    EventLog,
+    DaemonStderr,
+    ThreadDump,
+    DiceDump,
+    MaterializerState,
+    MaterializerFsck,
+    ReLogs,
+    HeapDump,
+}
+
+impl DiagnosticKind {
+    /// The name of the kind, which names the directory artifacts of this kind are archived in.
+    pub fn name(self) -> &'static str {
+        match self {
+            DiagnosticKind::EventLog => "event_log",
+            DiagnosticKind::DaemonStderr => "daemon_stderr",
+            DiagnosticKind::ThreadDump => "thread_dump",
+            DiagnosticKind::DiceDump => "dice_dump",
+            DiagnosticKind::MaterializerState => "materializer_state",
+            DiagnosticKind::MaterializerFsck => "materializer_fsck",
+            DiagnosticKind::ReLogs => "re_logs",
+            DiagnosticKind::HeapDump => "heap_dump",
+        }
+    }
+
+    /// How long artifacts of this kind are kept. Heap dumps are large and only useful to debug a
+    /// recent issue.
+    pub fn retention(self) -> Ttl {
+        match self {
+            DiagnosticKind::HeapDump => Ttl::from_secs(30 * 86_400),
+            DiagnosticKind::EventLog
+            | DiagnosticKind::DaemonStderr
+            | DiagnosticKind::ThreadDump
+            | DiagnosticKind::DiceDump
+            | DiagnosticKind::MaterializerState
+            | DiagnosticKind::MaterializerFsck
+            | DiagnosticKind::ReLogs => Ttl::default(),
+        }
+    }
+
+    /// Whether artifacts of this kind are compressed when stored.
+    fn compressed(self) -> bool {
+        matches!(self, DiagnosticKind::ReLogs)
+    }
+}
+
+pub enum DiagnosticContents {
+    File(AbsPathBuf),
+    /// A directory, stored as a tarball.
+    Directory(AbsPathBuf),
+    /// Contents collected in memory, such as the output of a command.
+    Bytes(Vec<u8>),
+}
+
+pub struct DiagnosticArtifact {
+    kind: DiagnosticKind,
+    /// What the artifact is about, such as a trace id or an RE session id.
+    id: String,
+    contents: DiagnosticContents,
+    /// The extension of the name the artifact is stored under, if not that of its file.
+    extension: Option<String>,
+}
+
+impl DiagnosticArtifact {
+    pub fn new(kind: DiagnosticKind, id: impl Into<String>, contents: DiagnosticContents) -> Self {
+        Self {
+            kind,
+            id: id.into(),
+            contents,
+            extension: None,
+        }
+    }
+
+    /// Store the artifact with this extension, including the leading `.`.
+    pub fn with_extension(mut self, extension: impl Into<String>) -> Self {
+        self.extension = Some(extension.into());
+        self
+    }
+
+    pub fn file(kind: DiagnosticKind, id: impl Into<String>, path: &AbsPath) -> Self {
+        Self::new(kind, id, DiagnosticContents::File(path.to_owned()))
+    }
+
+    pub fn bytes(kind: DiagnosticKind, id: impl Into<String>, bytes: Vec<u8>) -> Self {
+        Self::new(kind, id, DiagnosticContents::Bytes(bytes))
+    }
+
+    pub fn kind(&self) -> DiagnosticKind {
+        self.kind
+    }
+
+    /// The name the artifact is stored under.
+    pub fn file_name(&self) -> String {
+        let id = &self.id;
+        match self.kind {
+            DiagnosticKind::DaemonStderr => format!("{}.stderr", id),
+            DiagnosticKind::ThreadDump => format!("{}_thread_dump", id),
+            DiagnosticKind::DiceDump => format!("{}_dice-dump.tar", id),
+            DiagnosticKind::MaterializerState => format!("{}_materializer_state", id),
+            DiagnosticKind::MaterializerFsck => format!("{}_materializer_fsck", id),
+            DiagnosticKind::ReLogs => format!("{}-re_logs.zst", id),
+            DiagnosticKind::EventLog | DiagnosticKind::HeapDump => {
+                format!("{}-{}{}", id, self.kind.name(), self.extension())
+            }
+        }
+    }
+
+    /// The extension of the file the artifact was read from, or `.tar` for directories.
+    fn extension(&self) -> String {
+        if let Some(extension) = &self.extension {
+            return extension.clone();
+        }
+        match &self.contents {
+            // The whole extension, e.g. `.pb.zst` for event logs.
+            DiagnosticContents::File(path) => path
+                .file_name()
+                .and_then(|f| f.to_str())
+                .and_then(|f| f.split_once('.'))
+                .map_or_else(String::new, |(_, extension)| format!(".{}", extension)),
+            DiagnosticContents::Directory(..) => ".tar".to_owned(),
+            DiagnosticContents::Bytes(..) => String::new(),
+        }
+    }
+
+    /// The contents of the artifact, and the `tar` process archiving it if it is a directory,
+    /// whose exit status must be checked once the contents are read.
+    async fn reader(
+        &self,
+    ) -> anyhow::Result<(Box<dyn AsyncRead + Unpin + Send + '_>, Option<Child>)> {
+        let mut tar = None;
+        let reader: Box<dyn AsyncRead + Unpin + Send + '_> = match &self.contents {
+            DiagnosticContents::File(path) => Box::new(
+                tokio::fs::File::open(path)
+                    .await
+                    .with_context(|| format!("Failed to open `{}`", path.display()))?,
+            ),
+            DiagnosticContents::Directory(path) => {
+                let mut child = async_background_command("tar")
+                    .arg("-c")
+                    .arg(path.as_os_str())
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::null())
+                    .spawn()
+                    .context("Failed to spawn tar")?;
+                let stdout = child.stdout.take().context("tar has no stdout")?;
+                tar = Some(child);
+                Box::new(stdout)
+            }
+            DiagnosticContents::Bytes(bytes) => Box::new(Cursor::new(bytes)),
+        };
+        let reader: Box<dyn AsyncRead + Unpin + Send + '_> = if self.kind.compressed() {
+            Box::new(ZstdEncoder::with_quality(
+                BufReader::new(reader),
+                async_compression::Level::Default,
+            ))
+        } else {
+            reader
+        };
+        Ok((reader, tar))
+    }
+
+    /// Check that the contents of the artifact were read successfully.
+    async fn finish(&self, tar: Option<Child>) -> anyhow::Result<()> {
+        if let (Some(mut tar), DiagnosticContents::Directory(path)) = (tar, &self.contents) {
+            let status = tar.wait().await.context("Failed to wait for tar")?;
+            if !status.success() {
+                return Err(DiagnosticsError::TarFailed(path.display().to_string(), status).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where diagnostic artifacts are stored.
+pub enum DiagnosticsDestination {
+    /// Uploaded to Manifold, under `flat/`.
+    Manifold {
+        client: ManifoldClient,
+        bucket: Bucket,
+    },
+    /// Archived in a local directory, in a subdirectory per kind. Artifacts older than the
+    /// retention of their kind are removed from it as new artifacts of the same kind are
+    /// archived.
+    Directory(AbsNormPathBuf),
+}
+
+impl DiagnosticsDestination {
+    /// Store an artifact, returning where it was stored, for humans.
+    pub async fn store(&self, artifact: &DiagnosticArtifact) -> anyhow::Result<String> {
+        self.store_as(artifact, &artifact.file_name()).await
+    }
+
+    /// Store an artifact under a name other than that of its kind.
+    pub async fn store_as(
+        &self,
+        artifact: &DiagnosticArtifact,
+        file_name: &str,
+    ) -> anyhow::Result<String> {
+        let (mut reader, tar) = artifact.reader().await?;
+        let location = match self {
+            DiagnosticsDestination::Manifold { client, bucket } => {
+                let path = format!("flat/{}", file_name);
+                client
+                    .read_and_upload(*bucket, &path, artifact.kind.retention(), &mut reader)
+                    .await?;
+                manifold_leads(bucket, &path)
+            }
+            DiagnosticsDestination::Directory(dir) => {
+                let dir = dir.join(ForwardRelativePath::new(artifact.kind.name())?);
+                fs_util::create_dir_all(&dir)?;
+                prune_expired(&dir, artifact.kind)?;
+                let path = dir.join(ForwardRelativePath::new(file_name)?);
+                let mut file = tokio::fs::File::create(&path)
+                    .await
+                    .with_context(|| format!("Failed to create `{}`", path.display()))?;
+                tokio::io::copy(&mut reader, &mut file).await?;
+                path.display().to_string()
+            }
+        };
+        drop(reader);
+        artifact.finish(tar).await?;
+        Ok(location)
+    }
+}
+
+fn manifold_leads(bucket: &Bucket, path: &str) -> String {
+    let full_path = format!("{}/{}", bucket.name, path);
+    let command = format!("manifold get {}", full_path);
+    let url = format!("https://interncache-all.fbcdn.net/manifold/{}", full_path);
+    format!("{}\n{}", command, url)
+}
+
+/// Remove the artifacts from the archive directory of a kind which are older than its retention.
+fn prune_expired(dir: &AbsNormPath, kind: DiagnosticKind) -> anyhow::Result<()> {
+    let now = SystemTime::now();
+    for entry in fs_util::read_dir(dir)? {
+        let entry = entry?;
+        let modified = entry.metadata()?.modified()?;
+        if now.duration_since(modified).unwrap_or_default() > kind.retention().duration() {
+            fs_util::remove_all(AbsPathBuf::new(entry.path())?)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(p: &str) -> AbsPathBuf {
+        if cfg!(windows) {
+            AbsPathBuf::new(format!("C:{}", p.replace('/', "\\"))).unwrap()
+        } else {
+            AbsPathBuf::new(p).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(
+            "abc-event_log.pb.zst",
+            DiagnosticArtifact::file(
+                DiagnosticKind::EventLog,
+                "abc",
+                &path("/logs/20240101_build_abc_events.pb.zst")
+            )
+            .file_name()
+        );
+        assert_eq!(
+            "abc-event_log.pb",
+            DiagnosticArtifact::file(
+                DiagnosticKind::EventLog,
+                "abc",
+                &path("/logs/20240101_build_abc_events.proto")
+            )
+            .with_extension(".pb")
+            .file_name()
+        );
+        assert_eq!(
+            "session-re_logs.zst",
+            DiagnosticArtifact::file(
+                DiagnosticKind::ReLogs,
+                "session",
+                &path("/re/session/REClientFolly.log")
+            )
+            .file_name()
+        );
+        assert_eq!(
+            "abc.stderr",
+            DiagnosticArtifact::file(
+                DiagnosticKind::DaemonStderr,
+                "abc",
+                &path("/daemon/buckd.stderr")
+            )
+            .file_name()
+        );
+        assert_eq!(
+            "abc_dice-dump.tar",
+            DiagnosticArtifact::new(
+                DiagnosticKind::DiceDump,
+                "abc",
+                DiagnosticContents::Directory(path("/dice/dump"))
+            )
+            .file_name()
+        );
+        assert_eq!(
+            "abc_thread_dump",
+            DiagnosticArtifact::bytes(DiagnosticKind::ThreadDump, "abc", Vec::new()).file_name()
+        );
+        assert_eq!(
+            "abc_materializer_fsck",
+            DiagnosticArtifact::bytes(DiagnosticKind::MaterializerFsck, "abc", Vec::new())
+                .file_name()
+        );
+        assert_eq!(
+            "abc-heap_dump.tar",
+            DiagnosticArtifact::new(
+                DiagnosticKind::HeapDump,
+                "abc",
+                DiagnosticContents::Directory(path("/heap"))
+            )
+            .file_name()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_store_in_directory() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let destination =
+            DiagnosticsDestination::Directory(temp_dir.path().to_path_buf().try_into()?);
+        let artifact =
+            DiagnosticArtifact::bytes(DiagnosticKind::ThreadDump, "abc", b"dump".to_vec());
+
+        destination.store(&artifact).await?;
+        assert_eq!(
+            "dump",
+            std::fs::read_to_string(temp_dir.path().join("thread_dump").join("abc_thread_dump"))?
+        );
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_store_directory_checks_tar_status() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let destination =
+            DiagnosticsDestination::Directory(temp_dir.path().join("archive").try_into()?);
+
+        let dump = temp_dir.path().join("dump");
+        std::fs::create_dir(&dump)?;
+        std::fs::write(dump.join("state"), "state")?;
+        let artifact = DiagnosticArtifact::new(
+            DiagnosticKind::DiceDump,
+            "abc",
+            DiagnosticContents::Directory(AbsPathBuf::new(dump.clone())?),
+        );
+        destination.store(&artifact).await?;
+
+        let missing = DiagnosticArtifact::new(
+            DiagnosticKind::DiceDump,
+            "missing",
+            DiagnosticContents::Directory(AbsPathBuf::new(dump.join("missing"))?),
+        );
+        assert!(destination.store(&missing).await.is_err());
+        Ok(())
+    }
+}
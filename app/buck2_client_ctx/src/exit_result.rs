@@ -278,6 +278,8 @@ pub enum ExitCode {
     ConnectError,
     SignalInterrupt,
     BrokenPipe,
+    /// The command was cancelled because it ran for longer than `--timeout`.
+    Timeout,
     /// Something other than buck2 itself (usually a test runner) explicitly requested that this
     /// exit code be returned
     Explicit(u8),
@@ -292,6 +294,7 @@ impl ExitCode {
             InfraError => 2,
             UserError => 3,
             DaemonIsBusy => 4,
+            Timeout => 5,
             ConnectError => 11,
             BrokenPipe => 130,
             SignalInterrupt => 141,
@@ -115,7 +115,8 @@ impl<'a> ImmediateConfigContext<'a> {
 
                 match is_paranoid_enabled(&paranoid_info_path) {
                     Ok(paranoid) => {
-                        daemon_startup_config.paranoid = paranoid;
+                        daemon_startup_config.paranoid =
+                            paranoid || daemon_startup_config.paranoid_config.enabled;
                     }
                     Err(e) => {
                         tracing::warn!(
@@ -0,0 +1,148 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::fs::OpenOptions;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+
+use anyhow::Context;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use fs4::FileExt;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// One invocation, as recorded in the invocation history.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InvocationHistoryEntry {
+    pub trace_id: String,
+    pub command_name: String,
+    pub cli_args: Vec<String>,
+    /// Start of the invocation, in milliseconds since the epoch.
+    pub start_time_ms: u64,
+    pub duration_ms: Option<u64>,
+    /// `None` if the command did not finish, for example because the daemon crashed.
+    pub success: Option<bool>,
+    /// Hash of the buckconfigs the command ran with.
+    pub config_hash: Option<String>,
+}
+
+/// A small index of recent invocations, so that `buck2 log recent` does not need to read
+/// every event log.
+///
+/// Entries are appended to a JSON-lines file, which is truncated to its most recent entries
+/// when it grows too large.
+pub struct InvocationHistory {
+    path: AbsNormPathBuf,
+}
+
+impl InvocationHistory {
+    /// Number of entries kept when the history is truncated.
+    const ENTRIES_RETAINED: usize = 1000;
+
+    pub fn new(path: AbsNormPathBuf) -> Self {
+        Self { path }
+    }
+
+    fn parse(contents: &str) -> Vec<InvocationHistoryEntry> {
+        // Skip lines we cannot parse, which may be written by another version of buck2, or
+        // truncated by a crash.
+        contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+
+    /// Entries of the history, from the oldest to the most recent.
+    pub fn read(&self) -> anyhow::Result<Vec<InvocationHistoryEntry>> {
+        Ok(fs_util::read_to_string_if_exists(&self.path)?
+            .map(|contents| Self::parse(&contents))
+            .unwrap_or_default())
+    }
+
+    pub fn append(&self, entry: &InvocationHistoryEntry) -> anyhow::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs_util::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&self.path)
+            .with_context(|| format!("Error opening invocation history `{}`", self.path))?;
+        // Commands running concurrently may finish at the same time.
+        file.lock_exclusive()?;
+
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        file.write_all(line.as_bytes())?;
+
+        // Only truncate once the history is twice as large as what we keep, so that we
+        // rarely rewrite it.
+        let mut contents = String::new();
+        file.seek(SeekFrom::Start(0))?;
+        file.read_to_string(&mut contents)?;
+        if contents.lines().count() > 2 * Self::ENTRIES_RETAINED {
+            let mut entries = Self::parse(&contents);
+            entries.drain(..entries.len().saturating_sub(Self::ENTRIES_RETAINED));
+            let mut out = String::new();
+            for entry in &entries {
+                out.push_str(&serde_json::to_string(entry)?);
+                out.push('\n');
+            }
+            file.set_len(0)?;
+            file.write_all(out.as_bytes())?;
+        }
+
+        file.unlock()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+    use buck2_core::fs::paths::file_name::FileName;
+
+    use super::*;
+
+    fn entry(trace_id: &str) -> InvocationHistoryEntry {
+        InvocationHistoryEntry {
+            trace_id: trace_id.to_owned(),
+            command_name: "build".to_owned(),
+            cli_args: vec!["buck2".to_owned(), "build".to_owned(), "//:x".to_owned()],
+            start_time_ms: 1,
+            duration_ms: Some(2),
+            success: Some(true),
+            config_hash: None,
+        }
+    }
+
+    #[test]
+    fn test_append_and_truncate() -> anyhow::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let history = InvocationHistory::new(
+            AbsNormPathBuf::new(tempdir.path().to_owned())?.join(FileName::new("history")?),
+        );
+        assert_eq!(Vec::<InvocationHistoryEntry>::new(), history.read()?);
+
+        for i in 0..=(2 * InvocationHistory::ENTRIES_RETAINED) {
+            history.append(&entry(&i.to_string()))?;
+        }
+        let entries = history.read()?;
+        assert_eq!(InvocationHistory::ENTRIES_RETAINED, entries.len());
+        assert_eq!(
+            (2 * InvocationHistory::ENTRIES_RETAINED).to_string(),
+            entries.last().unwrap().trace_id
+        );
+        Ok(())
+    }
+}
@@ -25,12 +25,14 @@ pub mod common;
 pub mod console_interaction_stream;
 pub mod daemon;
 pub mod daemon_constraints;
+pub mod diagnostics;
 pub mod events_ctx;
 pub mod exit_result;
 pub mod file_tailer;
 pub mod final_console;
 pub mod ide_support;
 pub mod immediate_config;
+pub mod invocation_history;
 pub mod manifold;
 pub mod output_destination_arg;
 pub mod path_arg;
@@ -38,6 +38,10 @@ impl Ttl {
             duration: Duration::from_secs(ttl),
         }
     }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
 }
 
 impl Default for Ttl {
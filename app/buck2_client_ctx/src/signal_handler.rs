@@ -7,14 +7,48 @@
  * of this source tree.
  */
 
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use dupe::Dupe;
 use futures::future;
 use futures::future::Either;
 use futures::Future;
 
+use crate::daemon::client::HardCanceller;
+use crate::exit_result::ExitCode;
+use crate::exit_result::ExitResult;
+
+/// How long to wait for the daemon to acknowledge a hard cancel before exiting anyway.
+const HARD_CANCEL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The daemon command to forcibly cancel on a second ctrl+c. It is set once `work` has
+/// connected to the daemon.
+#[derive(Clone, Dupe, Default)]
+pub struct HardCancelHandle(Arc<Mutex<Option<HardCanceller>>>);
+
+impl HardCancelHandle {
+    pub fn set(&self, canceller: HardCanceller) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = Some(canceller);
+    }
+
+    fn take(&self) -> Option<HardCanceller> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+}
+
 /// A simple SIGINT handler that lets `work` and ctrl+c future race. When ctrl+c
 /// is hit, it allows the `work` future and the other clean-up implementations
 /// such as AsyncCleanupContext to be dropped.
-pub async fn with_simple_sigint_handler<F: Future>(work: F) -> Option<F::Output> {
+///
+/// Cancellation waits on that clean-up and on the daemon, which can hang (e.g. on stuck RE
+/// calls), so a second ctrl+c asks the daemon to forcibly terminate the command and log what
+/// it was doing, and exits the process right away.
+pub async fn with_simple_sigint_handler<F: Future>(
+    work: F,
+    hard_cancel: HardCancelHandle,
+) -> Option<F::Output> {
     let exit = tokio::signal::ctrl_c();
 
     futures::pin_mut!(work);
@@ -22,6 +56,27 @@ pub async fn with_simple_sigint_handler<F: Future>(work: F) -> Option<F::Output>
 
     match future::select(work, exit).await {
         Either::Left((res, _)) => Some(res),
-        Either::Right((_, _)) => None,
+        Either::Right((_, _)) => {
+            crate::eprintln!("Cancelling, press Ctrl-C again to exit immediately").ok();
+            tokio::spawn(async move {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    if let Some(canceller) = hard_cancel.take() {
+                        crate::eprintln!("Forcibly cancelling the command").ok();
+                        match tokio::time::timeout(HARD_CANCEL_TIMEOUT, canceller.cancel()).await {
+                            Ok(Ok(_)) => {}
+                            Ok(Err(e)) => {
+                                crate::eprintln!("Failed to forcibly cancel the command: {:#}", e)
+                                    .ok();
+                            }
+                            Err(_) => {
+                                crate::eprintln!("Timed out forcibly cancelling the command").ok();
+                            }
+                        }
+                    }
+                    ExitResult::status(ExitCode::SignalInterrupt).report();
+                }
+            });
+            None
+        }
     }
 }
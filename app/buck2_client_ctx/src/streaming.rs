@@ -9,6 +9,7 @@
 
 use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use buck2_common::argv::Argv;
@@ -28,6 +29,7 @@ use crate::exit_result::ExitCode;
 use crate::exit_result::ExitResult;
 use crate::path_arg::PathArg;
 use crate::signal_handler::with_simple_sigint_handler;
+use crate::signal_handler::HardCancelHandle;
 use crate::subscribers::get::get_console_with_root;
 use crate::subscribers::get::try_get_build_graph_stats;
 use crate::subscribers::get::try_get_build_id_writer;
@@ -36,6 +38,16 @@ use crate::subscribers::get::try_get_re_log_subscriber;
 use crate::subscribers::recorder::try_get_invocation_recorder;
 use crate::subscribers::subscriber::EventSubscriber;
 
+/// How long after `--timeout` the client waits for a command to be stopped by the daemon (see
+/// `StreamingCommand::daemon_handles_timeout`) before cancelling it.
+const DAEMON_TIMEOUT_GRACE: Duration = Duration::from_secs(30);
+
+#[derive(Debug, thiserror::Error)]
+enum StreamingCommandError {
+    #[error("Command timed out after {0}")]
+    Timeout(humantime::FormattedDuration),
+}
+
 fn default_subscribers<'a, T: StreamingCommand>(
     cmd: &T,
     ctx: &ClientCommandContext<'a>,
@@ -138,6 +150,13 @@ pub trait StreamingCommand: Sized + Send + Sync {
         true
     }
 
+    /// Whether the daemon stops this command itself when `--timeout` expires, so that it can
+    /// report partial results. The client then only cancels the command if the daemon has not
+    /// stopped it shortly after the timeout.
+    fn daemon_handles_timeout(&self) -> bool {
+        false
+    }
+
     /// Currently only for BxlCommand.
     fn user_event_log(&self) -> &Option<PathArg> {
         &None
@@ -154,6 +173,14 @@ impl<T: StreamingCommand> BuckSubcommand for T {
     /// Handles all of the business of setting up a runtime, server, and subscribers.
     fn exec(self, matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
         ctx.with_runtime(async move |mut ctx| {
+            let timeout = self.event_log_opts().timeout().map(|t| {
+                if self.daemon_handles_timeout() {
+                    t + DAEMON_TIMEOUT_GRACE
+                } else {
+                    t
+                }
+            });
+            let hard_cancel = HardCancelHandle::default();
             let work = async {
                 let constraints = if T::existing_only() {
                     BuckdConnectConstraints::ExistingOnly
@@ -189,6 +216,7 @@ impl<T: StreamingCommand> BuckSubcommand for T {
                         return ExitResult::err_with_exit_code(e, ExitCode::ConnectError);
                     }
                 };
+                hard_cancel.set(buckd.hard_canceller(ctx.trace_id.dupe()));
 
                 let command_result = self.exec_impl(&mut buckd, matches, &mut ctx).await;
 
@@ -197,7 +225,21 @@ impl<T: StreamingCommand> BuckSubcommand for T {
                 command_result
             };
 
-            with_simple_sigint_handler(work)
+            let work = async {
+                match timeout {
+                    None => work.await,
+                    Some(timeout) => match tokio::time::timeout(timeout, work).await {
+                        Ok(command_result) => command_result,
+                        Err(_) => ExitResult::err_with_exit_code(
+                            StreamingCommandError::Timeout(humantime::format_duration(timeout))
+                                .into(),
+                            ExitCode::Timeout,
+                        ),
+                    },
+                }
+            };
+
+            with_simple_sigint_handler(work, hard_cancel.dupe())
                 .await
                 .unwrap_or_else(|| ExitResult::status(ExitCode::SignalInterrupt))
         })
@@ -74,6 +74,16 @@ impl UnpackingEventSubscriber for ErrorConsole {
         Ok(())
     }
 
+    async fn handle_streaming_output(
+        &mut self,
+        output: &buck2_data::StreamingOutput,
+    ) -> anyhow::Result<()> {
+        // This is output of the command rather than progress, so it's printed even with
+        // `--console none`.
+        crate::print!("{}", output.data)?;
+        crate::stdio::flush()
+    }
+
     async fn handle_structured_error(
         &mut self,
         _err: &buck2_data::StructuredError,
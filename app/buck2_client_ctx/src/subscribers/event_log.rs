@@ -12,6 +12,7 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 use buck2_common::argv::SanitizedArgv;
+use buck2_common::compression::CompressionConfig;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::paths::abs_path::AbsPathBuf;
 use buck2_core::fs::working_dir::WorkingDir;
@@ -39,6 +40,7 @@ impl<'a> EventLog<'a> {
         command_name: String,
         log_size_counter_bytes: Option<Arc<AtomicU64>>,
         allow_vpnless: bool,
+        compression: CompressionConfig,
     ) -> anyhow::Result<EventLog> {
         Ok(Self {
             writer: WriteEventLog::new(
@@ -51,6 +53,7 @@ impl<'a> EventLog<'a> {
                 command_name,
                 log_size_counter_bytes,
                 allow_vpnless,
+                compression,
             )?,
         })
     }
@@ -115,6 +115,7 @@ pub(crate) fn try_get_event_log_subscriber<'a, T: StreamingCommand>(
         T::COMMAND_NAME.to_owned(),
         log_size_counter_bytes,
         ctx.allow_vpnless_for_logging()?,
+        ctx.event_log_compression()?,
     )?;
     Ok(Some(Box::new(log)))
 }
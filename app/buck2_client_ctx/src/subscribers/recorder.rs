@@ -9,13 +9,18 @@
 
 use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
+use std::sync::OnceLock;
+use std::time::Duration;
 
+use buck2_common::vcs::detect_vcs;
+use buck2_common::vcs::state_with_timeout;
 use dupe::Dupe;
 
 use crate::build_count::BuildCountManager;
 use crate::client_ctx::ClientCommandContext;
 use crate::client_metadata::ClientMetadata;
 use crate::common::CommonDaemonCommandOptions;
+use crate::invocation_history::InvocationHistory;
 
 mod imp {
     use std::cmp;
@@ -26,14 +31,17 @@ mod imp {
     use std::sync::atomic::AtomicU64;
     use std::sync::atomic::Ordering;
     use std::sync::Arc;
+    use std::sync::OnceLock;
     use std::time::Duration;
     use std::time::Instant;
     use std::time::SystemTime;
+    use std::time::UNIX_EPOCH;
 
     use anyhow::Context;
     use async_trait::async_trait;
     use buck2_cli_proto::command_result;
     use buck2_common::convert::ProstDurationExt;
+    use buck2_common::vcs::VcsState;
     use buck2_core::fs::fs_util;
     use buck2_core::fs::paths::abs_path::AbsPathBuf;
     use buck2_event_observer::action_stats;
@@ -53,6 +61,8 @@ mod imp {
     use termwiz::istty::IsTty;
 
     use crate::build_count::BuildCountManager;
+    use crate::invocation_history::InvocationHistory;
+    use crate::invocation_history::InvocationHistoryEntry;
     use crate::subscribers::observer::ErrorObserver;
     use crate::subscribers::recorder::system_memory_stats;
     use crate::subscribers::subscriber::EventSubscriber;
@@ -66,6 +76,7 @@ mod imp {
         start_time: Instant,
         async_cleanup_context: AsyncCleanupContext<'a>,
         build_count_manager: BuildCountManager,
+        invocation_history: InvocationHistory,
         trace_id: TraceId,
         command_end: Option<buck2_data::CommandEnd>,
         command_duration: Option<prost_types::Duration>,
@@ -107,6 +118,7 @@ mod imp {
         time_to_first_test_discovery: Option<Duration>,
         system_total_memory_bytes: Option<u64>,
         file_watcher_stats: Option<buck2_data::FileWatcherStats>,
+        environment_provenance: Option<buck2_data::EnvironmentProvenance>,
         time_to_last_action_execution_end: Option<Duration>,
         initial_sink_success_count: Option<u64>,
         initial_sink_failure_count: Option<u64>,
@@ -133,6 +145,8 @@ mod imp {
         client_metadata: Vec<buck2_data::ClientMetadata>,
         errors: Vec<buck2_data::ProcessedErrorReport>,
         target_rule_type_names: Vec<String>,
+        /// Set in the background when source control answers.
+        vcs_state: Arc<OnceLock<VcsState>>,
     }
 
     impl<'a> InvocationRecorder<'a> {
@@ -145,10 +159,12 @@ mod imp {
             trace_id: TraceId,
             isolation_dir: String,
             build_count_manager: BuildCountManager,
+            invocation_history: InvocationHistory,
             filesystem: String,
             restarted_trace_id: Option<TraceId>,
             log_size_counter_bytes: Option<Arc<AtomicU64>>,
             client_metadata: Vec<buck2_data::ClientMetadata>,
+            vcs_state: Arc<OnceLock<VcsState>>,
         ) -> Self {
             Self {
                 fb,
@@ -159,6 +175,7 @@ mod imp {
                 start_time: Instant::now(),
                 async_cleanup_context,
                 build_count_manager,
+                invocation_history,
                 trace_id,
                 command_end: None,
                 command_duration: None,
@@ -200,6 +217,7 @@ mod imp {
                 time_to_first_test_discovery: None,
                 system_total_memory_bytes: Some(system_memory_stats()),
                 file_watcher_stats: None,
+                environment_provenance: None,
                 time_to_last_action_execution_end: None,
                 initial_sink_success_count: None,
                 initial_sink_failure_count: None,
@@ -226,6 +244,7 @@ mod imp {
                 client_metadata,
                 errors: Vec::new(),
                 target_rule_type_names: Vec::new(),
+                vcs_state,
             }
         }
 
@@ -293,9 +312,14 @@ mod imp {
                 );
             }
 
+            self.record_in_history();
+
             let mut metadata = Self::default_metadata();
             metadata.strings.extend(std::mem::take(&mut self.metadata));
 
+            // Not recorded if source control has not answered by the end of the command.
+            let vcs_state = self.vcs_state.get();
+
             let record = buck2_data::InvocationRecord {
                 command_name: Some(self.command_name.to_owned()),
                 command_end: self.command_end.take(),
@@ -364,6 +388,7 @@ mod imp {
                     .and_then(|d| u64::try_from(d.as_millis()).ok()),
                 system_total_memory_bytes: self.system_total_memory_bytes,
                 file_watcher_stats: self.file_watcher_stats.take(),
+                environment_provenance: self.environment_provenance.take(),
                 time_to_last_action_execution_end_ms: self
                     .time_to_last_action_execution_end
                     .and_then(|d| u64::try_from(d.as_millis()).ok()),
@@ -401,6 +426,9 @@ mod imp {
                 client_metadata: std::mem::take(&mut self.client_metadata),
                 errors: std::mem::take(&mut self.errors),
                 target_rule_type_names: std::mem::take(&mut self.target_rule_type_names),
+                vcs: vcs_state.map(|s| s.vcs.to_owned()),
+                vcs_revision: vcs_state.map(|s| s.revision.clone()),
+                vcs_dirty: vcs_state.and_then(|s| s.dirty),
             };
 
             let event = BuckEvent::new(
@@ -445,6 +473,28 @@ mod imp {
             }
         }
 
+        fn record_in_history(&self) {
+            let elapsed = self.start_time.elapsed();
+            let entry = InvocationHistoryEntry {
+                trace_id: self.trace_id.to_string(),
+                command_name: self.command_name.to_owned(),
+                cli_args: self.cli_args.clone(),
+                start_time_ms: (SystemTime::now() - elapsed)
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_millis() as u64),
+                duration_ms: u64::try_from(elapsed.as_millis()).ok(),
+                success: self
+                    .command_end
+                    .as_ref()
+                    .map(|end| end.is_success)
+                    .or(self.instant_command_is_success),
+                config_hash: self.metadata.get("buckconfig_hash").cloned(),
+            };
+            if let Err(e) = self.invocation_history.append(&entry) {
+                tracing::warn!("Failed to record invocation in history: {:#}", e);
+            }
+        }
+
         // Collects client-side state and data, suitable for telemetry.
         // NOTE: If data is visible from the daemon, put it in cli::metadata::collect()
         fn default_metadata() -> buck2_data::TypedMetadata {
@@ -508,6 +558,7 @@ mod imp {
             _event: &BuckEvent,
         ) -> anyhow::Result<()> {
             self.metadata.extend(command.metadata.clone());
+            self.environment_provenance = command.environment_provenance.clone();
             self.time_to_command_critical_section = Some(self.start_time.elapsed());
             Ok(())
         }
@@ -1142,6 +1193,10 @@ mod imp {
     }
 }
 
+/// How long to wait for source control to return the revision and dirty state recorded in the
+/// invocation record.
+const VCS_STATE_TIMEOUT: Duration = Duration::from_secs(2);
+
 pub fn try_get_invocation_recorder<'a>(
     ctx: &ClientCommandContext<'a>,
     opts: &CommonDaemonCommandOptions,
@@ -1169,6 +1224,24 @@ pub fn try_get_invocation_recorder<'a>(
         filesystem = "default".to_owned();
     }
 
+    let vcs_state = Arc::new(OnceLock::new());
+    let vcs = if ctx.record_vcs_revision()? {
+        detect_vcs(ctx.paths()?.project_root().root())
+    } else {
+        None
+    };
+    if let Some(vcs) = vcs {
+        let vcs_state = vcs_state.dupe();
+        tokio::spawn(async move {
+            match state_with_timeout(&*vcs, VCS_STATE_TIMEOUT).await {
+                Ok(state) => {
+                    let _ignored = vcs_state.set(state);
+                }
+                Err(e) => tracing::debug!("Failed to get source control state: {:#}", e),
+            }
+        });
+    }
+
     let recorder = imp::InvocationRecorder::new(
         ctx.fbinit(),
         ctx.async_cleanup_context().dupe(),
@@ -1178,6 +1251,7 @@ pub fn try_get_invocation_recorder<'a>(
         ctx.trace_id.dupe(),
         ctx.paths()?.isolation.as_str().to_owned(),
         BuildCountManager::new(ctx.paths()?.build_count_dir()),
+        InvocationHistory::new(ctx.paths()?.invocation_history_path()),
         filesystem,
         ctx.restarted_trace_id.dupe(),
         log_size_counter_bytes,
@@ -1185,6 +1259,7 @@ pub fn try_get_invocation_recorder<'a>(
             .iter()
             .map(ClientMetadata::to_proto)
             .collect(),
+        vcs_state,
     );
     Ok(Box::new(recorder))
 }
@@ -386,6 +386,25 @@ where
         self.handle_stderr(&message.message).await
     }
 
+    async fn handle_installer_log(&mut self, log: &buck2_data::InstallerLog) -> anyhow::Result<()> {
+        if log.level() == buck2_data::installer_log::Level::Debug
+            && !self.verbosity.print_all_commands()
+        {
+            return Ok(());
+        }
+        self.handle_stderr(&format!("[{}] {}", log.installer, log.message))
+            .await
+    }
+
+    async fn handle_streaming_output(
+        &mut self,
+        output: &buck2_data::StreamingOutput,
+    ) -> anyhow::Result<()> {
+        crate::subscribers::errorconsole::ErrorConsole
+            .handle_streaming_output(output)
+            .await
+    }
+
     async fn handle_re_session_created(
         &mut self,
         session: &buck2_data::RemoteExecutionSessionCreated,
@@ -115,6 +115,9 @@ pub trait UnpackingEventSubscriber: Send {
             buck2_data::instant_event::Data::ConsoleWarning(message) => {
                 self.handle_console_warning(message, event).await
             }
+            buck2_data::instant_event::Data::StreamingOutput(output) => {
+                self.handle_streaming_output(output).await
+            }
             buck2_data::instant_event::Data::ReSession(session) => {
                 self.handle_re_session_created(session, event).await
             }
@@ -137,6 +140,9 @@ pub trait UnpackingEventSubscriber: Send {
             buck2_data::instant_event::Data::ActionError(error) => {
                 self.handle_action_error(error).await
             }
+            buck2_data::instant_event::Data::InstallerLog(log) => {
+                self.handle_installer_log(log).await
+            }
             _ => Ok(()),
         }
     }
@@ -177,6 +183,13 @@ pub trait UnpackingEventSubscriber: Send {
         _event: &BuckEvent,
     ) -> anyhow::Result<()>;
 
+    async fn handle_streaming_output(
+        &mut self,
+        _output: &buck2_data::StreamingOutput,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
     async fn handle_re_session_created(
         &mut self,
         _session: &buck2_data::RemoteExecutionSessionCreated,
@@ -219,6 +232,13 @@ pub trait UnpackingEventSubscriber: Send {
         Ok(())
     }
 
+    async fn handle_installer_log(
+        &mut self,
+        _log: &buck2_data::InstallerLog,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
     /// Give the subscriber a chance to react to errors as we start trying to clean up.
     /// They may return another error, which will be incorporated into the end result.
     async fn handle_error(&mut self, _error: &anyhow::Error) -> anyhow::Result<()>;
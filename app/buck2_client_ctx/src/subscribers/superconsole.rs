@@ -52,6 +52,7 @@ use crate::subscribers::superconsole::commands::CommandsComponent;
 use crate::subscribers::superconsole::debug_events::DebugEventsComponent;
 use crate::subscribers::superconsole::debugger::StarlarkDebuggerComponent;
 use crate::subscribers::superconsole::dice::DiceComponent;
+use crate::subscribers::superconsole::downloads::DownloadsComponent;
 use crate::subscribers::superconsole::io::IoHeader;
 use crate::subscribers::superconsole::re::ReHeader;
 use crate::subscribers::superconsole::session_info::SessionInfoComponent;
@@ -64,6 +65,7 @@ mod common;
 pub(crate) mod debug_events;
 mod debugger;
 pub(crate) mod dice;
+mod downloads;
 pub(crate) mod io;
 mod re;
 pub mod session_info;
@@ -178,6 +180,12 @@ impl<'s> Component for BuckRootComponent<'s> {
             },
             mode,
         )?;
+        draw.draw(
+            &DownloadsComponent {
+                download_state: self.state.simple_console.observer.download_state(),
+            },
+            mode,
+        )?;
         draw.draw(
             &TestHeader {
                 session_info: self.state.session_info(),
@@ -600,6 +608,17 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
         }
     }
 
+    async fn handle_streaming_output(
+        &mut self,
+        output: &buck2_data::StreamingOutput,
+    ) -> anyhow::Result<()> {
+        // Streamed output goes to stdout, which the superconsole does not draw on.
+        self.state
+            .simple_console
+            .handle_streaming_output(output)
+            .await
+    }
+
     async fn handle_console_warning(
         &mut self,
         message: &buck2_data::ConsoleWarning,
@@ -623,6 +642,34 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
         }
     }
 
+    async fn handle_installer_log(&mut self, log: &buck2_data::InstallerLog) -> anyhow::Result<()> {
+        match &mut self.super_console {
+            Some(super_console) => {
+                let foreground_color = match log.level() {
+                    buck2_data::installer_log::Level::Info => None,
+                    buck2_data::installer_log::Level::Debug => {
+                        if !self.verbosity.print_all_commands() {
+                            return Ok(());
+                        }
+                        Some(Color::DarkGrey)
+                    }
+                    buck2_data::installer_log::Level::Warn => Some(Color::Yellow),
+                    buck2_data::installer_log::Level::Error => Some(Color::Red),
+                };
+                let style = ContentStyle {
+                    foreground_color,
+                    ..Default::default()
+                };
+                super_console.emit(Lines::from_multiline_string(
+                    &format!("[{}] {}", log.installer, log.message),
+                    style,
+                ));
+                Ok(())
+            }
+            None => self.state.simple_console.handle_installer_log(log).await,
+        }
+    }
+
     async fn handle_action_execution_end(
         &mut self,
         action: &buck2_data::ActionExecutionEnd,
@@ -0,0 +1,80 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use buck2_event_observer::download_state::DownloadState;
+use buck2_event_observer::fmt_duration::fmt_duration;
+use buck2_event_observer::humanized::HumanizedBytes;
+use buck2_event_observer::humanized::HumanizedBytesPerSecond;
+use gazebo::prelude::*;
+use superconsole::Component;
+use superconsole::Dimensions;
+use superconsole::DrawMode;
+use superconsole::Lines;
+
+/// Number of running downloads listed, the others are only counted.
+const MAX_DOWNLOADS_SHOWN: usize = 3;
+
+/// Downloads from the network which are running, with their progress.
+pub(crate) struct DownloadsComponent<'s> {
+    pub(crate) download_state: &'s DownloadState,
+}
+
+impl<'s> Component for DownloadsComponent<'s> {
+    fn draw_unchecked(&self, dimensions: Dimensions, mode: DrawMode) -> anyhow::Result<Lines> {
+        let state = self.download_state;
+        if state.active().len() == 0 || mode == DrawMode::Final {
+            return Ok(Lines::new());
+        }
+
+        let mut lines = vec![format!(
+            "Downloads: {} running, {} done ({})",
+            state.active().len(),
+            state.finished,
+            HumanizedBytes::new(state.finished_bytes),
+        )];
+        for download in state.active().take(MAX_DOWNLOADS_SHOWN) {
+            let size = match download.total_bytes {
+                Some(total_bytes) => format!(
+                    "{} / {}",
+                    HumanizedBytes::fixed_width(download.bytes_downloaded),
+                    HumanizedBytes::new(total_bytes)
+                ),
+                None => HumanizedBytes::fixed_width(download.bytes_downloaded).to_string(),
+            };
+            let remaining = match download.remaining() {
+                Some(remaining) => format!(", {} remaining", fmt_duration(remaining, 1.0)),
+                None => String::new(),
+            };
+            let progress = format!(
+                " {} at {}{}",
+                size,
+                HumanizedBytesPerSecond::new(download.bytes_per_second()),
+                remaining
+            );
+            // Truncate the URL rather than the progress.
+            let url_width = dimensions.width.saturating_sub(progress.len() + 2).max(10);
+            let excess = download.url.chars().count().saturating_sub(url_width);
+            let url = if excess > 0 {
+                format!(
+                    "...{}",
+                    download.url.chars().skip(excess + 3).collect::<String>()
+                )
+            } else {
+                download.url.clone()
+            };
+            lines.push(format!("  {}{}", url, progress));
+        }
+        let hidden = state.active().len().saturating_sub(MAX_DOWNLOADS_SHOWN);
+        if hidden > 0 {
+            lines.push(format!("  and {} more", hidden));
+        }
+
+        Ok(Lines(lines.into_try_map(|v| vec![v].try_into())?))
+    }
+}
@@ -74,6 +74,12 @@ impl TestCounterColumn {
         get_from_test_state: |test_state| test_state.timeout,
         get_from_test_statues: |_test_statuses| &None,
     };
+    const MEMORY_LIMIT: TestCounterColumn = TestCounterColumn {
+        label: "Memory Limit",
+        color: Some(Color::Red),
+        get_from_test_state: |test_state| test_state.memory_limit_exceeded,
+        get_from_test_statues: |_test_statuses| &None,
+    };
 
     fn to_span_from_test_state(&self, test_state: &TestState) -> anyhow::Result<Span> {
         StylizedCount {
@@ -129,6 +135,10 @@ impl TestCounterComponent {
         spans.push(TestCounterColumn::SKIP.to_span_from_test_state(test_state)?);
         spans.push(". ".try_into()?);
         spans.push(TestCounterColumn::TIMEOUT.to_span_from_test_state(test_state)?);
+        if test_state.memory_limit_exceeded > 0 {
+            spans.push(". ".try_into()?);
+            spans.push(TestCounterColumn::MEMORY_LIMIT.to_span_from_test_state(test_state)?);
+        }
         Ok(Lines::from_iter([Line::from_iter(spans)]))
     }
 }
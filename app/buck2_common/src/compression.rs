@@ -0,0 +1,149 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Compression of data buck2 produces itself, such as event logs (`buck2.event_log_compression`)
+//! and the contents of deferred write actions (`buck2.write_action_compression`).
+//!
+//! Settings are written `CODEC` or `CODEC:LEVEL`, for example `zstd`, `zstd:19` or `gzip:6`, or
+//! `none` to disable compression. Higher levels trade CPU for size.
+
+use std::fmt;
+use std::str::FromStr;
+
+use allocative::Allocative;
+use derive_more::Display;
+use dupe::Dupe;
+
+#[derive(Debug, buck2_error::Error)]
+enum CompressionConfigError {
+    #[error("Invalid compression `{0}`, expected `none`, `gzip[:LEVEL]` or `zstd[:LEVEL]`")]
+    Invalid(String),
+    #[error("Invalid {codec} compression level `{level}`, expected {min} to {max}")]
+    InvalidLevel {
+        codec: CompressionCodec,
+        level: i32,
+        min: i32,
+        max: i32,
+    },
+}
+
+#[derive(Debug, Display, Eq, PartialEq, Clone, Copy, Dupe, Hash, Allocative)]
+pub enum CompressionCodec {
+    #[display(fmt = "none")]
+    None,
+    #[display(fmt = "gzip")]
+    Gzip,
+    #[display(fmt = "zstd")]
+    Zstd,
+}
+
+impl CompressionCodec {
+    /// Range of the levels of this codec.
+    fn levels(self) -> Option<(i32, i32)> {
+        match self {
+            CompressionCodec::None => None,
+            CompressionCodec::Gzip => Some((0, 9)),
+            CompressionCodec::Zstd => Some((1, 22)),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Dupe, Hash, Allocative)]
+pub struct CompressionConfig {
+    pub codec: CompressionCodec,
+    /// Compression level, or `None` for the default of the codec.
+    pub level: Option<i32>,
+}
+
+impl CompressionConfig {
+    pub const NONE: CompressionConfig = CompressionConfig {
+        codec: CompressionCodec::None,
+        level: None,
+    };
+
+    pub const ZSTD: CompressionConfig = CompressionConfig {
+        codec: CompressionCodec::Zstd,
+        level: None,
+    };
+}
+
+impl fmt::Display for CompressionConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.level {
+            Some(level) => write!(f, "{}:{}", self.codec, level),
+            None => write!(f, "{}", self.codec),
+        }
+    }
+}
+
+impl FromStr for CompressionConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (codec, level) = match s.split_once(':') {
+            Some((codec, level)) => (codec, Some(level)),
+            None => (s, None),
+        };
+        let codec = match codec {
+            "none" => CompressionCodec::None,
+            "gzip" => CompressionCodec::Gzip,
+            "zstd" => CompressionCodec::Zstd,
+            _ => return Err(CompressionConfigError::Invalid(s.to_owned()).into()),
+        };
+        let level = match (level, codec.levels()) {
+            (None, _) => None,
+            (Some(level), Some((min, max))) => {
+                let level = level
+                    .parse()
+                    .map_err(|_| CompressionConfigError::Invalid(s.to_owned()))?;
+                if level < min || level > max {
+                    return Err(CompressionConfigError::InvalidLevel {
+                        codec,
+                        level,
+                        min,
+                        max,
+                    }
+                    .into());
+                }
+                Some(level)
+            }
+            (Some(_), None) => return Err(CompressionConfigError::Invalid(s.to_owned()).into()),
+        };
+        Ok(CompressionConfig { codec, level })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compression::CompressionCodec;
+    use crate::compression::CompressionConfig;
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        assert_eq!(CompressionConfig::NONE, "none".parse()?);
+        assert_eq!(CompressionConfig::ZSTD, "zstd".parse()?);
+        assert_eq!(
+            CompressionConfig {
+                codec: CompressionCodec::Gzip,
+                level: Some(6),
+            },
+            "gzip:6".parse()?
+        );
+        assert_eq!(
+            "zstd:19",
+            "zstd:19".parse::<CompressionConfig>()?.to_string()
+        );
+
+        assert!("lz4".parse::<CompressionConfig>().is_err());
+        assert!("none:1".parse::<CompressionConfig>().is_err());
+        assert!("gzip:10".parse::<CompressionConfig>().is_err());
+        assert!("zstd:fast".parse::<CompressionConfig>().is_err());
+        Ok(())
+    }
+}
@@ -11,6 +11,7 @@
 
 use std::sync::Arc;
 
+use buck2_core::fs::case_insensitive::FilesystemCaseSensitivity;
 use dice::DiceData;
 use dice::DiceDataBuilder;
 use dupe::Dupe;
@@ -41,6 +42,30 @@ impl SetIoProvider for DiceDataBuilder {
     }
 }
 
+pub trait HasCaseSensitivity {
+    fn get_case_sensitivity(&self) -> FilesystemCaseSensitivity;
+}
+
+pub trait SetCaseSensitivity {
+    fn set_case_sensitivity(&mut self, case_sensitivity: FilesystemCaseSensitivity);
+}
+
+impl HasCaseSensitivity for DiceData {
+    /// Filesystems are treated as case-sensitive unless detected otherwise.
+    fn get_case_sensitivity(&self) -> FilesystemCaseSensitivity {
+        self.get::<FilesystemCaseSensitivity>()
+            .ok()
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+impl SetCaseSensitivity for DiceDataBuilder {
+    fn set_case_sensitivity(&mut self, case_sensitivity: FilesystemCaseSensitivity) {
+        self.set(case_sensitivity)
+    }
+}
+
 pub mod testing {
     use buck2_core::fs::project::ProjectRootTemp;
 
@@ -10,6 +10,7 @@
 use std::collections::HashSet;
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::mem;
 use std::sync::Arc;
 
 use allocative::Allocative;
@@ -30,23 +31,39 @@ use cmp_any::PartialEqAny;
 use derivative::Derivative;
 use derive_more::Display;
 use dice::DiceComputations;
+use dice::DiceData;
+use dice::DiceDataBuilder;
 use dice::DiceTransactionUpdater;
 use dice::Key;
 use dupe::Dupe;
+use parking_lot::Mutex;
 
 use crate::dice::cells::HasCellResolver;
 use crate::dice::data::HasIoProvider;
 use crate::dice::file_ops::keys::FileOpsKey;
 use crate::dice::file_ops::keys::FileOpsValue;
+use crate::external_symlink::ExternalSymlink;
+use crate::external_symlink_policy::AllCellSymlinkPolicies;
+use crate::external_symlink_policy::ExternalSymlinkPolicy;
 use crate::file_ops::FileOps;
+use crate::file_ops::FileType;
 use crate::file_ops::RawDirEntry;
 use crate::file_ops::RawPathMetadata;
+use crate::file_ops::RawSymlink;
 use crate::file_ops::ReadDirOutput;
 use crate::file_ops::SimpleDirEntry;
 use crate::ignores::all_cells::AllCellIgnores;
 use crate::ignores::all_cells::HasAllCellIgnores;
 use crate::io::IoProvider;
 
+#[derive(Debug, buck2_error::Error)]
+enum ExternalSymlinkError {
+    #[error(
+        "Symlink `{0}` points outside the project root, to `{1}`, which is disallowed by `project.external_symlinks = error` in this cell"
+    )]
+    Disallowed(CellPath, String),
+}
+
 pub trait HasFileOps<'c> {
     type T: FileOps;
     fn file_ops(&'c self) -> Self::T;
@@ -101,6 +118,10 @@ async fn get_default_file_ops(dice: &DiceComputations) -> buck2_error::Result<Ar
         io: Arc<dyn IoProvider>,
         cells: CellResolver,
         ignores: Arc<AllCellIgnores>,
+        symlink_policies: Arc<AllCellSymlinkPolicies>,
+        // Safe to ignore because this does not change during the lifetime of the daemon.
+        #[derivative(PartialEq = "ignore")]
+        followed_external_symlinks: Option<Arc<FollowedExternalSymlinks>>,
     }
 
     impl DiceFileOpsDelegate {
@@ -120,6 +141,21 @@ async fn get_default_file_ops(dice: &DiceComputations) -> buck2_error::Result<Ar
         fn io_provider(&self) -> &dyn IoProvider {
             self.io.as_ref()
         }
+
+        /// Read the metadata of the target of an external symlink.
+        async fn follow_external_symlink(
+            &self,
+            at: CellPathRef<'_>,
+            symlink: &ExternalSymlink,
+        ) -> anyhow::Result<Option<RawPathMetadata>> {
+            let res = self
+                .io_provider()
+                .read_external_path_metadata_if_exists(symlink.to_path_buf())
+                .await
+                .with_context(|| format!("Error following symlink `{}` to `{}`", at, symlink))?;
+            res.map(|meta| meta.try_map(|path| Ok(Arc::new(self.get_cell_path(&path)?))))
+                .transpose()
+        }
     }
 
     #[async_trait]
@@ -191,6 +227,23 @@ async fn get_default_file_ops(dice: &DiceComputations) -> buck2_error::Result<Ar
                             continue;
                         }
                     };
+                    // Globs see symlinks through directory listings, so apply the external
+                    // symlink policy here too: a followed symlink lists as its target.
+                    let file_type = if file_type.is_symlink()
+                        && self.symlink_policies.get(path.cell()) != ExternalSymlinkPolicy::Opaque
+                    {
+                        if let Some(followed) = &self.followed_external_symlinks {
+                            followed.record_dir(path.to_owned());
+                        }
+                        let child = path.join(&file_name);
+                        match self.read_path_metadata_if_exists(child.as_ref()).await? {
+                            Some(RawPathMetadata::File(_)) => FileType::File,
+                            Some(RawPathMetadata::Directory) => FileType::Directory,
+                            _ => file_type,
+                        }
+                    } else {
+                        file_type
+                    };
                     included_entries.push(SimpleDirEntry {
                         file_name,
                         file_type,
@@ -214,8 +267,34 @@ async fn get_default_file_ops(dice: &DiceComputations) -> buck2_error::Result<Ar
                 .read_path_metadata_if_exists(project_path)
                 .await
                 .with_context(|| format!("Error accessing metadata for path `{}`", path))?;
-            res.map(|meta| meta.try_map(|path| Ok(Arc::new(self.get_cell_path(&path)?))))
-                .transpose()
+            let res = res
+                .map(|meta| meta.try_map(|path| Ok(Arc::new(self.get_cell_path(&path)?))))
+                .transpose()?;
+
+            if let Some(RawPathMetadata::Symlink {
+                at,
+                to: RawSymlink::External(symlink),
+            }) = &res
+            {
+                match self.symlink_policies.get(at.cell()) {
+                    ExternalSymlinkPolicy::Opaque => {}
+                    ExternalSymlinkPolicy::Follow => {
+                        if let Some(followed) = &self.followed_external_symlinks {
+                            followed.record_path(path.to_owned());
+                        }
+                        return self.follow_external_symlink((**at).as_ref(), symlink).await;
+                    }
+                    ExternalSymlinkPolicy::Error => {
+                        return Err(ExternalSymlinkError::Disallowed(
+                            (**at).clone(),
+                            symlink.to_string(),
+                        )
+                        .into());
+                    }
+                }
+            }
+
+            Ok(res)
         }
 
         async fn is_ignored(&self, path: CellPathRef<'async_trait>) -> anyhow::Result<bool> {
@@ -242,11 +321,15 @@ async fn get_default_file_ops(dice: &DiceComputations) -> buck2_error::Result<Ar
             let io = ctx.global_data().get_io_provider();
 
             let ignores = ctx.new_all_cell_ignores().await?;
+            let symlink_policies = AllCellSymlinkPolicies::compute(ctx).await?;
+            let followed_external_symlinks = ctx.global_data().get_followed_external_symlinks();
 
             Ok(FileOpsValue(Arc::new(DiceFileOpsDelegate {
                 io,
                 cells,
                 ignores,
+                symlink_policies,
+                followed_external_symlinks,
             })))
         }
 
@@ -265,6 +348,60 @@ async fn get_default_file_ops(dice: &DiceComputations) -> buck2_error::Result<Ar
     Ok(dice.compute(&FileOpsKey()).await??.0)
 }
 
+/// Paths read through source symlinks that the cell's policy says to follow out of the project
+/// root. The file watcher does not see changes to their targets, so they are dirtied at the start
+/// of every command instead.
+#[derive(Allocative)]
+pub struct FollowedExternalSymlinks {
+    changes: Mutex<FileChangeTracker>,
+}
+
+impl FollowedExternalSymlinks {
+    pub fn new() -> Self {
+        Self {
+            changes: Mutex::new(FileChangeTracker::new()),
+        }
+    }
+
+    fn record_path(&self, path: CellPath) {
+        self.changes
+            .lock()
+            .paths_to_dirty
+            .insert(PathMetadataKey(path));
+    }
+
+    fn record_dir(&self, path: CellPath) {
+        self.changes.lock().dirs_to_dirty.insert(ReadDirKey(path));
+    }
+
+    /// Dirty everything read through a followed external symlink since the last call. Paths that
+    /// are read again are recorded again when they are recomputed.
+    pub fn write_to_dice(&self, ctx: &mut DiceTransactionUpdater) -> anyhow::Result<()> {
+        let changes = mem::replace(&mut *self.changes.lock(), FileChangeTracker::new());
+        changes.write_to_dice(ctx)
+    }
+}
+
+pub trait HasFollowedExternalSymlinks {
+    fn get_followed_external_symlinks(&self) -> Option<Arc<FollowedExternalSymlinks>>;
+}
+
+pub trait SetFollowedExternalSymlinks {
+    fn set_followed_external_symlinks(&mut self, followed: Arc<FollowedExternalSymlinks>);
+}
+
+impl HasFollowedExternalSymlinks for DiceData {
+    fn get_followed_external_symlinks(&self) -> Option<Arc<FollowedExternalSymlinks>> {
+        self.get::<Arc<FollowedExternalSymlinks>>().ok().cloned()
+    }
+}
+
+impl SetFollowedExternalSymlinks for DiceDataBuilder {
+    fn set_followed_external_symlinks(&mut self, followed: Arc<FollowedExternalSymlinks>) {
+        self.set(followed)
+    }
+}
+
 #[derive(Allocative)]
 pub struct FileChangeTracker {
     files_to_dirty: HashSet<ReadFileKey>,
@@ -485,3 +622,103 @@ impl<'c> FileOps for DiceFileOps<'c> {
 pub mod testing {
     pub use super::keys::FileOpsKey;
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::sync::Arc;
+
+    use buck2_core::cells::cell_path::CellPath;
+    use buck2_core::cells::cell_root_path::CellRootPathBuf;
+    use buck2_core::cells::name::CellName;
+    use buck2_core::cells::paths::CellRelativePathBuf;
+    use buck2_core::cells::CellResolver;
+    use buck2_core::fs::project::ProjectRootTemp;
+    use buck2_core::fs::project_rel_path::ProjectRelativePath;
+    use dice::DetectCycles;
+    use dice::Dice;
+    use dice::DiceTransaction;
+    use dupe::Dupe;
+
+    use crate::dice::cells::SetCellResolver;
+    use crate::dice::data::testing::SetTestingIoProvider;
+    use crate::dice::file_ops::FollowedExternalSymlinks;
+    use crate::dice::file_ops::HasFileOps;
+    use crate::dice::file_ops::SetFollowedExternalSymlinks;
+    use crate::file_ops::FileOps;
+    use crate::file_ops::RawPathMetadata;
+    use crate::legacy_configs::dice::SetLegacyConfigs;
+    use crate::legacy_configs::testing::legacy_buck_config_from_entries;
+    use crate::legacy_configs::LegacyBuckConfigs;
+
+    async fn read_link(ctx: &DiceTransaction) -> RawPathMetadata {
+        let path = CellPath::new(
+            CellName::testing_new("root"),
+            CellRelativePathBuf::unchecked_new("link".to_owned()),
+        );
+        match ctx
+            .file_ops()
+            .read_path_metadata_if_exists(path.as_ref())
+            .await
+            .unwrap()
+        {
+            Some(meta @ RawPathMetadata::File(_)) => meta,
+            meta => panic!(
+                "Expected the symlink to be followed to a file, got {:?}",
+                meta
+            ),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_followed_external_symlink_is_dirtied_on_every_command() -> anyhow::Result<()> {
+        let project_root = ProjectRootTemp::new()?;
+        let external = tempfile::tempdir()?;
+        let target = external.path().join("target");
+        std::fs::write(&target, "old")?;
+        std::os::unix::fs::symlink(
+            &target,
+            project_root
+                .path()
+                .resolve(ProjectRelativePath::unchecked_new("link")),
+        )?;
+
+        let followed = Arc::new(FollowedExternalSymlinks::new());
+        let mut builder = Dice::builder();
+        builder.set_testing_io_provider(&project_root);
+        builder.set_followed_external_symlinks(followed.dupe());
+        let dice = builder.build(DetectCycles::Disabled);
+
+        let cell = CellName::testing_new("root");
+        let mut updater = dice.updater();
+        updater.set_cell_resolver(CellResolver::testing_with_name_and_path(
+            cell,
+            CellRootPathBuf::testing_new(""),
+        ))?;
+        updater.set_legacy_configs(LegacyBuckConfigs::new(hashmap![
+            cell => legacy_buck_config_from_entries([("project", "external_symlinks", "follow")])?,
+        ]))?;
+        let ctx = updater.commit().await;
+        let before = read_link(&ctx).await;
+        drop(ctx);
+
+        // The target is outside the project root, so no file watcher reports this change.
+        std::fs::write(&target, "new contents")?;
+
+        let mut updater = dice.updater();
+        followed.write_to_dice(&mut updater)?;
+        let ctx = updater.commit().await;
+        let after = read_link(&ctx).await;
+        assert_ne!(before, after);
+        drop(ctx);
+
+        // The path was recorded again when it was recomputed, so later changes are seen too.
+        std::fs::write(&target, "newer contents")?;
+
+        let mut updater = dice.updater();
+        followed.write_to_dice(&mut updater)?;
+        let ctx = updater.commit().await;
+        assert_ne!(after, read_link(&ctx).await);
+
+        Ok(())
+    }
+}
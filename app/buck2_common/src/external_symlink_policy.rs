@@ -0,0 +1,103 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! How source symlinks that point outside the project root are handled, configured per cell with
+//! `project.external_symlinks`.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use allocative::Allocative;
+use buck2_core::cells::name::CellName;
+use derive_more::Display;
+use dice::DiceComputations;
+use dupe::Dupe;
+
+use crate::dice::cells::HasCellResolver;
+use crate::legacy_configs::dice::HasLegacyConfigs;
+use crate::legacy_configs::view::LegacyBuckConfigView;
+
+#[derive(
+    Debug, Display, Default, Eq, PartialEq, Clone, Copy, Dupe, Hash, Allocative
+)]
+pub enum ExternalSymlinkPolicy {
+    /// Keep the symlink as a symlink to an absolute path. Its target is neither read nor hashed.
+    #[default]
+    #[display(fmt = "opaque")]
+    Opaque,
+    /// Read through the symlink and treat its target as if it were in the repo.
+    #[display(fmt = "follow")]
+    Follow,
+    /// Fail on any access to the symlink.
+    #[display(fmt = "error")]
+    Error,
+}
+
+impl FromStr for ExternalSymlinkPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "opaque" => Ok(ExternalSymlinkPolicy::Opaque),
+            "follow" => Ok(ExternalSymlinkPolicy::Follow),
+            "error" => Ok(ExternalSymlinkPolicy::Error),
+            _ => Err(anyhow::anyhow!(
+                "Invalid external symlink policy: `{}`, expected one of `opaque`, `follow` or `error`",
+                s
+            )),
+        }
+    }
+}
+
+/// External symlink policies for all cells.
+#[derive(Allocative, Debug, Eq, PartialEq)]
+pub(crate) struct AllCellSymlinkPolicies {
+    policies: HashMap<CellName, ExternalSymlinkPolicy>,
+}
+
+impl AllCellSymlinkPolicies {
+    pub(crate) fn get(&self, cell: CellName) -> ExternalSymlinkPolicy {
+        self.policies.get(&cell).copied().unwrap_or_default()
+    }
+
+    pub(crate) async fn compute(ctx: &DiceComputations) -> anyhow::Result<Arc<Self>> {
+        let cells = ctx.get_cell_resolver().await?;
+        let configs = ctx.get_legacy_configs_on_dice().await?;
+
+        let mut policies = HashMap::new();
+        for (cell_name, _) in cells.cells() {
+            let config = configs.get(cell_name)?;
+            let policy = (&config as &dyn LegacyBuckConfigView)
+                .parse("project", "external_symlinks")?
+                .unwrap_or_default();
+            policies.insert(cell_name, policy);
+        }
+
+        Ok(Arc::new(AllCellSymlinkPolicies { policies }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            ExternalSymlinkPolicy::Follow,
+            "follow".parse::<ExternalSymlinkPolicy>().unwrap()
+        );
+        assert_eq!(
+            ExternalSymlinkPolicy::Error,
+            "error".parse::<ExternalSymlinkPolicy>().unwrap()
+        );
+        assert!("hash".parse::<ExternalSymlinkPolicy>().is_err());
+    }
+}
@@ -110,6 +110,19 @@ impl InvocationPaths {
             .join(ForwardRelativePath::unchecked_new("build_count"))
     }
 
+    /// Index of recent invocations, shown by `buck2 log recent`. It is not in the log
+    /// directory, whose files are cleaned up as logs.
+    pub fn invocation_history_path(&self) -> AbsNormPathBuf {
+        self.buck_out_path()
+            .join(ForwardRelativePath::unchecked_new("invocation_history.jsonl"))
+    }
+
+    /// Target universes saved by `buck2 cquery --save-target-universe`.
+    pub fn target_universes_dir(&self) -> AbsNormPathBuf {
+        self.buck_out_path()
+            .join(ForwardRelativePath::unchecked_new("target_universes"))
+    }
+
     pub fn dice_dump_dir(&self) -> AbsNormPathBuf {
         self.buck_out_path()
             .join(ForwardRelativePath::unchecked_new("dice_dump"))
@@ -139,6 +152,12 @@ impl InvocationPaths {
             .join(ForwardRelativePath::unchecked_new("paranoid"))
     }
 
+    /// Directory where paranoid downloads that failed verification are kept for investigation.
+    pub fn paranoid_quarantine_dir(&self) -> ProjectRelativePathBuf {
+        self.buck_out_dir()
+            .join(ForwardRelativePath::unchecked_new("paranoid_quarantine"))
+    }
+
     pub fn cache_dir_path(&self) -> AbsNormPathBuf {
         self.roots.project_root.root().join(self.cache_dir())
     }
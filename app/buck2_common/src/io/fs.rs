@@ -164,6 +164,37 @@ impl IoProvider for FsIoProvider {
         .await?
     }
 
+    async fn read_external_path_metadata_if_exists(
+        &self,
+        path: PathBuf,
+    ) -> anyhow::Result<Option<RawPathMetadata<ProjectRelativePathBuf>>> {
+        let file_digest_config = FileDigestConfig::source(self.cas_digest_config);
+
+        tokio::task::spawn_blocking(move || {
+            let path = AbsPathBuf::new(path)?;
+            let meta = match std::fs::metadata(&path) {
+                Ok(meta) => meta,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+                Err(e) => {
+                    return Err(anyhow::Error::from(e))
+                        .with_context(|| format!("Error accessing `{}`", path.display()));
+                }
+            };
+            if meta.is_dir() {
+                return Ok(Some(RawPathMetadata::Directory));
+            }
+            let digest = FileDigest::from_file(&path, file_digest_config).with_context(|| {
+                format!("Error collecting file digest for `{}`", path.display())
+            })?;
+            let digest = TrackedFileDigest::new(digest, file_digest_config.as_cas_digest_config());
+            Ok(Some(RawPathMetadata::File(FileMetadata {
+                digest,
+                is_executable: is_executable(&meta),
+            })))
+        })
+        .await?
+    }
+
     async fn settle(&self) -> anyhow::Result<()> {
         Ok(())
     }
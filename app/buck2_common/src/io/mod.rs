@@ -10,6 +10,8 @@
 pub mod fs;
 pub mod trace;
 
+use std::path::PathBuf;
+
 use allocative::Allocative;
 use async_trait::async_trait;
 use buck2_core::fs::project::ProjectRoot;
@@ -32,6 +34,13 @@ pub trait IoProvider: Allocative + Send + Sync {
         path: ProjectRelativePathBuf,
     ) -> anyhow::Result<Option<RawPathMetadata<ProjectRelativePathBuf>>>;
 
+    /// Read the metadata of a path outside the project root, following symlinks. This is used
+    /// to hash the targets of external symlinks when the cell's policy says to follow them.
+    async fn read_external_path_metadata_if_exists(
+        &self,
+        path: PathBuf,
+    ) -> anyhow::Result<Option<RawPathMetadata<ProjectRelativePathBuf>>>;
+
     /// Request that this I/O provider be up to date with whatever I/O operations the user might
     /// have done until this point.
     async fn settle(&self) -> anyhow::Result<()>;
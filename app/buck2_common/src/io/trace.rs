@@ -8,6 +8,7 @@
  */
 
 use std::borrow::Cow;
+use std::path::PathBuf;
 
 use allocative::Allocative;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
@@ -169,6 +170,13 @@ impl IoProvider for TracingIoProvider {
         Ok(res)
     }
 
+    async fn read_external_path_metadata_if_exists(
+        &self,
+        path: PathBuf,
+    ) -> anyhow::Result<Option<RawPathMetadata<ProjectRelativePathBuf>>> {
+        self.io.read_external_path_metadata_if_exists(path).await
+    }
+
     async fn settle(&self) -> anyhow::Result<()> {
         self.io.settle().await
     }
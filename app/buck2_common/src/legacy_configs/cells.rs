@@ -10,6 +10,9 @@
 use std::cell::OnceCell;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::thread;
 
 use anyhow::Context;
 use buck2_core::buck2_env;
@@ -26,13 +29,19 @@ use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use gazebo::prelude::*;
+use parking_lot::Mutex;
 
+use crate::legacy_configs::external_cells::resolve_external_cells;
 use crate::legacy_configs::init::DaemonStartupConfig;
+use crate::legacy_configs::parse_cache::parse_cell_config;
+use crate::legacy_configs::parse_cache::CACHE_DIR;
 use crate::legacy_configs::path::BuckConfigFile;
 use crate::legacy_configs::path::DEFAULT_BUCK_CONFIG_FILES;
 use crate::legacy_configs::push_all_files_from_a_directory;
+use crate::legacy_configs::schema::BuckConfigSchema;
 use crate::legacy_configs::BuckConfigParseOptions;
 use crate::legacy_configs::CellResolutionState;
+use crate::legacy_configs::ConfigFileStamp;
 use crate::legacy_configs::ConfigParserFileOps;
 use crate::legacy_configs::DefaultConfigParserFileOps;
 use crate::legacy_configs::LegacyBuckConfig;
@@ -69,16 +78,17 @@ impl BuckConfigBasedCells {
     /// and without parsing any configs for any referenced cells. This means this function might return
     /// an empty mapping if the root `.buckconfig` does not contain the cell definitions.
     pub fn parse_immediate_config(project_fs: &ProjectRoot) -> anyhow::Result<ImmediateConfig> {
-        Self::parse_immediate_config_with_file_ops(project_fs, &mut DefaultConfigParserFileOps {})
+        Self::parse_immediate_config_with_file_ops(project_fs, &DefaultConfigParserFileOps {})
     }
 
     /// Private function with semantics of `parse_immediate_config` but usable for testing.
     pub(crate) fn parse_immediate_config_with_file_ops(
         project_fs: &ProjectRoot,
-        file_ops: &mut dyn ConfigParserFileOps,
+        file_ops: &dyn ConfigParserFileOps,
     ) -> anyhow::Result<ImmediateConfig> {
         let opts = BuckConfigParseOptions {
             follow_includes: false,
+            require_locked_external_cells: false,
         };
         let cells = Self::parse_with_file_ops_and_options(
             project_fs,
@@ -100,12 +110,19 @@ impl BuckConfigBasedCells {
         })
     }
 
+    /// Parse the configs of all cells, leaving out the external cells which `buck2 fetch-cells`
+    /// has not locked yet, so this works while `cells.lock` is outdated.
     pub fn parse(project_fs: &ProjectRoot) -> anyhow::Result<Self> {
-        Self::parse_with_file_ops(
+        let opts = BuckConfigParseOptions {
+            follow_includes: true,
+            require_locked_external_cells: false,
+        };
+        Self::parse_with_file_ops_and_options(
             project_fs,
-            &mut DefaultConfigParserFileOps {},
+            &DefaultConfigParserFileOps {},
             &[],
             ProjectRelativePath::empty(),
+            opts,
         )
     }
 
@@ -114,46 +131,42 @@ impl BuckConfigBasedCells {
         config_args: &[LegacyConfigCmdArg],
         cwd: &ProjectRelativePath,
     ) -> anyhow::Result<Self> {
-        Self::parse_with_file_ops(
-            project_fs,
-            &mut DefaultConfigParserFileOps {},
-            config_args,
-            cwd,
-        )
+        Self::parse_with_file_ops(project_fs, &DefaultConfigParserFileOps {}, config_args, cwd)
     }
 
     pub fn parse_with_file_ops(
         project_fs: &ProjectRoot,
-        file_ops: &mut dyn ConfigParserFileOps,
+        file_ops: &dyn ConfigParserFileOps,
         config_args: &[LegacyConfigCmdArg],
         cwd: &ProjectRelativePath,
     ) -> anyhow::Result<Self> {
         let opts = BuckConfigParseOptions {
             follow_includes: true,
+            require_locked_external_cells: true,
         };
         Self::parse_with_file_ops_and_options(project_fs, file_ops, config_args, cwd, opts)
     }
 
     fn parse_with_file_ops_and_options(
         project_fs: &ProjectRoot,
-        file_ops: &mut dyn ConfigParserFileOps,
+        file_ops: &dyn ConfigParserFileOps,
         config_args: &[LegacyConfigCmdArg],
         cwd: &ProjectRelativePath,
         options: BuckConfigParseOptions,
     ) -> anyhow::Result<Self> {
         // Tracing file ops to record config file accesses on command invocation.
         struct TracingFileOps<'a> {
-            inner: &'a mut dyn ConfigParserFileOps,
-            trace: HashSet<AbsNormPathBuf>,
+            inner: &'a dyn ConfigParserFileOps,
+            trace: Mutex<HashSet<AbsNormPathBuf>>,
         }
 
         impl ConfigParserFileOps for TracingFileOps<'_> {
             fn read_file_lines(
-                &mut self,
+                &self,
                 path: &AbsNormPath,
             ) -> anyhow::Result<Box<dyn Iterator<Item = Result<String, std::io::Error>>>>
             {
-                self.trace.insert(path.to_buf());
+                self.trace.lock().insert(path.to_buf());
                 self.inner.read_file_lines(path)
             }
 
@@ -164,9 +177,13 @@ impl BuckConfigBasedCells {
             fn file_id(&self, path: &AbsNormPath) -> String {
                 self.inner.file_id(path)
             }
+
+            fn file_stamp(&self, path: &AbsNormPath) -> Option<ConfigFileStamp> {
+                self.inner.file_stamp(path)
+            }
         }
 
-        let mut file_ops = TracingFileOps {
+        let file_ops = TracingFileOps {
             inner: file_ops,
             trace: Default::default(),
         };
@@ -186,102 +203,27 @@ impl BuckConfigBasedCells {
             cwd: &project_fs.resolve(cwd),
         };
         // NOTE: This will _not_ perform IO unless it needs to.
-        let processed_config_args = LegacyBuckConfig::process_config_args(
-            config_args,
-            Some(&cell_resolution),
-            &mut file_ops,
-        )?;
+        let processed_config_args =
+            LegacyBuckConfig::process_config_args(config_args, Some(&cell_resolution), &file_ops)?;
 
         let extra_external_config = buck2_env!("BUCK2_TEST_EXTRA_EXTERNAL_CONFIG")?;
 
         let skip_default_external_config =
             buck2_env!("BUCK2_TEST_SKIP_DEFAULT_EXTERNAL_CONFIG", bool)?;
 
-        while let Some(path) = work.pop() {
-            if buckconfigs.contains_key(&path) {
-                continue;
-            }
-
-            let mut buckconfig_paths: Vec<MainConfigFile> = Vec::new();
-
-            for buckconfig in DEFAULT_BUCK_CONFIG_FILES {
-                if skip_default_external_config && buckconfig.is_external() {
-                    continue;
-                }
-
-                match buckconfig {
-                    BuckConfigFile::ProjectRelativeFile(file) => {
-                        let buckconfig_path = ForwardRelativePath::new(file)?;
-                        buckconfig_paths.push(MainConfigFile {
-                            path: project_fs
-                                .resolve(&path.project_relative_path().join(buckconfig_path)),
-                            owned_by_project: true,
-                        });
-                    }
-
-                    BuckConfigFile::ProjectRelativeFolder(folder) => {
-                        let buckconfig_folder_path = ForwardRelativePath::new(folder)?;
-                        let buckconfig_folder_abs_path = project_fs
-                            .resolve(&path.project_relative_path().join(buckconfig_folder_path));
-                        push_all_files_from_a_directory(
-                            &mut buckconfig_paths,
-                            &buckconfig_folder_abs_path,
-                            true,
-                        )?;
-                    }
-                    BuckConfigFile::UserFile(file) => {
-                        let home_dir = dirs::home_dir();
-                        if let Some(home_dir_path) = home_dir {
-                            let buckconfig_path = ForwardRelativePath::new(file)?;
-                            buckconfig_paths.push(MainConfigFile {
-                                path: AbsNormPath::new(&home_dir_path)?
-                                    .join_normalized(buckconfig_path)?,
-                                owned_by_project: false,
-                            });
-                        }
-                    }
-                    BuckConfigFile::UserFolder(folder) => {
-                        let home_dir = dirs::home_dir();
-                        if let Some(home_dir_path) = home_dir {
-                            let buckconfig_path = ForwardRelativePath::new(folder)?;
-                            let buckconfig_folder_abs_path = AbsNormPath::new(&home_dir_path)?
-                                .join_normalized(buckconfig_path)?;
-                            push_all_files_from_a_directory(
-                                &mut buckconfig_paths,
-                                &buckconfig_folder_abs_path,
-                                false,
-                            )?;
-                        }
-                    }
-                    BuckConfigFile::GlobalFile(file) => {
-                        buckconfig_paths.push(MainConfigFile {
-                            path: AbsNormPathBuf::from(String::from(*file))?,
-                            owned_by_project: false,
-                        });
-                    }
-                    BuckConfigFile::GlobalFolder(folder) => {
-                        let buckconfig_folder_abs_path =
-                            AbsNormPathBuf::from(String::from(*folder))?;
-                        push_all_files_from_a_directory(
-                            &mut buckconfig_paths,
-                            &buckconfig_folder_abs_path,
-                            false,
-                        )?;
-                    }
-                }
-            }
-
-            if let Some(f) = extra_external_config {
-                buckconfig_paths.push(MainConfigFile {
-                    path: AbsNormPathBuf::from(f.to_owned())?,
-                    owned_by_project: false,
-                });
-            }
+        let cache_dir = project_fs.resolve(ProjectRelativePath::unchecked_new(CACHE_DIR));
 
-            let existing_configs: Vec<MainConfigFile> = buckconfig_paths
-                .into_iter()
-                .filter(|main_config_file| file_ops.file_exists(&main_config_file.path))
-                .collect();
+        // Returns `None` if the cell has no config file owned by the project.
+        let parse_cell = |path: &CellRootPathBuf| -> anyhow::Result<Option<LegacyBuckConfig>> {
+            let existing_configs: Vec<MainConfigFile> = Self::main_config_files(
+                project_fs,
+                path,
+                skip_default_external_config,
+                extra_external_config,
+            )?
+            .into_iter()
+            .filter(|main_config_file| file_ops.file_exists(&main_config_file.path))
+            .collect();
 
             // Must contains a buckconfig owned by project, otherwise no cell can be found.
             // This also check if existing_configs is empty
@@ -290,28 +232,64 @@ impl BuckConfigBasedCells {
                 .any(|main_config_file| main_config_file.owned_by_project);
 
             if !has_project_owned_config {
-                buckconfigs.insert(path, LegacyBuckConfig::empty());
-                continue;
+                return Ok(None);
             };
 
-            let config = LegacyBuckConfig::parse_with_file_ops_with_includes(
+            let config = parse_cell_config(
+                &cache_dir,
                 existing_configs.as_slice(),
-                &mut file_ops,
+                &file_ops,
                 &processed_config_args,
                 options.follow_includes,
             )?;
 
-            let is_root = path.is_repo_root();
+            let config = match BuckConfigSchema::load(
+                &project_fs.resolve(path.project_relative_path()),
+                &file_ops,
+            )? {
+                Some(schema) => {
+                    schema.validate(&config)?;
+                    schema.apply_defaults(&config)
+                }
+                None => config,
+            };
 
-            let repositories = config.get_section("repositories");
-            if let Some(repositories) = repositories {
-                let mut seen_dot = false;
-                for (alias, alias_path) in repositories.iter() {
-                    if alias_path.as_str() == "." {
-                        seen_dot = true;
+            Ok(Some(config))
+        };
+        let parallelism = thread::available_parallelism().map_or(1, |n| n.get());
+
+        while !work.is_empty() {
+            // Cells are found in the `[repositories]` of the cells referring to them, so configs
+            // are parsed one wave of newly found cells at a time, the cells of a wave in parallel
+            // on at most one thread per core.
+            let mut wave: Vec<CellRootPathBuf> = Vec::new();
+            for path in work.drain(..) {
+                if !buckconfigs.contains_key(&path) && !wave.contains(&path) {
+                    wave.push(path);
+                }
+            }
+            let configs = parallel_map(&wave, parallelism, &parse_cell);
+
+            for (path, config) in wave.into_iter().zip(configs) {
+                let config = match config? {
+                    Some(config) => config,
+                    None => {
+                        buckconfigs.insert(path, LegacyBuckConfig::empty());
+                        continue;
                     }
+                };
 
-                    let alias_path = CellRootPathBuf::new(path
+                let is_root = path.is_repo_root();
+
+                let repositories = config.get_section("repositories");
+                if let Some(repositories) = repositories {
+                    let mut seen_dot = false;
+                    for (alias, alias_path) in repositories.iter() {
+                        if alias_path.as_str() == "." {
+                            seen_dot = true;
+                        }
+
+                        let alias_path = CellRootPathBuf::new(path
                         .join_normalized(RelativePath::new(alias_path.as_str()))
                         .with_context(|| {
                             format!(
@@ -321,44 +299,63 @@ impl BuckConfigBasedCells {
                                 path
                             )
                         })?);
-                    let alias = NonEmptyCellAlias::new(alias.to_owned())?;
-                    if is_root {
-                        root_aliases.insert(alias.clone(), alias_path.clone());
+                        let alias = NonEmptyCellAlias::new(alias.to_owned())?;
+                        if is_root {
+                            root_aliases.insert(alias.clone(), alias_path.clone());
+                        }
+                        cells_aggregator.add_cell_entry(path.clone(), alias, alias_path.clone())?;
+                        work.push(alias_path);
                     }
-                    cells_aggregator.add_cell_entry(path.clone(), alias, alias_path.clone())?;
-                    work.push(alias_path);
-                }
 
-                if is_root && !seen_dot {
+                    if is_root && !seen_dot {
+                        return Err(CellsError::MissingRootCellName.into());
+                    }
+
+                    if is_root {
+                        for cell in resolve_external_cells(
+                            project_fs,
+                            &config,
+                            options.require_locked_external_cells,
+                        )? {
+                            let alias = cell.alias;
+                            let alias_path = cell.source.cell_root();
+                            root_aliases.insert(alias.clone(), alias_path.clone());
+                            cells_aggregator.add_cell_entry(
+                                path.clone(),
+                                alias,
+                                alias_path.clone(),
+                            )?;
+                            work.push(alias_path);
+                        }
+                    }
+                } else if is_root {
                     return Err(CellsError::MissingRootCellName.into());
                 }
-            } else if is_root {
-                return Err(CellsError::MissingRootCellName.into());
-            }
 
-            if let Some(aliases) = config.get_section("repository_aliases") {
-                for (alias, destination) in aliases.iter() {
-                    let alias = NonEmptyCellAlias::new(alias.to_owned())?;
-                    let destination = NonEmptyCellAlias::new(destination.as_str().to_owned())?;
-                    let alias_path = cells_aggregator.add_cell_alias(
-                        path.clone(),
-                        alias.clone(),
-                        destination,
-                    )?;
-                    if path.as_str() == "" {
-                        root_aliases.insert(alias, alias_path.clone());
+                if let Some(aliases) = config.get_section("repository_aliases") {
+                    for (alias, destination) in aliases.iter() {
+                        let alias = NonEmptyCellAlias::new(alias.to_owned())?;
+                        let destination = NonEmptyCellAlias::new(destination.as_str().to_owned())?;
+                        let alias_path = cells_aggregator.add_cell_alias(
+                            path.clone(),
+                            alias.clone(),
+                            destination,
+                        )?;
+                        if path.as_str() == "" {
+                            root_aliases.insert(alias, alias_path.clone());
+                        }
                     }
                 }
-            }
 
-            if let Some(buildfiles) = Self::parse_buildfile_name(&config)? {
-                cells_aggregator.set_buildfiles(path.clone(), buildfiles);
-            }
-            if let Some(buildfile) = config.parse::<String>("buildfile", "extra_for_test")? {
-                cells_aggregator.add_buildfile(path.clone(), FileNameBuf::try_from(buildfile)?);
-            }
+                if let Some(buildfiles) = Self::parse_buildfile_name(&config)? {
+                    cells_aggregator.set_buildfiles(path.clone(), buildfiles);
+                }
+                if let Some(buildfile) = config.parse::<String>("buildfile", "extra_for_test")? {
+                    cells_aggregator.add_buildfile(path.clone(), FileNameBuf::try_from(buildfile)?);
+                }
 
-            buckconfigs.insert(path, config);
+                buckconfigs.insert(path, config);
+            }
         }
 
         for cell_path in buckconfigs.keys() {
@@ -380,10 +377,95 @@ impl BuckConfigBasedCells {
         Ok(Self {
             configs_by_name: LegacyBuckConfigs::new(configs_by_name),
             cell_resolver,
-            config_paths: file_ops.trace,
+            config_paths: file_ops.trace.into_inner(),
         })
     }
 
+    /// The config files which may exist for a cell, in the order they are parsed.
+    fn main_config_files(
+        project_fs: &ProjectRoot,
+        path: &CellRootPathBuf,
+        skip_default_external_config: bool,
+        extra_external_config: Option<&str>,
+    ) -> anyhow::Result<Vec<MainConfigFile>> {
+        let mut buckconfig_paths: Vec<MainConfigFile> = Vec::new();
+
+        for buckconfig in DEFAULT_BUCK_CONFIG_FILES {
+            if skip_default_external_config && buckconfig.is_external() {
+                continue;
+            }
+
+            match buckconfig {
+                BuckConfigFile::ProjectRelativeFile(file) => {
+                    let buckconfig_path = ForwardRelativePath::new(file)?;
+                    buckconfig_paths.push(MainConfigFile {
+                        path: project_fs
+                            .resolve(&path.project_relative_path().join(buckconfig_path)),
+                        owned_by_project: true,
+                    });
+                }
+
+                BuckConfigFile::ProjectRelativeFolder(folder) => {
+                    let buckconfig_folder_path = ForwardRelativePath::new(folder)?;
+                    let buckconfig_folder_abs_path = project_fs
+                        .resolve(&path.project_relative_path().join(buckconfig_folder_path));
+                    push_all_files_from_a_directory(
+                        &mut buckconfig_paths,
+                        &buckconfig_folder_abs_path,
+                        true,
+                    )?;
+                }
+                BuckConfigFile::UserFile(file) => {
+                    let home_dir = dirs::home_dir();
+                    if let Some(home_dir_path) = home_dir {
+                        let buckconfig_path = ForwardRelativePath::new(file)?;
+                        buckconfig_paths.push(MainConfigFile {
+                            path: AbsNormPath::new(&home_dir_path)?
+                                .join_normalized(buckconfig_path)?,
+                            owned_by_project: false,
+                        });
+                    }
+                }
+                BuckConfigFile::UserFolder(folder) => {
+                    let home_dir = dirs::home_dir();
+                    if let Some(home_dir_path) = home_dir {
+                        let buckconfig_path = ForwardRelativePath::new(folder)?;
+                        let buckconfig_folder_abs_path =
+                            AbsNormPath::new(&home_dir_path)?.join_normalized(buckconfig_path)?;
+                        push_all_files_from_a_directory(
+                            &mut buckconfig_paths,
+                            &buckconfig_folder_abs_path,
+                            false,
+                        )?;
+                    }
+                }
+                BuckConfigFile::GlobalFile(file) => {
+                    buckconfig_paths.push(MainConfigFile {
+                        path: AbsNormPathBuf::from(String::from(*file))?,
+                        owned_by_project: false,
+                    });
+                }
+                BuckConfigFile::GlobalFolder(folder) => {
+                    let buckconfig_folder_abs_path = AbsNormPathBuf::from(String::from(*folder))?;
+                    push_all_files_from_a_directory(
+                        &mut buckconfig_paths,
+                        &buckconfig_folder_abs_path,
+                        false,
+                    )?;
+                }
+            }
+        }
+
+        if let Some(f) = extra_external_config {
+            buckconfig_paths.push(MainConfigFile {
+                path: AbsNormPathBuf::from(f.to_owned())?,
+                owned_by_project: false,
+            });
+        }
+
+        Ok(buckconfig_paths)
+    }
+
     /// Deal with the `buildfile.name` key (and `name_v2`)
     fn parse_buildfile_name(config: &LegacyBuckConfig) -> anyhow::Result<Option<Vec<FileNameBuf>>> {
         // For buck2, we support a slightly different mechanism for setting the buildfile to
@@ -409,6 +491,41 @@ impl BuckConfigBasedCells {
     }
 }
 
+/// Applies `f` to each item on at most `max_threads` threads, returning the results in order.
+fn parallel_map<T: Sync, R: Send>(
+    items: &[T],
+    max_threads: usize,
+    f: impl Fn(&T) -> R + Sync,
+) -> Vec<R> {
+    let threads = max_threads.min(items.len());
+    if threads <= 1 {
+        return items.iter().map(f).collect();
+    }
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, R)> = thread::scope(|s| {
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut results = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(i) else {
+                            break results;
+                        };
+                        results.push((i, f(item)));
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+            .collect()
+    });
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
 /// Limited view of the root config. This does not follow includes.
 pub struct ImmediateConfig {
     pub cell_resolver: CellResolver,
@@ -424,11 +541,33 @@ mod tests {
     use gazebo::prelude::*;
     use indoc::indoc;
 
+    use crate::legacy_configs::cells::parallel_map;
     use crate::legacy_configs::cells::BuckConfigBasedCells;
     use crate::legacy_configs::testing::TestConfigParserFileOps;
     use crate::legacy_configs::tests::assert_config_value;
     use crate::legacy_configs::LegacyConfigCmdArg;
 
+    #[test]
+    fn test_parallel_map_bounds_threads_and_keeps_order() {
+        use std::sync::atomic::AtomicUsize;
+        use std::sync::atomic::Ordering;
+
+        let running = AtomicUsize::new(0);
+        let max_running = AtomicUsize::new(0);
+        let items: Vec<usize> = (0..32).collect();
+        let results = parallel_map(&items, 3, |i| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            max_running.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            running.fetch_sub(1, Ordering::SeqCst);
+            i * 2
+        });
+        assert_eq!(items.iter().map(|i| i * 2).collect::<Vec<_>>(), results);
+        assert!(max_running.load(Ordering::SeqCst) <= 3);
+
+        assert_eq!(vec![2], parallel_map(&[1], 0, |i| i * 2));
+    }
+
     fn create_project_filesystem() -> ProjectRoot {
         #[cfg(not(windows))]
         let root_path = "/".to_owned();
@@ -439,7 +578,7 @@ mod tests {
 
     #[test]
     fn test_cells() -> anyhow::Result<()> {
-        let mut file_ops = TestConfigParserFileOps::new(&[
+        let file_ops = TestConfigParserFileOps::new(&[
             (
                 "/.buckconfig",
                 indoc!(
@@ -483,7 +622,7 @@ mod tests {
         let project_fs = create_project_filesystem();
         let cells = BuckConfigBasedCells::parse_with_file_ops(
             &project_fs,
-            &mut file_ops,
+            &file_ops,
             &[],
             ProjectRelativePath::empty(),
         )?;
@@ -529,7 +668,7 @@ mod tests {
 
     #[test]
     fn test_multi_cell_with_config_file() -> anyhow::Result<()> {
-        let mut file_ops = TestConfigParserFileOps::new(&[
+        let file_ops = TestConfigParserFileOps::new(&[
             (
                 "/.buckconfig",
                 indoc!(
@@ -585,7 +724,7 @@ mod tests {
         let file_arg = "C:/other/cli-conf";
         let cells = BuckConfigBasedCells::parse_with_file_ops(
             &project_fs,
-            &mut file_ops,
+            &file_ops,
             &[LegacyConfigCmdArg::file(file_arg)?],
             ProjectRelativePath::empty(),
         )?;
@@ -604,7 +743,7 @@ mod tests {
 
     #[test]
     fn test_multi_cell_no_repositories_in_non_root_cell() -> anyhow::Result<()> {
-        let mut file_ops = TestConfigParserFileOps::new(&[
+        let file_ops = TestConfigParserFileOps::new(&[
             (
                 "/.buckconfig",
                 indoc!(
@@ -629,7 +768,7 @@ mod tests {
         let project_fs = create_project_filesystem();
         let cells = BuckConfigBasedCells::parse_with_file_ops(
             &project_fs,
-            &mut file_ops,
+            &file_ops,
             &[],
             ProjectRelativePath::empty(),
         )?;
@@ -645,7 +784,7 @@ mod tests {
 
     #[test]
     fn test_multi_cell_with_cell_relative() -> anyhow::Result<()> {
-        let mut file_ops = TestConfigParserFileOps::new(&[
+        let file_ops = TestConfigParserFileOps::new(&[
             (
                 "/.buckconfig",
                 indoc!(
@@ -691,7 +830,7 @@ mod tests {
         let project_fs = create_project_filesystem();
         let cells = BuckConfigBasedCells::parse_with_file_ops(
             &project_fs,
-            &mut file_ops,
+            &file_ops,
             &[
                 LegacyConfigCmdArg::file("other//app-conf")?,
                 LegacyConfigCmdArg::file("//global-conf")?,
@@ -710,7 +849,7 @@ mod tests {
 
     #[test]
     fn test_local_config_file_overwrite_config_file() -> anyhow::Result<()> {
-        let mut file_ops = TestConfigParserFileOps::new(&[
+        let file_ops = TestConfigParserFileOps::new(&[
             (
                 "/.buckconfig",
                 indoc!(
@@ -740,7 +879,7 @@ mod tests {
         let project_fs = create_project_filesystem();
         let cells = BuckConfigBasedCells::parse_with_file_ops(
             &project_fs,
-            &mut file_ops,
+            &file_ops,
             &[],
             ProjectRelativePath::empty(),
         )?;
@@ -761,7 +900,7 @@ mod tests {
 
     #[test]
     fn test_multi_cell_local_config_file_overwrite_config_file() -> anyhow::Result<()> {
-        let mut file_ops = TestConfigParserFileOps::new(&[
+        let file_ops = TestConfigParserFileOps::new(&[
             (
                 "/.buckconfig",
                 indoc!(
@@ -817,7 +956,7 @@ mod tests {
         let project_fs = create_project_filesystem();
         let cells = BuckConfigBasedCells::parse_with_file_ops(
             &project_fs,
-            &mut file_ops,
+            &file_ops,
             &[],
             ProjectRelativePath::empty(),
         )?;
@@ -846,4 +985,49 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_config_schema_rejects_unknown_key() -> anyhow::Result<()> {
+        let file_ops = TestConfigParserFileOps::new(&[
+            (
+                "/.buckconfig",
+                indoc!(
+                    r#"
+                            [repositories]
+                                root = .
+                                other = other/
+                        "#
+                ),
+            ),
+            (
+                "/other/.buckconfig",
+                indoc!(
+                    r#"
+                            [buck2]
+                                materialzations = deferred
+                        "#
+                ),
+            ),
+            (
+                "/other/.buckconfig.schema.json",
+                r#"{"buck2": {"materializations": {"type": "string"}}}"#,
+            ),
+        ])?;
+
+        let project_fs = create_project_filesystem();
+        let err = BuckConfigBasedCells::parse_with_file_ops(
+            &project_fs,
+            &file_ops,
+            &[],
+            ProjectRelativePath::empty(),
+        )
+        .unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("did you mean `buck2.materializations`?"),
+            "{:#}",
+            err
+        );
+
+        Ok(())
+    }
 }
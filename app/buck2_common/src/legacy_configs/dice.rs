@@ -411,19 +411,19 @@ mod tests {
         let config1 = Some(LegacyBuckConfigs::new(hashmap![
             CellName::testing_new("cell1")
             => {
-                let mut file_ops = TestConfigParserFileOps::new(&[("/test", "[sec1]\na=b\n[sec2]\nx=y")])?;
+                let file_ops = TestConfigParserFileOps::new(&[("/test", "[sec1]\na=b\n[sec2]\nx=y")])?;
                 LegacyBuckConfig::parse_with_file_ops(
                     path,
-                    &mut file_ops,
+                    &file_ops,
                     &[LegacyConfigCmdArg::flag("sec1.a=c")?],
                 )?
             },
             CellName::testing_new("cell2")
             => {
-                let mut file_ops = TestConfigParserFileOps::new(&[("/test", "[sec1]\nx=y\n[sec2]\na=b")])?;
+                let file_ops = TestConfigParserFileOps::new(&[("/test", "[sec1]\nx=y\n[sec2]\na=b")])?;
                 LegacyBuckConfig::parse_with_file_ops(
                     path,
-                    &mut file_ops,
+                    &file_ops,
                     &[],
                 )?
             }
@@ -432,10 +432,10 @@ mod tests {
         let config2 = Some(LegacyBuckConfigs::new(hashmap![
             CellName::testing_new("cell1")
             => {
-                let mut file_ops = TestConfigParserFileOps::new(&[("/test", "[sec1]\na=b\n[sec2]\nx=y")])?;
+                let file_ops = TestConfigParserFileOps::new(&[("/test", "[sec1]\na=b\n[sec2]\nx=y")])?;
                 LegacyBuckConfig::parse_with_file_ops(
                     path,
-                    &mut file_ops,
+                    &file_ops,
                     &[LegacyConfigCmdArg::flag("sec1.a=c")?],
                 )?
             },
@@ -444,10 +444,10 @@ mod tests {
         let config3 = Some(LegacyBuckConfigs::new(hashmap![
             CellName::testing_new("cell1")
             => {
-                let mut file_ops = TestConfigParserFileOps::new(&[("/test", "[sec1]\na=c\n[sec2]\nx=y")])?;
+                let file_ops = TestConfigParserFileOps::new(&[("/test", "[sec1]\na=c\n[sec2]\nx=y")])?;
                 LegacyBuckConfig::parse_with_file_ops(
                     path,
-                    &mut file_ops,
+                    &file_ops,
                     &[],
                 )?
             },
@@ -456,10 +456,10 @@ mod tests {
         let config4 = Some(LegacyBuckConfigs::new(hashmap![
             CellName::testing_new("cell1")
             => {
-                let mut file_ops = TestConfigParserFileOps::new(&[("/test", "[sec1]\na=b\n[sec2]\nx=y")])?;
+                let file_ops = TestConfigParserFileOps::new(&[("/test", "[sec1]\na=b\n[sec2]\nx=y")])?;
                 LegacyBuckConfig::parse_with_file_ops(
                     path,
-                    &mut file_ops,
+                    &file_ops,
                     &[LegacyConfigCmdArg::flag("sec1.d=e")?],
                 )?
             },
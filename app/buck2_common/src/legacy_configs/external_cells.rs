@@ -0,0 +1,1341 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Cells whose content is not part of the repository, but fetched by `buck2 fetch-cells`.
+//!
+//! External cells are declared in the root `.buckconfig`:
+//!
+//! ```ini
+//! [external_cells]
+//!   rules = git
+//!
+//! [external_cell_rules]
+//!   git_origin = https://github.com/example/rules.git
+//!   rev = v1.2
+//! ```
+//!
+//! `rev` may be a branch, a tag or a commit, and defaults to `HEAD`. `buck2 fetch-cells` records
+//! the commit it resolved to in the `cells.lock` file in the project root, which is meant to be
+//! checked in: as long as the declaration does not change, the locked commit is used.
+//!
+//! A cell can also be fetched from an archive, verified against its sha256:
+//!
+//! ```ini
+//! [external_cells]
+//!   zlib = http_archive
+//!
+//! [external_cell_zlib]
+//!   url = https://example.com/zlib-1.3.tar.gz
+//!   sha256 = 0123...
+//!   strip_prefix = zlib-1.3
+//! ```
+//!
+//! Parsing the config only resolves the cells against the lockfile, it never accesses the
+//! network nor writes anything: commands fail if the lockfile does not match the declarations,
+//! until `buck2 fetch-cells` updates it. The content of each commit or archive is fetched by the daemon,
+//! once per commit or hash, into `buck-out/external_cells`.
+//!
+//! Archives are downloaded with the daemon's HTTP client, so they honour its proxy and TLS
+//! settings, and unpacked in process: they must be tar archives, optionally compressed with gzip
+//! or zstd. Git is run as a subprocess, and killed if it does not finish in time.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::future::Future;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
+use std::io::Write;
+use std::process::Stdio;
+use std::time::Duration;
+
+use allocative::Allocative;
+use anyhow::Context;
+use async_trait::async_trait;
+use buck2_core::cells::alias::NonEmptyCellAlias;
+use buck2_core::cells::cell_root_path::CellRootPathBuf;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_futures::cancellation::CancellationContext;
+use buck2_http::HttpClient;
+use buck2_util::process::async_background_command;
+use derive_more::Display;
+use dice::DiceComputations;
+use dice::Key;
+use futures::StreamExt;
+use sha2::Digest;
+use sha2::Sha256;
+
+use crate::dice::data::HasIoProvider;
+use crate::http::HasHttpClient;
+use crate::legacy_configs::LegacyBuckConfig;
+
+/// Lockfile recording the commits external cells resolved to, relative to the project root.
+pub const EXTERNAL_CELLS_LOCKFILE: &str = "cells.lock";
+
+/// Directory where the content of external cells is fetched to, relative to the project root.
+/// Shared between isolation dirs, since the content of a commit never changes.
+const EXTERNAL_CELLS_DIR: &str = "buck-out/external_cells";
+
+/// File written into fetched content before it is moved in place.
+const FETCHED_MARKER: &str = ".buck2_fetched";
+
+/// How long resolving a revision with `git ls-remote` may take.
+const GIT_LS_REMOTE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long any other git command, or the download of an archive, may take.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(600);
+
+#[derive(Debug, buck2_error::Error)]
+enum ExternalCellsError {
+    #[error("Unknown origin `{1}` for external cell `{0}`, expected `git` or `http_archive`")]
+    #[buck2(user)]
+    UnknownOrigin(String, String),
+    #[error("Missing `{1}` in section `[external_cell_{0}]` of the root buckconfig")]
+    #[buck2(user)]
+    MissingKey(String, &'static str),
+    #[error("Revision `{rev}` of external cell `{cell}` not found in `{origin}`")]
+    #[buck2(user)]
+    RevNotFound {
+        cell: String,
+        rev: String,
+        origin: String,
+    },
+    #[error("Command `{command}` failed while fetching external cell `{cell}`:\n{stderr}")]
+    CommandFailed {
+        cell: String,
+        command: String,
+        stderr: String,
+    },
+    #[error(
+        "Command `{command}` timed out after {timeout:?} while fetching external cell `{cell}`"
+    )]
+    CommandTimedOut {
+        cell: String,
+        command: String,
+        timeout: Duration,
+    },
+    #[error("Download of `{url}` for external cell `{cell}` timed out after {timeout:?}")]
+    DownloadTimedOut {
+        cell: String,
+        url: String,
+        timeout: Duration,
+    },
+    #[error(
+        "Archive for external cell `{0}` from `{1}` could not be unpacked, expected a tar archive, optionally compressed with gzip or zstd"
+    )]
+    #[buck2(user)]
+    InvalidArchive(String, String),
+    #[error("Invalid sha256 `{1}` for external cell `{0}`, expected 64 hex digits")]
+    #[buck2(user)]
+    InvalidSha256(String, String),
+    #[error(
+        "Invalid `strip_prefix` `{1}` for external cell `{0}`, expected a relative path without `..`"
+    )]
+    #[buck2(user)]
+    InvalidStripPrefix(String, String),
+    #[error(
+        "Archive for external cell `{cell}` from `{url}` has sha256 `{actual}`, expected `{expected}`"
+    )]
+    #[buck2(user)]
+    Sha256Mismatch {
+        cell: String,
+        url: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("Archive for external cell `{0}` does not contain `strip_prefix` directory `{1}`")]
+    #[buck2(user)]
+    MissingStripPrefix(String, ForwardRelativePathBuf),
+    #[error(
+        "External cell `{0}` is not locked in `cells.lock`, or its `git_origin` or `rev` changed since it was locked. Run `buck2 fetch-cells` to update the lockfile"
+    )]
+    #[buck2(user)]
+    NotLocked(String),
+    #[error(
+        "`cells.lock` locks `{0}`, which is not a git external cell. Run `buck2 fetch-cells` to update the lockfile"
+    )]
+    #[buck2(user)]
+    UnknownLockedCell(String),
+}
+
+/// What `buck2 fetch-cells` did to the lock of a git cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExternalCellLockStatus {
+    /// The lockfile already had this cell, with the same declaration.
+    Unchanged,
+    /// The revision was resolved and the commit recorded.
+    Locked,
+}
+
+/// Where an external cell is declared to come from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ExternalCellOrigin {
+    Git {
+        origin: String,
+        rev: String,
+    },
+    HttpArchive {
+        url: String,
+        sha256: String,
+        strip_prefix: Option<ForwardRelativePathBuf>,
+    },
+}
+
+impl ExternalCellOrigin {
+    fn parse(config: &LegacyBuckConfig, cell: &str, kind: &str) -> anyhow::Result<Self> {
+        let section = format!("external_cell_{}", cell);
+        match kind {
+            "git" => {
+                let origin = config
+                    .get(&section, "git_origin")
+                    .ok_or_else(|| ExternalCellsError::MissingKey(cell.to_owned(), "git_origin"))?;
+                let rev = config.get(&section, "rev").unwrap_or("HEAD");
+                Ok(ExternalCellOrigin::Git {
+                    origin: origin.to_owned(),
+                    rev: rev.to_owned(),
+                })
+            }
+            "http_archive" => {
+                let url = config
+                    .get(&section, "url")
+                    .ok_or_else(|| ExternalCellsError::MissingKey(cell.to_owned(), "url"))?;
+                let sha256 = config
+                    .get(&section, "sha256")
+                    .ok_or_else(|| ExternalCellsError::MissingKey(cell.to_owned(), "sha256"))?;
+                if sha256.len() != 64 || !sha256.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    return Err(ExternalCellsError::InvalidSha256(
+                        cell.to_owned(),
+                        sha256.to_owned(),
+                    )
+                    .into());
+                }
+                let strip_prefix = config
+                    .get(&section, "strip_prefix")
+                    .map(|prefix| {
+                        ForwardRelativePathBuf::try_from(prefix.to_owned()).map_err(|_| {
+                            ExternalCellsError::InvalidStripPrefix(
+                                cell.to_owned(),
+                                prefix.to_owned(),
+                            )
+                        })
+                    })
+                    .transpose()?;
+                Ok(ExternalCellOrigin::HttpArchive {
+                    url: url.to_owned(),
+                    sha256: sha256.to_ascii_lowercase(),
+                    strip_prefix,
+                })
+            }
+            _ => Err(ExternalCellsError::UnknownOrigin(cell.to_owned(), kind.to_owned()).into()),
+        }
+    }
+}
+
+/// The content of an external cell, pinned to a commit or an archive hash.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Allocative)]
+pub enum ExternalCellSource {
+    Git {
+        origin: String,
+        commit: String,
+    },
+    HttpArchive {
+        url: String,
+        sha256: String,
+        strip_prefix: Option<ForwardRelativePathBuf>,
+    },
+}
+
+impl ExternalCellSource {
+    /// Where the commit or the extracted archive is fetched to.
+    fn content_path(&self) -> ProjectRelativePathBuf {
+        let (kind, key) = match self {
+            ExternalCellSource::Git { commit, .. } => ("git", commit),
+            ExternalCellSource::HttpArchive { sha256, .. } => ("http_archive", sha256),
+        };
+        ProjectRelativePath::unchecked_new(EXTERNAL_CELLS_DIR)
+            .join(ForwardRelativePath::unchecked_new(kind))
+            .join(ForwardRelativePath::unchecked_new(key))
+    }
+
+    /// The root of the cell, within the fetched content.
+    pub fn cell_root(&self) -> CellRootPathBuf {
+        let content = self.content_path();
+        CellRootPathBuf::new(match self {
+            ExternalCellSource::HttpArchive {
+                strip_prefix: Some(prefix),
+                ..
+            } => content.join(prefix),
+            _ => content,
+        })
+    }
+}
+
+/// An external cell resolved against the lockfile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalCell {
+    pub alias: NonEmptyCellAlias,
+    pub source: ExternalCellSource,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+struct LockedGitCell {
+    git_origin: String,
+    rev: String,
+    commit: String,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+struct CellsLockfile {
+    git: BTreeMap<String, LockedGitCell>,
+}
+
+impl CellsLockfile {
+    fn path(project_fs: &ProjectRoot) -> AbsNormPathBuf {
+        project_fs.resolve(ProjectRelativePath::unchecked_new(EXTERNAL_CELLS_LOCKFILE))
+    }
+
+    fn load(project_fs: &ProjectRoot) -> anyhow::Result<Self> {
+        let path = Self::path(project_fs);
+        match fs_util::read_to_string_if_exists(&path)? {
+            Some(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Error parsing external cells lockfile `{}`", path)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    fn save(&self, project_fs: &ProjectRoot) -> anyhow::Result<()> {
+        let mut content = serde_json::to_string_pretty(self)?;
+        content.push('\n');
+        fs_util::write(Self::path(project_fs), content)
+    }
+
+    /// The commit locked for a git cell, if the lockfile has the cell with the same declaration.
+    fn locked_commit(&self, cell: &str, origin: &str, rev: &str) -> Option<&str> {
+        match self.git.get(cell) {
+            Some(locked) if locked.git_origin == origin && locked.rev == rev => {
+                Some(&locked.commit)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn is_commit_hash(rev: &str) -> bool {
+    rev.len() == 40 && rev.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// The external cells declared in the root config, by alias.
+fn declared_external_cells(
+    root_config: &LegacyBuckConfig,
+) -> anyhow::Result<Vec<(String, ExternalCellOrigin)>> {
+    let Some(external_cells) = root_config.get_section("external_cells") else {
+        return Ok(Vec::new());
+    };
+    external_cells
+        .iter()
+        .map(|(cell, kind)| {
+            Ok((
+                cell.to_owned(),
+                ExternalCellOrigin::parse(root_config, cell, kind.as_str())?,
+            ))
+        })
+        .collect()
+}
+
+/// Resolve the external cells declared in the root config against the lockfile.
+///
+/// This only reads the lockfile. If it does not match the declarations, this fails when
+/// `require_locked` is set, and otherwise leaves out the git cells which are not locked.
+pub fn resolve_external_cells(
+    project_fs: &ProjectRoot,
+    root_config: &LegacyBuckConfig,
+    require_locked: bool,
+) -> anyhow::Result<Vec<ExternalCell>> {
+    let declared = declared_external_cells(root_config)?;
+    let lockfile = CellsLockfile::load(project_fs)?;
+    if require_locked {
+        for cell in lockfile.git.keys() {
+            if !declared.iter().any(|(declared_cell, origin)| {
+                declared_cell == cell && matches!(origin, ExternalCellOrigin::Git { .. })
+            }) {
+                return Err(ExternalCellsError::UnknownLockedCell(cell.to_owned()).into());
+            }
+        }
+    }
+
+    let mut cells = Vec::new();
+    for (cell, origin) in declared {
+        let source = match origin {
+            ExternalCellOrigin::Git { origin, rev } => {
+                let commit = if is_commit_hash(&rev) {
+                    rev
+                } else {
+                    match lockfile.locked_commit(&cell, &origin, &rev) {
+                        Some(commit) => commit.to_owned(),
+                        None if require_locked => {
+                            return Err(ExternalCellsError::NotLocked(cell).into());
+                        }
+                        None => continue,
+                    }
+                };
+                ExternalCellSource::Git { origin, commit }
+            }
+            ExternalCellOrigin::HttpArchive {
+                url,
+                sha256,
+                strip_prefix,
+            } => ExternalCellSource::HttpArchive {
+                url,
+                sha256,
+                strip_prefix,
+            },
+        };
+        cells.push(ExternalCell {
+            alias: NonEmptyCellAlias::new(cell)?,
+            source,
+        });
+    }
+    Ok(cells)
+}
+
+/// Record in the lockfile the commit each git cell resolves to, for the cells which are not
+/// locked yet or whose declaration changed, or for all of them if `update` is set. Cells which
+/// are no longer declared are removed from the lockfile.
+///
+/// This is the only place the lockfile is written: it is used by `buck2 fetch-cells`.
+pub async fn lock_external_cells(
+    project_fs: &ProjectRoot,
+    root_config: &LegacyBuckConfig,
+    update: bool,
+) -> anyhow::Result<Vec<(String, ExternalCellLockStatus)>> {
+    let old_lockfile = CellsLockfile::load(project_fs)?;
+    let mut lockfile = CellsLockfile::default();
+    let mut statuses = Vec::new();
+    for (cell, origin) in declared_external_cells(root_config)? {
+        if let ExternalCellOrigin::Git { origin, rev } = origin {
+            let (commit, status) = match old_lockfile.locked_commit(&cell, &origin, &rev) {
+                Some(commit) if !update => (commit.to_owned(), ExternalCellLockStatus::Unchanged),
+                locked => {
+                    let commit = resolve_git_rev(&cell, &origin, &rev).await?;
+                    let status = if locked == Some(commit.as_str()) {
+                        ExternalCellLockStatus::Unchanged
+                    } else {
+                        ExternalCellLockStatus::Locked
+                    };
+                    (commit, status)
+                }
+            };
+            statuses.push((cell.clone(), status));
+            lockfile.git.insert(
+                cell,
+                LockedGitCell {
+                    git_origin: origin,
+                    rev,
+                    commit,
+                },
+            );
+        }
+    }
+    if lockfile != old_lockfile {
+        lockfile.save(project_fs)?;
+    }
+    Ok(statuses)
+}
+
+/// Run git, killing it if it does not finish within `timeout`.
+async fn git(
+    cell: &str,
+    dir: Option<&AbsNormPath>,
+    args: &[&str],
+    timeout: Duration,
+) -> anyhow::Result<String> {
+    let mut command = async_background_command("git");
+    if let Some(dir) = dir {
+        command.current_dir(dir.as_path());
+    }
+    command
+        .args(args)
+        // Fail instead of waiting for credentials nobody is going to type.
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+    let command_str = || format!("git {}", args.join(" "));
+    let output = match tokio::time::timeout(timeout, command.output()).await {
+        Ok(output) => output.context("Error running `git`")?,
+        Err(_) => {
+            return Err(ExternalCellsError::CommandTimedOut {
+                cell: cell.to_owned(),
+                command: command_str(),
+                timeout,
+            }
+            .into());
+        }
+    };
+    if !output.status.success() {
+        return Err(ExternalCellsError::CommandFailed {
+            cell: cell.to_owned(),
+            command: command_str(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }
+        .into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Resolve a branch, tag or commit to a commit hash.
+async fn resolve_git_rev(cell: &str, origin: &str, rev: &str) -> anyhow::Result<String> {
+    if is_commit_hash(rev) {
+        return Ok(rev.to_owned());
+    }
+    let out = git(
+        cell,
+        None,
+        &["ls-remote", origin, rev],
+        GIT_LS_REMOTE_TIMEOUT,
+    )
+    .await?;
+    parse_ls_remote(&out, rev).ok_or_else(|| {
+        ExternalCellsError::RevNotFound {
+            cell: cell.to_owned(),
+            rev: rev.to_owned(),
+            origin: origin.to_owned(),
+        }
+        .into()
+    })
+}
+
+/// The commit `rev` resolves to in the output of `git ls-remote <origin> <rev>`.
+///
+/// `ls-remote` matches its pattern against the end of ref names, so `v1` also lists
+/// `refs/heads/feature/v1`: only the ref named `rev` itself (e.g. `HEAD`), `refs/tags/<rev>`
+/// and `refs/heads/<rev>` are accepted, in that order, like git resolves revisions. For an
+/// annotated tag, the peeled `^{}` line is used, since it names the commit and not the tag.
+fn parse_ls_remote(output: &str, rev: &str) -> Option<String> {
+    let refs: HashMap<&str, &str> = output
+        .lines()
+        .filter_map(|line| {
+            let (commit, name) = line.split_once('\t')?;
+            is_commit_hash(commit).then_some((name.trim(), commit))
+        })
+        .collect();
+    [
+        rev.to_owned(),
+        format!("refs/tags/{}", rev),
+        format!("refs/heads/{}", rev),
+    ]
+    .iter()
+    .find_map(|name| {
+        refs.get(format!("{}^{{}}", name).as_str())
+            .or_else(|| refs.get(name.as_str()))
+    })
+    .map(|commit| (*commit).to_owned())
+}
+
+fn is_fetched(dest: &AbsNormPath) -> anyhow::Result<bool> {
+    fs_util::try_exists(dest.join(ForwardRelativePath::unchecked_new(FETCHED_MARKER)))
+}
+
+/// Populate `dest` using `fetch`, unless it was populated before.
+///
+/// `fetch` is given an empty directory unique to this call, and returns the directory (itself
+/// or a subdirectory) to rename to `dest`. So concurrent fetches of the same content, possibly
+/// from daemons of different isolation dirs, do not interfere, and an interrupted fetch never
+/// leaves a partial cell behind.
+async fn materialize<F, Fut>(
+    project_fs: &ProjectRoot,
+    dest: &ProjectRelativePath,
+    fetch: F,
+) -> anyhow::Result<()>
+where
+    F: FnOnce(AbsNormPathBuf) -> Fut,
+    Fut: Future<Output = anyhow::Result<AbsNormPathBuf>>,
+{
+    let dest = project_fs.resolve(dest);
+    if is_fetched(&dest)? {
+        return Ok(());
+    }
+
+    let tmp = project_fs.resolve(
+        &ProjectRelativePath::unchecked_new(EXTERNAL_CELLS_DIR)
+            .join(ForwardRelativePath::unchecked_new("tmp"))
+            .join(ForwardRelativePath::new(&format!(
+                "{}-{:016x}",
+                std::process::id(),
+                rand::random::<u64>()
+            ))?),
+    );
+    fs_util::create_dir_all(&tmp)?;
+    let result = async {
+        let content = fetch(tmp.clone()).await?;
+        fs_util::write(
+            content.join(ForwardRelativePath::unchecked_new(FETCHED_MARKER)),
+            "",
+        )?;
+
+        if let Some(parent) = dest.parent() {
+            fs_util::create_dir_all(parent)?;
+        }
+        // Left over by a version which did not fetch into a temporary directory.
+        if !is_fetched(&dest)? {
+            fs_util::remove_all(&dest)?;
+        }
+        if let Err(e) = fs_util::rename(&content, &dest) {
+            // Another fetch of the same content got there first.
+            if !is_fetched(&dest)? {
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+    .await;
+    fs_util::remove_all(&tmp)?;
+    result
+}
+
+async fn fetch_git_commit(
+    project_fs: &ProjectRoot,
+    cell: &str,
+    origin: &str,
+    commit: &str,
+    dest: &ProjectRelativePath,
+) -> anyhow::Result<()> {
+    materialize(project_fs, dest, |tmp| async move {
+        git(cell, Some(&tmp), &["init", "--quiet"], FETCH_TIMEOUT).await?;
+        git(
+            cell,
+            Some(&tmp),
+            &["fetch", "--quiet", "--depth", "1", origin, commit],
+            FETCH_TIMEOUT,
+        )
+        .await?;
+        git(
+            cell,
+            Some(&tmp),
+            &["checkout", "--quiet", "FETCH_HEAD"],
+            FETCH_TIMEOUT,
+        )
+        .await?;
+        fs_util::remove_all(tmp.join(ForwardRelativePath::unchecked_new(".git")))?;
+        Ok(tmp)
+    })
+    .await
+}
+
+/// Download `url` to `path`, returning the sha256 of its content.
+async fn download(client: &HttpClient, url: &str, path: &AbsNormPath) -> anyhow::Result<String> {
+    let response = client.get(url).await?;
+    let mut body = response.into_body();
+    let mut file = std::io::BufWriter::new(fs_util::create_file(path)?);
+    let mut hasher = Sha256::new();
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk.with_context(|| format!("Error downloading `{}`", url))?;
+        hasher.update(&chunk);
+        file.write_all(&chunk)?;
+    }
+    file.flush()?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Unpack a tar archive, compressed with gzip or zstd or not at all, into `dest`.
+///
+/// Entries which would be written outside of `dest` are skipped.
+fn unpack_archive(archive: &AbsNormPath, dest: &AbsNormPath) -> anyhow::Result<()> {
+    let mut file = BufReader::new(fs_util::open_file(archive)?);
+    let magic = file.fill_buf()?;
+    let reader: Box<dyn Read> = if magic.starts_with(&[0x1f, 0x8b]) {
+        Box::new(flate2::bufread::GzDecoder::new(file))
+    } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        Box::new(zstd::stream::read::Decoder::with_buffer(file)?)
+    } else {
+        Box::new(file)
+    };
+    tar::Archive::new(reader).unpack(dest.as_path())?;
+    Ok(())
+}
+
+async fn fetch_http_archive(
+    project_fs: &ProjectRoot,
+    client: &HttpClient,
+    cell: &str,
+    url: &str,
+    sha256: &str,
+    dest: &ProjectRelativePath,
+) -> anyhow::Result<()> {
+    materialize(project_fs, dest, |tmp| async move {
+        let archive = tmp.join(ForwardRelativePath::unchecked_new("archive"));
+        let actual =
+            match tokio::time::timeout(FETCH_TIMEOUT, download(client, url, &archive)).await {
+                Ok(actual) => actual?,
+                Err(_) => {
+                    return Err(ExternalCellsError::DownloadTimedOut {
+                        cell: cell.to_owned(),
+                        url: url.to_owned(),
+                        timeout: FETCH_TIMEOUT,
+                    }
+                    .into());
+                }
+            };
+        if actual != sha256 {
+            return Err(ExternalCellsError::Sha256Mismatch {
+                cell: cell.to_owned(),
+                url: url.to_owned(),
+                expected: sha256.to_owned(),
+                actual,
+            }
+            .into());
+        }
+
+        let content = tmp.join(ForwardRelativePath::unchecked_new("content"));
+        fs_util::create_dir_all(&content)?;
+        {
+            let archive = archive.clone();
+            let content = content.clone();
+            tokio::task::spawn_blocking(move || unpack_archive(&archive, &content)).await?
+        }
+        .map_err(|e| {
+            e.context(ExternalCellsError::InvalidArchive(
+                cell.to_owned(),
+                url.to_owned(),
+            ))
+        })?;
+        fs_util::remove_file(&archive)?;
+        Ok(content)
+    })
+    .await
+}
+
+/// Fetch the content of an external cell into `buck-out/external_cells`, unless it was
+/// fetched before. Accesses the network.
+async fn fetch_external_cell(
+    project_fs: &ProjectRoot,
+    client: &HttpClient,
+    cell: &str,
+    source: &ExternalCellSource,
+) -> anyhow::Result<()> {
+    let dest = source.content_path();
+    match source {
+        ExternalCellSource::Git { origin, commit } => {
+            fetch_git_commit(project_fs, cell, origin, commit, &dest).await
+        }
+        ExternalCellSource::HttpArchive {
+            url,
+            sha256,
+            strip_prefix,
+        } => {
+            fetch_http_archive(project_fs, client, cell, url, sha256, &dest).await?;
+            if let Some(prefix) = strip_prefix {
+                let root = project_fs.resolve(source.cell_root().project_relative_path());
+                if !fs_util::try_exists(&root)? {
+                    return Err(ExternalCellsError::MissingStripPrefix(
+                        cell.to_owned(),
+                        prefix.clone(),
+                    )
+                    .into());
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Whether a cell is an external cell, i.e. its root is where external cells are fetched to.
+pub fn is_external_cell_path(path: &ProjectRelativePath) -> bool {
+    path.starts_with(ProjectRelativePath::unchecked_new(EXTERNAL_CELLS_DIR))
+}
+
+#[derive(Clone, Display, Debug, Eq, Hash, PartialEq, Allocative)]
+#[display(fmt = "ExternalCellKey({}, {:?})", alias, source)]
+struct ExternalCellKey {
+    alias: NonEmptyCellAlias,
+    source: ExternalCellSource,
+}
+
+#[async_trait]
+impl Key for ExternalCellKey {
+    type Value = buck2_error::Result<()>;
+
+    async fn compute(
+        &self,
+        ctx: &mut DiceComputations,
+        _cancellations: &CancellationContext,
+    ) -> buck2_error::Result<()> {
+        let io = ctx.global_data().get_io_provider();
+        let client = ctx.per_transaction_data().get_http_client();
+        fetch_external_cell(
+            io.project_root(),
+            &client,
+            self.alias.as_str(),
+            &self.source,
+        )
+        .await?;
+        Ok(())
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x.is_ok() && y.is_ok()
+    }
+
+    fn validity(x: &Self::Value) -> bool {
+        // A failed fetch, e.g. because of the network, is retried by the next command.
+        x.is_ok()
+    }
+}
+
+/// Fetch the content of external cells which are not available locally yet, concurrently.
+pub async fn materialize_external_cells(
+    ctx: &DiceComputations,
+    cells: &[ExternalCell],
+) -> anyhow::Result<()> {
+    let keys: Vec<ExternalCellKey> = cells
+        .iter()
+        .map(|cell| ExternalCellKey {
+            alias: cell.alias.clone(),
+            source: cell.source.clone(),
+        })
+        .collect();
+    for result in futures::future::join_all(keys.iter().map(|key| ctx.compute(key))).await {
+        result??;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use buck2_core::fs::fs_util;
+    use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+    use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+    use buck2_core::fs::project::ProjectRootTemp;
+    use buck2_core::fs::project_rel_path::ProjectRelativePath;
+    use buck2_http::HttpClient;
+    use buck2_http::HttpClientBuilder;
+    use httptest::matchers::request;
+    use httptest::responders;
+    use httptest::Expectation;
+    use indoc::formatdoc;
+    use indoc::indoc;
+    use sha2::Digest;
+    use sha2::Sha256;
+
+    use crate::legacy_configs::external_cells::fetch_external_cell;
+    use crate::legacy_configs::external_cells::is_commit_hash;
+    use crate::legacy_configs::external_cells::is_external_cell_path;
+    use crate::legacy_configs::external_cells::lock_external_cells;
+    use crate::legacy_configs::external_cells::parse_ls_remote;
+    use crate::legacy_configs::external_cells::resolve_external_cells;
+    use crate::legacy_configs::external_cells::unpack_archive;
+    use crate::legacy_configs::external_cells::CellsLockfile;
+    use crate::legacy_configs::external_cells::ExternalCellLockStatus;
+    use crate::legacy_configs::external_cells::ExternalCellOrigin;
+    use crate::legacy_configs::external_cells::ExternalCellSource;
+    use crate::legacy_configs::external_cells::EXTERNAL_CELLS_DIR;
+    use crate::legacy_configs::testing::parse;
+
+    fn run(dir: &AbsNormPath, program: &str, args: &[&str]) -> anyhow::Result<String> {
+        let output = Command::new(program)
+            .args(args)
+            .current_dir(dir.as_path())
+            .output()?;
+        assert!(output.status.success(), "{} {:?} failed", program, args);
+        Ok(String::from_utf8(output.stdout)?)
+    }
+
+    /// Commit `content` as `BUCK` to a git repository, returning its path and the commit.
+    fn git_commit(root: &AbsNormPath, content: &str) -> anyhow::Result<(String, String)> {
+        let repo = root.join_normalized("origin")?;
+        fs_util::create_dir_all(&repo)?;
+        fs_util::write(repo.join_normalized("BUCK")?, content)?;
+        run(&repo, "git", &["init", "--quiet"])?;
+        run(&repo, "git", &["add", "BUCK"])?;
+        run(
+            &repo,
+            "git",
+            &[
+                "-c",
+                "user.name=test",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "--quiet",
+                "-m",
+                "init",
+            ],
+        )?;
+        let commit = run(&repo, "git", &["rev-parse", "HEAD"])?.trim().to_owned();
+        Ok((repo.as_path().to_string_lossy().into_owned(), commit))
+    }
+
+    /// A tar archive containing `a-1.0/BUCK`, compressed with `compression`.
+    fn archive(compression: &str) -> anyhow::Result<Vec<u8>> {
+        let content = b"# rules";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        let mut builder = tar::Builder::new(Vec::new());
+        builder.append_data(&mut header, "a-1.0/BUCK", &content[..])?;
+        let tar = builder.into_inner()?;
+        Ok(match compression {
+            "gzip" => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+                std::io::Write::write_all(&mut encoder, &tar)?;
+                encoder.finish()?
+            }
+            "zstd" => zstd::bulk::compress(&tar, 0)?,
+            _ => tar,
+        })
+    }
+
+    fn client() -> anyhow::Result<HttpClient> {
+        Ok(HttpClientBuilder::https_with_system_roots()?.build())
+    }
+
+    #[test]
+    fn test_parse_git_origin() -> anyhow::Result<()> {
+        let config = parse(
+            &[(
+                "/config",
+                indoc!(
+                    r#"
+                    [external_cell_a]
+                        git_origin = https://example.com/a.git
+                        rev = v1
+                    [external_cell_b]
+                        git_origin = https://example.com/b.git
+                    "#
+                ),
+            )],
+            "/config",
+        )?;
+        assert_eq!(
+            ExternalCellOrigin::Git {
+                origin: "https://example.com/a.git".to_owned(),
+                rev: "v1".to_owned(),
+            },
+            ExternalCellOrigin::parse(&config, "a", "git")?
+        );
+        assert_eq!(
+            ExternalCellOrigin::Git {
+                origin: "https://example.com/b.git".to_owned(),
+                rev: "HEAD".to_owned(),
+            },
+            ExternalCellOrigin::parse(&config, "b", "git")?
+        );
+        assert!(ExternalCellOrigin::parse(&config, "c", "git").is_err());
+        assert!(ExternalCellOrigin::parse(&config, "a", "svn").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lockfile_format() -> anyhow::Result<()> {
+        let lockfile: CellsLockfile = serde_json::from_str(indoc!(
+            r#"
+            {
+              "git": {
+                "a": {
+                  "git_origin": "https://example.com/a.git",
+                  "rev": "v1",
+                  "commit": "0123456789abcdef0123456789abcdef01234567"
+                }
+              }
+            }
+            "#
+        ))?;
+        let commit = lockfile
+            .locked_commit("a", "https://example.com/a.git", "v1")
+            .unwrap();
+        assert!(is_commit_hash(commit));
+        assert_eq!(
+            None,
+            lockfile.locked_commit("a", "https://example.com/a.git", "v2")
+        );
+        assert_eq!(
+            "buck-out/external_cells/git/0123456789abcdef0123456789abcdef01234567",
+            ExternalCellSource::Git {
+                origin: "https://example.com/a.git".to_owned(),
+                commit: commit.to_owned(),
+            }
+            .cell_root()
+            .as_str()
+        );
+        assert!(!is_commit_hash("main"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_ls_remote() {
+        let branch = "1111111111111111111111111111111111111111";
+        let other = "2222222222222222222222222222222222222222";
+        let tag = "3333333333333333333333333333333333333333";
+        let tagged = "4444444444444444444444444444444444444444";
+        let output = format!(
+            "{other}\trefs/heads/feature/v1\n\
+             {branch}\trefs/heads/v1\n\
+             {other}\trefs/heads/main\n\
+             {tag}\trefs/tags/v2\n\
+             {tagged}\trefs/tags/v2^{{}}\n\
+             {other}\trefs/tags/old/v2\n\
+             {branch}\tHEAD\n"
+        );
+        assert_eq!(Some(branch), parse_ls_remote(&output, "v1").as_deref());
+        // Annotated tags resolve to the commit, not the tag object.
+        assert_eq!(Some(tagged), parse_ls_remote(&output, "v2").as_deref());
+        assert_eq!(
+            Some(tagged),
+            parse_ls_remote(&output, "refs/tags/v2").as_deref()
+        );
+        assert_eq!(Some(branch), parse_ls_remote(&output, "HEAD").as_deref());
+        // Only a suffix of `refs/heads/feature/v1` and `refs/tags/old/v2`.
+        assert_eq!(None, parse_ls_remote(&output, "feature").as_deref());
+        assert_eq!(None, parse_ls_remote(&output, "old").as_deref());
+        assert_eq!(None, parse_ls_remote(&output, "v3").as_deref());
+        assert_eq!(None, parse_ls_remote("", "v1").as_deref());
+
+        // Tags win over branches of the same name, like in `git rev-parse`.
+        let output = format!("{branch}\trefs/heads/v1\n{tag}\trefs/tags/v1\n");
+        assert_eq!(Some(tag), parse_ls_remote(&output, "v1").as_deref());
+    }
+
+    #[test]
+    fn test_parse_http_archive_origin() -> anyhow::Result<()> {
+        let config = parse(
+            &[(
+                "/config",
+                indoc!(
+                    r#"
+                    [external_cell_a]
+                        url = https://example.com/a.tar.gz
+                        sha256 = 0123456789ABCDEF0123456789abcdef0123456789abcdef0123456789abcdef
+                        strip_prefix = a-1.0
+                    [external_cell_b]
+                        url = https://example.com/b.tar.gz
+                        sha256 = 0123
+                    [external_cell_c]
+                        url = https://example.com/c.tar.gz
+                        sha256 = 0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef
+                        strip_prefix = ../c
+                    [external_cell_d]
+                        url = https://example.com/d.tar.gz
+                        sha256 = 0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef
+                        strip_prefix = /d
+                    "#
+                ),
+            )],
+            "/config",
+        )?;
+        let a = ExternalCellOrigin::parse(&config, "a", "http_archive")?;
+        assert_eq!(
+            ExternalCellOrigin::HttpArchive {
+                url: "https://example.com/a.tar.gz".to_owned(),
+                sha256: "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef"
+                    .to_owned(),
+                strip_prefix: Some(ForwardRelativePathBuf::unchecked_new("a-1.0".to_owned())),
+            },
+            a
+        );
+        assert!(ExternalCellOrigin::parse(&config, "b", "http_archive").is_err());
+        assert!(ExternalCellOrigin::parse(&config, "c", "http_archive").is_err());
+        assert!(ExternalCellOrigin::parse(&config, "d", "http_archive").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_external_cells() -> anyhow::Result<()> {
+        let fs = ProjectRootTemp::new()?;
+        let sha256 = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+        let commit = "0123456789abcdef0123456789abcdef01234567";
+        let config = parse(
+            &[(
+                "/config",
+                &formatdoc!(
+                    r#"
+                    [external_cells]
+                        locked = git
+                        pinned = git
+                        unlocked = git
+                        archive = http_archive
+                    [external_cell_locked]
+                        git_origin = https://example.com/locked.git
+                        rev = v1
+                    [external_cell_pinned]
+                        git_origin = https://example.com/pinned.git
+                        rev = {commit}
+                    [external_cell_unlocked]
+                        git_origin = https://example.com/unlocked.git
+                    [external_cell_archive]
+                        url = https://example.com/archive.tar.gz
+                        sha256 = {sha256}
+                        strip_prefix = archive-1.0
+                    "#
+                ),
+            )],
+            "/config",
+        )?;
+        fs.write_file(
+            "cells.lock",
+            &formatdoc!(
+                r#"
+                {{
+                  "git": {{
+                    "locked": {{
+                      "git_origin": "https://example.com/locked.git",
+                      "rev": "v1",
+                      "commit": "{commit}"
+                    }}
+                  }}
+                }}
+                "#
+            ),
+        );
+
+        // `unlocked` is not locked.
+        assert!(resolve_external_cells(fs.path(), &config, true).is_err());
+
+        let roots: Vec<(String, String)> = resolve_external_cells(fs.path(), &config, false)?
+            .into_iter()
+            .map(|cell| {
+                (
+                    cell.alias.as_str().to_owned(),
+                    cell.source.cell_root().as_str().to_owned(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    "archive".to_owned(),
+                    format!("{EXTERNAL_CELLS_DIR}/http_archive/{sha256}/archive-1.0")
+                ),
+                (
+                    "locked".to_owned(),
+                    format!("{EXTERNAL_CELLS_DIR}/git/{commit}")
+                ),
+                (
+                    "pinned".to_owned(),
+                    format!("{EXTERNAL_CELLS_DIR}/git/{commit}")
+                ),
+            ],
+            roots
+        );
+        for (_, root) in &roots {
+            assert!(is_external_cell_path(ProjectRelativePath::new(root)?));
+        }
+        // Resolving never fetches anything.
+        assert!(!fs_util::try_exists(fs.path().resolve(
+            ProjectRelativePath::unchecked_new(EXTERNAL_CELLS_DIR)
+        ))?);
+
+        let config = parse(
+            &[(
+                "/config",
+                indoc!(
+                    r#"
+                    [external_cells]
+                        locked = git
+                    [external_cell_locked]
+                        git_origin = https://example.com/locked.git
+                        rev = v1
+                    "#
+                ),
+            )],
+            "/config",
+        )?;
+        assert_eq!(1, resolve_external_cells(fs.path(), &config, true)?.len());
+        let config = parse(&[("/config", "")], "/config")?;
+        // The lockfile has a cell which is not declared.
+        assert!(resolve_external_cells(fs.path(), &config, true).is_err());
+        assert_eq!(0, resolve_external_cells(fs.path(), &config, false)?.len());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_lock_and_fetch_git() -> anyhow::Result<()> {
+        let fs = ProjectRootTemp::new()?;
+        let tempdir = tempfile::tempdir()?;
+        let (origin, commit) = git_commit(AbsNormPath::new(tempdir.path())?, "# rules")?;
+        let config = parse(
+            &[(
+                "/config",
+                &formatdoc!(
+                    r#"
+                    [external_cells]
+                        rules = git
+                    [external_cell_rules]
+                        git_origin = {origin}
+                    "#
+                ),
+            )],
+            "/config",
+        )?;
+
+        assert!(resolve_external_cells(fs.path(), &config, true).is_err());
+        assert_eq!(
+            Vec::new(),
+            resolve_external_cells(fs.path(), &config, false)?
+        );
+        assert_eq!(
+            vec![("rules".to_owned(), ExternalCellLockStatus::Locked)],
+            lock_external_cells(fs.path(), &config, false).await?
+        );
+        assert_eq!(
+            Some(commit.as_str()),
+            CellsLockfile::load(fs.path())?.locked_commit("rules", &origin, "HEAD")
+        );
+        assert_eq!(
+            vec![("rules".to_owned(), ExternalCellLockStatus::Unchanged)],
+            lock_external_cells(fs.path(), &config, true).await?
+        );
+
+        let cells = resolve_external_cells(fs.path(), &config, true)?;
+        assert_eq!(1, cells.len());
+        fetch_external_cell(fs.path(), &client()?, "rules", &cells[0].source).await?;
+        let root = fs
+            .path()
+            .resolve(cells[0].source.cell_root().project_relative_path());
+        assert_eq!(
+            "# rules",
+            fs_util::read_to_string(root.join_normalized("BUCK")?)?
+        );
+        assert!(!fs_util::try_exists(root.join_normalized(".git")?)?);
+
+        // A new commit is only locked with `update`.
+        let (_, new_commit) = git_commit(AbsNormPath::new(tempdir.path())?, "# rules v2")?;
+        assert_ne!(commit, new_commit);
+        lock_external_cells(fs.path(), &config, false).await?;
+        assert_eq!(
+            Some(commit.as_str()),
+            CellsLockfile::load(fs.path())?.locked_commit("rules", &origin, "HEAD")
+        );
+        assert_eq!(
+            vec![("rules".to_owned(), ExternalCellLockStatus::Locked)],
+            lock_external_cells(fs.path(), &config, true).await?
+        );
+        assert_eq!(
+            Some(new_commit.as_str()),
+            CellsLockfile::load(fs.path())?.locked_commit("rules", &origin, "HEAD")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_http_archive() -> anyhow::Result<()> {
+        let fs = ProjectRootTemp::new()?;
+        let client = client()?;
+        let server = httptest::Server::run();
+        let content = archive("gzip")?;
+        let sha256 = hex::encode(Sha256::digest(&content));
+        // Fetched content is reused, without downloading it again.
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/a.tar.gz"))
+                .times(1)
+                .respond_with(responders::status_code(200).body(content)),
+        );
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/c.tar.gz"))
+                .respond_with(responders::status_code(200).body(archive("gzip")?)),
+        );
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/missing.tar.gz"))
+                .respond_with(responders::status_code(404)),
+        );
+        let url = server.url_str("/a.tar.gz");
+
+        let source = ExternalCellSource::HttpArchive {
+            url: url.clone(),
+            sha256: sha256.clone(),
+            strip_prefix: Some(ForwardRelativePathBuf::unchecked_new("a-1.0".to_owned())),
+        };
+        fetch_external_cell(fs.path(), &client, "a", &source).await?;
+        let root = fs
+            .path()
+            .resolve(source.cell_root().project_relative_path());
+        assert_eq!(
+            "# rules",
+            fs_util::read_to_string(root.join_normalized("BUCK")?)?
+        );
+        // Nothing is left in the temporary directory.
+        let tmp = fs
+            .path()
+            .resolve(ProjectRelativePath::unchecked_new(EXTERNAL_CELLS_DIR))
+            .join_normalized("tmp")?;
+        assert_eq!(0, fs_util::read_dir(&tmp)?.count());
+        fetch_external_cell(fs.path(), &client, "a", &source).await?;
+
+        // Same archive, missing prefix.
+        let missing = ExternalCellSource::HttpArchive {
+            url: url.clone(),
+            sha256: sha256.clone(),
+            strip_prefix: Some(ForwardRelativePathBuf::unchecked_new("b-1.0".to_owned())),
+        };
+        assert!(
+            fetch_external_cell(fs.path(), &client, "b", &missing)
+                .await
+                .is_err()
+        );
+
+        // Wrong hash.
+        let wrong = ExternalCellSource::HttpArchive {
+            url: server.url_str("/c.tar.gz"),
+            sha256: "0".repeat(64),
+            strip_prefix: None,
+        };
+        assert!(
+            fetch_external_cell(fs.path(), &client, "c", &wrong)
+                .await
+                .is_err()
+        );
+
+        // Not found.
+        let not_found = ExternalCellSource::HttpArchive {
+            url: server.url_str("/missing.tar.gz"),
+            sha256: "1".repeat(64),
+            strip_prefix: None,
+        };
+        assert!(
+            fetch_external_cell(fs.path(), &client, "d", &not_found)
+                .await
+                .is_err()
+        );
+        assert_eq!(0, fs_util::read_dir(&tmp)?.count());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unpack_archive() -> anyhow::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let root = AbsNormPath::new(tempdir.path())?;
+        for compression in ["none", "gzip", "zstd"] {
+            let path = root.join_normalized(&format!("{compression}.archive"))?;
+            fs_util::write(&path, archive(compression)?)?;
+            let dest = root.join_normalized(compression)?;
+            fs_util::create_dir_all(&dest)?;
+            unpack_archive(&path, &dest)?;
+            assert_eq!(
+                "# rules",
+                fs_util::read_to_string(dest.join_normalized("a-1.0/BUCK")?)?
+            );
+        }
+
+        let path = root.join_normalized("invalid.archive")?;
+        fs_util::write(&path, "not an archive")?;
+        let dest = root.join_normalized("invalid")?;
+        fs_util::create_dir_all(&dest)?;
+        assert!(unpack_archive(&path, &dest).is_err());
+        Ok(())
+    }
+}
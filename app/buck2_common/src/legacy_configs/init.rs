@@ -11,11 +11,33 @@ use std::time::Duration;
 
 use allocative::Allocative;
 use anyhow::Context;
+use buck2_http::ProxyConfig;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::compression::CompressionConfig;
 use crate::legacy_configs::LegacyBuckConfig;
 
+#[derive(Debug, buck2_error::Error)]
+enum DaemonStartupConfigError {
+    #[buck2(user)]
+    #[error(
+        "Invalid `buck2.endpoint_permissions` value `{0}`, expected an octal file mode such as `600`"
+    )]
+    InvalidEndpointPermissions(String),
+}
+
+/// By default only the user running the daemon can read its endpoint and auth token, and
+/// therefore connect to it.
+pub const DEFAULT_ENDPOINT_PERMISSIONS: u32 = 0o600;
+
+/// Whether a mode of the file holding the daemon endpoint and auth token lets users other than the
+/// owner read it. Those users are then allowed to connect to the daemon: the client accepts a
+/// daemon owned by another user, and the daemon accepts their connections.
+pub fn is_endpoint_shared(permissions: u32) -> bool {
+    permissions & 0o044 != 0
+}
+
 /// Helper enum to categorize the kind of timeout we get from the startup config.
 #[derive(Clone, Debug)]
 pub enum Timeout {
@@ -52,6 +74,11 @@ pub struct HttpConfig {
     read_timeout_ms: Option<u64>,
     write_timeout_ms: Option<u64>,
     pub max_redirects: Option<usize>,
+    https_proxy: Option<String>,
+    http_proxy: Option<String>,
+    no_proxy: Option<String>,
+    /// PEM-encoded CA certificates to trust in addition to the system roots.
+    pub ca_bundle: Option<String>,
 }
 
 impl HttpConfig {
@@ -60,15 +87,33 @@ impl HttpConfig {
         let read_timeout_ms = config.parse("http", "read_timeout_ms")?;
         let write_timeout_ms = config.parse("http", "write_timeout_ms")?;
         let max_redirects = config.parse("http", "max_redirects")?;
+        let https_proxy = config.get("http", "https_proxy").map(ToOwned::to_owned);
+        let http_proxy = config.get("http", "http_proxy").map(ToOwned::to_owned);
+        let no_proxy = config.get("http", "no_proxy").map(ToOwned::to_owned);
+        let ca_bundle = config.get("http", "ca_bundle").map(ToOwned::to_owned);
 
         Ok(Self {
             connect_timeout_ms,
             read_timeout_ms,
             write_timeout_ms,
             max_redirects,
+            https_proxy,
+            http_proxy,
+            no_proxy,
+            ca_bundle,
         })
     }
 
+    /// Proxy settings from buckconfig. Unset values fall back to the environment when the
+    /// client is built.
+    pub fn proxy_config(&self) -> ProxyConfig {
+        ProxyConfig {
+            https_proxy: self.https_proxy.clone(),
+            http_proxy: self.http_proxy.clone(),
+            no_proxy: self.no_proxy.clone(),
+        }
+    }
+
     pub fn connect_timeout(&self) -> Timeout {
         match self.connect_timeout_ms.map(Duration::from_millis) {
             Some(Duration::ZERO) => Timeout::NoTimeout,
@@ -94,6 +139,45 @@ impl HttpConfig {
     }
 }
 
+/// Paranoid mode settings, from the `[paranoid]` section. The values are parsed by the daemon.
+#[derive(
+    Allocative,
+    Clone,
+    Debug,
+    Default,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq
+)]
+pub struct ParanoidConfig {
+    /// Enable paranoid mode, in addition to `buck2 debug paranoid enable` and `BUCK_PARANOID`.
+    pub enabled: bool,
+    /// Probability that an action's remote outputs are verified, between 0 and 1.
+    pub verify_probability: Option<String>,
+    /// Per-category overrides of `verify_probability`, as `category=probability` pairs.
+    pub category_verify_probability: Option<String>,
+    /// Whether to re-hash outputs downloaded from RE and compare them with the reported digests.
+    pub verify_digests: bool,
+}
+
+impl ParanoidConfig {
+    pub fn from_config(config: &LegacyBuckConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            enabled: config.parse("paranoid", "enabled")?.unwrap_or_default(),
+            verify_probability: config
+                .get("paranoid", "verify_probability")
+                .map(ToOwned::to_owned),
+            category_verify_probability: config
+                .get("paranoid", "category_verify_probability")
+                .map(ToOwned::to_owned),
+            verify_digests: config
+                .parse("paranoid", "verify_digests")?
+                .unwrap_or_default(),
+        })
+    }
+}
+
 /// Configurations that are used at startup by the daemon. Those are actually read by the client,
 /// and passed on to the daemon.
 ///
@@ -112,8 +196,16 @@ pub struct DaemonStartupConfig {
     pub allow_vpnless: bool,
     pub allow_vpnless_for_logging: bool,
     pub paranoid: bool,
+    pub paranoid_config: ParanoidConfig,
     pub materializations: Option<String>,
     pub http: HttpConfig,
+    /// Octal file mode for the file holding the daemon endpoint and auth token (e.g. `600`), which
+    /// decides which users can connect to the daemon.
+    pub endpoint_permissions: Option<String>,
+    /// Compression of event logs, e.g. `zstd:3`. Interpreted by the client, which writes them.
+    pub event_log_compression: Option<String>,
+    /// Whether the client records the source control revision in the invocation record.
+    pub record_vcs_revision: bool,
 }
 
 impl DaemonStartupConfig {
@@ -135,13 +227,44 @@ impl DaemonStartupConfig {
             allow_vpnless,
             allow_vpnless_for_logging,
             paranoid: false, // Setup later in ImmediateConfig
+            paranoid_config: ParanoidConfig::from_config(config)?,
             materializations: config
                 .get("buck2", "materializations")
                 .map(ToOwned::to_owned),
             http: HttpConfig::from_config(config)?,
+            endpoint_permissions: config
+                .get("buck2", "endpoint_permissions")
+                .map(ToOwned::to_owned),
+            event_log_compression: config
+                .get("buck2", "event_log_compression")
+                .map(ToOwned::to_owned),
+            record_vcs_revision: config
+                .parse("buck2", "record_vcs_revision")?
+                .unwrap_or_default(),
         })
     }
 
+    /// The mode of the file holding the daemon endpoint and auth token.
+    pub fn endpoint_permissions(&self) -> anyhow::Result<u32> {
+        match &self.endpoint_permissions {
+            None => Ok(DEFAULT_ENDPOINT_PERMISSIONS),
+            Some(mode) => match u32::from_str_radix(mode, 8) {
+                Ok(mode) if mode <= 0o777 => Ok(mode),
+                _ => Err(DaemonStartupConfigError::InvalidEndpointPermissions(mode.clone()).into()),
+            },
+        }
+    }
+
+    /// Compression of event logs, zstd with its default level if not configured.
+    pub fn event_log_compression(&self) -> anyhow::Result<CompressionConfig> {
+        match &self.event_log_compression {
+            Some(compression) => compression
+                .parse()
+                .context("Invalid `buck2.event_log_compression`"),
+            None => Ok(CompressionConfig::ZSTD),
+        }
+    }
+
     pub fn serialize(&self) -> anyhow::Result<String> {
         serde_json::to_string(&self).context("Error serializing DaemonStartupConfig")
     }
@@ -158,8 +281,62 @@ impl DaemonStartupConfig {
             allow_vpnless: false,
             allow_vpnless_for_logging: false,
             paranoid: false,
+            paranoid_config: ParanoidConfig::default(),
             materializations: None,
             http: HttpConfig::default(),
+            endpoint_permissions: None,
+            event_log_compression: None,
+            record_vcs_revision: false,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::legacy_configs;
+    use crate::legacy_configs::init::is_endpoint_shared;
+    use crate::legacy_configs::init::DaemonStartupConfig;
+
+    #[test]
+    fn test_record_vcs_revision() -> anyhow::Result<()> {
+        let empty = legacy_configs::testing::parse(&[("/config", "")], "/config")?;
+        assert!(!DaemonStartupConfig::new(&empty)?.record_vcs_revision);
+
+        let config = legacy_configs::testing::parse(
+            &[(
+                "/config",
+                indoc!(
+                    r#"
+            [buck2]
+              record_vcs_revision = true
+        "#
+                ),
+            )],
+            "/config",
+        )?;
+        assert!(DaemonStartupConfig::new(&config)?.record_vcs_revision);
+        Ok(())
+    }
+
+    #[test]
+    fn test_endpoint_permissions() -> anyhow::Result<()> {
+        let permissions = |value: Option<&str>| {
+            let mut config = DaemonStartupConfig::testing_empty();
+            config.endpoint_permissions = value.map(ToOwned::to_owned);
+            config.endpoint_permissions()
+        };
+        assert_eq!(0o600, permissions(None)?);
+        assert_eq!(0o640, permissions(Some("640"))?);
+        assert_eq!(0o644, permissions(Some("0644"))?);
+        assert!(permissions(Some("1000")).is_err());
+        assert!(permissions(Some("rw-r--r--")).is_err());
+
+        assert!(!is_endpoint_shared(0o600));
+        assert!(!is_endpoint_shared(0o700));
+        assert!(is_endpoint_shared(0o640));
+        assert!(is_endpoint_shared(0o604));
+        Ok(())
+    }
+}
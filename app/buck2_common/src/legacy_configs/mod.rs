@@ -12,8 +12,12 @@
 
 pub mod cells;
 pub mod dice;
+pub mod external_cells;
 pub mod init;
+mod parse_cache;
 pub(crate) mod path;
+pub(crate) mod schema;
+pub mod toolchain_downloads;
 pub mod view;
 
 use std::cell::OnceCell;
@@ -200,6 +204,16 @@ impl SectionBuilder {
 #[derive(Debug, Allocative)]
 struct ConfigData {
     values: SortedMap<String, LegacyBuckConfigSection>,
+    /// Config files read, in the order they were read.
+    files: Vec<LegacyBuckConfigFile>,
+}
+
+/// A config file read to produce a config.
+#[derive(Debug, Clone, Allocative)]
+pub struct LegacyBuckConfigFile {
+    pub path: String,
+    /// Zero for the main config files, otherwise the number of includes leading to this file.
+    pub include_depth: usize,
 }
 
 #[derive(Clone, Debug, Allocative)]
@@ -216,6 +230,8 @@ enum ResolvedValue {
 enum Location {
     File(ConfigFileLocation),
     CommandLineArgument,
+    /// Not set, but declared with a default in the buckconfig schema of the cell.
+    SchemaDefault,
 }
 
 impl Location {
@@ -223,11 +239,12 @@ impl Location {
         match self {
             Self::File(x) => LegacyBuckConfigLocation::File(&x.source_file.id, x.line),
             Self::CommandLineArgument => LegacyBuckConfigLocation::CommandLineArgument,
+            Self::SchemaDefault => LegacyBuckConfigLocation::SchemaDefault,
         }
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 struct ConfigArgumentPair {
     section: String,
     key: String,
@@ -250,7 +267,7 @@ pub struct ConfigSectionAndKey {
 /// Represents a configuration argument that can be passed
 /// on the command line. For example, `--config foo.bar=val`
 /// or `--config-file foo.bcfg`.
-#[derive(Debug, Display)]
+#[derive(Debug, Clone, Display)]
 pub enum LegacyConfigCmdArg {
     /// A single config key-value pair (in `a.b=c` format).
     Flag(LegacyConfigCmdArgFlag),
@@ -292,7 +309,7 @@ impl LegacyConfigCmdArg {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LegacyConfigCmdArgFlag {
     cell: Option<String>,
     section: String,
@@ -313,7 +330,7 @@ impl fmt::Display for LegacyConfigCmdArgFlag {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LegacyConfigCmdArgFile {
     cell: Option<String>,
     path: String,
@@ -330,7 +347,7 @@ impl fmt::Display for LegacyConfigCmdArgFile {
 
 /// Private representation of a processed config arg, namely after file
 /// path resolution has been performed.
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 enum ResolvedLegacyConfigArg {
     /// A single config key-value pair (in `a.b=c` format).
     Flag(ConfigArgumentPair),
@@ -420,7 +437,7 @@ fn parse_config_arg(raw_arg: &str) -> anyhow::Result<ParsedConfigArg> {
     })
 }
 
-#[derive(Debug, Allocative)]
+#[derive(Debug, Clone, Allocative)]
 struct ConfigValue {
     raw_value: String,
     resolved_value: ResolvedValue,
@@ -494,7 +511,8 @@ impl ConfigValue {
 }
 
 struct LegacyConfigParser<'a> {
-    file_ops: &'a mut dyn ConfigParserFileOps,
+    file_ops: &'a dyn ConfigParserFileOps,
+    files: Vec<LegacyBuckConfigFile>,
     include_stack: Vec<ConfigFileLocation>,
     current_file: Option<Arc<ConfigFile>>,
     values: BTreeMap<String, SectionBuilder>,
@@ -510,9 +528,9 @@ struct LegacyConfigParser<'a> {
 static FILE_INCLUDE: Lazy<Regex> =
     Lazy::new(|| Regex::new("<(?P<optional>\\?)?file:(?P<include>..*)>").unwrap());
 
-pub trait ConfigParserFileOps {
+pub trait ConfigParserFileOps: Send + Sync {
     fn read_file_lines(
-        &mut self,
+        &self,
         path: &AbsNormPath,
     ) -> anyhow::Result<Box<dyn Iterator<Item = Result<String, std::io::Error>>>>;
 
@@ -521,13 +539,32 @@ pub trait ConfigParserFileOps {
     fn file_id(&self, path: &AbsNormPath) -> String {
         path.to_string()
     }
+
+    /// Stamp of the file, which changes when the file does, taken without reading the file.
+    /// `None` if these file ops cannot provide stamps, in which case parsed configs are not cached.
+    fn file_stamp(&self, _path: &AbsNormPath) -> Option<ConfigFileStamp> {
+        None
+    }
+}
+
+/// Metadata of a config file used to tell if it changed without reading it.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ConfigFileStamp {
+    Missing,
+    File {
+        len: u64,
+        /// Nanoseconds since the Unix epoch.
+        modified_ns: u64,
+        /// Zero where inodes are not available.
+        inode: u64,
+    },
 }
 
 struct DefaultConfigParserFileOps {}
 
 impl ConfigParserFileOps for DefaultConfigParserFileOps {
     fn read_file_lines(
-        &mut self,
+        &self,
         path: &AbsNormPath,
     ) -> anyhow::Result<Box<dyn Iterator<Item = Result<String, std::io::Error>>>> {
         let f = std::fs::File::open(path).with_context(|| format!("Reading file `{:?}`", path))?;
@@ -538,12 +575,37 @@ impl ConfigParserFileOps for DefaultConfigParserFileOps {
     fn file_exists(&self, path: &AbsNormPath) -> bool {
         PathBuf::from(path.as_os_str()).exists()
     }
+
+    fn file_stamp(&self, path: &AbsNormPath) -> Option<ConfigFileStamp> {
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Some(ConfigFileStamp::Missing);
+            }
+            Err(_) => return None,
+        };
+        let modified = metadata
+            .modified()
+            .ok()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()?;
+        #[cfg(unix)]
+        let inode = std::os::unix::fs::MetadataExt::ino(&metadata);
+        #[cfg(not(unix))]
+        let inode = 0;
+        Some(ConfigFileStamp::File {
+            len: metadata.len(),
+            modified_ns: u64::try_from(modified.as_nanos()).ok()?,
+            inode,
+        })
+    }
 }
 
 impl<'a> LegacyConfigParser<'a> {
-    fn new(file_ops: &'a mut dyn ConfigParserFileOps) -> Self {
+    fn new(file_ops: &'a dyn ConfigParserFileOps) -> Self {
         LegacyConfigParser {
             values: BTreeMap::new(),
+            files: Vec::new(),
             include_stack: Vec::new(),
             current_file: None,
             current_section: Self::unspecified_section(),
@@ -580,6 +642,10 @@ impl<'a> LegacyConfigParser<'a> {
             id: self.file_ops.file_id(path),
             include_source: Some(Location::File(include_source)),
         });
+        self.files.push(LegacyBuckConfigFile {
+            path: source_file.id.clone(),
+            include_depth: self.include_stack.len(),
+        });
         self.current_file = Some(source_file);
         Ok(())
     }
@@ -589,6 +655,10 @@ impl<'a> LegacyConfigParser<'a> {
             id: self.file_ops.file_id(path),
             include_source: source,
         });
+        self.files.push(LegacyBuckConfigFile {
+            path: source_file.id.clone(),
+            include_depth: 0,
+        });
         self.current_file = Some(source_file);
         Ok(())
     }
@@ -795,11 +865,11 @@ impl<'a> LegacyConfigParser<'a> {
     }
 
     fn finish(self) -> anyhow::Result<LegacyBuckConfig> {
-        let LegacyConfigParser { values, .. } = self;
+        let LegacyConfigParser { values, files, .. } = self;
 
         let values = ConfigResolver::resolve(values)?;
 
-        Ok(LegacyBuckConfig(Arc::new(ConfigData { values })))
+        Ok(LegacyBuckConfig(Arc::new(ConfigData { values, files })))
     }
 }
 
@@ -1007,6 +1077,7 @@ pub struct LegacyBuckConfigValue<'a> {
 pub enum LegacyBuckConfigLocation<'a> {
     File(&'a str, usize),
     CommandLineArgument,
+    SchemaDefault,
 }
 
 impl<'a> Display for LegacyBuckConfigLocation<'a> {
@@ -1018,6 +1089,9 @@ impl<'a> Display for LegacyBuckConfigLocation<'a> {
             Self::CommandLineArgument => {
                 write!(f, "on the command line")
             }
+            Self::SchemaDefault => {
+                write!(f, "as the default in the buckconfig schema")
+            }
         }
     }
 }
@@ -1035,6 +1109,7 @@ impl<'a> LegacyBuckConfigValue<'a> {
         match &self.value.source {
             Location::File(file) => LegacyBuckConfigLocation::File(&file.source_file.id, file.line),
             Location::CommandLineArgument => LegacyBuckConfigLocation::CommandLineArgument,
+            Location::SchemaDefault => LegacyBuckConfigLocation::SchemaDefault,
         }
     }
 
@@ -1054,6 +1129,9 @@ impl<'a> LegacyBuckConfigValue<'a> {
                 Location::CommandLineArgument => {
                     // No stack
                 }
+                Location::SchemaDefault => {
+                    res.push(LegacyBuckConfigLocation::SchemaDefault);
+                }
             }
         }
         res
@@ -1064,6 +1142,54 @@ impl LegacyBuckConfig {
     pub fn empty() -> Self {
         Self(Arc::new(ConfigData {
             values: SortedMap::new(),
+            files: Vec::new(),
+        }))
+    }
+
+    /// The config with `defaults`, as `(section, key, value)`, set for the keys it does not set.
+    pub(crate) fn with_defaults<'a>(
+        &self,
+        defaults: impl IntoIterator<Item = (&'a str, &'a str, &'a str)>,
+    ) -> Self {
+        let mut values: BTreeMap<String, BTreeMap<String, ConfigValue>> = self
+            .0
+            .values
+            .iter()
+            .map(|(name, section)| {
+                (
+                    name.clone(),
+                    section
+                        .values
+                        .iter()
+                        .map(|(key, value)| (key.clone(), value.clone()))
+                        .collect(),
+                )
+            })
+            .collect();
+        for (section, key, value) in defaults {
+            values
+                .entry(section.to_owned())
+                .or_default()
+                .entry(key.to_owned())
+                .or_insert_with(|| ConfigValue {
+                    raw_value: value.to_owned(),
+                    resolved_value: ResolvedValue::Literal,
+                    source: Location::SchemaDefault,
+                });
+        }
+        Self(Arc::new(ConfigData {
+            values: values
+                .into_iter()
+                .map(|(name, values)| {
+                    (
+                        name,
+                        LegacyBuckConfigSection {
+                            values: SortedMap::from_iter(values),
+                        },
+                    )
+                })
+                .collect(),
+            files: self.0.files.clone(),
         }))
     }
 
@@ -1073,7 +1199,7 @@ impl LegacyBuckConfig {
 
     pub fn parse_with_file_ops(
         path: &AbsNormPath,
-        file_ops: &mut dyn ConfigParserFileOps,
+        file_ops: &dyn ConfigParserFileOps,
         config_args: &[LegacyConfigCmdArg],
     ) -> anyhow::Result<Self> {
         // This function is only used internally for tests, so it's to skip cell resolution
@@ -1095,7 +1221,7 @@ impl LegacyBuckConfig {
     fn resolve_config_flag_arg(
         flag_arg: &LegacyConfigCmdArgFlag,
         cell_resolution: Option<&CellResolutionState>,
-        file_ops: &mut dyn ConfigParserFileOps,
+        file_ops: &dyn ConfigParserFileOps,
     ) -> anyhow::Result<ConfigArgumentPair> {
         let cell_path = flag_arg
             .cell
@@ -1123,7 +1249,7 @@ impl LegacyBuckConfig {
     fn resolve_config_file_arg(
         file_arg: &LegacyConfigCmdArgFile,
         cell_resolution: Option<&CellResolutionState>,
-        file_ops: &mut dyn ConfigParserFileOps,
+        file_ops: &dyn ConfigParserFileOps,
     ) -> anyhow::Result<AbsNormPathBuf> {
         if let Some(cell_alias) = &file_arg.cell {
             let cell_resolution_state = cell_resolution.ok_or_else(|| {
@@ -1170,7 +1296,7 @@ impl LegacyBuckConfig {
     fn process_config_args(
         args: &[LegacyConfigCmdArg],
         cell_resolution: Option<&CellResolutionState>,
-        file_ops: &mut dyn ConfigParserFileOps,
+        file_ops: &dyn ConfigParserFileOps,
     ) -> anyhow::Result<Vec<ResolvedLegacyConfigArg>> {
         let resolved_args = args.map(|unprocessed_arg| match unprocessed_arg {
             LegacyConfigCmdArg::Flag(value) => {
@@ -1189,7 +1315,7 @@ impl LegacyBuckConfig {
 
     fn parse_with_file_ops_with_includes(
         main_config_files: &[MainConfigFile],
-        file_ops: &mut dyn ConfigParserFileOps,
+        file_ops: &dyn ConfigParserFileOps,
         config_args: &[ResolvedLegacyConfigArg],
         follow_includes: bool,
     ) -> anyhow::Result<Self> {
@@ -1306,12 +1432,20 @@ impl LegacyBuckConfig {
     pub fn get_section(&self, section: &str) -> Option<&LegacyBuckConfigSection> {
         self.0.values.get(section)
     }
+
+    /// Config files read to produce this config, in the order they were read.
+    pub fn files(&self) -> &[LegacyBuckConfigFile] {
+        &self.0.files
+    }
 }
 
 // Options on how to exactly parse config files
 struct BuckConfigParseOptions {
     // Defines whether includes are followed, this can significantly reduce parse time.
     follow_includes: bool,
+    // Whether all external cells must be locked, by the lockfile or a commit in the config. When
+    // not set, git cells which are not locked are left out of the cells.
+    require_locked_external_cells: bool,
 }
 
 fn push_all_files_from_a_directory(
@@ -1377,7 +1511,10 @@ pub mod testing {
                 .insert(key.to_owned(), ConfigValue::new_raw_arg(value.to_owned()));
         }
         let values = ConfigResolver::resolve(values)?;
-        Ok(LegacyBuckConfig(Arc::new(ConfigData { values })))
+        Ok(LegacyBuckConfig(Arc::new(ConfigData {
+            values,
+            files: Vec::new(),
+        })))
     }
 
     pub fn parse(data: &[(&str, &str)], path: &str) -> anyhow::Result<LegacyBuckConfig> {
@@ -1389,13 +1526,13 @@ pub mod testing {
         path: &str,
         config_args: &[LegacyConfigCmdArg],
     ) -> anyhow::Result<LegacyBuckConfig> {
-        let mut file_ops = TestConfigParserFileOps::new(data)?;
+        let file_ops = TestConfigParserFileOps::new(data)?;
         #[cfg(not(windows))]
         let path = &AbsNormPathBuf::from(path.into())?;
         // Need to add some disk drive on Windows to make path absolute.
         #[cfg(windows)]
         let path = &AbsNormPathBuf::from(format!("C:{}", path))?;
-        LegacyBuckConfig::parse_with_file_ops(path, &mut file_ops, config_args)
+        LegacyBuckConfig::parse_with_file_ops(path, &file_ops, config_args)
     }
 
     pub struct TestConfigParserFileOps {
@@ -1423,7 +1560,7 @@ pub mod testing {
         }
 
         fn read_file_lines(
-            &mut self,
+            &self,
             path: &AbsNormPath,
         ) -> anyhow::Result<
             Box<(dyn std::iter::Iterator<Item = Result<String, std::io::Error>> + 'static)>,
@@ -1742,6 +1879,26 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    #[cfg(not(windows))]
+    fn test_files() -> anyhow::Result<()> {
+        let config = parse(
+            &[
+                ("/base", "base = okay!\n"),
+                ("/middle", "<file:base>\n"),
+                ("/config", "[section]\n<file:middle>\n<?file:missing>\n"),
+            ],
+            "/config",
+        )?;
+        let files: Vec<_> = config
+            .files()
+            .iter()
+            .map(|f| (f.path.as_str(), f.include_depth))
+            .collect();
+        assert_eq!(vec![("/config", 0), ("/middle", 1), ("/base", 2)], files);
+        Ok(())
+    }
+
     #[test]
     fn test_config_args_ordering() -> anyhow::Result<()> {
         let config_args = vec![
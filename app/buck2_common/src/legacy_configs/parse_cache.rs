@@ -0,0 +1,711 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Cache of the parsed configs of cells, persisted under `buck-out`, so that a cell's configs
+//! are only parsed again, e.g. when the daemon starts, if one of their files changed.
+//!
+//! An entry records every file accessed to parse the config, including includes and files
+//! which were only checked for existence. For each file, its stamp (size, modification time and
+//! inode) and the digest of its content are taken on first access, before the parser reads it,
+//! so an edit made while parsing makes the entry stale rather than caching the new content under
+//! the old digest. An entry is used if the stamp of each file is unchanged, without reading
+//! them, or else if the file's content still has the recorded digest.
+
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use dupe::Dupe;
+use parking_lot::Mutex;
+use starlark_map::sorted_map::SortedMap;
+
+use crate::legacy_configs::ConfigData;
+use crate::legacy_configs::ConfigFile;
+use crate::legacy_configs::ConfigFileLocation;
+use crate::legacy_configs::ConfigFileStamp;
+use crate::legacy_configs::ConfigParserFileOps;
+use crate::legacy_configs::ConfigValue;
+use crate::legacy_configs::LegacyBuckConfig;
+use crate::legacy_configs::LegacyBuckConfigFile;
+use crate::legacy_configs::LegacyBuckConfigSection;
+use crate::legacy_configs::Location;
+use crate::legacy_configs::MainConfigFile;
+use crate::legacy_configs::ResolvedLegacyConfigArg;
+use crate::legacy_configs::ResolvedValue;
+
+/// Directory of the cache, relative to the project root.
+pub(crate) const CACHE_DIR: &str = "buck-out/config_cache";
+
+/// Bumped whenever the format of the entries changes.
+const CACHE_VERSION: u32 = 1;
+
+/// Files modified this recently before the parse started may be modified again without their
+/// stamp changing (coarse timestamps), so only their digest is trusted.
+const RACY_WINDOW: Duration = Duration::from_secs(2);
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct CacheEntry {
+    version: u32,
+    main_config_files: Vec<String>,
+    config_args: Vec<PersistedConfigArg>,
+    follow_includes: bool,
+    files: Vec<FileEntry>,
+    config: PersistedConfig,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct FileEntry {
+    path: String,
+    /// `None` if the stamp cannot be trusted, see `RACY_WINDOW`.
+    stamp: Option<ConfigFileStamp>,
+    /// Hex digest of the content, `None` if the file did not exist.
+    digest: Option<String>,
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+enum PersistedConfigArg {
+    Flag {
+        section: String,
+        key: String,
+        value: Option<String>,
+        cell_path: Option<String>,
+    },
+    File(String),
+}
+
+impl PersistedConfigArg {
+    fn new(arg: &ResolvedLegacyConfigArg) -> Self {
+        match arg {
+            ResolvedLegacyConfigArg::Flag(pair) => Self::Flag {
+                section: pair.section.clone(),
+                key: pair.key.clone(),
+                value: pair.value.clone(),
+                cell_path: pair.cell_path.as_ref().map(|p| p.to_string()),
+            },
+            ResolvedLegacyConfigArg::File(path) => Self::File(path.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct PersistedConfig {
+    /// Files the values come from, a file always after the file including it.
+    sources: Vec<PersistedSource>,
+    values: Vec<(String, Vec<(String, PersistedValue)>)>,
+    files: Vec<(String, usize)>,
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct PersistedSource {
+    id: String,
+    include_source: Option<PersistedLocation>,
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct PersistedValue {
+    raw_value: String,
+    resolved_value: PersistedResolvedValue,
+    source: PersistedLocation,
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+enum PersistedResolvedValue {
+    Unknown,
+    Literal,
+    Resolved(String),
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+enum PersistedLocation {
+    /// Index into `PersistedConfig::sources`.
+    File {
+        source: usize,
+        line: usize,
+    },
+    CommandLineArgument,
+    SchemaDefault,
+}
+
+#[derive(buck2_error::Error, Debug)]
+enum ParseCacheError {
+    #[error("Cached config refers to unknown source file {0}")]
+    UnknownSource(usize),
+}
+
+#[derive(Default)]
+struct SourcesBuilder {
+    indices: HashMap<*const ConfigFile, usize>,
+    sources: Vec<PersistedSource>,
+}
+
+impl SourcesBuilder {
+    fn location(&mut self, location: &Location) -> PersistedLocation {
+        match location {
+            Location::File(x) => PersistedLocation::File {
+                source: self.source(&x.source_file),
+                line: x.line,
+            },
+            Location::CommandLineArgument => PersistedLocation::CommandLineArgument,
+            Location::SchemaDefault => PersistedLocation::SchemaDefault,
+        }
+    }
+
+    fn source(&mut self, file: &Arc<ConfigFile>) -> usize {
+        if let Some(index) = self.indices.get(&Arc::as_ptr(file)) {
+            return *index;
+        }
+        let include_source = file.include_source.as_ref().map(|l| self.location(l));
+        let index = self.sources.len();
+        self.sources.push(PersistedSource {
+            id: file.id.clone(),
+            include_source,
+        });
+        self.indices.insert(Arc::as_ptr(file), index);
+        index
+    }
+}
+
+impl PersistedConfig {
+    fn new(config: &LegacyBuckConfig) -> Self {
+        let mut sources = SourcesBuilder::default();
+        let values = config
+            .0
+            .values
+            .iter()
+            .map(|(section, values)| {
+                let values = values
+                    .values
+                    .iter()
+                    .map(|(key, value)| {
+                        let resolved_value = match &value.resolved_value {
+                            ResolvedValue::Unknown => PersistedResolvedValue::Unknown,
+                            ResolvedValue::Literal => PersistedResolvedValue::Literal,
+                            ResolvedValue::Resolved(v) => {
+                                PersistedResolvedValue::Resolved(v.clone())
+                            }
+                        };
+                        let value = PersistedValue {
+                            raw_value: value.raw_value.clone(),
+                            resolved_value,
+                            source: sources.location(&value.source),
+                        };
+                        (key.clone(), value)
+                    })
+                    .collect();
+                (section.clone(), values)
+            })
+            .collect();
+        PersistedConfig {
+            sources: sources.sources,
+            values,
+            files: config
+                .0
+                .files
+                .iter()
+                .map(|f| (f.path.clone(), f.include_depth))
+                .collect(),
+        }
+    }
+
+    fn into_config(self) -> anyhow::Result<LegacyBuckConfig> {
+        fn location(
+            sources: &[Arc<ConfigFile>],
+            location: PersistedLocation,
+        ) -> anyhow::Result<Location> {
+            Ok(match location {
+                PersistedLocation::File { source, line } => Location::File(ConfigFileLocation {
+                    source_file: sources
+                        .get(source)
+                        .ok_or(ParseCacheError::UnknownSource(source))?
+                        .dupe(),
+                    line,
+                }),
+                PersistedLocation::CommandLineArgument => Location::CommandLineArgument,
+                PersistedLocation::SchemaDefault => Location::SchemaDefault,
+            })
+        }
+
+        let mut sources = Vec::with_capacity(self.sources.len());
+        for source in self.sources {
+            let include_source = match source.include_source {
+                Some(l) => Some(location(&sources, l)?),
+                None => None,
+            };
+            sources.push(Arc::new(ConfigFile {
+                id: source.id,
+                include_source,
+            }));
+        }
+
+        let values = self
+            .values
+            .into_iter()
+            .map(|(section, values)| {
+                let values = values
+                    .into_iter()
+                    .map(|(key, value)| {
+                        let resolved_value = match value.resolved_value {
+                            PersistedResolvedValue::Unknown => ResolvedValue::Unknown,
+                            PersistedResolvedValue::Literal => ResolvedValue::Literal,
+                            PersistedResolvedValue::Resolved(v) => ResolvedValue::Resolved(v),
+                        };
+                        let value = ConfigValue {
+                            raw_value: value.raw_value,
+                            resolved_value,
+                            source: location(&sources, value.source)?,
+                        };
+                        anyhow::Ok((key, value))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                anyhow::Ok((
+                    section,
+                    LegacyBuckConfigSection {
+                        values: SortedMap::from_iter(values),
+                    },
+                ))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(LegacyBuckConfig(Arc::new(ConfigData {
+            values: SortedMap::from_iter(values),
+            files: self
+                .files
+                .into_iter()
+                .map(|(path, include_depth)| LegacyBuckConfigFile {
+                    path,
+                    include_depth,
+                })
+                .collect(),
+        })))
+    }
+}
+
+fn file_digest(
+    file_ops: &dyn ConfigParserFileOps,
+    path: &AbsNormPath,
+) -> anyhow::Result<Option<String>> {
+    if !file_ops.file_exists(path) {
+        return Ok(None);
+    }
+    let mut hasher = blake3::Hasher::new();
+    for line in file_ops.read_file_lines(path)? {
+        hasher.update(line?.as_bytes());
+        hasher.update(b"\n");
+    }
+    Ok(Some(hasher.finalize().to_hex().to_string()))
+}
+
+fn is_racy(stamp: &ConfigFileStamp, now: SystemTime) -> bool {
+    match stamp {
+        ConfigFileStamp::Missing => false,
+        ConfigFileStamp::File { modified_ns, .. } => {
+            let modified = SystemTime::UNIX_EPOCH + Duration::from_nanos(*modified_ns);
+            modified + RACY_WINDOW >= now
+        }
+    }
+}
+
+/// Records the stamp and digest of each file accessed by the parser, before the parser reads it.
+struct RecordingFileOps<'a> {
+    inner: &'a dyn ConfigParserFileOps,
+    accessed: Mutex<Vec<FileEntry>>,
+    /// Set if a file could not be stamped or hashed, in which case nothing is cached.
+    failed: AtomicBool,
+    now: SystemTime,
+}
+
+impl RecordingFileOps<'_> {
+    fn record(&self, path: &AbsNormPath) {
+        let path_str = path.to_string();
+        if self.accessed.lock().iter().any(|f| f.path == path_str) {
+            return;
+        }
+        let (stamp, digest) = match (self.inner.file_stamp(path), file_digest(self.inner, path)) {
+            (Some(stamp), Ok(digest)) => (stamp, digest),
+            _ => {
+                self.failed.store(true, Ordering::Relaxed);
+                return;
+            }
+        };
+        let stamp = if is_racy(&stamp, self.now) {
+            None
+        } else {
+            Some(stamp)
+        };
+        self.accessed.lock().push(FileEntry {
+            path: path_str,
+            stamp,
+            digest,
+        });
+    }
+}
+
+impl ConfigParserFileOps for RecordingFileOps<'_> {
+    fn read_file_lines(
+        &self,
+        path: &AbsNormPath,
+    ) -> anyhow::Result<Box<dyn Iterator<Item = Result<String, std::io::Error>>>> {
+        self.record(path);
+        self.inner.read_file_lines(path)
+    }
+
+    fn file_exists(&self, path: &AbsNormPath) -> bool {
+        self.record(path);
+        self.inner.file_exists(path)
+    }
+
+    fn file_id(&self, path: &AbsNormPath) -> String {
+        self.inner.file_id(path)
+    }
+
+    fn file_stamp(&self, path: &AbsNormPath) -> Option<ConfigFileStamp> {
+        self.inner.file_stamp(path)
+    }
+}
+
+/// Whether the files are unchanged, and their entries with refreshed stamps if any stamp changed.
+fn check_files(
+    files: &[FileEntry],
+    file_ops: &dyn ConfigParserFileOps,
+    now: SystemTime,
+) -> anyhow::Result<Option<Option<Vec<FileEntry>>>> {
+    let mut refreshed = None;
+    for (i, file) in files.iter().enumerate() {
+        let path = AbsNormPathBuf::from(file.path.clone())?;
+        let Some(stamp) = file_ops.file_stamp(&path) else {
+            return Ok(None);
+        };
+        if file.stamp.as_ref() == Some(&stamp) {
+            continue;
+        }
+        if file_digest(file_ops, &path)? != file.digest {
+            return Ok(None);
+        }
+        if !is_racy(&stamp, now) {
+            refreshed.get_or_insert_with(|| files.to_vec())[i].stamp = Some(stamp);
+        }
+    }
+    Ok(Some(refreshed))
+}
+
+fn entry_path(
+    cache_dir: &AbsNormPath,
+    main_config_files: &[String],
+) -> anyhow::Result<AbsNormPathBuf> {
+    let mut hasher = blake3::Hasher::new();
+    for path in main_config_files {
+        hasher.update(path.as_bytes());
+        hasher.update(b"\0");
+    }
+    cache_dir.join_normalized(format!("{}.json", hasher.finalize().to_hex()))
+}
+
+fn read_entry(path: &AbsNormPath) -> Option<CacheEntry> {
+    let data = std::fs::read(path).ok()?;
+    match serde_json::from_slice::<CacheEntry>(&data) {
+        Ok(entry) if entry.version == CACHE_VERSION => Some(entry),
+        Ok(_) => None,
+        Err(e) => {
+            tracing::debug!("Ignoring invalid config cache entry `{}`: {:#}", path, e);
+            None
+        }
+    }
+}
+
+/// Writes the entry through a temporary file, so concurrent readers never see a partial entry.
+fn write_entry(path: &AbsNormPath, entry: &CacheEntry) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let tmp = AbsNormPathBuf::from(format!("{}.{}.tmp", path, std::process::id()))?;
+    std::fs::write(&tmp, serde_json::to_vec(entry)?)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Parse the config of a cell from its main config files, or reuse the config cached in
+/// `cache_dir` if none of the files it was parsed from changed.
+pub(crate) fn parse_cell_config(
+    cache_dir: &AbsNormPath,
+    main_config_files: &[MainConfigFile],
+    file_ops: &dyn ConfigParserFileOps,
+    config_args: &[ResolvedLegacyConfigArg],
+    follow_includes: bool,
+) -> anyhow::Result<LegacyBuckConfig> {
+    parse_cell_config_at(
+        cache_dir,
+        main_config_files,
+        file_ops,
+        config_args,
+        follow_includes,
+        SystemTime::now(),
+    )
+}
+
+fn parse_cell_config_at(
+    cache_dir: &AbsNormPath,
+    main_config_files: &[MainConfigFile],
+    file_ops: &dyn ConfigParserFileOps,
+    config_args: &[ResolvedLegacyConfigArg],
+    follow_includes: bool,
+    now: SystemTime,
+) -> anyhow::Result<LegacyBuckConfig> {
+    let parse = |file_ops: &dyn ConfigParserFileOps| {
+        LegacyBuckConfig::parse_with_file_ops_with_includes(
+            main_config_files,
+            file_ops,
+            config_args,
+            follow_includes,
+        )
+    };
+
+    if main_config_files
+        .iter()
+        .any(|f| file_ops.file_stamp(&f.path).is_none())
+    {
+        return parse(file_ops);
+    }
+
+    let main_config_file_paths: Vec<String> = main_config_files
+        .iter()
+        .map(|f| f.path.to_string())
+        .collect();
+    let config_args: Vec<PersistedConfigArg> =
+        config_args.iter().map(PersistedConfigArg::new).collect();
+    let entry_path = entry_path(cache_dir, &main_config_file_paths)?;
+
+    if let Some(mut entry) = read_entry(&entry_path) {
+        if entry.main_config_files == main_config_file_paths
+            && entry.config_args == config_args
+            && entry.follow_includes == follow_includes
+        {
+            if let Some(refreshed) = check_files(&entry.files, file_ops, now)? {
+                let config = entry.config.into_config()?;
+                if let Some(files) = refreshed {
+                    entry.files = files;
+                    entry.config = PersistedConfig::new(&config);
+                    if let Err(e) = write_entry(&entry_path, &entry) {
+                        tracing::debug!("Failed to update config cache `{}`: {:#}", entry_path, e);
+                    }
+                }
+                return Ok(config);
+            }
+        }
+    }
+
+    let recording = RecordingFileOps {
+        inner: file_ops,
+        accessed: Mutex::new(Vec::new()),
+        failed: AtomicBool::new(false),
+        now,
+    };
+    let config = parse(&recording)?;
+
+    if !recording.failed.into_inner() {
+        let entry = CacheEntry {
+            version: CACHE_VERSION,
+            main_config_files: main_config_file_paths,
+            config_args,
+            follow_includes,
+            files: recording.accessed.into_inner(),
+            config: PersistedConfig::new(&config),
+        };
+        if let Err(e) = write_entry(&entry_path, &entry) {
+            tracing::debug!("Failed to write config cache `{}`: {:#}", entry_path, e);
+        }
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+
+    use super::*;
+    use crate::legacy_configs::DefaultConfigParserFileOps;
+
+    /// Counts the files read, to tell if a config came from the cache.
+    struct CountingFileOps {
+        reads: Mutex<usize>,
+    }
+
+    impl ConfigParserFileOps for CountingFileOps {
+        fn read_file_lines(
+            &self,
+            path: &AbsNormPath,
+        ) -> anyhow::Result<Box<dyn Iterator<Item = Result<String, std::io::Error>>>> {
+            *self.reads.lock() += 1;
+            DefaultConfigParserFileOps {}.read_file_lines(path)
+        }
+
+        fn file_exists(&self, path: &AbsNormPath) -> bool {
+            DefaultConfigParserFileOps {}.file_exists(path)
+        }
+
+        fn file_stamp(&self, path: &AbsNormPath) -> Option<ConfigFileStamp> {
+            DefaultConfigParserFileOps {}.file_stamp(path)
+        }
+    }
+
+    struct TestRepo {
+        _dir: tempfile::TempDir,
+        root: AbsNormPathBuf,
+    }
+
+    impl TestRepo {
+        fn new() -> anyhow::Result<Self> {
+            let dir = tempfile::tempdir()?;
+            let root = AbsNormPathBuf::new(dir.path().canonicalize()?)?;
+            Ok(TestRepo { _dir: dir, root })
+        }
+
+        fn path(&self, path: &str) -> AbsNormPathBuf {
+            self.root.join(ForwardRelativePath::unchecked_new(path))
+        }
+
+        /// Replaces the file, so its stamp changes even within the timestamp granularity.
+        fn write(&self, path: &str, content: &str) -> anyhow::Result<()> {
+            let tmp = self.path("tmp");
+            std::fs::write(&tmp, content)?;
+            std::fs::rename(&tmp, self.path(path))?;
+            Ok(())
+        }
+
+        /// Returns the value of `a.b` and the number of files read, with no file modified within
+        /// the racy window.
+        fn parse(&self) -> anyhow::Result<(Option<String>, usize)> {
+            self.parse_at(SystemTime::now() + Duration::from_secs(60))
+        }
+
+        fn parse_at(&self, now: SystemTime) -> anyhow::Result<(Option<String>, usize)> {
+            let file_ops = CountingFileOps {
+                reads: Mutex::new(0),
+            };
+            let config = parse_cell_config_at(
+                &self.path("buck-out/config_cache"),
+                &[MainConfigFile {
+                    path: self.path(".buckconfig"),
+                    owned_by_project: true,
+                }],
+                &file_ops,
+                &[],
+                true,
+                now,
+            )?;
+            let value = config.get("a", "b").map(|v| v.to_owned());
+            Ok((value, file_ops.reads.into_inner()))
+        }
+    }
+
+    #[test]
+    fn test_parse_cell_config_persists_and_reparses_changed_files() -> anyhow::Result<()> {
+        let repo = TestRepo::new()?;
+        repo.write(".buckconfig", "[a]\nb = c\n<?file:optional>\n")?;
+        let (value, reads) = repo.parse()?;
+        assert_eq!(Some("c"), value.as_deref());
+        assert!(reads > 0);
+
+        // A hit only checks stamps and reads no config file.
+        assert_eq!((Some("c".to_owned()), 0), repo.parse()?);
+
+        repo.write(".buckconfig", "[a]\nb = xy\n<?file:optional>\n")?;
+        assert_eq!(Some("xy"), repo.parse()?.0.as_deref());
+
+        repo.write("optional", "[a]\nb = y\n")?;
+        assert_eq!(Some("y"), repo.parse()?.0.as_deref());
+        assert_eq!((Some("y".to_owned()), 0), repo.parse()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_cell_config_checks_digest_of_touched_files() -> anyhow::Result<()> {
+        let repo = TestRepo::new()?;
+        repo.write(".buckconfig", "[a]\nb = c\n")?;
+        repo.parse()?;
+
+        // Same content with a new stamp: a hit after hashing, which refreshes the stamp.
+        repo.write(".buckconfig", "[a]\nb = c\n")?;
+        assert_eq!((Some("c".to_owned()), 1), repo.parse()?);
+        assert_eq!((Some("c".to_owned()), 0), repo.parse()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_cell_config_does_not_trust_racy_stamps() -> anyhow::Result<()> {
+        let repo = TestRepo::new()?;
+        repo.write(".buckconfig", "[a]\nb = c\n")?;
+        // Parsed right after the write, so the stamp is not recorded.
+        repo.parse_at(SystemTime::now())?;
+        // Rewritten in place, possibly with the same size, inode and timestamp.
+        std::fs::write(repo.path(".buckconfig"), "[a]\nb = d\n")?;
+        assert_eq!(Some("d"), repo.parse()?.0.as_deref());
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_racy() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(100);
+        let stamp = |secs| ConfigFileStamp::File {
+            len: 1,
+            modified_ns: Duration::from_secs(secs).as_nanos() as u64,
+            inode: 0,
+        };
+        assert!(is_racy(&stamp(99), now));
+        assert!(!is_racy(&stamp(90), now));
+        assert!(!is_racy(&ConfigFileStamp::Missing, now));
+    }
+
+    #[test]
+    fn test_persisted_config_roundtrip() -> anyhow::Result<()> {
+        let repo = TestRepo::new()?;
+        repo.write(".buckconfig", "[a]\nb = $(config a.c)\nc = d\n<file:inc>\n")?;
+        repo.write("inc", "[e]\nf = g\n")?;
+        let config = LegacyBuckConfig::parse_with_file_ops_with_includes(
+            &[MainConfigFile {
+                path: repo.path(".buckconfig"),
+                owned_by_project: true,
+            }],
+            &DefaultConfigParserFileOps {},
+            &[],
+            true,
+        )?;
+        let persisted = PersistedConfig::new(&config);
+        let json = serde_json::to_vec(&persisted)?;
+        let restored = serde_json::from_slice::<PersistedConfig>(&json)?.into_config()?;
+        assert!(config.compare(&restored));
+        assert_eq!(persisted, PersistedConfig::new(&restored));
+        assert_eq!(Some("d"), restored.get("a", "b"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_into_config_rejects_unknown_source() {
+        let persisted = PersistedConfig {
+            sources: Vec::new(),
+            values: vec![(
+                "a".to_owned(),
+                vec![(
+                    "b".to_owned(),
+                    PersistedValue {
+                        raw_value: "c".to_owned(),
+                        resolved_value: PersistedResolvedValue::Literal,
+                        source: PersistedLocation::File { source: 0, line: 1 },
+                    },
+                )],
+            )],
+            files: Vec::new(),
+        };
+        assert!(persisted.into_config().is_err());
+    }
+}
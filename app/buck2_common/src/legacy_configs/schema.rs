@@ -0,0 +1,394 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Optional per-cell schema for buckconfig keys.
+//!
+//! A cell may declare the buckconfig keys it expects in a `.buckconfig.schema.json`
+//! file next to its `.buckconfig`:
+//!
+//! ```json
+//! {
+//!   "buck2": {
+//!     "materializations": {"type": "enum", "values": ["all", "deferred", "none"], "default": "deferred"},
+//!     "old_key": {"type": "bool", "deprecated": "use `buck2.new_key` instead"}
+//!   }
+//! }
+//! ```
+//!
+//! Only sections mentioned in the schema are validated: within such a section, every key
+//! set in the config must be declared, and its value must match the declared type. Declared
+//! keys which are not set read as their `default`, if they have one.
+
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use itertools::Itertools;
+
+use crate::legacy_configs::ConfigParserFileOps;
+use crate::legacy_configs::LegacyBuckConfig;
+
+pub(crate) const BUCKCONFIG_SCHEMA_FILE: &str = ".buckconfig.schema.json";
+
+#[derive(Debug, buck2_error::Error)]
+#[buck2(user)]
+enum ConfigSchemaError {
+    #[error(
+        "Unknown buckconfig `{section}.{key}` (defined {location}), not declared in the schema for section `{section}`{}",
+        did_you_mean(.suggestion)
+    )]
+    UnknownKey {
+        section: String,
+        key: String,
+        location: String,
+        suggestion: Option<String>,
+    },
+    #[error(
+        "Invalid value for buckconfig `{section}.{key}` (defined {location}): expected {expected}, got `{value}`"
+    )]
+    InvalidValue {
+        section: String,
+        key: String,
+        location: String,
+        expected: String,
+        value: String,
+    },
+    #[error(
+        "Invalid default `{default}` for `{section}.{key}` in buckconfig schema: expected {expected}"
+    )]
+    InvalidDefault {
+        section: String,
+        key: String,
+        expected: String,
+        default: String,
+    },
+    #[error("Buckconfig schema declares `{section}.{key}` as `enum` but lists no `values`")]
+    EnumWithoutValues { section: String, key: String },
+}
+
+fn did_you_mean(suggestion: &Option<String>) -> String {
+    match suggestion {
+        Some(s) => format!(", did you mean `{}`?", s),
+        None => String::new(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ConfigKeyType {
+    String,
+    Bool,
+    Int,
+    List,
+    Enum,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigKeySchema {
+    #[serde(rename = "type")]
+    ty: ConfigKeyType,
+    /// Allowed values for `enum` keys.
+    #[serde(default)]
+    values: Vec<String>,
+    default: Option<String>,
+    /// If set, the key is deprecated, and this is the message shown when it is used.
+    deprecated: Option<String>,
+}
+
+impl ConfigKeySchema {
+    fn expected(&self) -> String {
+        match self.ty {
+            ConfigKeyType::String => "a string".to_owned(),
+            ConfigKeyType::Bool => "`true` or `false`".to_owned(),
+            ConfigKeyType::Int => "an integer".to_owned(),
+            ConfigKeyType::List => "a comma-separated list".to_owned(),
+            ConfigKeyType::Enum => format!(
+                "one of {}",
+                self.values.iter().map(|v| format!("`{}`", v)).join(", ")
+            ),
+        }
+    }
+
+    fn accepts(&self, value: &str) -> bool {
+        match self.ty {
+            ConfigKeyType::String | ConfigKeyType::List => true,
+            ConfigKeyType::Bool => value.parse::<bool>().is_ok(),
+            ConfigKeyType::Int => value.parse::<i64>().is_ok(),
+            ConfigKeyType::Enum => self.values.iter().any(|v| v == value),
+        }
+    }
+}
+
+/// Declared buckconfig keys of a cell, by section and key.
+#[derive(Debug, Default)]
+pub(crate) struct BuckConfigSchema {
+    sections: BTreeMap<String, BTreeMap<String, ConfigKeySchema>>,
+}
+
+impl BuckConfigSchema {
+    pub(crate) fn parse(content: &str) -> anyhow::Result<Self> {
+        let schema = BuckConfigSchema {
+            sections: serde_json::from_str(content)?,
+        };
+        for (section, keys) in &schema.sections {
+            for (key, key_schema) in keys {
+                if key_schema.ty == ConfigKeyType::Enum && key_schema.values.is_empty() {
+                    return Err(ConfigSchemaError::EnumWithoutValues {
+                        section: section.clone(),
+                        key: key.clone(),
+                    }
+                    .into());
+                }
+                if let Some(default) = &key_schema.default {
+                    if !key_schema.accepts(default) {
+                        return Err(ConfigSchemaError::InvalidDefault {
+                            section: section.clone(),
+                            key: key.clone(),
+                            expected: key_schema.expected(),
+                            default: default.clone(),
+                        }
+                        .into());
+                    }
+                }
+            }
+        }
+        Ok(schema)
+    }
+
+    /// Load the schema of the cell whose main config is in `cell_dir`, if it has one.
+    pub(crate) fn load(
+        cell_dir: &AbsNormPath,
+        file_ops: &dyn ConfigParserFileOps,
+    ) -> anyhow::Result<Option<Self>> {
+        let path = cell_dir.join_normalized(BUCKCONFIG_SCHEMA_FILE)?;
+        if !file_ops.file_exists(&path) {
+            return Ok(None);
+        }
+        let content: String = file_ops
+            .read_file_lines(&path)?
+            .collect::<Result<Vec<_>, _>>()?
+            .join("\n");
+        Ok(Some(Self::parse(&content).with_context(|| {
+            format!("Error parsing buckconfig schema `{}`", path)
+        })?))
+    }
+
+    /// Check the config against the schema. Unknown keys and mistyped values are errors,
+    /// deprecated keys are reported as warnings.
+    pub(crate) fn validate(&self, config: &LegacyBuckConfig) -> anyhow::Result<()> {
+        for (section, keys) in &self.sections {
+            let Some(config_section) = config.get_section(section) else {
+                continue;
+            };
+            for (key, value) in config_section.iter() {
+                let Some(key_schema) = keys.get(key) else {
+                    return Err(ConfigSchemaError::UnknownKey {
+                        section: section.clone(),
+                        key: key.to_owned(),
+                        location: value.location().to_string(),
+                        suggestion: keys
+                            .keys()
+                            .map(|k| (k, strsim::levenshtein(k, key)))
+                            .filter(|(_, distance)| *distance <= 3)
+                            .min_by_key(|(_, distance)| *distance)
+                            .map(|(k, _)| format!("{}.{}", section, k)),
+                    }
+                    .into());
+                };
+                if !key_schema.accepts(value.as_str()) {
+                    return Err(ConfigSchemaError::InvalidValue {
+                        section: section.clone(),
+                        key: key.to_owned(),
+                        location: value.location().to_string(),
+                        expected: key_schema.expected(),
+                        value: value.as_str().to_owned(),
+                    }
+                    .into());
+                }
+                if let Some(message) = &key_schema.deprecated {
+                    tracing::warn!(
+                        "Buckconfig `{}.{}` (defined {}) is deprecated: {}",
+                        section,
+                        key,
+                        value.location(),
+                        message
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The config with the declared defaults set for the keys it does not set.
+    pub(crate) fn apply_defaults(&self, config: &LegacyBuckConfig) -> LegacyBuckConfig {
+        config.with_defaults(self.sections.iter().flat_map(|(section, keys)| {
+            keys.iter().filter_map(move |(key, key_schema)| {
+                Some((
+                    section.as_str(),
+                    key.as_str(),
+                    key_schema.default.as_deref()?,
+                ))
+            })
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::legacy_configs::schema::BuckConfigSchema;
+    use crate::legacy_configs::testing::parse;
+    use crate::legacy_configs::LegacyBuckConfigLocation;
+
+    const SCHEMA: &str = indoc!(
+        r#"
+        {
+          "buck2": {
+            "materializations": {"type": "enum", "values": ["all", "deferred"], "default": "deferred"},
+            "sqlite_materializer_state": {"type": "bool"},
+            "old": {"type": "int", "deprecated": "do not use"}
+          }
+        }
+        "#
+    );
+
+    #[test]
+    fn test_valid_config() -> anyhow::Result<()> {
+        let schema = BuckConfigSchema::parse(SCHEMA)?;
+        let config = parse(
+            &[(
+                "/config",
+                indoc!(
+                    r#"
+                    [buck2]
+                        materializations = all
+                        sqlite_materializer_state = true
+                        old = 3
+                    [other]
+                        anything = goes
+                    "#
+                ),
+            )],
+            "/config",
+        )?;
+        schema.validate(&config)
+    }
+
+    #[test]
+    fn test_unknown_key_suggests() -> anyhow::Result<()> {
+        let schema = BuckConfigSchema::parse(SCHEMA)?;
+        let config = parse(
+            &[(
+                "/config",
+                indoc!(
+                    r#"
+                    [buck2]
+                        materialzations = all
+                    "#
+                ),
+            )],
+            "/config",
+        )?;
+        let err = format!("{:#}", schema.validate(&config).unwrap_err());
+        assert!(err.contains("buck2.materialzations"), "{}", err);
+        assert!(
+            err.contains("did you mean `buck2.materializations`?"),
+            "{}",
+            err
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_value() -> anyhow::Result<()> {
+        let schema = BuckConfigSchema::parse(SCHEMA)?;
+        let config = parse(
+            &[(
+                "/config",
+                indoc!(
+                    r#"
+                    [buck2]
+                        materializations = sometimes
+                    "#
+                ),
+            )],
+            "/config",
+        )?;
+        let err = format!("{:#}", schema.validate(&config).unwrap_err());
+        assert!(err.contains("one of `all`, `deferred`"), "{}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_defaults() -> anyhow::Result<()> {
+        let schema = BuckConfigSchema::parse(SCHEMA)?;
+        let config = parse(
+            &[(
+                "/config",
+                indoc!(
+                    r#"
+                    [buck2]
+                        sqlite_materializer_state = true
+                    "#
+                ),
+            )],
+            "/config",
+        )?;
+        assert_eq!(config.get("buck2", "materializations"), None);
+
+        let config = schema.apply_defaults(&config);
+        assert_eq!(config.get("buck2", "materializations"), Some("deferred"));
+        assert_eq!(
+            config.get("buck2", "sqlite_materializer_state"),
+            Some("true")
+        );
+        // No default declared.
+        assert_eq!(config.get("buck2", "old"), None);
+        assert_eq!(
+            config
+                .get_section("buck2")
+                .unwrap()
+                .get("materializations")
+                .unwrap()
+                .location(),
+            LegacyBuckConfigLocation::SchemaDefault
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_defaults_do_not_override() -> anyhow::Result<()> {
+        let schema = BuckConfigSchema::parse(SCHEMA)?;
+        let config = parse(
+            &[(
+                "/config",
+                indoc!(
+                    r#"
+                    [buck2]
+                        materializations = all
+                    "#
+                ),
+            )],
+            "/config",
+        )?;
+        let config = schema.apply_defaults(&config);
+        assert_eq!(config.get("buck2", "materializations"), Some("all"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_default() {
+        assert!(
+            BuckConfigSchema::parse(r#"{"a": {"b": {"type": "int", "default": "x"}}}"#).is_err()
+        );
+        assert!(BuckConfigSchema::parse(r#"{"a": {"b": {"type": "enum"}}}"#).is_err());
+    }
+}
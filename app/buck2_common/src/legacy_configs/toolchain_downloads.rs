@@ -0,0 +1,275 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Toolchains downloaded from URLs, rather than expected to be installed on the system.
+//!
+//! Downloads are declared in the root `.buckconfig`:
+//!
+//! ```ini
+//! [toolchain_downloads]
+//!   clang = https://example.com/clang-17.tar.xz
+//! ```
+//!
+//! `buck2 fetch-toolchains` downloads each of them once and records their hashes in the
+//! `toolchains.lock` file in the project root. This file is meant to be checked in, and included
+//! at the end of the root `.buckconfig`:
+//!
+//! ```ini
+//! <?file:toolchains.lock>
+//! ```
+//!
+//! It defines the `[toolchain_lock]` section, from which the `toolchain_download` macro of the
+//! prelude declares an `http_file` with the locked URL and hashes.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::io::Read;
+use std::process::Command;
+
+use anyhow::Context;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use sha1::Sha1;
+use sha2::Digest;
+use sha2::Sha256;
+
+use crate::legacy_configs::LegacyBuckConfig;
+
+/// Lockfile recording the hashes of toolchain downloads, relative to the project root.
+pub const TOOLCHAINS_LOCKFILE: &str = "toolchains.lock";
+
+/// Section of the root config declaring toolchain downloads.
+const DOWNLOADS_SECTION: &str = "toolchain_downloads";
+
+/// Section of the lockfile recording the locked downloads.
+const LOCK_SECTION: &str = "toolchain_lock";
+
+/// Directory where toolchains are downloaded to while they are hashed.
+const DOWNLOAD_DIR: &str = "buck-out/toolchain_downloads";
+
+#[derive(Debug, buck2_error::Error)]
+enum ToolchainDownloadsError {
+    #[error("Downloading toolchain `{name}` from `{url}` failed:\n{stderr}")]
+    DownloadFailed {
+        name: String,
+        url: String,
+        stderr: String,
+    },
+    #[error("Invalid toolchain name `{0}`, expected letters, digits, `_` and `-`")]
+    InvalidName(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockedToolchainDownload {
+    pub url: String,
+    pub sha1: String,
+    pub sha256: String,
+}
+
+impl LockedToolchainDownload {
+    /// The entry for `name` in the `[toolchain_lock]` section, if it is complete.
+    fn from_config(config: &LegacyBuckConfig, name: &str) -> Option<Self> {
+        let get = |key: &str| {
+            config
+                .get(LOCK_SECTION, &format!("{}.{}", name, key))
+                .map(str::to_owned)
+        };
+        Some(Self {
+            url: get("url")?,
+            sha1: get("sha1")?,
+            sha256: get("sha256")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolchainLockStatus {
+    /// The lockfile already had this download, for the same URL.
+    Unchanged,
+    /// The toolchain was downloaded and its hashes recorded.
+    Locked,
+}
+
+fn validate_name(name: &str) -> anyhow::Result<()> {
+    if name.is_empty()
+        || !name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+    {
+        return Err(ToolchainDownloadsError::InvalidName(name.to_owned()).into());
+    }
+    Ok(())
+}
+
+fn hash_file(path: &AbsNormPath) -> anyhow::Result<(String, String)> {
+    let mut file = fs_util::open_file(path)?;
+    let mut sha1 = Sha1::new();
+    let mut sha256 = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        sha1.update(&buf[..n]);
+        sha256.update(&buf[..n]);
+    }
+    Ok((hex::encode(sha1.finalize()), hex::encode(sha256.finalize())))
+}
+
+fn fetch(project_fs: &ProjectRoot, name: &str, url: &str) -> anyhow::Result<(String, String)> {
+    let dir = project_fs.resolve(ProjectRelativePath::unchecked_new(DOWNLOAD_DIR));
+    fs_util::create_dir_all(&dir)?;
+    let path = dir.join(ForwardRelativePath::new(name)?);
+
+    let output = Command::new("curl")
+        .args([
+            "--fail",
+            "--silent",
+            "--show-error",
+            "--location",
+            "--output",
+        ])
+        .arg(path.as_path())
+        .arg(url)
+        .output()
+        .context("Error running `curl`")?;
+    if !output.status.success() {
+        return Err(ToolchainDownloadsError::DownloadFailed {
+            name: name.to_owned(),
+            url: url.to_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }
+        .into());
+    }
+
+    let hashes = hash_file(&path)?;
+    fs_util::remove_file(&path)?;
+    Ok(hashes)
+}
+
+fn format_lockfile(locked: &BTreeMap<String, LockedToolchainDownload>) -> String {
+    let mut out = String::new();
+    out.push_str("# Generated by `buck2 fetch-toolchains`. Do not edit.\n");
+    let _ = writeln!(out, "[{}]", LOCK_SECTION);
+    for (name, download) in locked {
+        let _ = writeln!(out, "  {}.url = {}", name, download.url);
+        let _ = writeln!(out, "  {}.sha1 = {}", name, download.sha1);
+        let _ = writeln!(out, "  {}.sha256 = {}", name, download.sha256);
+    }
+    out
+}
+
+/// Lock the toolchain downloads declared in the root config, downloading those that are not in
+/// the lockfile yet, or whose URL changed, or all of them if `update` is set.
+///
+/// The current lockfile is read from the root config, so it must be included from there.
+pub fn lock_toolchain_downloads(
+    project_fs: &ProjectRoot,
+    root_config: &LegacyBuckConfig,
+    update: bool,
+) -> anyhow::Result<Vec<(String, ToolchainLockStatus)>> {
+    let mut locked = BTreeMap::new();
+    let mut statuses = Vec::new();
+
+    if let Some(downloads) = root_config.get_section(DOWNLOADS_SECTION) {
+        for (name, url) in downloads.iter() {
+            validate_name(name)?;
+            let url = url.as_str();
+
+            let (download, status) = match LockedToolchainDownload::from_config(root_config, name) {
+                Some(download) if !update && download.url == url => {
+                    (download, ToolchainLockStatus::Unchanged)
+                }
+                _ => {
+                    let (sha1, sha256) = fetch(project_fs, name, url)?;
+                    let download = LockedToolchainDownload {
+                        url: url.to_owned(),
+                        sha1,
+                        sha256,
+                    };
+                    (download, ToolchainLockStatus::Locked)
+                }
+            };
+            locked.insert(name.to_owned(), download);
+            statuses.push((name.to_owned(), status));
+        }
+    }
+
+    fs_util::write(
+        project_fs.resolve(ProjectRelativePath::unchecked_new(TOOLCHAINS_LOCKFILE)),
+        format_lockfile(&locked),
+    )?;
+
+    Ok(statuses)
+}
+
+/// Whether the root config includes the lockfile.
+pub fn includes_toolchains_lockfile(root_config: &LegacyBuckConfig) -> bool {
+    root_config.get_section(LOCK_SECTION).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use indoc::indoc;
+
+    use crate::legacy_configs::testing::parse;
+    use crate::legacy_configs::toolchain_downloads::format_lockfile;
+    use crate::legacy_configs::toolchain_downloads::validate_name;
+    use crate::legacy_configs::toolchain_downloads::LockedToolchainDownload;
+
+    #[test]
+    fn test_lockfile_round_trip() -> anyhow::Result<()> {
+        let clang = LockedToolchainDownload {
+            url: "https://example.com/clang.tar.xz?v=1".to_owned(),
+            sha1: "a".repeat(40),
+            sha256: "b".repeat(64),
+        };
+        let lockfile = format_lockfile(&BTreeMap::from([("clang".to_owned(), clang.clone())]));
+        let config = parse(&[("/config", &lockfile)], "/config")?;
+        assert_eq!(
+            Some(clang),
+            LockedToolchainDownload::from_config(&config, "clang")
+        );
+        assert_eq!(None, LockedToolchainDownload::from_config(&config, "gcc"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_incomplete_lock_entry() -> anyhow::Result<()> {
+        let config = parse(
+            &[(
+                "/config",
+                indoc!(
+                    r#"
+                    [toolchain_lock]
+                        clang.url = https://example.com/clang.tar.xz
+                        clang.sha1 = aaaa
+                    "#
+                ),
+            )],
+            "/config",
+        )?;
+        assert_eq!(None, LockedToolchainDownload::from_config(&config, "clang"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("clang-17_x86").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("a.b").is_err());
+        assert!(validate_name("a/b").is_err());
+    }
+}
@@ -25,11 +25,13 @@ pub mod argv;
 pub mod buckd_connection;
 pub mod cas_digest;
 pub mod client_utils;
+pub mod compression;
 pub mod convert;
 pub mod daemon_dir;
 pub mod dice;
 pub mod events;
 pub mod external_symlink;
+pub mod external_symlink_policy;
 pub mod file_ops;
 pub mod find_buildfile;
 pub mod home_buck_tmp;
@@ -49,3 +51,4 @@ pub mod pattern;
 pub mod sqlite;
 pub mod target_aliases;
 pub mod temp_path;
+pub mod vcs;
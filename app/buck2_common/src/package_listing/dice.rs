@@ -24,6 +24,7 @@ use dupe::Dupe;
 use smallvec::SmallVec;
 
 use crate::dice::cells::HasCellResolver;
+use crate::dice::data::HasCaseSensitivity;
 use crate::dice::file_ops::HasFileOps;
 use crate::package_listing::interpreter::InterpreterPackageListingResolver;
 use crate::package_listing::listing::PackageListing;
@@ -86,8 +87,13 @@ impl Key for PackageListingKey {
 
         let cell_resolver = ctx.get_cell_resolver().await?;
         let file_ops = ctx.file_ops();
+        let case_insensitive = ctx
+            .global_data()
+            .get_case_sensitivity()
+            .sources_case_insensitive;
         let (result, spans) = async_record_root_spans(
             InterpreterPackageListingResolver::new(cell_resolver, Arc::new(file_ops))
+                .with_case_insensitive(case_insensitive)
                 .resolve(self.0.dupe()),
         )
         .await;
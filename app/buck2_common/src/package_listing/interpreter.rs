@@ -13,6 +13,7 @@ use async_trait::async_trait;
 use buck2_core::cells::cell_path::CellPath;
 use buck2_core::cells::cell_path::CellPathRef;
 use buck2_core::cells::CellResolver;
+use buck2_core::fs::case_insensitive::CaseInsensitivePaths;
 use buck2_core::fs::paths::file_name::FileNameBuf;
 use buck2_core::package::package_relative_path::PackageRelativePath;
 use buck2_core::package::PackageLabel;
@@ -36,6 +37,10 @@ enum PackageListingError {
     NoBuildFile(CellPath, Vec<FileNameBuf>),
     #[error("Expected `{0}` to be within a package directory, but there was no buildfile in any parent directories. Expected one of `{}`", .1.join("`, `"))]
     NoContainingPackage(CellPath, Vec<FileNameBuf>),
+    #[error(
+        "Package `{0}` contains `{1}` and `{2}`, which differ only in case and collide on a case-insensitive filesystem"
+    )]
+    CaseInsensitiveConflict(PackageLabel, String, String),
 }
 
 #[async_trait]
@@ -101,11 +106,24 @@ impl<'c> PackageListingResolver for InterpreterPackageListingResolver<'c> {
 pub struct InterpreterPackageListingResolver<'c> {
     cell_resolver: CellResolver,
     fs: Arc<dyn FileOps + 'c>,
+    /// Whether to reject packages containing paths that differ only in case.
+    case_insensitive: bool,
 }
 
 impl<'c> InterpreterPackageListingResolver<'c> {
     pub fn new(cell_resolver: CellResolver, fs: Arc<dyn FileOps + 'c>) -> Self {
-        Self { cell_resolver, fs }
+        Self {
+            cell_resolver,
+            fs,
+            case_insensitive: false,
+        }
+    }
+
+    /// Reject packages containing paths that differ only in case, which collide when the sources
+    /// are on a case-insensitive filesystem.
+    pub fn with_case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
     }
 
     pub async fn gather_package_listing<'a>(
@@ -130,15 +148,29 @@ impl<'c> InterpreterPackageListingResolver<'c> {
             .user()?;
 
         let mut work = FuturesUnordered::new();
+        // Only track paths when needed: this is on the hot path of every package listing.
+        let mut case_insensitive_paths = self.case_insensitive.then(CaseInsensitivePaths::new);
 
         let root = &root;
-        let process_entries = |work: &mut FuturesUnordered<_>,
-                               files: &mut Vec<ArcS<PackageRelativePath>>,
-                               path: &PackageRelativePath,
-                               entries: &[SimpleDirEntry]|
+        let mut process_entries = |work: &mut FuturesUnordered<_>,
+                                   files: &mut Vec<ArcS<PackageRelativePath>>,
+                                   path: &PackageRelativePath,
+                                   entries: &[SimpleDirEntry]|
          -> anyhow::Result<()> {
             for d in entries {
                 let child_path = path.join(&d.file_name).to_arc();
+                if let Some(case_insensitive_paths) = &mut case_insensitive_paths {
+                    if let Some(conflict) =
+                        case_insensitive_paths.insert(child_path.as_forward_rel_path())
+                    {
+                        return Err(PackageListingError::CaseInsensitiveConflict(
+                            root.dupe(),
+                            conflict.to_string(),
+                            child_path.to_string(),
+                        )
+                        .into());
+                    }
+                }
                 if d.file_type.is_dir() {
                     work.push(async move {
                         let entries = self
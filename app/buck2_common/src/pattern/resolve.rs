@@ -7,7 +7,11 @@
  * of this source tree.
  */
 
+use std::sync::Arc;
+use std::sync::Mutex;
+
 use anyhow::Context;
+use buck2_core::cells::cell_path::CellPath;
 use buck2_core::cells::CellResolver;
 use buck2_core::package::PackageLabel;
 use buck2_core::pattern::display_precise_pattern;
@@ -16,12 +20,16 @@ use buck2_core::pattern::pattern_type::PatternType;
 use buck2_core::pattern::PackageSpec;
 use buck2_core::pattern::ParsedPattern;
 use buck2_core::target::name::TargetName;
+use dice::DiceTransaction;
 use dupe::Dupe;
+use futures::Stream;
+use futures::StreamExt;
 use gazebo::prelude::VecExt;
 use indexmap::IndexMap;
 
 use crate::file_ops::FileOps;
 use crate::pattern::package_roots::find_package_roots;
+use crate::pattern::package_roots::find_package_roots_stream;
 
 #[derive(Debug, buck2_error::Error)]
 enum ResolvedPatternError {
@@ -67,33 +75,54 @@ impl ResolvedPattern<ConfiguredProvidersPatternExtra> {
     pub fn convert_pattern<U: PatternType>(self) -> anyhow::Result<ResolvedPattern<U>> {
         let mut specs = IndexMap::with_capacity(self.specs.len());
         for (package, spec) in self.specs {
-            let spec = match spec {
-                PackageSpec::Targets(targets) => {
-                    PackageSpec::Targets(targets.into_try_map(|(target_name, extra)| {
-                        let extra = U::from_configured_providers(extra.clone()).context(
-                            ResolvedPatternError::InvalidPattern(
-                                U::NAME,
-                                display_precise_pattern(&package, target_name.as_ref(), &extra)
-                                    .to_string(),
-                            ),
-                        )?;
-                        anyhow::Ok((target_name, extra))
-                    })?)
-                }
-                PackageSpec::All => PackageSpec::All,
-            };
+            let spec = convert_package_spec(&package, spec)?;
             specs.insert(package, spec);
         }
         Ok(ResolvedPattern { specs })
     }
 }
 
+/// Converts the spec of a single package, see [`ResolvedPattern::convert_pattern`].
+pub fn convert_package_spec<U: PatternType>(
+    package: &PackageLabel,
+    spec: PackageSpec<ConfiguredProvidersPatternExtra>,
+) -> anyhow::Result<PackageSpec<U>> {
+    Ok(match spec {
+        PackageSpec::Targets(targets) => {
+            PackageSpec::Targets(targets.into_try_map(|(target_name, extra)| {
+                let extra = U::from_configured_providers(extra.clone()).context(
+                    ResolvedPatternError::InvalidPattern(
+                        U::NAME,
+                        display_precise_pattern(package, target_name.as_ref(), &extra).to_string(),
+                    ),
+                )?;
+                anyhow::Ok((target_name, extra))
+            })?)
+        }
+        PackageSpec::All => PackageSpec::All,
+    })
+}
+
 /// Resolves a list of [ParsedPattern] to a [ResolvedPattern].
 pub async fn resolve_target_patterns<P: PatternType>(
     cell_resolver: &CellResolver,
     patterns: &[ParsedPattern<P>],
     file_ops: &dyn FileOps,
 ) -> anyhow::Result<ResolvedPattern<P>> {
+    // Walk the directories of all recursive patterns at once, rather than one pattern after the
+    // other, but still add their packages in the order of the patterns.
+    let mut recursive_roots =
+        futures::future::try_join_all(patterns.iter().filter_map(|pattern| match pattern {
+            ParsedPattern::Recursive(cell_path) => Some(async move {
+                find_package_roots(cell_path.clone(), file_ops, cell_resolver)
+                    .await
+                    .context("Error resolving recursive target pattern.")
+            }),
+            _ => None,
+        }))
+        .await?
+        .into_iter();
+
     let mut resolved = ResolvedPattern::new();
     for pattern in patterns {
         match pattern {
@@ -103,11 +132,8 @@ pub async fn resolve_target_patterns<P: PatternType>(
             ParsedPattern::Package(package) => {
                 resolved.add_package(package.dupe());
             }
-            ParsedPattern::Recursive(cell_path) => {
-                let roots = find_package_roots(cell_path.clone(), file_ops, cell_resolver)
-                    .await
-                    .context("Error resolving recursive target pattern.")?;
-                for package in roots {
+            ParsedPattern::Recursive(_) => {
+                for package in recursive_roots.next().into_iter().flatten() {
                     resolved.add_package(package);
                 }
             }
@@ -116,6 +142,67 @@ pub async fn resolve_target_patterns<P: PatternType>(
     Ok(resolved)
 }
 
+/// Resolves a list of [ParsedPattern] to a stream of packages and what to load from each of
+/// them, so that callers can start loading packages while recursive patterns are still being
+/// walked.
+///
+/// Each package is yielded once, with the same spec as [`resolve_target_patterns`] would give it,
+/// but in no particular order. Explicit packages that are not under a recursive pattern come
+/// first, and those that are only come after the walk, unless the walk finds them.
+pub fn stream_target_patterns<P: PatternType>(
+    ctx: &DiceTransaction,
+    patterns: Vec<ParsedPattern<P>>,
+) -> impl Stream<Item = anyhow::Result<(PackageLabel, PackageSpec<P>)>> {
+    let mut spec = ResolvedPattern::<P>::new();
+    let mut recursive_paths: Vec<CellPath> = Vec::new();
+
+    for pattern in patterns {
+        match pattern {
+            ParsedPattern::Target(package, target_name, extra) => {
+                spec.add_target(package, target_name, extra);
+            }
+            ParsedPattern::Package(package) => {
+                spec.add_package(package);
+            }
+            ParsedPattern::Recursive(cell_path) => {
+                recursive_paths.push(cell_path);
+            }
+        }
+    }
+
+    // A recursive pattern includes all the targets of the packages it finds, so explicit
+    // packages that it may find wait for the walk to be done.
+    let (deferred, ready): (IndexMap<_, _>, IndexMap<_, _>) =
+        spec.specs.into_iter().partition(|(package, _)| {
+            recursive_paths
+                .iter()
+                .any(|path| package.as_cell_path().starts_with(path.as_ref()))
+        });
+    let deferred = Arc::new(Mutex::new(deferred));
+
+    let found = {
+        let deferred = deferred.dupe();
+        find_package_roots_stream(ctx, recursive_paths).map(move |package| {
+            let package = package?;
+            deferred.lock().unwrap().shift_remove(&package);
+            Ok((package, PackageSpec::All))
+        })
+    };
+    // Lazy, so that this only runs once the walk is done.
+    let remaining = futures::stream::once(futures::future::lazy(move |_| {
+        futures::stream::iter(
+            std::mem::take(&mut *deferred.lock().unwrap())
+                .into_iter()
+                .map(Ok),
+        )
+    }))
+    .flatten();
+
+    futures::stream::iter(ready.into_iter().map(Ok))
+        .chain(found)
+        .chain(remaining)
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::BTreeMap;
@@ -0,0 +1,367 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Source control of the project, for the features which depend on revisions: the files changed
+//! since a revision (`buck2 impact --modified-since`, `buck2 test --impacted-by`) and the revision
+//! recorded in the invocation record.
+
+use std::time::Duration;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_util::process::async_background_command;
+
+#[derive(Debug, buck2_error::Error)]
+pub enum VcsError {
+    #[error("`{0}` failed with code '{1}' and error '{2}'")]
+    Command(String, i32, String),
+    #[buck2(user)]
+    #[error("The project root is not inside a repository (tried hg and git)")]
+    NoRepository,
+    #[error("`{0}` did not return the revision within {1:?}")]
+    Timeout(&'static str, Duration),
+    #[buck2(user)]
+    #[error("Invalid revision `{0}`: revisions cannot start with `-`")]
+    InvalidRevision(String),
+}
+
+/// The revision the working copy is based on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VcsState {
+    /// `hg`, `sl` or `git`.
+    pub vcs: &'static str,
+    pub revision: String,
+    /// Whether tracked files have uncommitted changes. Missing if source control did not answer
+    /// in time.
+    pub dirty: Option<bool>,
+}
+
+#[async_trait]
+pub trait Vcs: Send + Sync {
+    /// The program used, `hg`, `sl` or `git`.
+    fn name(&self) -> &'static str;
+
+    /// Files changed between `rev` and the working copy, limited to the files under the project
+    /// root and relative to it.
+    async fn changed_files(&self, rev: &str) -> anyhow::Result<Vec<String>>;
+
+    /// The revision the working copy is based on. Only reads the revision, so it doesn't look at
+    /// the working copy or take its locks.
+    async fn revision(&self) -> anyhow::Result<String>;
+
+    /// Whether tracked files have uncommitted changes. Doesn't look for untracked files, and
+    /// doesn't update the state source control keeps about the working copy.
+    async fn dirty(&self) -> anyhow::Result<bool>;
+}
+
+/// The revision and the dirty state of the working copy, queried concurrently. Each query is
+/// given up (and its command killed) after `timeout`, so that a slow or locked repository doesn't
+/// hold up the caller: the state is recorded without the dirty flag if only that query timed out.
+pub async fn state_with_timeout(vcs: &dyn Vcs, timeout: Duration) -> anyhow::Result<VcsState> {
+    let (revision, dirty) = futures::future::join(
+        tokio::time::timeout(timeout, vcs.revision()),
+        tokio::time::timeout(timeout, vcs.dirty()),
+    )
+    .await;
+    let revision = match revision {
+        Ok(revision) => revision?,
+        Err(_) => return Err(VcsError::Timeout(vcs.name(), timeout).into()),
+    };
+    let dirty = match dirty {
+        Ok(Ok(dirty)) => Some(dirty),
+        Ok(Err(e)) => {
+            tracing::debug!("Failed to get whether the working copy is dirty: {:#}", e);
+            None
+        }
+        Err(_) => None,
+    };
+    Ok(VcsState {
+        vcs: vcs.name(),
+        revision,
+        dirty,
+    })
+}
+
+/// Revisions are passed to source control as arguments, where a value starting with `-` would be
+/// parsed as an option.
+fn check_revision(rev: &str) -> anyhow::Result<()> {
+    if rev.starts_with('-') {
+        return Err(VcsError::InvalidRevision(rev.to_owned()).into());
+    }
+    Ok(())
+}
+
+/// Find the source control of the repository containing the project root, by looking for its
+/// directory in the project root and its ancestors.
+pub fn detect_vcs(project_root: &AbsNormPath) -> Option<Box<dyn Vcs>> {
+    let root = project_root.to_buf();
+    let mut dir = Some(project_root);
+    while let Some(d) = dir {
+        if d.as_path().join(".hg").exists() {
+            return Some(Box::new(Hg {
+                program: "hg",
+                root,
+            }));
+        }
+        if d.as_path().join(".sl").exists() {
+            return Some(Box::new(Hg {
+                program: "sl",
+                root,
+            }));
+        }
+        // A file rather than a directory in worktrees.
+        if d.as_path().join(".git").exists() {
+            return Some(Box::new(Git { root }));
+        }
+        dir = d.parent();
+    }
+    None
+}
+
+/// Mercurial, or Sapling which has the same interface.
+struct Hg {
+    program: &'static str,
+    /// The project root, where commands run.
+    root: AbsNormPathBuf,
+}
+
+#[async_trait]
+impl Vcs for Hg {
+    fn name(&self) -> &'static str {
+        self.program
+    }
+
+    async fn changed_files(&self, rev: &str) -> anyhow::Result<Vec<String>> {
+        check_revision(rev)?;
+        // Prints paths relative to the current directory, limited to the files under it.
+        let output = run(
+            &self.root,
+            self.program,
+            &[
+                "status",
+                "--no-status",
+                "-mard",
+                &format!("--rev={}", rev),
+                ".",
+            ],
+        )
+        .await?;
+        Ok(lines(&output))
+    }
+
+    async fn revision(&self) -> anyhow::Result<String> {
+        let revision = run(
+            &self.root,
+            self.program,
+            &["log", "-r", ".", "-T", "{node}"],
+        )
+        .await?;
+        Ok(revision.trim().to_owned())
+    }
+
+    async fn dirty(&self) -> anyhow::Result<bool> {
+        // Modified, added, removed and deleted files only: looking for unknown files is the
+        // expensive part of a status.
+        let status = run(&self.root, self.program, &["status", "-mard"]).await?;
+        Ok(!status.trim().is_empty())
+    }
+}
+
+struct Git {
+    /// The project root, where commands run.
+    root: AbsNormPathBuf,
+}
+
+#[async_trait]
+impl Vcs for Git {
+    fn name(&self) -> &'static str {
+        "git"
+    }
+
+    async fn changed_files(&self, rev: &str) -> anyhow::Result<Vec<String>> {
+        check_revision(rev)?;
+        // Prints paths relative to the current directory, limited to the files under it.
+        let output = run(
+            &self.root,
+            "git",
+            &[
+                "diff",
+                "--name-only",
+                "--relative",
+                "--end-of-options",
+                rev,
+                "--",
+            ],
+        )
+        .await?;
+        Ok(lines(&output))
+    }
+
+    async fn revision(&self) -> anyhow::Result<String> {
+        let revision = run(&self.root, "git", &["rev-parse", "HEAD"]).await?;
+        Ok(revision.trim().to_owned())
+    }
+
+    async fn dirty(&self) -> anyhow::Result<bool> {
+        // `GIT_OPTIONAL_LOCKS=0` (set by `run`) stops `status` from taking the index lock to
+        // refresh it, so it doesn't compete with the commands of the user.
+        let status = run(
+            &self.root,
+            "git",
+            &["status", "--porcelain", "--untracked-files=no"],
+        )
+        .await?;
+        Ok(!status.trim().is_empty())
+    }
+}
+
+fn lines(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_owned())
+        .collect()
+}
+
+async fn run(dir: &AbsNormPath, program: &str, args: &[&str]) -> anyhow::Result<String> {
+    let result = async_background_command(program)
+        .args(args)
+        .current_dir(dir.as_path())
+        .env("HGPLAIN", "1")
+        .env("GIT_OPTIONAL_LOCKS", "0")
+        // So that the command doesn't outlive a timeout.
+        .kill_on_drop(true)
+        .output()
+        .await
+        .with_context(|| format!("running `{}`", program))?;
+    if !result.status.success() {
+        // On Unix, `code()` will return `None` if the process was terminated by a signal.
+        let code = result.status.code().unwrap_or(1);
+        return Err(VcsError::Command(
+            format!("{} {}", program, args.join(" ")),
+            code,
+            String::from_utf8_lossy(&result.stderr).trim().to_owned(),
+        )
+        .into());
+    }
+    String::from_utf8(result.stdout).with_context(|| format!("`{}` output was not UTF-8", program))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use buck2_core::fs::fs_util;
+    use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+
+    use crate::vcs::detect_vcs;
+    use crate::vcs::state_with_timeout;
+    use crate::vcs::Vcs;
+
+    /// Answers after the delays.
+    struct SlowVcs {
+        revision_delay: Duration,
+        dirty_delay: Duration,
+    }
+
+    #[async_trait]
+    impl Vcs for SlowVcs {
+        fn name(&self) -> &'static str {
+            "slow"
+        }
+
+        async fn changed_files(&self, _rev: &str) -> anyhow::Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+
+        async fn revision(&self) -> anyhow::Result<String> {
+            tokio::time::sleep(self.revision_delay).await;
+            Ok("abc".to_owned())
+        }
+
+        async fn dirty(&self) -> anyhow::Result<bool> {
+            tokio::time::sleep(self.dirty_delay).await;
+            Ok(true)
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_state_with_timeout() {
+        let vcs = SlowVcs {
+            revision_delay: Duration::from_secs(1),
+            dirty_delay: Duration::from_secs(3),
+        };
+        let state = state_with_timeout(&vcs, Duration::from_secs(4))
+            .await
+            .unwrap();
+        assert_eq!(
+            ("slow", "abc", Some(true)),
+            (state.vcs, state.revision.as_str(), state.dirty)
+        );
+
+        // Only the dirty state timed out.
+        let state = state_with_timeout(&vcs, Duration::from_secs(2))
+            .await
+            .unwrap();
+        assert_eq!(("abc", None), (state.revision.as_str(), state.dirty));
+
+        let err = state_with_timeout(&vcs, Duration::from_millis(500))
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("did not return the revision"),
+            "{:#}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_detect_vcs() -> anyhow::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let root = AbsNormPathBuf::new(tempdir.path().to_owned())?;
+        let project = root.join_normalized("repo/project")?;
+        fs_util::create_dir_all(&project)?;
+
+        // The temporary directory may itself be inside a repository, so only check a repository
+        // closer to the project is found.
+        fs_util::create_dir_all(root.join_normalized("repo/.git")?)?;
+        assert_eq!(detect_vcs(&project).map(|v| v.name()), Some("git"));
+
+        fs_util::create_dir_all(root.join_normalized("repo/.sl")?)?;
+        assert_eq!(detect_vcs(&project).map(|v| v.name()), Some("sl"));
+
+        fs_util::create_dir_all(project.join_normalized(".hg")?)?;
+        assert_eq!(detect_vcs(&project).map(|v| v.name()), Some("hg"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_changed_files_rejects_options() -> anyhow::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let root = AbsNormPathBuf::new(tempdir.path().to_owned())?;
+        for dir in [".git", ".hg"] {
+            let project = root.join_normalized(dir.trim_start_matches('.'))?;
+            fs_util::create_dir_all(project.join_normalized(dir)?)?;
+            let vcs = detect_vcs(&project).unwrap();
+            let err = vcs
+                .changed_files("--output=/tmp/changed")
+                .await
+                .unwrap_err();
+            assert!(
+                err.to_string().contains("revisions cannot start with `-`"),
+                "{:#}",
+                err
+            );
+        }
+        Ok(())
+    }
+}
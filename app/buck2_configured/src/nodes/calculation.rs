@@ -15,7 +15,9 @@ use allocative::Allocative;
 use async_trait::async_trait;
 use buck2_build_api::actions::execute::dice_data::HasFallbackExecutorConfig;
 use buck2_build_api::transition::TRANSITION_CALCULATION;
+use buck2_common::dice::cells::HasCellResolver;
 use buck2_common::dice::cycles::CycleGuard;
+use buck2_common::legacy_configs::dice::HasLegacyConfigs;
 use buck2_core::cells::name::CellName;
 use buck2_core::configuration::compatibility::IncompatiblePlatformReason;
 use buck2_core::configuration::compatibility::IncompatiblePlatformReasonCause;
@@ -50,6 +52,7 @@ use buck2_node::attrs::internal::TARGET_COMPATIBLE_WITH_ATTRIBUTE_FIELD;
 use buck2_node::configuration::resolved::ConfigurationSettingKeyRef;
 use buck2_node::configuration::resolved::ResolvedConfiguration;
 use buck2_node::configuration::toolchain_constraints::ToolchainConstraints;
+use buck2_node::dependency_policy::DependencyPolicy;
 use buck2_node::nodes::configured::ConfiguredTargetNode;
 use buck2_node::nodes::configured_frontend::ConfiguredTargetNodeCalculation;
 use buck2_node::nodes::configured_frontend::ConfiguredTargetNodeCalculationImpl;
@@ -588,6 +591,45 @@ async fn check_plugin_deps(
     Ok(())
 }
 
+async fn get_dependency_policy(ctx: &DiceComputations) -> anyhow::Result<Arc<DependencyPolicy>> {
+    #[derive(Clone, Display, Debug, Dupe, Eq, Hash, PartialEq, Allocative)]
+    #[display(fmt = "DependencyPolicyKey")]
+    struct DependencyPolicyKey;
+
+    #[async_trait]
+    impl Key for DependencyPolicyKey {
+        type Value = buck2_error::Result<Arc<DependencyPolicy>>;
+        async fn compute(
+            &self,
+            ctx: &mut DiceComputations,
+            _cancellation: &CancellationContext,
+        ) -> Self::Value {
+            // Like the target platform detector, policies are read off the root cell's config, so
+            // that they apply to the whole graph.
+            let resolver = ctx.get_cell_resolver().await?;
+            let root_cell = resolver.root_cell();
+            let config = ctx.get_legacy_config_for_cell(root_cell).await?;
+            Ok(Arc::new(match config.get_section("dependency_policy") {
+                None => DependencyPolicy::default(),
+                Some(section) => DependencyPolicy::parse(
+                    section.iter().map(|(name, value)| (name, value.as_str())),
+                    root_cell,
+                    &resolver,
+                )?,
+            }))
+        }
+
+        fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+            match (x, y) {
+                (Ok(x), Ok(y)) => x == y,
+                _ => false,
+            }
+        }
+    }
+
+    Ok(ctx.compute(&DependencyPolicyKey).await??)
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum CheckVisibility {
     Yes,
@@ -723,6 +765,11 @@ async fn gather_deps(
 
     let mut traversal = Traversal::default();
     for a in target_node.attrs(AttrInspectOptions::All) {
+        // Attributes which cannot have deps are only configured here to check that their
+        // selects resolve. Otherwise they are configured when they are inspected.
+        if !a.attr.coercer().0.may_have_deps && !a.value.has_selects() {
+            continue;
+        }
         let configured_attr = a.configure(attr_cfg_ctx)?;
         configured_attr.traverse(target_node.label().pkg(), &mut traversal)?;
     }
@@ -912,6 +959,17 @@ async fn compute_configured_target_node_no_transition(
         errors_and_incompats.unpack_dep_into(target_label, dep, *check_visibility, &mut exec_deps);
     }
 
+    let dependency_policy = get_dependency_policy(ctx).await?;
+    if !dependency_policy.is_empty() {
+        for dep in deps.iter().chain(&exec_deps) {
+            if let Err(e) =
+                dependency_policy.check(target_label.unconfigured(), dep.label().unconfigured())
+            {
+                errors_and_incompats.errs.push(e);
+            }
+        }
+    }
+
     if let Some(ret) = errors_and_incompats.finalize() {
         return ret;
     }
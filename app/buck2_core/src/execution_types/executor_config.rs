@@ -44,7 +44,7 @@ pub struct RemoteExecutorDependency {
     pub id: String,
 }
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone, Dupe, Display, Allocative)]
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone, Dupe, Display, Allocative)]
 pub struct RemoteExecutorUseCase(Intern<String>);
 
 impl RemoteExecutorUseCase {
@@ -0,0 +1,156 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Detection of paths that only differ in case.
+//!
+//! On a case-insensitive filesystem two paths such as `foo/Bar.h` and `foo/bar.h` refer to the
+//! same file. Writing both leaves whichever was written last, which makes builds
+//! nondeterministic. Whether a filesystem is case-insensitive depends on the volume (APFS and NTFS
+//! can be either), so it is detected at runtime rather than assumed from the OS.
+
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use allocative::Allocative;
+use anyhow::Context;
+use dupe::Dupe;
+
+use crate::fs::fs_util;
+use crate::fs::paths::abs_path::AbsPath;
+use crate::fs::paths::forward_rel_path::ForwardRelativePath;
+use crate::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+
+/// Whether the volumes holding the sources and the outputs are case-insensitive. Detected once
+/// when the daemon starts.
+#[derive(Clone, Copy, Dupe, Debug, Default, PartialEq, Eq, Allocative)]
+pub struct FilesystemCaseSensitivity {
+    /// The volume holding the project root.
+    pub sources_case_insensitive: bool,
+    /// The volume holding `buck-out`.
+    pub outputs_case_insensitive: bool,
+}
+
+impl FilesystemCaseSensitivity {
+    pub fn detect(project_root: &AbsPath, buck_out: &AbsPath) -> anyhow::Result<Self> {
+        Ok(Self {
+            sources_case_insensitive: is_case_insensitive_dir(project_root)?,
+            outputs_case_insensitive: is_case_insensitive_dir(buck_out)?,
+        })
+    }
+}
+
+/// Whether the filesystem holding `dir` is case-insensitive. This creates a file with an
+/// uppercase name in `dir` and checks whether it can be found under its lowercase name.
+pub fn is_case_insensitive_dir(dir: &AbsPath) -> anyhow::Result<bool> {
+    let unique = format!(
+        "{}-{}",
+        std::process::id(),
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos())
+    );
+    let upper = dir.join(format!(".BUCK2-CASE-PROBE-{}", unique));
+    let lower = dir.join(format!(".buck2-case-probe-{}", unique));
+
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&upper)
+        .with_context(|| format!("Error creating `{}`", upper.display()))?;
+    let result = fs_util::try_exists(&lower);
+    fs_util::remove_file(&upper)?;
+    result
+}
+
+/// A set of paths that reports paths that collide with an existing path when case is ignored.
+#[derive(Default, Allocative)]
+pub struct CaseInsensitivePaths {
+    /// Maps the lowercased path (and every prefix of it) to the first path inserted with it.
+    folded: HashMap<String, ForwardRelativePathBuf>,
+}
+
+impl CaseInsensitivePaths {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `path`. If `path` or one of its parent directories differs only in case from a
+    /// previously recorded path, return that previously recorded path (or directory).
+    pub fn insert(&mut self, path: &ForwardRelativePath) -> Option<ForwardRelativePathBuf> {
+        let mut prefix = ForwardRelativePathBuf::empty();
+        for component in path.iter() {
+            prefix.push(component);
+            match self.folded.get(&prefix.as_str().to_lowercase()) {
+                Some(existing) if existing != &prefix => return Some(existing.clone()),
+                Some(_) => {}
+                None => {
+                    self.folded
+                        .insert(prefix.as_str().to_lowercase(), prefix.clone());
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(s: &str) -> &ForwardRelativePath {
+        ForwardRelativePath::new(s).unwrap()
+    }
+
+    #[test]
+    fn test_same_path_is_not_a_conflict() {
+        let mut paths = CaseInsensitivePaths::new();
+        assert_eq!(None, paths.insert(path("foo/bar.h")));
+        assert_eq!(None, paths.insert(path("foo/bar.h")));
+        assert_eq!(None, paths.insert(path("foo/baz.h")));
+    }
+
+    #[test]
+    fn test_file_conflict() {
+        let mut paths = CaseInsensitivePaths::new();
+        assert_eq!(None, paths.insert(path("foo/Bar.h")));
+        assert_eq!(
+            Some(ForwardRelativePathBuf::unchecked_new(
+                "foo/Bar.h".to_owned()
+            )),
+            paths.insert(path("foo/bar.h"))
+        );
+    }
+
+    #[test]
+    fn test_is_case_insensitive_dir() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = AbsPath::new(tempdir.path()).unwrap();
+        // Probe the volume directly rather than assuming the answer from the OS.
+        let upper = dir.join("PROBE");
+        std::fs::write(&upper, "").unwrap();
+        let expected = dir.join("probe").exists();
+        std::fs::remove_file(&upper).unwrap();
+
+        assert_eq!(expected, is_case_insensitive_dir(dir).unwrap());
+        // The probe file is cleaned up.
+        assert_eq!(0, std::fs::read_dir(dir).unwrap().count());
+    }
+
+    #[test]
+    fn test_directory_conflict() {
+        let mut paths = CaseInsensitivePaths::new();
+        assert_eq!(None, paths.insert(path("Foo/a.h")));
+        assert_eq!(
+            Some(ForwardRelativePathBuf::unchecked_new("Foo".to_owned())),
+            paths.insert(path("foo/b.h"))
+        );
+    }
+}
@@ -109,16 +109,17 @@ fn symlink_impl(original: &Path, link: &AbsPath) -> anyhow::Result<()> {
         }
     };
 
+    let link = crate::fs::long_path::long_path_safe(link);
     let target_metadata = target_canonical.metadata();
     match target_metadata {
         Ok(meta) if meta.is_dir() => {
-            permission_check(std::os::windows::fs::symlink_dir(&target_canonical, link))
+            permission_check(std::os::windows::fs::symlink_dir(&target_canonical, &link))
         }
         Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
         _ => {
             // Either file or not existent. Default to file.
             // TODO(T144443238): This will cause issues if the file type turns out to be directory, fix this
-            permission_check(std::os::windows::fs::symlink_file(&target_canonical, link))
+            permission_check(std::os::windows::fs::symlink_file(&target_canonical, &link))
         }
     }
 }
@@ -239,7 +240,7 @@ pub fn try_exists<P: AsRef<AbsPath>>(path: P) -> anyhow::Result<bool> {
 
 pub fn remove_file<P: AsRef<AbsPath>>(path: P) -> anyhow::Result<()> {
     let _guard = IoCounterKey::Remove.guard();
-    remove_file_impl(path.as_ref().as_maybe_relativized())
+    remove_file_impl(&path.as_ref().as_maybe_relativized())
         .with_context(|| format!("remove_file({})", P::as_ref(&path).display()))
 }
 
@@ -612,6 +613,28 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_file_operations_with_length_over_max_path() -> anyhow::Result<()> {
+        let max_path = 260;
+
+        let tempdir = tempfile::tempdir()?;
+        let root = AbsPath::new(tempdir.path())?;
+        let dir = root.join("subdir/".repeat(max_path / 7));
+        let file = dir.join("file");
+        let renamed = dir.join("renamed");
+        assert!(file.to_str().unwrap().len() > max_path);
+
+        create_dir_all(&dir)?;
+        write(&file, b"contents")?;
+        assert_eq!(read_to_string(&file)?, "contents");
+        assert!(metadata(&file)?.is_file());
+        fs_util::rename(&file, &renamed)?;
+        assert_eq!(read_to_string(&renamed)?, "contents");
+        remove_file(&renamed)?;
+        assert!(!fs_util::try_exists(&renamed)?);
+        Ok(())
+    }
+
     #[test]
     fn test_symlink_with_target_length_over_max_path() -> anyhow::Result<()> {
         // In Windows, the maximum length of a path is 260.
@@ -0,0 +1,96 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Support for paths longer than `MAX_PATH` on Windows.
+//!
+//! Win32 file APIs reject paths longer than `MAX_PATH` (260 characters) unless they use the
+//! verbatim `\\?\` prefix, and deep `buck-out` paths easily exceed that. Verbatim paths are
+//! passed to the filesystem without normalization, so they must be absolute and use `\` as the
+//! separator.
+
+use std::borrow::Cow;
+use std::path::Path;
+
+/// Paths at least this long are converted to verbatim form. Directory creation is limited to
+/// `MAX_PATH - 12` characters (to leave room for an 8.3 file name), so use that as the limit.
+const LONG_PATH_THRESHOLD: usize = 260 - 12;
+
+const VERBATIM_PREFIX: &str = r"\\?\";
+const VERBATIM_UNC_PREFIX: &str = r"\\?\UNC\";
+
+/// Returns the verbatim form of an absolute Windows path, or `None` if the path is already
+/// verbatim, is not a drive or UNC path, or contains `.` or `..` components (which verbatim
+/// paths would not resolve).
+fn to_verbatim(path: &str) -> Option<String> {
+    if path.starts_with(VERBATIM_PREFIX) || path.starts_with(r"\\.\") {
+        return None;
+    }
+    let path = path.replace('/', r"\");
+    if path.split('\\').any(|c| c == "." || c == "..") {
+        return None;
+    }
+    if let Some(unc) = path.strip_prefix(r"\\") {
+        return Some(format!("{}{}", VERBATIM_UNC_PREFIX, unc));
+    }
+    let bytes = path.as_bytes();
+    if bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && bytes[2] == b'\\' {
+        return Some(format!("{}{}", VERBATIM_PREFIX, path));
+    }
+    None
+}
+
+/// Returns the verbatim form of `path` if it is too long to be used with the legacy Win32 APIs.
+fn to_verbatim_if_long(path: &str) -> Option<String> {
+    if path.len() < LONG_PATH_THRESHOLD {
+        return None;
+    }
+    to_verbatim(path)
+}
+
+/// Make an absolute path usable by filesystem APIs regardless of its length. This is a no-op
+/// on platforms other than Windows.
+pub(crate) fn long_path_safe(path: &Path) -> Cow<'_, Path> {
+    if cfg!(windows) {
+        if let Some(verbatim) = path.to_str().and_then(to_verbatim_if_long) {
+            return Cow::Owned(verbatim.into());
+        }
+    }
+    Cow::Borrowed(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_verbatim() {
+        assert_eq!(
+            Some(r"\\?\C:\foo\bar".to_owned()),
+            to_verbatim(r"C:\foo/bar")
+        );
+        assert_eq!(
+            Some(r"\\?\UNC\server\share\foo".to_owned()),
+            to_verbatim(r"\\server\share\foo")
+        );
+        assert_eq!(None, to_verbatim(r"\\?\C:\foo"));
+        assert_eq!(None, to_verbatim("/foo/bar"));
+        assert_eq!(None, to_verbatim(r"foo\bar"));
+        assert_eq!(None, to_verbatim(r"C:\foo\..\bar"));
+    }
+
+    #[test]
+    fn test_to_verbatim_if_long() {
+        assert_eq!(None, to_verbatim_if_long(r"C:\foo\bar"));
+        let long = format!(r"C:\repo\buck-out{}", r"\deep".repeat(60));
+        assert_eq!(
+            Some(format!(r"{}{}", VERBATIM_PREFIX, long)),
+            to_verbatim_if_long(&long)
+        );
+    }
+}
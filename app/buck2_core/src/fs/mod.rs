@@ -10,8 +10,10 @@
 pub mod artifact_path_resolver;
 pub mod async_fs_util;
 pub mod buck_out_path;
+pub mod case_insensitive;
 pub mod cwd;
 pub mod fs_util;
+pub mod long_path;
 pub mod paths;
 pub mod project;
 pub mod project_rel_path;
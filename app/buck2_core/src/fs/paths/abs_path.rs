@@ -8,6 +8,7 @@
  */
 
 use std::borrow::Borrow;
+use std::borrow::Cow;
 use std::ffi::OsString;
 use std::fmt;
 use std::ops::Deref;
@@ -20,6 +21,7 @@ use derive_more::Display;
 use ref_cast::RefCast;
 
 use crate::fs::cwd;
+use crate::fs::long_path;
 
 #[derive(buck2_error::Error, Debug)]
 enum AbsPathError {
@@ -153,8 +155,13 @@ impl AbsPath {
         self.0.ancestors().map(AbsPath::ref_cast)
     }
 
-    pub fn as_maybe_relativized(&self) -> &Path {
-        cwd::maybe_relativize(&self.0)
+    /// The path to pass to filesystem APIs: relative to the cwd if possible, and in a form that
+    /// supports long paths on Windows.
+    pub fn as_maybe_relativized(&self) -> Cow<'_, Path> {
+        match long_path::long_path_safe(&self.0) {
+            Cow::Borrowed(path) => Cow::Borrowed(cwd::maybe_relativize(path)),
+            Cow::Owned(path) => Cow::Owned(path),
+        }
     }
 
     pub fn as_maybe_relativized_str(&self) -> anyhow::Result<&str> {
@@ -10,6 +10,8 @@
 // Eden's Thrift API does sometime want &Vec<...>.
 #![allow(clippy::useless_vec)]
 
+use std::path::PathBuf;
+
 use allocative::Allocative;
 use anyhow::Context as _;
 use async_trait::async_trait;
@@ -367,6 +369,14 @@ impl IoProvider for EdenIoProvider {
         "eden"
     }
 
+    async fn read_external_path_metadata_if_exists(
+        &self,
+        path: PathBuf,
+    ) -> anyhow::Result<Option<RawPathMetadata<ProjectRelativePathBuf>>> {
+        // Paths outside the repo are not served by Eden.
+        self.fs.read_external_path_metadata_if_exists(path).await
+    }
+
     async fn eden_version(&self) -> anyhow::Result<Option<String>> {
         self.manager.get_eden_version().await
     }
@@ -21,6 +21,8 @@ use anyhow::Context as _;
 use async_compression::tokio::write::GzipEncoder;
 use async_compression::tokio::write::ZstdEncoder;
 use buck2_cli_proto::*;
+use buck2_common::compression::CompressionCodec;
+use buck2_common::compression::CompressionConfig;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::paths::abs_path::AbsPathBuf;
 use buck2_core::fs::working_dir::WorkingDir;
@@ -144,6 +146,7 @@ pub struct WriteEventLog<'a> {
     buf: Vec<u8>,
     log_size_counter_bytes: Option<Arc<AtomicU64>>,
     allow_vpnless: bool,
+    compression: CompressionConfig,
 }
 
 impl<'a> WriteEventLog<'a> {
@@ -157,6 +160,7 @@ impl<'a> WriteEventLog<'a> {
         command_name: String,
         log_size_counter_bytes: Option<Arc<AtomicU64>>,
         allow_vpnless: bool,
+        compression: CompressionConfig,
     ) -> anyhow::Result<Self> {
         Ok(Self {
             state: LogWriterState::Unopened {
@@ -171,6 +175,7 @@ impl<'a> WriteEventLog<'a> {
             buf: Vec::new(),
             log_size_counter_bytes,
             allow_vpnless,
+            compression,
         })
     }
 
@@ -266,7 +271,11 @@ impl<'a> WriteEventLog<'a> {
         // The event-log is going to be written to file containing the build uuid.
         // But we don't know the build uuid until we've gotten the CommandStart event.
         // So we'll just create it when we know where to put it.
-        let encoding = Encoding::PROTO_ZSTD;
+        let encoding = match self.compression.codec {
+            CompressionCodec::None => Encoding::PROTO,
+            CompressionCodec::Gzip => Encoding::PROTO_GZIP,
+            CompressionCodec::Zstd => Encoding::PROTO_ZSTD,
+        };
         let file_name = &get_logfile_name(event, encoding, &self.command_name)?;
         let path = EventLogPathBuf {
             path: logdir.as_abs_path().join(file_name),
@@ -277,6 +286,7 @@ impl<'a> WriteEventLog<'a> {
             event.trace_id()?.clone(),
             self.log_size_counter_bytes.clone(),
             self.allow_vpnless,
+            self.compression.level,
         )
         .await?;
         let mut writers = vec![writer];
@@ -373,6 +383,7 @@ async fn start_persist_subprocess(
     trace_id: TraceId,
     bytes_written: Option<Arc<AtomicU64>>,
     allow_vpnless: bool,
+    level: Option<i32>,
 ) -> anyhow::Result<NamedEventLogWriter> {
     let current_exe = std::env::current_exe().context("No current_exe")?;
     let mut command = buck2_util::process::async_background_command(current_exe);
@@ -409,7 +420,7 @@ async fn start_persist_subprocess(
         )
     })?;
     let pipe = child.stdin.take().expect("stdin was piped");
-    let mut writer = get_writer(path, pipe, bytes_written, EventLogType::System)?;
+    let mut writer = get_writer(path, pipe, bytes_written, EventLogType::System, level)?;
 
     // Only spawn this if we are going to wait.
     if block {
@@ -436,7 +447,7 @@ async fn open_event_log_for_writing(
             )
         })?;
 
-    get_writer(path, file, bytes_written, event_log_type)
+    get_writer(path, file, bytes_written, event_log_type, None)
 }
 
 fn get_writer(
@@ -444,16 +455,23 @@ fn get_writer(
     file: impl AsyncWrite + std::marker::Send + std::marker::Unpin + std::marker::Sync + 'static,
     bytes_written: Option<Arc<AtomicU64>>,
     event_log_type: EventLogType,
+    level: Option<i32>,
 ) -> Result<NamedEventLogWriter, anyhow::Error> {
     let file = match path.encoding.compression {
         Compression::None => Box::new(CountingReader::new(file, bytes_written)) as EventLogWriter,
         Compression::Gzip => Box::new(GzipEncoder::with_quality(
             CountingReader::new(file, bytes_written),
-            async_compression::Level::Fastest,
+            level.map_or(
+                async_compression::Level::Fastest,
+                async_compression::Level::Precise,
+            ),
         )) as EventLogWriter,
         Compression::Zstd => Box::new(ZstdEncoder::with_quality(
             CountingReader::new(file, bytes_written),
-            async_compression::Level::Default,
+            level.map_or(
+                async_compression::Level::Default,
+                async_compression::Level::Precise,
+            ),
         )) as EventLogWriter,
     };
     Ok(NamedEventLogWriter {
@@ -624,6 +642,7 @@ mod tests {
                 buf: Vec::new(),
                 log_size_counter_bytes: None,
                 allow_vpnless: false,
+                compression: CompressionConfig::ZSTD,
             })
         }
     }
@@ -339,7 +339,9 @@ pub fn display_event(event: &BuckEvent, opts: TargetDisplayOptions) -> anyhow::R
             Data::LocalResources(..) => Ok("Local resources setup".to_owned()),
             Data::ReleaseLocalResources(..) => Ok("Releasing local resources".to_owned()),
             Data::CreateOutputHashesFile(..) => Ok("Creating output hashes file".to_owned()),
-            Data::BxlEnsureArtifacts(..) => Err(ParseEventError::UnexpectedEvent.into()),
+            Data::BxlEnsureArtifacts(ensure) => {
+                Ok(format!("Ensuring {} artifacts", ensure.artifact_count))
+            }
             Data::ActionErrorHandlerExecution(..) => {
                 Ok("Running error handler on action failure".to_owned())
             }
@@ -531,6 +533,9 @@ pub fn format_test_result(test_result: &buck2_data::TestResult) -> anyhow::Resul
         TestStatus::UNKNOWN => Span::new_styled("? Unknown".to_owned().cyan()),
         TestStatus::RERUN => Span::new_styled("↻ Rerun".to_owned().cyan()),
         TestStatus::LISTING_FAILED => Span::new_styled("⚠ Listing failed".to_owned().red()),
+        TestStatus::MEMORY_LIMIT_EXCEEDED => {
+            Span::new_styled("⚠ Memory limit exceeded".to_owned().red())
+        }
     }?;
     let mut base = Line::from_iter([prefix, Span::new_unstyled(format!(": {}", name,))?]);
     if let Some(duration) = duration {
@@ -0,0 +1,140 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use buck2_data::DownloadProgress;
+
+/// A download which is running.
+#[derive(Debug)]
+pub struct ActiveDownload {
+    pub url: String,
+    pub bytes_downloaded: u64,
+    pub total_bytes: Option<u64>,
+    started: SystemTime,
+    updated: SystemTime,
+}
+
+impl ActiveDownload {
+    /// Average speed of the download so far, in bytes per second.
+    pub fn bytes_per_second(&self) -> u64 {
+        let elapsed = self
+            .updated
+            .duration_since(self.started)
+            .unwrap_or_default()
+            .as_secs_f64();
+        if elapsed > 0.0 {
+            (self.bytes_downloaded as f64 / elapsed) as u64
+        } else {
+            0
+        }
+    }
+
+    /// Estimated time until the download is over, if its size and speed are known.
+    pub fn remaining(&self) -> Option<Duration> {
+        let total_bytes = self.total_bytes?;
+        let speed = self.bytes_per_second();
+        if speed == 0 {
+            return None;
+        }
+        Some(Duration::from_secs(
+            total_bytes.saturating_sub(self.bytes_downloaded) / speed,
+        ))
+    }
+}
+
+/// Downloads from the network, e.g. by `download_file` actions.
+#[derive(Debug, Default)]
+pub struct DownloadState {
+    /// Running downloads, in the order they started.
+    active: BTreeMap<u64, ActiveDownload>,
+    /// Number and total size of the downloads which succeeded.
+    pub finished: u64,
+    pub finished_bytes: u64,
+}
+
+impl DownloadState {
+    pub fn update(&mut self, event_time: SystemTime, progress: &DownloadProgress) {
+        if progress.finished {
+            self.active.remove(&progress.id);
+            if progress.succeeded {
+                self.finished += 1;
+                self.finished_bytes += progress.bytes_downloaded;
+            }
+            return;
+        }
+        let download = self
+            .active
+            .entry(progress.id)
+            .or_insert_with(|| ActiveDownload {
+                url: progress.url.clone(),
+                bytes_downloaded: 0,
+                total_bytes: None,
+                started: event_time,
+                updated: event_time,
+            });
+        download.bytes_downloaded = progress.bytes_downloaded;
+        download.total_bytes = progress.total_bytes;
+        download.updated = event_time;
+    }
+
+    pub fn active(&self) -> impl ExactSizeIterator<Item = &ActiveDownload> {
+        self.active.values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(id: u64, bytes_downloaded: u64, finished: bool) -> DownloadProgress {
+        DownloadProgress {
+            id,
+            url: format!("https://example.com/{}", id),
+            bytes_downloaded,
+            total_bytes: Some(1000),
+            finished,
+            succeeded: finished,
+        }
+    }
+
+    #[test]
+    fn test_update() {
+        let start = SystemTime::UNIX_EPOCH;
+        let mut state = DownloadState::default();
+        state.update(start, &progress(1, 0, false));
+        state.update(start, &progress(2, 0, false));
+        state.update(start + Duration::from_secs(2), &progress(1, 200, false));
+
+        let active: Vec<_> = state.active().collect();
+        assert_eq!(2, active.len());
+        assert_eq!(100, active[0].bytes_per_second());
+        assert_eq!(Some(Duration::from_secs(8)), active[0].remaining());
+        assert_eq!(None, active[1].remaining());
+
+        state.update(start + Duration::from_secs(3), &progress(1, 1000, true));
+        assert_eq!(1, state.active().len());
+        assert_eq!(1, state.finished);
+        assert_eq!(1000, state.finished_bytes);
+
+        // Failed downloads are no longer active, but aren't counted.
+        state.update(
+            start + Duration::from_secs(4),
+            &DownloadProgress {
+                succeeded: false,
+                ..progress(2, 500, true)
+            },
+        );
+        assert_eq!(0, state.active().len());
+        assert_eq!(1, state.finished);
+        assert_eq!(1000, state.finished_bytes);
+    }
+}
@@ -17,6 +17,7 @@ use buck2_wrapper_common::invocation_id::TraceId;
 use crate::action_stats::ActionStats;
 use crate::debug_events::DebugEventsState;
 use crate::dice_state::DiceState;
+use crate::download_state::DownloadState;
 use crate::re_state::ReState;
 use crate::session_info::SessionInfo;
 use crate::span_tracker::BuckEventSpanTracker;
@@ -32,6 +33,7 @@ pub struct EventObserver<E> {
     session_info: SessionInfo,
     test_state: TestState,
     starlark_debugger_state: StarlarkDebuggerState,
+    download_state: DownloadState,
     /// When running without the Superconsole, we skip some state that we don't need. This might be
     /// premature optimization.
     extra: E,
@@ -54,6 +56,7 @@ where
             },
             test_state: TestState::default(),
             starlark_debugger_state: StarlarkDebuggerState::new(),
+            download_state: DownloadState::default(),
             extra: E::new(),
         }
     }
@@ -113,6 +116,9 @@ where
                             self.starlark_debugger_state
                                 .update(event.timestamp(), snapshot)?;
                         }
+                        DownloadProgress(progress) => {
+                            self.download_state.update(event.timestamp(), progress);
+                        }
                         TagEvent(tags) => {
                             if tags.tags.contains(&"which-dice:Modern".to_owned()) {
                                 self.session_info.modern_dice = true;
@@ -154,6 +160,10 @@ where
         &self.starlark_debugger_state
     }
 
+    pub fn download_state(&self) -> &DownloadState {
+        &self.download_state
+    }
+
     pub fn test_state(&self) -> &TestState {
         &self.test_state
     }
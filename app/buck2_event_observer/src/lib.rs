@@ -15,6 +15,7 @@ pub mod cache_hit_rate;
 pub mod debug_events;
 pub mod dice_state;
 pub mod display;
+pub mod download_state;
 pub mod event_observer;
 pub mod fmt_duration;
 pub mod humanized;
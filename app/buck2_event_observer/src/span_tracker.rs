@@ -508,8 +508,7 @@ pub fn is_span_shown(event: &BuckEvent) -> bool {
             Data::Command(..)
             | Data::CommandCritical(..)
             | Data::Materialization(..)
-            | Data::DiceCriticalSection(..)
-            | Data::BxlEnsureArtifacts(..),
+            | Data::DiceCriticalSection(..),
         ) => false,
         Some(
             Data::ActionExecution(..)
@@ -537,6 +536,7 @@ pub fn is_span_shown(event: &BuckEvent) -> bool {
             | Data::DynamicLambda(..)
             | Data::BxlExecution(..)
             | Data::BxlDiceInvocation(..)
+            | Data::BxlEnsureArtifacts(..)
             | Data::ReUpload(..)
             | Data::ConnectToInstaller(..)
             | Data::LocalResources(..)
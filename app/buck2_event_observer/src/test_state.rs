@@ -22,6 +22,7 @@ pub struct TestState {
     pub unknown: u64,
     pub listing_success: u64,
     pub listing_failed: u64,
+    pub memory_limit_exceeded: u64,
 }
 
 impl TestState {
@@ -38,6 +39,7 @@ impl TestState {
             TestStatus::RERUN => &mut self.retry,
             TestStatus::LISTING_SUCCESS => &mut self.listing_success,
             TestStatus::LISTING_FAILED => &mut self.listing_failed,
+            TestStatus::MEMORY_LIMIT_EXCEEDED => &mut self.memory_limit_exceeded,
         };
         *counter += 1;
 
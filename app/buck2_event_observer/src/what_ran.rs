@@ -246,6 +246,16 @@ pub fn emit_what_ran_entry(
             .iter()
             .last()
             .and_then(|cmd| cmd.details.as_ref().map(|d| d.stderr.as_ref())),
+        Some(buck2_data::span_end_event::Data::TestDiscovery(buck2_data::TestDiscoveryEnd {
+            command_report,
+            ..
+        }))
+        | Some(buck2_data::span_end_event::Data::TestEnd(buck2_data::TestRunEnd {
+            command_report,
+            ..
+        })) => command_report
+            .as_ref()
+            .and_then(|c| c.details.as_ref().map(|d| d.stderr.as_ref())),
         _ => None,
     };
     output.emit_command(WhatRanOutputCommand {
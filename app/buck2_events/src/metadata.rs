@@ -119,3 +119,77 @@ pub fn hostname() -> Option<String> {
     })
     .clone()
 }
+
+/// Environment variables recorded in the environment provenance: they commonly change the
+/// output or the performance of builds. Values of other variables are never recorded, as they
+/// may contain secrets.
+const PROVENANCE_ENV_VARS: &[&str] = &[
+    "ANDROID_HOME",
+    "ANDROID_NDK_HOME",
+    "ANDROID_SDK_ROOT",
+    "AR",
+    "CC",
+    "CFLAGS",
+    "CXX",
+    "CXXFLAGS",
+    "DEVELOPER_DIR",
+    "JAVA_HOME",
+    "LANG",
+    "LC_ALL",
+    "LDFLAGS",
+    "MACOSX_DEPLOYMENT_TARGET",
+    "PYTHONHASHSEED",
+    "RUSTFLAGS",
+    "SDKROOT",
+    "SOURCE_DATE_EPOCH",
+    "TZ",
+];
+
+/// The CPU model, as reported by the OS.
+fn cpu_model() -> Option<String> {
+    if cfg!(target_os = "linux") {
+        let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").ok()?;
+        cpuinfo.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == "model name").then(|| value.trim().to_owned())
+        })
+    } else if cfg!(target_os = "macos") {
+        let output = std::process::Command::new("sysctl")
+            .args(["-n", "machdep.cpu.brand_string"])
+            .output()
+            .ok()?;
+        let model = String::from_utf8(output.stdout).ok()?.trim().to_owned();
+        (!model.is_empty()).then_some(model)
+    } else {
+        None
+    }
+}
+
+/// Characteristics of this machine and of the environment of this process, to correlate
+/// builds against them.
+///
+/// Unlike `collect`, this is recorded in open source builds too.
+pub fn environment_provenance(
+    tool_versions: HashMap<String, String>,
+) -> buck2_data::EnvironmentProvenance {
+    static HOST: OnceLock<buck2_data::EnvironmentProvenance> = OnceLock::new();
+
+    let mut provenance = HOST
+        .get_or_init(|| buck2_data::EnvironmentProvenance {
+            os: os_type(),
+            os_version: sys_info::os_release().ok(),
+            cpu_arch: env::consts::ARCH.to_owned(),
+            cpu_model: cpu_model(),
+            cpu_count: sys_info::cpu_num().ok().map(u64::from),
+            total_memory_bytes: sys_info::mem_info().ok().map(|mem| mem.total * 1024),
+            env: HashMap::new(),
+            tool_versions: HashMap::new(),
+        })
+        .clone();
+    provenance.env = PROVENANCE_ENV_VARS
+        .iter()
+        .filter_map(|var| Some(((*var).to_owned(), env::var(var).ok()?)))
+        .collect();
+    provenance.tool_versions = tool_versions;
+    provenance
+}
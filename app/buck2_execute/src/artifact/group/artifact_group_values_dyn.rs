@@ -12,6 +12,8 @@ use buck2_core::fs::artifact_path_resolver::ArtifactFs;
 use crate::artifact::artifact_dyn::ArtifactDyn;
 use crate::artifact_value::ArtifactValue;
 use crate::directory::ActionDirectoryBuilder;
+use crate::directory::ActionSharedDirectory;
+use crate::execute::merged_directory_cache::SharedDirectoryKey;
 
 /// This is like `ArtifactGroupValues`, but without dependency on `Artifact`.
 pub trait ArtifactGroupValuesDyn: Send + Sync + 'static {
@@ -22,4 +24,7 @@ pub trait ArtifactGroupValuesDyn: Send + Sync + 'static {
         builder: &mut ActionDirectoryBuilder,
         artifact_fs: &ArtifactFs,
     ) -> anyhow::Result<()>;
+
+    /// The precomputed directory containing all the values and its key, if there is one.
+    fn directory(&self) -> Option<(&SharedDirectoryKey, &ActionSharedDirectory)>;
 }
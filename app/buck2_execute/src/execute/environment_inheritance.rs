@@ -8,6 +8,7 @@
  */
 
 use std::ffi::OsString;
+use std::sync::Arc;
 use std::sync::OnceLock;
 
 use dupe::Dupe;
@@ -57,10 +58,10 @@ const ENV_ALLOW_LIST: &[&str] = &[
     "WINDIR",
 ];
 
-#[derive(Copy, Clone, Dupe, Debug)]
+#[derive(Clone, Dupe, Debug)]
 pub struct EnvironmentInheritance {
     clear: bool,
-    values: &'static [(&'static str, OsString)],
+    values: Arc<[(String, OsString)]>,
     exclusions: &'static [&'static str],
 }
 
@@ -72,33 +73,41 @@ impl EnvironmentInheritance {
 
         // We create this *once* since getenv is actually not cheap (being O(n) of the environment
         // size).
-        static TEST_CELL: OnceLock<Vec<(&'static str, OsString)>> = OnceLock::new();
+        static TEST_CELL: OnceLock<Arc<[(String, OsString)]>> = OnceLock::new();
 
         let values = TEST_CELL.get_or_init(|| {
-            let mut ret = Vec::new();
-            for list in allowlists.iter() {
-                for key in list.iter() {
-                    if let Some(value) = std::env::var_os(key) {
-                        ret.push((*key, value));
-                    }
-                }
-            }
-            ret
+            Self::read_env(allowlists.iter().flat_map(|list| list.iter().copied())).into()
         });
 
         Self {
             clear: true,
-            values,
+            values: values.dupe(),
             exclusions: &[],
         }
     }
 
+    /// Like `test_allowlist`, but with an allowlist declared by the test rather than the default
+    /// one.
+    pub fn test_allowlist_from<'a>(allowlist: impl IntoIterator<Item = &'a str>) -> Self {
+        Self {
+            clear: true,
+            values: Self::read_env(allowlist).into(),
+            exclusions: &[],
+        }
+    }
+
+    fn read_env<'a>(keys: impl IntoIterator<Item = &'a str>) -> Vec<(String, OsString)> {
+        keys.into_iter()
+            .filter_map(|key| Some((key.to_owned(), std::env::var_os(key)?)))
+            .collect()
+    }
+
     /// Exclude some vars that are known to cause issues. In an ideal world we should do a
     /// migration to lock this down everywhere.
     pub fn local_command_exclusions() -> Self {
         Self {
             clear: false,
-            values: &[],
+            values: Arc::new([]),
             exclusions: &[
                 "PYTHONPATH",
                 "PYTHONHOME",
@@ -111,14 +120,14 @@ impl EnvironmentInheritance {
 
     pub fn empty() -> Self {
         Self {
-            values: &[],
+            values: Arc::new([]),
             exclusions: &[],
             clear: true,
         }
     }
 
-    pub fn values(&self) -> impl Iterator<Item = (&'static str, &'static OsString)> {
-        self.values.iter().map(|(k, v)| (*k, v))
+    pub fn values(&self) -> impl Iterator<Item = (&str, &OsString)> {
+        self.values.iter().map(|(k, v)| (k.as_str(), v))
     }
 
     pub fn exclusions(&self) -> impl Iterator<Item = &'static str> {
@@ -12,20 +12,28 @@ use buck2_core::directory::DirectoryEntry;
 use buck2_core::fs::artifact_path_resolver::ArtifactFs;
 use dupe::Dupe;
 
+use crate::digest_config::DigestConfig;
 use crate::directory::ActionDirectoryBuilder;
 use crate::directory::ActionDirectoryMember;
+use crate::execute::merged_directory_cache::MergedDirectoryCache;
 use crate::execute::request::CommandExecutionInput;
 
 pub fn inputs_directory(
     inputs: &[CommandExecutionInput],
     fs: &ArtifactFs,
+    digest_config: DigestConfig,
+    merged_directories: &MergedDirectoryCache,
 ) -> anyhow::Result<ActionDirectoryBuilder> {
     let mut builder = ActionDirectoryBuilder::empty();
+    // Precomputed directories (from transitive set projections) are merged on their own, so that
+    // the merged directories can be shared with other actions that have the same inputs.
+    let mut shared = Vec::new();
     for input in inputs {
         match input {
-            CommandExecutionInput::Artifact(group) => {
-                group.add_to_directory(&mut builder, fs)?;
-            }
+            CommandExecutionInput::Artifact(group) => match group.directory() {
+                Some(dir) => shared.push(dir),
+                None => group.add_to_directory(&mut builder, fs)?,
+            },
             CommandExecutionInput::ActionMetadata(metadata) => {
                 let path = fs.buck_out_path_resolver().resolve_gen(&metadata.path);
                 builder.insert(
@@ -42,5 +50,8 @@ pub fn inputs_directory(
             }
         };
     }
+    if let Some(shared) = merged_directories.merge(&shared, digest_config)? {
+        builder.merge(shared.into_builder())?;
+    }
     Ok(builder)
 }
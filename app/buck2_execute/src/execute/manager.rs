@@ -46,6 +46,9 @@ pub struct CommandExecutionManager {
     pub liveliness_observer: Arc<dyn LivelinessObserver>,
     pub intend_to_fallback_on_failure: bool,
     pub execution_kind: Option<CommandExecutionKind>,
+    /// Set when a cache hit for this command failed verification, so that the entry isn't used
+    /// again when executing it remotely.
+    pub skip_cache_read: bool,
 }
 
 impl CommandExecutionManager {
@@ -60,6 +63,7 @@ impl CommandExecutionManager {
             liveliness_observer,
             intend_to_fallback_on_failure: false,
             execution_kind: None,
+            skip_cache_read: false,
         }
     }
 
@@ -101,6 +105,11 @@ impl CommandExecutionManager {
         self.execution_kind = Some(execution_kind);
         self
     }
+
+    pub fn with_skip_cache_read(mut self, skip_cache_read: bool) -> Self {
+        self.skip_cache_read = skip_cache_read;
+        self
+    }
 }
 
 impl CommandExecutionManagerLike for CommandExecutionManager {
@@ -0,0 +1,316 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use allocative::Allocative;
+use buck2_common::file_ops::TrackedFileDigest;
+use buck2_core::directory::FingerprintedDirectory;
+use cmp_any::PartialEqAny;
+use dice::UserComputationData;
+use dupe::Dupe;
+
+use crate::digest_config::DigestConfig;
+use crate::directory::ActionSharedDirectory;
+use crate::directory::INTERNER;
+
+/// Identity of a precomputed directory, e.g. the key of the transitive set projection it was
+/// computed for.
+pub trait SharedDirectoryKeyDyn: Debug + Allocative + Send + Sync + 'static {
+    fn eq_token(&self) -> PartialEqAny;
+    fn hash(&self) -> u64;
+}
+
+#[derive(Debug, Clone, Dupe, Allocative)]
+pub struct SharedDirectoryKey(Arc<dyn SharedDirectoryKeyDyn>);
+
+impl SharedDirectoryKey {
+    pub fn new(key: impl SharedDirectoryKeyDyn) -> Self {
+        Self(Arc::new(key))
+    }
+}
+
+impl PartialEq for SharedDirectoryKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.eq_token() == other.0.eq_token()
+    }
+}
+
+impl Eq for SharedDirectoryKey {}
+
+impl Hash for SharedDirectoryKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash().hash(state)
+    }
+}
+
+#[derive(Default)]
+struct MergedDirectoryCacheData {
+    /// Fingerprint of the merged directory for a sequence of precomputed directories, and the
+    /// tick at which it was last used. The merged directories themselves are kept alive by the
+    /// actions using them, and are looked up in `INTERNER`.
+    entries: HashMap<Vec<SharedDirectoryKey>, (TrackedFileDigest, u64)>,
+    tick: u64,
+}
+
+/// Directories obtained by merging the precomputed directories of action inputs (transitive set
+/// projections), keyed by the keys of the directories that were merged.
+///
+/// Sibling actions taking the same projections as inputs merge the same directories. Caching the
+/// result lets those actions reuse the merged directory nodes instead of rebuilding and
+/// re-hashing them. This is owned by the daemon, and holds at most `capacity` entries: when full,
+/// the least recently used half is evicted.
+pub struct MergedDirectoryCache {
+    capacity: usize,
+    data: Mutex<MergedDirectoryCacheData>,
+}
+
+impl MergedDirectoryCache {
+    /// Default value of `buck2.merged_directory_cache_size`.
+    pub const DEFAULT_CAPACITY: usize = 100_000;
+
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            data: Mutex::new(MergedDirectoryCacheData::default()),
+        }
+    }
+
+    pub fn testing_new() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&self, keys: &[SharedDirectoryKey]) -> Option<ActionSharedDirectory> {
+        let mut data = self.data.lock().unwrap();
+        data.tick += 1;
+        let tick = data.tick;
+        let (fingerprint, used) = data.entries.get_mut(keys)?;
+        *used = tick;
+        INTERNER.get(fingerprint)
+    }
+
+    fn insert(&self, keys: Vec<SharedDirectoryKey>, directory: &ActionSharedDirectory) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut data = self.data.lock().unwrap();
+        if data.entries.len() >= self.capacity {
+            let mut ticks: Vec<u64> = data.entries.values().map(|(_, used)| *used).collect();
+            let evicted = (ticks.len() + 1) / 2;
+            let (_, cutoff, _) = ticks.select_nth_unstable(evicted - 1);
+            let cutoff = *cutoff;
+            data.entries.retain(|_, (_, used)| *used > cutoff);
+        }
+        data.tick += 1;
+        let tick = data.tick;
+        data.entries
+            .insert(keys, (directory.fingerprint().dupe(), tick));
+    }
+
+    /// Merge `directories` together, in order, reusing the result of previous merges of the same
+    /// directories (or of a prefix of them). Returns `None` if `directories` is empty.
+    pub fn merge(
+        &self,
+        directories: &[(&SharedDirectoryKey, &ActionSharedDirectory)],
+        digest_config: DigestConfig,
+    ) -> anyhow::Result<Option<ActionSharedDirectory>> {
+        let keys: Vec<SharedDirectoryKey> = directories.iter().map(|(k, _)| (*k).dupe()).collect();
+
+        // Find the longest prefix that was merged before.
+        let mut merged = None;
+        let mut done = 0;
+        for len in (2..=keys.len()).rev() {
+            if let Some(dir) = self.get(&keys[..len]) {
+                merged = Some(dir);
+                done = len;
+                break;
+            }
+        }
+
+        for (i, (_, dir)) in directories.iter().enumerate().skip(done) {
+            merged = Some(match merged {
+                None => (*dir).dupe(),
+                Some(merged) if merged.fingerprint() == dir.fingerprint() => merged,
+                Some(merged) => {
+                    let mut builder = merged.into_builder();
+                    builder.merge((*dir).dupe().into_builder())?;
+                    let merged = builder
+                        .fingerprint(digest_config.as_directory_serializer())
+                        .shared(&*INTERNER);
+                    self.insert(keys[..=i].to_vec(), &merged);
+                    merged
+                }
+            });
+        }
+
+        Ok(merged)
+    }
+}
+
+pub trait SetMergedDirectoryCache {
+    fn set_merged_directory_cache(&mut self, cache: Arc<MergedDirectoryCache>);
+}
+
+pub trait HasMergedDirectoryCache {
+    fn get_merged_directory_cache(&self) -> Arc<MergedDirectoryCache>;
+}
+
+impl SetMergedDirectoryCache for UserComputationData {
+    fn set_merged_directory_cache(&mut self, cache: Arc<MergedDirectoryCache>) {
+        self.data.set(cache);
+    }
+}
+
+impl HasMergedDirectoryCache for UserComputationData {
+    fn get_merged_directory_cache(&self) -> Arc<MergedDirectoryCache> {
+        self.data
+            .get::<Arc<MergedDirectoryCache>>()
+            .expect("MergedDirectoryCache should be set")
+            .dupe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_common::file_ops::FileMetadata;
+    use buck2_core::fs::project_rel_path::ProjectRelativePath;
+
+    use super::*;
+    use crate::directory::insert_file;
+    use crate::directory::ActionDirectoryBuilder;
+
+    #[derive(Debug, PartialEq, Eq, Hash, Allocative)]
+    struct TestKey(u32);
+
+    impl SharedDirectoryKeyDyn for TestKey {
+        fn eq_token(&self) -> PartialEqAny {
+            PartialEqAny::new(self)
+        }
+
+        fn hash(&self) -> u64 {
+            self.0 as u64
+        }
+    }
+
+    fn shared(files: &[&str]) -> anyhow::Result<ActionSharedDirectory> {
+        let digest_config = DigestConfig::testing_default();
+        let mut builder = ActionDirectoryBuilder::empty();
+        for file in files {
+            insert_file(
+                &mut builder,
+                ProjectRelativePath::new(file)?,
+                FileMetadata::empty(digest_config.cas_digest_config()),
+            )?;
+        }
+        Ok(builder
+            .fingerprint(digest_config.as_directory_serializer())
+            .shared(&*INTERNER))
+    }
+
+    #[test]
+    fn test_merge() -> anyhow::Result<()> {
+        let digest_config = DigestConfig::testing_default();
+        let cache = MergedDirectoryCache::testing_new();
+
+        let (ka, a) = (
+            SharedDirectoryKey::new(TestKey(1)),
+            shared(&["d1/f1", "d2/f2"])?,
+        );
+        let (kb, b) = (SharedDirectoryKey::new(TestKey(2)), shared(&["d1/f3"])?);
+        let (kc, c) = (SharedDirectoryKey::new(TestKey(3)), shared(&["d3/f4"])?);
+
+        assert!(cache.merge(&[], digest_config)?.is_none());
+        let single = cache.merge(&[(&ka, &a)], digest_config)?.unwrap();
+        assert!(single.ptr_eq(&a));
+        assert!(cache.is_empty());
+
+        let merged = cache
+            .merge(&[(&ka, &a), (&kb, &b)], digest_config)?
+            .unwrap();
+        let expected = shared(&["d1/f1", "d1/f3", "d2/f2"])?;
+        assert_eq!(merged.fingerprint(), expected.fingerprint());
+        assert_eq!(cache.len(), 1);
+
+        // The same merge is served from the cache, and returns the same directory.
+        let merged_again = cache
+            .merge(&[(&ka, &a), (&kb, &b)], digest_config)?
+            .unwrap();
+        assert!(merged.ptr_eq(&merged_again));
+        assert_eq!(cache.len(), 1);
+
+        // A longer sequence reuses the cached prefix, and caches its own result.
+        let merged = cache
+            .merge(&[(&ka, &a), (&kb, &b), (&kc, &c)], digest_config)?
+            .unwrap();
+        let expected = shared(&["d1/f1", "d1/f3", "d2/f2", "d3/f4"])?;
+        assert_eq!(merged.fingerprint(), expected.fingerprint());
+        assert_eq!(cache.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() -> anyhow::Result<()> {
+        let digest_config = DigestConfig::testing_default();
+        let cache = MergedDirectoryCache::new(2);
+
+        let (ka, a) = (SharedDirectoryKey::new(TestKey(1)), shared(&["a"])?);
+        let (kb, b) = (SharedDirectoryKey::new(TestKey(2)), shared(&["b"])?);
+        let (kc, c) = (SharedDirectoryKey::new(TestKey(3)), shared(&["c"])?);
+
+        let ab = cache
+            .merge(&[(&ka, &a), (&kb, &b)], digest_config)?
+            .unwrap();
+        cache.merge(&[(&kb, &b), (&kc, &c)], digest_config)?;
+        assert_eq!(cache.len(), 2);
+
+        // Use `ab` again so that `bc` is the least recently used entry.
+        let ab_again = cache
+            .merge(&[(&ka, &a), (&kb, &b)], digest_config)?
+            .unwrap();
+        assert!(ab.ptr_eq(&ab_again));
+
+        cache.merge(&[(&ka, &a), (&kc, &c)], digest_config)?;
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&[ka.dupe(), kb.dupe()]).is_some());
+        assert!(cache.get(&[kb.dupe(), kc.dupe()]).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_zero_capacity_disables_cache() -> anyhow::Result<()> {
+        let digest_config = DigestConfig::testing_default();
+        let cache = MergedDirectoryCache::new(0);
+
+        let (ka, a) = (SharedDirectoryKey::new(TestKey(1)), shared(&["a"])?);
+        let (kb, b) = (SharedDirectoryKey::new(TestKey(2)), shared(&["b"])?);
+
+        let merged = cache
+            .merge(&[(&ka, &a), (&kb, &b)], digest_config)?
+            .unwrap();
+        assert_eq!(merged.fingerprint(), shared(&["a", "b"])?.fingerprint());
+        assert!(cache.is_empty());
+
+        Ok(())
+    }
+}
@@ -20,6 +20,7 @@ pub mod environment_inheritance;
 pub mod inputs_directory;
 pub mod kind;
 pub mod manager;
+pub mod merged_directory_cache;
 pub mod output;
 pub mod paths_with_digest;
 pub mod prepared;
@@ -28,6 +28,7 @@ use derive_more::Display;
 use dupe::Dupe;
 use gazebo::variants::UnpackVariants;
 use host_sharing::host_sharing::HostSharingRequirements;
+use host_sharing::ResourceClass;
 use indexmap::IndexSet;
 use itertools::Itertools;
 use prost::Message;
@@ -43,6 +44,7 @@ use crate::directory::ActionDirectoryMember;
 use crate::directory::ActionImmutableDirectory;
 use crate::execute::environment_inheritance::EnvironmentInheritance;
 use crate::execute::inputs_directory::inputs_directory;
+use crate::execute::merged_directory_cache::MergedDirectoryCache;
 use crate::execute::paths_with_digest::PathsWithDigestBlobData;
 
 /// What protobuf messages can be stored in the action metadata blobs.
@@ -213,8 +215,9 @@ impl CommandExecutionPaths {
         outputs: IndexSet<CommandExecutionOutput>,
         fs: &ArtifactFs,
         digest_config: DigestConfig,
+        merged_directories: &MergedDirectoryCache,
     ) -> anyhow::Result<Self> {
-        let mut builder = inputs_directory(&inputs, fs)?;
+        let mut builder = inputs_directory(&inputs, fs, digest_config, merged_directories)?;
 
         let output_paths = outputs
             .iter()
@@ -295,6 +298,9 @@ pub struct CommandExecutionRequest {
     timeout: Option<Duration>,
     executor_preference: ExecutorPreference,
     host_sharing_requirements: HostSharingRequirements,
+    /// Resource class declared by the rule, if any. Otherwise the local executor classifies the
+    /// command itself.
+    resource_class: Option<ResourceClass>,
     // Used to disable the low pass filter for concurrent local actions. Enabled by default
     low_pass_filter: bool,
     /// Working directory, relative to the project root.
@@ -338,6 +344,7 @@ impl CommandExecutionRequest {
             timeout: None,
             executor_preference: ExecutorPreference::Default,
             host_sharing_requirements: HostSharingRequirements::default(),
+            resource_class: None,
             low_pass_filter: true,
             working_directory: None,
             prefetch_lossy_stderr: false,
@@ -374,6 +381,11 @@ impl CommandExecutionRequest {
         self
     }
 
+    pub fn with_resource_class(mut self, resource_class: Option<ResourceClass>) -> Self {
+        self.resource_class = resource_class;
+        self
+    }
+
     pub fn with_low_pass_filter(mut self, low_pass_filter: bool) -> Self {
         self.low_pass_filter = low_pass_filter;
         self
@@ -464,6 +476,10 @@ impl CommandExecutionRequest {
         &self.host_sharing_requirements
     }
 
+    pub fn resource_class(&self) -> Option<ResourceClass> {
+        self.resource_class
+    }
+
     pub fn low_pass_filter(&self) -> bool {
         self.low_pass_filter
     }
@@ -17,4 +17,14 @@ pub struct ExecutorGlobalKnobs {
     /// Whether to emit action keys to execution logs (thos are pretty verbose and omitted by
     /// default).
     pub log_action_keys: bool,
+
+    /// Maximum number of bytes of each of stdout and stderr kept for a local action. Output past
+    /// this is truncated in the middle, and the full output is written to `buck-out`.
+    pub action_output_max_bytes: Option<usize>,
+
+    /// Whether to run local actions at a reduced CPU and IO priority.
+    pub low_priority: bool,
+
+    /// Probability with which an action cache hit is verified before it is used.
+    pub action_cache_verify_probability: f64,
 }
@@ -8,8 +8,11 @@
  */
 
 use std::io::Write;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Duration;
+use std::time::Instant;
 
 use allocative::Allocative;
 use anyhow::Context as _;
@@ -22,6 +25,8 @@ use buck2_common::file_ops::TrackedFileDigest;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_events::dispatch::get_dispatcher_opt;
+use buck2_events::dispatch::EventDispatcher;
 use buck2_http::retries::http_retry;
 use buck2_http::retries::AsHttpError;
 use buck2_http::retries::HttpError;
@@ -224,17 +229,23 @@ pub async fn http_download(
         || async {
             let file = fs_util::create_file(&abs_path).map_err(HttpDownloadError::IoError)?;
 
-            let stream = client
+            let response = client
                 .get(url)
                 .await
-                .map_err(|e| HttpDownloadError::Client(HttpError::Client(e)))?
-                .into_body();
+                .map_err(|e| HttpDownloadError::Client(HttpError::Client(e)))?;
+            let total_bytes = response
+                .headers()
+                .get(hyper::header::CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok());
+            let stream = response.into_body();
             let buf_writer = std::io::BufWriter::new(file);
 
             let digest = copy_and_hash(
                 url,
                 &abs_path,
                 stream,
+                total_bytes,
                 buf_writer,
                 digest_config.cas_digest_config(),
                 checksum,
@@ -257,11 +268,77 @@ pub async fn http_download(
     .await?)
 }
 
+/// How often the progress of a download is reported.
+const DOWNLOAD_PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Reports the progress of a download to the dispatcher of the command it runs for, if any. The
+/// download is reported as finished when this is dropped, and as successful if `succeeded` was
+/// called.
+struct DownloadProgressReporter {
+    dispatcher: Option<EventDispatcher>,
+    id: u64,
+    url: String,
+    total_bytes: Option<u64>,
+    bytes_downloaded: u64,
+    last_report: Instant,
+    succeeded: bool,
+}
+
+impl DownloadProgressReporter {
+    fn new(url: &str, total_bytes: Option<u64>) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        let reporter = Self {
+            dispatcher: get_dispatcher_opt(),
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            url: url.to_owned(),
+            total_bytes,
+            bytes_downloaded: 0,
+            last_report: Instant::now(),
+            succeeded: false,
+        };
+        reporter.report(false);
+        reporter
+    }
+
+    fn update(&mut self, bytes_downloaded: u64) {
+        self.bytes_downloaded = bytes_downloaded;
+        if self.last_report.elapsed() >= DOWNLOAD_PROGRESS_INTERVAL {
+            self.last_report = Instant::now();
+            self.report(false);
+        }
+    }
+
+    fn succeeded(&mut self) {
+        self.succeeded = true;
+    }
+
+    fn report(&self, finished: bool) {
+        if let Some(dispatcher) = &self.dispatcher {
+            dispatcher.instant_event(buck2_data::DownloadProgress {
+                id: self.id,
+                url: self.url.clone(),
+                bytes_downloaded: self.bytes_downloaded,
+                total_bytes: self.total_bytes,
+                finished,
+                succeeded: finished && self.succeeded,
+            });
+        }
+    }
+}
+
+impl Drop for DownloadProgressReporter {
+    fn drop(&mut self) {
+        self.report(true);
+    }
+}
+
 /// Copy a stream into a writer while producing its digest and checksumming it.
 async fn copy_and_hash(
     url: &str,
     abs_path: &(impl std::fmt::Display + ?Sized),
     mut stream: impl Stream<Item = Result<Bytes, hyper::Error>> + Unpin,
+    total_bytes: Option<u64>,
     mut writer: impl Write,
     digest_config: CasDigestConfig,
     checksum: &Checksum,
@@ -299,6 +376,8 @@ async fn copy_and_hash(
         validators.push((validator, sha256, "sha256"));
     }
 
+    let mut progress = DownloadProgressReporter::new(url, total_bytes);
+
     while let Some(chunk) = stream.next().await {
         let chunk = chunk.map_err(|source| HttpError::Transfer {
             received: digester.bytes_read(),
@@ -316,6 +395,7 @@ async fn copy_and_hash(
                 hasher.update(&chunk);
             }
         }
+        progress.update(digester.bytes_read());
     }
     writer
         .flush()
@@ -350,6 +430,7 @@ async fn copy_and_hash(
         }
     }
 
+    progress.succeeded();
     Ok(digest)
 }
 
@@ -371,6 +452,7 @@ mod test {
             "test",
             "test",
             stream::iter(vec![Ok(Bytes::from("foo")), Ok(Bytes::from("bar"))]),
+            None,
             &mut out,
             digest_config,
             checksum,
@@ -0,0 +1,334 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use allocative::Allocative;
+use buck2_core::execution_types::executor_config::RemoteExecutorUseCase;
+use futures::channel::oneshot;
+use futures::future::Shared;
+use futures::FutureExt;
+use remote_execution::GetDigestsTtlRequest;
+use remote_execution::REClient;
+use remote_execution::TDigest;
+
+use crate::re::metadata::RemoteExecutionMetadataExt;
+
+type DigestTtls = Result<Arc<HashMap<TDigest, i64>>, Arc<anyhow::Error>>;
+
+/// Resolves once an upload started by another action has finished successfully. Resolves to an
+/// error if that upload failed or was cancelled.
+pub(crate) type InFlightUpload = Shared<oneshot::Receiver<()>>;
+
+#[derive(Default)]
+pub struct CasBatcherStats {
+    /// Number of `get_digests_ttl` requests sent.
+    pub find_missing_batches: u64,
+    /// Number of digests actions asked about.
+    pub find_missing_digests_requested: u64,
+    /// Number of digests sent to RE, after deduplication across actions.
+    pub find_missing_digests_queried: u64,
+    /// Number of digests an action did not upload because another action was uploading them.
+    pub upload_digests_deduplicated: u64,
+}
+
+struct PendingFindMissing {
+    id: u64,
+    digests: HashSet<TDigest>,
+    result: Shared<oneshot::Receiver<DigestTtls>>,
+}
+
+/// Batches CAS existence checks issued by concurrent actions, and deduplicates concurrent
+/// uploads of the same digest.
+///
+/// The first action to check digests for a use case waits for `window`, during which other
+/// actions add their digests to its batch, and then sends a single request for all of them.
+#[derive(Allocative)]
+pub(crate) struct CasBatcher {
+    /// Zero disables batching of existence checks.
+    window: Duration,
+    #[allocative(skip)]
+    pending: Mutex<HashMap<RemoteExecutorUseCase, PendingFindMissing>>,
+    #[allocative(skip)]
+    uploads: Mutex<HashMap<TDigest, InFlightUpload>>,
+    next_id: AtomicU64,
+    find_missing_batches: AtomicU64,
+    find_missing_digests_requested: AtomicU64,
+    find_missing_digests_queried: AtomicU64,
+    upload_digests_deduplicated: AtomicU64,
+}
+
+impl CasBatcher {
+    pub(crate) fn new(window: Duration) -> Self {
+        Self {
+            window,
+            pending: Mutex::new(HashMap::new()),
+            uploads: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+            find_missing_batches: AtomicU64::new(0),
+            find_missing_digests_requested: AtomicU64::new(0),
+            find_missing_digests_queried: AtomicU64::new(0),
+            upload_digests_deduplicated: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn stats(&self) -> CasBatcherStats {
+        CasBatcherStats {
+            find_missing_batches: self.find_missing_batches.load(Ordering::Relaxed),
+            find_missing_digests_requested: self
+                .find_missing_digests_requested
+                .load(Ordering::Relaxed),
+            find_missing_digests_queried: self.find_missing_digests_queried.load(Ordering::Relaxed),
+            upload_digests_deduplicated: self.upload_digests_deduplicated.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Return the TTL of each of `digests`, in the same order. Digests RE does not know about
+    /// have a TTL of zero.
+    pub(crate) async fn get_digests_ttl(
+        &self,
+        client: &REClient,
+        use_case: RemoteExecutorUseCase,
+        digests: Vec<TDigest>,
+    ) -> anyhow::Result<Vec<(TDigest, i64)>> {
+        self.find_missing_digests_requested
+            .fetch_add(digests.len() as u64, Ordering::Relaxed);
+
+        if self.window.is_zero() {
+            return self.query(client, use_case, digests).await;
+        }
+
+        let (result, leader) = {
+            let mut pending = self.pending.lock().unwrap();
+            match pending.entry(use_case) {
+                Entry::Occupied(mut e) => {
+                    e.get_mut().digests.extend(digests.iter().cloned());
+                    (e.get().result.clone(), None)
+                }
+                Entry::Vacant(e) => {
+                    let (sender, receiver) = oneshot::channel();
+                    let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                    let result = receiver.shared();
+                    e.insert(PendingFindMissing {
+                        id,
+                        digests: digests.iter().cloned().collect(),
+                        result: result.clone(),
+                    });
+                    (result, Some((id, sender)))
+                }
+            }
+        };
+
+        if let Some((id, sender)) = leader {
+            let batch = PendingBatchGuard {
+                batcher: self,
+                use_case,
+                id,
+            };
+            tokio::time::sleep(self.window).await;
+            let batch_digests = batch.take();
+
+            let res = self
+                .query(client, use_case, batch_digests)
+                .await
+                .map(|ttls| Arc::new(ttls.into_iter().collect()))
+                .map_err(Arc::new);
+            let _ignored = sender.send(res);
+        }
+
+        match result.await {
+            Ok(Ok(ttls)) => Ok(digests
+                .into_iter()
+                .map(|digest| {
+                    let ttl = ttls.get(&digest).copied().unwrap_or_default();
+                    (digest, ttl)
+                })
+                .collect()),
+            Ok(Err(e)) => Err(anyhow::anyhow!("{:#}", e)),
+            // The action that started the batch was cancelled before sending it.
+            Err(oneshot::Canceled) => self.query(client, use_case, digests).await,
+        }
+    }
+
+    async fn query(
+        &self,
+        client: &REClient,
+        use_case: RemoteExecutorUseCase,
+        digests: Vec<TDigest>,
+    ) -> anyhow::Result<Vec<(TDigest, i64)>> {
+        self.find_missing_batches.fetch_add(1, Ordering::Relaxed);
+        self.find_missing_digests_queried
+            .fetch_add(digests.len() as u64, Ordering::Relaxed);
+
+        let response = client
+            .get_digests_ttl(
+                use_case.metadata(),
+                GetDigestsTtlRequest {
+                    digests,
+                    ..Default::default()
+                },
+            )
+            .boxed()
+            .await?;
+
+        Ok(response
+            .digests_with_ttl
+            .into_iter()
+            .map(|d| (d.digest, d.ttl))
+            .collect())
+    }
+
+    /// Claim the uploads of `digests`. Digests that another action is already uploading are not
+    /// claimed, and are returned along with the upload to wait for instead.
+    pub(crate) fn claim_uploads<'a>(
+        &self,
+        digests: impl IntoIterator<Item = &'a TDigest>,
+    ) -> (UploadClaim<'_>, Vec<(TDigest, InFlightUpload)>) {
+        let (sender, receiver) = oneshot::channel();
+        let receiver = receiver.shared();
+
+        let mut claimed = Vec::new();
+        let mut in_flight = Vec::new();
+        {
+            let mut uploads = self.uploads.lock().unwrap();
+            for digest in digests {
+                match uploads.entry(digest.clone()) {
+                    Entry::Occupied(e) => in_flight.push((digest.clone(), e.get().clone())),
+                    Entry::Vacant(e) => {
+                        e.insert(receiver.clone());
+                        claimed.push(digest.clone());
+                    }
+                }
+            }
+        }
+
+        self.upload_digests_deduplicated
+            .fetch_add(in_flight.len() as u64, Ordering::Relaxed);
+
+        (
+            UploadClaim {
+                batcher: self,
+                digests: claimed,
+                sender: Some(sender),
+            },
+            in_flight,
+        )
+    }
+}
+
+/// Removes a pending batch if the action that started it is cancelled before sending it.
+struct PendingBatchGuard<'a> {
+    batcher: &'a CasBatcher,
+    use_case: RemoteExecutorUseCase,
+    id: u64,
+}
+
+impl PendingBatchGuard<'_> {
+    fn take(self) -> Vec<TDigest> {
+        self.remove()
+            .map(|batch| batch.digests.into_iter().collect())
+            .unwrap_or_default()
+    }
+
+    fn remove(&self) -> Option<PendingFindMissing> {
+        let mut pending = self.batcher.pending.lock().unwrap();
+        match pending.entry(self.use_case) {
+            Entry::Occupied(e) if e.get().id == self.id => Some(e.remove()),
+            _ => None,
+        }
+    }
+}
+
+impl Drop for PendingBatchGuard<'_> {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
+
+/// Uploads claimed by an action. Other actions waiting for them are notified when this is
+/// dropped: of a success if `succeeded` was called, of a failure otherwise.
+pub(crate) struct UploadClaim<'a> {
+    batcher: &'a CasBatcher,
+    digests: Vec<TDigest>,
+    sender: Option<oneshot::Sender<()>>,
+}
+
+impl UploadClaim<'_> {
+    pub(crate) fn succeeded(mut self) {
+        if let Some(sender) = self.sender.take() {
+            let _ignored = sender.send(());
+        }
+    }
+}
+
+impl Drop for UploadClaim<'_> {
+    fn drop(&mut self) {
+        let mut uploads = self.batcher.uploads.lock().unwrap();
+        for digest in &self.digests {
+            uploads.remove(digest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest(hash: &str) -> TDigest {
+        TDigest {
+            hash: hash.to_owned(),
+            size_in_bytes: 1,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_claim_uploads() {
+        let batcher = CasBatcher::new(Duration::ZERO);
+        let (a, b) = (digest("a"), digest("b"));
+
+        let (claim, in_flight) = batcher.claim_uploads([&a]);
+        assert!(in_flight.is_empty());
+
+        let (other_claim, in_flight) = batcher.claim_uploads([&a, &b]);
+        assert_eq!(other_claim.digests, vec![b.clone()]);
+        assert_eq!(in_flight.len(), 1);
+        assert_eq!(in_flight[0].0, a);
+
+        claim.succeeded();
+        assert!(in_flight[0].1.clone().await.is_ok());
+        drop(other_claim);
+
+        // Once both claims are released, the digests can be claimed again.
+        let (claim, in_flight) = batcher.claim_uploads([&a, &b]);
+        assert!(in_flight.is_empty());
+        drop(claim);
+
+        assert_eq!(batcher.stats().upload_digests_deduplicated, 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_upload_notifies_waiters() {
+        let batcher = CasBatcher::new(Duration::ZERO);
+        let a = digest("a");
+
+        let (claim, _) = batcher.claim_uploads([&a]);
+        let (_other_claim, in_flight) = batcher.claim_uploads([&a]);
+        drop(claim);
+
+        assert!(in_flight[0].1.clone().await.is_err());
+    }
+}
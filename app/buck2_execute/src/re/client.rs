@@ -41,6 +41,7 @@ use remote_execution::DownloadRequest;
 use remote_execution::ExecuteRequest;
 use remote_execution::ExecuteResponse;
 use remote_execution::ExecuteWithProgressResponse;
+use remote_execution::ExtendDigestsTtlRequest;
 use remote_execution::GetDigestsTtlRequest;
 use remote_execution::HostResourceRequirements;
 use remote_execution::InlinedBlobWithDigest;
@@ -71,6 +72,7 @@ use crate::execute::manager::CommandExecutionManager;
 use crate::knobs::ExecutorGlobalKnobs;
 use crate::materialize::materializer::Materializer;
 use crate::re::action_identity::ReActionIdentity;
+use crate::re::cas_batcher::CasBatcher;
 use crate::re::convert::platform_to_proto;
 use crate::re::metadata::RemoteExecutionMetadataExt;
 use crate::re::stats::OpStats;
@@ -352,6 +354,19 @@ impl RemoteExecutionClient {
             .await
     }
 
+    pub async fn extend_digest_ttl(
+        &self,
+        digests: Vec<TDigest>,
+        ttl: Duration,
+        use_case: RemoteExecutorUseCase,
+    ) -> anyhow::Result<()> {
+        self.data
+            .client
+            .extend_digest_ttl(digests, ttl, use_case)
+            .await
+            .map_err(|e| self.decorate_error(e))
+    }
+
     pub async fn write_action_result(
         &self,
         digest: ActionDigest,
@@ -387,6 +402,7 @@ impl RemoteExecutionClient {
         stats.materializes = RemoteExecutionClientOpStats::from(&self.data.materializes);
         stats.get_digest_expirations =
             RemoteExecutionClientOpStats::from(&self.data.get_digest_expirations);
+        stats.cas_batching = self.data.client.cas_batcher.stats();
     }
 }
 
@@ -398,6 +414,8 @@ struct RemoteExecutionClientImpl {
     /// How many simultaneous requests to RE
     #[allocative(skip)]
     cas_semaphore: Arc<Semaphore>,
+    /// Batches existence checks and deduplicates uploads across actions.
+    cas_batcher: CasBatcher,
     /// How many files we can be downloading concurrently.
     #[allocative(skip)]
     download_files_semapore: Arc<Semaphore>,
@@ -432,6 +450,9 @@ impl RemoteExecutionClientImpl {
             let download_concurrency =
                 buck2_env!("BUCK2_RE_DOWNLOAD_CONCURRENCY", type=usize, default=256)?;
 
+            let find_missing_batch_window_ms =
+                buck2_env!("BUCK2_RE_FIND_MISSING_BATCH_WINDOW_MS", type=u64, default=5)?;
+
             // Split things up into smaller chunks.
             let download_chunk_size = std::cmp::max(download_concurrency / 8, 1);
 
@@ -673,6 +694,7 @@ impl RemoteExecutionClientImpl {
                 client: Some(client),
                 skip_remote_cache,
                 cas_semaphore: Arc::new(Semaphore::new(static_metadata.cas_semaphore_size())),
+                cas_batcher: CasBatcher::new(Duration::from_millis(find_missing_batch_window_ms)),
                 download_files_semapore: Arc::new(Semaphore::new(download_concurrency)),
                 download_chunk_size,
             }
@@ -734,6 +756,7 @@ impl RemoteExecutionClientImpl {
         Uploader::upload(
             fs,
             self.client().get_cas_client(),
+            &self.cas_batcher,
             materializer,
             dir_path,
             input_dir,
@@ -1143,6 +1166,25 @@ impl RemoteExecutionClientImpl {
             .collect())
     }
 
+    async fn extend_digest_ttl(
+        &self,
+        digests: Vec<TDigest>,
+        ttl: Duration,
+        use_case: RemoteExecutorUseCase,
+    ) -> anyhow::Result<()> {
+        self.client()
+            .get_cas_client()
+            .extend_digest_ttl(
+                use_case.metadata(),
+                ExtendDigestsTtlRequest {
+                    digests,
+                    ttl: ttl.as_secs() as i64,
+                    ..Default::default()
+                },
+            )
+            .await
+    }
+
     async fn write_action_result(
         &self,
         digest: ActionDigest,
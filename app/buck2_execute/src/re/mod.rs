@@ -8,6 +8,7 @@
  */
 
 pub mod action_identity;
+pub mod cas_batcher;
 pub mod client;
 pub mod convert;
 pub mod manager;
@@ -14,6 +14,8 @@ use std::sync::atomic::Ordering;
 use allocative::Allocative;
 use futures::FutureExt;
 
+use crate::re::cas_batcher::CasBatcherStats;
+
 #[derive(Default)]
 pub struct RemoteExecutionClientOpStats {
     pub started: u32,
@@ -44,6 +46,7 @@ pub struct RemoteExecutionClientStats {
     pub materializes: RemoteExecutionClientOpStats,
     pub write_action_results: RemoteExecutionClientOpStats,
     pub get_digest_expirations: RemoteExecutionClientOpStats,
+    pub cas_batching: CasBatcherStats,
 }
 
 #[derive(Default, Allocative)]
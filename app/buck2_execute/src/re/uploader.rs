@@ -28,7 +28,6 @@ use chrono::Duration;
 use chrono::Utc;
 use futures::FutureExt;
 use gazebo::prelude::*;
-use remote_execution::GetDigestsTtlRequest;
 use remote_execution::InlinedBlobWithDigest;
 use remote_execution::NamedDigest;
 use remote_execution::REClient;
@@ -48,6 +47,7 @@ use crate::execute::blobs::ActionBlobs;
 use crate::materialize::materializer::ArtifactNotMaterializedReason;
 use crate::materialize::materializer::CasDownloadInfo;
 use crate::materialize::materializer::Materializer;
+use crate::re::cas_batcher::CasBatcher;
 use crate::re::metadata::RemoteExecutionMetadataExt;
 
 #[derive(Clone, Debug, Default)]
@@ -61,6 +61,7 @@ pub struct Uploader {}
 impl Uploader {
     async fn find_missing<'a>(
         client: &REClient,
+        batcher: &CasBatcher,
         input_dir: &'a ActionImmutableDirectory,
         blobs: &'a ActionBlobs,
         use_case: &RemoteExecutorUseCase,
@@ -100,15 +101,13 @@ impl Uploader {
             }
 
             // Find out which ones are missing
-            let request = GetDigestsTtlRequest {
-                digests: input_digests.iter().map(|d| d.to_re()).collect(),
-                ..Default::default()
-            };
-            client
-                .get_digests_ttl(use_case.metadata(), request)
-                .boxed()
+            batcher
+                .get_digests_ttl(
+                    client,
+                    *use_case,
+                    input_digests.iter().map(|d| d.to_re()).collect(),
+                )
                 .await?
-                .digests_with_ttl
         };
 
         let mut upload_blobs = Vec::new();
@@ -118,9 +117,8 @@ impl Uploader {
         let mut input_digests = input_digests.into_iter().collect::<Vec<_>>();
         input_digests.sort();
 
-        let mut digest_ttls = digest_ttls.into_try_map(|d| {
-            anyhow::Ok((FileDigest::from_re(&d.digest, digest_config)?, d.ttl))
-        })?;
+        let mut digest_ttls = digest_ttls
+            .into_try_map(|(d, ttl)| anyhow::Ok((FileDigest::from_re(&d, digest_config)?, ttl)))?;
         digest_ttls.sort();
 
         if input_digests.len() != digest_ttls.len() {
@@ -166,6 +164,7 @@ impl Uploader {
     pub async fn upload(
         fs: &ProjectRoot,
         client: &REClient,
+        batcher: &CasBatcher,
         materializer: &Arc<dyn Materializer>,
         dir_path: &ProjectRelativePath,
         input_dir: &ActionImmutableDirectory,
@@ -174,7 +173,7 @@ impl Uploader {
         digest_config: DigestConfig,
     ) -> anyhow::Result<UploadStats> {
         let (mut upload_blobs, mut missing_digests) =
-            Self::find_missing(client, input_dir, blobs, &use_case, digest_config).await?;
+            Self::find_missing(client, batcher, input_dir, blobs, &use_case, digest_config).await?;
 
         if upload_blobs.is_empty() && missing_digests.is_empty() {
             return Ok(UploadStats::default());
@@ -324,6 +323,24 @@ impl Uploader {
                 .context("Error materializing paths for upload")?;
         }
 
+        // Leave digests that another action is already uploading to that action.
+        let (claim, in_flight) = batcher.claim_uploads(
+            upload_files
+                .iter()
+                .map(|f| &f.digest)
+                .chain(upload_blobs.iter().map(|b| &b.digest)),
+        );
+        let in_flight_digests = in_flight
+            .iter()
+            .map(|(d, _)| d.clone())
+            .collect::<HashSet<_>>();
+        let (waiting_files, upload_files): (Vec<_>, Vec<_>) = upload_files
+            .into_iter()
+            .partition(|f| in_flight_digests.contains(&f.digest));
+        let (waiting_blobs, upload_blobs): (Vec<_>, Vec<_>) = upload_blobs
+            .into_iter()
+            .partition(|b| in_flight_digests.contains(&b.digest));
+
         // Compute stats of digests we're about to upload so we can report them
         // to the span end event of this stage of execution.
         let stats = {
@@ -349,25 +366,60 @@ impl Uploader {
         };
 
         // Upload
-        let upload_res = if !upload_files.is_empty() || !upload_blobs.is_empty() {
-            client
-                .upload(
-                    use_case.metadata(),
-                    UploadRequest {
-                        files_with_digest: Some(upload_files),
-                        inlined_blobs_with_digest: Some(upload_blobs),
-                        // all find missing checks are done previously
-                        // and we can skip them and upload all digests
-                        upload_only_missing: false,
-                        ..Default::default()
-                    },
-                )
-                .boxed()
-                .await
-                .map(|_| ())
-        } else {
-            Ok(())
-        };
+        Self::upload_missing(client, &use_case, upload_files, upload_blobs).await?;
+        claim.succeeded();
+
+        // Upload ourselves whatever the other actions failed to upload.
+        let mut failed = HashSet::new();
+        for (digest, upload) in in_flight {
+            if upload.await.is_err() {
+                failed.insert(digest);
+            }
+        }
+        if !failed.is_empty() {
+            Self::upload_missing(
+                client,
+                &use_case,
+                waiting_files
+                    .into_iter()
+                    .filter(|f| failed.contains(&f.digest))
+                    .collect(),
+                waiting_blobs
+                    .into_iter()
+                    .filter(|b| failed.contains(&b.digest))
+                    .collect(),
+            )
+            .await?;
+        }
+
+        Ok(stats)
+    }
+
+    async fn upload_missing(
+        client: &REClient,
+        use_case: &RemoteExecutorUseCase,
+        upload_files: Vec<NamedDigest>,
+        upload_blobs: Vec<InlinedBlobWithDigest>,
+    ) -> anyhow::Result<()> {
+        if upload_files.is_empty() && upload_blobs.is_empty() {
+            return Ok(());
+        }
+
+        let upload_res = client
+            .upload(
+                use_case.metadata(),
+                UploadRequest {
+                    files_with_digest: Some(upload_files),
+                    inlined_blobs_with_digest: Some(upload_blobs),
+                    // all find missing checks are done previously
+                    // and we can skip them and upload all digests
+                    upload_only_missing: false,
+                    ..Default::default()
+                },
+            )
+            .boxed()
+            .await
+            .map(|_| ());
 
         if let Err(e) = upload_res.as_ref() {
             if let Some(re_client_error) = e.downcast_ref::<REClientError>() {
@@ -381,9 +433,7 @@ impl Uploader {
             }
         }
 
-        upload_res.context("RE: upload")?;
-
-        Ok(stats)
+        upload_res.context("RE: upload")
     }
 }
 
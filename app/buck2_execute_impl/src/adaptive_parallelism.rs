@@ -0,0 +1,288 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::sync::Arc;
+use std::sync::Weak;
+use std::time::Duration;
+
+use buck2_events::dispatch::EventDispatcher;
+use buck2_util::system_stats::MemoryStats;
+use buck2_util::system_stats::UnixSystemStats;
+use host_sharing::host_sharing::HostSharingGuard;
+use host_sharing::HostSharingBroker;
+use host_sharing::ResourceClass;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Debug)]
+pub struct AdaptiveParallelismConfig {
+    /// Throttle when the 1 minute load average exceeds this many times the number of CPUs.
+    pub max_load_per_cpu: f64,
+    /// Throttle when less than this fraction of the memory of the machine is available.
+    pub min_available_memory: f64,
+    /// Never throttle below this many concurrent actions.
+    pub min_concurrency: usize,
+}
+
+impl Default for AdaptiveParallelismConfig {
+    fn default() -> Self {
+        Self {
+            max_load_per_cpu: 1.5,
+            min_available_memory: 0.1,
+            min_concurrency: 1,
+        }
+    }
+}
+
+struct Sample {
+    load1: Option<f64>,
+    memory: Option<MemoryStats>,
+}
+
+impl Sample {
+    fn get() -> Self {
+        Self {
+            load1: UnixSystemStats::get().map(|s| s.load1),
+            memory: MemoryStats::get(),
+        }
+    }
+
+    /// Why the machine is overloaded, if it is.
+    fn pressure(&self, config: &AdaptiveParallelismConfig, cpus: usize) -> Option<&'static str> {
+        if let Some(memory) = &self.memory {
+            if (memory.available_bytes as f64)
+                < (memory.total_bytes as f64) * config.min_available_memory
+            {
+                return Some("memory pressure");
+            }
+        }
+        if let Some(load1) = self.load1 {
+            if load1 > config.max_load_per_cpu * cpus as f64 {
+                return Some("high load average");
+            }
+        }
+        None
+    }
+}
+
+/// Permits of one budget of the host sharing broker held back by the controller.
+struct Throttle {
+    resource_class: ResourceClass,
+    /// Number of permits the controller wants to hold.
+    target: usize,
+    held: Vec<HostSharingGuard>,
+}
+
+impl Throttle {
+    fn new(resource_class: ResourceClass) -> Self {
+        Self {
+            resource_class,
+            target: 0,
+            held: Vec::new(),
+        }
+    }
+
+    /// Adjust the target after a sample, and take the permits needed to reach it which are free.
+    /// Returns whether the target changed.
+    fn update(
+        &mut self,
+        broker: &HostSharingBroker,
+        config: &AdaptiveParallelismConfig,
+        overloaded: bool,
+    ) -> bool {
+        let max_concurrency = broker.num_permits(self.resource_class);
+        let min_concurrency = config.min_concurrency.min(max_concurrency);
+        let concurrency = max_concurrency - self.target;
+
+        let changed = if overloaded && concurrency > min_concurrency {
+            // Back off multiplicatively, recover additively.
+            self.target += (concurrency / 4).max(1).min(concurrency - min_concurrency);
+            true
+        } else if !overloaded && self.target > 0 {
+            self.target -= 1;
+            true
+        } else {
+            false
+        };
+
+        // Never wait for permits here: those that are in use are taken at the next samples, as
+        // running actions release them, and the controller keeps reacting in the meantime.
+        self.held.truncate(self.target);
+        let missing = self.target - self.held.len();
+        self.held
+            .extend(broker.try_reserve(self.resource_class, missing));
+
+        changed
+    }
+}
+
+/// A feedback controller which reduces the number of local actions running concurrently while the
+/// machine is short on memory or overloaded, and ramps it back up once it recovers. This is done
+/// by holding permits of the host sharing broker, from the budget of IO-bound actions too if they
+/// have their own. It takes permits as running actions release them, so a reduction only applies
+/// once actions finish, and it never kills actions.
+///
+/// The controller stops when the broker is dropped, i.e. at the end of the command.
+pub fn spawn_adaptive_parallelism(
+    broker: &Arc<HostSharingBroker>,
+    config: AdaptiveParallelismConfig,
+    events: EventDispatcher,
+) {
+    tokio::spawn(run_adaptive_parallelism(
+        Arc::downgrade(broker),
+        config,
+        Sample::get,
+        move |event| events.instant_event(event),
+    ));
+}
+
+async fn run_adaptive_parallelism(
+    broker: Weak<HostSharingBroker>,
+    config: AdaptiveParallelismConfig,
+    mut sample: impl FnMut() -> Sample,
+    mut on_change: impl FnMut(buck2_data::LocalConcurrencyChanged),
+) {
+    let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut cpu = Throttle::new(ResourceClass::Cpu);
+    let mut io = Throttle::new(ResourceClass::Io);
+    let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let Some(broker) = broker.upgrade() else {
+            return;
+        };
+        let sample = sample();
+        let pressure = sample.pressure(&config, cpus);
+
+        if broker.has_io_permits() {
+            io.update(&broker, &config, pressure.is_some());
+        }
+        if !cpu.update(&broker, &config, pressure.is_some()) {
+            continue;
+        }
+
+        let max_concurrency = broker.num_machine_permits();
+        on_change(buck2_data::LocalConcurrencyChanged {
+            concurrency: (max_concurrency - cpu.target) as u64,
+            max_concurrency: max_concurrency as u64,
+            reason: pressure.unwrap_or("recovered").to_owned(),
+            load1: sample.load1,
+            memory_available_bytes: sample.memory.as_ref().map(|m| m.available_bytes),
+            memory_total_bytes: sample.memory.as_ref().map(|m| m.total_bytes),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+    use std::sync::atomic::Ordering;
+
+    use dupe::Dupe;
+    use host_sharing::HostSharingStrategy;
+
+    use super::*;
+
+    fn sample(load1: f64, available_bytes: u64) -> Sample {
+        Sample {
+            load1: Some(load1),
+            memory: Some(MemoryStats {
+                total_bytes: 100,
+                available_bytes,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_pressure() {
+        let config = AdaptiveParallelismConfig::default();
+        assert_eq!(sample(4.0, 50).pressure(&config, 4), None);
+        assert_eq!(sample(4.0, 5).pressure(&config, 4), Some("memory pressure"));
+        assert_eq!(
+            sample(7.0, 50).pressure(&config, 4),
+            Some("high load average")
+        );
+        let unknown = Sample {
+            load1: None,
+            memory: None,
+        };
+        assert_eq!(unknown.pressure(&config, 4), None);
+    }
+
+    fn available(broker: &HostSharingBroker, resource_class: ResourceClass) -> usize {
+        broker.try_reserve(resource_class, usize::MAX).len()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_controller() {
+        let broker = Arc::new(
+            HostSharingBroker::new(HostSharingStrategy::SmallerTasksFirst, 8).with_io_permits(4),
+        );
+        let overloaded = Arc::new(AtomicBool::new(true));
+        let changes = Arc::new(parking_lot::Mutex::new(Vec::new()));
+
+        let controller = tokio::spawn(run_adaptive_parallelism(
+            Arc::downgrade(&broker),
+            AdaptiveParallelismConfig::default(),
+            {
+                let overloaded = overloaded.dupe();
+                move || {
+                    sample(
+                        0.0,
+                        if overloaded.load(Ordering::SeqCst) {
+                            5
+                        } else {
+                            50
+                        },
+                    )
+                }
+            },
+            {
+                let changes = changes.dupe();
+                move |event: buck2_data::LocalConcurrencyChanged| {
+                    changes.lock().push((event.concurrency, event.reason))
+                }
+            },
+        ));
+
+        // Samples are taken at 0s, 2s, 4s...: check the permits between them.
+        let half = SAMPLE_INTERVAL / 2;
+        tokio::time::sleep(half).await;
+        assert_eq!(available(&broker, ResourceClass::Cpu), 6);
+        assert_eq!(available(&broker, ResourceClass::Io), 3);
+
+        // Permits which are in use don't block the controller: it takes them once released.
+        let running = broker.try_reserve(ResourceClass::Cpu, 6);
+        tokio::time::sleep(SAMPLE_INTERVAL).await;
+        assert_eq!(available(&broker, ResourceClass::Cpu), 0);
+        drop(running);
+        tokio::time::sleep(SAMPLE_INTERVAL).await;
+        // 8 -> 6 -> 5 -> 4 concurrent actions.
+        assert_eq!(available(&broker, ResourceClass::Cpu), 4);
+
+        overloaded.store(false, Ordering::SeqCst);
+        tokio::time::sleep(SAMPLE_INTERVAL).await;
+        assert_eq!(available(&broker, ResourceClass::Cpu), 5);
+
+        assert_eq!(
+            *changes.lock(),
+            vec![
+                (6, "memory pressure".to_owned()),
+                (5, "memory pressure".to_owned()),
+                (4, "memory pressure".to_owned()),
+                (5, "recovered".to_owned()),
+            ]
+        );
+
+        // The controller stops with the broker.
+        drop(broker);
+        tokio::time::sleep(SAMPLE_INTERVAL).await;
+        assert!(controller.is_finished());
+    }
+}
@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::collections::HashSet;
 use std::ops::ControlFlow;
 use std::sync::Arc;
 
@@ -16,6 +17,7 @@ use buck2_action_metadata_proto::REMOTE_DEP_FILE_KEY;
 use buck2_core::execution_types::executor_config::RemoteExecutorUseCase;
 use buck2_core::fs::artifact_path_resolver::ArtifactFs;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::soft_error;
 use buck2_execute::execute::action_digest::ActionDigest;
 use buck2_execute::execute::action_digest::ActionDigestKind;
 use buck2_execute::execute::dep_file_digest::DepFileDigest;
@@ -34,13 +36,29 @@ use buck2_execute::re::manager::ManagedRemoteExecutionClient;
 use buck2_execute::re::remote_action_result::RemoteActionResult;
 use buck2_execute::re::remote_action_result::RemoteDepFileResult;
 use buck2_futures::cancellation::CancellationContext;
+use chrono::DateTime;
+use chrono::Utc;
 use dupe::Dupe;
 use prost::Message;
+use rand::Rng;
+use remote_execution::TDigest;
 
 use crate::re::download::download_action_results;
 use crate::re::download::DownloadResult;
 use crate::re::paranoid_download::ParanoidDownloader;
 
+#[derive(buck2_error::Error, Debug)]
+enum ActionCacheVerificationError {
+    #[error(
+        "Action cache hit for `{action_digest}` refers to {missing} output(s) missing from the CAS, the action will be executed again"
+    )]
+    #[buck2(infra)]
+    MissingOutputs {
+        action_digest: ActionDigest,
+        missing: usize,
+    },
+}
+
 pub struct ActionCacheChecker {
     pub artifact_fs: ArtifactFs,
     pub materializer: Arc<dyn Materializer>,
@@ -81,7 +99,7 @@ async fn query_action_cache_and_download_result(
     manager: CommandExecutionManager,
     cancellations: &CancellationContext<'_>,
     upload_all_actions: bool,
-    log_action_keys: bool,
+    knobs: &ExecutorGlobalKnobs,
     details: RemoteCommandExecutionDetails,
 ) -> ControlFlow<CommandExecutionResult, CommandExecutionManager> {
     let request = command.request;
@@ -162,7 +180,36 @@ async fn query_action_cache_and_download_result(
             }
         };
 
-    let action_key = if log_action_keys {
+    // Sampled cache hits are checked to catch corrupt entries before they are used: their outputs
+    // must all exist in the CAS, and they are re-hashed when downloaded if paranoid mode is on.
+    let verify = should_verify(knobs.action_cache_verify_probability);
+    if verify {
+        match count_missing_outputs(re_client, re_use_case, response.as_ref()).await {
+            Ok(0) => {}
+            Ok(missing) => {
+                let e: anyhow::Error = ActionCacheVerificationError::MissingOutputs {
+                    action_digest: digest.dupe(),
+                    missing,
+                }
+                .into();
+                return match soft_error!("action_cache_hit_verification_failed", e) {
+                    // RE doesn't let us delete the entry: instead, the action runs again without
+                    // reading from the action cache, and its result replaces the entry if it is
+                    // uploaded.
+                    Ok(_) => ControlFlow::Continue(manager.with_skip_cache_read(true)),
+                    Err(e) => ControlFlow::Break(manager.error("action_cache_verification", e)),
+                };
+            }
+            // Failing to query the CAS says nothing about the entry, which is used as usual.
+            Err(e) => tracing::warn!(
+                "Failed to verify action cache hit for `{}`: {:#}",
+                digest,
+                e
+            ),
+        }
+    }
+
+    let action_key = if knobs.log_action_keys {
         let identity = ReActionIdentity::new(
             command.target,
             re_action_key.as_deref(),
@@ -189,7 +236,9 @@ async fn query_action_cache_and_download_result(
         request.outputs(),
         details,
         &response,
-        paranoid.as_ref(),
+        paranoid
+            .as_ref()
+            .filter(|p| verify || p.should_verify(&command.target.as_proto_action_name().category)),
         cancellations,
         action_exit_code,
         artifact_fs,
@@ -218,6 +267,52 @@ async fn query_action_cache_and_download_result(
     ControlFlow::Break(res)
 }
 
+fn should_verify(probability: f64) -> bool {
+    probability >= 1.0 || (probability > 0.0 && rand::thread_rng().gen_bool(probability))
+}
+
+/// Count the outputs of an action result which are no longer in the CAS.
+async fn count_missing_outputs(
+    re_client: &ManagedRemoteExecutionClient,
+    re_use_case: RemoteExecutorUseCase,
+    response: &dyn RemoteActionResult,
+) -> anyhow::Result<usize> {
+    // Outputs with the same contents share a digest, which the CAS only reports once.
+    let digests: HashSet<TDigest> = response
+        .output_files()
+        .iter()
+        .map(|f| f.digest.digest.clone())
+        .chain(
+            response
+                .output_directories()
+                .iter()
+                .map(|d| d.tree_digest.clone()),
+        )
+        .collect();
+    if digests.is_empty() {
+        return Ok(0);
+    }
+    let expirations = re_client
+        .get_digest_expirations(digests.iter().cloned().collect(), re_use_case)
+        .await?;
+    Ok(missing_digests(&digests, &expirations, Utc::now()))
+}
+
+/// Digests the CAS doesn't have are either omitted from the expirations or reported as already
+/// expired.
+fn missing_digests(
+    digests: &HashSet<TDigest>,
+    expirations: &[(TDigest, DateTime<Utc>)],
+    now: DateTime<Utc>,
+) -> usize {
+    let present: HashSet<&TDigest> = expirations
+        .iter()
+        .filter(|(digest, expires)| *expires > now && digests.contains(digest))
+        .map(|(digest, _)| digest)
+        .collect();
+    digests.len() - present.len()
+}
+
 #[async_trait]
 impl PreparedCommandOptionalExecutor for ActionCacheChecker {
     async fn maybe_execute(
@@ -252,7 +347,7 @@ impl PreparedCommandOptionalExecutor for ActionCacheChecker {
             manager,
             cancellations,
             self.upload_all_actions,
-            self.knobs.log_action_keys,
+            &self.knobs,
             details,
         )
         .await;
@@ -288,6 +383,11 @@ impl PreparedCommandOptionalExecutor for RemoteDepFileCacheChecker {
         manager: CommandExecutionManager,
         cancellations: &CancellationContext,
     ) -> ControlFlow<CommandExecutionResult, CommandExecutionManager> {
+        // Don't look the action up again if a cache hit for it just failed verification.
+        if manager.skip_cache_read {
+            return ControlFlow::Continue(manager);
+        }
+
         // If the remote dep file key is not set, just fallback to the next execution method
         let remote_dep_file_key = match command.request.remote_dep_file_key() {
             None => {
@@ -323,7 +423,7 @@ impl PreparedCommandOptionalExecutor for RemoteDepFileCacheChecker {
             manager,
             cancellations,
             self.upload_all_actions,
-            self.knobs.log_action_keys,
+            &self.knobs,
             details,
         )
         .await
@@ -339,3 +439,63 @@ fn command_execution_kind_for_cache_type(
         CacheType::RemoteDepFileCache(_) => CommandExecutionKind::RemoteDepFileCache { details },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    fn digest(hash: &str) -> TDigest {
+        TDigest {
+            hash: hash.to_owned(),
+            size_in_bytes: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_missing_digests() {
+        let now = Utc::now();
+        let later = now + Duration::hours(1);
+        let digests = HashSet::from([digest("a"), digest("b"), digest("c")]);
+
+        assert_eq!(
+            0,
+            missing_digests(
+                &digests,
+                &[
+                    (digest("a"), later),
+                    (digest("b"), later),
+                    (digest("c"), later)
+                ],
+                now
+            )
+        );
+        // Omitted and expired digests are missing.
+        assert_eq!(
+            2,
+            missing_digests(&digests, &[(digest("a"), later), (digest("b"), now)], now)
+        );
+        // Digests reported twice, or which weren't asked for, don't hide missing ones.
+        assert_eq!(
+            1,
+            missing_digests(
+                &digests,
+                &[
+                    (digest("a"), later),
+                    (digest("a"), later),
+                    (digest("b"), later),
+                    (digest("d"), later)
+                ],
+                now
+            )
+        );
+    }
+
+    #[test]
+    fn test_should_verify() {
+        assert!(!should_verify(0.0));
+        assert!(should_verify(1.0));
+    }
+}
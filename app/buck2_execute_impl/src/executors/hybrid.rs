@@ -82,10 +82,12 @@ where
         liveliness_observer: Arc<dyn LivelinessObserver>,
         cancellations: &CancellationContext<'_>,
         intend_to_fallback_on_failure: bool,
+        skip_cache_read: bool,
     ) -> CommandExecutionResult {
         let remote_manager =
             CommandExecutionManager::new(claim_manager, events, liveliness_observer)
-                .with_intend_to_fallback_on_failure(intend_to_fallback_on_failure);
+                .with_intend_to_fallback_on_failure(intend_to_fallback_on_failure)
+                .with_skip_cache_read(skip_cache_read);
         self.remote
             .exec_cmd(command, remote_manager, cancellations)
             .await
@@ -183,6 +185,7 @@ where
             manager.liveliness_observer.dupe(),
             cancellations,
             fallback_on_failure,
+            manager.skip_cache_read,
         );
 
         if executor_preference.requires_local()
@@ -27,6 +27,7 @@ use buck2_core::fs::artifact_path_resolver::ArtifactFs;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::paths::abs_path::AbsPath;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_core::tag_error;
@@ -49,6 +50,7 @@ use buck2_execute::execute::kind::CommandExecutionKind;
 use buck2_execute::execute::manager::CommandExecutionManager;
 use buck2_execute::execute::manager::CommandExecutionManagerExt;
 use buck2_execute::execute::manager::CommandExecutionManagerWithClaim;
+use buck2_execute::execute::merged_directory_cache::MergedDirectoryCache;
 use buck2_execute::execute::output::CommandStdStreams;
 use buck2_execute::execute::prepared::PreparedCommand;
 use buck2_execute::execute::prepared::PreparedCommandExecutor;
@@ -59,12 +61,14 @@ use buck2_execute::execute::request::CommandExecutionRequest;
 use buck2_execute::execute::request::ExecutorPreference;
 use buck2_execute::execute::result::CommandExecutionMetadata;
 use buck2_execute::execute::result::CommandExecutionResult;
+use buck2_execute::execute::target::CommandExecutionTarget;
 use buck2_execute::knobs::ExecutorGlobalKnobs;
 use buck2_execute::materialize::materializer::MaterializationError;
 use buck2_execute::materialize::materializer::Materializer;
 use buck2_forkserver::client::ForkserverClient;
-use buck2_forkserver::run::gather_output;
+use buck2_forkserver::run::gather_output_with_priority;
 use buck2_forkserver::run::maybe_absolutize_exe;
+use buck2_forkserver::run::output_limit::OutputLimit;
 use buck2_forkserver::run::timeout_into_cancellation;
 use buck2_forkserver::run::GatherOutputStatus;
 use buck2_futures::cancellable_future::CancellationObserver;
@@ -85,6 +89,7 @@ use tracing::info;
 
 use crate::executors::worker::WorkerHandle;
 use crate::executors::worker::WorkerPool;
+use crate::resource_classifier::ResourceClassifier;
 
 #[derive(Debug, buck2_error::Error)]
 enum LocalExecutionError {
@@ -101,6 +106,7 @@ pub struct LocalExecutor {
     materializer: Arc<dyn Materializer>,
     blocking_executor: Arc<dyn BlockingExecutor>,
     pub(crate) host_sharing_broker: Arc<HostSharingBroker>,
+    resource_classifier: Arc<ResourceClassifier>,
     root: AbsNormPathBuf,
     #[cfg_attr(not(unix), allow(unused))]
     forkserver: Option<ForkserverClient>,
@@ -108,6 +114,7 @@ pub struct LocalExecutor {
     knobs: ExecutorGlobalKnobs,
     #[allow(unused)]
     worker_pool: Option<Arc<WorkerPool>>,
+    merged_directory_cache: Arc<MergedDirectoryCache>,
 }
 
 impl LocalExecutor {
@@ -116,20 +123,24 @@ impl LocalExecutor {
         materializer: Arc<dyn Materializer>,
         blocking_executor: Arc<dyn BlockingExecutor>,
         host_sharing_broker: Arc<HostSharingBroker>,
+        resource_classifier: Arc<ResourceClassifier>,
         root: AbsNormPathBuf,
         forkserver: Option<ForkserverClient>,
         knobs: ExecutorGlobalKnobs,
         worker_pool: Option<Arc<WorkerPool>>,
+        merged_directory_cache: Arc<MergedDirectoryCache>,
     ) -> Self {
         Self {
             artifact_fs,
             materializer,
             blocking_executor,
             host_sharing_broker,
+            resource_classifier,
             root,
             forkserver,
             knobs,
             worker_pool,
+            merged_directory_cache,
         }
     }
 
@@ -155,6 +166,8 @@ impl LocalExecutor {
                 None => Cow::Borrowed(&self.root),
             };
 
+            let output_limit = self.output_limit();
+
             match &self.forkserver {
                 Some(forkserver) => {
                     #[cfg(unix)]
@@ -169,6 +182,8 @@ impl LocalExecutor {
                             env_inheritance,
                             liveliness_observer,
                             self.knobs.enable_miniperf && !disable_miniperf,
+                            self.knobs.low_priority,
+                            &output_limit,
                         )
                         .await
                     }
@@ -200,16 +215,37 @@ impl LocalExecutor {
                     let cancellation =
                         select(timeout.boxed(), alive.boxed()).map(|r| r.factor_first().0);
 
-                    gather_output(cmd, cancellation).await
+                    gather_output_with_priority(
+                        cmd,
+                        cancellation,
+                        &output_limit,
+                        self.knobs.low_priority,
+                    )
+                    .await
                 }
                 .with_context(|| format!("Failed to gather output from command: {}", exe)),
             }
         }
     }
 
+    fn output_limit(&self) -> OutputLimit {
+        OutputLimit {
+            max_bytes: self.knobs.action_output_max_bytes,
+            spill_dir: Some(
+                self.root.join(
+                    self.artifact_fs
+                        .buck_out_path_resolver()
+                        .root()
+                        .join(ForwardRelativePath::unchecked_new("action_output")),
+                ),
+            ),
+        }
+    }
+
     async fn exec_request(
         &self,
         action_digest: &ActionDigest,
+        target: &dyn CommandExecutionTarget,
         request: &CommandExecutionRequest,
         manager: CommandExecutionManager,
         cancellation: CancellationObserver,
@@ -347,6 +383,7 @@ impl LocalExecutor {
             }
         };
         let build_id: &str = &dispatcher.trace_id().to_string();
+        let action_key: &str = &target.re_action_key();
 
         let iter_env = || {
             tmpdirs
@@ -367,6 +404,10 @@ impl LocalExecutor {
                     "BUCK_BUILD_ID",
                     StrOrOsStr::from(build_id),
                 )))
+                .chain(std::iter::once((
+                    "BUCK2_ACTION_KEY",
+                    StrOrOsStr::from(action_key),
+                )))
         };
         let liveliness_observer = manager.liveliness_observer.dupe().and(cancellation);
 
@@ -481,6 +522,11 @@ impl LocalExecutor {
                     }
                 };
 
+                self.resource_classifier.record(
+                    &target.as_proto_action_name().category,
+                    execution_stats.as_ref(),
+                    timing.execution_time,
+                );
                 timing.execution_stats = execution_stats;
                 timing.hashing_duration = hashing_time.hashing_duration;
                 timing.hashed_artifacts_count = hashing_time.hashed_artifacts_count;
@@ -541,7 +587,12 @@ impl LocalExecutor {
         request: &CommandExecutionRequest,
         digest_config: DigestConfig,
     ) -> anyhow::Result<(IndexMap<CommandExecutionOutput, ArtifactValue>, HashingInfo)> {
-        let mut builder = inputs_directory(request.inputs(), &self.artifact_fs)?;
+        let mut builder = inputs_directory(
+            request.inputs(),
+            &self.artifact_fs,
+            digest_config,
+            &self.merged_directory_cache,
+        )?;
 
         // Read outputs from disk and add them to the builder
         let mut entries = Vec::new();
@@ -720,7 +771,7 @@ impl PreparedCommandExecutor for LocalExecutor {
 
         let PreparedCommand {
             request,
-            target: _,
+            target,
             prepared_action,
             digest_config,
         } = command;
@@ -749,12 +800,16 @@ impl PreparedCommandExecutor for LocalExecutor {
 
         let _worker_permit = self.acquire_worker_permit(request).await;
 
+        let resource_class = self.resource_classifier.classify(
+            &target.as_proto_action_name().category,
+            request.resource_class(),
+        );
         let _permit = executor_stage_async(
             buck2_data::LocalStage {
                 stage: Some(buck2_data::LocalQueued {}.into()),
             },
             self.host_sharing_broker
-                .acquire(request.host_sharing_requirements()),
+                .acquire_for_class(request.host_sharing_requirements(), resource_class),
         )
         .await;
 
@@ -765,6 +820,7 @@ impl PreparedCommandExecutor for LocalExecutor {
                 Self::exec_request(
                     self,
                     &prepared_action.action_and_blobs.action,
+                    *target,
                     request,
                     manager,
                     cancellation,
@@ -1085,6 +1141,8 @@ mod unix {
         env_inheritance: Option<&EnvironmentInheritance>,
         liveliness_observer: impl LivelinessObserver + 'static,
         enable_miniperf: bool,
+        low_priority: bool,
+        output_limit: &OutputLimit,
     ) -> anyhow::Result<(GatherOutputStatus, Vec<u8>, Vec<u8>)> {
         let exe = exe.as_ref();
 
@@ -1102,10 +1160,15 @@ mod unix {
             enable_miniperf,
             std_redirects: None,
             graceful_shutdown_timeout_s: None,
+            low_priority,
         };
         apply_local_execution_environment(&mut req, working_directory, env, env_inheritance);
         forkserver
-            .execute(req, async move { liveliness_observer.while_alive().await })
+            .execute(
+                req,
+                async move { liveliness_observer.while_alive().await },
+                output_limit,
+            )
             .await
     }
 
@@ -1171,6 +1234,7 @@ mod tests {
     use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
     use buck2_execute::execute::blocking::testing::DummyBlockingExecutor;
     use buck2_execute::materialize::nodisk::NoDiskMaterializer;
+    use buck2_forkserver::run::gather_output;
     use host_sharing::HostSharingStrategy;
 
     use super::*;
@@ -1184,7 +1248,8 @@ mod tests {
         };
         cmd.args(["-c", "echo hello"]);
 
-        let (status, stdout, stderr) = gather_output(cmd, futures::future::pending()).await?;
+        let (status, stdout, stderr) =
+            gather_output(cmd, futures::future::pending(), &OutputLimit::default()).await?;
         assert!(matches!(status, GatherOutputStatus::Finished{ exit_code, .. } if exit_code == 0));
         assert_eq!(str::from_utf8(&stdout)?.trim(), "hello");
         assert_eq!(stderr, b"");
@@ -1213,6 +1278,7 @@ mod tests {
         let (status, stdout, stderr) = gather_output(
             cmd,
             timeout_into_cancellation(Some(Duration::from_secs(timeout))),
+            &OutputLimit::default(),
         )
         .await?;
         assert!(
@@ -1241,6 +1307,7 @@ mod tests {
         let (status, stdout, stderr) = gather_output(
             cmd,
             timeout_into_cancellation(Some(Duration::from_secs(timeout))),
+            &OutputLimit::default(),
         )
         .await?;
         assert!(
@@ -1282,10 +1349,12 @@ mod tests {
                 HostSharingStrategy::SmallerTasksFirst,
                 1,
             )),
+            Arc::new(ResourceClassifier::new(Vec::new())),
             temp.path().root().to_buf(),
             None,
             ExecutorGlobalKnobs::default(),
             None,
+            Arc::new(MergedDirectoryCache::testing_new()),
         );
 
         Ok((executor, temp.path().root().to_buf(), temp))
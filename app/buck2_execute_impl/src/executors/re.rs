@@ -138,6 +138,9 @@ impl ReExecutor {
         let identity =
             ReActionIdentity::new(action, self.re_action_key.as_deref(), request.paths());
 
+        // A cache hit for this action may have failed verification, in which case RE must not
+        // serve it again.
+        let skip_cache_read = self.skip_cache_read || manager.skip_cache_read;
         let execute_response = self
             .re_client
             .execute(
@@ -147,7 +150,7 @@ impl ReExecutor {
                 self.re_use_case,
                 &identity,
                 &mut manager,
-                self.skip_cache_read,
+                skip_cache_read,
                 self.skip_cache_write,
                 self.re_max_queue_time_ms.map(Duration::from_millis),
                 &self.knobs,
@@ -313,7 +316,9 @@ impl PreparedCommandExecutor for ReExecutor {
             request.outputs(),
             details,
             &response,
-            self.paranoid.as_ref(),
+            self.paranoid
+                .as_ref()
+                .filter(|p| p.should_verify(&target.as_proto_action_name().category)),
             cancellations,
             response.action_result.exit_code,
             &self.artifact_fs,
@@ -30,6 +30,7 @@ use buck2_execute::execute::request::WorkerSpec;
 use buck2_execute::execute::result::CommandExecutionMetadata;
 use buck2_execute::execute::result::CommandExecutionResult;
 use buck2_forkserver::client::ForkserverClient;
+use buck2_forkserver::run::output_limit::OutputLimit;
 use buck2_forkserver::run::GatherOutputStatus;
 use buck2_worker_proto::execute_command::EnvironmentEntry;
 use buck2_worker_proto::worker_client::WorkerClient;
@@ -149,10 +150,16 @@ fn spawn_via_forkserver(
                 stderr: stderr_path.as_os_str().as_bytes().into(),
             }),
             graceful_shutdown_timeout_s,
+            low_priority: false,
         };
         apply_local_execution_environment(&mut req, &working_directory, env, None);
         let res = forkserver
-            .execute(req, async move { liveliness_observer.while_alive().await })
+            .execute(
+                req,
+                async move { liveliness_observer.while_alive().await },
+                // Output is redirected to files, so there is nothing to limit.
+                &OutputLimit::default(),
+            )
             .await
             .map(|(status, _, _)| status);
 
@@ -14,7 +14,9 @@
 #![feature(control_flow_enum)]
 #![feature(used_with_arg)]
 
+pub mod adaptive_parallelism;
 pub mod executors;
 pub mod low_pass_filter;
 pub mod materializers;
 pub mod re;
+pub mod resource_classifier;
@@ -21,6 +21,7 @@ use buck2_core::directory::unordered_entry_walk;
 use buck2_core::directory::DirectoryEntry;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_events::dispatch::with_dispatcher_async;
 use buck2_events::dispatch::EventDispatcher;
 use buck2_execute::digest::CasDigestFromReExt;
 use buck2_execute::digest::CasDigestToReExt;
@@ -277,9 +278,7 @@ impl DefaultIoHandler {
                 stat.file_count = 1;
                 self.io_executor
                     .execute_io_inline(|| {
-                        let data =
-                            zstd::bulk::decompress(&write.compressed_data, write.decompressed_size)
-                                .context("Error decompressing data")?;
+                        let data = write.decompress()?;
                         stat.total_bytes = write.decompressed_size as u64;
                         self.fs.write_file(&path, data, write.is_executable)
                     })
@@ -354,6 +353,9 @@ impl IoHandler for DefaultIoHandler {
                 _ => None,
             },
         };
+        // Materialization runs outside of the command that requested it, so make its events, such
+        // as the progress of downloads, go to that command.
+        let dispatcher = event_dispatcher.dupe();
         event_dispatcher
             .span_async(materialization_start, async move {
                 let path_string = path.as_str().to_owned();
@@ -361,9 +363,17 @@ impl IoHandler for DefaultIoHandler {
                     file_count: 0,
                     total_bytes: 0,
                 };
-                let res = self
-                    .materialize_entry_span(path, method.dupe(), entry, &mut stat, cancellations)
-                    .await;
+                let res = with_dispatcher_async(
+                    dispatcher,
+                    self.materialize_entry_span(
+                        path,
+                        method.dupe(),
+                        entry,
+                        &mut stat,
+                        cancellations,
+                    ),
+                )
+                .await;
                 let error = res.as_ref().err().map(|e| format!("{:#}", e));
 
                 (
@@ -548,9 +558,7 @@ struct WriteIoRequest {
 impl WriteIoRequest {
     fn execute_inner(&self, project_fs: &ProjectRoot) -> anyhow::Result<()> {
         cleanup_path(project_fs, &self.path)?;
-        let data =
-            zstd::bulk::decompress(&self.write.compressed_data, self.write.decompressed_size)
-                .context("Error decompressing data")?;
+        let data = self.write.decompress()?;
         project_fs.write_file(&self.path, data, self.write.is_executable)?;
         Ok(())
     }
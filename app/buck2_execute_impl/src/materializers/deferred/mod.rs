@@ -18,6 +18,8 @@ mod tests;
 use std::borrow::Cow;
 use std::collections::HashSet;
 use std::collections::VecDeque;
+use std::io::Read;
+use std::io::Write;
 use std::pin::Pin;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::AtomicUsize;
@@ -28,6 +30,8 @@ use std::task::Poll;
 
 use allocative::Allocative;
 use async_trait::async_trait;
+use buck2_common::compression::CompressionCodec;
+use buck2_common::compression::CompressionConfig;
 use buck2_common::file_ops::FileMetadata;
 use buck2_common::file_ops::TrackedFileDigest;
 use buck2_core::buck2_env;
@@ -138,6 +142,8 @@ pub struct DeferredMaterializerAccessor<T: IoHandler + 'static> {
     /// materializes them, otherwise skips them.
     materialize_final_artifacts: bool,
     defer_write_actions: bool,
+    /// How the contents of deferred write actions are compressed while they are held in memory.
+    write_compression: CompressionConfig,
 
     io: Arc<T>,
 
@@ -170,6 +176,7 @@ fn access_time_update_max_buffer_size() -> anyhow::Result<usize> {
 pub struct DeferredMaterializerConfigs {
     pub materialize_final_artifacts: bool,
     pub defer_write_actions: bool,
+    pub write_compression: CompressionConfig,
     pub ttl_refresh: TtlRefreshConfiguration,
     pub update_access_times: AccessTimesUpdates,
 }
@@ -801,19 +808,11 @@ impl<T: IoHandler + Allocative> Materializer for DeferredMaterializerAccessor<T>
                 is_executable,
             };
 
-            // NOTE: The zstd crate doesn't release extra capacity of its encoding buffer so it's
-            // important to do so here (or the compressed Vec is the same capacity as the input!).
-            let compressed_data = zstd::bulk::compress(&content, 0)
-                .with_context(|| format!("Error compressing {} bytes", content.len()))?
-                .into_boxed_slice();
+            let write = WriteFile::new(&content, is_executable, self.write_compression)?;
 
             paths.push(path);
             values.push(ArtifactValue::file(meta));
-            methods.push(ArtifactMaterializationMethod::Write(Arc::new(WriteFile {
-                compressed_data,
-                decompressed_size: content.len(),
-                is_executable,
-            })));
+            methods.push(ArtifactMaterializationMethod::Write(Arc::new(write)));
         }
 
         for (path, (value, method)) in std::iter::zip(paths, std::iter::zip(values.iter(), methods))
@@ -1038,6 +1037,7 @@ impl DeferredMaterializerAccessor<DefaultIoHandler> {
             command_sender,
             materialize_final_artifacts: configs.materialize_final_artifacts,
             defer_write_actions: configs.defer_write_actions,
+            write_compression: configs.write_compression,
             io,
             materializer_state_info,
             stats,
@@ -2228,6 +2228,59 @@ impl ExistingFutures {
 pub struct WriteFile {
     #[derivative(Debug = "ignore")]
     compressed_data: Box<[u8]>,
+    codec: CompressionCodec,
     decompressed_size: usize,
     is_executable: bool,
 }
+
+impl WriteFile {
+    fn new(
+        content: &[u8],
+        is_executable: bool,
+        compression: CompressionConfig,
+    ) -> anyhow::Result<Self> {
+        let compressed_data = match compression.codec {
+            CompressionCodec::None => content.to_vec(),
+            CompressionCodec::Gzip => {
+                let level = compression
+                    .level
+                    .map_or(flate2::Compression::fast(), |level| {
+                        flate2::Compression::new(level as u32)
+                    });
+                let mut encoder = flate2::write::GzEncoder::new(Vec::new(), level);
+                encoder
+                    .write_all(content)
+                    .and_then(|()| encoder.finish())
+                    .with_context(|| format!("Error compressing {} bytes", content.len()))?
+            }
+            CompressionCodec::Zstd => zstd::bulk::compress(content, compression.level.unwrap_or(0))
+                .with_context(|| format!("Error compressing {} bytes", content.len()))?,
+        };
+        Ok(Self {
+            // NOTE: The zstd crate doesn't release extra capacity of its encoding buffer so it's
+            // important to do so here (or the compressed Vec is the same capacity as the input!).
+            compressed_data: compressed_data.into_boxed_slice(),
+            codec: compression.codec,
+            decompressed_size: content.len(),
+            is_executable,
+        })
+    }
+
+    fn decompress(&self) -> anyhow::Result<Vec<u8>> {
+        let data = match self.codec {
+            CompressionCodec::None => self.compressed_data.to_vec(),
+            CompressionCodec::Gzip => {
+                let mut data = Vec::with_capacity(self.decompressed_size);
+                flate2::read::GzDecoder::new(&*self.compressed_data)
+                    .read_to_end(&mut data)
+                    .context("Error decompressing data")?;
+                data
+            }
+            CompressionCodec::Zstd => {
+                zstd::bulk::decompress(&self.compressed_data, self.decompressed_size)
+                    .context("Error decompressing data")?
+            }
+        };
+        Ok(data)
+    }
+}
@@ -22,6 +22,16 @@ use super::Version;
 use super::VersionTracker;
 use super::*;
 
+#[test]
+fn test_write_file_compression() -> anyhow::Result<()> {
+    let content = b"hello world ".repeat(100);
+    for compression in ["none", "gzip", "gzip:9", "zstd", "zstd:19"] {
+        let write = WriteFile::new(&content, false, compression.parse()?)?;
+        assert_eq!(content, write.decompress()?, "{}", compression);
+    }
+    Ok(())
+}
+
 #[test]
 fn test_find_artifacts() -> anyhow::Result<()> {
     let artifact1 = ProjectRelativePathBuf::unchecked_new("foo/bar/baz".to_owned());
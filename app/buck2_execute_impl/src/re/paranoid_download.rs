@@ -7,14 +7,26 @@
  * of this source tree.
  */
 
+use std::collections::HashMap;
+use std::fmt::Write;
 use std::ops::ControlFlow;
 use std::sync::Arc;
 
 use allocative::Allocative;
+use anyhow::Context as _;
+use buck2_common::file_ops::FileDigest;
+use buck2_common::file_ops::FileDigestConfig;
+use buck2_common::legacy_configs::init::ParanoidConfig;
+use buck2_core::directory::unordered_entry_walk;
+use buck2_core::directory::DirectoryEntry;
 use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_core::soft_error;
 use buck2_execute::artifact_value::ArtifactValue;
+use buck2_execute::digest_config::DigestConfig;
+use buck2_execute::directory::ActionDirectoryMember;
 use buck2_execute::execute::blocking::BlockingExecutor;
 use buck2_execute::execute::blocking::IoRequest;
 use buck2_execute::execute::clean_output_paths::CleanOutputPaths;
@@ -31,9 +43,83 @@ use futures::future::BoxFuture;
 use futures::future::FutureExt;
 use futures::future::Shared;
 use gazebo::prelude::*;
+use rand::Rng;
 
 use crate::materializers::immediate::cas_download;
 
+#[derive(buck2_error::Error, Debug)]
+enum ParanoidError {
+    #[error("Invalid `paranoid.{}` value `{}`: expected a probability between 0 and 1", .0, .1)]
+    InvalidProbability(&'static str, String),
+
+    #[error("Invalid `paranoid.category_verify_probability` entry `{}`: expected `category=probability`", .0)]
+    InvalidCategoryProbability(String),
+
+    #[error(
+        "{} downloaded file(s) did not match their expected digest (origin: {}). \
+        The files were quarantined in `{}`",
+        .count,
+        .origin,
+        .quarantine
+    )]
+    DigestMismatch {
+        count: usize,
+        origin: String,
+        quarantine: ProjectRelativePathBuf,
+    },
+}
+
+/// Which actions paranoid mode verifies, and how.
+#[derive(Allocative, Debug, Clone)]
+pub struct ParanoidVerification {
+    /// Probability used for action categories without an override.
+    default_probability: f64,
+    category_probability: HashMap<String, f64>,
+    verify_digests: bool,
+}
+
+impl ParanoidVerification {
+    pub fn from_config(config: &ParanoidConfig) -> anyhow::Result<Self> {
+        fn parse_probability(key: &'static str, value: &str) -> anyhow::Result<f64> {
+            match value.trim().parse::<f64>() {
+                Ok(p) if (0.0..=1.0).contains(&p) => Ok(p),
+                _ => Err(ParanoidError::InvalidProbability(key, value.to_owned()).into()),
+            }
+        }
+
+        let default_probability = match &config.verify_probability {
+            Some(v) => parse_probability("verify_probability", v)?,
+            None => 1.0,
+        };
+
+        let mut category_probability = HashMap::new();
+        if let Some(v) = &config.category_verify_probability {
+            for item in v.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+                let (category, probability) = item
+                    .split_once('=')
+                    .ok_or_else(|| ParanoidError::InvalidCategoryProbability(item.to_owned()))?;
+                category_probability.insert(
+                    category.trim().to_owned(),
+                    parse_probability("category_verify_probability", probability)?,
+                );
+            }
+        }
+
+        Ok(Self {
+            default_probability,
+            category_probability,
+            verify_digests: config.verify_digests,
+        })
+    }
+
+    fn probability(&self, category: &str) -> f64 {
+        self.category_probability
+            .get(category)
+            .copied()
+            .unwrap_or(self.default_probability)
+    }
+}
+
 /// Download eagerly from RE, without taking a claim until the files are on disk.
 #[derive(Allocative, Dupe, Clone)]
 pub struct ParanoidDownloader {
@@ -46,6 +132,10 @@ struct ParanoidDownloaderInner {
     io: Arc<dyn BlockingExecutor>,
     re: Arc<ReConnectionManager>,
     cache_path: ProjectRelativePathBuf,
+    /// Where downloads that fail digest verification are moved to, along with a report.
+    quarantine_path: ProjectRelativePathBuf,
+    digest_config: DigestConfig,
+    verification: ParanoidVerification,
 }
 
 impl ParanoidDownloader {
@@ -54,6 +144,9 @@ impl ParanoidDownloader {
         io: Arc<dyn BlockingExecutor>,
         re: Arc<ReConnectionManager>,
         cache_path: ProjectRelativePathBuf,
+        quarantine_path: ProjectRelativePathBuf,
+        digest_config: DigestConfig,
+        verification: ParanoidVerification,
     ) -> Self {
         Self {
             inner: Arc::new(ParanoidDownloaderInner {
@@ -61,9 +154,19 @@ impl ParanoidDownloader {
                 io,
                 re,
                 cache_path,
+                quarantine_path,
+                digest_config,
+                verification,
             }),
         }
     }
+
+    /// Whether the outputs of an action in this category should go through paranoid
+    /// downloads. Sampled independently for every action.
+    pub fn should_verify(&self, category: &str) -> bool {
+        let probability = self.inner.verification.probability(category);
+        probability >= 1.0 || (probability > 0.0 && rand::thread_rng().gen_bool(probability))
+    }
 }
 
 impl ParanoidDownloader {
@@ -132,6 +235,16 @@ impl ParanoidDownloader {
             }
         };
 
+        if self.inner.verification.verify_digests {
+            if let Err(e) = self.verify_digests(&info, &artifacts, cancellations).await {
+                // Report the corruption even if the action ends up being retried locally.
+                let e = match soft_error!("paranoid_digest_mismatch", e) {
+                    Ok(e) | Err(e) => e,
+                };
+                return ControlFlow::Break(manager.error("paranoid_verify", e));
+            }
+        }
+
         // Claim the request before copying the outputs.
         let manager = manager.claim().await;
 
@@ -172,6 +285,105 @@ impl ParanoidDownloader {
     }
 }
 
+impl ParanoidDownloader {
+    /// Re-hash the files we just downloaded and compare them with the digests RE gave us.
+    async fn verify_digests(
+        &self,
+        info: &CasDownloadInfo,
+        artifacts: &[(ProjectRelativePathBuf, ArtifactValue)],
+        cancellations: &CancellationContext<'_>,
+    ) -> anyhow::Result<()> {
+        let mut files = Vec::new();
+        for (path, value) in artifacts {
+            let mut walk = unordered_entry_walk(value.entry().as_ref());
+            while let Some((entry_path, entry)) = walk.next() {
+                if let DirectoryEntry::Leaf(ActionDirectoryMember::File(m)) = entry {
+                    files.push((path.join_normalized(entry_path.get())?, *m.digest.data()));
+                }
+            }
+        }
+
+        let quarantine = self
+            .inner
+            .quarantine_path
+            .join(ForwardRelativePath::new(&format!(
+                "{:016x}",
+                rand::thread_rng().gen::<u64>()
+            ))?);
+
+        self.inner
+            .io
+            .execute_io(
+                Box::new(VerifyDownloadedDigests {
+                    cache_path: self.inner.cache_path.clone(),
+                    files,
+                    digest_config: FileDigestConfig::build(
+                        self.inner.digest_config.cas_digest_config(),
+                    ),
+                    origin: info.origin.to_string(),
+                    quarantine,
+                }),
+                cancellations,
+            )
+            .await
+    }
+}
+
+/// Hash downloaded files. If any of them does not match, move the mismatching files into a
+/// quarantine directory and write a report next to them.
+struct VerifyDownloadedDigests {
+    cache_path: ProjectRelativePathBuf,
+    files: Vec<(ProjectRelativePathBuf, FileDigest)>,
+    digest_config: FileDigestConfig,
+    origin: String,
+    quarantine: ProjectRelativePathBuf,
+}
+
+impl IoRequest for VerifyDownloadedDigests {
+    fn execute(self: Box<Self>, project_fs: &ProjectRoot) -> anyhow::Result<()> {
+        let mut mismatches = Vec::new();
+        for (path, expected) in self.files {
+            let cache_path = project_fs.resolve(&self.cache_path.join(&path));
+            let actual = FileDigest::from_file_disk(&cache_path, self.digest_config)
+                .with_context(|| format!("Error hashing `{}`", cache_path))?;
+            if actual != expected {
+                mismatches.push((path, expected, actual));
+            }
+        }
+
+        if mismatches.is_empty() {
+            return Ok(());
+        }
+
+        let mut report = format!("origin: {}\n", self.origin);
+        for (path, expected, actual) in &mismatches {
+            writeln!(report, "{}: expected {}, got {}", path, expected, actual)?;
+
+            let from = project_fs.resolve(&self.cache_path.join(path));
+            let to = project_fs.resolve(&self.quarantine.join(path));
+            if let Some(p) = to.parent() {
+                fs_util::create_dir_all(p)?;
+            }
+            fs_util::rename(&from, &to)?;
+        }
+        fs_util::write(
+            project_fs.resolve(
+                &self
+                    .quarantine
+                    .join(ForwardRelativePath::unchecked_new("report.txt")),
+            ),
+            report,
+        )?;
+
+        Err(ParanoidError::DigestMismatch {
+            count: mismatches.len(),
+            origin: self.origin,
+            quarantine: self.quarantine,
+        }
+        .into())
+    }
+}
+
 struct CacheDownload {
     inner: Option<CacheDownloadInner>,
 }
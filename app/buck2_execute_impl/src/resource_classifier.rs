@@ -0,0 +1,163 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use host_sharing::ResourceClass;
+use parking_lot::Mutex;
+
+/// Below this rate of userspace instructions per second, a command spends most of its time
+/// waiting on IO rather than computing.
+const IO_BOUND_INSTRUCTIONS_PER_SECOND: f64 = 500_000_000.0;
+
+/// Number of local executions of a category needed before its history is used to classify it.
+const MIN_EXECUTIONS: u64 = 3;
+
+/// Decides which resource class a local command is scheduled against. In order of precedence, a
+/// command is classified by:
+///
+/// - The resource class declared by its rule.
+/// - Its category, if it is configured as IO-bound.
+/// - The rate of instructions of the previous local executions of its category, when the
+///   executor captured performance counters for them.
+///
+/// Everything else is CPU-bound.
+///
+/// The daemon keeps a classifier across commands, so that the history of a category carries
+/// over from one command to the next. Each command uses it with its own configured categories.
+pub struct ResourceClassifier {
+    io_categories: HashSet<String>,
+    history: Arc<Mutex<HashMap<String, CategoryHistory>>>,
+}
+
+#[derive(Default)]
+struct CategoryHistory {
+    executions: u64,
+    instructions: u64,
+    execution_time: Duration,
+}
+
+impl CategoryHistory {
+    fn is_io_bound(&self) -> bool {
+        let seconds = self.execution_time.as_secs_f64();
+        self.executions >= MIN_EXECUTIONS
+            && seconds > 0.0
+            && (self.instructions as f64 / seconds) < IO_BOUND_INSTRUCTIONS_PER_SECOND
+    }
+}
+
+impl ResourceClassifier {
+    pub fn new(io_categories: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            io_categories: io_categories.into_iter().collect(),
+            history: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// A classifier with these configured IO-bound categories, sharing the execution history of
+    /// this one.
+    pub fn with_io_categories(&self, io_categories: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            io_categories: io_categories.into_iter().collect(),
+            history: self.history.clone(),
+        }
+    }
+
+    pub fn classify(&self, category: &str, declared: Option<ResourceClass>) -> ResourceClass {
+        if let Some(declared) = declared {
+            return declared;
+        }
+        if self.io_categories.contains(category) {
+            return ResourceClass::Io;
+        }
+        match self.history.lock().get(category) {
+            Some(history) if history.is_io_bound() => ResourceClass::Io,
+            _ => ResourceClass::Cpu,
+        }
+    }
+
+    /// Record a local execution of a command of this category.
+    pub fn record(
+        &self,
+        category: &str,
+        execution_stats: Option<&buck2_data::CommandExecutionStats>,
+        execution_time: Duration,
+    ) {
+        let Some(instructions) = execution_stats.and_then(|s| s.cpu_instructions_user) else {
+            return;
+        };
+        let mut history = self.history.lock();
+        let history = history.entry(category.to_owned()).or_default();
+        history.executions += 1;
+        history.instructions += instructions;
+        history.execution_time += execution_time;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(instructions: u64) -> buck2_data::CommandExecutionStats {
+        buck2_data::CommandExecutionStats {
+            cpu_instructions_user: Some(instructions),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_declared_and_configured() {
+        let classifier = ResourceClassifier::new(["cxx_link".to_owned()]);
+        assert_eq!(classifier.classify("cxx_link", None), ResourceClass::Io);
+        assert_eq!(classifier.classify("cxx_compile", None), ResourceClass::Cpu);
+        assert_eq!(
+            classifier.classify("cxx_link", Some(ResourceClass::Cpu)),
+            ResourceClass::Cpu
+        );
+        assert_eq!(
+            classifier.classify("cxx_compile", Some(ResourceClass::Io)),
+            ResourceClass::Io
+        );
+    }
+
+    #[test]
+    fn test_history() {
+        let classifier = ResourceClassifier::new(Vec::new());
+        let second = Duration::from_secs(1);
+        for _ in 0..MIN_EXECUTIONS {
+            assert_eq!(classifier.classify("copy", None), ResourceClass::Cpu);
+            classifier.record("copy", Some(&stats(1_000_000)), second);
+            classifier.record("cxx_compile", Some(&stats(3_000_000_000)), second);
+            // Executions without performance counters are not recorded.
+            classifier.record("archive", None, second);
+        }
+        assert_eq!(classifier.classify("copy", None), ResourceClass::Io);
+        assert_eq!(classifier.classify("cxx_compile", None), ResourceClass::Cpu);
+        assert_eq!(classifier.classify("archive", None), ResourceClass::Cpu);
+    }
+
+    #[test]
+    fn test_history_shared_across_commands() {
+        let daemon = ResourceClassifier::new(Vec::new());
+
+        let first = daemon.with_io_categories(["cxx_link".to_owned()]);
+        for _ in 0..MIN_EXECUTIONS {
+            first.record("copy", Some(&stats(1_000_000)), Duration::from_secs(1));
+        }
+        assert_eq!(first.classify("cxx_link", None), ResourceClass::Io);
+
+        // The next command has other configured categories, but still knows about `copy`.
+        let second = daemon.with_io_categories(Vec::new());
+        assert_eq!(second.classify("copy", None), ResourceClass::Io);
+        assert_eq!(second.classify("cxx_link", None), ResourceClass::Cpu);
+    }
+}
@@ -265,7 +265,7 @@ impl FsSnapshot {
             let filetype = FileType::from(filetype);
             match filetype {
                 FileType::File => {
-                    let hash = file_hash(disk_path.as_maybe_relativized())?;
+                    let hash = file_hash(&disk_path.as_maybe_relativized())?;
                     self.add_entry(cell_path, EntryInfo::File(hash));
                 }
                 FileType::Directory => {
@@ -27,6 +27,7 @@ use tonic::Request;
 
 use crate::convert::decode_event_stream;
 use crate::run::decode_command_event_stream;
+use crate::run::output_limit::OutputLimit;
 use crate::run::GatherOutputStatus;
 
 #[derive(Clone, Dupe, Allocative)]
@@ -91,6 +92,7 @@ impl ForkserverClient {
         &self,
         req: buck2_forkserver_proto::CommandRequest,
         cancel: C,
+        output_limit: &OutputLimit,
     ) -> anyhow::Result<(GatherOutputStatus, Vec<u8>, Vec<u8>)>
     where
         C: Future<Output = ()> + Send + 'static,
@@ -123,7 +125,7 @@ impl ForkserverClient {
             .context("Error dispatching command to Forkserver")?
             .into_inner();
         let stream = decode_event_stream(stream);
-        decode_command_event_stream(stream).await
+        decode_command_event_stream(stream, output_limit).await
     }
 
     pub async fn set_log_filter(&self, log_filter: String) -> anyhow::Result<()> {
@@ -8,6 +8,7 @@
  */
 
 mod interruptible_async_read;
+pub mod output_limit;
 pub mod process_group;
 pub mod status_decoder;
 
@@ -37,6 +38,8 @@ use tokio_util::codec::FramedRead;
 
 use self::interruptible_async_read::InterruptNotifiable;
 use self::interruptible_async_read::InterruptibleAsyncRead;
+use self::output_limit::OutputCapture;
+use self::output_limit::OutputLimit;
 use self::status_decoder::DecodedStatus;
 use self::status_decoder::DefaultStatusDecoder;
 use self::status_decoder::StatusDecoder;
@@ -273,20 +276,23 @@ where
 
 pub(crate) async fn decode_command_event_stream<S>(
     stream: S,
+    output_limit: &OutputLimit,
 ) -> anyhow::Result<(GatherOutputStatus, Vec<u8>, Vec<u8>)>
 where
     S: Stream<Item = anyhow::Result<CommandEvent>>,
 {
     futures::pin_mut!(stream);
 
-    let mut stdout = Vec::<u8>::new();
-    let mut stderr = Vec::<u8>::new();
+    let mut stdout = OutputCapture::new(output_limit, "stdout");
+    let mut stderr = OutputCapture::new(output_limit, "stderr");
 
     while let Some(event) = stream.try_next().await? {
         match event {
-            CommandEvent::Stdout(bytes) => stdout.extend(&bytes),
-            CommandEvent::Stderr(bytes) => stderr.extend(&bytes),
-            CommandEvent::Exit(exit) => return Ok((exit, stdout, stderr)),
+            CommandEvent::Stdout(bytes) => stdout.push(&bytes).await,
+            CommandEvent::Stderr(bytes) => stderr.push(&bytes).await,
+            CommandEvent::Exit(exit) => {
+                return Ok((exit, stdout.finish().await, stderr.finish().await));
+            }
         }
     }
 
@@ -298,11 +304,25 @@ where
 pub async fn gather_output<T>(
     cmd: Command,
     cancellation: T,
+    output_limit: &OutputLimit,
 ) -> anyhow::Result<(GatherOutputStatus, Vec<u8>, Vec<u8>)>
 where
     T: Future<Output = anyhow::Result<GatherOutputStatus>> + Send,
 {
-    let cmd = prepare_command(cmd);
+    gather_output_with_priority(cmd, cancellation, output_limit, false).await
+}
+
+/// Like `gather_output`, but optionally runs the command at a lower CPU and IO priority.
+pub async fn gather_output_with_priority<T>(
+    cmd: Command,
+    cancellation: T,
+    output_limit: &OutputLimit,
+    low_priority: bool,
+) -> anyhow::Result<(GatherOutputStatus, Vec<u8>, Vec<u8>)>
+where
+    T: Future<Output = anyhow::Result<GatherOutputStatus>> + Send,
+{
+    let cmd = prepare_command_with_priority(cmd, low_priority);
 
     let process_details =
         spawn_retry_txt_busy(cmd, || tokio::time::sleep(Duration::from_millis(50))).await;
@@ -314,7 +334,7 @@ where
         DefaultKillProcess::default(),
         true,
     )?;
-    decode_command_event_stream(stream).await
+    decode_command_event_stream(stream, output_limit).await
 }
 
 /// Dependency injection for kill. We use this in testing.
@@ -371,11 +391,26 @@ pub fn maybe_absolutize_exe<'a>(
     Ok(exe.into())
 }
 
-pub fn prepare_command(mut cmd: Command) -> tokio::process::Command {
+pub fn prepare_command(cmd: Command) -> tokio::process::Command {
+    prepare_command_with_priority(cmd, false)
+}
+
+/// Like `prepare_command`, but optionally lowers the priority of the process when spawning it.
+/// The command itself is left unchanged.
+pub fn prepare_command_with_priority(
+    mut cmd: Command,
+    low_priority: bool,
+) -> tokio::process::Command {
     #[cfg(unix)]
     {
         use std::os::unix::process::CommandExt;
         cmd.process_group(0);
+        if low_priority {
+            // SAFETY: `lower_priority` only makes async-signal-safe system calls.
+            unsafe {
+                cmd.pre_exec(lower_priority);
+            }
+        }
     }
 
     #[cfg(windows)]
@@ -383,8 +418,16 @@ pub fn prepare_command(mut cmd: Command) -> tokio::process::Command {
         // On windows we create suspended process to assign it to a job (group) and then resume.
         // This is necessary because the process might finish before we add it to a job
         use std::os::windows::process::CommandExt;
+        // Processes created by a below normal priority process inherit its priority class.
+        let priority = if low_priority {
+            winapi::um::winbase::BELOW_NORMAL_PRIORITY_CLASS
+        } else {
+            0
+        };
         cmd.creation_flags(
-            winapi::um::winbase::CREATE_NO_WINDOW | winapi::um::winbase::CREATE_SUSPENDED,
+            winapi::um::winbase::CREATE_NO_WINDOW
+                | winapi::um::winbase::CREATE_SUSPENDED
+                | priority,
         );
     }
 
@@ -394,6 +437,40 @@ pub fn prepare_command(mut cmd: Command) -> tokio::process::Command {
     cmd.into()
 }
 
+/// Lower the CPU and IO priority of the current process, called between fork and exec.
+#[cfg(unix)]
+fn lower_priority() -> std::io::Result<()> {
+    #[cfg(target_os = "macos")]
+    {
+        // The background policy lowers both CPU and IO priority.
+        if unsafe { libc::setpriority(libc::PRIO_DARWIN_PROCESS, 0, libc::PRIO_DARWIN_BG) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, 19) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        // There is no libc wrapper for `ioprio_set`. This is the lowest priority of the
+        // best-effort class, which is the class used by `ionice -c 2 -n 7`.
+        const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+        const IOPRIO_CLASS_BE: libc::c_int = 2;
+        const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+        let ioprio = (IOPRIO_CLASS_BE << IOPRIO_CLASS_SHIFT) | 7;
+        if unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, ioprio) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
 /// fork-exec is a bit tricky in a busy process. We often have files open to writing just prior to
 /// executing them (as we download from RE), and many processes being spawned concurrently. We do
 /// close the fds properly before the exec, but what can happn is:
@@ -459,7 +536,8 @@ mod tests {
         };
         cmd.args(["-c", "echo hello"]);
 
-        let (status, stdout, stderr) = gather_output(cmd, futures::future::pending()).await?;
+        let (status, stdout, stderr) =
+            gather_output(cmd, futures::future::pending(), &OutputLimit::default()).await?;
         assert!(matches!(status, GatherOutputStatus::Finished { exit_code, .. } if exit_code == 0));
         assert_eq!(str::from_utf8(&stdout)?.trim(), "hello");
         assert_eq!(stderr, b"");
@@ -467,6 +545,27 @@ mod tests {
         Ok(())
     }
 
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_gather_output_with_low_priority() -> anyhow::Result<()> {
+        let mut cmd = background_command("sh");
+        // The nice value is the 19th field of `/proc/self/stat`, counting the command name, which
+        // ends with `)`, as the 2nd.
+        cmd.args(["-c", "cut -d ')' -f 2 /proc/self/stat | cut -d ' ' -f 18"]);
+
+        let (status, stdout, _stderr) = gather_output_with_priority(
+            cmd,
+            futures::future::pending(),
+            &OutputLimit::default(),
+            true,
+        )
+        .await?;
+        assert_matches!(status, GatherOutputStatus::Finished { exit_code: 0, .. });
+        assert_eq!(str::from_utf8(&stdout)?.trim(), "19");
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_gather_does_not_wait_for_children() -> anyhow::Result<()> {
         // If we wait for sleep, this will time out.
@@ -488,6 +587,7 @@ mod tests {
         let (status, stdout, stderr) = gather_output(
             cmd,
             timeout_into_cancellation(Some(Duration::from_secs(timeout))),
+            &OutputLimit::default(),
         )
         .await?;
         assert!(matches!(status, GatherOutputStatus::Finished { exit_code, .. } if exit_code == 0));
@@ -515,6 +615,7 @@ mod tests {
         let (status, stdout, _stderr) = gather_output(
             cmd,
             timeout_into_cancellation(Some(Duration::from_secs(timeout))),
+            &OutputLimit::default(),
         )
         .await?;
         assert!(matches!(status, GatherOutputStatus::TimedOut(..)));
@@ -601,6 +702,7 @@ mod tests {
         let (_status, stdout, _stderr) = gather_output(
             cmd,
             timeout_into_cancellation(Some(Duration::from_secs(timeout))),
+            &OutputLimit::default(),
         )
         .await?;
         let out = str::from_utf8(&stdout)?;
@@ -654,7 +756,8 @@ mod tests {
 
         let mut cmd = background_command("sh");
         cmd.arg("-c").arg("kill -KILL \"$$\"");
-        let (status, _stdout, _stderr) = gather_output(cmd, futures::future::pending()).await?;
+        let (status, _stdout, _stderr) =
+            gather_output(cmd, futures::future::pending(), &OutputLimit::default()).await?;
 
         assert_matches!(
             status,
@@ -728,7 +831,8 @@ mod tests {
             true,
         )?;
 
-        let (status, _stdout, _stderr) = decode_command_event_stream(stream).await?;
+        let (status, _stdout, _stderr) =
+            decode_command_event_stream(stream, &OutputLimit::default()).await?;
         assert!(matches!(status, GatherOutputStatus::TimedOut(..)));
 
         assert!(*killed.lock().unwrap());
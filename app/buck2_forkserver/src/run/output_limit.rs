@@ -0,0 +1,199 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::VecDeque;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::file_name::FileName;
+use tokio::io::AsyncWriteExt;
+
+/// Bounds how much of a command's stdout and stderr is kept in memory.
+#[derive(Clone, Debug, Default)]
+pub struct OutputLimit {
+    /// Maximum number of bytes kept for each stream. Past this, the middle of the stream is
+    /// dropped and only its head and tail are kept. `None` keeps everything.
+    pub max_bytes: Option<usize>,
+    /// Directory where the full contents of truncated streams are written.
+    pub spill_dir: Option<AbsNormPathBuf>,
+}
+
+/// Accumulates one output stream of a command, within an `OutputLimit`.
+pub(crate) struct OutputCapture<'a> {
+    limit: &'a OutputLimit,
+    stream: &'static str,
+    head: Vec<u8>,
+    tail: VecDeque<u8>,
+    truncated: bool,
+    total: u64,
+    spill: Option<(AbsNormPathBuf, tokio::fs::File)>,
+}
+
+impl<'a> OutputCapture<'a> {
+    pub(crate) fn new(limit: &'a OutputLimit, stream: &'static str) -> Self {
+        Self {
+            limit,
+            stream,
+            head: Vec::new(),
+            tail: VecDeque::new(),
+            truncated: false,
+            total: 0,
+            spill: None,
+        }
+    }
+
+    pub(crate) async fn push(&mut self, bytes: &[u8]) {
+        self.total += bytes.len() as u64;
+
+        let max_bytes = match self.limit.max_bytes {
+            Some(max_bytes) if self.truncated || self.head.len() + bytes.len() > max_bytes => {
+                max_bytes
+            }
+            _ => {
+                self.head.extend(bytes);
+                return;
+            }
+        };
+
+        if !self.truncated {
+            self.truncated = true;
+            self.spill = self.start_spill().await;
+        }
+
+        if let Some((path, file)) = &mut self.spill {
+            if let Err(e) = file.write_all(bytes).await {
+                tracing::warn!("Error writing command output to `{}`: {:#}", path, e);
+                self.spill = None;
+            }
+        }
+
+        let head_bytes = max_bytes / 2;
+        if self.head.len() > head_bytes {
+            self.tail.extend(self.head.drain(head_bytes..));
+        }
+        self.tail.extend(bytes);
+
+        let tail_bytes = max_bytes - head_bytes;
+        if self.tail.len() > tail_bytes {
+            self.tail.drain(..self.tail.len() - tail_bytes);
+        }
+    }
+
+    /// Create the spill file, and write what we have received so far to it.
+    async fn start_spill(&self) -> Option<(AbsNormPathBuf, tokio::fs::File)> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        let dir = self.limit.spill_dir.as_ref()?;
+        let res = async {
+            let name = format!(
+                "{}-{}.{}",
+                std::process::id(),
+                NEXT_ID.fetch_add(1, Ordering::Relaxed),
+                self.stream
+            );
+            let path = dir.join(FileName::new(&name)?);
+            tokio::fs::create_dir_all(dir).await?;
+            let mut file = tokio::fs::File::create(&path).await?;
+            file.write_all(&self.head).await?;
+            anyhow::Ok((path, file))
+        }
+        .await;
+
+        match res {
+            Ok(spill) => Some(spill),
+            Err(e) => {
+                tracing::warn!("Error creating file for command output: {:#}", e);
+                None
+            }
+        }
+    }
+
+    pub(crate) async fn finish(self) -> Vec<u8> {
+        if !self.truncated {
+            return self.head;
+        }
+
+        let full_output = match self.spill {
+            Some((path, mut file)) => match file.flush().await {
+                Ok(()) => format!("; full {} in `{}`", self.stream, path),
+                Err(e) => {
+                    tracing::warn!("Error writing command output to `{}`: {:#}", path, e);
+                    String::new()
+                }
+            },
+            None => String::new(),
+        };
+
+        let kept = self.head.len() + self.tail.len();
+        let mut out = self.head;
+        out.extend(
+            format!(
+                "\n[... {} bytes truncated{} ...]\n",
+                self.total - kept as u64,
+                full_output
+            )
+            .as_bytes(),
+        );
+        out.extend(self.tail);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_output_capture_unlimited() {
+        let limit = OutputLimit::default();
+        let mut capture = OutputCapture::new(&limit, "stdout");
+        capture.push(b"hello ").await;
+        capture.push(b"world").await;
+        assert_eq!(capture.finish().await, b"hello world");
+    }
+
+    #[tokio::test]
+    async fn test_output_capture_truncates() {
+        let limit = OutputLimit {
+            max_bytes: Some(6),
+            spill_dir: None,
+        };
+        let mut capture = OutputCapture::new(&limit, "stdout");
+        capture.push(b"abcd").await;
+        capture.push(b"efgh").await;
+        capture.push(b"ijkl").await;
+        assert_eq!(
+            String::from_utf8(capture.finish().await).unwrap(),
+            "abc\n[... 6 bytes truncated ...]\njkl"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_output_capture_spills() -> anyhow::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let limit = OutputLimit {
+            max_bytes: Some(4),
+            spill_dir: Some(AbsNormPathBuf::new(tempdir.path().to_owned())?),
+        };
+        let mut capture = OutputCapture::new(&limit, "stderr");
+        capture.push(b"abc").await;
+        capture.push(b"def").await;
+        let out = String::from_utf8(capture.finish().await)?;
+
+        let path = out
+            .split('`')
+            .nth(1)
+            .expect("output should point to the full stderr");
+        assert_eq!(std::fs::read_to_string(path)?, "abcdef");
+        assert!(out.starts_with("ab\n[... 2 bytes truncated; full stderr in"));
+        assert!(out.ends_with("ef"));
+        Ok(())
+    }
+}
@@ -44,7 +44,7 @@ use tonic::Streaming;
 
 use crate::convert::encode_event_stream;
 use crate::run::maybe_absolutize_exe;
-use crate::run::prepare_command;
+use crate::run::prepare_command_with_priority;
 use crate::run::process_group::ProcessGroup;
 use crate::run::status_decoder::DefaultStatusDecoder;
 use crate::run::status_decoder::MiniperfStatusDecoder;
@@ -97,12 +97,14 @@ impl Forkserver for UnixForkserverService {
                 .context("RequestEvent was not a CommandRequest!")?;
 
             let cancel = async move {
-                stream
-                    .message()
-                    .await?
-                    .and_then(|m| m.data)
-                    .and_then(|m| m.into_cancel_request())
-                    .context("RequestEvent was not a CancelRequest!")?;
+                // If the daemon hangs up without sending a CancelRequest (e.g. because the
+                // command was forcibly cancelled), nobody is waiting for the process anymore, so
+                // kill it rather than leave it running.
+                if let Ok(Some(msg)) = stream.message().await {
+                    msg.data
+                        .and_then(|m| m.into_cancel_request())
+                        .context("RequestEvent was not a CancelRequest!")?;
+                }
 
                 anyhow::Ok(GatherOutputStatus::Cancelled)
             };
@@ -116,6 +118,7 @@ impl Forkserver for UnixForkserverService {
                 enable_miniperf,
                 std_redirects,
                 graceful_shutdown_timeout_s,
+                low_priority,
             } = msg;
 
             let exe = OsStr::from_bytes(&exe);
@@ -161,7 +164,7 @@ impl Forkserver for UnixForkserverService {
                 }
             }
 
-            let mut cmd = prepare_command(cmd);
+            let mut cmd = prepare_command_with_priority(cmd, low_priority);
             let stream_stdio = std_redirects.is_none();
             if let Some(std_redirects) = std_redirects {
                 cmd.stdout(File::create(OsStr::from_bytes(&std_redirects.stdout))?);
@@ -8,6 +8,7 @@
  */
 
 use std::path::Path;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -28,7 +29,7 @@ use tokio_rustls::TlsConnector;
 
 use super::HttpClient;
 use super::RequestClient;
-use crate::proxy;
+use crate::proxy::ProxyConfig;
 use crate::stats::HttpNetworkStats;
 use crate::tls;
 use crate::x2p;
@@ -58,6 +59,8 @@ impl TimeoutConfig {
 
 pub struct HttpClientBuilder {
     tls_config: ClientConfig,
+    client_auth_cert: Option<PathBuf>,
+    ca_bundle: Option<PathBuf>,
     proxies: Vec<Proxy>,
     max_redirects: Option<usize>,
     supports_vpnless: bool,
@@ -65,11 +68,12 @@ pub struct HttpClientBuilder {
 }
 
 impl HttpClientBuilder {
-    /// Builds an http client compatible with OSS usage.
-    pub fn oss() -> anyhow::Result<Self> {
+    /// Builds an http client compatible with OSS usage. Proxy settings not set in
+    /// `proxy_config` are read from the environment.
+    pub fn oss(proxy_config: &ProxyConfig) -> anyhow::Result<Self> {
         tracing::debug!("Using OSS client");
         let mut builder = Self::https_with_system_roots()?;
-        builder.with_proxy_from_env()?;
+        builder.with_proxy_config(&proxy_config.clone().with_env_fallback()?)?;
         Ok(builder)
     }
 
@@ -92,9 +96,11 @@ impl HttpClientBuilder {
 
     /// Creates a barebones https client using system roots for TLS authentication.
     pub fn https_with_system_roots() -> anyhow::Result<Self> {
-        let tls_config = tls::tls_config_with_system_roots()?;
+        let tls_config = tls::tls_config_with_system_roots(None)?;
         Ok(Self {
             tls_config,
+            client_auth_cert: None,
+            ca_bundle: None,
             proxies: Vec::new(),
             max_redirects: None,
             supports_vpnless: false,
@@ -108,7 +114,24 @@ impl HttpClientBuilder {
     }
 
     pub fn with_client_auth_cert<P: AsRef<Path>>(&mut self, path: P) -> anyhow::Result<&mut Self> {
-        let tls_config = tls::tls_config_with_single_cert(path.as_ref(), path.as_ref())?;
+        self.client_auth_cert = Some(path.as_ref().to_owned());
+        self.rebuild_tls_config()
+    }
+
+    /// Trust the PEM-encoded CA certificates in `path` in addition to the system roots.
+    pub fn with_ca_bundle<P: AsRef<Path>>(&mut self, path: P) -> anyhow::Result<&mut Self> {
+        self.ca_bundle = Some(path.as_ref().to_owned());
+        self.rebuild_tls_config()
+    }
+
+    fn rebuild_tls_config(&mut self) -> anyhow::Result<&mut Self> {
+        let ca_bundle = self.ca_bundle.as_deref();
+        let tls_config = match &self.client_auth_cert {
+            Some(cert) => {
+                tls::tls_config_with_single_cert(cert.as_path(), cert.as_path(), ca_bundle)?
+            }
+            None => tls::tls_config_with_system_roots(ca_bundle)?,
+        };
         Ok(self.with_tls_config(tls_config))
     }
 
@@ -122,16 +145,17 @@ impl HttpClientBuilder {
         self
     }
 
-    pub fn with_proxy_from_env(&mut self) -> anyhow::Result<&mut Self> {
-        if let Some(proxy) = proxy::https_proxy_from_env()? {
-            self.with_proxy(proxy);
-        }
-        if let Some(proxy) = proxy::http_proxy_from_env()? {
+    pub fn with_proxy_config(&mut self, config: &ProxyConfig) -> anyhow::Result<&mut Self> {
+        for proxy in config.proxies()? {
             self.with_proxy(proxy);
         }
         Ok(self)
     }
 
+    pub fn with_proxy_from_env(&mut self) -> anyhow::Result<&mut Self> {
+        self.with_proxy_config(&ProxyConfig::from_env()?)
+    }
+
     pub fn with_connect_timeout(&mut self, connect_timeout: Option<Duration>) -> &mut Self {
         if let Some(timeout_config) = &mut self.timeout_config {
             timeout_config.connect_timeout = connect_timeout;
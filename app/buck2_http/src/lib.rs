@@ -23,6 +23,7 @@ mod x2p;
 pub use client::to_bytes;
 pub use client::HttpClient;
 pub use client::HttpClientBuilder;
+pub use proxy::ProxyConfig;
 
 fn http_error_label(status: StatusCode) -> &'static str {
     if status.is_server_error() {
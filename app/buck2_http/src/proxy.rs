@@ -29,47 +29,68 @@ fn env_to_string(env: &'static str) -> anyhow::Result<Option<String>> {
         .map_err(|original| anyhow::anyhow!("Invalid utf8 string: '{:?}'", original))
 }
 
-fn noproxy_from_env(scheme: Scheme) -> anyhow::Result<Option<NoProxy>> {
-    Ok(env_to_string("NO_PROXY")?.map(|no_proxy| NoProxy::new(scheme, no_proxy)))
+/// Proxy settings for HTTP clients. Values that are not set explicitly (e.g. in buckconfig) are
+/// read from the standard `HTTPS_PROXY`, `HTTP_PROXY` and `NO_PROXY` environment variables.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProxyConfig {
+    pub https_proxy: Option<String>,
+    pub http_proxy: Option<String>,
+    pub no_proxy: Option<String>,
 }
 
-/// Returns a hyper_proxy::Proxy struct that proxies connections to the uri at
-/// $HTTPS_PROXY (or $https_proxy if the former is unset). Respects $NO_PROXY.
-pub(super) fn https_proxy_from_env() -> anyhow::Result<Option<Proxy>> {
-    if let Some(https_proxy) = env_to_string("HTTPS_PROXY")? {
-        let uri: DefaultSchemeUri = https_proxy
-            .parse()
-            .with_context(|| format!("Invalid HTTPS_PROXY uri: {}", https_proxy))?;
-        if let Some(no_proxy) = noproxy_from_env(Scheme::HTTPS)? {
-            Ok(Some(Proxy::new(
-                no_proxy.into_proxy_intercept(),
-                uri.into(),
-            )))
-        } else {
-            Ok(Some(Proxy::new(Intercept::Https, uri.into())))
+impl ProxyConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        Self::default().with_env_fallback()
+    }
+
+    /// Fill in unset values from the environment.
+    pub fn with_env_fallback(self) -> anyhow::Result<Self> {
+        Ok(Self {
+            https_proxy: self
+                .https_proxy
+                .map_or_else(|| env_to_string("HTTPS_PROXY"), |v| Ok(Some(v)))?,
+            http_proxy: self
+                .http_proxy
+                .map_or_else(|| env_to_string("HTTP_PROXY"), |v| Ok(Some(v)))?,
+            no_proxy: self
+                .no_proxy
+                .map_or_else(|| env_to_string("NO_PROXY"), |v| Ok(Some(v)))?,
+        })
+    }
+
+    /// Returns hyper_proxy::Proxy structs that proxy https connections to `https_proxy` and
+    /// http connections to `http_proxy`, except for hosts matching `no_proxy`.
+    pub(super) fn proxies(&self) -> anyhow::Result<Vec<Proxy>> {
+        let mut proxies = Vec::new();
+        if let Some(https_proxy) = &self.https_proxy {
+            proxies.push(self.proxy(
+                https_proxy,
+                Scheme::HTTPS,
+                Intercept::Https,
+                "HTTPS_PROXY",
+            )?);
         }
-    } else {
-        Ok(None)
+        if let Some(http_proxy) = &self.http_proxy {
+            proxies.push(self.proxy(http_proxy, Scheme::HTTP, Intercept::Http, "HTTP_PROXY")?);
+        }
+        Ok(proxies)
     }
-}
 
-/// Returns a hyper_proxy::Proxy struct that proxies connections to the uri at
-/// $HTTP_PROXY (or $http_proxy if the former is unset). Respects $NO_PROXY.
-pub(super) fn http_proxy_from_env() -> anyhow::Result<Option<Proxy>> {
-    if let Some(http_proxy) = env_to_string("HTTP_PROXY")? {
-        let uri: DefaultSchemeUri = http_proxy
+    fn proxy(
+        &self,
+        proxy: &str,
+        scheme: Scheme,
+        intercept: Intercept,
+        name: &str,
+    ) -> anyhow::Result<Proxy> {
+        let uri: DefaultSchemeUri = proxy
             .parse()
-            .with_context(|| format!("Invalid HTTP_PROXY uri: {}", http_proxy))?;
-        if let Some(no_proxy) = noproxy_from_env(Scheme::HTTP)? {
-            Ok(Some(Proxy::new(
-                no_proxy.into_proxy_intercept(),
-                uri.into(),
-            )))
-        } else {
-            Ok(Some(Proxy::new(Intercept::Http, uri.into())))
-        }
-    } else {
-        Ok(None)
+            .with_context(|| format!("Invalid {} uri: {}", name, proxy))?;
+        let intercept = match &self.no_proxy {
+            Some(no_proxy) => NoProxy::new(scheme, no_proxy).into_proxy_intercept(),
+            None => intercept,
+        };
+        Ok(Proxy::new(intercept, uri.into()))
     }
 }
 
@@ -230,6 +251,29 @@ mod tests {
         s.parse().unwrap()
     }
 
+    #[test]
+    fn test_proxy_config_proxies() -> anyhow::Result<()> {
+        let config = ProxyConfig {
+            https_proxy: Some("proxy.example.com:3128".to_owned()),
+            http_proxy: None,
+            no_proxy: Some(".internal.example.com".to_owned()),
+        };
+        let proxies = config.proxies()?;
+        assert_eq!(1, proxies.len());
+        assert_eq!(&uri("http://proxy.example.com:3128/"), proxies[0].uri());
+        assert!(
+            proxies[0]
+                .intercept()
+                .matches(&uri("https://www.example.com/"))
+        );
+        assert!(
+            !proxies[0]
+                .intercept()
+                .matches(&uri("https://a.internal.example.com/"))
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_domain_match() {
         let d = Domain(".facebook.com".to_owned());
@@ -41,6 +41,38 @@ fn load_system_root_certs_disk(path: &OsStr) -> anyhow::Result<Vec<Vec<u8>>> {
     Ok(certs)
 }
 
+/// Load a PEM-encoded bundle of additional CA certificates, e.g. for a corporate
+/// TLS-intercepting proxy.
+fn load_ca_bundle(path: &Path) -> anyhow::Result<Vec<Certificate>> {
+    let file = File::open(path)
+        .with_context(|| format!("Error opening CA bundle `{}`", path.display()))?;
+    let mut reader = BufReader::new(file);
+    let certs = rustls_pemfile::certs(&mut reader)
+        .with_context(|| format!("Error parsing CA bundle `{}`", path.display()))?
+        .into_map(Certificate);
+    if certs.is_empty() {
+        return Err(anyhow::anyhow!(
+            "CA bundle `{}` contains no certificates",
+            path.display()
+        ));
+    }
+    Ok(certs)
+}
+
+/// Load system root certs, trying a few different methods to get a valid root
+/// certificate store, and add the certificates from `ca_bundle` if set.
+fn load_root_certs(ca_bundle: Option<&Path>) -> anyhow::Result<RootCertStore> {
+    let mut roots = load_system_root_certs()?;
+    if let Some(ca_bundle) = ca_bundle {
+        for cert in load_ca_bundle(ca_bundle)? {
+            roots
+                .add(&cert)
+                .with_context(|| format!("Invalid certificate in `{}`", ca_bundle.display()))?;
+        }
+    }
+    Ok(roots)
+}
+
 /// Load system root certs, trying a few different methods to get a valid root
 /// certificate store.
 fn load_system_root_certs() -> anyhow::Result<RootCertStore> {
@@ -105,8 +137,8 @@ fn load_cert_pair<P: AsRef<Path>>(
     Ok((certs, key))
 }
 
-pub fn tls_config_with_system_roots() -> anyhow::Result<ClientConfig> {
-    let system_roots = load_system_root_certs()?;
+pub fn tls_config_with_system_roots(ca_bundle: Option<&Path>) -> anyhow::Result<ClientConfig> {
+    let system_roots = load_root_certs(ca_bundle)?;
     Ok(ClientConfig::builder()
         .with_safe_defaults()
         .with_root_certificates(system_roots)
@@ -116,8 +148,9 @@ pub fn tls_config_with_system_roots() -> anyhow::Result<ClientConfig> {
 pub fn tls_config_with_single_cert<P: AsRef<Path>>(
     cert_path: P,
     key_path: P,
+    ca_bundle: Option<&Path>,
 ) -> anyhow::Result<ClientConfig> {
-    let system_roots = load_system_root_certs()?;
+    let system_roots = load_root_certs(ca_bundle)?;
     let (cert, key) =
         load_cert_pair(cert_path, key_path).context("Error loading certificate pair")?;
     ClientConfig::builder()
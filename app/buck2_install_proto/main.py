@@ -8,10 +8,12 @@
 
 import argparse
 import os
+import queue
 import signal
 import subprocess
 import sys
 import threading
+import uuid
 from concurrent.futures import ThreadPoolExecutor
 from pathlib import Path
 from typing import Optional
@@ -24,6 +26,7 @@ class RsyncInstallerService(install_pb2_grpc.InstallerServicer):
     def __init__(self, stop_event, argsparse, *args, **kwargs):
         self.args = argsparse
         self.stop_event = stop_event
+        self.logs = queue.Queue()
         if argsparse.install_location == "":
             self.dst = argsparse.dst
         else:
@@ -33,15 +36,43 @@ class RsyncInstallerService(install_pb2_grpc.InstallerServicer):
         install_id = request.install_id
         files = request.files
 
-        print(
-            f"Received request with install info: {install_id=:} and {len(files)} files"
+        self.log(
+            install_pb2.INFO,
+            f"Received request with install info: {install_id=:} and {len(files)} files",
+            install_id,
         )
 
         install_response = install_pb2.InstallResponse()
         install_response.install_id = install_id
+        # Files are copied one by one, so a previous install can always be updated.
+        install_response.incremental = True
+        install_response.device = self.dst
+        install_response.installation = self.installation()
         return install_response
 
+    def installation(self):
+        # A marker created in the destination by the first install, so that every
+        # file is pushed again once the destination is deleted. Remote destinations
+        # are not checked, so every file is pushed to them.
+        if self.args.install_location != "":
+            return ""
+        marker = Path(self.dst) / ".buck2_installation"
+        if not marker.exists():
+            marker.parent.mkdir(parents=True, exist_ok=True)
+            marker.write_text(uuid.uuid4().hex)
+        return marker.read_text()
+
     def FileReady(self, request, _context):
+        if request.unchanged:
+            self.log(
+                install_pb2.DEBUG,
+                f"Skipping unchanged `{request.name}`",
+                request.install_id,
+            )
+            return install_pb2.FileResponse(
+                install_id=request.install_id, name=request.name, path=request.path
+            )
+
         (_out, stderr, code) = self.rsync_install(
             request.path, os.path.join(self.dst, request.name)
         )
@@ -53,12 +84,37 @@ class RsyncInstallerService(install_pb2_grpc.InstallerServicer):
 
         if code != 0:
             error_detail = install_pb2.ErrorDetail()
-            error_detail.message = stderr
+            error_detail.message = f"rsync of `{request.name}` exited with {code}"
+            error_detail.category = "rsync_failed"
+            error_detail.device = self.args.install_location
+            error_detail.details = stderr
             response["error_detail"] = error_detail
+            self.log(install_pb2.ERROR, stderr, request.install_id)
+        else:
+            self.log(
+                install_pb2.DEBUG,
+                f"Installed `{request.name}` to {self.dst}",
+                request.install_id,
+            )
 
         file_response = install_pb2.FileResponse(**response)
         return file_response
 
+    def StreamLogs(self, _request, _context):
+        # Keep streaming until shutdown, then flush whatever is left.
+        while not self.stop_event.is_set() or not self.logs.empty():
+            try:
+                yield self.logs.get(timeout=0.1)
+            except queue.Empty:
+                continue
+
+    def log(self, level, message, install_id=""):
+        self.logs.put(
+            install_pb2.InstallerLog(
+                level=level, message=message, install_id=install_id
+            )
+        )
+
     def ShutdownServer(self, _request, _context):
         shutdown(self.stop_event)
         response = install_pb2.ShutdownResponse()
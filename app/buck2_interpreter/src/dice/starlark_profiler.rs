@@ -86,11 +86,12 @@ impl StarlarkProfilerConfiguration {
         match self {
             StarlarkProfilerConfiguration::None
             | StarlarkProfilerConfiguration::ProfileLastLoading(_)
-            | StarlarkProfilerConfiguration::ProfileLastAnalysis(_)
-            | StarlarkProfilerConfiguration::ProfileBxl(_) => {
+            | StarlarkProfilerConfiguration::ProfileLastAnalysis(_) => {
                 StarlarkProfileModeOrInstrumentation::None
             }
-            StarlarkProfilerConfiguration::ProfileAnalysisRecursively(profile_mode) => {
+            // The analyses triggered by the BXL are profiled along with it.
+            StarlarkProfilerConfiguration::ProfileAnalysisRecursively(profile_mode)
+            | StarlarkProfilerConfiguration::ProfileBxl(profile_mode) => {
                 StarlarkProfileModeOrInstrumentation::Profile(profile_mode.dupe())
             }
         }
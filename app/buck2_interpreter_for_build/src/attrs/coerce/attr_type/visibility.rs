@@ -14,19 +14,21 @@ use buck2_node::attrs::coercion_context::AttrCoercionContext;
 use buck2_node::attrs::configurable::AttrIsConfigurable;
 use buck2_node::visibility::VisibilityPattern;
 use buck2_node::visibility::VisibilityWithinViewBuilder;
+use starlark::values::type_repr::StarlarkTypeRepr;
 use starlark::values::Value;
 
 use crate::attrs::coerce::attr_type::list::coerce_list;
 use crate::attrs::coerce::attr_type::ty_maybe_select::TyMaybeSelect;
 use crate::attrs::coerce::attr_type::AttrTypeExt;
 use crate::attrs::coerce::AttrTypeCoerce;
+use crate::interpreter::package_group::StarlarkPackageGroup;
 use crate::interpreter::selector::StarlarkSelector;
 
 #[derive(Debug, buck2_error::Error)]
 enum VisibilityAttrTypeCoerceError {
     #[error("Visibility attribute is not configurable (internal error)")]
     AttrTypeNotConfigurable,
-    #[error("Visibility must be a list of string, got `{0}`")]
+    #[error("Visibility must be a list of strings and package groups, got `{0}`")]
     WrongType(String),
     #[error("Visibility attribute is not configurable (i.e. cannot use `select()`): `{0}`")]
     NotConfigurable(String),
@@ -48,7 +50,14 @@ impl AttrTypeCoerce for VisibilityAttrType {
     }
 
     fn starlark_type(&self) -> TyMaybeSelect {
-        AttrType::list(AttrType::string()).starlark_type()
+        let group = || TyMaybeSelect::Basic(StarlarkPackageGroup::starlark_type_repr());
+        TyMaybeSelect::Union(vec![
+            TyMaybeSelect::List(Box::new(TyMaybeSelect::Union(vec![
+                AttrType::string().starlark_type(),
+                group(),
+            ]))),
+            group(),
+        ])
     }
 }
 
@@ -56,6 +65,12 @@ pub(crate) fn parse_visibility_with_view(
     ctx: &dyn AttrCoercionContext,
     attr: Value,
 ) -> anyhow::Result<VisibilityWithinViewBuilder> {
+    if let Some(group) = StarlarkPackageGroup::from_value(attr) {
+        let mut builder = VisibilityWithinViewBuilder::with_capacity(0);
+        group.add_to(&mut builder);
+        return Ok(builder);
+    }
+
     let list = match coerce_list(attr) {
         Ok(list) => list,
         Err(e) => {
@@ -68,6 +83,10 @@ pub(crate) fn parse_visibility_with_view(
 
     let mut builder = VisibilityWithinViewBuilder::with_capacity(list.len());
     for item in list {
+        if let Some(group) = StarlarkPackageGroup::from_value(*item) {
+            group.add_to(&mut builder);
+            continue;
+        }
         let Some(item) = item.unpack_str() else {
             if StarlarkSelector::from_value(*item).is_some() {
                 return Err(VisibilityAttrTypeCoerceError::NotConfigurable(attr.to_repr()).into());
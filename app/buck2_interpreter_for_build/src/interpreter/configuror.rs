@@ -21,6 +21,7 @@ use buck2_interpreter::file_loader::LoadedModules;
 use buck2_interpreter::package_imports::ImplicitImport;
 use buck2_interpreter::paths::module::StarlarkModulePath;
 use buck2_interpreter::prelude_path::PreludePath;
+use buck2_node::metadata::map::MetadataMap;
 use buck2_node::super_package::SuperPackage;
 use dupe::Dupe;
 use starlark::environment::Globals;
@@ -203,6 +204,8 @@ impl BuildInterpreterConfiguror {
         );
 
         let imports = loaded_modules.imports().cloned().collect();
+        let package_metadata =
+            MetadataMap::from_package_values(super_package.package_values().package_values_json()?);
 
         Ok(ModuleInternals::new(
             attr_coercer,
@@ -213,6 +216,7 @@ impl BuildInterpreterConfiguror {
             skip_targets_with_duplicate_names,
             package_listing,
             super_package,
+            package_metadata,
         ))
     }
 
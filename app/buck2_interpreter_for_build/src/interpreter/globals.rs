@@ -32,6 +32,7 @@ use crate::interpreter::functions::soft_error::register_soft_error;
 use crate::interpreter::functions::starlark::register_set_starlark_peak_allocated_byte_limit;
 use crate::interpreter::functions::warning::register_warning;
 use crate::interpreter::natives::register_module_natives;
+use crate::interpreter::package_group::register_package_group;
 use crate::interpreter::selector::register_select;
 use crate::plugins::register_plugins;
 use crate::rule::register_rule_function;
@@ -66,6 +67,7 @@ pub fn register_universal_natives(builder: &mut GlobalsBuilder) {
     register_target_label(builder);
     register_path(builder);
     register_select(builder);
+    register_package_group(builder);
     register_promise(builder);
     register_sha256(builder);
     register_dedupe(builder);
@@ -27,5 +27,6 @@ pub mod module_internals;
 pub mod natives;
 pub mod package_file_calculation;
 pub mod package_file_extra;
+pub(crate) mod package_group;
 pub mod selector;
 pub mod testing;
@@ -21,6 +21,7 @@ use buck2_core::package::package_relative_path::PackageRelativePath;
 use buck2_core::target::name::TargetNameRef;
 use buck2_events::dispatch::console_message;
 use buck2_interpreter::package_imports::ImplicitImport;
+use buck2_node::metadata::map::MetadataMap;
 use buck2_node::nodes::eval_result::EvaluationResult;
 use buck2_node::nodes::targets_map::TargetsMap;
 use buck2_node::nodes::targets_map::TargetsMapRecordError;
@@ -87,6 +88,8 @@ pub struct ModuleInternals {
     /// The files owned by this directory. Is `None` for .bzl files.
     package_listing: PackageListing,
     pub(crate) super_package: SuperPackage,
+    /// The metadata of the package, propagated to its targets.
+    package_metadata: MetadataMap,
 }
 
 #[derive(Debug)]
@@ -126,6 +129,7 @@ impl ModuleInternals {
         skip_targets_with_duplicate_names: bool,
         package_listing: PackageListing,
         super_package: SuperPackage,
+        package_metadata: MetadataMap,
     ) -> Self {
         Self {
             attr_coercion_context,
@@ -137,6 +141,7 @@ impl ModuleInternals {
             skip_targets_with_duplicate_names,
             package_listing,
             super_package,
+            package_metadata,
         }
     }
 
@@ -184,6 +189,7 @@ impl ModuleInternals {
                             package: Arc::new(Package {
                                 buildfile_path: self.buildfile_path.dupe(),
                                 oncall,
+                                metadata: self.package_metadata.clone(),
                             }),
                             recorder: TargetsRecorder::new(),
                         });
@@ -0,0 +1,127 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::fmt;
+use std::fmt::Display;
+
+use allocative::Allocative;
+use buck2_core::pattern::ParsedPattern;
+use buck2_node::visibility::VisibilityPattern;
+use buck2_node::visibility::VisibilityWithinViewBuilder;
+use starlark::any::ProvidesStaticType;
+use starlark::environment::GlobalsBuilder;
+use starlark::eval::Evaluator;
+use starlark::starlark_module;
+use starlark::starlark_simple_value;
+use starlark::values::list_or_tuple::UnpackListOrTuple;
+use starlark::values::starlark_value;
+use starlark::values::NoSerialize;
+use starlark::values::StarlarkValue;
+
+use crate::interpreter::build_context::BuildContext;
+
+/// A reusable set of visibility patterns, created with `package_group()`.
+///
+/// Patterns are resolved relative to the cell of the file which declared the group,
+/// and included groups are flattened at creation, so a group is just a list of patterns.
+#[derive(Debug, ProvidesStaticType, NoSerialize, Allocative)]
+pub(crate) struct StarlarkPackageGroup {
+    public: bool,
+    patterns: Vec<VisibilityPattern>,
+}
+
+starlark_simple_value!(StarlarkPackageGroup);
+
+impl Display for StarlarkPackageGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "package_group([")?;
+        let mut first = true;
+        if self.public {
+            write!(f, "\"{}\"", VisibilityPattern::PUBLIC)?;
+            first = false;
+        }
+        for pattern in &self.patterns {
+            if !first {
+                write!(f, ", ")?;
+            }
+            write!(f, "\"{}\"", pattern)?;
+            first = false;
+        }
+        write!(f, "])")
+    }
+}
+
+#[starlark_value(type = "PackageGroup")]
+impl<'v> StarlarkValue<'v> for StarlarkPackageGroup {}
+
+impl StarlarkPackageGroup {
+    pub(crate) fn add_to(&self, builder: &mut VisibilityWithinViewBuilder) {
+        if self.public {
+            builder.add_public();
+        }
+        for pattern in &self.patterns {
+            builder.add(pattern.clone());
+        }
+    }
+}
+
+#[starlark_module]
+pub(crate) fn register_package_group(globals: &mut GlobalsBuilder) {
+    /// Declare a reusable group of visibility patterns, which can be used in place of
+    /// (or as an element of) `visibility` and `within_view` lists.
+    ///
+    /// `packages` are target patterns (or `"PUBLIC"`) resolved relative to the cell
+    /// of the file calling this function. `includes` are other package groups whose
+    /// patterns are added to this one.
+    ///
+    /// ```python
+    /// # //visibility/groups.bzl
+    /// CORE = package_group(packages = ["//core/..."])
+    /// ANDROID = package_group(packages = ["//android/..."], includes = [CORE])
+    ///
+    /// # //lib/BUCK
+    /// load("//visibility:groups.bzl", "ANDROID")
+    /// java_library(name = "lib", visibility = [ANDROID, "//tools:lint"])
+    /// ```
+    fn package_group<'v>(
+        #[starlark(require = named, default = UnpackListOrTuple::default())]
+        packages: UnpackListOrTuple<String>,
+        #[starlark(require = named, default = UnpackListOrTuple::default())]
+        includes: UnpackListOrTuple<&'v StarlarkPackageGroup>,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<StarlarkPackageGroup> {
+        let build_context = BuildContext::from_context(eval)?;
+        let cell_name = build_context.cell_info().name().name();
+        let cell_resolver = build_context.cell_info().cell_resolver();
+
+        let mut public = false;
+        let mut patterns = Vec::with_capacity(packages.items.len());
+        for package in &packages.items {
+            if package == VisibilityPattern::PUBLIC {
+                public = true;
+            } else {
+                patterns.push(VisibilityPattern(ParsedPattern::parse_precise(
+                    package,
+                    cell_name,
+                    cell_resolver,
+                )?));
+            }
+        }
+        for include in includes.items {
+            public |= include.public;
+            for pattern in &include.patterns {
+                if !patterns.contains(pattern) {
+                    patterns.push(pattern.clone());
+                }
+            }
+        }
+
+        Ok(StarlarkPackageGroup { public, patterns })
+    }
+}
@@ -29,6 +29,7 @@ use buck2_interpreter::factory::StarlarkPassthroughProvider;
 use buck2_interpreter::file_loader::LoadedModule;
 use buck2_interpreter::file_loader::LoadedModules;
 use buck2_interpreter::import_paths::ImplicitImportPaths;
+use buck2_interpreter::paths::bxl::BxlFilePath;
 use buck2_interpreter::paths::module::OwnedStarlarkModulePath;
 use buck2_interpreter::paths::module::StarlarkModulePath;
 use buck2_interpreter::paths::path::StarlarkPath;
@@ -101,7 +102,7 @@ pub fn cells(extra_root_config: Option<&str>) -> anyhow::Result<CellsData> {
         CellName::testing_new("root") =>
         LegacyBuckConfig::parse_with_file_ops(
             root_path,
-            &mut TestConfigParserFileOps::new(&[
+            &TestConfigParserFileOps::new(&[
                 (
                     "/root",
                     indoc!(
@@ -275,6 +276,32 @@ impl Tester {
         ))
     }
 
+    /// Evaluate a bxl file, with anything from `add_import` in the environment
+    pub fn eval_bxl_file(&self, path: &BxlFilePath, content: &str) -> anyhow::Result<LoadedModule> {
+        let interpreter = self.interpreter()?;
+        let ParseData(ast, _) =
+            interpreter.parse(StarlarkPath::BxlFile(path), content.to_owned())??;
+        let buckconfig = self
+            .configs
+            .get(self.cell_alias_resolver.resolve_self())
+            .unwrap();
+        let root_buckconfig = self.configs.get(self.cell_resolver.root_cell()).unwrap();
+        let mut provider = StarlarkPassthroughProvider;
+        let env = interpreter.eval_module(
+            StarlarkModulePath::BxlFile(path),
+            buckconfig,
+            root_buckconfig,
+            ast,
+            self.loaded_modules.clone(),
+            &mut provider,
+        )?;
+        Ok(LoadedModule::new(
+            OwnedStarlarkModulePath::BxlFile(path.clone()),
+            self.loaded_modules.clone(),
+            env,
+        ))
+    }
+
     /// Evaluate a build file, adding anything from `add_import` to the
     /// environment
     pub fn eval_build_file(
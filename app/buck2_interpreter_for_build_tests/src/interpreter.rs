@@ -192,7 +192,7 @@ fn cells() -> CellsData {
         config_paths: _,
     } = BuckConfigBasedCells::parse_with_file_ops(
         &project_fs,
-        &mut TestConfigParserFileOps::new(&[(
+        &TestConfigParserFileOps::new(&[(
             "/.buckconfig",
             indoc!(
                 r#"
@@ -0,0 +1,70 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use buck2_core::fs::project::ProjectRootTemp;
+use buck2_core::target::label::TargetLabel;
+use buck2_node::nodes::frontend::TargetGraphCalculation;
+use buck2_node::visibility::VisibilitySpecification;
+
+use crate::tests::calculation;
+
+#[tokio::test]
+async fn test_package_group_visibility() {
+    let fs = ProjectRootTemp::new().unwrap();
+
+    fs.write_file(
+        "rules.bzl",
+        r#"
+simple = rule(
+    impl = lambda ctx: fail(),
+    attrs = {},
+)
+"#,
+    );
+    fs.write_file(
+        "groups.bzl",
+        r#"
+CORE = package_group(packages = ["//core/..."])
+ANDROID = package_group(packages = ["//android/..."], includes = [CORE])
+"#,
+    );
+    fs.write_file(
+        "lib/BUCK",
+        r#"
+load("//:rules.bzl", "simple")
+load("//:groups.bzl", "ANDROID", "CORE")
+simple(name = "a", visibility = [ANDROID, "//tools:lint"])
+simple(name = "b", visibility = CORE)
+"#,
+    );
+
+    let ctx = calculation(&fs).await;
+
+    let a = ctx
+        .get_target_node(&TargetLabel::testing_parse("root//lib:a"))
+        .await
+        .unwrap();
+    assert_eq!(
+        &VisibilitySpecification::testing_parse(&[
+            "root//android/...",
+            "root//core/...",
+            "root//tools:lint"
+        ]),
+        a.visibility().unwrap(),
+    );
+
+    let b = ctx
+        .get_target_node(&TargetLabel::testing_parse("root//lib:b"))
+        .await
+        .unwrap();
+    assert_eq!(
+        &VisibilitySpecification::testing_parse(&["root//core/..."]),
+        b.visibility().unwrap(),
+    );
+}
@@ -82,6 +82,12 @@ pub struct AttrTypeInner2 {
     /// * `attrs.arg()`
     /// * collection of those e.g. `attrs.list(attrs.query(...))`
     pub may_have_queries: bool,
+    /// Attribute may reference targets or source files, so traversing its configured value may
+    /// find deps, labels or inputs.
+    ///
+    /// When this is false, the attribute does not need to be configured to compute the deps or
+    /// the inputs of a node.
+    pub may_have_deps: bool,
 }
 
 #[derive(Debug, Hash, Eq, PartialEq, Allocative)]
@@ -155,6 +161,7 @@ impl AttrType {
         Self(Arc::new(AttrTypeInner2 {
             inner: AttrTypeInner::Any(AnyAttrType),
             may_have_queries: false,
+            may_have_deps: false,
         }))
     }
 
@@ -172,6 +179,7 @@ impl AttrType {
                 anon_target_compatible,
             }),
             may_have_queries: true,
+            may_have_deps: true,
         }))
     }
 
@@ -179,6 +187,7 @@ impl AttrType {
         Ok(Self(Arc::new(AttrTypeInner2 {
             inner: AttrTypeInner::Enum(EnumAttrType::new(variants)?),
             may_have_queries: false,
+            may_have_deps: false,
         })))
     }
 
@@ -186,6 +195,7 @@ impl AttrType {
         Self(Arc::new(AttrTypeInner2 {
             inner: AttrTypeInner::Bool(BoolAttrType),
             may_have_queries: false,
+            may_have_deps: false,
         }))
     }
 
@@ -193,6 +203,7 @@ impl AttrType {
         Self(Arc::new(AttrTypeInner2 {
             inner: AttrTypeInner::Int(IntAttrType),
             may_have_queries: false,
+            may_have_deps: false,
         }))
     }
 
@@ -200,6 +211,7 @@ impl AttrType {
         Self(Arc::new(AttrTypeInner2 {
             inner: AttrTypeInner::ConfigurationDep(ConfigurationDepAttrType),
             may_have_queries: false,
+            may_have_deps: true,
         }))
     }
 
@@ -215,6 +227,7 @@ impl AttrType {
                 DepAttrTransition::Identity(plugin_kinds),
             )),
             may_have_queries: false,
+            may_have_deps: true,
         }))
     }
 
@@ -230,6 +243,7 @@ impl AttrType {
                 DepAttrTransition::Exec,
             )),
             may_have_queries: false,
+            may_have_deps: true,
         }))
     }
 
@@ -245,6 +259,7 @@ impl AttrType {
                 DepAttrTransition::Toolchain,
             )),
             may_have_queries: false,
+            may_have_deps: true,
         }))
     }
 
@@ -260,6 +275,7 @@ impl AttrType {
                 DepAttrTransition::Transition(cfg),
             )),
             may_have_queries: false,
+            may_have_deps: true,
         }))
     }
 
@@ -269,6 +285,7 @@ impl AttrType {
                 required_providers,
             }),
             may_have_queries: false,
+            may_have_deps: true,
         }))
     }
 
@@ -279,6 +296,7 @@ impl AttrType {
                 cfg,
             )),
             may_have_queries: false,
+            may_have_deps: true,
         }))
     }
 
@@ -286,48 +304,59 @@ impl AttrType {
         Self(Arc::new(AttrTypeInner2 {
             inner: AttrTypeInner::PluginDep(PluginDepAttrType::new(kind)),
             may_have_queries: false,
+            may_have_deps: true,
         }))
     }
 
     /// A dict attribute containing keys and values of the specified types.
     pub fn dict(key: AttrType, value: AttrType, sorted: bool) -> Self {
         let may_have_queries = key.0.may_have_queries || value.0.may_have_queries;
+        let may_have_deps = key.0.may_have_deps || value.0.may_have_deps;
         Self(Arc::new(AttrTypeInner2 {
             inner: AttrTypeInner::Dict(DictAttrType::new(key, value, sorted)),
             may_have_queries,
+            may_have_deps,
         }))
     }
 
     /// A list attribute containing items of some inner type.
     pub fn list(inner: AttrType) -> Self {
         let may_have_queries = inner.0.may_have_queries;
+        let may_have_deps = inner.0.may_have_deps;
         Self(Arc::new(AttrTypeInner2 {
             inner: AttrTypeInner::List(ListAttrType::new(inner)),
             may_have_queries,
+            may_have_deps,
         }))
     }
 
     pub fn tuple(xs: Vec<AttrType>) -> Self {
         let may_have_queries = xs.iter().any(|x| x.0.may_have_queries);
+        let may_have_deps = xs.iter().any(|x| x.0.may_have_deps);
         Self(Arc::new(AttrTypeInner2 {
             inner: AttrTypeInner::Tuple(TupleAttrType::new(xs)),
             may_have_queries,
+            may_have_deps,
         }))
     }
 
     pub fn one_of(xs: Vec<AttrType>) -> Self {
         let may_have_queries = xs.iter().any(|x| x.0.may_have_queries);
+        let may_have_deps = xs.iter().any(|x| x.0.may_have_deps);
         Self(Arc::new(AttrTypeInner2 {
             inner: AttrTypeInner::OneOf(OneOfAttrType::new(xs)),
             may_have_queries,
+            may_have_deps,
         }))
     }
 
     pub fn option(value: AttrType) -> Self {
         let may_have_queries = value.0.may_have_queries;
+        let may_have_deps = value.0.may_have_deps;
         Self(Arc::new(AttrTypeInner2 {
             inner: AttrTypeInner::Option(OptionAttrType::new(value)),
             may_have_queries,
+            may_have_deps,
         }))
     }
 
@@ -338,6 +367,7 @@ impl AttrType {
                 DepAttrTransition::Identity(PluginKindSet::EMPTY),
             ))),
             may_have_queries: true,
+            may_have_deps: true,
         }))
     }
 
@@ -348,6 +378,7 @@ impl AttrType {
         Self(Arc::new(AttrTypeInner2 {
             inner: AttrTypeInner::Source(SourceAttrType { allow_directory }),
             may_have_queries: false,
+            may_have_deps: true,
         }))
     }
 
@@ -357,6 +388,7 @@ impl AttrType {
         Self(Arc::new(AttrTypeInner2 {
             inner: AttrTypeInner::String(StringAttrType),
             may_have_queries: false,
+            may_have_deps: false,
         }))
     }
 
@@ -364,6 +396,7 @@ impl AttrType {
         Self(Arc::new(AttrTypeInner2 {
             inner: AttrTypeInner::Label(LabelAttrType),
             may_have_queries: false,
+            may_have_deps: true,
         }))
     }
 
@@ -371,6 +404,7 @@ impl AttrType {
         Self(Arc::new(AttrTypeInner2 {
             inner: AttrTypeInner::Visibility(VisibilityAttrType),
             may_have_queries: false,
+            may_have_deps: false,
         }))
     }
 
@@ -378,6 +412,7 @@ impl AttrType {
         Self(Arc::new(AttrTypeInner2 {
             inner: AttrTypeInner::WithinView(WithinViewAttrType),
             may_have_queries: false,
+            may_have_deps: false,
         }))
     }
 
@@ -385,6 +420,7 @@ impl AttrType {
         Self(Arc::new(AttrTypeInner2 {
             inner: AttrTypeInner::Metadata(MetadataAttrType),
             may_have_queries: false,
+            may_have_deps: false,
         }))
     }
 
@@ -157,6 +157,26 @@ impl CoercedSelector {
     }
 }
 
+/// How a single `select()` resolved in a particular configuration.
+#[derive(Debug)]
+pub struct SelectResolution {
+    /// All branches of the `select()` in declaration order, `DEFAULT` last.
+    pub branches: Vec<SelectBranchResolution>,
+}
+
+/// One branch of a `select()` as seen from a particular configuration.
+#[derive(Debug)]
+pub struct SelectBranchResolution {
+    /// The select key, `None` for `DEFAULT`.
+    pub key: Option<TargetLabel>,
+    /// Constraints and buckconfigs of the key, if the key matched the configuration.
+    pub matched: Option<ConfigSettingData>,
+    /// Whether this branch was picked.
+    pub selected: bool,
+    /// The value this branch produces (or would have produced) in this configuration.
+    pub value: anyhow::Result<ConfiguredAttr>,
+}
+
 /// CoercedAttr is the "coerced" representation of an attribute. It has been type-checked and converted to
 /// specific types (for example, where we expect target-like things, it has been converted to something like
 /// a TargetLabel or ProvidersLabel).
@@ -366,6 +386,22 @@ impl CoercedAttr {
         }
     }
 
+    /// Whether configuring this attribute resolves any `select()`. Attributes without selects
+    /// configure to the same value in any configuration, and configuring them cannot fail.
+    pub fn has_selects(&self) -> bool {
+        match self {
+            CoercedAttr::Selector(_) | CoercedAttr::Concat(_) => true,
+            CoercedAttr::List(list) => list.0.iter().any(|x| x.has_selects()),
+            CoercedAttr::Tuple(tuple) => tuple.0.iter().any(|x| x.has_selects()),
+            CoercedAttr::Dict(dict) => dict
+                .0
+                .iter()
+                .any(|(k, v)| k.has_selects() || v.has_selects()),
+            CoercedAttr::OneOf(x, _) => x.has_selects(),
+            _ => false,
+        }
+    }
+
     /// Traverses the coerced attribute and provides the traverser callbacks for all deps (those in select conditions
     /// are passed as configuration deps).
     pub fn traverse<'a>(
@@ -522,6 +558,83 @@ impl CoercedAttr {
         }
     }
 
+    /// Explain every `select()` reachable in this attribute in the provided context:
+    /// which branch was picked, which keys matched, and what every branch would produce.
+    /// Selects nested in branches which were not picked are not reported.
+    pub fn select_resolutions(
+        &self,
+        ty: &AttrType,
+        ctx: &dyn AttrConfigurationContext,
+    ) -> anyhow::Result<Vec<SelectResolution>> {
+        let mut resolutions = Vec::new();
+        self.collect_select_resolutions(ty, ctx, &mut resolutions)?;
+        Ok(resolutions)
+    }
+
+    fn collect_select_resolutions(
+        &self,
+        ty: &AttrType,
+        ctx: &dyn AttrConfigurationContext,
+        resolutions: &mut Vec<SelectResolution>,
+    ) -> anyhow::Result<()> {
+        match CoercedAttrWithType::pack(self, ty)? {
+            CoercedAttrWithType::Selector(select, t) => {
+                let selected = Self::select(ctx, select)?;
+                let branches = select
+                    .all_entries()
+                    .map(|(k, v)| {
+                        let (key, matched) = match k {
+                            CoercedSelectorKeyRef::Target(k) => {
+                                (Some(k.dupe()), ctx.matches(k).cloned())
+                            }
+                            CoercedSelectorKeyRef::Default => (None, None),
+                        };
+                        SelectBranchResolution {
+                            key,
+                            matched,
+                            selected: std::ptr::eq(v, selected),
+                            value: v.configure(t, ctx),
+                        }
+                    })
+                    .collect();
+                resolutions.push(SelectResolution { branches });
+                selected.collect_select_resolutions(t, ctx, resolutions)
+            }
+            CoercedAttrWithType::Concat(items, t) => {
+                for item in items {
+                    item.collect_select_resolutions(t, ctx, resolutions)?;
+                }
+                Ok(())
+            }
+            CoercedAttrWithType::List(list, t) => {
+                for item in list.iter() {
+                    item.collect_select_resolutions(&t.inner, ctx, resolutions)?;
+                }
+                Ok(())
+            }
+            CoercedAttrWithType::Tuple(list, t) => {
+                for (item, item_ty) in list.iter().zip(&t.xs) {
+                    item.collect_select_resolutions(item_ty, ctx, resolutions)?;
+                }
+                Ok(())
+            }
+            CoercedAttrWithType::Dict(dict, t) => {
+                for (_, v) in dict.iter() {
+                    v.collect_select_resolutions(&t.value, ctx, resolutions)?;
+                }
+                Ok(())
+            }
+            CoercedAttrWithType::Some(attr, t) => {
+                attr.collect_select_resolutions(&t.inner, ctx, resolutions)
+            }
+            CoercedAttrWithType::OneOf(l, i, t) => {
+                let item_ty = t.xs.get(i as usize).context("invalid enum")?;
+                l.collect_select_resolutions(item_ty, ctx, resolutions)
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Returns the "configured" representation of the attribute in the provided context.
     /// This handles the resolution of the select() conditions and delegates to
     /// the actual attr type for handling any appropriate configuration-time
@@ -676,10 +789,17 @@ impl CoercedAttr {
 mod tests {
 
     use buck2_core::target::label::TargetLabel;
+    use buck2_util::arc_str::ArcSlice;
+    use buck2_util::arc_str::ArcStr;
     use dupe::Dupe;
 
+    use crate::attrs::attr_type::list::ListLiteral;
+    use crate::attrs::attr_type::string::StringLiteral;
+    use crate::attrs::attr_type::AttrType;
     use crate::attrs::coerced_attr::CoercedAttr;
     use crate::attrs::coerced_attr::CoercedSelector;
+    use crate::attrs::configured_attr::ConfiguredAttr;
+    use crate::attrs::testing::configuration_ctx;
 
     #[test]
     fn test_check_all_keys_unique_small() {
@@ -717,4 +837,78 @@ mod tests {
         long[10].0 = long[0].0.dupe();
         assert!(CoercedSelector::check_all_keys_unique(&long).is_err());
     }
+
+    #[test]
+    fn test_select_resolutions() {
+        fn string(s: &str) -> CoercedAttr {
+            CoercedAttr::String(StringLiteral(ArcStr::from(s)))
+        }
+
+        let attr = CoercedAttr::Selector(Box::new(
+            CoercedSelector::new(
+                ArcSlice::new([
+                    (TargetLabel::testing_parse("root//some:config"), string("a")),
+                    (
+                        TargetLabel::testing_parse("root//other:config"),
+                        string("b"),
+                    ),
+                ]),
+                Some(string("c")),
+            )
+            .unwrap(),
+        ));
+
+        let resolutions = attr
+            .select_resolutions(&AttrType::string(), &configuration_ctx())
+            .unwrap();
+        assert_eq!(1, resolutions.len());
+        let branches = &resolutions[0].branches;
+        assert_eq!(3, branches.len());
+
+        assert!(branches[0].matched.is_none());
+        assert!(!branches[0].selected);
+
+        assert!(branches[1].matched.is_some());
+        assert!(branches[1].selected);
+        assert_eq!(
+            ConfiguredAttr::String(StringLiteral(ArcStr::from("b"))),
+            *branches[1].value.as_ref().unwrap()
+        );
+
+        assert_eq!(None, branches[2].key);
+        assert!(!branches[2].selected);
+        assert_eq!(
+            ConfiguredAttr::String(StringLiteral(ArcStr::from("c"))),
+            *branches[2].value.as_ref().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_has_selects() {
+        let string = CoercedAttr::String(StringLiteral(ArcStr::from("a")));
+        let select = CoercedAttr::Selector(Box::new(
+            CoercedSelector::new(ArcSlice::new([]), Some(string.clone())).unwrap(),
+        ));
+
+        assert!(!string.has_selects());
+        assert!(select.has_selects());
+        assert!(!CoercedAttr::List(ListLiteral(ArcSlice::new([string.clone()]))).has_selects());
+        assert!(CoercedAttr::List(ListLiteral(ArcSlice::new([string, select]))).has_selects());
+    }
+
+    #[test]
+    fn test_may_have_deps() {
+        assert!(!AttrType::list(AttrType::string()).0.may_have_deps);
+        assert!(
+            !AttrType::dict(AttrType::string(), AttrType::int(), false)
+                .0
+                .may_have_deps
+        );
+        assert!(AttrType::option(AttrType::source(false)).0.may_have_deps);
+        assert!(
+            AttrType::one_of(vec![AttrType::bool(), AttrType::arg(false)])
+                .0
+                .may_have_deps
+        );
+    }
 }
@@ -12,6 +12,7 @@ use buck2_core::package::PackageLabel;
 
 use crate::attrs::attr::Attribute;
 use crate::attrs::coerced_attr::CoercedAttr;
+use crate::attrs::coerced_attr::SelectResolution;
 use crate::attrs::configuration_context::AttrConfigurationContext;
 use crate::attrs::configured_attr_full::ConfiguredAttrFull;
 use crate::attrs::traversal::CoercedAttrTraversal;
@@ -38,6 +39,15 @@ impl<'a> CoercedAttrFull<'a> {
         })
     }
 
+    pub fn select_resolutions(
+        &self,
+        ctx: &dyn AttrConfigurationContext,
+    ) -> anyhow::Result<Vec<SelectResolution>> {
+        self.value
+            .select_resolutions(self.attr.coercer(), ctx)
+            .with_context(|| format!("resolving selects of attr `{}`", self.name))
+    }
+
     pub fn traverse(
         &self,
         pkg: PackageLabel,
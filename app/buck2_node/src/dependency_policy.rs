@@ -0,0 +1,195 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Dependency policies restrict which targets the targets of a subtree may depend on, so that
+//! layering can be enforced by the build itself.
+//!
+//! Policies are declared in the `dependency_policy` section of the root cell's buckconfig, one
+//! rule per key:
+//!
+//! ```ini
+//! [dependency_policy]
+//!   core = //core/... -> //third-party/... //base/...
+//!   no_test_deps = //app/... !-> //testing/...
+//! ```
+//!
+//! `SUBTREE -> PATTERNS` is an allowlist: targets matching `SUBTREE` may only depend on targets
+//! matching `SUBTREE` or one of `PATTERNS`. `SUBTREE !-> PATTERNS` is a denylist: targets
+//! matching `SUBTREE` may not depend on targets matching one of `PATTERNS`. Every rule matching
+//! a target applies. Rules are checked for each edge of the configured graph, including exec and
+//! toolchain deps.
+
+use allocative::Allocative;
+use buck2_core::cells::name::CellName;
+use buck2_core::cells::CellResolver;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::pattern::ParsedPattern;
+use buck2_core::target::label::TargetLabel;
+use dupe::Dupe;
+
+#[derive(Debug, buck2_error::Error)]
+pub enum DependencyPolicyError {
+    #[error(
+        "Error parsing dependency policy `dependency_policy.{0}`, expected `SUBTREE -> PATTERNS` or `SUBTREE !-> PATTERNS`, got `{1}`"
+    )]
+    #[buck2(user)]
+    UnrecognizedFormat(String, String),
+    #[error(
+        "Dependency `{target} -> {dep}` violates dependency policy `dependency_policy.{rule}` (`{spec}`)"
+    )]
+    #[buck2(user)]
+    Violation {
+        target: TargetLabel,
+        dep: TargetLabel,
+        rule: String,
+        spec: String,
+    },
+}
+
+#[derive(Debug, Eq, PartialEq, Allocative)]
+enum DependencyPolicyKind {
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Eq, PartialEq, Allocative)]
+struct DependencyPolicyRule {
+    name: String,
+    spec: String,
+    subtree: ParsedPattern<TargetPatternExtra>,
+    kind: DependencyPolicyKind,
+    patterns: Vec<ParsedPattern<TargetPatternExtra>>,
+}
+
+impl DependencyPolicyRule {
+    fn allows(&self, dep: &TargetLabel) -> bool {
+        let matches = self.patterns.iter().any(|p| p.matches(dep));
+        match self.kind {
+            DependencyPolicyKind::Allow => matches || self.subtree.matches(dep),
+            DependencyPolicyKind::Deny => !matches,
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Allocative, Default)]
+pub struct DependencyPolicy {
+    rules: Vec<DependencyPolicyRule>,
+}
+
+impl DependencyPolicy {
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Parse the rules, given as `(name, spec)` pairs, of the `dependency_policy` section.
+    pub fn parse<'a>(
+        rules: impl IntoIterator<Item = (&'a str, &'a str)>,
+        cell_name: CellName,
+        cell_resolver: &CellResolver,
+    ) -> anyhow::Result<Self> {
+        let parse_pattern = |pattern: &str| {
+            ParsedPattern::<TargetPatternExtra>::parse_precise(pattern, cell_name, cell_resolver)
+        };
+        let mut parsed = Vec::new();
+        for (name, spec) in rules {
+            let unrecognized =
+                || DependencyPolicyError::UnrecognizedFormat(name.to_owned(), spec.to_owned());
+            let (subtree, kind, patterns) = match spec.split_once("!->") {
+                Some((subtree, patterns)) => (subtree, DependencyPolicyKind::Deny, patterns),
+                None => match spec.split_once("->") {
+                    Some((subtree, patterns)) => (subtree, DependencyPolicyKind::Allow, patterns),
+                    None => return Err(unrecognized().into()),
+                },
+            };
+            let subtree = subtree.trim();
+            if subtree.is_empty() || subtree.contains(char::is_whitespace) {
+                return Err(unrecognized().into());
+            }
+            parsed.push(DependencyPolicyRule {
+                name: name.to_owned(),
+                spec: spec.trim().to_owned(),
+                subtree: parse_pattern(subtree)?,
+                kind,
+                patterns: patterns
+                    .split_whitespace()
+                    .map(parse_pattern)
+                    .collect::<anyhow::Result<_>>()?,
+            });
+        }
+        Ok(DependencyPolicy { rules: parsed })
+    }
+
+    /// Check that the edge `target -> dep` is allowed by every rule applying to `target`.
+    pub fn check(&self, target: &TargetLabel, dep: &TargetLabel) -> anyhow::Result<()> {
+        for rule in &self.rules {
+            if rule.subtree.matches(target) && !rule.allows(dep) {
+                return Err(DependencyPolicyError::Violation {
+                    target: target.dupe(),
+                    dep: dep.dupe(),
+                    rule: rule.name.clone(),
+                    spec: rule.spec.clone(),
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::cells::cell_root_path::CellRootPathBuf;
+
+    use super::*;
+
+    fn parse(rules: &[(&str, &str)]) -> anyhow::Result<DependencyPolicy> {
+        let cell_name = CellName::testing_new("root");
+        let cell_resolver =
+            CellResolver::testing_with_name_and_path(cell_name, CellRootPathBuf::testing_new(""));
+        DependencyPolicy::parse(rules.iter().copied(), cell_name, &cell_resolver)
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse(&[("a", "//core/...")]).is_err());
+        assert!(parse(&[("a", "-> //base/...")]).is_err());
+        assert!(parse(&[("a", "//core/... //x/... -> //base/...")]).is_err());
+        assert!(parse(&[("a", "//core/... -> missing//base/...")]).is_err());
+        assert!(parse(&[("a", "//core/... -> //base/...")]).is_ok());
+        assert!(parse(&[("a", "//core/... !-> //base/...")]).is_ok());
+    }
+
+    #[test]
+    fn test_check() -> anyhow::Result<()> {
+        let policy = parse(&[
+            ("core", "//core/... -> //base/... //third-party:zlib"),
+            ("app", "//app/... !-> //testing/..."),
+        ])?;
+        let check = |target: &str, dep: &str| {
+            policy
+                .check(
+                    &TargetLabel::testing_parse(target),
+                    &TargetLabel::testing_parse(dep),
+                )
+                .is_ok()
+        };
+
+        assert!(check("root//core/a:a", "root//core/b:b"));
+        assert!(check("root//core/a:a", "root//base/x:x"));
+        assert!(check("root//core/a:a", "root//third-party:zlib"));
+        assert!(!check("root//core/a:a", "root//third-party:openssl"));
+        assert!(!check("root//core/a:a", "root//app:app"));
+
+        assert!(check("root//app:app", "root//core/a:a"));
+        assert!(!check("root//app:app", "root//testing/mocks:mocks"));
+
+        assert!(check("root//other:other", "root//testing/mocks:mocks"));
+        Ok(())
+    }
+}
@@ -15,6 +15,7 @@ pub mod call_stack;
 pub mod cfg_constructor;
 pub mod configuration;
 pub mod configured_universe;
+pub mod dependency_policy;
 pub mod load_patterns;
 pub mod metadata;
 pub mod nodes;
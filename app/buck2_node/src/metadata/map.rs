@@ -9,6 +9,7 @@
 
 use std::fmt;
 use std::hash::Hash;
+use std::sync::Arc;
 
 use allocative::Allocative;
 use starlark_map::small_map::SmallMap;
@@ -19,21 +20,58 @@ use crate::metadata::key::MetadataKey;
 use crate::metadata::key::MetadataKeyRef;
 use crate::metadata::value::MetadataValue;
 
+/// Namespace of the package values which are the metadata of the targets of the package, e.g.
+/// `write_package_value("metadata.oncall", "my_team")`.
+pub const PACKAGE_METADATA_NAMESPACE: &str = "metadata";
+
+/// Cheap to clone, since the metadata of a package is shared by its targets.
 #[derive(Debug, Eq, PartialEq, Hash, Clone, Allocative, Default)]
 pub struct MetadataMap {
-    values: Box<SortedMap<MetadataKey, MetadataValue>>,
+    values: Arc<SortedMap<MetadataKey, MetadataValue>>,
 }
 
 impl MetadataMap {
     pub fn new(values: SmallMap<MetadataKey, MetadataValue>) -> Self {
         Self {
-            values: Box::new(SortedMap::from(values)),
+            values: Arc::new(SortedMap::from(values)),
         }
     }
 
+    /// The metadata of a package: the package values of its `PACKAGE` files in the
+    /// [`PACKAGE_METADATA_NAMESPACE`] namespace.
+    pub fn from_package_values(values: SmallMap<MetadataKey, serde_json::Value>) -> Self {
+        Self::new(
+            values
+                .into_iter()
+                .filter(|(k, _)| {
+                    k.as_str().split_once('.').map(|(namespace, _)| namespace)
+                        == Some(PACKAGE_METADATA_NAMESPACE)
+                })
+                .map(|(k, v)| (k, MetadataValue::new(v)))
+                .collect(),
+        )
+    }
+
     pub fn get(&self, key: &MetadataKeyRef) -> Option<&MetadataValue> {
         self.values.get(key)
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// This map with the values of `other` added, replacing the values of the same keys.
+    pub fn overlay(&self, other: &MetadataMap) -> MetadataMap {
+        let mut values: SmallMap<MetadataKey, MetadataValue> = self
+            .values
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        for (k, v) in other.values.iter() {
+            values.insert(k.clone(), v.clone());
+        }
+        MetadataMap::new(values)
+    }
 }
 
 impl MetadataMap {
@@ -50,7 +88,7 @@ impl MetadataMap {
 impl fmt::Display for MetadataMap {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let Self { values } = self;
-        let s = serde_json::to_string(values).map_err(|_| fmt::Error)?;
+        let s = serde_json::to_string(&**values).map_err(|_| fmt::Error)?;
         f.write_str(&s)?;
         Ok(())
     }
@@ -98,6 +136,45 @@ mod tests {
         assert_eq!(spec.to_value(), serde_json::json!({"foo.bar": "baz"}));
     }
 
+    #[test]
+    fn test_from_package_values() {
+        let mut values = SmallMap::new();
+        values.insert(
+            "metadata.oncall".to_owned().try_into().unwrap(),
+            serde_json::json!("team"),
+        );
+        values.insert(
+            "cfg.modifiers".to_owned().try_into().unwrap(),
+            serde_json::json!(["linux"]),
+        );
+        values.insert(
+            "foo.metadata".to_owned().try_into().unwrap(),
+            serde_json::json!(1),
+        );
+        assert_eq!(
+            MetadataMap::from_package_values(values).to_value(),
+            serde_json::json!({"metadata.oncall": "team"})
+        );
+    }
+
+    #[test]
+    fn test_overlay() {
+        let mut map = SmallMap::new();
+        map.insert(
+            "foo.bar".to_owned().try_into().unwrap(),
+            MetadataValue::new(serde_json::json!("qux")),
+        );
+        map.insert(
+            "foo.oncall".to_owned().try_into().unwrap(),
+            MetadataValue::new(serde_json::json!("team")),
+        );
+        let package = MetadataMap::new(map);
+        assert_eq!(
+            package.overlay(&make()).to_value(),
+            serde_json::json!({"foo.bar": "baz", "foo.oncall": "team"})
+        );
+    }
+
     #[test]
     fn test_any_matches() {
         // NOTE: We have more comprehensive tests for serde_json's any_matches, so only test our
@@ -49,6 +49,7 @@ use crate::attrs::attr_type::query::ResolvedQueryLiterals;
 use crate::attrs::attr_type::string::StringLiteral;
 use crate::attrs::attr_type::AttrType;
 use crate::attrs::coerced_attr::CoercedAttr;
+use crate::attrs::coerced_attr::SelectResolution;
 use crate::attrs::coerced_attr_full::CoercedAttrFull;
 use crate::attrs::configuration_context::AttrConfigurationContextImpl;
 use crate::attrs::configured_attr::ConfiguredAttr;
@@ -58,8 +59,11 @@ use crate::attrs::inspect_options::AttrInspectOptions;
 use crate::attrs::internal::TARGET_COMPATIBLE_WITH_ATTRIBUTE_FIELD;
 use crate::attrs::internal::TESTS_ATTRIBUTE_FIELD;
 use crate::configuration::resolved::ResolvedConfiguration;
+use crate::metadata::map::MetadataMap;
+use crate::nodes::attributes::CONFIGURATION_CONSTRAINTS;
 use crate::nodes::attributes::DEPS;
 use crate::nodes::attributes::EXECUTION_PLATFORM;
+use crate::nodes::attributes::METADATA;
 use crate::nodes::attributes::ONCALL;
 use crate::nodes::attributes::PACKAGE;
 use crate::nodes::attributes::PLUGINS;
@@ -142,6 +146,13 @@ impl TargetNodeOrForward {
         }
     }
 
+    fn metadata_with_package(&self) -> &MetadataMap {
+        match self {
+            TargetNodeOrForward::TargetNode(node) => node.metadata_with_package(),
+            TargetNodeOrForward::Forward(_, forward) => forward.metadata_with_package(),
+        }
+    }
+
     fn attr_or_none<'a>(
         &'a self,
         name: &str,
@@ -367,7 +378,7 @@ impl ConfiguredTargetNode {
             }
         }
         let mut traversal = InputsCollector { inputs: Vec::new() };
-        for a in self.attrs(AttrInspectOptions::All) {
+        for a in self.attrs_where(AttrInspectOptions::All, |attr| attr.0.may_have_deps) {
             a.traverse(self.label().pkg(), &mut traversal)
                 .expect("inputs collector shouldn't return errors");
         }
@@ -401,12 +412,7 @@ impl ConfiguredTargetNode {
             }
         }
 
-        for a in self.attrs(AttrInspectOptions::All) {
-            // Optimization.
-            if !a.attr.coercer().0.may_have_queries {
-                continue;
-            }
-
+        for a in self.attrs_where(AttrInspectOptions::All, |attr| attr.0.may_have_queries) {
             a.traverse(self.label().pkg(), &mut traversal).unwrap();
         }
         traversal.queries.into_iter()
@@ -492,10 +498,18 @@ impl ConfiguredTargetNode {
                     Some(x) => ConfiguredAttr::String(StringLiteral(ArcStr::from(x))),
                 },
             ),
+            (
+                METADATA,
+                ConfiguredAttr::Metadata(self.0.target_node.metadata_with_package().clone()),
+            ),
             (
                 TARGET_CONFIGURATION,
                 ConfiguredAttr::String(StringLiteral(ArcStr::from(self.0.label.cfg().to_string()))),
             ),
+            (
+                CONFIGURATION_CONSTRAINTS,
+                self.configuration_constraints_as_attr(),
+            ),
             (
                 EXECUTION_PLATFORM,
                 ConfiguredAttr::String(StringLiteral(
@@ -514,6 +528,11 @@ impl ConfiguredTargetNode {
         self.0.target_node.oncall()
     }
 
+    /// The metadata of the target, including the metadata of its package.
+    pub fn metadata_with_package(&self) -> &MetadataMap {
+        self.0.target_node.metadata_with_package()
+    }
+
     fn attr_configuration_context(&self) -> AttrConfigurationContextImpl {
         AttrConfigurationContextImpl::new(
             &self.0.resolved_configuration,
@@ -533,6 +552,24 @@ impl ConfiguredTargetNode {
         })
     }
 
+    /// Like `attrs`, but only configures the attributes whose type matches `filter`. This is
+    /// cheaper than configuring every attribute when looking for specific ones, such as those
+    /// which may have deps.
+    pub fn attrs_where<'a>(
+        &'a self,
+        opts: AttrInspectOptions,
+        filter: impl Fn(&AttrType) -> bool + 'a,
+    ) -> impl Iterator<Item = ConfiguredAttrFull<'a>> + 'a {
+        self.0
+            .target_node
+            .attrs(opts)
+            .filter(move |a| filter(a.attr.coercer()))
+            .map(move |a| {
+                a.configure(&self.attr_configuration_context())
+                    .expect("checked attr configuration in constructor")
+            })
+    }
+
     pub fn get<'a>(
         &'a self,
         attr: &str,
@@ -544,6 +581,23 @@ impl ConfiguredTargetNode {
         })
     }
 
+    /// Explain how `select()`s resolved in this node's configuration, for each attribute
+    /// which has any.
+    pub fn select_resolutions<'a>(
+        &'a self,
+        opts: AttrInspectOptions,
+    ) -> anyhow::Result<Vec<(&'a str, Vec<SelectResolution>)>> {
+        let ctx = self.attr_configuration_context();
+        let mut resolutions = Vec::new();
+        for a in self.0.target_node.attrs(opts) {
+            let attr_resolutions = a.select_resolutions(&ctx)?;
+            if !attr_resolutions.is_empty() {
+                resolutions.push((a.name, attr_resolutions));
+            }
+        }
+        Ok(resolutions)
+    }
+
     pub fn call_stack(&self) -> Option<String> {
         match &self.0.target_node {
             TargetNodeOrForward::TargetNode(n) => n.call_stack(),
@@ -585,6 +639,24 @@ impl ConfiguredTargetNode {
         &self.0.plugin_lists
     }
 
+    fn configuration_constraints_as_attr(&self) -> ConfiguredAttr {
+        // Builtin configurations (e.g. the unspecified one) have no constraints.
+        let constraints = match self.0.label.cfg().data() {
+            Ok(data) => data
+                .constraints
+                .iter()
+                .map(|(key, value)| {
+                    (
+                        ConfiguredAttr::String(StringLiteral(ArcStr::from(key.to_string()))),
+                        ConfiguredAttr::String(StringLiteral(ArcStr::from(value.to_string()))),
+                    )
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        ConfiguredAttr::Dict(constraints.into_iter().collect())
+    }
+
     fn plugins_as_attr(&self) -> ConfiguredAttr {
         let mut kinds = Vec::new();
         for (kind, plugins) in self.plugin_lists().iter_by_kind() {
@@ -35,6 +35,10 @@ pub mod attributes {
     /// The target hash of this target.
     pub static TARGET_HASH: &str = "buck.target_hash";
 
+    /// The metadata of this node: the `metadata.*` package values of its `PACKAGE` files,
+    /// overridden by its `metadata` attribute.
+    pub static METADATA: &str = "buck.metadata";
+
     /// The callstack for this target.
     pub static TARGET_CALL_STACK: &str = "buck.target_call_stack";
 
@@ -47,6 +51,9 @@ pub mod attributes {
     /// The resolved target configuration for this node.
     pub static TARGET_CONFIGURATION: &str = "buck.target_configuration";
 
+    /// The constraint values of the target configuration of this node, keyed by constraint setting.
+    pub static CONFIGURATION_CONSTRAINTS: &str = "buck.configuration_constraints";
+
     /// The input source files/directories that this node uses.
     pub static INPUTS: &str = "buck.inputs";
 
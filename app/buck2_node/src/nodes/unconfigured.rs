@@ -38,6 +38,7 @@ use crate::call_stack::StarlarkCallStack;
 use crate::metadata::map::MetadataMap;
 use crate::nodes::attributes::CONFIGURATION_DEPS;
 use crate::nodes::attributes::DEPS;
+use crate::nodes::attributes::METADATA;
 use crate::nodes::attributes::ONCALL;
 use crate::nodes::attributes::PACKAGE;
 use crate::nodes::attributes::TYPE;
@@ -45,11 +46,14 @@ use crate::package::Package;
 use crate::rule::Rule;
 use crate::rule_type::RuleType;
 use crate::visibility::VisibilitySpecification;
+use crate::visibility::WithinViewSpecification;
 
 #[derive(Debug, buck2_error::Error)]
 enum TargetNodeError {
     #[error("`visibility` attribute coerced incorrectly (`{0}`) (internal error)")]
     IncorrectVisibilityAttribute(String),
+    #[error("`within_view` attribute coerced incorrectly (`{0}`) (internal error)")]
+    IncorrectWithinViewAttribute(String),
     #[error(
         "`metadata` attribute should be coerced as a dict of strings to JSON values. Found `{0}` instead (internal error)"
     )]
@@ -98,6 +102,10 @@ pub struct TargetNodeData {
 
     /// Call stack for the target.
     call_stack: Option<StarlarkCallStack>,
+
+    /// The package metadata overridden by the `metadata` attribute, when both are non-empty.
+    /// Cached because it is needed for every target in query output.
+    merged_metadata: Option<MetadataMap>,
 }
 
 impl TargetNode {
@@ -109,6 +117,22 @@ impl TargetNode {
         deps_cache: CoercedDeps,
         call_stack: Option<StarlarkCallStack>,
     ) -> TargetNode {
+        let merged_metadata = match rule
+            .attributes
+            .attr_or_none(
+                &attributes,
+                METADATA_ATTRIBUTE_FIELD,
+                AttrInspectOptions::All,
+            )
+            .map(|attr| attr.value)
+        {
+            Some(CoercedAttr::Metadata(metadata))
+                if !metadata.is_empty() && !package.metadata.is_empty() =>
+            {
+                Some(package.metadata.overlay(metadata))
+            }
+            _ => None,
+        };
         TargetNode(Arc::new(TargetNodeData {
             rule,
             package,
@@ -116,6 +140,7 @@ impl TargetNode {
             attributes,
             deps_cache,
             call_stack,
+            merged_metadata,
         }))
     }
 
@@ -217,6 +242,10 @@ impl TargetNode {
                     Some(x) => CoercedAttr::String(StringLiteral(ArcStr::from(x))),
                 },
             ),
+            (
+                METADATA,
+                CoercedAttr::Metadata(self.metadata_with_package().clone()),
+            ),
         ]
         .into_iter()
     }
@@ -244,6 +273,17 @@ impl TargetNode {
         }
     }
 
+    pub fn within_view(&self) -> anyhow::Result<&WithinViewSpecification> {
+        match self.0.attributes.get(AttributeSpec::within_view_attr_id()) {
+            Some(CoercedAttr::WithinView(v)) => Ok(v),
+            Some(a) => Err(TargetNodeError::IncorrectWithinViewAttribute(
+                a.as_display_no_ctx().to_string(),
+            )
+            .into()),
+            None => Ok(&WithinViewSpecification::PUBLIC),
+        }
+    }
+
     pub fn is_visible_to(&self, target: &TargetLabel) -> anyhow::Result<bool> {
         if self.label().pkg() == target.pkg() {
             return Ok(true);
@@ -454,6 +494,24 @@ impl TargetNode {
             })
             .transpose()
     }
+
+    /// The metadata of the package of this target, from its `PACKAGE` files.
+    pub fn package_metadata(&self) -> &MetadataMap {
+        &self.0.package.metadata
+    }
+
+    /// The metadata of the package of this target, overridden by the `metadata` attribute of
+    /// the target.
+    pub fn metadata_with_package(&self) -> &MetadataMap {
+        if let Some(merged) = &self.0.merged_metadata {
+            return merged;
+        }
+        match self.metadata() {
+            Ok(Some(metadata)) if !metadata.is_empty() => metadata,
+            // An invalid `metadata` attribute is an internal error reported by `metadata()`.
+            Ok(_) | Err(_) => self.package_metadata(),
+        }
+    }
 }
 
 pub mod testing {
@@ -473,8 +531,10 @@ pub mod testing {
     use crate::attrs::coerced_deps_collector::CoercedDepsCollector;
     use crate::attrs::fmt_context::AttrFmtContext;
     use crate::attrs::inspect_options::AttrInspectOptions;
+    use crate::attrs::internal::internal_attrs;
     use crate::attrs::spec::AttributeSpec;
     use crate::attrs::values::AttrValues;
+    use crate::metadata::map::MetadataMap;
     use crate::nodes::targets_map::TargetsMap;
     use crate::rule_type::RuleType;
 
@@ -484,6 +544,15 @@ pub mod testing {
             rule_type: RuleType,
             attrs: Vec<(&str, Attribute, CoercedAttr)>,
         ) -> Self;
+
+        /// Like `testing_new`, in a package with the given metadata. `attrs` may set internal
+        /// attributes such as `metadata`.
+        fn testing_new_in_package(
+            label: TargetLabel,
+            rule_type: RuleType,
+            attrs: Vec<(&str, Attribute, CoercedAttr)>,
+            package_metadata: MetadataMap,
+        ) -> Self;
     }
 
     impl TargetNodeExt for TargetNode {
@@ -491,10 +560,20 @@ pub mod testing {
             label: TargetLabel,
             rule_type: RuleType,
             attrs: Vec<(&str, Attribute, CoercedAttr)>,
+        ) -> TargetNode {
+            Self::testing_new_in_package(label, rule_type, attrs, MetadataMap::default())
+        }
+
+        fn testing_new_in_package(
+            label: TargetLabel,
+            rule_type: RuleType,
+            attrs: Vec<(&str, Attribute, CoercedAttr)>,
+            package_metadata: MetadataMap,
         ) -> TargetNode {
             let attr_spec = AttributeSpec::testing_new(
                 attrs
                     .iter()
+                    .filter(|(name, _, _)| !internal_attrs().contains_key(*name))
                     .map(|(name, attr, _)| ((*name).to_owned(), attr.clone()))
                     .collect(),
             );
@@ -508,11 +587,16 @@ pub mod testing {
 
             let mut deps_cache = CoercedDepsCollector::new();
 
+            let mut values = Vec::with_capacity(attrs.len());
             for (name, _attr, val) in attrs.into_iter() {
                 let idx = attr_spec.attribute_id_by_name(name).unwrap();
                 let attr = attr_spec.attribute(name).unwrap();
                 val.traverse(attr.coercer(), label.pkg(), &mut deps_cache)
                     .unwrap();
+                values.push((idx, val));
+            }
+            values.sort_by_key(|(idx, _)| *idx);
+            for (idx, val) in values {
                 attributes.push_sorted(idx, val);
             }
 
@@ -531,6 +615,7 @@ pub mod testing {
                 Arc::new(Package {
                     buildfile_path,
                     oncall: None,
+                    metadata: package_metadata,
                 }),
                 label,
                 attributes,
@@ -572,3 +657,106 @@ pub mod testing {
         Ok(Value::from(map))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use buck2_core::bzl::ImportPath;
+    use buck2_core::target::label::TargetLabel;
+    use starlark_map::small_map::SmallMap;
+
+    use crate::attrs::attr::Attribute;
+    use crate::attrs::attr_type::AttrType;
+    use crate::attrs::coerced_attr::CoercedAttr;
+    use crate::attrs::fmt_context::AttrFmtContext;
+    use crate::metadata::map::MetadataMap;
+    use crate::metadata::value::MetadataValue;
+    use crate::nodes::attributes::METADATA;
+    use crate::nodes::unconfigured::testing::TargetNodeExt;
+    use crate::nodes::unconfigured::TargetNode;
+    use crate::rule_type::RuleType;
+    use crate::rule_type::StarlarkRuleType;
+
+    fn metadata(values: serde_json::Value) -> MetadataMap {
+        let serde_json::Value::Object(values) = values else {
+            unreachable!()
+        };
+        let values: SmallMap<_, _> = values
+            .into_iter()
+            .map(|(k, v)| (k.try_into().unwrap(), MetadataValue::new(v)))
+            .collect();
+        MetadataMap::new(values)
+    }
+
+    fn node(package: serde_json::Value, target: Option<serde_json::Value>) -> TargetNode {
+        let rule_type = RuleType::Starlark(Arc::new(StarlarkRuleType {
+            import_path: ImportPath::testing_new("cell//pkg:rules.bzl"),
+            name: "foo_lib".to_owned(),
+        }));
+        let attrs = target
+            .into_iter()
+            .map(|target| {
+                (
+                    "metadata",
+                    Attribute::new(None, "", AttrType::metadata()),
+                    CoercedAttr::Metadata(metadata(target)),
+                )
+            })
+            .collect();
+        TargetNode::testing_new_in_package(
+            TargetLabel::testing_parse("cell//pkg:foo"),
+            rule_type,
+            attrs,
+            metadata(package),
+        )
+    }
+
+    /// `buck.metadata` as printed by `uquery --output-attribute`.
+    fn query_metadata(node: &TargetNode) -> serde_json::Value {
+        let (_, attr) = node
+            .special_attrs()
+            .find(|(name, _)| *name == METADATA)
+            .unwrap();
+        attr.to_json(&AttrFmtContext::NO_CONTEXT).unwrap()
+    }
+
+    #[test]
+    fn test_metadata_from_package() {
+        let node = node(serde_json::json!({"metadata.oncall": "team"}), None);
+        assert_eq!(
+            serde_json::json!({"metadata.oncall": "team"}),
+            query_metadata(&node)
+        );
+    }
+
+    #[test]
+    fn test_metadata_from_target() {
+        let node = node(
+            serde_json::json!({}),
+            Some(serde_json::json!({"foo.bar": 1})),
+        );
+        assert_eq!(serde_json::json!({"foo.bar": 1}), query_metadata(&node));
+    }
+
+    #[test]
+    fn test_metadata_target_overrides_package() {
+        let node = node(
+            serde_json::json!({"metadata.oncall": "team", "metadata.tier": "prod"}),
+            Some(serde_json::json!({"metadata.oncall": "other_team", "foo.bar": 1})),
+        );
+        assert_eq!(
+            serde_json::json!({
+                "metadata.oncall": "other_team",
+                "metadata.tier": "prod",
+                "foo.bar": 1,
+            }),
+            query_metadata(&node)
+        );
+        // Cached on the node rather than merged on every call.
+        assert!(std::ptr::eq(
+            node.metadata_with_package(),
+            node.metadata_with_package()
+        ));
+    }
+}
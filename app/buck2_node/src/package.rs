@@ -12,6 +12,8 @@ use std::sync::Arc;
 use allocative::Allocative;
 use buck2_core::build_file_path::BuildFilePath;
 
+use crate::metadata::map::MetadataMap;
+
 /// Package-specific data for `TargetNode`.
 ///
 /// (Note this has nothing to do with `PACKAGE` files which are not implemented
@@ -22,4 +24,7 @@ pub struct Package {
     pub buildfile_path: Arc<BuildFilePath>,
     /// The oncall attribute, if set
     pub oncall: Option<Arc<String>>,
+    /// The package values of the `PACKAGE` files of the package, which are the metadata of its
+    /// targets unless overridden by their `metadata` attribute.
+    pub metadata: MetadataMap,
 }
@@ -110,6 +110,21 @@ impl VisibilityPatternList {
     }
 }
 
+impl VisibilityPatternList {
+    /// Patterns of this list which match the target, for explaining visibility decisions.
+    /// `PUBLIC` matches everything and is reported as itself.
+    pub fn matching_patterns(&self, target: &TargetLabel) -> Vec<String> {
+        match self {
+            VisibilityPatternList::Public => vec![VisibilityPattern::PUBLIC.to_owned()],
+            VisibilityPatternList::List(patterns) => patterns
+                .iter()
+                .filter(|p| p.0.matches(target))
+                .map(|p| p.to_string())
+                .collect(),
+        }
+    }
+}
+
 impl Display for VisibilityPatternList {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
@@ -262,3 +277,33 @@ impl VisibilityWithinViewBuilder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::target::label::TargetLabel;
+
+    use crate::visibility::VisibilityPatternList;
+
+    #[test]
+    fn test_matching_patterns() {
+        let target = TargetLabel::testing_parse("root//foo/bar:baz");
+        let list = VisibilityPatternList::testing_parse(&[
+            "root//foo/...",
+            "root//other:x",
+            "root//foo/bar:",
+        ]);
+        assert_eq!(
+            vec!["root//foo/...".to_owned(), "root//foo/bar:".to_owned()],
+            list.matching_patterns(&target)
+        );
+        assert_eq!(
+            vec!["PUBLIC".to_owned()],
+            VisibilityPatternList::testing_parse(&["PUBLIC"]).matching_patterns(&target)
+        );
+        assert!(
+            VisibilityPatternList::testing_parse(&["root//other/..."])
+                .matching_patterns(&target)
+                .is_empty()
+        );
+    }
+}
@@ -60,6 +60,8 @@ pub fn starlark_profiler_configuration_from_request(
             })
         }
         ProfileOpts::BxlProfile(_) => Ok(StarlarkProfilerConfiguration::ProfileBxl(profile_mode)),
+        // Benchmarks time analysis, so it must not be slowed down by profiling.
+        ProfileOpts::AnalysisBench(_) => Ok(StarlarkProfilerConfiguration::None),
     }
 }
 
@@ -167,6 +167,13 @@ pub trait QueryEnvironment: Send + Sync {
     /// Evaluates a file literal
     async fn eval_file_literal(&self, literal: &str) -> anyhow::Result<FileSet>;
 
+    /// Evaluates a file path containing glob characters to the matching files.
+    async fn eval_file_glob(&self, _pattern: &str) -> anyhow::Result<FileSet> {
+        Err(anyhow::anyhow!(QueryError::FunctionUnimplemented(
+            "glob() is implemented only for uquery, cquery and aquery."
+        )))
+    }
+
     /// Performs a depth first traversal, with a post-order callback. The
     /// delegate defines the traversal and receives the callback.
     async fn dfs_postorder(
@@ -251,6 +258,28 @@ pub trait QueryEnvironment: Send + Sync {
         Ok(delegate.path)
     }
 
+    /// All the paths following reverse dependencies from `from` up to any node of `to`.
+    ///
+    /// A reverse path from `from` to `to` is a dependency path from `to` to `from`, so this is
+    /// `allpaths` with its arguments swapped.
+    async fn allrdepspaths(
+        &self,
+        from: &TargetSet<Self::Target>,
+        to: &TargetSet<Self::Target>,
+    ) -> anyhow::Result<TargetSet<Self::Target>> {
+        self.allpaths(to, from).await
+    }
+
+    /// Some path following reverse dependencies from `from` up to any node of `to`, ordered
+    /// starting at `from`.
+    async fn somerdepspaths(
+        &self,
+        from: &TargetSet<Self::Target>,
+        to: &TargetSet<Self::Target>,
+    ) -> anyhow::Result<TargetSet<Self::Target>> {
+        self.somepath(to, from).await
+    }
+
     async fn allbuildfiles(&self, _universe: &TargetSet<Self::Target>) -> anyhow::Result<FileSet> {
         Err(anyhow::anyhow!(QueryError::FunctionUnimplemented(
             "allbuildfiles() is implemented only for uquery and cquery.",
@@ -263,6 +292,15 @@ pub trait QueryEnvironment: Send + Sync {
         )))
     }
 
+    async fn targets_in_buildfile(
+        &self,
+        _buildfiles: &FileSet,
+    ) -> anyhow::Result<TargetSet<Self::Target>> {
+        Err(anyhow::anyhow!(QueryError::FunctionUnimplemented(
+            "targets_in_buildfile() is implemented only for uquery."
+        )))
+    }
+
     async fn rdeps(
         &self,
         universe: &TargetSet<Self::Target>,
@@ -366,3 +366,28 @@ async fn test_paths_with_cycles_present() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_rdeps_paths() -> anyhow::Result<()> {
+    let mut env = TestEnvBuilder::default();
+    env.edge(1, 2);
+    env.edge(2, 3);
+    env.edge(1, 10);
+    env.edge(10, 11);
+    env.edge(11, 3);
+    // Unused edges
+    env.edge(3, 4);
+    env.edge(20, 10);
+    let env = env.build();
+
+    let path = env.allrdepspaths(&env.set("3")?, &env.set("1")?).await?;
+    assert_eq!(path, env.allpaths(&env.set("1")?, &env.set("3")?).await?);
+
+    let path = env.somerdepspaths(&env.set("3")?, &env.set("1")?).await?;
+    assert_eq!(path, env.set("3,11,10,1")?);
+
+    let path = env.allrdepspaths(&env.set("1")?, &env.set("3")?).await?;
+    assert_eq!(path, TargetSet::new());
+
+    Ok(())
+}
@@ -212,6 +212,28 @@ pub trait TargetSetExt {
         self.attrfilter(attribute, &filter)
     }
 
+    /// Filter targets by regex partial match on the string rendering of the whole attribute, so
+    /// that for a list or dict, the regex can match across its elements.
+    fn attrregexfilter_rendered(
+        &self,
+        attribute: &str,
+        value: &str,
+    ) -> anyhow::Result<TargetSet<Self::T>> {
+        let regex = Regex::new(value)?;
+        self.filter(|node| {
+            node.map_attr(attribute, |val| match val {
+                None => Ok(false),
+                Some(v) => Ok(regex.is_match(&node.attr_to_string_alternate(v))?),
+            })
+        })
+    }
+
+    /// Filter targets where the attribute, or any of its elements for a list or dict, is one of
+    /// `values`.
+    fn attrin(&self, attribute: &str, values: &[&str]) -> anyhow::Result<TargetSet<Self::T>> {
+        self.attrfilter(attribute, &|v| Ok(values.contains(&v)))
+    }
+
     /// Filter targets by fully qualified name using regex partial match.
     fn filter_name(&self, regex: &str) -> anyhow::Result<TargetSet<Self::T>> {
         let mut re = RegexBuilder::new(regex);
@@ -247,3 +269,248 @@ impl<T: QueryTarget> Display for TargetSet<T> {
         fmt_container(f, "[", "]", self.targets.iter().map(|t| t.node_ref()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    use buck2_core::build_file_path::BuildFilePath;
+    use buck2_core::cells::cell_path::CellPath;
+    use buck2_query::query::environment::LabeledNode;
+    use derive_more::Display;
+    use dupe::Dupe;
+    use serde::Serializer;
+
+    use crate::query::environment::NodeLabel;
+    use crate::query::environment::QueryTarget;
+    use crate::query::syntax::simple::eval::set::TargetSet;
+    use crate::query::syntax::simple::eval::set::TargetSetExt;
+
+    #[derive(Clone, Hash, PartialEq, Eq, Debug, Display)]
+    struct TestTargetRef(&'static str);
+
+    impl NodeLabel for TestTargetRef {}
+
+    #[derive(Debug, PartialEq, Eq)]
+    enum TestAttr {
+        String(&'static str),
+        List(Vec<&'static str>),
+    }
+
+    #[derive(Debug, Clone, Dupe, PartialEq, Eq)]
+    struct TestTarget(Arc<(TestTargetRef, BTreeMap<&'static str, TestAttr>)>);
+
+    impl TestTarget {
+        fn new(label: &'static str, attrs: Vec<(&'static str, TestAttr)>) -> Self {
+            Self(Arc::new((
+                TestTargetRef(label),
+                attrs.into_iter().collect(),
+            )))
+        }
+    }
+
+    impl LabeledNode for TestTarget {
+        type NodeRef = TestTargetRef;
+
+        fn node_ref(&self) -> &Self::NodeRef {
+            &self.0.0
+        }
+    }
+
+    impl QueryTarget for TestTarget {
+        type Attr<'a> = TestAttr;
+
+        fn inputs_for_each<E, F: FnMut(CellPath) -> Result<(), E>>(
+            &self,
+            _func: F,
+        ) -> Result<(), E> {
+            unimplemented!()
+        }
+
+        fn rule_type(&self) -> Cow<str> {
+            unimplemented!()
+        }
+
+        fn buildfile_path(&self) -> &BuildFilePath {
+            unimplemented!()
+        }
+
+        fn deps<'a>(&'a self) -> Box<dyn Iterator<Item = &'a Self::NodeRef> + Send + 'a> {
+            Box::new(std::iter::empty())
+        }
+
+        fn exec_deps<'a>(&'a self) -> Box<dyn Iterator<Item = &'a Self::NodeRef> + Send + 'a> {
+            Box::new(std::iter::empty())
+        }
+
+        fn target_deps<'a>(&'a self) -> Box<dyn Iterator<Item = &'a Self::NodeRef> + Send + 'a> {
+            Box::new(std::iter::empty())
+        }
+
+        fn attr_to_string_alternate(&self, attr: &Self::Attr<'_>) -> String {
+            match attr {
+                TestAttr::String(s) => (*s).to_owned(),
+                TestAttr::List(items) => format!("[{}]", items.join(", ")),
+            }
+        }
+
+        fn attr_serialize<S: Serializer>(
+            &self,
+            _attr: &Self::Attr<'_>,
+            _serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            unimplemented!()
+        }
+
+        fn attr_any_matches(
+            attr: &Self::Attr<'_>,
+            filter: &dyn Fn(&str) -> anyhow::Result<bool>,
+        ) -> anyhow::Result<bool> {
+            match attr {
+                TestAttr::String(s) => filter(s),
+                TestAttr::List(items) => {
+                    for item in items {
+                        if filter(item)? {
+                            return Ok(true);
+                        }
+                    }
+                    Ok(false)
+                }
+            }
+        }
+
+        fn special_attrs_for_each<E, F: FnMut(&str, &Self::Attr<'_>) -> Result<(), E>>(
+            &self,
+            _func: F,
+        ) -> Result<(), E> {
+            unimplemented!()
+        }
+
+        fn attrs_for_each<E, F: FnMut(&str, &Self::Attr<'_>) -> Result<(), E>>(
+            &self,
+            mut func: F,
+        ) -> Result<(), E> {
+            for (name, attr) in &self.0.1 {
+                func(name, attr)?;
+            }
+            Ok(())
+        }
+
+        fn map_attr<R, F: FnMut(Option<&Self::Attr<'_>>) -> R>(&self, key: &str, mut func: F) -> R {
+            func(self.0.1.get(key))
+        }
+
+        fn call_stack(&self) -> Option<String> {
+            None
+        }
+    }
+
+    fn targets() -> TargetSet<TestTarget> {
+        let mut targets = TargetSet::new();
+        targets.insert(TestTarget::new(
+            "//:cpp",
+            vec![
+                ("srcs", TestAttr::List(vec!["a.cpp", "b.h"])),
+                ("labels", TestAttr::List(vec!["ci_only", "slow"])),
+                ("name", TestAttr::String("cpp")),
+            ],
+        ));
+        targets.insert(TestTarget::new(
+            "//:py",
+            vec![
+                ("srcs", TestAttr::List(vec!["main.py"])),
+                ("labels", TestAttr::List(vec!["manual"])),
+                ("name", TestAttr::String("py")),
+            ],
+        ));
+        targets.insert(TestTarget::new(
+            "//:genrule",
+            vec![("name", TestAttr::String("genrule"))],
+        ));
+        targets
+    }
+
+    fn labels(targets: TargetSet<TestTarget>) -> Vec<&'static str> {
+        targets.iter().map(|t| t.node_ref().0).collect()
+    }
+
+    #[test]
+    fn test_attrregexfilter_matches_elements() -> anyhow::Result<()> {
+        // Partial match on an element, as before.
+        assert_eq!(
+            vec!["//:cpp"],
+            labels(targets().attrregexfilter("srcs", r"\.cpp$")?)
+        );
+        assert_eq!(
+            vec!["//:cpp", "//:py"],
+            labels(targets().attrregexfilter("srcs", r"\.(h|py)$")?)
+        );
+        assert_eq!(
+            vec!["//:genrule"],
+            labels(targets().attrregexfilter("name", "gen")?)
+        );
+        // Targets without the attribute never match.
+        assert_eq!(
+            Vec::<&str>::new(),
+            labels(targets().attrregexfilter("srcs", "genrule")?)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_attrregexfilter_does_not_match_whole_attribute() -> anyhow::Result<()> {
+        assert_eq!(
+            Vec::<&str>::new(),
+            labels(targets().attrregexfilter("srcs", r"a\.cpp, b\.h")?)
+        );
+        assert_eq!(
+            Vec::<&str>::new(),
+            labels(targets().attrregexfilter("srcs", r"^\[main\.py\]$")?)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_attrregexfilter_rendered() -> anyhow::Result<()> {
+        // Matches across the elements of a list, which no single element matches.
+        assert_eq!(
+            vec!["//:cpp"],
+            labels(targets().attrregexfilter_rendered("srcs", r"a\.cpp, b\.h")?)
+        );
+        assert_eq!(
+            vec!["//:py"],
+            labels(targets().attrregexfilter_rendered("srcs", r"^\[main\.py\]$")?)
+        );
+        // An element alone is not the whole rendering.
+        assert_eq!(
+            Vec::<&str>::new(),
+            labels(targets().attrregexfilter_rendered("srcs", r"^main\.py$")?)
+        );
+        assert_eq!(
+            vec!["//:genrule"],
+            labels(targets().attrregexfilter_rendered("name", "^gen")?)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_attrin() -> anyhow::Result<()> {
+        assert_eq!(
+            vec!["//:cpp", "//:py"],
+            labels(targets().attrin("labels", &["manual", "ci_only"])?)
+        );
+        // Elements must be equal to one of the values, not only contain it.
+        assert_eq!(
+            Vec::<&str>::new(),
+            labels(targets().attrin("labels", &["ci", "man"])?)
+        );
+        assert_eq!(
+            vec!["//:py", "//:genrule"],
+            labels(targets().attrin("name", &["py", "genrule"])?)
+        );
+        assert_eq!(Vec::<&str>::new(), labels(targets().attrin("labels", &[])?));
+        Ok(())
+    }
+}
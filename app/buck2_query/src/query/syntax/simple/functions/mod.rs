@@ -228,6 +228,44 @@ impl<Env: QueryEnvironment> DefaultQueryFunctionsModule<Env> {
         Ok(self.implementation.somepath(env, &from, &to).await?.into())
     }
 
+    /// Computes all reverse dependency paths.
+    ///
+    /// The `allrdepspaths(from, to)` function evaluates to the graph formed by the paths following
+    /// reverse dependencies from the target expression from up to the target expression to. For example,
+    /// `buck2 uquery "allrdepspaths('//foo/bar/lib:baz', '//ci/...')"`
+    /// shows every path from `//foo/bar/lib:baz` up to the targets in `//ci/...` that depend on it.
+    ///
+    /// This is the same as `allpaths(to, from)`.
+    async fn allrdepspaths(
+        &self,
+        env: &Env,
+        from: TargetSet<Env::Target>,
+        to: TargetSet<Env::Target>,
+    ) -> QueryFuncResult<Env> {
+        Ok(self
+            .implementation
+            .allrdepspaths(env, &from, &to)
+            .await?
+            .into())
+    }
+
+    /// Computes a single reverse dependency path.
+    ///
+    /// The `somerdepspaths(from, to)` function evaluates to one path following reverse dependencies
+    /// from the target expression from up to the target expression to, starting at from.
+    async fn somerdepspaths(
+        &self,
+        env: &Env,
+        from: TargetSet<Env::Target>,
+        to: TargetSet<Env::Target>,
+    ) -> QueryFuncResult<Env> {
+        Ok(self
+            .implementation
+            .somerdepspaths(env, &from, &to)
+            .await?
+            .into())
+    }
+
     /// The `attrfilter(attribute, value, targets)` operator evaluates the given target expression and filters the resulting build targets to those where the specified attribute contains the specified value.
     /// In this context, the term attribute refers to an argument in a build rule, such as name, headers, srcs, or deps.
     ///
@@ -261,6 +299,12 @@ impl<Env: QueryEnvironment> DefaultQueryFunctionsModule<Env> {
             .into())
     }
 
+    /// The `attrregexfilter(attribute, regex, targets)` operator is like `attrfilter()` but the
+    /// value is a regular expression, which is partially matched against the attribute value,
+    /// or its elements for a list or dict.
+    ///
+    /// For example:
+    /// `buck2 uquery "attrregexfilter(srcs, '\.cpp$', '//...')"` returns the build targets with a `.cpp` source.
     async fn attrregexfilter(
         &self,
         attr: String,
@@ -273,6 +317,42 @@ impl<Env: QueryEnvironment> DefaultQueryFunctionsModule<Env> {
             .into())
     }
 
+    /// The `attrregexfilter_rendered(attribute, regex, targets)` operator is like
+    /// `attrregexfilter()`, but the regular expression is partially matched against the string
+    /// rendering of the whole attribute, as printed by `--output-attribute`. So for a list or
+    /// dict, one regex can match across its elements.
+    ///
+    /// For example:
+    /// `buck2 uquery "attrregexfilter_rendered(srcs, 'a\.h.*b\.h', '//...')"` returns the build
+    /// targets with both `a.h` and `b.h` in their sources, in this order.
+    async fn attrregexfilter_rendered(
+        &self,
+        attr: String,
+        value: String,
+        targets: TargetSet<Env::Target>,
+    ) -> QueryFuncResult<Env> {
+        Ok(self
+            .implementation
+            .attrregexfilter_rendered(&attr, &value, &targets)?
+            .into())
+    }
+
+    /// The `attrin(attribute, values, targets)` operator filters the targets to those where the
+    /// attribute is one of the whitespace-separated `values`, or for a list (or dict) attribute,
+    /// where any of its elements is one of them.
+    ///
+    /// For example:
+    /// `buck2 uquery "attrin(labels, 'ci_only manual', '//...')"` returns the build targets having
+    /// either `ci_only` or `manual` in their `labels`.
+    async fn attrin(
+        &self,
+        attr: String,
+        values: String,
+        targets: TargetSet<Env::Target>,
+    ) -> QueryFuncResult<Env> {
+        Ok(self.implementation.attrin(&attr, &values, &targets)?.into())
+    }
+
     async fn buildfile(&self, targets: TargetSet<Env::Target>) -> QueryFuncResult<Env> {
         Ok(self.implementation.buildfile(&targets).into())
     }
@@ -302,6 +382,20 @@ impl<Env: QueryEnvironment> DefaultQueryFunctionsModule<Env> {
             .into())
     }
 
+    /// Find all targets defined in the given build files.
+    ///
+    /// Together with `rbuildfiles()`, this finds the targets affected by a change to a build file
+    /// or to a `.bzl` file loaded by build files. For example,
+    /// `buck2 uquery "targets_in_buildfile(rbuildfiles(allbuildfiles(//...), 'foo/defs.bzl'))"`
+    /// returns the targets of the packages under `//...` which load `foo/defs.bzl`, directly or not.
+    async fn targets_in_buildfile(&self, env: &Env, buildfiles: FileSet) -> QueryFuncResult<Env> {
+        Ok(self
+            .implementation
+            .targets_in_buildfile(env, &buildfiles)
+            .await?
+            .into())
+    }
+
     async fn deps(
         &self,
         evaluator: &QueryEvaluator<'_, Env>,
@@ -337,6 +431,18 @@ impl<Env: QueryEnvironment> DefaultQueryFunctionsModule<Env> {
         }
     }
 
+    /// The `glob(pattern)` function returns the files matching `pattern`, which is a file path
+    /// (in any cell, e.g. `cell//foo/**/*.h`) containing glob characters. In a glob, `*` does not
+    /// match `/` and `**` matches any number of directories. Ignored directories are not searched.
+    ///
+    /// A pattern without glob characters is the file literal itself.
+    ///
+    /// Example: `buck2 uquery "owner(glob('foo/**/*.h'))"` returns the owners of all the headers
+    /// under `foo`.
+    async fn glob(&self, env: &Env, pattern: String) -> QueryFuncResult<Env> {
+        Ok(self.implementation.glob(env, &pattern).await?.into())
+    }
+
     async fn inputs(&self, targets: TargetSet<Env::Target>) -> QueryFuncResult<Env> {
         Ok(self.implementation.inputs(&targets)?.into())
     }
@@ -367,6 +473,9 @@ impl<Env: QueryEnvironment> DefaultQueryFunctionsModule<Env> {
     /// It is possible for the specified file to have multiple owners, in which case, owner() returns a set of targets.
     ///
     /// If no owner for the file is found, owner() outputs the message: `No owner was found for <file>`
+    ///
+    /// Files can be in any cell, using the cell alias (`owner('cell//path/to/file.txt')`). To find
+    /// the owners of the files matching a glob, use `glob()`: `owner(glob('cell//foo/**/*.h'))`.
     async fn owner(&self, env: &Env, files: FileSet) -> QueryFuncResult<Env> {
         Ok(self.implementation.owner(env, &files).await?.into())
     }
@@ -498,6 +607,24 @@ impl<Env: QueryEnvironment> DefaultQueryFunctions<Env> {
         Ok(env.somepath(from, to).await?)
     }
 
+    pub async fn allrdepspaths(
+        &self,
+        env: &Env,
+        from: &TargetSet<Env::Target>,
+        to: &TargetSet<Env::Target>,
+    ) -> Result<TargetSet<Env::Target>, QueryError> {
+        Ok(env.allrdepspaths(from, to).await?)
+    }
+
+    pub async fn somerdepspaths(
+        &self,
+        env: &Env,
+        from: &TargetSet<Env::Target>,
+        to: &TargetSet<Env::Target>,
+    ) -> Result<TargetSet<Env::Target>, QueryError> {
+        Ok(env.somerdepspaths(from, to).await?)
+    }
+
     pub fn attrfilter(
         &self,
         attr: &str,
@@ -525,10 +652,33 @@ impl<Env: QueryEnvironment> DefaultQueryFunctions<Env> {
         targets.attrregexfilter(attr, value)
     }
 
+    pub fn attrregexfilter_rendered(
+        &self,
+        attr: &str,
+        value: &str,
+        targets: &TargetSet<Env::Target>,
+    ) -> anyhow::Result<TargetSet<Env::Target>> {
+        targets.attrregexfilter_rendered(attr, value)
+    }
+
+    pub fn attrin(
+        &self,
+        attr: &str,
+        values: &str,
+        targets: &TargetSet<Env::Target>,
+    ) -> anyhow::Result<TargetSet<Env::Target>> {
+        let values: Vec<&str> = values.split_whitespace().collect();
+        targets.attrin(attr, &values)
+    }
+
     pub fn buildfile(&self, targets: &TargetSet<Env::Target>) -> FileSet {
         targets.buildfile()
     }
 
+    pub async fn glob(&self, env: &Env, pattern: &str) -> anyhow::Result<FileSet> {
+        env.eval_file_glob(pattern).await
+    }
+
     pub async fn allbuildfiles(
         &self,
         env: &Env,
@@ -546,6 +696,14 @@ impl<Env: QueryEnvironment> DefaultQueryFunctions<Env> {
         env.rbuildfiles(universe, argset).await
     }
 
+    pub async fn targets_in_buildfile(
+        &self,
+        env: &Env,
+        buildfiles: &FileSet,
+    ) -> anyhow::Result<TargetSet<Env::Target>> {
+        env.targets_in_buildfile(buildfiles).await
+    }
+
     pub async fn deps(
         &self,
         env: &Env,
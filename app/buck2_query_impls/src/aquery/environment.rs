@@ -116,6 +116,14 @@ impl<'c> QueryEnvironment for AqueryEnvironment<'c> {
             .await
     }
 
+    async fn eval_file_glob(&self, pattern: &str) -> anyhow::Result<FileSet> {
+        self.delegate
+            .cquery_delegate()
+            .uquery_delegate()
+            .eval_file_glob(pattern)
+            .await
+    }
+
     async fn dfs_postorder(
         &self,
         root: &TargetSet<Self::Target>,
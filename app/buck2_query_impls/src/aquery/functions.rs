@@ -12,8 +12,10 @@ use std::fmt::Debug;
 use std::marker::PhantomData;
 
 use buck2_artifact::artifact::provide_outputs::ProvideActionKey;
+use buck2_build_api::actions::query::ActionData;
 use buck2_build_api::actions::query::ActionQueryNode;
 use buck2_build_api::actions::query::ActionQueryNodeData;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_query::query::syntax::simple::eval::error::QueryError;
 use buck2_query::query::syntax::simple::eval::set::TargetSet;
 use buck2_query::query::syntax::simple::eval::values::QueryValue;
@@ -23,6 +25,9 @@ use buck2_query::query::syntax::simple::functions::DefaultQueryFunctionsModule;
 use buck2_query::query::syntax::simple::functions::QueryFunctions;
 use buck2_query::query_module;
 use buck2_query_parser::BinaryOp;
+use globset::Glob;
+use globset::GlobMatcher;
+use regex::Regex;
 
 use crate::aquery::environment::AqueryEnvironment;
 
@@ -64,6 +69,35 @@ pub fn aquery_functions<'a>() -> impl QueryFunctions<Env = AqueryEnvironment<'a>
     }
 }
 
+/// Keep the actions (not the analysis nodes) of `targets` for which `filter` holds.
+fn filter_actions(
+    targets: TargetSet<ActionQueryNode>,
+    filter: impl Fn(&ActionData) -> anyhow::Result<bool>,
+) -> Result<QueryValue<ActionQueryNode>, QueryError> {
+    let mut res = TargetSet::new();
+    for node in targets.into_iter() {
+        if let ActionQueryNodeData::Action(action) = node.data() {
+            if filter(action)? {
+                res.insert(node);
+            }
+        }
+    }
+    Ok(res.into())
+}
+
+fn any_path_matches_glob(paths: &[ProjectRelativePathBuf], matcher: &GlobMatcher) -> bool {
+    paths.iter().any(|path| matcher.is_match(path.as_str()))
+}
+
+fn any_path_is(paths: &[ProjectRelativePathBuf], path: &str) -> bool {
+    let path = path.trim_end_matches('/');
+    paths.iter().any(|p| p.as_str() == path)
+}
+
+fn identifier_matches(regex: &Regex, identifier: Option<&str>) -> bool {
+    regex.is_match(identifier.unwrap_or(""))
+}
+
 #[derive(Debug)]
 pub(crate) struct AqueryFunctions<'a>(pub(crate) PhantomData<&'a ()>);
 
@@ -134,4 +168,127 @@ impl<'a> AqueryFunctions<'a> {
 
         Ok(res.into())
     }
+
+    /// Select the actions with an output matching a glob, the glob being matched against the
+    /// path of the output relative to the project root.
+    ///
+    /// For example, `buck2 aquery "outputs_glob('buck-out/**/foo.o', deps(//foo:bar))"` finds
+    /// the action producing `foo.o`.
+    pub(crate) async fn outputs_glob(
+        &self,
+        glob: String,
+        targets: TargetSet<ActionQueryNode>,
+    ) -> Result<QueryValue<ActionQueryNode>, QueryError> {
+        let matcher = Glob::new(&glob)
+            .map_err(anyhow::Error::from)?
+            .compile_matcher();
+        filter_actions(targets, |action| {
+            Ok(any_path_matches_glob(&action.output_paths()?, &matcher))
+        })
+    }
+
+    /// Select the actions taking the given artifact as an input, the artifact being given by
+    /// its path relative to the project root (e.g. `foo/bar.cpp` or
+    /// `buck-out/v2/gen/root/<hash>/foo/__bar__/bar.o`).
+    ///
+    /// Only the direct inputs of the actions are considered, not the inputs they get through
+    /// transitive sets.
+    pub(crate) async fn has_input(
+        &self,
+        path: String,
+        targets: TargetSet<ActionQueryNode>,
+    ) -> Result<QueryValue<ActionQueryNode>, QueryError> {
+        filter_actions(targets, |action| {
+            Ok(any_path_is(&action.input_paths()?, &path))
+        })
+    }
+
+    /// Select the actions whose category matches a regex (partially, use `^` and `$` to match
+    /// the whole category).
+    pub(crate) async fn category(
+        &self,
+        regex: String,
+        targets: TargetSet<ActionQueryNode>,
+    ) -> Result<QueryValue<ActionQueryNode>, QueryError> {
+        let regex = Regex::new(&regex).map_err(anyhow::Error::from)?;
+        filter_actions(targets, |action| {
+            Ok(regex.is_match(action.action().category().as_str()))
+        })
+    }
+
+    /// Select the actions whose identifier matches a regex (partially, use `^` and `$` to match
+    /// the whole identifier). Actions without identifier are matched as an empty identifier.
+    pub(crate) async fn identifier(
+        &self,
+        regex: String,
+        targets: TargetSet<ActionQueryNode>,
+    ) -> Result<QueryValue<ActionQueryNode>, QueryError> {
+        let regex = Regex::new(&regex).map_err(anyhow::Error::from)?;
+        filter_actions(targets, |action| {
+            Ok(identifier_matches(&regex, action.action().identifier()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+    use globset::Glob;
+    use regex::Regex;
+
+    use crate::aquery::functions::any_path_is;
+    use crate::aquery::functions::any_path_matches_glob;
+    use crate::aquery::functions::identifier_matches;
+
+    fn paths(paths: &[&str]) -> Vec<ProjectRelativePathBuf> {
+        paths
+            .iter()
+            .map(|p| ProjectRelativePathBuf::unchecked_new((*p).to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn test_outputs_glob() -> anyhow::Result<()> {
+        let outputs = paths(&[
+            "buck-out/v2/gen/root/0123456789abcdef/foo/__bar__/foo.o",
+            "buck-out/v2/gen/root/0123456789abcdef/foo/__bar__/foo.d",
+        ]);
+        let matches = |glob: &str| -> anyhow::Result<bool> {
+            Ok(any_path_matches_glob(
+                &outputs,
+                &Glob::new(glob)?.compile_matcher(),
+            ))
+        };
+        assert!(matches("buck-out/**/foo.o")?);
+        assert!(matches("**/__bar__/*.d")?);
+        assert!(!matches("foo/foo.o")?);
+        assert!(!matches("buck-out/**/bar.o")?);
+        assert!(!any_path_matches_glob(
+            &[],
+            &Glob::new("**")?.compile_matcher()
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_has_input() {
+        let inputs = paths(&["foo/bar.cpp", "foo/include"]);
+        assert!(any_path_is(&inputs, "foo/bar.cpp"));
+        assert!(any_path_is(&inputs, "foo/include/"));
+        assert!(!any_path_is(&inputs, "foo"));
+        assert!(!any_path_is(&inputs, "bar.cpp"));
+    }
+
+    #[test]
+    fn test_identifier() -> anyhow::Result<()> {
+        assert!(identifier_matches(&Regex::new("bar")?, Some("foo/bar.cpp")));
+        assert!(!identifier_matches(
+            &Regex::new("^bar")?,
+            Some("foo/bar.cpp")
+        ));
+        // Actions without identifier are matched as an empty identifier.
+        assert!(identifier_matches(&Regex::new("^$")?, None));
+        assert!(!identifier_matches(&Regex::new(".")?, None));
+        Ok(())
+    }
 }
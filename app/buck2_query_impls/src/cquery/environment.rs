@@ -33,6 +33,7 @@ use dice::DiceComputations;
 use dupe::Dupe;
 use tracing::warn;
 
+use crate::cquery::functions::CqueryFunctions;
 use crate::uquery::environment::allbuildfiles;
 use crate::uquery::environment::rbuildfiles;
 use crate::uquery::environment::QueryLiterals;
@@ -103,7 +104,10 @@ impl<'c> CqueryEnvironment<'c> {
     pub(crate) fn describe() -> QueryEnvironmentDescription {
         QueryEnvironmentDescription {
             name: "Cquery Environment".to_owned(),
-            mods: vec![DefaultQueryFunctionsModule::<Self>::describe()],
+            mods: vec![
+                DefaultQueryFunctionsModule::<Self>::describe(),
+                CqueryFunctions::describe(),
+            ],
         }
     }
 
@@ -219,6 +223,13 @@ impl<'c> QueryEnvironment for CqueryEnvironment<'c> {
             .await
     }
 
+    async fn eval_file_glob(&self, pattern: &str) -> anyhow::Result<FileSet> {
+        self.delegate
+            .uquery_delegate()
+            .eval_file_glob(pattern)
+            .await
+    }
+
     async fn dfs_postorder(
         &self,
         root: &TargetSet<ConfiguredTargetNode>,
@@ -18,7 +18,6 @@ use buck2_events::dispatch::console_message;
 use buck2_node::configured_universe::CqueryUniverse;
 use buck2_node::nodes::configured::ConfiguredTargetNode;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
-use buck2_query::query::syntax::simple::functions::DefaultQueryFunctionsModule;
 use dice::DiceComputations;
 use dupe::Dupe;
 use futures::stream::FuturesUnordered;
@@ -27,6 +26,7 @@ use gazebo::prelude::*;
 
 use crate::analysis::evaluator::eval_query;
 use crate::cquery::environment::CqueryEnvironment;
+use crate::cquery::functions::cquery_functions;
 use crate::dice::get_dice_query_delegate;
 use crate::dice::DiceQueryData;
 use crate::dice::DiceQueryDelegate;
@@ -36,7 +36,6 @@ use crate::uquery::environment::UqueryDelegate;
 
 pub struct CqueryEvaluator<'c> {
     dice_query_delegate: DiceQueryDelegate<'c>,
-    functions: DefaultQueryFunctionsModule<CqueryEnvironment<'c>>,
     owner_behavior: CqueryOwnerBehavior,
 }
 
@@ -47,7 +46,9 @@ impl CqueryEvaluator<'_> {
         query_args: &[A],
         target_universe: Option<&[U]>,
     ) -> anyhow::Result<QueryEvaluationResult<ConfiguredTargetNode>> {
-        eval_query(&self.functions, query, query_args, async move |literals| {
+        let functions = cquery_functions();
+
+        eval_query(&functions, query, query_args, async move |literals| {
             let (universe, resolved_literals) = match target_universe {
                 None => {
                     if literals.is_empty() {
@@ -103,10 +104,8 @@ pub async fn get_cquery_evaluator<'a, 'c: 'a>(
 ) -> anyhow::Result<CqueryEvaluator<'c>> {
     let dice_query_delegate =
         get_dice_query_delegate(ctx, working_dir, global_target_platform).await?;
-    let functions = DefaultQueryFunctionsModule::new();
     Ok(CqueryEvaluator {
         dice_query_delegate,
-        functions,
         owner_behavior,
     })
 }
@@ -0,0 +1,235 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::fmt;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+use buck2_core::configuration::data::ConfigurationData;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_query::query::syntax::simple::eval::error::QueryError;
+use buck2_query::query::syntax::simple::eval::set::TargetSet;
+use buck2_query::query::syntax::simple::eval::values::QueryValue;
+use buck2_query::query::syntax::simple::functions::helpers::QueryBinaryOp;
+use buck2_query::query::syntax::simple::functions::helpers::QueryFunction;
+use buck2_query::query::syntax::simple::functions::DefaultQueryFunctionsModule;
+use buck2_query::query::syntax::simple::functions::QueryFunctions;
+use buck2_query::query_module;
+use buck2_query_parser::BinaryOp;
+
+use crate::cquery::environment::CqueryEnvironment;
+
+pub fn cquery_functions<'a>() -> impl QueryFunctions<Env = CqueryEnvironment<'a>> {
+    struct Functions<'a> {
+        defaults: DefaultQueryFunctionsModule<CqueryEnvironment<'a>>,
+        extra_functions: CqueryFunctions<'a>,
+    }
+
+    impl Debug for Functions<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Functions").finish_non_exhaustive()
+        }
+    }
+
+    impl<'a> QueryFunctions for Functions<'a> {
+        type Env = CqueryEnvironment<'a>;
+
+        fn get(&self, name: &str) -> Option<&dyn QueryFunction<CqueryEnvironment<'a>>> {
+            if let Some(v) = self.extra_functions.get(name) {
+                Some(v)
+            } else {
+                self.defaults.get(name)
+            }
+        }
+
+        fn get_op(&self, op: BinaryOp) -> Option<&dyn QueryBinaryOp<CqueryEnvironment<'a>>> {
+            if let Some(v) = self.extra_functions.get_op(op) {
+                Some(v)
+            } else {
+                self.defaults.get_op(op)
+            }
+        }
+    }
+
+    Functions {
+        defaults: DefaultQueryFunctionsModule::new(),
+        extra_functions: CqueryFunctions(PhantomData),
+    }
+}
+
+/// Whether `cfg` is designated by `name`: its full name (`cfg//:platform#hash`), the name of
+/// its platform or its hash.
+fn configuration_matches(cfg: &ConfigurationData, name: &str) -> bool {
+    cfg.full_name() == name || cfg.short_name() == name || cfg.output_hash().as_str() == name
+}
+
+fn filter_configurations(
+    configurations: &str,
+    targets: TargetSet<ConfiguredTargetNode>,
+) -> TargetSet<ConfiguredTargetNode> {
+    let configurations: Vec<&str> = configurations.split_whitespace().collect();
+    let mut res = TargetSet::new();
+    for target in targets.into_iter() {
+        let cfg = target.label().cfg();
+        if configurations
+            .iter()
+            .any(|name| configuration_matches(cfg, name))
+        {
+            res.insert(target);
+        }
+    }
+    res
+}
+
+#[derive(Debug)]
+pub(crate) struct CqueryFunctions<'a>(pub(crate) PhantomData<&'a ()>);
+
+#[query_module(CqueryEnvironment<'a>)]
+impl<'a> CqueryFunctions<'a> {
+    /// The `configurations(configurations, targets)` function filters the targets to those
+    /// configured in one of the whitespace-separated `configurations`. Each configuration is
+    /// given by its full name (as printed after the targets, e.g. `cfg//:linux#0123456789abcdef`),
+    /// the name of its platform (e.g. `cfg//:linux`) or its hash.
+    ///
+    /// For example:
+    /// `buck2 cquery "configurations('cfg//:linux', deps(//foo:bar))"` returns the dependencies
+    /// of `//foo:bar` which are configured for `cfg//:linux`, leaving out e.g. the execution deps.
+    ///
+    /// The constraints of the configuration of each target are available in the
+    /// `buck.configuration_constraints` attribute (e.g. `--output-attribute buck.configuration_constraints`).
+    pub(crate) async fn configurations(
+        &self,
+        configurations: String,
+        targets: TargetSet<ConfiguredTargetNode>,
+    ) -> Result<QueryValue<ConfiguredTargetNode>, QueryError> {
+        Ok(filter_configurations(&configurations, targets).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use buck2_core::configuration::constraints::ConstraintKey;
+    use buck2_core::configuration::constraints::ConstraintValue;
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::configuration::data::ConfigurationDataData;
+    use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
+    use buck2_core::target::label::TargetLabel;
+    use buck2_node::attrs::attr_type::string::StringLiteral;
+    use buck2_node::attrs::configured_attr::ConfiguredAttr;
+    use buck2_node::nodes::attributes::CONFIGURATION_CONSTRAINTS;
+    use buck2_node::nodes::configured::ConfiguredTargetNode;
+    use buck2_query::query::environment::LabeledNode;
+    use buck2_query::query::syntax::simple::eval::set::TargetSet;
+
+    use crate::cquery::functions::filter_configurations;
+
+    fn linux() -> ConfigurationData {
+        ConfigurationData::from_platform(
+            "cfg//:linux".to_owned(),
+            ConfigurationDataData::new(BTreeMap::from_iter([(
+                ConstraintKey(TargetLabel::testing_parse("cfg//os:os")),
+                ConstraintValue(TargetLabel::testing_parse("cfg//os:linux")),
+            )])),
+        )
+        .unwrap()
+    }
+
+    fn macos() -> ConfigurationData {
+        ConfigurationData::from_platform(
+            "cfg//:macos".to_owned(),
+            ConfigurationDataData::new(BTreeMap::from_iter([(
+                ConstraintKey(TargetLabel::testing_parse("cfg//os:os")),
+                ConstraintValue(TargetLabel::testing_parse("cfg//os:macos")),
+            )])),
+        )
+        .unwrap()
+    }
+
+    fn targets() -> TargetSet<ConfiguredTargetNode> {
+        let mut targets = TargetSet::new();
+        for (name, cfg) in [
+            ("root//:a", linux()),
+            ("root//:b", macos()),
+            ("root//:c", ConfigurationData::unspecified()),
+        ] {
+            targets.insert(ConfiguredTargetNode::testing_new(
+                ConfiguredTargetLabel::testing_parse(name, cfg),
+                "foo_lib",
+            ));
+        }
+        targets
+    }
+
+    fn names(targets: TargetSet<ConfiguredTargetNode>) -> Vec<String> {
+        targets
+            .iter()
+            .map(|t| t.node_ref().unconfigured().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_filter_configurations() {
+        assert_eq!(
+            vec!["root//:a"],
+            names(filter_configurations("cfg//:linux", targets()))
+        );
+        assert_eq!(
+            vec!["root//:a"],
+            names(filter_configurations(linux().full_name(), targets()))
+        );
+        assert_eq!(
+            vec!["root//:b"],
+            names(filter_configurations(
+                macos().output_hash().as_str(),
+                targets()
+            ))
+        );
+        assert_eq!(
+            vec!["root//:a", "root//:b"],
+            names(filter_configurations("cfg//:macos  cfg//:linux", targets()))
+        );
+        assert_eq!(
+            Vec::<String>::new(),
+            names(filter_configurations("cfg//:windows", targets()))
+        );
+        assert_eq!(
+            Vec::<String>::new(),
+            names(filter_configurations("", targets()))
+        );
+    }
+
+    #[test]
+    fn test_configuration_constraints_attr() {
+        let constraints = |target: &ConfiguredTargetNode| {
+            target
+                .special_attrs()
+                .find(|(name, _)| *name == CONFIGURATION_CONSTRAINTS)
+                .map(|(_, attr)| attr)
+                .unwrap()
+        };
+        let string = |s: &str| ConfiguredAttr::String(StringLiteral(s.into()));
+
+        let targets: Vec<_> = targets().into_iter().collect();
+        assert_eq!(
+            ConfiguredAttr::Dict(
+                vec![(string("cfg//os:os"), string("cfg//os:linux"))]
+                    .into_iter()
+                    .collect()
+            ),
+            constraints(&targets[0])
+        );
+        // Builtin configurations have no constraints.
+        assert_eq!(
+            ConfiguredAttr::Dict(Vec::new().into_iter().collect()),
+            constraints(&targets[2])
+        );
+    }
+}
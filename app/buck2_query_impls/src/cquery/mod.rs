@@ -10,3 +10,4 @@
 pub(crate) mod bxl;
 pub mod environment;
 pub mod evaluator;
+pub mod functions;
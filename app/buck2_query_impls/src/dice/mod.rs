@@ -8,6 +8,7 @@
  */
 
 use std::collections::HashMap;
+use std::collections::VecDeque;
 use std::sync::Arc;
 
 use async_trait::async_trait;
@@ -15,6 +16,8 @@ use buck2_build_api::configure_targets::load_compatible_patterns;
 use buck2_common::dice::cells::HasCellResolver;
 use buck2_common::dice::data::HasIoProvider;
 use buck2_common::dice::file_ops::HasFileOps;
+use buck2_common::file_ops::FileOps;
+use buck2_common::file_ops::FileType;
 use buck2_common::package_boundary::HasPackageBoundaryExceptions;
 use buck2_common::package_boundary::PackageBoundaryExceptions;
 use buck2_common::package_listing::dice::HasPackageListingResolver;
@@ -57,7 +60,10 @@ use buck2_query::query::syntax::simple::eval::set::TargetSet;
 use dice::DiceComputations;
 use dupe::Dupe;
 use gazebo::prelude::*;
+use globset::GlobBuilder;
+use globset::GlobMatcher;
 use indexmap::indexset;
+use indexmap::IndexSet;
 
 use crate::cquery::environment::CqueryDelegate;
 use crate::uquery::environment::QueryLiterals;
@@ -131,6 +137,49 @@ impl LiteralParser {
             &self.project_root,
         )
     }
+
+    /// If the pattern contains glob characters, split it into the directory to search and the
+    /// glob matching paths relative to it.
+    fn parse_file_glob<'a>(&self, pattern: &'a str) -> anyhow::Result<Option<(CellPath, &'a str)>> {
+        let Some((dir, glob)) = split_file_glob(pattern) else {
+            return Ok(None);
+        };
+        let dir = if dir.is_empty() {
+            self.working_dir.clone()
+        } else if dir == "/" || dir.ends_with("//") {
+            // Filesystem or cell root.
+            self.parse_file_literal(dir)?
+        } else {
+            self.parse_file_literal(dir.trim_end_matches('/'))?
+        };
+        Ok(Some((dir, glob)))
+    }
+}
+
+/// Split a file pattern containing glob characters into the directory part (the components
+/// before the first glob, with its trailing `/`) and the glob.
+fn split_file_glob(pattern: &str) -> Option<(&str, &str)> {
+    let glob_start = pattern.find(|c| matches!(c, '*' | '?' | '[' | '{'))?;
+    Some(match pattern[..glob_start].rfind('/') {
+        Some(i) => (&pattern[..=i], &pattern[i + 1..]),
+        None => ("", pattern),
+    })
+}
+
+/// The matcher for the paths relative to the searched directory, and the maximum depth of the
+/// matching paths (`None` if unbounded).
+fn file_glob_matcher(glob: &str) -> anyhow::Result<(GlobMatcher, Option<usize>)> {
+    let matcher = GlobBuilder::new(glob)
+        .literal_separator(true)
+        .build()?
+        .compile_matcher();
+    // Without `**`, the glob only matches paths with as many components as it has.
+    let max_depth = if glob.contains("**") {
+        None
+    } else {
+        Some(glob.split('/').count())
+    };
+    Ok((matcher, max_depth))
 }
 
 /// A Uquery delegate that resolves TargetNodes with the provided
@@ -209,6 +258,41 @@ impl<'c> DiceQueryDelegate<'c> {
     }
 }
 
+impl<'c> DiceQueryDelegate<'c> {
+    /// List the files under `dir` matching `glob`. `*` does not match `/`, `**` matches
+    /// any number of directories. Ignored directories are not searched.
+    async fn expand_file_glob(&self, dir: CellPath, glob: &str) -> anyhow::Result<FileSet> {
+        let (matcher, max_depth) = file_glob_matcher(glob)?;
+
+        let file_ops = self.ctx.file_ops();
+        let mut files = IndexSet::new();
+        let mut queue = VecDeque::from([(dir, String::new(), 1)]);
+        while let Some((dir, relative, depth)) = queue.pop_front() {
+            for entry in file_ops.read_dir(dir.as_ref()).await?.included.iter() {
+                let path = dir.join(&entry.file_name);
+                let relative = if relative.is_empty() {
+                    entry.file_name.as_str().to_owned()
+                } else {
+                    format!("{}/{}", relative, entry.file_name)
+                };
+                match entry.file_type {
+                    FileType::Directory => {
+                        if max_depth.map_or(true, |max_depth| depth < max_depth) {
+                            queue.push_back((path, relative, depth + 1));
+                        }
+                    }
+                    FileType::File | FileType::Symlink | FileType::Unknown => {
+                        if matcher.is_match(&relative) {
+                            files.insert(FileNode(path));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(FileSet::new(files))
+    }
+}
+
 #[async_trait]
 impl<'c> UqueryDelegate for DiceQueryDelegate<'c> {
     async fn eval_build_file(
@@ -273,6 +357,13 @@ impl<'c> UqueryDelegate for DiceQueryDelegate<'c> {
         Ok(FileSet::new(indexset![FileNode(cell_path)]))
     }
 
+    async fn eval_file_glob(&self, pattern: &str) -> anyhow::Result<FileSet> {
+        match self.query_data.literal_parser.parse_file_glob(pattern)? {
+            Some((dir, glob)) => self.expand_file_glob(dir, glob).await,
+            None => self.eval_file_literal(pattern).await,
+        }
+    }
+
     fn ctx(&self) -> &DiceComputations {
         self.ctx
     }
@@ -392,3 +483,43 @@ pub(crate) async fn get_dice_query_delegate<'a, 'c: 'a>(
         )?),
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::dice::file_glob_matcher;
+    use crate::dice::split_file_glob;
+
+    #[test]
+    fn test_split_file_glob() {
+        assert_eq!(None, split_file_glob("foo/bar.h"));
+        assert_eq!(None, split_file_glob("cell//foo/bar.h"));
+        assert_eq!(Some(("", "*.h")), split_file_glob("*.h"));
+        assert_eq!(Some(("foo/", "**/*.h")), split_file_glob("foo/**/*.h"));
+        assert_eq!(
+            Some(("cell//foo/", "b?r/*.{h,cpp}")),
+            split_file_glob("cell//foo/b?r/*.{h,cpp}")
+        );
+        assert_eq!(Some(("cell//", "[ab]/x")), split_file_glob("cell//[ab]/x"));
+    }
+
+    #[test]
+    fn test_file_glob_matcher() -> anyhow::Result<()> {
+        let (matcher, max_depth) = file_glob_matcher("*.h")?;
+        assert_eq!(Some(1), max_depth);
+        assert!(matcher.is_match("a.h"));
+        // `*` does not match `/`.
+        assert!(!matcher.is_match("a/b.h"));
+
+        let (matcher, max_depth) = file_glob_matcher("*/*.{h,cpp}")?;
+        assert_eq!(Some(2), max_depth);
+        assert!(matcher.is_match("a/b.cpp"));
+        assert!(!matcher.is_match("b.cpp"));
+
+        let (matcher, max_depth) = file_glob_matcher("**/*.h")?;
+        assert_eq!(None, max_depth);
+        assert!(matcher.is_match("a.h"));
+        assert!(matcher.is_match("a/b/c.h"));
+        assert!(!matcher.is_match("a/b/c.cpp"));
+        Ok(())
+    }
+}
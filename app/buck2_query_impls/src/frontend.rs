@@ -12,12 +12,15 @@ use buck2_build_api::actions::query::ActionQueryNode;
 use buck2_build_api::query::oneshot::CqueryOwnerBehavior;
 use buck2_build_api::query::oneshot::QueryFrontend;
 use buck2_build_api::query::oneshot::QUERY_FRONTEND;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::legacy_configs::dice::HasLegacyConfigs;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_core::target::label::TargetLabel;
 use buck2_node::configured_universe::CqueryUniverse;
 use buck2_node::nodes::configured::ConfiguredTargetNode;
 use buck2_node::nodes::unconfigured::TargetNode;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
+use buck2_query_parser::macros::QueryMacros;
 use dice::DiceComputations;
 
 use crate::aquery::evaluator::get_aquery_evaluator;
@@ -28,6 +31,34 @@ use crate::uquery::evaluator::get_uquery_evaluator;
 
 struct QueryFrontendImpl;
 
+/// Query macros declared in the `[query_macros]` section of the root cell's buckconfig.
+async fn query_macros(ctx: &DiceComputations) -> anyhow::Result<QueryMacros> {
+    let cells = ctx.get_cell_resolver().await?;
+    let config = ctx.get_legacy_config_for_cell(cells.root_cell()).await?;
+    let mut macros = QueryMacros::new();
+    if let Some(section) = config.get_section("query_macros") {
+        for (declaration, body) in section.iter() {
+            macros.add(declaration, body.as_str())?;
+        }
+    }
+    Ok(macros)
+}
+
+/// Expand query macros in `--target-universe` patterns. A macro there may expand
+/// to several whitespace-separated patterns.
+fn expand_universe(macros: &QueryMacros, literals: &[String]) -> anyhow::Result<Vec<String>> {
+    let mut expanded = Vec::new();
+    for literal in literals {
+        expanded.extend(
+            macros
+                .expand(literal)?
+                .split_whitespace()
+                .map(str::to_owned),
+        );
+    }
+    Ok(expanded)
+}
+
 pub(crate) fn init_query_frontend() {
     QUERY_FRONTEND.init(&QueryFrontendImpl);
 }
@@ -42,9 +73,10 @@ impl QueryFrontend for QueryFrontendImpl {
         query_args: &[String],
         global_target_platform: Option<TargetLabel>,
     ) -> anyhow::Result<QueryEvaluationResult<TargetNode>> {
+        let query = query_macros(ctx).await?.expand(query)?;
         let evaluator = get_uquery_evaluator(ctx, working_dir, global_target_platform).await?;
 
-        evaluator.eval_query(query, query_args).await
+        evaluator.eval_query(&query, query_args).await
     }
 
     async fn eval_cquery(
@@ -57,6 +89,11 @@ impl QueryFrontend for QueryFrontendImpl {
         global_target_platform: Option<TargetLabel>,
         target_universe: Option<&[String]>,
     ) -> anyhow::Result<QueryEvaluationResult<ConfiguredTargetNode>> {
+        let macros = query_macros(ctx).await?;
+        let query = macros.expand(query)?;
+        let target_universe = target_universe
+            .map(|universe| expand_universe(&macros, universe))
+            .transpose()?;
         let evaluator =
             get_cquery_evaluator(ctx, working_dir, global_target_platform, owner_behavior).await?;
 
@@ -67,7 +104,7 @@ impl QueryFrontend for QueryFrontendImpl {
         //   buck2 cquery --target-universe android//:binary 'deps("some//:lib (<arm32>)")'
         //   ```
         evaluator
-            .eval_query(query, query_args, target_universe.as_ref().map(|v| &v[..]))
+            .eval_query(&query, query_args, target_universe.as_ref().map(|v| &v[..]))
             .await
     }
 
@@ -79,9 +116,10 @@ impl QueryFrontend for QueryFrontendImpl {
         query_args: &[String],
         global_target_platform: Option<TargetLabel>,
     ) -> anyhow::Result<QueryEvaluationResult<ActionQueryNode>> {
+        let query = query_macros(ctx).await?.expand(query)?;
         let evaluator = get_aquery_evaluator(ctx, working_dir, global_target_platform).await?;
 
-        evaluator.eval_query(query, query_args).await
+        evaluator.eval_query(&query, query_args).await
     }
 
     async fn universe_from_literals(
@@ -91,11 +129,12 @@ impl QueryFrontend for QueryFrontendImpl {
         literals: &[String],
         global_target_platform: Option<TargetLabel>,
     ) -> anyhow::Result<CqueryUniverse> {
+        let literals = expand_universe(&query_macros(ctx).await?, literals)?;
         let query_delegate = get_dice_query_delegate(ctx, cwd, global_target_platform).await?;
         Ok(preresolve_literals_and_build_universe(
             &query_delegate,
             query_delegate.query_data(),
-            literals,
+            &literals,
         )
         .await?
         .0)
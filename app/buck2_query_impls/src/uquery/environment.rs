@@ -67,6 +67,13 @@ enum RBuildFilesError {
     CellMissingBuildFileNames(CellName),
 }
 
+#[derive(Debug, buck2_error::Error)]
+enum TargetsInBuildfileError {
+    #[error("`{0}` is not a build file")]
+    #[buck2(user)]
+    NotABuildFile(CellPath),
+}
+
 pub enum SpecialAttr {
     String(String),
 }
@@ -93,6 +100,8 @@ pub trait UqueryDelegate: Send + Sync {
 
     async fn eval_file_literal(&self, literal: &str) -> anyhow::Result<FileSet>;
 
+    async fn eval_file_glob(&self, pattern: &str) -> anyhow::Result<FileSet>;
+
     // Get all enclosing packages needed to compute owner function.
     // This always includes the immediate enclosing package of the path but can also include
     // all parent packages if the package matches `project.package_boundary_exceptions` buckconfig.
@@ -226,6 +235,10 @@ impl<'c> QueryEnvironment for UqueryEnvironment<'c> {
         self.delegate.eval_file_literal(literal).await
     }
 
+    async fn eval_file_glob(&self, pattern: &str) -> anyhow::Result<FileSet> {
+        self.delegate.eval_file_glob(pattern).await
+    }
+
     async fn dfs_postorder(
         &self,
         root: &TargetSet<TargetNode>,
@@ -251,6 +264,28 @@ impl<'c> QueryEnvironment for UqueryEnvironment<'c> {
         return rbuildfiles(universe, argset, self.delegate).await;
     }
 
+    async fn targets_in_buildfile(
+        &self,
+        buildfiles: &FileSet,
+    ) -> anyhow::Result<TargetSet<Self::Target>> {
+        let results = futures::future::try_join_all(buildfiles.iter().map(|path| async move {
+            let not_a_build_file = || TargetsInBuildfileError::NotABuildFile(path.clone());
+            let package = PackageLabel::from_cell_path(path.parent().ok_or_else(not_a_build_file)?);
+            let result = self.delegate.eval_build_file(package).await?;
+            if result.buildfile_path().path() != *path {
+                return Err(not_a_build_file().into());
+            }
+            anyhow::Ok(result)
+        }))
+        .await?;
+
+        let mut targets = TargetSet::new();
+        for result in results {
+            targets.extend(result.targets().values().map(|node| node.dupe()));
+        }
+        Ok(targets)
+    }
+
     async fn owner(&self, paths: &FileSet) -> anyhow::Result<TargetSet<Self::Target>> {
         let mut result: TargetSet<Self::Target> = TargetSet::new();
         for path in paths.iter() {
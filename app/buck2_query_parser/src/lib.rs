@@ -46,6 +46,7 @@
 //!
 //! ```
 
+pub mod macros;
 pub mod placeholder;
 pub mod span;
 pub mod spanned;
@@ -0,0 +1,395 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Named query macros, expanded textually before parsing.
+//!
+//! A macro is declared as `name(param1, param2) = body` (or `name = body` for a macro
+//! without parameters), and parameters are referenced in the body as `$param1`. For example,
+//! with `rdeps_ci(x) = rdeps(//ci/..., $x, 1)`, the query `rdeps_ci(//foo:bar)` expands to
+//! `rdeps(//ci/..., //foo:bar, 1)`. Macros may use other macros.
+//!
+//! Expansion is textual, but arguments and expanded bodies which use operators are
+//! parenthesized, so that operators keep their precedence: with `but(x, y) = $x - $y`, the
+//! query `but(a + b, c) ^ d` expands to `((a + b) - c) ^ d`. Lists of words, as used in
+//! `set()` or `--target-universe`, are substituted as is.
+
+use std::collections::HashMap;
+
+#[derive(Debug, buck2_error::Error)]
+#[buck2(user)]
+enum QueryMacroError {
+    #[error("Invalid query macro declaration `{0}`, expected `name` or `name(param, ...)`")]
+    InvalidDeclaration(String),
+    #[error("Query macro `{0}` expects {1} arguments, got {2}")]
+    WrongArgCount(String, usize, usize),
+    #[error("Query macro `{0}` expects arguments, used as `{0}` without any")]
+    MissingArgs(String),
+    #[error("Unclosed `(` in arguments of query macro `{0}`")]
+    UnclosedArgs(String),
+    #[error("Query macro expansion exceeded depth {0}, the macros are likely recursive")]
+    TooDeep(usize),
+}
+
+const MAX_EXPANSION_DEPTH: usize = 50;
+
+#[derive(Debug)]
+struct QueryMacro {
+    params: Vec<String>,
+    body: String,
+}
+
+#[derive(Debug, Default)]
+pub struct QueryMacros {
+    macros: HashMap<String, QueryMacro>,
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Characters of a `WORD` in the query grammar.
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "*/@._:$#%-".contains(c)
+}
+
+/// Whether `s` is a single word, quoted string or function call, and can be used as an operand
+/// without parentheses.
+fn is_atom(s: &str) -> bool {
+    let s = s.trim();
+    if s.starts_with('"') || s.starts_with('\'') {
+        return quoted_len(s) == s.len();
+    }
+    let name_len = s.find(|c| !is_word_char(c)).unwrap_or(s.len());
+    let rest = &s[name_len..];
+    if rest.is_empty() {
+        return name_len > 0;
+    }
+    if !rest.starts_with('(') {
+        return false;
+    }
+    // The parenthesis opened after the name must be closed at the end.
+    let mut depth = 0;
+    let mut i = 0;
+    while let Some(c) = rest[i..].chars().next() {
+        match c {
+            '"' | '\'' => {
+                i += quoted_len(&rest[i..]);
+                continue;
+            }
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1 == rest.len();
+                }
+            }
+            _ => {}
+        }
+        i += c.len_utf8();
+    }
+    false
+}
+
+/// Whether `s` is a whitespace-separated list of words, which is not an expression by itself.
+fn is_word_list(s: &str) -> bool {
+    s.split_whitespace().all(|word| {
+        word.chars().all(is_word_char) && !matches!(word, "-" | "except" | "intersect" | "union")
+    })
+}
+
+fn parenthesize(s: &str) -> String {
+    if is_atom(s) || is_word_list(s) {
+        s.to_owned()
+    } else {
+        format!("({})", s)
+    }
+}
+
+/// Length of the quoted string at the start of `s`, which starts with a quote.
+fn quoted_len(s: &str) -> usize {
+    let quote = s.chars().next().unwrap();
+    match s[1..].find(quote) {
+        Some(end) => end + 2,
+        None => s.len(),
+    }
+}
+
+impl QueryMacros {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.macros.is_empty()
+    }
+
+    /// Declare a macro, `declaration` being `name` or `name(param, ...)`.
+    pub fn add(&mut self, declaration: &str, body: &str) -> anyhow::Result<()> {
+        let invalid = || QueryMacroError::InvalidDeclaration(declaration.to_owned());
+        let (name, params) = match declaration.split_once('(') {
+            None => (declaration.trim(), Vec::new()),
+            Some((name, rest)) => {
+                let params = rest.trim_end().strip_suffix(')').ok_or_else(invalid)?;
+                let params = if params.trim().is_empty() {
+                    Vec::new()
+                } else {
+                    params.split(',').map(|p| p.trim().to_owned()).collect()
+                };
+                (name.trim(), params)
+            }
+        };
+        if !is_identifier(name) || !params.iter().all(|p| is_identifier(p)) {
+            return Err(invalid().into());
+        }
+        self.macros.insert(
+            name.to_owned(),
+            QueryMacro {
+                params,
+                body: body.to_owned(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Expand all macro uses in `query`.
+    pub fn expand(&self, query: &str) -> anyhow::Result<String> {
+        if self.macros.is_empty() {
+            return Ok(query.to_owned());
+        }
+        self.expand_at_depth(query, 0)
+    }
+
+    fn expand_at_depth(&self, query: &str, depth: usize) -> anyhow::Result<String> {
+        if depth > MAX_EXPANSION_DEPTH {
+            return Err(QueryMacroError::TooDeep(MAX_EXPANSION_DEPTH).into());
+        }
+
+        let mut out = String::with_capacity(query.len());
+        let mut i = 0;
+        while let Some(c) = query[i..].chars().next() {
+            if c == '"' || c == '\'' {
+                let len = quoted_len(&query[i..]);
+                out.push_str(&query[i..i + len]);
+                i += len;
+            } else if is_word_char(c) {
+                let len = query[i..]
+                    .find(|c| !is_word_char(c))
+                    .unwrap_or(query.len() - i);
+                let word = &query[i..i + len];
+                i += len;
+                let Some(m) = self.macros.get(word) else {
+                    out.push_str(word);
+                    continue;
+                };
+                let after_ws = query[i..].len() - query[i..].trim_start().len();
+                let args = if query[i + after_ws..].starts_with('(') {
+                    let (args, args_len) = parse_args(word, &query[i + after_ws + 1..])?;
+                    i += after_ws + 1 + args_len;
+                    args
+                } else if m.params.is_empty() {
+                    Vec::new()
+                } else {
+                    return Err(QueryMacroError::MissingArgs(word.to_owned()).into());
+                };
+                if args.len() != m.params.len() {
+                    return Err(QueryMacroError::WrongArgCount(
+                        word.to_owned(),
+                        m.params.len(),
+                        args.len(),
+                    )
+                    .into());
+                }
+                let body = substitute(&m.body, &m.params, &args);
+                out.push_str(&parenthesize(&self.expand_at_depth(&body, depth + 1)?));
+            } else {
+                out.push(c);
+                i += c.len_utf8();
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Parse comma-separated macro arguments up to the closing `)`.
+/// Returns the trimmed arguments and the length consumed, including the `)`.
+fn parse_args(name: &str, s: &str) -> anyhow::Result<(Vec<String>, usize)> {
+    let mut args = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    let mut i = 0;
+    while let Some(c) = s[i..].chars().next() {
+        match c {
+            '"' | '\'' => {
+                i += quoted_len(&s[i..]);
+                continue;
+            }
+            '(' => depth += 1,
+            ')' if depth == 0 => {
+                let last = s[start..i].trim();
+                if !last.is_empty() || !args.is_empty() {
+                    args.push(last.to_owned());
+                }
+                return Ok((args, i + 1));
+            }
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                args.push(s[start..i].trim().to_owned());
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += c.len_utf8();
+    }
+    Err(QueryMacroError::UnclosedArgs(name.to_owned()).into())
+}
+
+/// Replace `$param` references in `body` with the corresponding argument.
+fn substitute(body: &str, params: &[String], args: &[String]) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut rest = body;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        let len = after
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(after.len());
+        match params.iter().position(|p| p == &after[..len]) {
+            Some(index) => out.push_str(&parenthesize(&args[index])),
+            None => {
+                out.push('$');
+                out.push_str(&after[..len]);
+            }
+        }
+        rest = &after[len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::macros::is_atom;
+    use crate::macros::is_word_list;
+    use crate::macros::QueryMacros;
+    use crate::parse_expr;
+    use crate::BinaryOp;
+    use crate::Expr;
+
+    fn macros() -> QueryMacros {
+        let mut macros = QueryMacros::new();
+        macros.add("rdeps_ci(x)", "rdeps(//ci/..., $x, 1)").unwrap();
+        macros.add("ci_universe", "//ci/...").unwrap();
+        macros.add("pair(a, b)", "set($a $b)").unwrap();
+        macros
+            .add("nested(x)", "rdeps_ci(pair($x, //z:z))")
+            .unwrap();
+        macros.add("loop", "loop").unwrap();
+        macros
+    }
+
+    #[test]
+    fn test_expand() -> anyhow::Result<()> {
+        let macros = macros();
+        assert_eq!(
+            "rdeps(//ci/..., //foo:bar, 1)",
+            macros.expand("rdeps_ci(//foo:bar)")?
+        );
+        assert_eq!(
+            "deps(//ci/...) + rdeps(//ci/..., deps(//a:b, 1), 1)",
+            macros.expand("deps(ci_universe) + rdeps_ci(deps(//a:b, 1))")?
+        );
+        assert_eq!(
+            "rdeps(//ci/..., set(//y:y //z:z), 1)",
+            macros.expand("nested(//y:y)")?
+        );
+        // Not expanded: part of a larger word, or quoted.
+        assert_eq!(
+            "//ci_universe:x 'ci_universe'",
+            macros.expand("//ci_universe:x 'ci_universe'")?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_atom() {
+        assert!(is_atom("//foo:bar"));
+        assert!(is_atom(" 'a b' "));
+        assert!(is_atom("deps(//a:b + //c:d, 1)"));
+        assert!(is_atom("set(//a:b //c:d)"));
+        assert!(!is_atom("//a:b + //c:d"));
+        assert!(!is_atom("deps(//a:b) ^ //c:d"));
+        assert!(is_atom("(//a:b + //c:d)"));
+        assert!(!is_atom(""));
+    }
+
+    #[test]
+    fn test_is_word_list() {
+        assert!(is_word_list("//a/... //b/..."));
+        assert!(!is_word_list("//a/... except //b/..."));
+        assert!(!is_word_list("//a/... - //b/..."));
+        assert!(!is_word_list("//a/... + //b/..."));
+        assert!(!is_word_list("deps(//a:b) //c:d"));
+    }
+
+    #[test]
+    fn test_expand_precedence() -> anyhow::Result<()> {
+        let mut macros = QueryMacros::new();
+        macros.add("but(x, y)", "$x - $y")?;
+        macros.add("universe", "//a/... //b/...")?;
+
+        // Lists of patterns are not parenthesized.
+        assert_eq!("set(//a/... //b/...)", macros.expand("set(universe)")?);
+        // Keyword operators are operators too.
+        assert_eq!(
+            "((//a/... intersect //b/...) - //c:c)",
+            macros.expand("but(//a/... intersect //b/..., //c:c)")?
+        );
+
+        let expanded = macros.expand("but(//a/... + //b/..., //c:c) ^ //d/...")?;
+        assert_eq!("((//a/... + //b/...) - //c:c) ^ //d/...", expanded);
+
+        // The macro use is the left operand of `^`, and its first argument that of `-`.
+        let expr = parse_expr(&expanded)?;
+        let Expr::BinaryOpSequence(left, ops) = &expr.value else {
+            panic!("unexpected expression: {}", expr.value);
+        };
+        assert!(matches!(ops.as_slice(), [(BinaryOp::Intersect, _)]));
+        let Expr::BinaryOpSequence(left, ops) = &left.value else {
+            panic!("unexpected expression: {}", left.value);
+        };
+        assert!(matches!(ops.as_slice(), [(BinaryOp::Except, _)]));
+        assert!(matches!(
+            &left.value,
+            Expr::BinaryOpSequence(_, ops) if matches!(ops.as_slice(), [(BinaryOp::Union, _)])
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        let macros = macros();
+        assert!(macros.expand("rdeps_ci").is_err());
+        assert!(macros.expand("rdeps_ci(a, b)").is_err());
+        assert!(macros.expand("rdeps_ci(a").is_err());
+        assert!(macros.expand("loop").is_err());
+    }
+
+    #[test]
+    fn test_invalid_declaration() {
+        let mut macros = QueryMacros::new();
+        assert!(macros.add("bad name", "x").is_err());
+        assert!(macros.add("f(x", "x").is_err());
+        assert!(macros.add("f(1)", "x").is_err());
+    }
+}
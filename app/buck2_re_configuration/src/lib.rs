@@ -184,8 +184,8 @@ pub struct Buck2OssReConfiguration {
     pub action_cache_address: Option<String>,
     /// Whether to use TLS to interact with remote execution.
     pub tls: bool,
-    /// Path to a CA certificates bundle. This must be PEM-encoded. If none is set, a default
-    /// bundle will be used.
+    /// Path to a CA certificates bundle. This must be PEM-encoded. If none is set, `http.ca_bundle`
+    /// is used, and if that is not set either, a default bundle will be used.
     ///
     /// This can contain environment variables using shell interpolation syntax (i.e. $VAR). They
     /// will be substituted before using the value.
@@ -252,7 +252,10 @@ impl Buck2OssReConfiguration {
             tls: legacy_config
                 .parse(BUCK2_RE_CLIENT_CFG_SECTION, "tls")?
                 .unwrap_or(true),
-            tls_ca_certs: legacy_config.parse(BUCK2_RE_CLIENT_CFG_SECTION, "tls_ca_certs")?,
+            // Fall back to the CA bundle shared by all of buck2's network clients.
+            tls_ca_certs: legacy_config
+                .parse(BUCK2_RE_CLIENT_CFG_SECTION, "tls_ca_certs")?
+                .or(legacy_config.parse("http", "ca_bundle")?),
             tls_client_cert: legacy_config.parse(BUCK2_RE_CLIENT_CFG_SECTION, "tls_client_cert")?,
             http_headers: legacy_config
                 .parse_list(BUCK2_RE_CLIENT_CFG_SECTION, "http_headers")?
@@ -9,10 +9,14 @@
 
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::fmt::Write;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 use buck2_cli_proto::ClientContext;
 use buck2_event_observer::dice_state::DiceState;
+use buck2_event_observer::display::display_event;
+use buck2_event_observer::display::TargetDisplayOptions;
 use buck2_event_observer::pending_estimate::pending_estimate;
 use buck2_event_observer::span_tracker;
 use buck2_event_observer::span_tracker::RootData;
@@ -70,6 +74,10 @@ pub struct ActiveCommandHandle {
     /// because we want to allow shutdown events to jump the queue.
     daemon_shutdown_channel: Arc<Mutex<Option<oneshot::Sender<buck2_data::DaemonShutdown>>>>,
 
+    /// A channel to forcibly terminate this command, without waiting for it to reach a point
+    /// where it can be cancelled gracefully.
+    hard_cancel_channel: Arc<Mutex<Option<oneshot::Sender<()>>>>,
+
     /// State for this command. This is used to expose what this command is doing to other clients.
     state: Arc<ActiveCommandState>,
 }
@@ -83,6 +91,17 @@ impl ActiveCommandHandle {
         }
     }
 
+    /// Forcibly terminate this command. Returns false if it was already being forcibly
+    /// terminated or has finished.
+    pub fn hard_cancel(&self) -> bool {
+        let channel = self.hard_cancel_channel.lock().take();
+
+        match channel {
+            Some(channel) => channel.send(()).is_ok(),
+            None => false,
+        }
+    }
+
     pub fn state(&self) -> &ActiveCommandState {
         self.state.as_ref()
     }
@@ -100,10 +119,13 @@ impl Drop for ActiveCommandDropGuard {
 
 /// A handle to the stats for this command. We use this to broadcast state about this command.
 pub struct ActiveCommandState {
-    #[allow(unused)]
     pub argv: Vec<String>,
 
     spans: Mutex<SpansSnapshot>,
+
+    /// Start events of the root spans that are currently open. They are only formatted when
+    /// dumped.
+    in_flight: Mutex<HashMap<SpanId, Arc<BuckEvent>>>,
 }
 
 impl ActiveCommandState {
@@ -111,10 +133,34 @@ impl ActiveCommandState {
         *self.spans.lock()
     }
 
+    /// Describe what this command is doing, longest running spans first. This is logged when
+    /// the command is forcibly cancelled, to find out what it was stuck on.
+    pub fn dump(&self, now: SystemTime) -> String {
+        let spans = self.spans();
+        let mut in_flight = self.in_flight.lock().values().cloned().collect::<Vec<_>>();
+        in_flight.sort_by_key(|event| event.timestamp());
+
+        let mut dump = format!(
+            "argv: {}\nspans: {} open, {} closed, {} pending\n",
+            self.argv.join(" "),
+            spans.open,
+            spans.closed,
+            spans.pending
+        );
+        for event in in_flight {
+            let description = display_event(&event, TargetDisplayOptions::for_log())
+                .unwrap_or_else(|e| format!("<{:#}>", e));
+            let elapsed = now.duration_since(event.timestamp()).unwrap_or_default();
+            let _ignored = writeln!(dump, "  [{:.1}s] {}", elapsed.as_secs_f64(), description);
+        }
+        dump
+    }
+
     fn new(argv: Vec<String>) -> Self {
         Self {
             argv,
             spans: Mutex::new(SpansSnapshot::default()),
+            in_flight: Mutex::new(HashMap::new()),
         }
     }
 }
@@ -169,6 +215,10 @@ impl ActiveCommandStateWriter {
 
                 if is_root {
                     self.roots.insert(span_id, false, RootData::new(buck_event));
+                    self.shared
+                        .in_flight
+                        .lock()
+                        .insert(span_id, Arc::new(buck_event.clone()));
                     changed = true;
                 } else {
                     self.non_roots.insert(span_id);
@@ -182,6 +232,7 @@ impl ActiveCommandStateWriter {
 
                 // If it's a root, then we increment closed.
                 if self.roots.remove(span_id).is_some() {
+                    self.shared.in_flight.lock().remove(&span_id);
                     self.closed += 1;
                     changed = true;
                 } else {
@@ -219,11 +270,13 @@ pub struct ActiveCommand {
     pub guard: ActiveCommandDropGuard,
     pub state: ActiveCommandStateWriter,
     pub daemon_shutdown_channel: oneshot::Receiver<buck2_data::DaemonShutdown>,
+    pub hard_cancel_channel: oneshot::Receiver<()>,
 }
 
 impl ActiveCommand {
     pub fn new(event_dispatcher: &EventDispatcher, client_ctx: &ClientContext) -> Self {
         let (sender, receiver) = oneshot::channel();
+        let (hard_cancel_sender, hard_cancel_receiver) = oneshot::channel();
 
         let state = Arc::new(ActiveCommandState::new(client_ctx.sanitized_argv.clone()));
 
@@ -243,6 +296,7 @@ impl ActiveCommand {
                 ActiveCommandHandle {
                     dispatcher: event_dispatcher.dupe(),
                     daemon_shutdown_channel: Arc::new(Mutex::new(Some(sender))),
+                    hard_cancel_channel: Arc::new(Mutex::new(Some(hard_cancel_sender))),
                     state: state.dupe(),
                 },
             );
@@ -268,6 +322,7 @@ impl ActiveCommand {
         Self {
             guard: ActiveCommandDropGuard { trace_id },
             daemon_shutdown_channel: receiver,
+            hard_cancel_channel: hard_cancel_receiver,
             state: ActiveCommandStateWriter::new(state),
         }
     }
@@ -407,4 +462,75 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_active_command_state_dump() {
+        let mut writer = ActiveCommandStateWriter::new(Arc::new(ActiveCommandState::new(vec![
+            "buck2".to_owned(),
+            "build".to_owned(),
+        ])));
+
+        let root = SpanId::next();
+        let trace = TraceId::new();
+        let start = SystemTime::UNIX_EPOCH;
+
+        writer.peek_event(&BuckEvent::new(
+            start,
+            trace.clone(),
+            Some(root),
+            None,
+            buck2_data::SpanStartEvent {
+                data: Some(
+                    buck2_data::LoadBuildFileStart {
+                        module_id: "root//foo:BUCK".to_owned(),
+                        cell: "root".to_owned(),
+                    }
+                    .into(),
+                ),
+            }
+            .into(),
+        ));
+
+        assert_eq!(
+            writer
+                .shared
+                .dump(start + std::time::Duration::from_secs(3)),
+            "argv: buck2 build\n\
+             spans: 1 open, 0 closed, 0 pending\n  \
+             [3.0s] root//foo:BUCK -- evaluating build file\n"
+        );
+
+        writer.peek_event(&BuckEvent::new(
+            start,
+            trace,
+            Some(root),
+            None,
+            buck2_data::SpanEndEvent {
+                data: Some(buck2_data::LoadBuildFileEnd::default().into()),
+                ..Default::default()
+            }
+            .into(),
+        ));
+
+        assert_eq!(
+            writer.shared.dump(start),
+            "argv: buck2 build\nspans: 0 open, 1 closed, 0 pending\n"
+        );
+    }
+
+    #[test]
+    fn test_hard_cancel() {
+        let dispatcher = EventDispatcher::null_sink_with_trace(TraceId::new());
+        let mut command = ActiveCommand::new(&dispatcher, &ClientContext::default());
+
+        let handle = active_commands().get(dispatcher.trace_id()).unwrap().dupe();
+        assert!(command.hard_cancel_channel.try_recv().is_err());
+        assert!(handle.hard_cancel());
+        assert_eq!(command.hard_cancel_channel.try_recv(), Ok(()));
+        // Only the first request terminates the command.
+        assert!(!handle.hard_cancel());
+
+        drop(command);
+        assert!(active_commands().get(dispatcher.trace_id()).is_none());
+    }
 }
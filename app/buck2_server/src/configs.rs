@@ -20,6 +20,8 @@ use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
 
+use crate::system_toolchains::system_toolchain_config_args;
+
 fn config_type_from_i32(value: i32) -> anyhow::Result<ConfigType> {
     ConfigType::from_i32(value).with_context(|| {
         format!(
@@ -55,5 +57,16 @@ pub fn parse_legacy_cells(
     // store the base configs + overlaid ones separately, so we can cheaply
     // recompose.
     let res = BuckConfigBasedCells::parse_with_config_args(fs, config_overrides, cwd)?;
+
+    // System toolchains are exposed as config values, which requires parsing again.
+    let root_config = res.configs_by_name.get(res.cell_resolver.root_cell())?;
+    let probes = system_toolchain_config_args(root_config)?;
+    let res = if probes.is_empty() {
+        res
+    } else {
+        let mut config_args = config_overrides.to_vec();
+        config_args.extend(probes);
+        BuckConfigBasedCells::parse_with_config_args(fs, &config_args, cwd)?
+    };
     Ok((res.cell_resolver, res.configs_by_name, res.config_paths))
 }
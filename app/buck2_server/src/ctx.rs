@@ -17,12 +17,15 @@ use std::sync::Arc;
 use allocative::Allocative;
 use anyhow::Context;
 use async_trait::async_trait;
+use buck2_build_api::actions::calculation::ActionCycleDescriptor;
 use buck2_build_api::actions::execute::dice_data::set_fallback_executor_config;
 use buck2_build_api::actions::execute::dice_data::SetCommandExecutor;
 use buck2_build_api::actions::execute::dice_data::SetReClient;
 use buck2_build_api::actions::impls::run_action_knobs::HasRunActionKnobs;
 use buck2_build_api::actions::impls::run_action_knobs::RunActionKnobs;
+use buck2_build_api::build::GraphBudgetsLock;
 use buck2_build_api::build::HasCreateUnhashedSymlinkLock;
+use buck2_build_api::build::HasGraphBudgetsLock;
 use buck2_build_api::build_signals::create_build_signals;
 use buck2_build_api::build_signals::BuildSignalsInstaller;
 use buck2_build_api::build_signals::SetBuildSignals;
@@ -40,6 +43,8 @@ use buck2_common::dice::cells::HasCellResolver;
 use buck2_common::dice::cycles::CycleDetectorAdapter;
 use buck2_common::dice::cycles::PairDiceCycleDetector;
 use buck2_common::dice::data::HasIoProvider;
+use buck2_common::dice::file_ops::HasFollowedExternalSymlinks;
+use buck2_common::external_symlink_policy::ExternalSymlinkPolicy;
 use buck2_common::http::SetHttpClient;
 use buck2_common::invocation_paths::InvocationPaths;
 use buck2_common::io::trace::TracingIoProvider;
@@ -68,15 +73,20 @@ use buck2_events::dispatch::EventDispatcher;
 use buck2_events::metadata;
 use buck2_execute::execute::blocking::BlockingExecutor;
 use buck2_execute::execute::blocking::SetBlockingExecutor;
+use buck2_execute::execute::merged_directory_cache::MergedDirectoryCache;
+use buck2_execute::execute::merged_directory_cache::SetMergedDirectoryCache;
 use buck2_execute::knobs::ExecutorGlobalKnobs;
 use buck2_execute::materialize::materializer::Materializer;
 use buck2_execute::materialize::materializer::SetMaterializer;
 use buck2_execute::re::client::RemoteExecutionClient;
 use buck2_execute::re::manager::ReConnectionHandle;
 use buck2_execute::re::manager::ReConnectionObserver;
+use buck2_execute_impl::adaptive_parallelism::spawn_adaptive_parallelism;
+use buck2_execute_impl::adaptive_parallelism::AdaptiveParallelismConfig;
 use buck2_execute_impl::executors::worker::WorkerPool;
 use buck2_execute_impl::low_pass_filter::LowPassFilter;
 use buck2_execute_impl::re::paranoid_download::ParanoidDownloader;
+use buck2_execute_impl::resource_classifier::ResourceClassifier;
 use buck2_file_watcher::file_watcher::FileWatcher;
 use buck2_file_watcher::mergebase::SetMergebase;
 use buck2_forkserver::client::ForkserverClient;
@@ -111,6 +121,8 @@ use dupe::Dupe;
 use gazebo::prelude::SliceExt;
 use host_sharing::HostSharingBroker;
 use host_sharing::HostSharingStrategy;
+use sha2::Digest;
+use sha2::Sha256;
 use tokio::sync::Mutex;
 use tracing::warn;
 
@@ -125,6 +137,7 @@ use crate::dice_tracker::BuckDiceTracker;
 use crate::heartbeat_guard::HeartbeatGuard;
 use crate::host_info;
 use crate::snapshot::SnapshotCollector;
+use crate::system_toolchains::system_toolchain_versions;
 
 #[derive(Debug, buck2_error::Error)]
 enum DaemonCommunicationError {
@@ -204,6 +217,10 @@ pub struct ServerCommandContext<'a> {
     /// Daemon uuid passed in from the client side to detect nested invocation.
     pub(crate) daemon_uuid_from_client: Option<String>,
 
+    /// Action key of the action that invoked this command, if any, used to report nested
+    /// invocations.
+    parent_action_from_client: Option<String>,
+
     /// Command named passed from the CLI
     pub(crate) command_name: String,
 
@@ -329,6 +346,7 @@ impl<'a> ServerCommandContext<'a> {
             unstable_typecheck: client_context.unstable_typecheck,
             heartbeat_guard_handle: Some(heartbeat_guard_handle),
             daemon_uuid_from_client: client_context.daemon_uuid.clone(),
+            parent_action_from_client: client_context.parent_action.clone(),
             command_name: client_context.command_name.clone(),
             sanitized_argv: client_context.sanitized_argv.clone(),
             debugger_handle,
@@ -395,6 +413,7 @@ impl<'a> ServerCommandContext<'a> {
 
         let create_unhashed_symlink_lock =
             self.base_context.daemon.create_unhashed_outputs_lock.dupe();
+        let graph_budgets_lock = self.base_context.daemon.graph_budgets_lock.dupe();
 
         DiceCommandDataProvider {
             cell_configs_loader: self.cell_configs_loader.dupe(),
@@ -412,18 +431,25 @@ impl<'a> ServerCommandContext<'a> {
             skip_cache_read,
             skip_cache_write,
             create_unhashed_symlink_lock,
+            graph_budgets_lock,
             starlark_debugger: self.debugger_handle.dupe(),
             keep_going: self
                 .build_options
                 .as_ref()
                 .map_or(false, |opts| opts.keep_going),
             http_client: self.base_context.daemon.http_client.dupe(),
+            merged_directory_cache: self.base_context.daemon.merged_directory_cache.dupe(),
+            resource_classifier: self.base_context.daemon.resource_classifier.dupe(),
             paranoid: self.base_context.daemon.paranoid.dupe(),
             spawner: self.base_context.spawner.dupe(),
             materialize_failed_inputs: self
                 .build_options
                 .as_ref()
                 .map_or(false, |opts| opts.materialize_failed_inputs),
+            low_priority: self
+                .build_options
+                .as_ref()
+                .map_or(false, |opts| opts.low_priority),
         }
     }
 
@@ -525,12 +551,16 @@ struct DiceCommandDataProvider {
     skip_cache_read: bool,
     skip_cache_write: bool,
     create_unhashed_symlink_lock: Arc<Mutex<()>>,
+    graph_budgets_lock: GraphBudgetsLock,
     starlark_debugger: Option<BuckStarlarkDebuggerHandle>,
     keep_going: bool,
     http_client: HttpClient,
+    merged_directory_cache: Arc<MergedDirectoryCache>,
+    resource_classifier: Arc<ResourceClassifier>,
     paranoid: Option<ParanoidDownloader>,
     spawner: Arc<BuckSpawner>,
     materialize_failed_inputs: bool,
+    low_priority: bool,
 }
 
 #[async_trait]
@@ -550,6 +580,9 @@ impl DiceDataProvider for DiceCommandDataProvider {
 
         let concurrency = match self.concurrency.as_ref() {
             Some(v) => v.dupe()?,
+            // Low priority builds leave half of the machine for interactive use, unless told
+            // otherwise.
+            None if self.low_priority => (parse_concurrency(config_threads)? / 2).max(1),
             None => parse_concurrency(config_threads)?,
         };
 
@@ -576,13 +609,58 @@ impl DiceDataProvider for DiceCommandDataProvider {
             .parse::<u32>("build", "persistent_worker_shutdown_timeout_s")?
             .or(Some(10));
 
+        let action_output_max_bytes = root_config
+            .parse::<usize>("build", "action_output_max_bytes")?
+            .or(Some(100 * 1024 * 1024));
+
+        let action_cache_verify_probability = root_config
+            .parse::<f64>("buck2", "action_cache_verify_probability")?
+            .unwrap_or(0.0);
+        if !(0.0..=1.0).contains(&action_cache_verify_probability) {
+            return Err(anyhow::anyhow!(
+                "Invalid `buck2.action_cache_verify_probability` value `{}`: expected a probability between 0 and 1",
+                action_cache_verify_probability
+            ));
+        }
+
         let executor_global_knobs = ExecutorGlobalKnobs {
             enable_miniperf,
             log_action_keys,
+            action_output_max_bytes,
+            low_priority: self.low_priority,
+            action_cache_verify_probability,
         };
 
-        let host_sharing_broker =
-            HostSharingBroker::new(HostSharingStrategy::SmallerTasksFirst, concurrency);
+        // IO-bound actions share the machine permits with the rest, unless given their own budget.
+        let io_threads = root_config
+            .parse::<usize>("build", "io_threads")?
+            .unwrap_or(0);
+        let host_sharing_broker = Arc::new(
+            HostSharingBroker::new(HostSharingStrategy::SmallerTasksFirst, concurrency)
+                .with_io_permits(io_threads),
+        );
+        if root_config
+            .parse::<bool>("build", "adaptive_parallelism")?
+            .unwrap_or(false)
+        {
+            let mut config = AdaptiveParallelismConfig::default();
+            if let Some(max_load_per_cpu) =
+                root_config.parse::<f64>("build", "adaptive_parallelism_max_load_per_cpu")?
+            {
+                config.max_load_per_cpu = max_load_per_cpu;
+            }
+            if let Some(percent) = root_config
+                .parse::<u8>("build", "adaptive_parallelism_min_available_memory_percent")?
+            {
+                config.min_available_memory = f64::from(percent) / 100.0;
+            }
+            spawn_adaptive_parallelism(&host_sharing_broker, config, self.events.dupe());
+        }
+        let resource_classifier = self.resource_classifier.with_io_categories(
+            root_config
+                .parse_list::<String>("build", "io_action_categories")?
+                .unwrap_or_default(),
+        );
 
         // We use the job count for the low pass filter too. The low pass filter prevents sending
         // RE-eligile tasks to local if their concurrency is higher than our threshold. While it
@@ -608,6 +686,9 @@ impl DiceDataProvider for DiceCommandDataProvider {
         run_action_knobs.use_network_action_output_cache |= root_config
             .parse::<bool>("buck2", "use_network_action_output_cache")?
             .unwrap_or(false);
+        run_action_knobs.log_action_details = root_config
+            .parse::<bool>("buck2", "log_action_details")?
+            .unwrap_or(false);
 
         let mut data = UserComputationData {
             data,
@@ -628,6 +709,7 @@ impl DiceDataProvider for DiceCommandDataProvider {
         data.set_command_executor(Box::new(CommandExecutorFactory::new(
             self.re_connection.dupe(),
             host_sharing_broker,
+            resource_classifier,
             low_pass_filter,
             self.materializer.dupe(),
             self.blocking_executor.dupe(),
@@ -644,23 +726,33 @@ impl DiceDataProvider for DiceCommandDataProvider {
             worker_pool,
             self.paranoid.dupe(),
             self.materialize_failed_inputs,
+            self.merged_directory_cache.dupe(),
         )));
         data.set_blocking_executor(self.blocking_executor.dupe());
         data.set_http_client(self.http_client.dupe());
+        data.set_merged_directory_cache(self.merged_directory_cache.dupe());
         data.set_materializer(self.materializer.dupe());
         data.set_build_signals(self.build_signals.build_signals.dupe());
         data.set_run_action_knobs(run_action_knobs);
         data.set_create_unhashed_symlink_lock(self.create_unhashed_symlink_lock.dupe());
+        data.set_graph_budgets_lock(self.graph_budgets_lock.dupe());
         data.set_starlark_debugger_handle(self.starlark_debugger.clone().map(|v| Box::new(v) as _));
         data.set_keep_going(self.keep_going);
         data.set_critical_path_backend(critical_path_backend);
         data.spawner = self.spawner.dupe();
 
-        let tags = vec![
+        let mut tags = vec![
             format!("lazy-cycle-detector:{}", has_cycle_detector),
             format!("miniperf:{}", enable_miniperf),
             format!("log-configured-graph-size:{}", log_configured_graph_size),
         ];
+        for (cell_name, _) in cell_resolver.cells() {
+            let policy = legacy_configs
+                .get(cell_name)?
+                .parse::<ExternalSymlinkPolicy>("project", "external_symlinks")?
+                .unwrap_or_default();
+            tags.push(format!("external-symlinks:{}:{}", cell_name, policy));
+        }
         self.events.instant_event(buck2_data::TagEvent { tags });
 
         self.events.instant_event(buck2_data::CommandOptions {
@@ -674,10 +766,34 @@ impl DiceDataProvider for DiceCommandDataProvider {
 fn create_cycle_detector() -> Arc<dyn UserCycleDetector> {
     Arc::new(PairDiceCycleDetector(
         CycleDetectorAdapter::<LoadCycleDescriptor>::new(),
-        CycleDetectorAdapter::<ConfiguredGraphCycleDescriptor>::new(),
+        PairDiceCycleDetector(
+            CycleDetectorAdapter::<ConfiguredGraphCycleDescriptor>::new(),
+            CycleDetectorAdapter::<ActionCycleDescriptor>::new(),
+        ),
     ))
 }
 
+/// Hash of the configs of all cells, recorded in the invocation history to tell which
+/// invocations ran with the same configuration.
+fn buckconfig_hash(configs: &LegacyBuckConfigs) -> String {
+    let mut cells: Vec<_> = configs.iter().collect();
+    cells.sort_by_key(|(cell, _)| cell.as_str());
+    let mut hasher = Sha256::new();
+    for (cell, config) in cells {
+        hasher.update(cell.as_str());
+        hasher.update([0]);
+        for (section, values) in config.iter() {
+            for (key, value) in values {
+                for s in [section, key, value] {
+                    hasher.update(s);
+                    hasher.update([0]);
+                }
+            }
+        }
+    }
+    hex::encode(&hasher.finalize()[..8])
+}
+
 struct DiceCommandUpdater {
     file_watcher: Arc<dyn FileWatcher>,
     cell_config_loader: Arc<CellConfigLoader>,
@@ -723,6 +839,16 @@ impl DiceUpdater for DiceCommandUpdater {
         let (mut ctx, mergebase) = self.file_watcher.sync(ctx).await?;
         user_data.set_mergebase(mergebase);
 
+        // The file watcher does not see changes outside the project root.
+        let followed_external_symlinks = ctx
+            .existing_state()
+            .await
+            .global_data()
+            .get_followed_external_symlinks();
+        if let Some(followed_external_symlinks) = followed_external_symlinks {
+            followed_external_symlinks.write_to_dice(&mut ctx)?;
+        }
+
         ctx.set_buck_out_path(Some(self.buck_out_dir.clone()))?;
 
         setup_interpreter(
@@ -787,6 +913,11 @@ impl<'a> ServerCommandContextTrait for ServerCommandContext<'a> {
             data: Box::new(self.dice_data_constructor(build_signals_installer).await),
             setup: Box::new(self.dice_updater().await?),
             is_nested_invocation,
+            parent_action: if is_nested_invocation {
+                self.parent_action_from_client.clone()
+            } else {
+                None
+            },
             sanitized_argv: self.sanitized_argv.clone(),
             exit_when_different_state: self.exit_when_different_state,
             build_signals: deferred_build_signals,
@@ -845,6 +976,18 @@ impl<'a> ServerCommandContextTrait for ServerCommandContext<'a> {
         Ok(metadata)
     }
 
+    async fn environment_provenance(
+        &self,
+        ctx: &DiceComputations,
+    ) -> anyhow::Result<buck2_data::EnvironmentProvenance> {
+        let (cells, configs, _paths) = self.cell_configs_loader.cells_and_configs(ctx).await?;
+        let tool_versions = match configs.get(cells.root_cell()) {
+            Ok(root_config) => system_toolchain_versions(root_config),
+            Err(_) => HashMap::new(),
+        };
+        Ok(metadata::environment_provenance(tool_versions))
+    }
+
     /// Gathers metadata from buckconfig to attach to events for when a command enters the critical
     /// section
     async fn config_metadata(
@@ -892,6 +1035,8 @@ impl<'a> ServerCommandContextTrait for ServerCommandContext<'a> {
             tracing_provider.add_config_paths(&self.base_context.project_root, paths);
         }
 
+        metadata.insert("buckconfig_hash".to_owned(), buckconfig_hash(&configs));
+
         let root_cell_config = configs.get(cells.root_cell());
         if let Ok(config) = root_cell_config {
             add_config(&mut metadata, config, "log", "repository", "repository");
@@ -33,6 +33,7 @@ use buck2_core::fs::project::ProjectRoot;
 use buck2_execute::execute::blocking::BlockingExecutor;
 use buck2_execute::execute::cache_uploader::force_cache_upload;
 use buck2_execute::execute::cache_uploader::NoOpCacheUploader;
+use buck2_execute::execute::merged_directory_cache::MergedDirectoryCache;
 use buck2_execute::execute::prepared::NoOpCommandOptionalExecutor;
 use buck2_execute::execute::prepared::PreparedCommandExecutor;
 use buck2_execute::execute::prepared::PreparedCommandOptionalExecutor;
@@ -52,6 +53,7 @@ use buck2_execute_impl::executors::to_re_platform::RePlatformFieldsToRePlatform;
 use buck2_execute_impl::executors::worker::WorkerPool;
 use buck2_execute_impl::low_pass_filter::LowPassFilter;
 use buck2_execute_impl::re::paranoid_download::ParanoidDownloader;
+use buck2_execute_impl::resource_classifier::ResourceClassifier;
 use buck2_forkserver::client::ForkserverClient;
 use dupe::Dupe;
 use host_sharing::HostSharingBroker;
@@ -75,6 +77,7 @@ pub struct CommandExecutorFactory {
     // sharing the same DICE context should be allowed to proceed concurrently, and we only have
     // one CommandExecutorFactory per DICE context).
     host_sharing_broker: Arc<HostSharingBroker>,
+    resource_classifier: Arc<ResourceClassifier>,
     low_pass_filter: Arc<LowPassFilter>,
     materializer: Arc<dyn Materializer>,
     blocking_executor: Arc<dyn BlockingExecutor>,
@@ -88,6 +91,7 @@ pub struct CommandExecutorFactory {
     worker_pool: Arc<WorkerPool>,
     paranoid: Option<ParanoidDownloader>,
     materialize_failed_inputs: bool,
+    merged_directory_cache: Arc<MergedDirectoryCache>,
     /// Cache permission checks per command.
     cache_upload_permission_checker: Arc<ActionCacheUploadPermissionChecker>,
 }
@@ -95,7 +99,8 @@ pub struct CommandExecutorFactory {
 impl CommandExecutorFactory {
     pub fn new(
         re_connection: Arc<ReConnectionHandle>,
-        host_sharing_broker: HostSharingBroker,
+        host_sharing_broker: Arc<HostSharingBroker>,
+        resource_classifier: ResourceClassifier,
         low_pass_filter: LowPassFilter,
         materializer: Arc<dyn Materializer>,
         blocking_executor: Arc<dyn BlockingExecutor>,
@@ -109,13 +114,15 @@ impl CommandExecutorFactory {
         worker_pool: Arc<WorkerPool>,
         paranoid: Option<ParanoidDownloader>,
         materialize_failed_inputs: bool,
+        merged_directory_cache: Arc<MergedDirectoryCache>,
     ) -> Self {
         let cache_upload_permission_checker = Arc::new(ActionCacheUploadPermissionChecker::new(
             re_connection.get_client(),
         ));
         Self {
             re_connection,
-            host_sharing_broker: Arc::new(host_sharing_broker),
+            host_sharing_broker,
+            resource_classifier: Arc::new(resource_classifier),
             low_pass_filter: Arc::new(low_pass_filter),
             materializer,
             blocking_executor,
@@ -129,6 +136,7 @@ impl CommandExecutorFactory {
             worker_pool,
             paranoid,
             materialize_failed_inputs,
+            merged_directory_cache,
             cache_upload_permission_checker,
         }
     }
@@ -154,10 +162,12 @@ impl HasCommandExecutor for CommandExecutorFactory {
                 self.materializer.dupe(),
                 self.blocking_executor.dupe(),
                 self.host_sharing_broker.dupe(),
+                self.resource_classifier.dupe(),
                 self.project_root.root().to_owned(),
                 self.forkserver.dupe(),
                 self.executor_global_knobs.dupe(),
                 worker_pool,
+                self.merged_directory_cache.dupe(),
             )
         };
 
@@ -7,12 +7,13 @@
  * of this source tree.
  */
 
+use std::net::SocketAddr;
+
 pub fn create_listener() -> anyhow::Result<(
     buck2_common::buckd_connection::ConnectionType,
     std::net::TcpListener,
 )> {
     use std::net::Ipv4Addr;
-    use std::net::SocketAddr;
 
     use buck2_common::buckd_connection::ConnectionType;
 
@@ -28,16 +29,114 @@ pub fn create_listener() -> anyhow::Result<(
     ))
 }
 
+/// Whether an accepted connection, from `peer` to the daemon listening on `local`, comes from a
+/// process owned by the same user as the daemon.
+///
+/// The endpoint is a loopback TCP socket, which any local user can connect to, so the auth token
+/// is not the only check: where the kernel exposes socket owners, connections from other users
+/// are rejected before any request is read. Where the owner cannot be determined, the connection
+/// is accepted and the auth token check still applies.
+///
+/// TCP sockets have no peer credentials, so this reads the socket tables of the kernel, on a
+/// blocking thread since their size grows with the number of sockets on the host.
+pub(crate) async fn is_connection_from_daemon_user(peer: SocketAddr, local: SocketAddr) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        let daemon_uid = nix::unistd::geteuid().as_raw();
+        let owner =
+            tokio::task::spawn_blocking(move || find_connection_uid(peer.port(), local.port()))
+                .await;
+        match owner {
+            Ok(Some(uid)) => uid == daemon_uid,
+            Ok(None) | Err(_) => true,
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (peer, local);
+        true
+    }
+}
+
+/// The owner of the socket with local port `port` connected to remote port `remote_port`.
+#[cfg(target_os = "linux")]
+fn find_connection_uid(port: u16, remote_port: u16) -> Option<u32> {
+    ["/proc/net/tcp", "/proc/net/tcp6"]
+        .iter()
+        .find_map(|table| {
+            let table = std::fs::read_to_string(table).ok()?;
+            find_socket_uid(&table, port, remote_port)
+        })
+}
+
+/// Finds the owner of the socket with local port `port` connected to remote port `remote_port`
+/// in a `/proc/net/tcp` style table.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn find_socket_uid(table: &str, port: u16, remote_port: u16) -> Option<u32> {
+    fn parse_port(address: &str) -> Option<u16> {
+        let (_, port) = address.rsplit_once(':')?;
+        u16::from_str_radix(port, 16).ok()
+    }
+
+    // Columns: sl local_address rem_address st tx_queue:rx_queue tr:tm->when retrnsmt uid ...
+    table.lines().skip(1).find_map(|line| {
+        let columns: Vec<&str> = line.split_whitespace().collect();
+        if columns.len() < 8 {
+            return None;
+        }
+        if parse_port(columns[1])? != port || parse_port(columns[2])? != remote_port {
+            return None;
+        }
+        columns[7].parse().ok()
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use assert_matches::assert_matches;
     use buck2_common::buckd_connection::ConnectionType;
 
     use crate::daemon::daemon_tcp::create_listener;
+    use crate::daemon::daemon_tcp::find_socket_uid;
 
     #[test]
     fn test_create_listener() {
         let (connection_type, _tcp_listener) = create_listener().unwrap();
         assert_matches!(connection_type, ConnectionType::Tcp { .. });
     }
+
+    #[test]
+    fn test_find_socket_uid() {
+        let table = "\
+  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:A0C2 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 101 1
+   1: 0100007F:D431 0100007F:A0C2 01 00000000:00000000 00:00000000 00000000  1001        0 102 1
+   2: 0100007F:A0C2 0100007F:D431 01 00000000:00000000 00:00000000 00000000  1000        0 103 1
+";
+        // The client side of the connection, as seen from the daemon listening on 0xA0C2.
+        assert_eq!(Some(1001), find_socket_uid(table, 0xD431, 0xA0C2));
+        // The daemon side of the same connection.
+        assert_eq!(Some(1000), find_socket_uid(table, 0xA0C2, 0xD431));
+        assert_eq!(None, find_socket_uid(table, 0xD432, 0xA0C2));
+        assert_eq!(None, find_socket_uid("", 0xD431, 0xA0C2));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_is_connection_from_daemon_user() {
+        use crate::daemon::daemon_tcp::is_connection_from_daemon_user;
+
+        let (_, listener) = create_listener().unwrap();
+        let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let _client = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        assert!(
+            is_connection_from_daemon_user(
+                server.peer_addr().unwrap(),
+                server.local_addr().unwrap()
+            )
+            .await
+        );
+    }
 }
@@ -33,12 +33,14 @@ use buck2_common::events::HasEvents;
 use buck2_common::invocation_paths::InvocationPaths;
 use buck2_common::io::trace::TracingIoProvider;
 use buck2_common::io::IoProvider;
+use buck2_common::legacy_configs::init::is_endpoint_shared;
 use buck2_common::legacy_configs::init::DaemonStartupConfig;
 use buck2_common::legacy_configs::LegacyBuckConfig;
 use buck2_common::memory;
 use buck2_core::buck2_env;
 use buck2_core::error::reload_hard_error_config;
 use buck2_core::error::reset_soft_error_counters;
+use buck2_core::fs::case_insensitive::FilesystemCaseSensitivity;
 use buck2_core::fs::cwd::WorkingDirectory;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::paths::abs_path::AbsPathBuf;
@@ -93,6 +95,7 @@ use crate::active_commands::ActiveCommand;
 use crate::active_commands::ActiveCommandStateWriter;
 use crate::clean_stale::clean_stale_command;
 use crate::ctx::ServerCommandContext;
+use crate::daemon::daemon_tcp::is_connection_from_daemon_user;
 use crate::daemon::multi_event_stream::MultiEventStream;
 use crate::daemon::server_allocative::spawn_allocative;
 use crate::daemon::state::DaemonState;
@@ -107,8 +110,16 @@ use crate::trace_io::trace_io_command;
 // TODO(cjhopman): Figure out a reasonable value for this.
 static DEFAULT_KILL_TIMEOUT: Duration = Duration::from_millis(500);
 
+static DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(300);
+
 static DEFAULT_INACTIVITY_TIMEOUT: Duration = Duration::from_secs(4 * 86400);
 
+#[derive(Debug, buck2_error::Error)]
+enum HardCancelError {
+    #[error("Command was forcibly cancelled")]
+    Cancelled,
+}
+
 pub trait BuckdServerDelegate: Allocative + Send + Sync {
     fn force_shutdown_with_timeout(&self, reason: String, timeout: Duration);
 }
@@ -156,11 +167,13 @@ impl BuckdServerInitPreferences {
         &self,
         io: Arc<dyn IoProvider>,
         digest_config: DigestConfig,
+        case_sensitivity: FilesystemCaseSensitivity,
         root_config: &LegacyBuckConfig,
     ) -> anyhow::Result<Arc<Dice>> {
         configure_dice_for_buck(
             io,
             digest_config,
+            case_sensitivity,
             Some(root_config),
             self.detect_cycles,
             self.which_dice,
@@ -225,6 +238,8 @@ impl Interceptor for BuckCheckAuthTokenInterceptor {
 pub(crate) struct BuckdServerData {
     /// The flag that is set to true when server is shutting down.
     stop_accepting_requests: AtomicBool,
+    /// The flag that is set to true while `kill --drain` waits for in-flight commands.
+    draining: AtomicBool,
     #[allocative(skip)]
     process_info: DaemonProcessInfo,
     base_daemon_constraints: buck2_cli_proto::DaemonConstraints,
@@ -283,6 +298,10 @@ impl BuckdServer {
             None
         };
 
+        // Other users can read the auth token when the endpoint is shared with them.
+        let allow_other_users =
+            is_endpoint_shared(init_ctx.daemon_startup_config.endpoint_permissions()?);
+
         let daemon_state = Arc::new(
             DaemonState::new(fb, paths, init_ctx, rt.clone(), materializations, cwd).await,
         );
@@ -290,6 +309,7 @@ impl BuckdServer {
         let auth_token = process_info.auth_token.clone();
         let api_server = BuckdServer(Arc::new(BuckdServerData {
             stop_accepting_requests: AtomicBool::new(false),
+            draining: AtomicBool::new(false),
             process_info,
             base_daemon_constraints,
             start_time: prost_types::Timestamp {
@@ -308,6 +328,25 @@ impl BuckdServer {
             rt,
         }));
 
+        let listener = listener.filter(move |stream| {
+            let addrs = match stream {
+                Ok(stream) if !allow_other_users => {
+                    stream.peer_addr().ok().zip(stream.local_addr().ok())
+                }
+                _ => None,
+            };
+            async move {
+                let accept = match addrs {
+                    Some((peer, local)) => is_connection_from_daemon_user(peer, local).await,
+                    None => true,
+                };
+                if !accept {
+                    tracing::warn!("Rejected a connection from a process owned by another user");
+                }
+                accept
+            }
+        });
+
         let shutdown = server_shutdown_signal(command_receiver, shutdown_receiver)?;
         let server = Server::builder()
             .layer(interceptor(BuckCheckAuthTokenInterceptor { auth_token }))
@@ -412,8 +451,15 @@ impl BuckdServer {
         let ActiveCommand {
             guard,
             daemon_shutdown_channel,
+            hard_cancel_channel,
             state,
         } = ActiveCommand::new(&dispatch, client_ctx);
+
+        // A drain may have started between `pre_run` and registering this command, in which case
+        // the drain could already have observed no active commands. Check again now that we are
+        // visible to it.
+        self.check_if_accepting_requests()?;
+
         let data = daemon_state.data()?;
 
         // Fire off a snapshot before we start doing anything else. We use the metrics emitted here
@@ -427,6 +473,7 @@ impl BuckdServer {
             state,
             dispatch.dupe(),
             daemon_shutdown_channel,
+            hard_cancel_channel,
             move |req, cancellations| {
                 async move {
                     let result: anyhow::Result<Res> = try {
@@ -504,16 +551,59 @@ impl BuckdServer {
 
     /// Checks if the server is accepting requests.
     fn check_if_accepting_requests(&self) -> Result<(), Status> {
-        if self.0.stop_accepting_requests.load(Ordering::Relaxed) {
-            Err(Status::failed_precondition(
-                "Failed to run command, `buckd` is shutting down soon!",
-            ))
-        } else {
-            Ok(())
+        check_if_accepting_requests(&self.0.stop_accepting_requests, &self.0.draining)
+    }
+}
+
+fn check_if_accepting_requests(
+    stop_accepting_requests: &AtomicBool,
+    draining: &AtomicBool,
+) -> Result<(), Status> {
+    if draining.load(Ordering::SeqCst) {
+        Err(Status::failed_precondition(
+            "Failed to run command, `buckd` is draining in-flight commands and will shut down soon!",
+        ))
+    } else if stop_accepting_requests.load(Ordering::Relaxed) {
+        Err(Status::failed_precondition(
+            "Failed to run command, `buckd` is shutting down soon!",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Waits for in-flight commands to finish, up to `timeout`, then flushes materializer state so
+/// that nothing is lost when the daemon exits. The caller must set `draining` first so that new
+/// commands are rejected.
+async fn drain_commands(data: &BuckdServerData, timeout: Duration) {
+    let active =
+        wait_for_commands(|| crate::active_commands::active_commands().len(), timeout).await;
+    if active != 0 {
+        tracing::warn!("Timed out draining {} active commands", active);
+    }
+
+    if let Ok(state) = data.daemon_state.data() {
+        if let Some(deferred) = state.materializer.as_deferred_materializer_extension() {
+            if let Err(e) = deferred.flush_all_access_times().await {
+                tracing::warn!("Failed to flush materializer access times: {:#}", e);
+            }
         }
     }
 }
 
+/// Polls `active` until it reports no commands or `timeout` elapses. Returns the number of
+/// commands still active.
+async fn wait_for_commands(active: impl Fn() -> usize, timeout: Duration) -> usize {
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        let count = active();
+        if count == 0 || tokio::time::Instant::now() >= deadline {
+            return count;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
 fn convert_positive_duration(proto_duration: &prost_types::Duration) -> Result<Duration, Status> {
     if proto_duration.seconds < 0 || proto_duration.nanos < 0 {
         return Err(Status::new(
@@ -632,6 +722,7 @@ fn streaming<
     state: ActiveCommandStateWriter,
     dispatcher: EventDispatcher,
     daemon_shutdown_channel: oneshot::Receiver<buck2_data::DaemonShutdown>,
+    hard_cancel_channel: oneshot::Receiver<()>,
     func: F,
     rt: &Handle,
 ) -> Response<ResponseStream>
@@ -654,9 +745,28 @@ where
     let trace_id = dispatcher.trace_id().dupe();
 
     let req = req.into_inner();
+    let hard_cancel_dispatcher = dispatcher.dupe();
     let events_ctx = EventsCtx { dispatcher };
     let spawned = spawn_cancellable(
-        |cancellations| func(req, cancellations),
+        move |cancellations| {
+            let work = func(req, cancellations);
+            async move {
+                // Graceful cancellation waits for the command to reach a point where it can stop,
+                // which may never happen if it is stuck. A hard cancel drops the command right
+                // away instead, which also kills the local processes it spawned.
+                match futures::future::select(work, hard_cancel_channel).await {
+                    futures::future::Either::Left(((), _)) => {}
+                    futures::future::Either::Right((Ok(()), work)) => {
+                        drop(work);
+                        hard_cancel_dispatcher.command_result(error_to_command_result(
+                            HardCancelError::Cancelled.into(),
+                        ));
+                    }
+                    futures::future::Either::Right((Err(_), work)) => work.await,
+                }
+            }
+            .boxed()
+        },
         &BuckSpawner::new(rt.clone()),
         &events_ctx,
     );
@@ -753,7 +863,16 @@ impl DaemonApi for BuckdServer {
                 callers: req.callers,
             };
 
-            self.0.daemon_shutdown.start_shutdown(reason, timeout);
+            if req.drain {
+                self.0.draining.store(true, Ordering::SeqCst);
+                let data = self.0.dupe();
+                tokio::spawn(async move {
+                    drain_commands(&data, timeout.unwrap_or(DEFAULT_DRAIN_TIMEOUT)).await;
+                    data.daemon_shutdown.start_shutdown(reason, None);
+                });
+            } else {
+                self.0.daemon_shutdown.start_shutdown(reason, timeout);
+            }
             Ok(KillResponse {})
         })
         .await
@@ -806,6 +925,18 @@ impl DaemonApi for BuckdServer {
             let mut daemon_constraints = self.0.base_daemon_constraints.clone();
             daemon_constraints.extra = extra_constraints;
 
+            let mut command_queue = Vec::new();
+            if let Ok(data) = daemon_state.data() {
+                for command in data.dice_manager.command_queue().await {
+                    command_queue.push(CommandQueueEntry {
+                        trace_id: command.trace_id.to_string(),
+                        argv: command.argv,
+                        waiting: command.waiting,
+                        elapsed: Some(command.since.elapsed().try_into()?),
+                    });
+                }
+            }
+
             let uptime = self.0.start_instant.elapsed();
             let base = StatusResponse {
                 process_info: Some(self.0.process_info.clone()),
@@ -825,7 +956,7 @@ impl DaemonApi for BuckdServer {
                     .as_ref()
                     .ok()
                     .map(|state| state.http_client.supports_vpnless()),
-                ..Default::default()
+                command_queue,
             };
             Ok(base)
         })
@@ -1177,6 +1308,7 @@ impl DaemonApi for BuckdServer {
         let ActiveCommand {
             guard,
             daemon_shutdown_channel,
+            hard_cancel_channel,
             state,
         } = active_command;
 
@@ -1187,6 +1319,7 @@ impl DaemonApi for BuckdServer {
             state,
             dispatcher.dupe(),
             daemon_shutdown_channel,
+            hard_cancel_channel,
             move |req, _| {
                 async move {
                     let result = try {
@@ -1366,6 +1499,35 @@ impl DaemonApi for BuckdServer {
         Ok(Response::new(SetLogFilterResponse {}))
     }
 
+    async fn cancel_command(
+        &self,
+        req: Request<CancelCommandRequest>,
+    ) -> Result<Response<CancelCommandResponse>, Status> {
+        let trace_id = req
+            .into_inner()
+            .trace_id
+            .parse()
+            .map_err(|e| Status::invalid_argument(format!("{:#}", e)))?;
+
+        let command = crate::active_commands::active_commands()
+            .get(&trace_id)
+            .map(|command| command.dupe());
+        let found = match command {
+            Some(command) => {
+                tracing::warn!(
+                    "Forcibly cancelling command {}, which was doing:\n{}",
+                    trace_id,
+                    command.state().dump(SystemTime::now())
+                );
+                command.hard_cancel();
+                true
+            }
+            None => false,
+        };
+
+        Ok(Response::new(CancelCommandResponse { found }))
+    }
+
     type TraceIoStream = ResponseStream;
     async fn trace_io(
         &self,
@@ -1440,3 +1602,59 @@ struct DefaultCommandOptions;
 
 impl OneshotCommandOptions for DefaultCommandOptions {}
 impl<Req> StreamingCommandOptions<Req> for DefaultCommandOptions {}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tonic::Code;
+
+    use super::check_if_accepting_requests;
+    use super::wait_for_commands;
+
+    #[test]
+    fn test_check_if_accepting_requests() {
+        let stop = AtomicBool::new(false);
+        let draining = AtomicBool::new(false);
+        assert!(check_if_accepting_requests(&stop, &draining).is_ok());
+
+        draining.store(true, Ordering::SeqCst);
+        let status = check_if_accepting_requests(&stop, &draining).unwrap_err();
+        assert_eq!(Code::FailedPrecondition, status.code());
+        assert!(status.message().contains("draining"));
+
+        draining.store(false, Ordering::SeqCst);
+        stop.store(true, Ordering::Relaxed);
+        let status = check_if_accepting_requests(&stop, &draining).unwrap_err();
+        assert_eq!(Code::FailedPrecondition, status.code());
+        assert!(status.message().contains("shutting down"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_wait_for_commands_finishes() {
+        let active = Arc::new(AtomicUsize::new(2));
+        tokio::spawn({
+            let active = active.clone();
+            async move {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                active.store(0, Ordering::SeqCst);
+            }
+        });
+
+        let remaining =
+            wait_for_commands(|| active.load(Ordering::SeqCst), Duration::from_secs(10)).await;
+        assert_eq!(0, remaining);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_wait_for_commands_times_out() {
+        let start = tokio::time::Instant::now();
+        let remaining = wait_for_commands(|| 3, Duration::from_secs(5)).await;
+        assert_eq!(3, remaining);
+        assert!(start.elapsed() >= Duration::from_secs(5));
+    }
+}
@@ -16,10 +16,12 @@ use std::time::Instant;
 
 use allocative::Allocative;
 use anyhow::Context;
+use buck2_build_api::build::GraphBudgetsLock;
 use buck2_build_api::spawner::BuckSpawner;
 use buck2_cli_proto::unstable_dice_dump_request::DiceDumpFormat;
 use buck2_common::cas_digest::DigestAlgorithm;
 use buck2_common::cas_digest::DigestAlgorithmKind;
+use buck2_common::compression::CompressionConfig;
 use buck2_common::ignores::ignore_set::IgnoreSet;
 use buck2_common::invocation_paths::InvocationPaths;
 use buck2_common::io::IoProvider;
@@ -29,6 +31,7 @@ use buck2_common::legacy_configs::init::Timeout;
 use buck2_core::buck2_env;
 use buck2_core::cells::name::CellName;
 use buck2_core::facebook_only;
+use buck2_core::fs::case_insensitive::FilesystemCaseSensitivity;
 use buck2_core::fs::cwd::WorkingDirectory;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
@@ -43,6 +46,7 @@ use buck2_events::EventSinkWithStats;
 use buck2_execute::digest_config::DigestConfig;
 use buck2_execute::execute::blocking::BlockingExecutor;
 use buck2_execute::execute::blocking::BuckBlockingExecutor;
+use buck2_execute::execute::merged_directory_cache::MergedDirectoryCache;
 use buck2_execute::materialize::materializer::MaterializationMethod;
 use buck2_execute::materialize::materializer::Materializer;
 use buck2_execute::re::manager::ReConnectionManager;
@@ -55,6 +59,8 @@ use buck2_execute_impl::materializers::sqlite::MaterializerState;
 use buck2_execute_impl::materializers::sqlite::MaterializerStateIdentity;
 use buck2_execute_impl::materializers::sqlite::MaterializerStateSqliteDb;
 use buck2_execute_impl::re::paranoid_download::ParanoidDownloader;
+use buck2_execute_impl::re::paranoid_download::ParanoidVerification;
+use buck2_execute_impl::resource_classifier::ResourceClassifier;
 use buck2_file_watcher::file_watcher::FileWatcher;
 use buck2_forkserver::client::ForkserverClient;
 use buck2_http::HttpClient;
@@ -156,6 +162,9 @@ pub struct DaemonStateData {
     #[allocative(skip)]
     pub create_unhashed_outputs_lock: Arc<Mutex<()>>,
 
+    #[allocative(skip)]
+    pub graph_budgets_lock: GraphBudgetsLock,
+
     /// A unique identifier for the materializer state.
     pub materializer_state_identity: Option<MaterializerStateIdentity>,
 
@@ -171,6 +180,22 @@ pub struct DaemonStateData {
 
     /// Spawner
     pub spawner: Arc<BuckSpawner>,
+
+    /// Compression of event logs, as configured when the daemon started. Event logs are written
+    /// by the client, this is only reported in snapshots.
+    pub event_log_compression: CompressionConfig,
+
+    /// Compression of the contents of deferred write actions.
+    pub write_action_compression: CompressionConfig,
+
+    /// Merged directories of transitive set projections, shared by actions across commands.
+    #[allocative(skip)]
+    pub merged_directory_cache: Arc<MergedDirectoryCache>,
+
+    /// Classifies local commands as CPU or IO-bound, learning from their executions across
+    /// commands.
+    #[allocative(skip)]
+    pub resource_classifier: Arc<ResourceClassifier>,
 }
 
 impl DaemonStateData {
@@ -308,6 +333,11 @@ impl DaemonState {
             let valid_cache_dirs = paths.valid_cache_dirs();
             let fs_duped = fs.dupe();
 
+            let write_action_compression = root_config
+                .parse("buck2", "write_action_compression")?
+                .unwrap_or(CompressionConfig::ZSTD);
+            let event_log_compression = init_ctx.daemon_startup_config.event_log_compression()?;
+
             let deferred_materializer_configs = {
                 let defer_write_actions = root_config
                     .parse::<RolloutPercentage>("buck2", "defer_write_actions")?
@@ -339,6 +369,7 @@ impl DaemonState {
                         MaterializationMethod::Deferred
                     ),
                     defer_write_actions,
+                    write_compression: write_action_compression,
                     ttl_refresh: TtlRefreshConfiguration {
                         frequency: std::time::Duration::from_secs(ttl_refresh_frequency),
                         min_ttl: chrono::Duration::seconds(ttl_refresh_min_ttl),
@@ -410,8 +441,14 @@ impl DaemonState {
             let forkserver =
                 maybe_launch_forkserver(root_config, &paths.forkserver_state_dir()).await?;
 
+            let case_sensitivity = FilesystemCaseSensitivity::detect(
+                paths.project_root().root().as_abs_path(),
+                paths.buck_out_path().as_abs_path(),
+            )
+            .context("Error detecting filesystem case sensitivity")?;
+
             let dice = init_ctx
-                .construct_dice(io.dupe(), digest_config, root_config)
+                .construct_dice(io.dupe(), digest_config, case_sensitivity, root_config)
                 .await?;
 
             // TODO(cjhopman): We want to use Expr::True here, but we need to workaround
@@ -441,6 +478,7 @@ impl DaemonState {
                 .unwrap_or(false);
 
             let create_unhashed_outputs_lock = Arc::new(Mutex::new(()));
+            let graph_budgets_lock = GraphBudgetsLock::default();
 
             let buffer_size = root_config
                 .parse("buck2", "event_log_buffer_size")?
@@ -463,6 +501,12 @@ impl DaemonState {
             )
             .context("failed to init scribe sink")?;
 
+            let merged_directory_cache = Arc::new(MergedDirectoryCache::new(
+                root_config
+                    .parse("buck2", "merged_directory_cache_size")?
+                    .unwrap_or(MergedDirectoryCache::DEFAULT_CAPACITY),
+            ));
+
             let enable_restarter = root_config
                 .parse::<RolloutPercentage>("buck2", "restarter")?
                 .unwrap_or_else(RolloutPercentage::never)
@@ -474,6 +518,11 @@ impl DaemonState {
                     blocking_executor.dupe(),
                     re_client_manager.dupe(),
                     paths.paranoid_cache_dir(),
+                    paths.paranoid_quarantine_dir(),
+                    digest_config,
+                    ParanoidVerification::from_config(
+                        &init_ctx.daemon_startup_config.paranoid_config,
+                    )?,
                 ))
             } else {
                 None
@@ -498,11 +547,16 @@ impl DaemonState {
                 disk_state_options,
                 start_time: std::time::Instant::now(),
                 create_unhashed_outputs_lock,
+                graph_budgets_lock,
                 materializer_state_identity,
                 enable_restarter,
                 http_client,
                 paranoid,
                 spawner: Arc::new(BuckSpawner::new(daemon_state_data_rt)),
+                event_log_compression,
+                write_action_compression,
+                merged_directory_cache,
+                resource_classifier: Arc::new(ResourceClassifier::new(Vec::new())),
             }))
         })
         .await?
@@ -810,10 +864,13 @@ fn http_client_from_startup_config(
     config: &DaemonStartupConfig,
 ) -> anyhow::Result<HttpClientBuilder> {
     let mut builder = if is_open_source() {
-        HttpClientBuilder::oss()?
+        HttpClientBuilder::oss(&config.http.proxy_config())?
     } else {
         HttpClientBuilder::internal(config.allow_vpnless)?
     };
+    if let Some(ca_bundle) = &config.http.ca_bundle {
+        builder.with_ca_bundle(ca_bundle)?;
+    }
     builder.with_max_redirects(config.http.max_redirects.unwrap_or(DEFAULT_MAX_REDIRECTS));
     match config.http.connect_timeout() {
         Timeout::Value(d) => {
@@ -0,0 +1,51 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use buck2_cli_proto::new_generic::FetchCellsRequest;
+use buck2_cli_proto::new_generic::FetchCellsResponse;
+use buck2_cli_proto::new_generic::FetchedCell;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::dice::data::HasIoProvider;
+use buck2_common::legacy_configs::dice::HasLegacyConfigs;
+use buck2_common::legacy_configs::external_cells::materialize_external_cells;
+use buck2_common::legacy_configs::external_cells::resolve_external_cells;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+
+use crate::ctx::ServerCommandContext;
+
+/// Fetch the external cells of the project which are not available locally yet.
+pub(crate) async fn fetch_cells_command(
+    context: &ServerCommandContext<'_>,
+    _req: FetchCellsRequest,
+) -> anyhow::Result<FetchCellsResponse> {
+    let context: &dyn ServerCommandContextTrait = context;
+    context
+        .with_dice_ctx(|_server_ctx, ctx| async move {
+            let cell_resolver = ctx.get_cell_resolver().await?;
+            let root_config = ctx
+                .get_legacy_config_for_cell(cell_resolver.root_cell())
+                .await?;
+            let io = ctx.global_data().get_io_provider();
+            let cells = resolve_external_cells(io.project_root(), &root_config, true)?;
+
+            materialize_external_cells(&ctx, &cells).await?;
+
+            Ok(FetchCellsResponse {
+                cells: cells
+                    .into_iter()
+                    .map(|cell| FetchedCell {
+                        alias: cell.alias.as_str().to_owned(),
+                        path: cell.source.cell_root().as_str().to_owned(),
+                    })
+                    .collect(),
+            })
+        })
+        .await
+}
@@ -20,6 +20,7 @@ mod configs;
 mod ctx;
 pub mod daemon;
 mod dice_tracker;
+mod fetch_cells;
 mod file_status;
 mod heartbeat_guard;
 mod host_info;
@@ -28,7 +29,9 @@ pub mod lsp;
 mod materialize;
 mod net_io;
 pub(crate) mod new_generic;
+mod pin_re_outputs;
 pub mod profile;
 mod snapshot;
 mod subscription;
+mod system_toolchains;
 mod trace_io;
@@ -13,7 +13,9 @@ use buck2_cli_proto::new_generic::NewGenericResponse;
 use buck2_server_ctx::other_server_commands::OTHER_SERVER_COMMANDS;
 
 use crate::ctx::ServerCommandContext;
+use crate::fetch_cells::fetch_cells_command;
 use crate::materialize::materialize_command;
+use crate::pin_re_outputs::pin_re_outputs_command;
 
 pub(crate) async fn new_generic_command(
     context: &ServerCommandContext<'_>,
@@ -29,6 +31,12 @@ pub(crate) async fn new_generic_command(
         NewGenericRequest::DebugEval(e) => NewGenericResponse::DebugEval(
             OTHER_SERVER_COMMANDS.get()?.debug_eval(context, e).await?,
         ),
+        NewGenericRequest::PinReOutputs(p) => {
+            NewGenericResponse::PinReOutputs(pin_re_outputs_command(context, p).await?)
+        }
+        NewGenericRequest::FetchCells(f) => {
+            NewGenericResponse::FetchCells(fetch_cells_command(context, f).await?)
+        }
     };
     let resp = serde_json::to_string(&resp).context("Could not serialize `NewGenericResponse`")?;
     Ok(buck2_cli_proto::NewGenericResponseMessage {
@@ -0,0 +1,290 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Pinning of the outputs of a build in RE, so that they are still in the CAS and the action
+//! cache weeks later.
+//!
+//! The TTL of the blobs referenced by action results is extended, including the files of output
+//! directories. Querying the action results also extends their own lifetime.
+
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::time::Duration;
+
+use buck2_build_api::actions::execute::dice_data::GetReClient;
+use buck2_cli_proto::new_generic::PinReOutputsRequest;
+use buck2_cli_proto::new_generic::PinReOutputsResponse;
+use buck2_core::execution_types::executor_config::RemoteExecutorUseCase;
+use buck2_execute::digest_config::HasDigestConfig;
+use buck2_execute::execute::action_digest::ActionDigest;
+use buck2_execute::re::manager::ManagedRemoteExecutionClient;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use chrono::DateTime;
+use chrono::Utc;
+use dupe::Dupe;
+use futures::StreamExt;
+use remote_execution as RE;
+use remote_execution::TDigest;
+
+use crate::ctx::ServerCommandContext;
+
+/// Number of action results queried concurrently.
+const CONCURRENT_ACTIONS: usize = 32;
+
+/// Number of blobs per request.
+const BLOBS_PER_REQUEST: usize = 1000;
+
+#[derive(Debug, buck2_error::Error)]
+enum PinReOutputsError {
+    #[error("Invalid action digest `{0}`, expected `HASH:SIZE`")]
+    #[buck2(user)]
+    InvalidActionDigest(String),
+}
+
+/// The blobs to pin with a use case.
+#[derive(Default)]
+struct Blobs {
+    files: Vec<TDigest>,
+    /// Digests of `Tree` messages, whose files are pinned too.
+    trees: Vec<TDigest>,
+}
+
+pub(crate) async fn pin_re_outputs_command(
+    context: &ServerCommandContext<'_>,
+    req: PinReOutputsRequest,
+) -> anyhow::Result<PinReOutputsResponse> {
+    let context: &dyn ServerCommandContextTrait = context;
+    context
+        .with_dice_ctx(|_server_ctx, ctx| async move {
+            let re_client = ctx.per_transaction_data().get_re_client();
+            let digest_config = ctx.global_data().get_digest_config();
+            let ttl = Duration::from_secs(req.ttl_s);
+
+            let mut response = PinReOutputsResponse {
+                actions_pinned: 0,
+                actions_missing: 0,
+                blobs_pinned: 0,
+                blobs_missing: 0,
+                earliest_expiration: None,
+            };
+
+            let mut blobs: BTreeMap<String, Blobs> = BTreeMap::new();
+            blobs.entry(req.use_case).or_default().files.extend(
+                req.blobs
+                    .iter()
+                    .map(|blob| blob.parse())
+                    .collect::<anyhow::Result<Vec<TDigest>>>()?,
+            );
+
+            let actions = req
+                .actions
+                .into_iter()
+                .map(|action| {
+                    let (digest, _) = ActionDigest::parse_digest(
+                        &action.digest,
+                        digest_config.cas_digest_config(),
+                    )
+                    .map_err(|_| PinReOutputsError::InvalidActionDigest(action.digest))?;
+                    anyhow::Ok((digest, action.use_case))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let mut results = futures::stream::iter(actions)
+                .map(|(digest, use_case)| {
+                    let re_client = re_client.dupe();
+                    async move {
+                        let result = re_client
+                            .action_cache(digest, RemoteExecutorUseCase::new(use_case.clone()))
+                            .await?;
+                        anyhow::Ok((use_case, result))
+                    }
+                })
+                .buffer_unordered(CONCURRENT_ACTIONS);
+            while let Some(result) = results.next().await {
+                let (use_case, result) = result?;
+                let Some(result) = result else {
+                    response.actions_missing += 1;
+                    continue;
+                };
+                response.actions_pinned += 1;
+                let result = result.action_result;
+                let blobs = blobs.entry(use_case).or_default();
+                blobs.files.extend(
+                    result
+                        .output_files
+                        .into_iter()
+                        .map(|f| f.digest.digest)
+                        .chain(result.stdout_digest)
+                        .chain(result.stderr_digest),
+                );
+                blobs
+                    .trees
+                    .extend(result.output_directories.into_iter().map(|d| d.tree_digest));
+            }
+
+            for (use_case, blobs) in blobs {
+                let use_case = RemoteExecutorUseCase::new(use_case);
+                let (pinned, missing) = pin_blobs(&re_client, blobs, ttl, use_case).await?;
+                response.blobs_pinned += pinned;
+                response.blobs_missing += missing;
+            }
+            if response.blobs_pinned > 0 {
+                let expiration = Utc::now() + chrono::Duration::from_std(ttl)?;
+                response.earliest_expiration = Some(expiration.to_rfc3339());
+            }
+            Ok(response)
+        })
+        .await
+}
+
+/// Extend the TTL of blobs and of the files of trees, returning how many were pinned and how
+/// many were no longer in the CAS.
+async fn pin_blobs(
+    re_client: &ManagedRemoteExecutionClient,
+    blobs: Blobs,
+    ttl: Duration,
+    use_case: RemoteExecutorUseCase,
+) -> anyhow::Result<(u64, u64)> {
+    let mut files = blobs.files;
+    let mut missing_count = 0;
+
+    let (trees, missing) = find_present(re_client, dedup(blobs.trees), use_case).await?;
+    missing_count += missing.len() as u64;
+    for chunk in trees.chunks(BLOBS_PER_REQUEST) {
+        let downloaded = re_client
+            .download_typed_blobs::<RE::Tree>(chunk.to_vec(), use_case)
+            .await?;
+        files.extend(downloaded.iter().flat_map(tree_file_digests));
+    }
+    files.extend(trees);
+
+    let (present, missing) = find_present(re_client, dedup(files), use_case).await?;
+    missing_count += missing.len() as u64;
+    for chunk in present.chunks(BLOBS_PER_REQUEST) {
+        re_client
+            .extend_digest_ttl(chunk.to_vec(), ttl, use_case)
+            .await?;
+    }
+    Ok((present.len() as u64, missing_count))
+}
+
+/// Split digests into those in the CAS and those which aren't.
+async fn find_present(
+    re_client: &ManagedRemoteExecutionClient,
+    digests: Vec<TDigest>,
+    use_case: RemoteExecutorUseCase,
+) -> anyhow::Result<(Vec<TDigest>, Vec<TDigest>)> {
+    let mut expirations = Vec::new();
+    for chunk in digests.chunks(BLOBS_PER_REQUEST) {
+        expirations.extend(
+            re_client
+                .get_digest_expirations(chunk.to_vec(), use_case)
+                .await?,
+        );
+    }
+    Ok(partition_present(digests, &expirations, Utc::now()))
+}
+
+/// Digests the CAS doesn't have are either omitted from the expirations or reported as already
+/// expired.
+fn partition_present(
+    digests: Vec<TDigest>,
+    expirations: &[(TDigest, DateTime<Utc>)],
+    now: DateTime<Utc>,
+) -> (Vec<TDigest>, Vec<TDigest>) {
+    let present: HashSet<&TDigest> = expirations
+        .iter()
+        .filter(|(_, expires)| *expires > now)
+        .map(|(digest, _)| digest)
+        .collect();
+    digests.into_iter().partition(|d| present.contains(d))
+}
+
+fn dedup(mut digests: Vec<TDigest>) -> Vec<TDigest> {
+    digests.sort_by(|a, b| (&a.hash, a.size_in_bytes).cmp(&(&b.hash, b.size_in_bytes)));
+    digests.dedup();
+    digests
+}
+
+/// The digests of the files in a tree, in all of its directories.
+fn tree_file_digests(tree: &RE::Tree) -> impl Iterator<Item = TDigest> + '_ {
+    tree.root
+        .iter()
+        .chain(&tree.children)
+        .flat_map(|dir| &dir.files)
+        .filter_map(|file| file.digest.as_ref())
+        .map(|digest| TDigest {
+            hash: digest.hash.clone(),
+            size_in_bytes: digest.size_bytes,
+            ..Default::default()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest(hash: &str) -> TDigest {
+        TDigest {
+            hash: hash.to_owned(),
+            size_in_bytes: 1,
+            ..Default::default()
+        }
+    }
+
+    fn dir(files: &[&str]) -> RE::Directory {
+        RE::Directory {
+            files: files
+                .iter()
+                .map(|hash| RE::FileNode {
+                    name: format!("{}.txt", hash),
+                    digest: Some(RE::Digest {
+                        hash: (*hash).to_owned(),
+                        size_bytes: 1,
+                    }),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_tree_file_digests() {
+        let tree = RE::Tree {
+            root: Some(dir(&["a", "b"])),
+            children: vec![dir(&["c"]), dir(&[])],
+        };
+        assert_eq!(
+            vec![digest("a"), digest("b"), digest("c")],
+            tree_file_digests(&tree).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_partition_present() {
+        let now = Utc::now();
+        let later = now + chrono::Duration::hours(1);
+        let (present, missing) = partition_present(
+            vec![digest("a"), digest("b"), digest("c")],
+            &[(digest("a"), later), (digest("b"), now)],
+            now,
+        );
+        assert_eq!(vec![digest("a")], present);
+        assert_eq!(vec![digest("b"), digest("c")], missing);
+    }
+
+    #[test]
+    fn test_dedup() {
+        assert_eq!(
+            vec![digest("a"), digest("b")],
+            dedup(vec![digest("b"), digest("a"), digest("b")])
+        );
+    }
+}
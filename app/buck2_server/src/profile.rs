@@ -9,9 +9,11 @@
 
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Context as _;
 use async_trait::async_trait;
+use buck2_analysis::analysis::calculation::benchmark_analysis;
 use buck2_analysis::analysis::calculation::profile_analysis;
 use buck2_analysis::analysis::calculation::profile_analysis_recursively;
 use buck2_cli_proto::profile_request::ProfileOpts;
@@ -32,6 +34,7 @@ use buck2_interpreter::starlark_profiler::StarlarkProfileDataAndStats;
 use buck2_interpreter::starlark_profiler::StarlarkProfiler;
 use buck2_interpreter::starlark_profiler::StarlarkProfilerOrInstrumentation;
 use buck2_interpreter_for_build::interpreter::dice_calculation_delegate::HasCalculationDelegate;
+use buck2_node::nodes::frontend::TargetGraphCalculation;
 use buck2_node::target_calculation::ConfiguredTargetCalculation;
 use buck2_profile::get_profile_response;
 use buck2_profile::starlark_profiler_configuration_from_request;
@@ -45,6 +48,7 @@ use buck2_server_ctx::template::ServerCommandTemplate;
 use dice::DiceTransaction;
 use dupe::Dupe;
 use futures::future::FutureExt;
+use gazebo::prelude::*;
 
 async fn generate_profile_analysis(
     ctx: DiceTransaction,
@@ -167,6 +171,27 @@ impl ServerCommandTemplate for ProfileServerCommand {
 
                 get_profile_response(profile_data, &self.req, output)
             }
+            ProfileOpts::AnalysisBench(opts) => {
+                let context = self
+                    .req
+                    .context
+                    .as_ref()
+                    .context("Missing client context")?;
+
+                let analysis_bench =
+                    generate_analysis_bench(server_ctx, ctx, context, opts).await?;
+                let elapsed = analysis_bench
+                    .iter()
+                    .flat_map(|t| &t.samples)
+                    .map(|d| Duration::try_from(d.clone()))
+                    .sum::<Result<Duration, _>>()?;
+
+                Ok(buck2_cli_proto::ProfileResponse {
+                    elapsed: Some(elapsed.try_into()?),
+                    total_retained_bytes: 0,
+                    analysis_bench,
+                })
+            }
             _ => {
                 return Err(anyhow::anyhow!(
                     "Expected target profile opts, not BXL profile opts"
@@ -243,6 +268,69 @@ async fn generate_profile(
     }
 }
 
+/// Analyze each target matching the patterns `opts.warmup` times, then time `opts.iterations`
+/// more analyses of it. Targets are benchmarked one at a time, so they do not compete for CPU.
+async fn generate_analysis_bench(
+    server_ctx: &dyn ServerCommandContextTrait,
+    mut ctx: DiceTransaction,
+    client_ctx: &ClientContext,
+    opts: &buck2_cli_proto::AnalysisBench,
+) -> anyhow::Result<Vec<buck2_cli_proto::AnalysisBenchTarget>> {
+    let cells = ctx.get_cell_resolver().await?;
+
+    let global_target_platform =
+        target_platform_from_client_context(client_ctx, server_ctx, &mut ctx).await?;
+
+    let parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
+        &mut ctx,
+        &opts.target_patterns,
+        server_ctx.working_dir(),
+    )
+    .await?;
+
+    let resolved = resolve_target_patterns(&cells, &parsed_patterns, &ctx.file_ops()).await?;
+
+    let mut results = Vec::new();
+    for (package, spec) in resolved.specs {
+        let targets = match spec {
+            PackageSpec::Targets(targets) => targets.into_map(|(t, _)| t),
+            PackageSpec::All => ctx
+                .get_interpreter_results(package.dupe())
+                .await?
+                .targets()
+                .keys()
+                .map(|t| t.to_owned())
+                .collect(),
+        };
+
+        for target in targets {
+            let label = TargetLabel::new(package.dupe(), target.as_ref());
+            let configured_target = ctx
+                .get_configured_target(&label, global_target_platform.as_ref())
+                .await?;
+
+            for _ in 0..opts.warmup {
+                benchmark_analysis(&ctx, &configured_target).await?;
+            }
+            let mut samples = Vec::with_capacity(opts.iterations as usize);
+            for _ in 0..opts.iterations {
+                samples.push(
+                    benchmark_analysis(&ctx, &configured_target)
+                        .await?
+                        .try_into()?,
+                );
+            }
+
+            results.push(buck2_cli_proto::AnalysisBenchTarget {
+                target: configured_target.to_string(),
+                samples,
+            });
+        }
+    }
+
+    Ok(results)
+}
+
 fn one<T>(it: impl IntoIterator<Item = T>) -> anyhow::Result<T> {
     let mut it = it.into_iter();
     let val = it.next().context("No value found")?;
@@ -54,6 +54,8 @@ impl SnapshotCollector {
     fn add_daemon_metrics(&self, snapshot: &mut buck2_data::Snapshot) {
         snapshot.blocking_executor_io_queue_size =
             self.daemon.blocking_executor.queue_size() as u64;
+        snapshot.event_log_compression = Some(self.daemon.event_log_compression.to_string());
+        snapshot.write_action_compression = Some(self.daemon.write_action_compression.to_string());
     }
 
     fn add_io_metrics(&self, snapshot: &mut buck2_data::Snapshot) {
@@ -121,6 +123,13 @@ impl SnapshotCollector {
                 stats.get_digest_expirations.finished_successfully;
             snapshot.re_get_digest_expirations_finished_with_error =
                 stats.get_digest_expirations.finished_with_error;
+            snapshot.re_find_missing_batches = stats.cas_batching.find_missing_batches;
+            snapshot.re_find_missing_digests_requested =
+                stats.cas_batching.find_missing_digests_requested;
+            snapshot.re_find_missing_digests_queried =
+                stats.cas_batching.find_missing_digests_queried;
+            snapshot.re_upload_digests_deduplicated =
+                stats.cas_batching.upload_digests_deduplicated;
 
             Ok(())
         }
@@ -0,0 +1,181 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Host tools that rules use from the system, such as the system `cc` or Xcode.
+//!
+//! They are declared in the root `.buckconfig`, as a program name (looked up in the `PATH` of
+//! the daemon) or an absolute path:
+//!
+//! ```ini
+//! [system_toolchains]
+//!   cc = cc
+//!   xcrun = /usr/bin/xcrun
+//! ```
+//!
+//! Each of them is probed for its path, version and a fingerprint of its content, which are
+//! exposed as `[system_toolchain_probes]` config values (`cc.path`, `cc.version` and
+//! `cc.fingerprint`). The `system_toolchain` macro of the prelude reads them, and makes the
+//! fingerprint an input of every action using the tool, so that changing the tool invalidates
+//! these actions.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use anyhow::Context;
+use buck2_common::legacy_configs::LegacyBuckConfig;
+use buck2_common::legacy_configs::LegacyConfigCmdArg;
+use once_cell::sync::Lazy;
+use sha2::Digest;
+use sha2::Sha256;
+
+/// Section of the root config declaring system toolchains.
+const SECTION: &str = "system_toolchains";
+
+/// Section the probes are exposed in.
+const PROBES_SECTION: &str = "system_toolchain_probes";
+
+#[derive(Debug, buck2_error::Error)]
+#[buck2(user)]
+enum SystemToolchainError {
+    #[error("System toolchain `{0}`: `{1}` not found")]
+    NotFound(String, String),
+    #[error("Invalid system toolchain name `{0}`, expected letters, digits, `_` and `-`")]
+    InvalidName(String),
+}
+
+#[derive(Clone, Debug)]
+struct SystemToolchainProbe {
+    version: String,
+    fingerprint: String,
+}
+
+/// File size and modification time of a tool when it was probed.
+type ToolStat = (u64, SystemTime);
+
+/// Probes by canonical path of the tool. A tool is probed again when its stat changes.
+static PROBES: Lazy<Mutex<HashMap<PathBuf, (ToolStat, SystemToolchainProbe)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn validate_name(name: &str) -> anyhow::Result<()> {
+    if name.is_empty()
+        || !name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+    {
+        return Err(SystemToolchainError::InvalidName(name.to_owned()).into());
+    }
+    Ok(())
+}
+
+fn resolve_program(program: &str) -> Option<PathBuf> {
+    let program = Path::new(program);
+    let path = if program.is_absolute() {
+        program.is_file().then(|| program.to_owned())?
+    } else {
+        let path_var = std::env::var_os("PATH")?;
+        std::env::split_paths(&path_var)
+            .map(|dir| dir.join(program))
+            .find(|path| path.is_file())?
+    };
+    fs::canonicalize(path).ok()
+}
+
+/// The first non-empty line of the output of `--version`, and the whole output.
+fn version_of(path: &Path) -> anyhow::Result<(String, Vec<u8>)> {
+    let output = Command::new(path)
+        .arg("--version")
+        .output()
+        .with_context(|| format!("Error running `{} --version`", path.display()))?;
+    let mut all = output.stdout;
+    all.extend(output.stderr);
+    let version = String::from_utf8_lossy(&all)
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default()
+        .to_owned();
+    Ok((version, all))
+}
+
+fn probe(path: &Path) -> anyhow::Result<SystemToolchainProbe> {
+    let metadata = fs::metadata(path)?;
+    let stat = (metadata.len(), metadata.modified()?);
+    if let Some((probed_stat, probe)) = PROBES.lock().unwrap().get(path) {
+        if *probed_stat == stat {
+            return Ok(probe.clone());
+        }
+    }
+
+    let (version, version_output) = version_of(path)?;
+    let mut hasher = Sha256::new();
+    hasher.update(fs::read(path)?);
+    hasher.update(&version_output);
+    let probe = SystemToolchainProbe {
+        version,
+        fingerprint: hex::encode(hasher.finalize()),
+    };
+
+    PROBES
+        .lock()
+        .unwrap()
+        .insert(path.to_owned(), (stat, probe.clone()));
+    Ok(probe)
+}
+
+/// Probe the system toolchains declared in the root config, and return the config values
+/// exposing them.
+pub(crate) fn system_toolchain_config_args(
+    root_config: &LegacyBuckConfig,
+) -> anyhow::Result<Vec<LegacyConfigCmdArg>> {
+    let mut args = Vec::new();
+    let toolchains = match root_config.get_section(SECTION) {
+        Some(toolchains) => toolchains,
+        None => return Ok(args),
+    };
+    for (name, program) in toolchains.iter() {
+        validate_name(name)?;
+        let program = program.as_str();
+        let path = resolve_program(program)
+            .ok_or_else(|| SystemToolchainError::NotFound(name.to_owned(), program.to_owned()))?;
+        let probe =
+            probe(&path).with_context(|| format!("Error probing system toolchain `{}`", name))?;
+
+        for (key, value) in [
+            ("path", path.to_string_lossy().into_owned()),
+            ("version", probe.version),
+            ("fingerprint", probe.fingerprint),
+        ] {
+            args.push(LegacyConfigCmdArg::flag(&format!(
+                "{}.{}.{}={}",
+                PROBES_SECTION, name, key, value
+            ))?);
+        }
+    }
+    Ok(args)
+}
+
+/// Versions of the system toolchains probed in the root config, by name.
+pub(crate) fn system_toolchain_versions(root_config: &LegacyBuckConfig) -> HashMap<String, String> {
+    let probes = match root_config.get_section(PROBES_SECTION) {
+        Some(probes) => probes,
+        None => return HashMap::new(),
+    };
+    probes
+        .iter()
+        .filter_map(|(key, value)| {
+            let name = key.strip_suffix(".version")?;
+            Some((name.to_owned(), value.as_str().to_owned()))
+        })
+        .collect()
+}
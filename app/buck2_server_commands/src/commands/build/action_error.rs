@@ -9,6 +9,8 @@
 
 //! Schema for the structured action error within the build report.
 
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::file_name::FileName;
 use buck2_data::command_execution_kind::Command;
 use buck2_data::ActionError;
 use buck2_data::CommandExecutionDetails;
@@ -19,6 +21,10 @@ use serde::Serialize;
 
 use crate::commands::build::BuildReportCollector;
 
+/// Maximum number of bytes of stderr included in the build report for each action. The full
+/// stderr of actions exceeding this is written to a file alongside the build report.
+const MAX_STDERR_BYTES: usize = 64 * 1024;
+
 #[derive(Debug, Clone, Serialize, PartialOrd, Ord, PartialEq, Eq)]
 struct BuildReportActionName {
     category: String,
@@ -57,8 +63,13 @@ pub(crate) struct BuildReportActionError {
     name: BuildReportActionName,
     key: BuildReportActionKey,
     digest: String,
+    exit_code: Option<i32>,
     error_content: String,
     stderr_content: String,
+    /// Set if `stderr_content` only holds the end of the stderr.
+    stderr_truncated: bool,
+    /// Path to the full stderr, if it was truncated and could be written.
+    full_stderr_path: Option<String>,
     stdout_content: String,
     error_diagnostics: Option<BuildReportActionErrorDiagnostics>,
 }
@@ -126,9 +137,23 @@ impl BuildReportActionError {
             }
         });
 
-        let stderr = command_details.map_or(String::default(), |c| c.stderr.clone());
+        let digest = get_action_digest(command_details).unwrap_or_default();
+        let exit_code = command_details.and_then(|c| c.signed_exit_code);
+
+        let mut stderr = command_details.map_or(String::default(), |c| c.stderr.clone());
         let stdout = command_details.map_or(String::default(), |c| c.stdout.clone());
 
+        let stderr_truncated = stderr.len() > MAX_STDERR_BYTES;
+        let mut full_stderr_path = None;
+        if stderr_truncated {
+            full_stderr_path = write_full_stderr(collector, &digest, &stderr);
+            let mut start = stderr.len() - MAX_STDERR_BYTES;
+            while !stderr.is_char_boundary(start) {
+                start += 1;
+            }
+            stderr.drain(..start);
+        }
+
         let error_content = collector.update_string_cache(reason);
         let stderr_content = collector.update_string_cache(stderr);
         let stdout_content = collector.update_string_cache(stdout);
@@ -138,13 +163,44 @@ impl BuildReportActionError {
             name,
             error_content,
             stderr_content,
+            stderr_truncated,
+            full_stderr_path,
             stdout_content,
-            digest: get_action_digest(command_details).unwrap_or_default(),
+            digest,
+            exit_code,
             error_diagnostics,
         }
     }
 }
 
+fn write_full_stderr(
+    collector: &mut BuildReportCollector<'_>,
+    digest: &str,
+    stderr: &str,
+) -> Option<String> {
+    let res = (|| {
+        // Digests are `hash:size`, keep only the hash.
+        let hash = digest.split(':').next().unwrap_or_default();
+        let name = if hash.is_empty() {
+            format!("{}.stderr", collector.next_full_output_index())
+        } else {
+            format!("{}.stderr", hash)
+        };
+        let path = collector.full_output_dir().join(FileName::new(&name)?);
+        fs_util::create_dir_all(collector.full_output_dir())?;
+        fs_util::write(&path, stderr)?;
+        anyhow::Ok(path)
+    })();
+
+    match res {
+        Ok(path) => Some(path.to_string()),
+        Err(e) => {
+            tracing::warn!("Error writing action stderr for the build report: {:#}", e);
+            None
+        }
+    }
+}
+
 fn get_action_digest(command_details: Option<&CommandExecutionDetails>) -> Option<String> {
     command_details.and_then(|command_details| {
         if let Some(command_kind) = &command_details.command_kind {
@@ -0,0 +1,143 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Attestation of the outputs of a build by a user-provided command.
+//!
+//! When `build.attestation_command` is set, it is run after each successful build, from the
+//! project root, with two arguments: a JSON file listing the files built (the subjects), and the
+//! path where it must write its attestation, for example a signed SLSA provenance statement.
+//! Both are in `buck-out/v2/build_report/<trace_id>`, next to the other files of the build
+//! report, which records the path of the attestation. If the command fails, so does the build.
+
+use std::collections::BTreeMap;
+use std::process::Stdio;
+
+use anyhow::Context;
+use buck2_build_api::build::ProviderArtifacts;
+use buck2_core::directory::Directory;
+use buck2_core::directory::DirectoryEntry;
+use buck2_core::directory::DirectoryIterator;
+use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_execute::directory::ActionDirectoryBuilder;
+use buck2_execute::directory::ActionDirectoryMember;
+use buck2_util::process::async_background_command;
+use buck2_wrapper_common::invocation_id::TraceId;
+use serde::Serialize;
+
+#[derive(Debug, buck2_error::Error)]
+#[buck2(user)]
+enum AttestationError {
+    #[error("Invalid `build.attestation_command`: `{0}`")]
+    InvalidCommand(String),
+    #[error("Attestation command `{0}` failed with {1}:\n{2}")]
+    Failed(String, std::process::ExitStatus, String),
+    #[error("Attestation command `{0}` did not write an attestation to `{1}`")]
+    NoAttestation(String, String),
+}
+
+/// A file built, in the format of in-toto resource descriptors, as used for the subjects of
+/// SLSA provenance.
+#[derive(Serialize)]
+pub(crate) struct AttestationSubject {
+    /// Path relative to the project root.
+    name: String,
+    /// Digest, by lowercase algorithm name, e.g. `sha1`.
+    digest: BTreeMap<String, String>,
+    size: u64,
+}
+
+#[derive(Serialize)]
+struct AttestationSubjects<'a> {
+    trace_id: &'a TraceId,
+    subjects: Vec<AttestationSubject>,
+}
+
+/// The files built, for the attestation command. Directories are listed file by file, and
+/// symlinks are omitted, as they are attested through the files they point to.
+pub(crate) fn attestation_subjects(
+    provider_artifacts: &[ProviderArtifacts],
+    artifact_fs: &ArtifactFs,
+) -> anyhow::Result<Vec<AttestationSubject>> {
+    let mut dir = ActionDirectoryBuilder::empty();
+    for artifact in provider_artifacts {
+        artifact.values.add_to_directory(&mut dir, artifact_fs)?;
+    }
+    let mut subjects = Vec::new();
+    for (path, entry) in dir.ordered_walk().with_paths() {
+        if let DirectoryEntry::Leaf(ActionDirectoryMember::File(metadata)) = entry {
+            let digest = metadata.digest.data();
+            subjects.push(AttestationSubject {
+                name: path.to_string(),
+                digest: BTreeMap::from([(
+                    digest.raw_digest().algorithm().to_string().to_lowercase(),
+                    digest.raw_digest().to_string(),
+                )]),
+                size: digest.size(),
+            });
+        }
+    }
+    Ok(subjects)
+}
+
+/// Run the attestation command over `subjects`, with its files in `dir`. Returns the path of
+/// the attestation.
+pub(crate) async fn run_attestation_command(
+    command: &str,
+    trace_id: &TraceId,
+    subjects: Vec<AttestationSubject>,
+    project_root: &ProjectRoot,
+    dir: &AbsNormPath,
+) -> anyhow::Result<AbsNormPathBuf> {
+    let argv = shlex::split(command)
+        .filter(|argv| !argv.is_empty())
+        .ok_or_else(|| AttestationError::InvalidCommand(command.to_owned()))?;
+
+    fs_util::create_dir_all(dir)?;
+    let subjects_path = dir.join(ForwardRelativePath::unchecked_new(
+        "attestation_subjects.json",
+    ));
+    let attestation_path = dir.join(ForwardRelativePath::unchecked_new("attestation.json"));
+    fs_util::write(
+        &subjects_path,
+        serde_json::to_vec_pretty(&AttestationSubjects { trace_id, subjects })?,
+    )
+    .context("Error writing attestation subjects")?;
+
+    let output = async_background_command(&argv[0])
+        .args(&argv[1..])
+        .arg(subjects_path.as_os_str())
+        .arg(attestation_path.as_os_str())
+        .current_dir(project_root.root())
+        .env("BUCK2_TRACE_ID", trace_id.to_string())
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .with_context(|| format!("Error running attestation command `{}`", command))?;
+    if !output.status.success() {
+        return Err(AttestationError::Failed(
+            command.to_owned(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        )
+        .into());
+    }
+    if !fs_util::try_exists(&attestation_path)? {
+        return Err(AttestationError::NoAttestation(
+            command.to_owned(),
+            attestation_path.to_string(),
+        )
+        .into());
+    }
+    Ok(attestation_path)
+}
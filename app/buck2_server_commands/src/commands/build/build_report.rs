@@ -17,6 +17,9 @@ use std::hash::Hash;
 use std::hash::Hasher;
 use std::sync::Arc;
 
+use buck2_artifact::actions::key::ActionKey;
+use buck2_artifact::artifact::artifact_type::BaseArtifactKind;
+use buck2_build_api::actions::calculation::ActionCalculation;
 use buck2_build_api::build::BuildProviderType;
 use buck2_build_api::build::BuildTargetResult;
 use buck2_build_api::build::ConfiguredBuildTargetResult;
@@ -24,6 +27,7 @@ use buck2_core::configuration::compatibility::MaybeCompatible;
 use buck2_core::configuration::data::ConfigurationData;
 use buck2_core::fs::artifact_path_resolver::ArtifactFs;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_core::provider::label::ConfiguredProvidersLabel;
@@ -33,9 +37,13 @@ use buck2_core::target::label::TargetLabel;
 use buck2_error::UniqueRootId;
 use buck2_events::errors::create_error_report;
 use buck2_execute::artifact::artifact_dyn::ArtifactDyn;
+use buck2_execute::output_size::OutputSize;
+use buck2_node::nodes::frontend::TargetGraphCalculation;
 use buck2_wrapper_common::invocation_id::TraceId;
 use derivative::Derivative;
+use dice::DiceComputations;
 use dupe::Dupe;
+use futures::future;
 use itertools::Either;
 use itertools::EitherOrBoth;
 use itertools::Itertools;
@@ -44,12 +52,12 @@ use starlark_map::small_set::SmallSet;
 
 use crate::commands::build::action_error::BuildReportActionError;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, PartialEq)]
 #[allow(clippy::upper_case_acronyms)] // We care about how they serialise
 enum BuildOutcome {
     SUCCESS,
     FAIL,
-    #[allow(dead_code)] // Part of the spec, but not yet used
+    /// The build timed out before this target was built.
     CANCELED,
 }
 
@@ -69,6 +77,30 @@ pub(crate) struct BuildReport {
     project_root: AbsNormPathBuf,
     truncated: bool,
     strings: BTreeMap<String, String>,
+    environment: buck2_data::EnvironmentProvenance,
+    /// The attestation of the outputs, if `build.attestation_command` is set.
+    attestation: Option<AbsNormPathBuf>,
+}
+
+impl BuildReport {
+    pub(crate) fn set_attestation(&mut self, attestation: AbsNormPathBuf) {
+        self.attestation = Some(attestation);
+    }
+}
+
+/// Where files which are part of the build report, but not in the report itself, are written.
+pub(crate) fn build_report_dir(
+    trace_id: &TraceId,
+    artifact_fs: &ArtifactFs,
+    project_root: &ProjectRoot,
+) -> AbsNormPathBuf {
+    project_root.resolve(
+        &artifact_fs
+            .buck_out_path_resolver()
+            .root()
+            .join(ForwardRelativePath::unchecked_new("build_report"))
+            .join(ForwardRelativePath::unchecked_new(&trace_id.to_string())),
+    )
 }
 
 /// The fields that stored in the unconfigured `BuildReportEntry` for buck1 backcompat.
@@ -98,6 +130,17 @@ struct MaybeConfiguredBuildReportEntry {
 pub(crate) struct ConfiguredBuildReportEntry {
     /// A list of errors that occurred while building this target
     errors: Vec<BuildReportError>,
+    /// Information about each output of this target, by path. Only present when
+    /// `build_report.include_artifacts` is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    artifacts: Option<BTreeMap<ProjectRelativePathBuf, BuildReportArtifact>>,
+    /// The metadata of this target, including the metadata of its package. Omitted if empty
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<serde_json::Value>,
+    /// The oncall to route the failure of this target to. Only present when the target failed
+    /// and has an oncall
+    #[serde(skip_serializing_if = "Option::is_none")]
+    oncall: Option<String>,
     #[serde(flatten)]
     inner: MaybeConfiguredBuildReportEntry,
 }
@@ -138,6 +181,113 @@ struct BuildReportError {
     cause_index: usize,
 }
 
+/// DO NOT UPDATE WITHOUT UPDATING `docs/users/build_observability/build_report.md`!
+#[derive(Debug, Serialize)]
+struct BuildReportArtifact {
+    /// The digest of the artifact, as `HASH:SIZE`. For a directory, this is the digest of the
+    /// directory tree
+    digest: Option<String>,
+    /// Whether the artifact is a directory
+    is_directory: bool,
+    /// The size of the artifact in bytes. For a directory, this is the total size of the files
+    /// in it
+    size: u64,
+    /// The category of the action which produced the artifact, absent for source files
+    action_category: Option<String>,
+    /// How the action which produced the artifact was executed, absent for source files
+    execution_kind: Option<&'static str>,
+}
+
+/// How the action producing a build artifact ran, as reported in the build report.
+pub(crate) struct ActionProvenance {
+    category: String,
+    execution_kind: Option<buck2_data::ActionExecutionKind>,
+}
+
+/// Look up the provenance of the actions which produced the outputs of a build, so that
+/// `BuildReportCollector` can report it per artifact. The actions have already been built, so
+/// this only reads their results.
+pub(crate) async fn collect_action_provenance(
+    ctx: &DiceComputations,
+    build_result: &BuildTargetResult,
+) -> anyhow::Result<HashMap<ActionKey, ActionProvenance>> {
+    let keys: HashSet<&ActionKey> = build_result
+        .configured
+        .values()
+        .flatten()
+        .flat_map(|result| result.outputs.iter())
+        .filter_map(|output| output.as_ref().ok())
+        .flat_map(|artifacts| artifacts.values.iter())
+        .filter_map(|(artifact, _value)| match artifact.as_parts().0 {
+            BaseArtifactKind::Build(artifact) => Some(artifact.key()),
+            BaseArtifactKind::Source(..) => None,
+        })
+        .collect();
+
+    future::try_join_all(keys.into_iter().map(|key| async move {
+        let action = ctx.get_action(key).await?;
+        let outputs = ctx.build_action(key.clone()).await?;
+        anyhow::Ok((
+            key.clone(),
+            ActionProvenance {
+                category: action.category().as_str().to_owned(),
+                execution_kind: outputs.execution_kind(),
+            },
+        ))
+    }))
+    .await
+    .map(|provenance| provenance.into_iter().collect())
+}
+
+/// The metadata of a target and who owns it, for the build report.
+pub(crate) struct TargetMetadata {
+    metadata: Option<serde_json::Value>,
+    oncall: Option<String>,
+}
+
+/// Look up the metadata of the targets of a build, including the metadata of their packages, and
+/// their oncall: the string value of `oncall_metadata_key` in their metadata if set, or else the
+/// `oncall()` of their build file. The target nodes have already been computed.
+pub(crate) async fn collect_target_metadata(
+    ctx: &DiceComputations,
+    build_result: &BuildTargetResult,
+    oncall_metadata_key: Option<&str>,
+) -> anyhow::Result<HashMap<TargetLabel, TargetMetadata>> {
+    let targets: HashSet<&TargetLabel> = build_result
+        .configured
+        .keys()
+        .map(|label| label.target().unconfigured())
+        .collect();
+
+    future::try_join_all(targets.into_iter().map(|target| async move {
+        let node = ctx.get_target_node(target).await?;
+        let metadata = node.metadata_with_package();
+        let metadata = (!metadata.is_empty()).then(|| metadata.to_value());
+        let oncall = oncall_metadata_key
+            .and_then(|key| metadata.as_ref()?.get(key)?.as_str())
+            .or_else(|| node.oncall())
+            .map(|oncall| oncall.to_owned());
+        anyhow::Ok((target.dupe(), TargetMetadata { metadata, oncall }))
+    }))
+    .await
+    .map(|metadata| metadata.into_iter().collect())
+}
+
+fn report_execution_kind(kind: buck2_data::ActionExecutionKind) -> Option<&'static str> {
+    use buck2_data::ActionExecutionKind;
+    match kind {
+        ActionExecutionKind::NotSet => None,
+        ActionExecutionKind::Local => Some("local"),
+        ActionExecutionKind::LocalWorker => Some("local_worker"),
+        ActionExecutionKind::Remote => Some("remote"),
+        ActionExecutionKind::ActionCache => Some("action_cache"),
+        ActionExecutionKind::RemoteDepFileCache => Some("remote_dep_file_cache"),
+        ActionExecutionKind::LocalDepFile => Some("local_dep_file"),
+        ActionExecutionKind::Simple => Some("simple"),
+        ActionExecutionKind::Deferred => Some("deferred"),
+    }
+}
+
 #[derive(Derivative, Serialize, Eq, PartialEq, Hash)]
 #[derivative(Debug)]
 #[serde(untagged)]
@@ -151,9 +301,15 @@ pub(crate) struct BuildReportCollector<'a> {
     overall_success: bool,
     include_unconfigured_section: bool,
     include_other_outputs: bool,
+    /// Set when the report includes information about each artifact.
+    action_provenance: Option<HashMap<ActionKey, ActionProvenance>>,
+    target_metadata: HashMap<TargetLabel, TargetMetadata>,
     error_cause_cache: HashMap<buck2_error::UniqueRootId, usize>,
     next_cause_index: usize,
     strings: BTreeMap<String, String>,
+    /// Where the full stderr of actions is written when it is too large for the report.
+    full_output_dir: AbsNormPathBuf,
+    next_full_output_index: usize,
 }
 
 impl<'a> BuildReportCollector<'a> {
@@ -163,6 +319,9 @@ impl<'a> BuildReportCollector<'a> {
         project_root: &ProjectRoot,
         include_unconfigured_section: bool,
         include_other_outputs: bool,
+        environment: buck2_data::EnvironmentProvenance,
+        action_provenance: Option<HashMap<ActionKey, ActionProvenance>>,
+        target_metadata: HashMap<TargetLabel, TargetMetadata>,
         build_result: &BuildTargetResult,
     ) -> BuildReport {
         let mut this: BuildReportCollector<'_> = Self {
@@ -170,9 +329,13 @@ impl<'a> BuildReportCollector<'a> {
             overall_success: true,
             include_unconfigured_section,
             include_other_outputs,
+            action_provenance,
+            target_metadata,
             error_cause_cache: HashMap::default(),
             next_cause_index: 0,
             strings: BTreeMap::default(),
+            full_output_dir: build_report_dir(trace_id, artifact_fs, project_root),
+            next_full_output_index: 0,
         };
         let mut entries = HashMap::new();
 
@@ -226,6 +389,8 @@ impl<'a> BuildReportCollector<'a> {
             // Setting this to false since we don't currently truncate buck2's build report.
             truncated: false,
             strings: this.strings,
+            environment,
+            attestation: None,
         }
     }
 
@@ -237,6 +402,16 @@ impl<'a> BuildReportCollector<'a> {
         hash
     }
 
+    pub(crate) fn full_output_dir(&self) -> &AbsNormPathBuf {
+        &self.full_output_dir
+    }
+
+    pub(crate) fn next_full_output_index(&mut self) -> usize {
+        let index = self.next_full_output_index;
+        self.next_full_output_index += 1;
+        index
+    }
+
     /// Always called for one unconfigured target at a time
     fn collect_results_for_unconfigured<'b>(
         &mut self,
@@ -261,10 +436,20 @@ impl<'a> BuildReportCollector<'a> {
             .filter_map(|(label, result)| Some((label, result.as_ref()?)))
             .group_by(|x| x.0.target().dupe())
         {
-            let configured_report = self.collect_results_for_configured(results);
+            let mut configured_report = self.collect_results_for_configured(results);
+            if let Some(target_metadata) = self.target_metadata.get(label.unconfigured()) {
+                configured_report.metadata = target_metadata.metadata.clone();
+                if !configured_report.errors.is_empty() {
+                    configured_report.oncall = target_metadata.oncall.clone();
+                }
+            }
             if let Some(report) = unconfigured_report.as_mut() {
                 if !configured_report.errors.is_empty() {
                     report.success = BuildOutcome::FAIL;
+                } else if configured_report.inner.success == BuildOutcome::CANCELED
+                    && report.success == BuildOutcome::SUCCESS
+                {
+                    report.success = BuildOutcome::CANCELED;
                 }
 
                 // FIXME(JakobDegen): This potentially overwrites entries from other
@@ -315,9 +500,14 @@ impl<'a> BuildReportCollector<'a> {
         >,
     ) -> ConfiguredBuildReportEntry {
         let mut configured_report = ConfiguredBuildReportEntry::default();
+        if self.action_provenance.is_some() {
+            configured_report.artifacts = Some(BTreeMap::new());
+        }
         let mut errors = Vec::new();
+        let mut unfinished = false;
         for (label, result) in results {
             let provider_name: Arc<str> = report_providers_name(label).into();
+            unfinished |= result.unfinished;
 
             result.outputs.iter().for_each(|res| {
                 match res {
@@ -343,7 +533,7 @@ impl<'a> BuildReportCollector<'a> {
                             }
                         }
 
-                        for (artifact, _value) in artifacts.values.iter() {
+                        for (artifact, value) in artifacts.values.iter() {
                             if is_default {
                                 configured_report
                                     .inner
@@ -361,6 +551,34 @@ impl<'a> BuildReportCollector<'a> {
                                     .or_default()
                                     .insert(artifact.resolve_path(self.artifact_fs).unwrap());
                             }
+
+                            // Artifacts are only reported alongside the outputs they are listed in.
+                            if !is_default && !self.include_other_outputs {
+                                continue;
+                            }
+                            if let (Some(report_artifacts), Some(action_provenance)) = (
+                                configured_report.artifacts.as_mut(),
+                                self.action_provenance.as_ref(),
+                            ) {
+                                let provenance = match artifact.as_parts().0 {
+                                    BaseArtifactKind::Build(artifact) => {
+                                        action_provenance.get(artifact.key())
+                                    }
+                                    BaseArtifactKind::Source(..) => None,
+                                };
+                                report_artifacts.insert(
+                                    artifact.resolve_path(self.artifact_fs).unwrap(),
+                                    BuildReportArtifact {
+                                        digest: value.digest().map(|d| d.to_string()),
+                                        is_directory: value.is_dir(),
+                                        size: value.calc_output_count_and_bytes().bytes,
+                                        action_category: provenance.map(|p| p.category.clone()),
+                                        execution_kind: provenance
+                                            .and_then(|p| p.execution_kind)
+                                            .and_then(report_execution_kind),
+                                    },
+                                );
+                            }
                         }
                     }
                     Err(e) => errors.push(e.dupe()),
@@ -378,6 +596,8 @@ impl<'a> BuildReportCollector<'a> {
         configured_report.errors = self.convert_error_list(&errors);
         if !configured_report.errors.is_empty() {
             configured_report.inner.success = BuildOutcome::FAIL;
+        } else if unfinished {
+            configured_report.inner.success = BuildOutcome::CANCELED;
         }
         configured_report
     }
@@ -0,0 +1,409 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Budgets on the size of the targets of a build, declared in the `graph_budget` section of the
+//! root cell's buckconfig, one budget per key:
+//!
+//! ```ini
+//! [graph_budget]
+//!   app = //app/... max_nodes=20000 max_output_bytes=500000000
+//! ```
+//!
+//! Every target of the build matching the pattern of a budget must have at most `max_nodes`
+//! nodes in its transitive configured graph and at most `max_output_bytes` bytes of outputs.
+
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use buck2_build_api::build::BuildTargetResult;
+use buck2_core::cells::name::CellName;
+use buck2_core::cells::CellResolver;
+use buck2_core::configuration::compatibility::MaybeCompatible;
+use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::pattern::ParsedPattern;
+use buck2_core::provider::label::ConfiguredProvidersLabel;
+use buck2_events::dispatch::console_message;
+use buck2_execute::output_size::OutputSize;
+
+/// Name of the file in buck-out that records the sizes of the targets measured by previous builds,
+/// to report how much they grew.
+const GRAPH_BUDGETS_FILE: &str = "graph_budgets.json";
+
+/// Number of violations listed in the report.
+const WORST_OFFENDERS: usize = 10;
+
+#[derive(Debug, buck2_error::Error)]
+enum GraphBudgetError {
+    #[error(
+        "Error parsing graph budget `graph_budget.{0}`, expected `PATTERN max_nodes=N max_output_bytes=N`, got `{1}`"
+    )]
+    #[buck2(user)]
+    UnrecognizedFormat(String, String),
+    #[error(
+        "`{target}` exceeds graph budget `graph_budget.{rule}`: {measure} is {actual}, the budget is {budget}"
+    )]
+    #[buck2(user)]
+    Exceeded {
+        target: ConfiguredProvidersLabel,
+        rule: String,
+        measure: &'static str,
+        actual: u64,
+        budget: u64,
+    },
+}
+
+struct GraphBudget {
+    name: String,
+    pattern: ParsedPattern<TargetPatternExtra>,
+    max_nodes: Option<u64>,
+    max_output_bytes: Option<u64>,
+}
+
+#[derive(Default)]
+pub(crate) struct GraphBudgets {
+    budgets: Vec<GraphBudget>,
+}
+
+impl GraphBudgets {
+    /// Parse the budgets, given as `(name, spec)` pairs, of the `graph_budget` section.
+    pub(crate) fn parse<'a>(
+        budgets: impl IntoIterator<Item = (&'a str, &'a str)>,
+        cell_name: CellName,
+        cell_resolver: &CellResolver,
+    ) -> anyhow::Result<Self> {
+        let mut parsed = Vec::new();
+        for (name, spec) in budgets {
+            let unrecognized =
+                || GraphBudgetError::UnrecognizedFormat(name.to_owned(), spec.to_owned());
+            let mut words = spec.split_whitespace();
+            let pattern = words.next().ok_or_else(unrecognized)?;
+            let mut budget = GraphBudget {
+                name: name.to_owned(),
+                pattern: ParsedPattern::parse_precise(pattern, cell_name, cell_resolver)?,
+                max_nodes: None,
+                max_output_bytes: None,
+            };
+            for word in words {
+                let (key, value) = word.split_once('=').ok_or_else(unrecognized)?;
+                let value = value.parse().map_err(|_| unrecognized())?;
+                match key {
+                    "max_nodes" => budget.max_nodes = Some(value),
+                    "max_output_bytes" => budget.max_output_bytes = Some(value),
+                    _ => return Err(unrecognized().into()),
+                }
+            }
+            parsed.push(budget);
+        }
+        Ok(GraphBudgets { budgets: parsed })
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.budgets.is_empty()
+    }
+
+    fn matching<'a>(
+        &'a self,
+        label: &'a ConfiguredProvidersLabel,
+    ) -> impl Iterator<Item = &'a GraphBudget> + 'a {
+        self.budgets
+            .iter()
+            .filter(|b| b.pattern.matches(label.target().unconfigured()))
+    }
+}
+
+/// The size of a target, as recorded in the graph budgets file.
+#[derive(
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize
+)]
+struct GraphSize {
+    nodes: Option<u64>,
+    output_bytes: u64,
+}
+
+#[derive(Debug, PartialEq)]
+struct Violation {
+    target: ConfiguredProvidersLabel,
+    rule: String,
+    measure: &'static str,
+    actual: u64,
+    budget: u64,
+    previous: Option<u64>,
+}
+
+/// Check the targets of the build against the budgets, print a report of the worst offenders and
+/// record the sizes of the targets for the next build. Returns an error for every budget which is
+/// exceeded.
+pub(crate) fn check_graph_budgets(
+    budgets: &GraphBudgets,
+    build_result: &BuildTargetResult,
+    artifact_fs: &ArtifactFs,
+    fs: &ProjectRoot,
+) -> anyhow::Result<Vec<buck2_error::Error>> {
+    let buck_out_root = fs.resolve(artifact_fs.buck_out_path_resolver().root());
+    let path = buck_out_root.join(ForwardRelativePath::unchecked_new(GRAPH_BUDGETS_FILE));
+
+    // Sizes we can't read (e.g. from an older version) are just rewritten.
+    let mut sizes: BTreeMap<String, GraphSize> = match fs_util::read_to_string_if_exists(&path)? {
+        Some(contents) => serde_json::from_str(&contents).unwrap_or_default(),
+        None => BTreeMap::new(),
+    };
+
+    let mut targets = Vec::new();
+    for (label, result) in &build_result.configured {
+        // We omit skipped targets here.
+        let Some(result) = result else { continue };
+        if budgets.matching(label).next().is_none() {
+            continue;
+        }
+
+        let size = GraphSize {
+            nodes: match &result.configured_graph_size {
+                Some(Ok(MaybeCompatible::Compatible(nodes))) => Some(*nodes),
+                _ => None,
+            },
+            output_bytes: result
+                .outputs
+                .iter()
+                .flatten()
+                .flat_map(|output| output.values.iter())
+                .map(|(_artifact, value)| value.calc_output_count_and_bytes().bytes)
+                .sum(),
+        };
+        targets.push((label, size));
+    }
+    let mut violations = find_violations(budgets, targets, &mut sizes);
+
+    fs_util::create_dir_all(&buck_out_root)?;
+    fs_util::write(&path, serde_json::to_vec_pretty(&sizes)?)
+        .context("writing graph budgets file")?;
+
+    if violations.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    console_message(report(&mut violations));
+
+    Ok(violations
+        .into_iter()
+        .map(|v| {
+            GraphBudgetError::Exceeded {
+                target: v.target,
+                rule: v.rule,
+                measure: v.measure,
+                actual: v.actual,
+                budget: v.budget,
+            }
+            .into()
+        })
+        .collect())
+}
+
+/// Check the sizes of targets against the budgets they match, and record them in `sizes`, which
+/// holds the sizes of the previous build.
+fn find_violations<'a>(
+    budgets: &GraphBudgets,
+    targets: impl IntoIterator<Item = (&'a ConfiguredProvidersLabel, GraphSize)>,
+    sizes: &mut BTreeMap<String, GraphSize>,
+) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    for (label, size) in targets {
+        let previous = sizes.insert(label.to_string(), size);
+
+        for budget in budgets.matching(label) {
+            let measures = [
+                (
+                    "transitive configured nodes",
+                    size.nodes,
+                    budget.max_nodes,
+                    previous.and_then(|p| p.nodes),
+                ),
+                (
+                    "output bytes",
+                    Some(size.output_bytes),
+                    budget.max_output_bytes,
+                    previous.map(|p| p.output_bytes),
+                ),
+            ];
+            for (measure, actual, max, previous) in measures {
+                if let (Some(actual), Some(max)) = (actual, max) {
+                    if actual > max {
+                        violations.push(Violation {
+                            target: label.clone(),
+                            rule: budget.name.clone(),
+                            measure,
+                            actual,
+                            budget: max,
+                            previous,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    violations
+}
+
+/// The report of the worst offenders, those furthest over their budget.
+fn report(violations: &mut [Violation]) -> String {
+    violations.sort_by(|a, b| {
+        (b.actual as f64 / b.budget as f64).total_cmp(&(a.actual as f64 / a.budget as f64))
+    });
+    let mut report = format!("{} graph budget(s) exceeded:", violations.len());
+    for v in violations.iter().take(WORST_OFFENDERS) {
+        let growth = match v.previous {
+            Some(previous) => format!(
+                ", {:+} since last build",
+                v.actual as i128 - previous as i128
+            ),
+            None => String::new(),
+        };
+        report.push_str(&format!(
+            "\n  {} ({}): {} {} > {}{}",
+            v.target, v.rule, v.measure, v.actual, v.budget, growth
+        ));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use buck2_core::cells::cell_root_path::CellRootPathBuf;
+    use buck2_core::cells::name::CellName;
+    use buck2_core::cells::CellResolver;
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::provider::label::ConfiguredProvidersLabel;
+    use buck2_core::provider::label::ProvidersLabel;
+    use buck2_core::target::label::TargetLabel;
+
+    use super::*;
+
+    fn parse(budgets: &[(&str, &str)]) -> anyhow::Result<GraphBudgets> {
+        let cell_resolver = CellResolver::testing_with_name_and_path(
+            CellName::testing_new("root"),
+            CellRootPathBuf::testing_new(""),
+        );
+        GraphBudgets::parse(
+            budgets.iter().copied(),
+            CellName::testing_new("root"),
+            &cell_resolver,
+        )
+    }
+
+    fn label(target: &str) -> ConfiguredProvidersLabel {
+        ProvidersLabel::default_for(TargetLabel::testing_parse(target))
+            .configure(ConfigurationData::testing_new())
+    }
+
+    fn size(nodes: u64, output_bytes: u64) -> GraphSize {
+        GraphSize {
+            nodes: Some(nodes),
+            output_bytes,
+        }
+    }
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let budgets = parse(&[
+            ("app", "//app/... max_nodes=100 max_output_bytes=2000"),
+            ("lib", "//lib:lib max_nodes=10"),
+        ])?;
+        assert_eq!(2, budgets.budgets.len());
+        assert_eq!("app", budgets.budgets[0].name);
+        assert_eq!(Some(100), budgets.budgets[0].max_nodes);
+        assert_eq!(Some(2000), budgets.budgets[0].max_output_bytes);
+        assert_eq!(Some(10), budgets.budgets[1].max_nodes);
+        assert_eq!(None, budgets.budgets[1].max_output_bytes);
+
+        assert!(parse(&[("app", "")]).is_err());
+        assert!(parse(&[("app", "//app/... max_nodes")]).is_err());
+        assert!(parse(&[("app", "//app/... max_nodes=many")]).is_err());
+        assert!(parse(&[("app", "//app/... max_depth=3")]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_violations() -> anyhow::Result<()> {
+        let budgets = parse(&[
+            ("app", "//app/... max_nodes=100 max_output_bytes=2000"),
+            ("bin", "//app:bin max_nodes=50"),
+        ])?;
+        let bin = label("root//app:bin");
+        let lib = label("root//app/lib:lib");
+        let mut sizes = BTreeMap::from([(bin.to_string(), size(40, 100))]);
+
+        let violations = find_violations(
+            &budgets,
+            [(&bin, size(60, 3000)), (&lib, size(100, 2000))],
+            &mut sizes,
+        );
+        assert_eq!(
+            vec![
+                Violation {
+                    target: bin.clone(),
+                    rule: "app".to_owned(),
+                    measure: "output bytes",
+                    actual: 3000,
+                    budget: 2000,
+                    previous: Some(100),
+                },
+                Violation {
+                    target: bin.clone(),
+                    rule: "bin".to_owned(),
+                    measure: "transitive configured nodes",
+                    actual: 60,
+                    budget: 50,
+                    previous: Some(40),
+                },
+            ],
+            violations
+        );
+        // The sizes of this build are recorded for the next one.
+        assert_eq!(Some(&size(60, 3000)), sizes.get(&bin.to_string()));
+        assert_eq!(Some(&size(100, 2000)), sizes.get(&lib.to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_report_lists_worst_offenders_first() {
+        let violation = |target: &str, actual, budget, previous| Violation {
+            target: label(target),
+            rule: "app".to_owned(),
+            measure: "transitive configured nodes",
+            actual,
+            budget,
+            previous,
+        };
+        let mut violations = vec![
+            violation("root//app:a", 110, 100, None),
+            violation("root//app:b", 300, 100, Some(250)),
+        ];
+        assert_eq!(
+            format!(
+                "2 graph budget(s) exceeded:\
+                \n  {} (app): transitive configured nodes 300 > 100, +50 since last build\
+                \n  {} (app): transitive configured nodes 110 > 100",
+                label("root//app:b"),
+                label("root//app:a"),
+            ),
+            report(&mut violations)
+        );
+    }
+}
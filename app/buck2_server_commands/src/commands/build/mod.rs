@@ -10,6 +10,7 @@
 use std::io::BufWriter;
 use std::io::Write;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Context as _;
 use async_trait::async_trait;
@@ -26,6 +27,7 @@ use buck2_build_api::build::BuildTargetResult;
 use buck2_build_api::build::ConfiguredBuildEvent;
 use buck2_build_api::build::ConvertMaterializationContext;
 use buck2_build_api::build::HasCreateUnhashedSymlinkLock;
+use buck2_build_api::build::HasGraphBudgetsLock;
 use buck2_build_api::build::MaterializationContext;
 use buck2_build_api::build::ProviderArtifacts;
 use buck2_build_api::build::ProvidersToBuild;
@@ -38,7 +40,9 @@ use buck2_cli_proto::HasClientContext;
 use buck2_common::dice::cells::HasCellResolver;
 use buck2_common::dice::file_ops::HasFileOps;
 use buck2_common::legacy_configs::dice::HasLegacyConfigs;
+use buck2_common::pattern::resolve::convert_package_spec;
 use buck2_common::pattern::resolve::resolve_target_patterns;
+use buck2_common::pattern::resolve::stream_target_patterns;
 use buck2_common::pattern::resolve::ResolvedPattern;
 use buck2_core::directory::Directory;
 use buck2_core::directory::DirectoryEntry;
@@ -81,18 +85,50 @@ use itertools::Either;
 use itertools::Itertools;
 use serde::ser::SerializeSeq;
 use serde::ser::Serializer;
+use tokio::sync::Semaphore;
 
+use crate::commands::build::attestation::attestation_subjects;
+use crate::commands::build::attestation::run_attestation_command;
+use crate::commands::build::build_report::build_report_dir;
+use crate::commands::build::build_report::collect_action_provenance;
+use crate::commands::build::build_report::collect_target_metadata;
 use crate::commands::build::build_report::BuildReportCollector;
+use crate::commands::build::graph_budget::check_graph_budgets;
+use crate::commands::build::graph_budget::GraphBudgets;
+use crate::commands::build::outputs_manifest::update_outputs_manifest;
 use crate::commands::build::result_report::ResultReporter;
 use crate::commands::build::result_report::ResultReporterOptions;
 use crate::commands::build::unhashed_outputs::create_unhashed_outputs;
 
 #[allow(unused)]
 mod action_error;
+mod attestation;
 mod build_report;
+mod graph_budget;
+mod outputs_manifest;
 mod result_report;
 mod unhashed_outputs;
 
+/// How many packages of the requested patterns are loaded at once, unless
+/// `build.package_load_concurrency` says otherwise.
+const DEFAULT_PACKAGE_LOAD_CONCURRENCY: usize = 500;
+
+#[derive(Debug, buck2_error::Error)]
+#[buck2(user)]
+enum BuildCommandError {
+    #[error("`build.package_load_concurrency` must be at least 1")]
+    ZeroPackageLoadConcurrency,
+}
+
+/// Limits the package loads of a single build command, so that a huge pattern is loaded and
+/// built progressively rather than all evaluated before anything is built.
+fn package_load_limit(concurrency: Option<usize>) -> anyhow::Result<Semaphore> {
+    match concurrency.unwrap_or(DEFAULT_PACKAGE_LOAD_CONCURRENCY) {
+        0 => Err(BuildCommandError::ZeroPackageLoadConcurrency.into()),
+        concurrency => Ok(Semaphore::new(concurrency)),
+    }
+}
+
 pub(crate) async fn build_command(
     ctx: &dyn ServerCommandContextTrait,
     partial_result_dispatcher: PartialResultDispatcher<NoPartialResult>,
@@ -225,9 +261,6 @@ async fn build(
         parse_patterns_from_cli_args(&mut ctx, &request.target_patterns, cwd).await?;
     server_ctx.log_target_pattern(&parsed_patterns);
 
-    let resolved_pattern: ResolvedPattern<ConfiguredProvidersPatternExtra> =
-        resolve_target_patterns(&cell_resolver, &parsed_patterns, &ctx.file_ops()).await?;
-
     let target_resolution_config: TargetResolutionConfig = if request.target_universe.is_empty() {
         TargetResolutionConfig::Default(global_target_platform)
     } else {
@@ -257,9 +290,38 @@ async fn build(
         .await?
         .unwrap_or_default();
 
+    let graph_budgets = match ctx
+        .get_legacy_config_for_cell(cell_resolver.root_cell())
+        .await?
+        .get_section("graph_budget")
+    {
+        Some(section) => GraphBudgets::parse(
+            section.iter().map(|(name, value)| (name, value.as_str())),
+            cell_resolver.root_cell(),
+            &cell_resolver,
+        )?,
+        None => GraphBudgets::default(),
+    };
+    // Budgets on the number of nodes need the size of the configured graph of every target.
+    let want_configured_graph_size = want_configured_graph_size || !graph_budgets.is_empty();
+
+    let package_loads = package_load_limit(
+        ctx.parse_legacy_config_property(
+            cell_resolver.root_cell(),
+            "build",
+            "package_load_concurrency",
+        )
+        .await?,
+    )?;
+
+    let timeout = request
+        .timeout
+        .as_ref()
+        .map(|timeout| Duration::new(timeout.seconds.max(0) as u64, timeout.nanos.max(0) as u32));
+
     let build_result = build_targets(
         &ctx,
-        resolved_pattern,
+        parsed_patterns,
         target_resolution_config,
         build_providers,
         &materialization_context,
@@ -267,10 +329,12 @@ async fn build(
         MissingTargetBehavior::from_skip(build_opts.skip_missing_targets),
         build_opts.skip_incompatible_targets,
         want_configured_graph_size,
+        &package_loads,
+        timeout,
     )
     .await?;
 
-    process_build_result(server_ctx, ctx, request, build_result).await
+    process_build_result(server_ctx, ctx, request, build_result, graph_budgets).await
 }
 
 async fn process_build_result(
@@ -278,6 +342,7 @@ async fn process_build_result(
     ctx: DiceTransaction,
     request: &buck2_cli_proto::BuildRequest,
     build_result: BuildTargetResult,
+    graph_budgets: GraphBudgets,
 ) -> anyhow::Result<buck2_cli_proto::BuildResponse> {
     let fs = server_ctx.project_root();
     let cwd = server_ctx.working_dir();
@@ -288,7 +353,7 @@ async fn process_build_result(
     let cell_resolver = ctx.get_cell_resolver().await?;
     let artifact_fs = ctx.get_artifact_fs().await?;
 
-    let result_reports = ResultReporter::convert(
+    let mut result_reports = ResultReporter::convert(
         &artifact_fs,
         ResultReporterOptions {
             return_outputs: response_options.return_outputs,
@@ -297,7 +362,27 @@ async fn process_build_result(
         &build_result,
     );
 
-    let build_report = if build_opts.unstable_print_build_report {
+    let mut build_report = if build_opts.unstable_print_build_report {
+        let include_artifacts = ctx
+            .parse_legacy_config_property(
+                cell_resolver.root_cell(),
+                "build_report",
+                "include_artifacts",
+            )
+            .await?
+            .unwrap_or(false);
+        let oncall_metadata_key = ctx
+            .get_legacy_config_property(
+                cell_resolver.root_cell(),
+                "build_report",
+                "oncall_metadata_key",
+            )
+            .await?;
+        let action_provenance = if include_artifacts {
+            Some(collect_action_provenance(&ctx, &build_result).await?)
+        } else {
+            None
+        };
         Some(BuildReportCollector::convert(
             server_ctx.events().trace_id(),
             &artifact_fs,
@@ -316,12 +401,49 @@ async fn process_build_result(
             )
             .await?
             .unwrap_or(false),
+            server_ctx.environment_provenance(&ctx).await?,
+            action_provenance,
+            collect_target_metadata(&ctx, &build_result, oncall_metadata_key.as_deref()).await?,
             &build_result,
         ))
     } else {
         None
     };
 
+    let should_write_outputs_manifest = ctx
+        .parse_legacy_config_property(cell_resolver.root_cell(), "buck2", "write_outputs_manifest")
+        .await?;
+
+    if should_write_outputs_manifest.unwrap_or(false) {
+        // The manifest is updated in place, so concurrent builds must not interleave. This is
+        // the same lock that guards the unhashed symlinks, which are also written to buck-out.
+        let lock = ctx
+            .per_transaction_data()
+            .get_create_unhashed_symlink_lock();
+        let _guard = lock.lock().await;
+        update_outputs_manifest(&build_result, &artifact_fs, fs)?;
+    }
+
+    if !graph_budgets.is_empty() {
+        // The sizes of previous builds are updated in place, so concurrent builds must not
+        // interleave.
+        let lock = ctx.per_transaction_data().get_graph_budgets_lock()?;
+        let _guard = lock.lock().await;
+        let exceeded = check_graph_budgets(&graph_budgets, &build_result, &artifact_fs, fs)?;
+        let warn_only = ctx
+            .parse_legacy_config_property(
+                cell_resolver.root_cell(),
+                "build",
+                "graph_budgets_warn_only",
+            )
+            .await?
+            .unwrap_or(false);
+        if !warn_only {
+            result_reports.build_errors.errors.extend(exceeded);
+        }
+    }
+
+    let timed_out = build_result.timed_out;
     let mut provider_artifacts = Vec::new();
     for v in build_result.configured.into_values() {
         // We omit skipped targets here.
@@ -345,6 +467,29 @@ async fn process_build_result(
         .await?;
     }
 
+    let attestation_command: Option<String> = ctx
+        .parse_legacy_config_property(cell_resolver.root_cell(), "build", "attestation_command")
+        .await?;
+
+    // Only attest complete builds.
+    if let Some(command) =
+        attestation_command.filter(|_| result_reports.build_errors.errors.is_empty())
+    {
+        let trace_id = server_ctx.events().trace_id();
+        let subjects = attestation_subjects(&provider_artifacts, &artifact_fs)?;
+        let attestation = run_attestation_command(
+            &command,
+            trace_id,
+            subjects,
+            fs,
+            &build_report_dir(trace_id, &artifact_fs, fs),
+        )
+        .await?;
+        if let Some(report) = build_report.as_mut() {
+            report.set_attestation(attestation);
+        }
+    }
+
     let should_create_unhashed_links = ctx
         .parse_legacy_config_property(cell_resolver.root_cell(), "buck2", "create_unhashed_links")
         .await?;
@@ -398,12 +543,13 @@ async fn process_build_result(
         project_root,
         serialized_build_report: serialized_build_report.unwrap_or_default(),
         errors,
+        timed_out,
     })
 }
 
 async fn build_targets(
-    ctx: &DiceComputations,
-    spec: ResolvedPattern<ConfiguredProvidersPatternExtra>,
+    ctx: &DiceTransaction,
+    parsed_patterns: Vec<ParsedPattern<ConfiguredProvidersPatternExtra>>,
     target_resolution_config: TargetResolutionConfig,
     build_providers: Arc<BuildProviders>,
     materialization_context: &MaterializationContext,
@@ -411,37 +557,51 @@ async fn build_targets(
     missing_target_behavior: MissingTargetBehavior,
     skip_incompatible_targets: bool,
     want_configured_graph_size: bool,
+    package_loads: &Semaphore,
+    timeout: Option<Duration>,
 ) -> anyhow::Result<BuildTargetResult> {
     let stream = match target_resolution_config {
         TargetResolutionConfig::Default(global_target_platform) => {
-            let spec = spec.convert_pattern().context(
-                "Cannot build with explicit configurations when universe is not specified",
-            )?;
+            // Packages are built as they are found, rather than after all patterns are resolved.
+            let specs = stream_target_patterns(ctx, parsed_patterns).map(|res| {
+                let (package, spec) = res?;
+                let spec = convert_package_spec(&package, spec).context(
+                    "Cannot build with explicit configurations when universe is not specified",
+                )?;
+                anyhow::Ok((package, spec))
+            });
+            let specs = Box::pin(specs);
             build_targets_with_global_target_platform(
                 ctx,
-                spec,
+                specs,
                 global_target_platform,
                 build_providers,
                 materialization_context,
                 missing_target_behavior,
                 skip_incompatible_targets,
                 want_configured_graph_size,
+                package_loads,
             )
             .left_stream()
         }
-        TargetResolutionConfig::Universe(universe) => build_targets_in_universe(
-            ctx,
-            spec,
-            universe,
-            build_providers,
-            materialization_context,
-            want_configured_graph_size,
-        )
-        .map(BuildEvent::Configured)
-        .right_stream(),
+        TargetResolutionConfig::Universe(universe) => {
+            let cell_resolver = ctx.get_cell_resolver().await?;
+            let spec =
+                resolve_target_patterns(&cell_resolver, &parsed_patterns, &ctx.file_ops()).await?;
+            build_targets_in_universe(
+                ctx,
+                spec,
+                universe,
+                build_providers,
+                materialization_context,
+                want_configured_graph_size,
+            )
+            .map(BuildEvent::Configured)
+            .right_stream()
+        }
     };
 
-    BuildTargetResult::collect_stream(stream, fail_fast).await
+    BuildTargetResult::collect_stream_with_timeout(stream, fail_fast, timeout).await
 }
 
 fn build_targets_in_universe<'a>(
@@ -478,30 +638,41 @@ fn build_targets_in_universe<'a>(
 
 fn build_targets_with_global_target_platform<'a>(
     ctx: &'a DiceComputations,
-    spec: ResolvedPattern<ProvidersPatternExtra>,
+    specs: impl Stream<Item = anyhow::Result<(PackageLabel, PackageSpec<ProvidersPatternExtra>)>>
+    + Unpin
+    + 'a,
     global_target_platform: Option<TargetLabel>,
     build_providers: Arc<BuildProviders>,
     materialization_context: &'a MaterializationContext,
     missing_target_behavior: MissingTargetBehavior,
     skip_incompatible_targets: bool,
     want_configured_graph_size: bool,
+    package_loads: &'a Semaphore,
 ) -> impl Stream<Item = BuildEvent> + Unpin + 'a {
-    futures::stream::iter(spec.specs.into_iter().map(move |(package, spec)| {
-        build_targets_for_spec(
-            ctx,
-            spec,
-            package,
-            global_target_platform.dupe(),
-            build_providers.dupe(),
-            materialization_context,
-            missing_target_behavior,
-            skip_incompatible_targets,
-            want_configured_graph_size,
-        )
-        .boxed()
-        .flatten_stream()
-    }))
-    .flatten_unordered(None)
+    specs
+        .map(move |res| match res {
+            Ok((package, spec)) => build_targets_for_spec(
+                ctx,
+                spec,
+                package,
+                global_target_platform.dupe(),
+                build_providers.dupe(),
+                materialization_context,
+                missing_target_behavior,
+                skip_incompatible_targets,
+                want_configured_graph_size,
+                package_loads,
+            )
+            .boxed()
+            .flatten_stream()
+            .left_stream(),
+            Err(e) => futures::stream::once(futures::future::ready(BuildEvent::OtherError {
+                label: None,
+                err: e.into(),
+            }))
+            .right_stream(),
+        })
+        .flatten_unordered(None)
 }
 
 struct TargetBuildSpec {
@@ -544,13 +715,18 @@ async fn build_targets_for_spec<'a>(
     missing_target_behavior: MissingTargetBehavior,
     skip_incompatible_targets: bool,
     want_configured_graph_size: bool,
+    package_loads: &'a Semaphore,
 ) -> impl Stream<Item = BuildEvent> + 'a {
     let skippable = match spec {
         PackageSpec::Targets(..) => skip_incompatible_targets,
         PackageSpec::All => true,
     };
 
-    let res = match ctx.get_interpreter_results(package.dupe()).await {
+    let res = match package_loads.acquire().await {
+        Ok(_permit) => ctx.get_interpreter_results(package.dupe()).await,
+        Err(e) => Err(e.into()),
+    };
+    let res = match res {
         Ok(res) => res,
         Err(e) => {
             let e: buck2_error::Error = e.into();
@@ -669,3 +845,18 @@ async fn build_target<'a>(
     .map(BuildEvent::Configured)
     .right_stream()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_load_limit() {
+        assert_eq!(
+            DEFAULT_PACKAGE_LOAD_CONCURRENCY,
+            package_load_limit(None).unwrap().available_permits()
+        );
+        assert_eq!(8, package_load_limit(Some(8)).unwrap().available_permits());
+        assert!(package_load_limit(Some(0)).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use anyhow::Context;
+use buck2_build_api::build::BuildProviderType;
+use buck2_build_api::build::BuildTargetResult;
+use buck2_core::base_deferred_key::BaseDeferredKey;
+use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::project::ProjectRoot;
+use dupe::Dupe;
+
+/// Name of the file in buck-out that maps the configured targets built so far to their outputs.
+const OUTPUTS_MANIFEST_FILE: &str = "outputs_manifest.json";
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+struct OutputsManifestEntry {
+    /// The directory the outputs of the target are written to.
+    output_dir: String,
+    /// The default outputs of the target.
+    outputs: BTreeSet<String>,
+    /// The other outputs that were built for the target, e.g. `DefaultInfo.other_outputs`.
+    other_outputs: BTreeSet<String>,
+}
+
+/// Add the outputs of the targets of this build to the outputs manifest, keyed by configured
+/// providers label. Paths are relative to the project root. Entries of targets that are not
+/// part of this build are kept as is, so IDEs can find anything that was built without calling
+/// into buck2.
+///
+/// The file is written to a temporary path first and then renamed, so readers never see a
+/// partially written manifest.
+pub(crate) fn update_outputs_manifest(
+    build_result: &BuildTargetResult,
+    artifact_fs: &ArtifactFs,
+    fs: &ProjectRoot,
+) -> anyhow::Result<()> {
+    let buck_out = artifact_fs.buck_out_path_resolver().root();
+    let buck_out_root = fs.resolve(buck_out);
+    let path = buck_out_root.join(ForwardRelativePath::unchecked_new(OUTPUTS_MANIFEST_FILE));
+    let tmp_path = buck_out_root.join(ForwardRelativePath::unchecked_new(&format!(
+        "{}.tmp",
+        OUTPUTS_MANIFEST_FILE
+    )));
+
+    // A manifest we can't read (e.g. from an older version) is just rewritten.
+    let mut manifest: BTreeMap<String, OutputsManifestEntry> =
+        match fs_util::read_to_string_if_exists(&path)? {
+            Some(contents) => serde_json::from_str(&contents).unwrap_or_default(),
+            None => BTreeMap::new(),
+        };
+
+    for (label, result) in &build_result.configured {
+        // We omit skipped targets here.
+        let Some(result) = result else { continue };
+
+        let output_dir = BaseDeferredKey::TargetLabel(label.target().dupe()).make_hashed_path(
+            buck_out,
+            ForwardRelativePath::unchecked_new("gen"),
+            None,
+            ForwardRelativePath::empty(),
+        );
+        let mut entry = OutputsManifestEntry {
+            output_dir: output_dir.as_str().trim_end_matches('/').to_owned(),
+            ..Default::default()
+        };
+
+        for output in result.outputs.iter().flatten() {
+            let paths = match output.provider_type {
+                BuildProviderType::Default => &mut entry.outputs,
+                BuildProviderType::DefaultOther
+                | BuildProviderType::Run
+                | BuildProviderType::Test => &mut entry.other_outputs,
+            };
+            for (artifact, _value) in output.values.iter() {
+                paths.insert(artifact.resolve_path(artifact_fs)?.to_string());
+            }
+        }
+
+        manifest.insert(label.to_string(), entry);
+    }
+
+    fs_util::create_dir_all(&buck_out_root)?;
+    fs_util::write(&tmp_path, serde_json::to_vec_pretty(&manifest)?)
+        .context("writing outputs manifest")?;
+    fs_util::rename(&tmp_path, &path).context("writing outputs manifest")?;
+    Ok(())
+}
@@ -7,6 +7,8 @@
  * of this source tree.
  */
 
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::HashSet;
 use std::path;
 
@@ -18,6 +20,7 @@ use buck2_core::fs::artifact_path_resolver::ArtifactFs;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::paths::abs_path::AbsPath;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_query::__derive_refs::indexmap::IndexMap;
 use itertools::Itertools;
@@ -57,6 +60,8 @@ pub(crate) fn create_unhashed_outputs(
             Err(_) => {}
         };
     }
+    write_unhashed_outputs_mapping(&unhashed_to_hashed, &buck_out_root, fs)?;
+
     // The IndexMap is used now to determine if and what conflicts exist where multiple hashed artifact locations
     // all want a symlink to the same unhashed artifact location and deal with them accordingly.
     let mut num_unhashed_links_made = 0;
@@ -80,6 +85,44 @@ pub(crate) fn create_unhashed_outputs(
     Ok(num_unhashed_links_made)
 }
 
+/// Name of the file in buck-out that maps the unhashed output paths of the last build to
+/// their hashed locations.
+const UNHASHED_OUTPUTS_MAPPING_FILE: &str = "unhashed_outputs.json";
+
+/// Write the mapping from unhashed paths to all the hashed paths that want them, including the
+/// ones that conflict (and so don't get a symlink), so tools can find the real outputs of each
+/// configuration. Paths are relative to the project root.
+///
+/// The file is written to a temporary path first and then renamed, so readers never see a
+/// partially written mapping.
+fn write_unhashed_outputs_mapping(
+    unhashed_to_hashed: &IndexMap<AbsNormPathBuf, HashSet<AbsNormPathBuf>>,
+    buck_out_root: &AbsNormPathBuf,
+    fs: &ProjectRoot,
+) -> anyhow::Result<()> {
+    let mut mapping = BTreeMap::new();
+    for (unhashed, hashed_set) in unhashed_to_hashed {
+        let hashed = hashed_set
+            .iter()
+            .map(|hashed| Ok(fs.relativize(hashed)?.to_string()))
+            .collect::<anyhow::Result<BTreeSet<_>>>()?;
+        mapping.insert(fs.relativize(unhashed)?.to_string(), hashed);
+    }
+
+    let path = buck_out_root.join(ForwardRelativePath::unchecked_new(
+        UNHASHED_OUTPUTS_MAPPING_FILE,
+    ));
+    let tmp_path = buck_out_root.join(ForwardRelativePath::unchecked_new(&format!(
+        "{}.tmp",
+        UNHASHED_OUTPUTS_MAPPING_FILE
+    )));
+    fs_util::create_dir_all(buck_out_root)?;
+    fs_util::write(&tmp_path, serde_json::to_vec_pretty(&mapping)?)
+        .context("writing unhashed outputs mapping")?;
+    fs_util::rename(&tmp_path, &path).context("writing unhashed outputs mapping")?;
+    Ok(())
+}
+
 fn create_unhashed_link(
     unhashed_path: &AbsNormPathBuf,
     original_path: &AbsNormPathBuf,
@@ -150,6 +193,9 @@ fn iter_reverse_ancestors<'a>(
 
 #[cfg(test)]
 mod test {
+    use buck2_core::fs::project::ProjectRootTemp;
+    use buck2_core::fs::project_rel_path::ProjectRelativePath;
+
     use super::*;
 
     #[test]
@@ -174,4 +220,50 @@ mod test {
         );
         assert_eq!(iter.next(), None);
     }
+
+    #[test]
+    fn test_write_unhashed_outputs_mapping() -> anyhow::Result<()> {
+        let temp = ProjectRootTemp::new()?;
+        let fs = temp.path();
+        let buck_out_root = fs.resolve(ProjectRelativePath::new("buck-out/v2")?);
+        let resolve = |path: &str| fs.resolve(ProjectRelativePath::new(path).unwrap());
+
+        let mut unhashed_to_hashed = IndexMap::new();
+        unhashed_to_hashed.insert(
+            resolve("buck-out/v2/gen/root/foo/out"),
+            HashSet::from([
+                resolve("buck-out/v2/gen/root/aaaa/foo/out"),
+                resolve("buck-out/v2/gen/root/bbbb/foo/out"),
+            ]),
+        );
+        unhashed_to_hashed.insert(
+            resolve("buck-out/v2/gen/root/bar/out"),
+            HashSet::from([resolve("buck-out/v2/gen/root/aaaa/bar/out")]),
+        );
+        write_unhashed_outputs_mapping(&unhashed_to_hashed, &buck_out_root, fs)?;
+
+        let mapping: BTreeMap<String, Vec<String>> = serde_json::from_slice(&fs_util::read(
+            buck_out_root.join(ForwardRelativePath::new(UNHASHED_OUTPUTS_MAPPING_FILE)?),
+        )?)?;
+        assert_eq!(
+            mapping,
+            BTreeMap::from([
+                (
+                    "buck-out/v2/gen/root/bar/out".to_owned(),
+                    vec!["buck-out/v2/gen/root/aaaa/bar/out".to_owned()],
+                ),
+                (
+                    "buck-out/v2/gen/root/foo/out".to_owned(),
+                    vec![
+                        "buck-out/v2/gen/root/aaaa/foo/out".to_owned(),
+                        "buck-out/v2/gen/root/bbbb/foo/out".to_owned(),
+                    ],
+                ),
+            ])
+        );
+        assert!(!fs_util::try_exists(buck_out_root.join(
+            ForwardRelativePath::new(&format!("{}.tmp", UNHASHED_OUTPUTS_MAPPING_FILE))?
+        ))?);
+        Ok(())
+    }
 }
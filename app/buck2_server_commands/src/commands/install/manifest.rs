@@ -0,0 +1,211 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Digests of the files of previous installs, per device, so that an incremental install only
+//! pushes the files which changed since.
+//!
+//! A manifest is only written after a successful install, and is removed when an install fails,
+//! since the files on the device are then unknown. It records the installation reported by the
+//! installer, so that a manifest of an app which was since uninstalled, or of a device which was
+//! wiped, is ignored.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use anyhow::Context;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+use serde::Deserialize;
+use serde::Serialize;
+
+#[derive(Default, Serialize, Deserialize)]
+struct InstalledManifest {
+    /// Identifies the installation of the app on the device, as reported by the installer.
+    #[serde(default)]
+    installation: String,
+    /// Digest of each file on the device, keyed by its name in `InstallInfo`.
+    files: BTreeMap<String, String>,
+}
+
+/// State of an incremental install of one app to one device.
+pub(crate) struct IncrementalInstall {
+    path: AbsNormPathBuf,
+    installation: String,
+    /// Files on the device before this install.
+    previous: BTreeMap<String, String>,
+    /// Files of this install which the installer has on the device.
+    installed: Mutex<BTreeMap<String, String>>,
+}
+
+impl IncrementalInstall {
+    /// Load the manifest of the last install of `install_id` to `device`. A missing or unreadable
+    /// manifest, or one of another `installation`, means every file is pushed. So does an empty
+    /// `installation`, since the manifest cannot then be checked against the device.
+    pub(crate) fn load(
+        manifest_dir: &AbsNormPath,
+        install_id: &str,
+        device: &str,
+        installation: &str,
+    ) -> anyhow::Result<Self> {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(install_id.as_bytes());
+        hasher.update(&[0]);
+        hasher.update(device.as_bytes());
+        let path = manifest_dir.join(ForwardRelativePathBuf::unchecked_new(format!(
+            "{}.json",
+            hasher.finalize().to_hex()
+        )));
+
+        let previous = match fs_util::read_to_string_if_exists(&path)? {
+            Some(contents) => match serde_json::from_str::<InstalledManifest>(&contents) {
+                Ok(manifest)
+                    if !installation.is_empty() && manifest.installation == installation =>
+                {
+                    manifest.files
+                }
+                Ok(_) => BTreeMap::new(),
+                Err(e) => {
+                    tracing::warn!("Ignoring invalid install manifest `{}`: {:#}", path, e);
+                    BTreeMap::new()
+                }
+            },
+            None => BTreeMap::new(),
+        };
+        Ok(Self {
+            path,
+            installation: installation.to_owned(),
+            previous,
+            installed: Mutex::new(BTreeMap::new()),
+        })
+    }
+
+    /// Whether the file was already on the device with this digest before this install.
+    pub(crate) fn is_unchanged(&self, name: &str, digest: &str) -> bool {
+        self.previous.get(name).map(String::as_str) == Some(digest)
+    }
+
+    /// Record that the installer has the file with this digest on the device.
+    pub(crate) fn record(&self, name: &str, digest: &str) {
+        self.installed
+            .lock()
+            .unwrap()
+            .insert(name.to_owned(), digest.to_owned());
+    }
+
+    /// Write the manifest of a successful install, made of the files recorded during it, or
+    /// remove the manifest after a failed one.
+    pub(crate) fn finish(&self, success: bool) -> anyhow::Result<()> {
+        if !success {
+            if fs_util::try_exists(&self.path)? {
+                fs_util::remove_file(&self.path)
+                    .with_context(|| format!("removing install manifest `{}`", self.path))?;
+            }
+            return Ok(());
+        }
+        if let Some(dir) = self.path.parent() {
+            fs_util::create_dir_all(dir)?;
+        }
+        let manifest = InstalledManifest {
+            installation: self.installation.clone(),
+            files: self.installed.lock().unwrap().clone(),
+        };
+        let contents = serde_json::to_string_pretty(&manifest)?;
+        fs_util::write(&self.path, contents)
+            .with_context(|| format!("writing install manifest `{}`", self.path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+
+    use super::IncrementalInstall;
+
+    fn manifest_dir(tempdir: &tempfile::TempDir) -> AbsNormPathBuf {
+        AbsNormPathBuf::try_from(tempdir.path().join("manifests")).unwrap()
+    }
+
+    fn install(
+        dir: &AbsNormPathBuf,
+        installation: &str,
+        files: &[(&str, &str)],
+        success: bool,
+    ) -> Vec<String> {
+        let install = IncrementalInstall::load(dir, "app", "device", installation).unwrap();
+        let unchanged = files
+            .iter()
+            .filter(|(name, digest)| install.is_unchanged(name, digest))
+            .map(|(name, _)| (*name).to_owned())
+            .collect();
+        for (name, digest) in files {
+            install.record(name, digest);
+        }
+        install.finish(success).unwrap();
+        unchanged
+    }
+
+    #[test]
+    fn test_unchanged_files() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = manifest_dir(&tempdir);
+        assert!(install(&dir, "1", &[("a", "1"), ("b", "1")], true).is_empty());
+        assert_eq!(
+            vec!["a".to_owned()],
+            install(&dir, "1", &[("a", "1"), ("b", "2")], true)
+        );
+        assert_eq!(
+            vec!["a".to_owned(), "b".to_owned()],
+            install(&dir, "1", &[("a", "1"), ("b", "2")], true)
+        );
+    }
+
+    #[test]
+    fn test_removed_files_leave_manifest() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = manifest_dir(&tempdir);
+        install(&dir, "1", &[("a", "1"), ("b", "1")], true);
+        install(&dir, "1", &[("a", "1")], true);
+        // `b` was removed from the app, so it is pushed when it comes back.
+        assert_eq!(
+            vec!["a".to_owned()],
+            install(&dir, "1", &[("a", "1"), ("b", "1")], true)
+        );
+    }
+
+    #[test]
+    fn test_failed_install() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = manifest_dir(&tempdir);
+        install(&dir, "1", &[("a", "1")], true);
+        assert_eq!(
+            vec!["a".to_owned()],
+            install(&dir, "1", &[("a", "1"), ("b", "1")], false)
+        );
+        // The files on the device are unknown after a failed install.
+        assert!(install(&dir, "1", &[("a", "1"), ("b", "1")], true).is_empty());
+    }
+
+    #[test]
+    fn test_other_installation() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = manifest_dir(&tempdir);
+        install(&dir, "1", &[("a", "1")], true);
+        // The app was reinstalled, or the device wiped.
+        assert!(install(&dir, "2", &[("a", "1")], true).is_empty());
+        assert_eq!(
+            vec!["a".to_owned()],
+            install(&dir, "2", &[("a", "1")], true)
+        );
+        // The installer cannot identify the installation.
+        assert!(install(&dir, "", &[("a", "1")], true).is_empty());
+        assert!(install(&dir, "", &[("a", "1")], true).is_empty());
+    }
+}
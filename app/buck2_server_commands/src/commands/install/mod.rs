@@ -14,7 +14,9 @@ use std::hash::Hasher;
 use std::net::Ipv4Addr;
 use std::net::SocketAddr;
 use std::net::TcpListener;
+use std::process::Child;
 use std::process::Stdio;
+use std::time::Duration;
 
 use anyhow::Context;
 use async_trait::async_trait;
@@ -52,15 +54,20 @@ use buck2_core::provider::label::ProvidersName;
 use buck2_core::target::name::TargetName;
 use buck2_data::InstallEventInfoEnd;
 use buck2_data::InstallEventInfoStart;
+use buck2_events::dispatch::get_dispatcher;
 use buck2_events::dispatch::span_async;
+use buck2_events::dispatch::EventDispatcher;
 use buck2_execute::artifact::artifact_dyn::ArtifactDyn;
 use buck2_execute::artifact::fs::ExecutorFs;
 use buck2_execute::artifact_value::ArtifactValue;
 use buck2_execute::directory::ActionDirectoryMember;
 use buck2_install_proto::installer_client::InstallerClient;
+use buck2_install_proto::ErrorDetail;
 use buck2_install_proto::FileReadyRequest;
 use buck2_install_proto::InstallInfoRequest;
+use buck2_install_proto::LogLevel;
 use buck2_install_proto::ShutdownRequest;
+use buck2_install_proto::StreamLogsRequest;
 use buck2_node::nodes::frontend::TargetGraphCalculation;
 use buck2_node::target_calculation::ConfiguredTargetCalculation;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
@@ -85,6 +92,10 @@ use starlark_map::small_map::SmallMap;
 use tokio::sync::mpsc;
 use tonic::transport::Channel;
 
+use crate::commands::install::manifest::IncrementalInstall;
+
+mod manifest;
+
 #[derive(Debug, buck2_error::Error)]
 pub enum InstallError {
     #[error("Target {1}:{0} cannot be installed as it does not expose an InstallInfo provider")]
@@ -110,6 +121,15 @@ pub enum InstallError {
     #[error("Communication with the installer failed with `{err}`")]
     InstallerCommunicationFailure { err: String },
 
+    #[error(
+        "Installer `{installer}` exited with {status} before buck2 could connect to it. More details can be found at `{installer_log}`"
+    )]
+    InstallerExited {
+        installer: String,
+        status: std::process::ExitStatus,
+        installer_log: String,
+    },
+
     #[error("Incorrect seconds/nanos argument")]
     NativeDateTime,
 }
@@ -276,6 +296,7 @@ async fn install(
                 installer_label,
                 installer_run_args,
                 request.installer_debug,
+                request.incremental,
             )
             .await
         };
@@ -317,6 +338,7 @@ async fn handle_install_request<'a>(
     installer_label: &ConfiguredProvidersLabel,
     initial_installer_run_args: &[String],
     installer_debug: bool,
+    incremental: bool,
 ) -> anyhow::Result<()> {
     let (files_tx, files_rx) = mpsc::unbounded_channel();
     let build_files = async move {
@@ -349,7 +371,7 @@ async fn handle_install_request<'a>(
             installer_log_filename.to_owned(),
         ]);
 
-        build_launch_installer(
+        let mut installer = build_launch_installer(
             ctx,
             materializations,
             installer_label,
@@ -358,27 +380,86 @@ async fn handle_install_request<'a>(
         )
         .await?;
 
-        let client: InstallerClient<Channel> = connect_to_installer(tcp_port).await?;
-        let artifact_fs = ctx.get_artifact_fs().await?;
+        let client: InstallerClient<Channel> = match connect_to_installer(tcp_port).await {
+            Ok(client) => client,
+            Err(e) => {
+                // Report why the installer is not there if it already died.
+                if let Ok(Some(status)) = installer.try_wait() {
+                    return Err(InstallError::InstallerExited {
+                        installer: installer_label.to_string(),
+                        status,
+                        installer_log: installer_log_filename,
+                    }
+                    .into());
+                }
+                return Err(e);
+            }
+        };
+        let mut log_forwarder = tokio::spawn(forward_installer_logs(
+            client.clone(),
+            get_dispatcher(),
+            installer_label.to_string(),
+        ));
 
-        for (install_id, install_files) in install_files_slice {
-            send_install_info(client.clone(), install_id, install_files, &artifact_fs).await?;
-        }
+        let result = async {
+            let artifact_fs = ctx.get_artifact_fs().await?;
 
-        let send_files_result = tokio_stream::wrappers::UnboundedReceiverStream::new(files_rx)
-            .map(anyhow::Ok)
-            .try_for_each_concurrent(None, |file| {
-                send_file(
-                    file,
-                    &artifact_fs,
+            let manifest_dir = install_log_dir.join(ForwardRelativePathBuf::unchecked_new(
+                "manifests".to_owned(),
+            ));
+            let mut incremental_installs = HashMap::new();
+            for (install_id, install_files) in install_files_slice {
+                let response = send_install_info(
                     client.clone(),
-                    installer_log_filename.to_owned(),
+                    install_id,
+                    install_files,
+                    &artifact_fs,
+                    incremental,
                 )
-            })
-            .await;
-        send_shutdown_command(client.clone()).await?;
-        send_files_result.context("Failed to send artifacts to installer")?;
-        anyhow::Ok(())
+                .await?;
+                if incremental && response.incremental {
+                    incremental_installs.insert(
+                        (*install_id).to_owned(),
+                        IncrementalInstall::load(
+                            &manifest_dir,
+                            install_id,
+                            &response.device,
+                            &response.installation,
+                        )?,
+                    );
+                }
+            }
+
+            let send_files_result = tokio_stream::wrappers::UnboundedReceiverStream::new(files_rx)
+                .map(anyhow::Ok)
+                .try_for_each_concurrent(None, |file| {
+                    let incremental_install = incremental_installs.get(&file.install_id);
+                    send_file(
+                        file,
+                        &artifact_fs,
+                        client.clone(),
+                        installer_log_filename.to_owned(),
+                        incremental_install,
+                    )
+                })
+                .await;
+            let shutdown_result = send_shutdown_command(client.clone()).await;
+            let success = send_files_result.is_ok() && shutdown_result.is_ok();
+            let manifest_result = incremental_installs
+                .values()
+                .try_for_each(|incremental_install| incremental_install.finish(success));
+            send_files_result.context("Failed to send artifacts to installer")?;
+            shutdown_result?;
+            manifest_result?;
+            anyhow::Ok(())
+        }
+        .await;
+
+        // The last logs of the installer usually explain its failure, so let them arrive before
+        // the error is reported. The stream ends when the installer shuts down.
+        let _ignored = tokio::time::timeout(LOG_DRAIN_TIMEOUT, &mut log_forwarder).await;
+        log_forwarder.abort();
+        result
     };
     try_join(build_installer_and_connect, build_files).await?;
     anyhow::Ok(())
@@ -389,7 +470,8 @@ async fn send_install_info(
     install_id: &str,
     install_files: &SmallMap<&str, Artifact>,
     artifact_fs: &ArtifactFs,
-) -> anyhow::Result<()> {
+    incremental: bool,
+) -> anyhow::Result<buck2_install_proto::InstallResponse> {
     let mut files_map = HashMap::new();
     for (file_name, artifact) in install_files {
         let artifact_path = &artifact_fs
@@ -403,6 +485,7 @@ async fn send_install_info(
     let install_info_request = tonic::Request::new(InstallInfoRequest {
         install_id: install_id.to_owned(),
         files: files_map,
+        incremental,
     });
 
     let response_result = client.install(install_info_request).await;
@@ -417,6 +500,16 @@ async fn send_install_info(
         }
     };
 
+    if let Some(error_detail) = &install_info_response.error_detail {
+        // The installer's error is more useful than a failure to shut it down.
+        let _ignored = send_shutdown_command(client.clone()).await;
+        return Err(InstallError::InternalInstallerFailure {
+            install_id: install_id.to_owned(),
+            err: format_error_detail(error_detail),
+        }
+        .into());
+    }
+
     if install_info_response.install_id != install_id {
         send_shutdown_command(client.clone()).await?;
         return Err(anyhow::anyhow!(
@@ -426,7 +519,7 @@ async fn send_install_info(
         ));
     }
 
-    Ok(())
+    Ok(install_info_response)
 }
 
 async fn send_shutdown_command(mut client: InstallerClient<Channel>) -> anyhow::Result<()> {
@@ -443,13 +536,64 @@ async fn send_shutdown_command(mut client: InstallerClient<Channel>) -> anyhow::
     };
 }
 
+/// How long to wait for the remaining logs of the installer once buck2 is done with it.
+const LOG_DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Forward the logs streamed by the installer to the event log, and so to the console, until the
+/// installer closes the stream.
+async fn forward_installer_logs(
+    mut client: InstallerClient<Channel>,
+    dispatcher: EventDispatcher,
+    installer: String,
+) {
+    let mut logs = match client
+        .stream_logs(tonic::Request::new(StreamLogsRequest {}))
+        .await
+    {
+        Ok(r) => r.into_inner(),
+        // Installers are not required to stream their logs.
+        Err(_) => return,
+    };
+    while let Ok(Some(log)) = logs.message().await {
+        let level = match log.level() {
+            LogLevel::Info => buck2_data::installer_log::Level::Info,
+            LogLevel::Debug => buck2_data::installer_log::Level::Debug,
+            LogLevel::Warn => buck2_data::installer_log::Level::Warn,
+            LogLevel::Error => buck2_data::installer_log::Level::Error,
+        };
+        dispatcher.instant_event(buck2_data::InstallerLog {
+            installer: installer.clone(),
+            level: level as i32,
+            message: log.message,
+            install_id: log.install_id,
+        });
+    }
+}
+
+/// Render an error reported by the installer with whatever structure the installer provided.
+fn format_error_detail(error_detail: &ErrorDetail) -> String {
+    let mut s = String::new();
+    if !error_detail.category.is_empty() {
+        s.push_str(&format!("[{}] ", error_detail.category));
+    }
+    s.push_str(&error_detail.message);
+    if !error_detail.device.is_empty() {
+        s.push_str(&format!(" (device `{}`)", error_detail.device));
+    }
+    if !error_detail.details.is_empty() {
+        s.push('\n');
+        s.push_str(&error_detail.details);
+    }
+    s
+}
+
 async fn build_launch_installer<'a>(
     ctx: &'a DiceComputations,
     materializations: &'a MaterializationContext,
     providers_label: &ConfiguredProvidersLabel,
     installer_run_args: &[String],
     installer_log_console: bool,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<Child> {
     let frozen_providers = ctx
         .get_providers(providers_label)
         .await?
@@ -483,14 +627,14 @@ async fn build_launch_installer<'a>(
         }))
         .await
         .context("Failed to build installer")?;
-        background_command(&run_args[0])
+        let installer = background_command(&run_args[0])
             .args(&run_args[1..])
             .args(installer_run_args)
             .stderr(get_stdio(installer_log_console)?)
             .spawn()
             .context("Failed to spawn installer")?;
 
-        Ok(())
+        Ok(installer)
     } else {
         Err(InstallError::NoRunInfoProvider(providers_label.target().name().to_owned()).into())
     }
@@ -551,8 +695,6 @@ async fn build_files(
 }
 
 async fn connect_to_installer(tcp_port: u16) -> anyhow::Result<InstallerClient<Channel>> {
-    use std::time::Duration;
-
     use buck2_common::client_utils::retrying;
 
     // These numbers might need to be configured based on the installer
@@ -585,6 +727,7 @@ async fn send_file(
     artifact_fs: &ArtifactFs,
     mut client: InstallerClient<Channel>,
     install_log: String,
+    incremental_install: Option<&IncrementalInstall>,
 ) -> anyhow::Result<()> {
     let install_id = file.install_id;
     let name = file.name;
@@ -617,21 +760,25 @@ async fn send_file(
         Data::Symlink(sym) => (format!("re-symlink:{}", sym), 0, "".to_owned()), // Messy :(
     };
 
+    let unchanged = incremental_install.map_or(false, |i| i.is_unchanged(&name, &digest));
+
     let path = &artifact_fs
         .fs()
         .resolve(&artifact.resolve_path(artifact_fs)?);
     let request = tonic::Request::new(FileReadyRequest {
         install_id: install_id.to_owned(),
         name: name.to_owned(),
-        digest,
+        digest: digest.clone(),
         digest_algorithm,
         size,
         path: path.to_string(),
+        unchanged,
     });
 
     let start = InstallEventInfoStart {
         artifact_name: name.to_owned(),
         file_path: path.to_string(),
+        unchanged,
     };
     let end = InstallEventInfoEnd {};
     span_async(start, async {
@@ -668,18 +815,57 @@ async fn send_file(
             .into());
         }
 
-        if let Some(error_detail) = response.error_detail {
+        if let Some(error_detail) = &response.error_detail {
             outcome = Err(InstallError::ProcessingFileReadyFailure {
                 install_id: install_id.to_owned(),
                 artifact: name.to_owned(),
                 path: path.to_owned(),
-                err: error_detail.message,
+                err: format_error_detail(error_detail),
                 installer_log: install_log.to_owned(),
             }
             .into());
         }
+
+        if let (Ok(()), Some(incremental_install)) = (&outcome, incremental_install) {
+            incremental_install.record(&name, &digest);
+        }
         (outcome, end)
     })
     .await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use buck2_install_proto::ErrorDetail;
+
+    use crate::commands::install::format_error_detail;
+
+    #[test]
+    fn test_format_error_detail() {
+        assert_eq!(
+            "Install failed",
+            format_error_detail(&ErrorDetail {
+                message: "Install failed".to_owned(),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            "[insufficient_storage] Install failed (device `emulator-5554`)\nadb: 0 bytes free",
+            format_error_detail(&ErrorDetail {
+                message: "Install failed".to_owned(),
+                category: "insufficient_storage".to_owned(),
+                device: "emulator-5554".to_owned(),
+                details: "adb: 0 bytes free".to_owned(),
+            })
+        );
+        assert_eq!(
+            "[device_not_found] No device",
+            format_error_detail(&ErrorDetail {
+                message: "No device".to_owned(),
+                category: "device_not_found".to_owned(),
+                ..Default::default()
+            })
+        );
+    }
+}
@@ -7,13 +7,22 @@
  * of this source tree.
  */
 
+use std::collections::BTreeMap;
+use std::collections::HashMap;
 use std::io::Write;
 
 use anyhow::Context;
 use async_trait::async_trait;
+use buck2_build_api::actions::query::ActionQueryNode;
 use buck2_build_api::query::oneshot::QUERY_FRONTEND;
+use buck2_cli_proto::AqueryHistoricalCost;
 use buck2_common::dice::cells::HasCellResolver;
+use buck2_data::ToProtoMessage;
+use buck2_event_observer::display::display_action_key;
+use buck2_event_observer::display::TargetDisplayOptions;
+use buck2_query::query::environment::LabeledNode;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
+use buck2_query::query::syntax::simple::eval::values::QueryEvaluationValue;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
 use buck2_server_ctx::pattern::target_platform_from_client_context;
@@ -23,6 +32,7 @@ use dice::DiceTransaction;
 
 use crate::commands::query::printer::QueryResultPrinter;
 use crate::commands::query::printer::ShouldPrintProviders;
+use crate::commands::query::printer::TargetAnnotations;
 
 pub(crate) async fn aquery_command(
     ctx: &dyn ServerCommandContextTrait,
@@ -75,12 +85,14 @@ async fn aquery(
         &cell_resolver,
         &request.output_attributes,
         request.unstable_output_format,
+        request.graph_options.as_ref(),
     )?;
 
     let buck2_cli_proto::AqueryRequest {
         query,
         query_args,
         context,
+        historical_costs,
         ..
     } = request;
 
@@ -101,6 +113,27 @@ async fn aquery(
         )
         .await?;
 
+    let output_configuration = if historical_costs.is_empty() {
+        output_configuration
+    } else {
+        let values: Vec<&QueryEvaluationValue<ActionQueryNode>> = match &query_result {
+            QueryEvaluationResult::Single(value) => vec![value],
+            QueryEvaluationResult::Multiple(results) => {
+                results.0.values().filter_map(|r| r.as_ref().ok()).collect()
+            }
+        };
+        output_configuration.with_annotations(historical_cost_annotations(
+            historical_costs,
+            values
+                .into_iter()
+                .filter_map(|value| match value {
+                    QueryEvaluationValue::TargetSet(targets) => Some(targets.iter()),
+                    QueryEvaluationValue::FileSet(..) => None,
+                })
+                .flatten(),
+        )?)
+    };
+
     match query_result {
         QueryEvaluationResult::Single(targets) => {
             output_configuration
@@ -115,3 +148,57 @@ async fn aquery(
     };
     Ok(buck2_cli_proto::AqueryResponse {})
 }
+
+/// Match the actions against the costs recorded in event logs by the client, which identifies
+/// actions by their owner, category and identifier.
+fn historical_cost_annotations<'a>(
+    historical_costs: &[AqueryHistoricalCost],
+    actions: impl Iterator<Item = &'a ActionQueryNode>,
+) -> anyhow::Result<TargetAnnotations> {
+    let costs: HashMap<(&str, &str, &str), &AqueryHistoricalCost> = historical_costs
+        .iter()
+        .map(|cost| {
+            (
+                (
+                    cost.owner.as_str(),
+                    cost.category.as_str(),
+                    cost.identifier.as_str(),
+                ),
+                cost,
+            )
+        })
+        .collect();
+
+    let mut annotations = TargetAnnotations::new();
+    for node in actions {
+        let Some(action) = node.action() else {
+            continue;
+        };
+        let owner = display_action_key(&action.key().as_proto(), TargetDisplayOptions::for_log())?;
+        let Some(cost) = costs.get(&(
+            owner.as_str(),
+            action.category().as_str(),
+            action.identifier().unwrap_or(""),
+        )) else {
+            continue;
+        };
+        annotations.insert(
+            node.node_ref().to_string(),
+            BTreeMap::from([
+                (
+                    "buck.estimated_duration_ms".to_owned(),
+                    (cost.duration_us / 1000).to_string(),
+                ),
+                (
+                    "buck.last_execution_kind".to_owned(),
+                    cost.last_execution_kind.clone(),
+                ),
+                (
+                    "buck.historical_samples".to_owned(),
+                    cost.samples.to_string(),
+                ),
+            ]),
+        );
+    }
+    Ok(annotations)
+}
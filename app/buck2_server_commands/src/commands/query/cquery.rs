@@ -95,6 +95,7 @@ async fn cquery(
         &cell_resolver,
         &request.output_attributes,
         request.unstable_output_format,
+        request.graph_options.as_ref(),
     )?;
 
     let CqueryRequest {
@@ -18,4 +18,6 @@ enum QueryCommandError {
         "query result was a set of files and one or more --output-attribute was requested, but files have not attributes"
     )]
     FileSetHasNoAttributes,
+    #[error("query result was a set of files, which cannot be printed as a `{0}` graph")]
+    FileSetHasNoGraph(&'static str),
 }
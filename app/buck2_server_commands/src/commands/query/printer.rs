@@ -9,6 +9,8 @@
 
 #![allow(clippy::drop_non_drop)] // FIXME?
 
+use std::collections::BTreeMap;
+use std::collections::HashMap;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::io::Write;
@@ -17,6 +19,8 @@ use async_trait::async_trait;
 use buck2_build_api::actions::query::ActionQueryNode;
 use buck2_build_api::actions::query::PRINT_ACTION_NODE;
 use buck2_build_api::interpreter::rule_defs::provider::collection::FrozenProviderCollectionValue;
+use buck2_cli_proto::query_graph_options;
+use buck2_cli_proto::QueryGraphOptions;
 use buck2_cli_proto::QueryOutputFormat;
 use buck2_core::cells::CellResolver;
 use buck2_core::configuration::compatibility::MaybeCompatible;
@@ -30,6 +34,7 @@ use buck2_util::indent::indent;
 use dupe::Clone_;
 use dupe::Copy_;
 use dupe::Dupe_;
+use futures::StreamExt;
 use gazebo::variants::UnpackVariants;
 use indent_write::fmt::IndentWriter;
 use regex::RegexSet;
@@ -39,9 +44,15 @@ use serde::Serialize;
 use serde::Serializer;
 
 use crate::commands::query::QueryCommandError;
+use crate::dot::targets::limit_depth;
+use crate::dot::targets::DotCluster;
 use crate::dot::targets::DotTargetGraph;
 use crate::dot::Dot;
 use crate::dot::DotCompact;
+use crate::dot::Mermaid;
+
+/// Number of targets computed ahead of the one being written with jsonl output.
+const JSONL_TARGETS_AHEAD: usize = 64;
 
 #[derive(Copy_, Dupe_, Clone_, UnpackVariants)]
 pub enum ShouldPrintProviders<'a, T> {
@@ -55,11 +66,18 @@ pub trait ProviderLookUp<T: QueryTarget>: Send + Sync {
     -> anyhow::Result<MaybeCompatible<FrozenProviderCollectionValue>>;
 }
 
+/// Extra values printed alongside targets, keyed by target label. These are printed
+/// whether or not attributes are requested.
+pub type TargetAnnotations = HashMap<String, BTreeMap<String, String>>;
+
 #[derive(Debug)]
 pub struct QueryResultPrinter<'a> {
     resolver: &'a CellResolver,
     attributes: Option<RegexSet>,
     output_format: QueryOutputFormat,
+    graph_cluster: Option<DotCluster>,
+    graph_max_depth: Option<u32>,
+    annotations: TargetAnnotations,
 }
 
 struct TargetSetJsonPrinter<'a, T: QueryTarget> {
@@ -72,12 +90,20 @@ impl<'a, T: QueryTarget> TargetSetJsonPrinter<'a, T> {
         target_call_stacks: bool,
         print_providers: ShouldPrintProviders<'a, T>,
         attributes: &'a Option<RegexSet>,
+        annotations: &'a TargetAnnotations,
         targets: &'a TargetSet<T>,
     ) -> anyhow::Result<TargetSetJsonPrinter<'a, T>> {
         Ok(TargetSetJsonPrinter {
-            value: printable_targets(targets, print_providers, attributes, target_call_stacks)
-                .await?,
+            value: printable_targets(
+                targets,
+                print_providers,
+                attributes,
+                annotations,
+                target_call_stacks,
+            )
+            .await?,
             is_complex: attributes.is_some()
+                || !annotations.is_empty()
                 || target_call_stacks
                 || print_providers.unpack_yes().is_some(),
         })
@@ -87,6 +113,7 @@ impl<'a, T: QueryTarget> TargetSetJsonPrinter<'a, T> {
 struct PrintableQueryTarget<'a, T: QueryTarget> {
     value: &'a T,
     attributes: &'a Option<RegexSet>,
+    annotations: Option<&'a BTreeMap<String, String>>,
     providers: Option<FrozenProviderCollectionValue>,
     target_call_stacks: bool,
 }
@@ -101,6 +128,17 @@ impl<'a, T: QueryTarget> Display for PrintableQueryTarget<'a, T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.value.node_ref())?;
 
+        if let Some(annotations) = self.annotations {
+            write!(f, " [")?;
+            for (i, (name, value)) in annotations.iter().enumerate() {
+                if i != 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}={}", name, value)?;
+            }
+            write!(f, "]")?;
+        }
+
         if self.target_call_stacks || self.providers.is_some() {
             writeln!(f)?;
         }
@@ -169,6 +207,12 @@ impl<'a, T: QueryTarget> Serialize for PrintableQueryTarget<'a, T> {
             map.serialize_entry("buck.target_call_stack", &self.value.call_stack())?;
         }
 
+        if let Some(annotations) = self.annotations {
+            for (name, value) in annotations {
+                map.serialize_entry(name, value)?;
+            }
+        }
+
         if let Some(providers) = &self.providers {
             map.serialize_entry("buck.providers", providers)?;
         }
@@ -220,13 +264,26 @@ impl<'a> QueryResultPrinter<'a> {
         resolver: &'a CellResolver,
         attributes: &[String],
         output_format: i32,
+        graph_options: Option<&QueryGraphOptions>,
     ) -> anyhow::Result<Self> {
-        Self::from_options(
-            resolver,
-            attributes,
-            QueryOutputFormat::from_i32(output_format)
-                .expect("cli should send a valid output_format enum"),
-        )
+        let graph_options = graph_options.cloned().unwrap_or_default();
+        let graph_cluster = match query_graph_options::Cluster::from_i32(graph_options.cluster)
+            .expect("cli should send a valid cluster enum")
+        {
+            query_graph_options::Cluster::NoCluster => None,
+            query_graph_options::Cluster::Package => Some(DotCluster::Package),
+            query_graph_options::Cluster::RuleType => Some(DotCluster::RuleType),
+        };
+        Ok(Self {
+            graph_cluster,
+            graph_max_depth: graph_options.max_depth,
+            ..Self::from_options(
+                resolver,
+                attributes,
+                QueryOutputFormat::from_i32(output_format)
+                    .expect("cli should send a valid output_format enum"),
+            )?
+        })
     }
 
     pub fn from_options(
@@ -250,9 +307,31 @@ impl<'a> QueryResultPrinter<'a> {
             resolver,
             attributes,
             output_format,
+            graph_cluster: None,
+            graph_max_depth: None,
+            annotations: TargetAnnotations::new(),
         })
     }
 
+    /// Print the given annotations alongside the targets.
+    pub fn with_annotations(self, annotations: TargetAnnotations) -> Self {
+        Self {
+            annotations,
+            ..self
+        }
+    }
+
+    fn dot_graph<T: QueryTarget>(&self, targets: TargetSet<T>) -> DotTargetGraph<T> {
+        DotTargetGraph {
+            targets: match self.graph_max_depth {
+                Some(max_depth) => limit_depth(targets, max_depth),
+                None => targets,
+            },
+            attributes: self.attributes.clone(),
+            cluster: self.graph_cluster,
+        }
+    }
+
     pub async fn print_multi_output<'b, T: QueryTarget, W: std::io::Write>(
         &self,
         mut output: W,
@@ -280,6 +359,7 @@ impl<'a> QueryResultPrinter<'a> {
                                     target_call_stacks,
                                     print_providers,
                                     &self.attributes,
+                                    &self.annotations,
                                     &targets,
                                 )
                                 .await?,
@@ -329,9 +409,14 @@ impl<'a> QueryResultPrinter<'a> {
         match result {
             QueryEvaluationValue::TargetSet(targets) => match self.output_format {
                 QueryOutputFormat::Default => {
-                    for target in
-                        printable_targets(&targets, print_providers, &self.attributes, call_stack)
-                            .await?
+                    for target in printable_targets(
+                        &targets,
+                        print_providers,
+                        &self.attributes,
+                        &self.annotations,
+                        call_stack,
+                    )
+                    .await?
                     {
                         writeln!(&mut output, "{}", target)?;
                     }
@@ -342,6 +427,7 @@ impl<'a> QueryResultPrinter<'a> {
                         call_stack,
                         print_providers,
                         &self.attributes,
+                        &self.annotations,
                         &targets,
                     )
                     .await?
@@ -351,22 +437,45 @@ impl<'a> QueryResultPrinter<'a> {
                     writeln!(&mut output)?
                 }
                 QueryOutputFormat::Dot => {
-                    Dot::render(
-                        &DotTargetGraph {
-                            targets,
-                            attributes: self.attributes.clone(),
-                        },
-                        &mut output,
-                    )?;
+                    Dot::render(&self.dot_graph(targets), &mut output)?;
                 }
                 QueryOutputFormat::DotCompact => {
-                    DotCompact::render(
-                        &DotTargetGraph {
-                            targets,
-                            attributes: self.attributes.clone(),
-                        },
-                        &mut output,
-                    )?;
+                    DotCompact::render(&self.dot_graph(targets), &mut output)?;
+                }
+                QueryOutputFormat::Mermaid => {
+                    Mermaid::render(&self.dot_graph(targets), &mut output)?;
+                }
+                QueryOutputFormat::Jsonl => {
+                    let is_complex = self.attributes.is_some()
+                        || !self.annotations.is_empty()
+                        || call_stack
+                        || print_providers.unpack_yes().is_some();
+                    // Write each target as soon as it is ready (providers may need analysis),
+                    // keeping the output order deterministic. Only a window of targets is
+                    // computed ahead of the one being written, so memory does not grow with
+                    // the size of the result.
+                    let mut printables = futures::stream::iter(targets.iter().map(|t| {
+                        printable_target(
+                            t,
+                            print_providers,
+                            &self.attributes,
+                            &self.annotations,
+                            call_stack,
+                        )
+                    }))
+                    .buffered(JSONL_TARGETS_AHEAD);
+                    while let Some(target) = printables.next().await {
+                        let target = target?;
+                        if is_complex {
+                            let mut line = BTreeMap::new();
+                            line.insert(target.label(), &target);
+                            serde_json::to_writer(&mut output, &line)?;
+                        } else {
+                            serde_json::to_writer(&mut output, &target.label())?;
+                        }
+                        writeln!(&mut output)?;
+                        output.flush()?;
+                    }
                 }
             },
             QueryEvaluationValue::FileSet(files) => {
@@ -400,6 +509,19 @@ impl<'a> QueryResultPrinter<'a> {
                     QueryOutputFormat::DotCompact => {
                         unimplemented!("dot_compact output for files not implemented yet")
                     }
+                    QueryOutputFormat::Mermaid => {
+                        return Err(QueryCommandError::FileSetHasNoGraph("mermaid").into());
+                    }
+                    QueryOutputFormat::Jsonl => {
+                        for file in files.iter() {
+                            serde_json::to_writer(
+                                &mut output,
+                                &self.resolver.resolve_path(file.as_ref())?.to_string(),
+                            )?;
+                            writeln!(&mut output)?;
+                            output.flush()?;
+                        }
+                    }
                 }
             }
         }
@@ -408,27 +530,46 @@ impl<'a> QueryResultPrinter<'a> {
     }
 }
 
+async fn printable_target<'a, T: QueryTarget>(
+    target: &'a T,
+    print_providers: ShouldPrintProviders<'a, T>,
+    attributes: &'a Option<RegexSet>,
+    annotations: &'a TargetAnnotations,
+    target_call_stacks: bool,
+) -> anyhow::Result<PrintableQueryTarget<'a, T>> {
+    Ok(PrintableQueryTarget {
+        value: target,
+        attributes,
+        annotations: if annotations.is_empty() {
+            None
+        } else {
+            annotations.get(&target.node_ref().to_string())
+        },
+        target_call_stacks,
+        providers: match print_providers {
+            ShouldPrintProviders::No => None,
+            ShouldPrintProviders::Yes(lookup) => {
+                Some(lookup.lookup(target).await?.require_compatible()?)
+            }
+        },
+    })
+}
+
 async fn printable_targets<'a, T: QueryTarget>(
     targets: &'a TargetSet<T>,
     print_providers: ShouldPrintProviders<'a, T>,
     attributes: &'a Option<RegexSet>,
+    annotations: &'a TargetAnnotations,
     target_call_stacks: bool,
 ) -> anyhow::Result<Vec<PrintableQueryTarget<'a, T>>> {
     futures::future::join_all(targets.iter().map(|t| {
-        let print_providers = &print_providers;
-        async move {
-            Ok(PrintableQueryTarget {
-                value: t,
-                attributes,
-                target_call_stacks,
-                providers: match print_providers {
-                    ShouldPrintProviders::No => None,
-                    ShouldPrintProviders::Yes(lookup) => {
-                        Some(lookup.lookup(t).await?.require_compatible()?)
-                    }
-                },
-            })
-        }
+        printable_target(
+            t,
+            print_providers,
+            attributes,
+            annotations,
+            target_call_stacks,
+        )
     }))
     .await
     .into_iter()
@@ -453,6 +594,7 @@ async fn print_action_node(
         cell_resolver,
         output_attributes,
         unstable_output_format,
+        None,
     )?;
 
     let mut result = TargetSet::new();
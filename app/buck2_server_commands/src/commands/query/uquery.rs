@@ -76,6 +76,7 @@ async fn uquery(
         &cell_resolver,
         &request.output_attributes,
         request.unstable_output_format,
+        request.graph_options.as_ref(),
     )?;
 
     let UqueryRequest {
@@ -145,6 +145,7 @@ pub(crate) async fn targets_batch(
                             node,
                             target_hash,
                             super_package: res.super_package(),
+                            configured: None,
                         },
                         &mut buffer,
                     )
@@ -152,13 +153,18 @@ pub(crate) async fn targets_batch(
             }
             Err(e) => {
                 stats.errors += 1;
+                let mut stdout = String::new();
                 let mut stderr = String::new();
+                formatter.package_error(package.dupe(), &e.dupe().into(), &mut stdout, &mut stderr);
 
-                if needs_separator {
-                    formatter.separator(&mut buffer);
+                // Some formats report errors separately, and write nothing here.
+                if !stdout.is_empty() {
+                    if needs_separator {
+                        formatter.separator(&mut buffer);
+                    }
+                    needs_separator = true;
+                    buffer.push_str(&stdout);
                 }
-                needs_separator = true;
-                formatter.package_error(package.dupe(), &e.dupe().into(), &mut buffer, &mut stderr);
 
                 server_ctx.stderr()?.write_all(stderr.as_bytes())?;
 
@@ -0,0 +1,362 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Manifests of `buck2 targets --streaming` exports, so that indexers can only export the
+//! packages which changed since a previous export.
+//!
+//! A manifest maps each exported package to two fingerprints: one of the files the package is
+//! loaded from, and one of its targets. A package whose files did not change is not loaded at
+//! all. The file fingerprint is made from the digests of the file state that the file watcher
+//! keeps up to date, so checking it only reads files which changed. A package which is loaded
+//! is only output if its targets changed. Both fingerprints are computed from contents rather
+//! than from file change events, so a manifest can be compared with exports from other daemons
+//! or other checkouts.
+
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Write;
+use std::sync::Mutex;
+
+use anyhow::Context;
+use buck2_common::dice::file_ops::HasFileOps;
+use buck2_common::file_ops::RawPathMetadata;
+use buck2_common::legacy_configs::dice::HasLegacyConfigs;
+use buck2_common::package_listing::dice::HasPackageListingResolver;
+use buck2_core::cells::build_file_cell::BuildFileCell;
+use buck2_core::cells::cell_path::CellPath;
+use buck2_core::package::PackageLabel;
+use buck2_interpreter::file_loader::LoadedModule;
+use buck2_interpreter::load_module::InterpreterCalculation;
+use buck2_interpreter::load_module::INTERPRETER_CALCULATION_IMPL;
+use buck2_interpreter::paths::package::PackageFilePath;
+use buck2_node::nodes::unconfigured::TargetNode;
+use dice::DiceComputations;
+use dupe::Dupe;
+
+use crate::target_hash::TargetHashes;
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+struct ExportManifest {
+    /// Fingerprint of the buckconfigs of all cells. Packages are only skipped if it did not
+    /// change, since configs can change the targets of any package.
+    config: String,
+    packages: BTreeMap<String, PackageFingerprints>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+struct PackageFingerprints {
+    /// Fingerprint of the files the package is loaded from.
+    inputs: String,
+    /// Fingerprint of the targets of the package.
+    targets: String,
+}
+
+/// Tracks the packages of an export, against the manifest of a previous export.
+pub(crate) struct IncrementalExport {
+    previous: ExportManifest,
+    current: Mutex<ExportManifest>,
+}
+
+impl IncrementalExport {
+    /// Start an export with the given config fingerprint, compared with the manifest at
+    /// `previous`, if any.
+    pub(crate) fn new(previous: Option<&str>, config: String) -> anyhow::Result<Self> {
+        let previous = match previous {
+            Some(path) => {
+                let file = File::open(path)
+                    .with_context(|| format!("Error opening manifest `{}`", path))?;
+                serde_json::from_reader(BufReader::new(file))
+                    .with_context(|| format!("Error parsing manifest `{}`", path))?
+            }
+            None => ExportManifest::default(),
+        };
+        Ok(Self {
+            previous,
+            current: Mutex::new(ExportManifest {
+                config,
+                packages: BTreeMap::new(),
+            }),
+        })
+    }
+
+    /// Return whether a package can be skipped without loading it, because neither its files
+    /// nor the configs changed since the previous export. A skipped package keeps its previous
+    /// fingerprints.
+    pub(crate) fn skip(&self, package: &PackageLabel, inputs: &str) -> bool {
+        let mut current = self.current.lock().unwrap();
+        if self.previous.config != current.config {
+            return false;
+        }
+        let package = package.to_string();
+        match self.previous.packages.get(&package) {
+            Some(previous) if previous.inputs == inputs => {
+                current.packages.insert(package, previous.clone());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Record the targets of a loaded package, and return whether they changed since the
+    /// previous export.
+    pub(crate) fn record<'a>(
+        &self,
+        package: &PackageLabel,
+        inputs: String,
+        targets: impl IntoIterator<Item = &'a TargetNode>,
+    ) -> bool {
+        let package = package.to_string();
+        let targets = TargetHashes::compute_immediate_package(targets).to_string();
+        let changed = self
+            .previous
+            .packages
+            .get(&package)
+            .map_or(true, |previous| previous.targets != targets);
+        self.current
+            .lock()
+            .unwrap()
+            .packages
+            .insert(package, PackageFingerprints { inputs, targets });
+        changed
+    }
+
+    /// Record that a package failed to load. It keeps its previous fingerprints, so that it is
+    /// not reported as removed, and is exported again once it loads.
+    pub(crate) fn record_error(&self, package: &PackageLabel) {
+        let package = package.to_string();
+        if let Some(previous) = self.previous.packages.get(&package) {
+            self.current
+                .lock()
+                .unwrap()
+                .packages
+                .insert(package, previous.clone());
+        }
+    }
+
+    /// Packages of the previous export which were not found in this one.
+    pub(crate) fn removed(&self) -> Vec<String> {
+        let current = self.current.lock().unwrap();
+        self.previous
+            .packages
+            .keys()
+            .filter(|package| !current.packages.contains_key(*package))
+            .cloned()
+            .collect()
+    }
+
+    /// Write the manifest of this export.
+    pub(crate) fn write(&self, path: &str) -> anyhow::Result<()> {
+        let file =
+            File::create(path).with_context(|| format!("Error creating manifest `{}`", path))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, &*self.current.lock().unwrap())?;
+        writer
+            .flush()
+            .with_context(|| format!("Error writing manifest `{}`", path))?;
+        Ok(())
+    }
+}
+
+/// Hash of `(name, value)` pairs, independent of their order.
+fn fingerprint(entries: impl IntoIterator<Item = (String, String)>) -> String {
+    let entries: BTreeMap<String, String> = entries.into_iter().collect();
+    let mut hasher = blake3::Hasher::new();
+    for (name, value) in &entries {
+        hasher.update(name.as_bytes());
+        hasher.update(&[0]);
+        hasher.update(value.as_bytes());
+        hasher.update(&[0]);
+    }
+    hasher.finalize().to_hex().to_string()
+}
+
+/// Fingerprint of the buckconfigs of all cells.
+pub(crate) async fn config_fingerprint(ctx: &DiceComputations) -> anyhow::Result<String> {
+    let configs = ctx.get_legacy_configs().await?;
+    let mut entries = Vec::new();
+    for (cell, config) in configs.iter() {
+        for (section, values) in config.iter() {
+            for (key, value) in values {
+                entries.push((format!("{}//{}.{}", cell, section, key), value.to_owned()));
+            }
+        }
+    }
+    Ok(fingerprint(entries))
+}
+
+/// Fingerprint of the files a package is loaded from: its build file, the names of the files
+/// it can glob, the `PACKAGE` files above it, and all the `.bzl` files they load. This parses
+/// the build file to find its loads, but doesn't evaluate it.
+pub(crate) async fn inputs_fingerprint(
+    ctx: &DiceComputations,
+    package: PackageLabel,
+) -> anyhow::Result<String> {
+    let listing = ctx.resolve_package_listing(package.dupe()).await?;
+    let mut entries = vec![(
+        "files".to_owned(),
+        listing
+            .files()
+            .files()
+            .map(|file| file.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )];
+    let mut paths = vec![package.as_cell_path().join(listing.buildfile())];
+
+    let mut modules = INTERPRETER_CALCULATION_IMPL
+        .get()?
+        .get_module_deps(ctx, package.dupe(), BuildFileCell::new(package.cell_name()))
+        .await?
+        .0;
+    let mut package_file = Some(PackageFilePath::for_dir(package.as_cell_path()));
+    while let Some(file) = package_file {
+        if let Some(imports) = INTERPRETER_CALCULATION_IMPL
+            .get()?
+            .get_package_file_deps(ctx, &file)
+            .await?
+        {
+            for import in &imports {
+                modules.push(ctx.get_loaded_module_from_import_path(import).await?);
+            }
+            paths.push(file.path().clone());
+        }
+        package_file = file.parent_package_file();
+    }
+
+    let mut seen = HashSet::new();
+    while let Some(module) = modules.pop() {
+        let path = module.path().path().clone();
+        if seen.insert(path.clone()) {
+            paths.push(path);
+            modules.extend(module.loaded_modules().map.values().map(LoadedModule::dupe));
+        }
+    }
+
+    for path in paths {
+        let digest = file_digest(ctx, &path).await?;
+        entries.push((path.to_string(), digest));
+    }
+    Ok(fingerprint(entries))
+}
+
+async fn file_digest(ctx: &DiceComputations, path: &CellPath) -> anyhow::Result<String> {
+    Ok(
+        match ctx
+            .file_ops()
+            .read_path_metadata_if_exists(path.as_ref())
+            .await?
+        {
+            Some(RawPathMetadata::File(metadata)) => metadata.digest.to_string(),
+            metadata => format!("{:?}", metadata),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    use buck2_core::package::PackageLabel;
+
+    use crate::commands::targets::export_manifest::fingerprint;
+    use crate::commands::targets::export_manifest::ExportManifest;
+    use crate::commands::targets::export_manifest::IncrementalExport;
+    use crate::commands::targets::export_manifest::PackageFingerprints;
+    use crate::target_hash::TargetHashes;
+
+    fn fingerprints(inputs: &str, targets: &str) -> PackageFingerprints {
+        PackageFingerprints {
+            inputs: inputs.to_owned(),
+            targets: targets.to_owned(),
+        }
+    }
+
+    fn export(previous_config: &str, current_config: &str) -> IncrementalExport {
+        let empty = TargetHashes::compute_immediate_package([]).to_string();
+        IncrementalExport {
+            previous: ExportManifest {
+                config: previous_config.to_owned(),
+                packages: BTreeMap::from([
+                    ("root//a".to_owned(), fingerprints("1", &empty)),
+                    ("root//b".to_owned(), fingerprints("1", "0")),
+                    ("root//c".to_owned(), fingerprints("1", "0")),
+                    ("root//d".to_owned(), fingerprints("1", "0")),
+                    ("root//f".to_owned(), fingerprints("1", "0")),
+                ]),
+            },
+            current: Mutex::new(ExportManifest {
+                config: current_config.to_owned(),
+                packages: BTreeMap::new(),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_incremental_export() {
+        let export = export("config", "config");
+
+        // Changed files, same targets: loaded but not output.
+        assert!(!export.skip(&PackageLabel::testing_parse("root//a"), "2"));
+        assert!(!export.record(&PackageLabel::testing_parse("root//a"), "2".to_owned(), []));
+        // Changed files and targets.
+        assert!(!export.skip(&PackageLabel::testing_parse("root//b"), "2"));
+        assert!(export.record(&PackageLabel::testing_parse("root//b"), "2".to_owned(), []));
+        // New package.
+        assert!(!export.skip(&PackageLabel::testing_parse("root//e"), "1"));
+        assert!(export.record(&PackageLabel::testing_parse("root//e"), "1".to_owned(), []));
+        // Failed to load.
+        export.record_error(&PackageLabel::testing_parse("root//c"));
+        // Unchanged files: not loaded.
+        assert!(export.skip(&PackageLabel::testing_parse("root//f"), "1"));
+
+        assert_eq!(vec!["root//d".to_owned()], export.removed());
+        let current = export.current.lock().unwrap();
+        assert_eq!(
+            Some(&fingerprints("1", "0")),
+            current.packages.get("root//c")
+        );
+        assert_eq!(
+            Some(&fingerprints("1", "0")),
+            current.packages.get("root//f")
+        );
+        assert_eq!(
+            Some("2"),
+            current.packages.get("root//a").map(|p| p.inputs.as_str())
+        );
+    }
+
+    #[test]
+    fn test_incremental_export_config_changed() {
+        let export = export("old", "new");
+        assert!(!export.skip(&PackageLabel::testing_parse("root//f"), "1"));
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let a = ("a".to_owned(), "1".to_owned());
+        let b = ("b".to_owned(), "2".to_owned());
+        assert_eq!(
+            fingerprint([a.clone(), b.clone()]),
+            fingerprint([b.clone(), a.clone()])
+        );
+        assert_ne!(
+            fingerprint([a.clone(), b]),
+            fingerprint([a, ("b".to_owned(), "3".to_owned())])
+        );
+        // Names and values can't run into each other.
+        assert_ne!(
+            fingerprint([("ab".to_owned(), "c".to_owned())]),
+            fingerprint([("a".to_owned(), "bc".to_owned())])
+        );
+    }
+}
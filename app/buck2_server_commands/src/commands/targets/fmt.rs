@@ -9,6 +9,7 @@
 
 use std::fmt::Write;
 use std::sync::Arc;
+use std::sync::Mutex;
 
 use anyhow::Context;
 use buck2_cli_proto::targets_request;
@@ -19,16 +20,20 @@ use buck2_cli_proto::TargetsRequest;
 use buck2_core::bzl::ImportPath;
 use buck2_core::cells::cell_path::CellPath;
 use buck2_core::package::PackageLabel;
+use buck2_node::attrs::fmt_context::AttrFmtContext;
 use buck2_node::attrs::hacks::value_to_json;
 use buck2_node::attrs::inspect_options::AttrInspectOptions;
+use buck2_node::attrs::json::ToJsonWithContext;
 use buck2_node::nodes::attributes::DEPS;
 use buck2_node::nodes::attributes::INPUTS;
+use buck2_node::nodes::attributes::METADATA;
 use buck2_node::nodes::attributes::ONCALL;
 use buck2_node::nodes::attributes::PACKAGE;
 use buck2_node::nodes::attributes::PACKAGE_VALUES;
 use buck2_node::nodes::attributes::TARGET_CALL_STACK;
 use buck2_node::nodes::attributes::TARGET_HASH;
 use buck2_node::nodes::attributes::TYPE;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
 use buck2_node::nodes::unconfigured::TargetNode;
 use buck2_node::super_package::SuperPackage;
 use buck2_util::indent::indent;
@@ -50,6 +55,9 @@ pub(crate) struct TargetInfo<'a> {
     pub(crate) node: &'a TargetNode,
     pub(crate) target_hash: Option<BuckTargetHash>,
     pub(crate) super_package: &'a SuperPackage,
+    /// The target configured for the target platform, to show attributes with their `select()`s
+    /// resolved.
+    pub(crate) configured: Option<&'a ConfiguredTargetNode>,
 }
 
 fn package_error_to_stderr(package: &PackageLabel, error: &anyhow::Error, stderr: &mut String) {
@@ -80,6 +88,8 @@ pub(crate) trait TargetFormatter: Send + Sync {
     ) {
         package_error_to_stderr(&package, error, stderr);
     }
+    /// Called for packages of the previous export manifest which were not found.
+    fn package_removed(&self, package: &str, buffer: &mut String) {}
 }
 
 pub(crate) struct JsonWriter {
@@ -235,11 +245,27 @@ impl TargetFormatter for JsonFormat {
             });
         }
 
+        let metadata = target_info.node.metadata_with_package();
+        if !metadata.is_empty() {
+            print_attr(self, buffer, &mut first, METADATA, || {
+                QuotedJson::from_serde_json_value(metadata.to_value())
+            });
+        }
+
         for a in target_info.node.attrs(self.attr_inspect_opts) {
             print_attr(self, buffer, &mut first, a.name, || {
-                QuotedJson::from_serde_json_value(
-                    value_to_json(a.value, target_info.node.label().pkg()).unwrap(),
-                )
+                let pkg = target_info.node.label().pkg();
+                // Only the attributes which are printed are configured.
+                let value = match target_info
+                    .configured
+                    .and_then(|configured| configured.get(a.name, self.attr_inspect_opts))
+                {
+                    Some(configured) => configured
+                        .value
+                        .to_json(&AttrFmtContext { package: Some(pkg) }),
+                    None => value_to_json(a.value, pkg),
+                };
+                QuotedJson::from_serde_json_value(value.unwrap())
             });
         }
 
@@ -318,6 +344,20 @@ impl TargetFormatter for JsonFormat {
         );
         self.writer.entry_end(stdout, first);
     }
+
+    fn package_removed(&self, package: &str, buffer: &mut String) {
+        self.writer.entry_start(buffer);
+        let mut first = true;
+        self.writer
+            .entry_item(buffer, &mut first, PACKAGE, QuotedJson::quote_str(package));
+        self.writer.entry_item(
+            buffer,
+            &mut first,
+            "buck.removed",
+            QuotedJson::from_serde_json_value(serde_json::Value::Bool(true)),
+        );
+        self.writer.entry_end(buffer, first);
+    }
 }
 
 #[derive(Debug, Default)]
@@ -365,6 +405,76 @@ impl TargetFormatter for TargetNameFormat {
     }
 }
 
+/// A JSON object with two keys: `targets` maps each target to its hash (`null` for targets
+/// without a hash, e.g. incompatible targets), and `errors` maps each package which failed to
+/// load to its error.
+#[derive(Default)]
+struct TargetHashesFormat {
+    /// Packages which failed to load, with their errors. These are written in `end`, so nothing is
+    /// written to stdout for them while targets are being output.
+    errors: Mutex<Vec<(String, String)>>,
+}
+
+impl TargetFormatter for TargetHashesFormat {
+    fn begin(&self, buffer: &mut String) {
+        buffer.push_str("{\n  \"targets\": {\n");
+    }
+
+    fn end(&self, _stats: &Stats, buffer: &mut String) {
+        buffer.push_str("\n  },\n  \"errors\": {");
+        let mut errors = self.errors.lock().unwrap();
+        errors.sort();
+        for (i, (package, error)) in errors.iter().enumerate() {
+            if i != 0 {
+                buffer.push(',');
+            }
+            write!(
+                buffer,
+                "\n    {}: {}",
+                QuotedJson::quote_str(package).as_str(),
+                QuotedJson::quote_str(error).as_str()
+            )
+            .unwrap();
+        }
+        if !errors.is_empty() {
+            buffer.push_str("\n  ");
+        }
+        buffer.push_str("}\n}\n");
+    }
+
+    fn separator(&self, buffer: &mut String) {
+        buffer.push_str(",\n");
+    }
+
+    fn target(&self, target_info: TargetInfo<'_>, buffer: &mut String) {
+        let hash = match target_info.target_hash {
+            Some(hash) => QuotedJson::quote_display(hash),
+            None => QuotedJson::from_serde_json_value(serde_json::Value::Null),
+        };
+        write!(
+            buffer,
+            "    {}: {}",
+            QuotedJson::quote_display(target_info.node.label()).as_str(),
+            hash.as_str()
+        )
+        .unwrap();
+    }
+
+    fn package_error(
+        &self,
+        package: PackageLabel,
+        error: &anyhow::Error,
+        _stdout: &mut String,
+        stderr: &mut String,
+    ) {
+        package_error_to_stderr(&package, error, stderr);
+        self.errors
+            .lock()
+            .unwrap()
+            .push((package.to_string(), format!("{:#}", error)));
+    }
+}
+
 pub(crate) fn print_target_call_stack_after_target(out: &mut String, call_stack: Option<&str>) {
     if let Some(call_stack) = call_stack {
         write!(out, "{}", indent("  ", call_stack)).unwrap();
@@ -393,6 +503,7 @@ pub(crate) fn create_formatter(
     match output_format {
         OutputFormat::Unknown => Err(FormatterError::OutputFormatNotSet.into()),
         OutputFormat::Stats => Ok(Arc::new(StatsFormat)),
+        OutputFormat::TargetHashes => Ok(Arc::new(TargetHashesFormat::default())),
         OutputFormat::Text => Ok(Arc::new(TargetNameFormat {
             target_call_stacks,
             target_hash_graph_type: TargetHashGraphType::from_i32(other.target_hash_graph_type)
@@ -421,3 +532,128 @@ pub(crate) fn create_formatter(
         })),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::any::Any;
+    use std::sync::Arc;
+
+    use allocative::Allocative;
+    use buck2_core::bzl::ImportPath;
+    use buck2_core::package::PackageLabel;
+    use buck2_core::target::label::TargetLabel;
+    use buck2_node::metadata::key::MetadataKey;
+    use buck2_node::metadata::key::MetadataKeyRef;
+    use buck2_node::metadata::super_package_values::SuperPackageValues;
+    use buck2_node::nodes::unconfigured::testing::TargetNodeExt;
+    use buck2_node::nodes::unconfigured::TargetNode;
+    use buck2_node::rule_type::RuleType;
+    use buck2_node::rule_type::StarlarkRuleType;
+    use buck2_node::super_package::SuperPackage;
+    use serde_json::json;
+    use starlark_map::small_map::SmallMap;
+
+    use super::*;
+
+    #[derive(Debug, Default, Allocative)]
+    struct NoPackageValues;
+
+    impl SuperPackageValues for NoPackageValues {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn is_empty(&self) -> bool {
+            true
+        }
+
+        fn package_values_json(&self) -> anyhow::Result<SmallMap<MetadataKey, serde_json::Value>> {
+            Ok(SmallMap::new())
+        }
+
+        fn contains_key(&self, _key: &MetadataKeyRef) -> bool {
+            false
+        }
+
+        fn get_package_value_json(
+            &self,
+            _key: &MetadataKeyRef,
+        ) -> anyhow::Result<Option<serde_json::Value>> {
+            Ok(None)
+        }
+    }
+
+    fn target(label: &str) -> TargetNode {
+        TargetNode::testing_new(
+            TargetLabel::testing_parse(label),
+            RuleType::Starlark(Arc::new(StarlarkRuleType {
+                import_path: ImportPath::testing_new("cell//pkg:rules.bzl"),
+                name: "foo".to_owned(),
+            })),
+            Vec::new(),
+        )
+    }
+
+    #[test]
+    fn test_target_hashes_format() {
+        let formatter = TargetHashesFormat::default();
+        let super_package = SuperPackage::empty::<NoPackageValues>();
+        let a = target("cell//a:a");
+        let b = target("cell//a:b");
+
+        let mut stdout = String::new();
+        let mut stderr = String::new();
+        formatter.begin(&mut stdout);
+        formatter.target(
+            TargetInfo {
+                node: &a,
+                target_hash: Some(BuckTargetHash(1)),
+                super_package: &super_package,
+                configured: None,
+            },
+            &mut stdout,
+        );
+        formatter.package_error(
+            PackageLabel::testing_parse("cell//bad"),
+            &anyhow::anyhow!("Syntax error"),
+            &mut String::new(),
+            &mut stderr,
+        );
+        formatter.separator(&mut stdout);
+        formatter.target(
+            TargetInfo {
+                node: &b,
+                target_hash: None,
+                super_package: &super_package,
+                configured: None,
+            },
+            &mut stdout,
+        );
+        formatter.end(&Stats::default(), &mut stdout);
+
+        let output: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        assert_eq!(
+            json!({
+                "targets": {
+                    "cell//a:a": BuckTargetHash(1).to_string(),
+                    "cell//a:b": null,
+                },
+                "errors": {
+                    "cell//bad": "Syntax error",
+                },
+            }),
+            output
+        );
+        assert!(stderr.contains("Error parsing cell//bad"), "{}", stderr);
+    }
+
+    #[test]
+    fn test_target_hashes_format_empty() {
+        let formatter = TargetHashesFormat::default();
+        let mut stdout = String::new();
+        formatter.begin(&mut stdout);
+        formatter.end(&Stats::default(), &mut stdout);
+        let output: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        assert_eq!(json!({"targets": {}, "errors": {}}), output);
+    }
+}
@@ -8,6 +8,7 @@
  */
 
 mod default;
+mod export_manifest;
 pub(crate) mod fmt;
 mod resolve_alias;
 mod streaming;
@@ -15,6 +16,7 @@ mod streaming;
 use std::fs::File;
 use std::io::BufWriter;
 use std::io::Write;
+use std::sync::Arc;
 
 use anyhow::Context as _;
 use async_trait::async_trait;
@@ -32,9 +34,12 @@ use buck2_server_ctx::pattern::target_platform_from_client_context;
 use buck2_server_ctx::template::run_server_command;
 use buck2_server_ctx::template::ServerCommandTemplate;
 use dice::DiceTransaction;
+use dupe::Dupe;
 
 use crate::commands::targets::default::targets_batch;
 use crate::commands::targets::default::TargetHashOptions;
+use crate::commands::targets::export_manifest::config_fingerprint;
+use crate::commands::targets::export_manifest::IncrementalExport;
 use crate::commands::targets::fmt::create_formatter;
 use crate::commands::targets::resolve_alias::targets_resolve_aliases;
 use crate::commands::targets::streaming::targets_streaming;
@@ -189,6 +194,24 @@ async fn targets(
                     TargetHashGraphType::None => None,
                     _ => Some(other.target_hash_use_fast_hash),
                 };
+                let resolve_attributes = if other.resolve_attributes {
+                    let client_ctx = request.client_context()?;
+                    Some(
+                        target_platform_from_client_context(client_ctx, server_ctx, &mut dice)
+                            .await?,
+                    )
+                } else {
+                    None
+                };
+                let export = if other.previous_manifest.is_some() || other.write_manifest.is_some()
+                {
+                    Some(Arc::new(IncrementalExport::new(
+                        other.previous_manifest.as_deref(),
+                        config_fingerprint(&dice).await?,
+                    )?))
+                } else {
+                    None
+                };
 
                 let res = targets_streaming(
                     server_ctx,
@@ -202,11 +225,17 @@ async fn targets(
                     other.imports,
                     hashing,
                     request.concurrency.as_ref().map(|x| x.concurrency as usize),
+                    resolve_attributes,
+                    export.dupe(),
                 )
                 .await;
                 // Make sure we always flush the outputter, even on failure, as we may have partially written to it
                 outputter.flush()?;
-                res?
+                let res = res?;
+                if let (Some(export), Some(path)) = (&export, &other.write_manifest) {
+                    export.write(path)?;
+                }
+                res
             } else {
                 let formatter = create_formatter(request, other)?;
                 let client_ctx = request.client_context()?;
@@ -15,6 +15,8 @@ enum ResolveAliasError {
     OutputFormatNotSet,
     #[error("`--stat` format is not supported by `--resolve-alias`")]
     StatFormatNotSupported,
+    #[error("`target_hashes` format is not supported by `--resolve-alias`")]
+    TargetHashesFormatNotSupported,
 }
 
 use std::collections::HashMap;
@@ -165,6 +167,9 @@ pub(crate) async fn targets_resolve_aliases(
             &json_writer as &dyn ResolveAliasFormatter
         }
         OutputFormat::Stats => return Err(ResolveAliasError::StatFormatNotSupported.into()),
+        OutputFormat::TargetHashes => {
+            return Err(ResolveAliasError::TargetHashesFormatNotSupported.into());
+        }
     };
 
     let mut needs_separator = false;
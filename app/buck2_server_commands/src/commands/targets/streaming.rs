@@ -16,29 +16,31 @@ use std::sync::Arc;
 use std::sync::Mutex;
 
 use buck2_cli_proto::TargetsResponse;
-use buck2_common::pattern::package_roots::find_package_roots_stream;
-use buck2_common::pattern::resolve::ResolvedPattern;
+use buck2_common::pattern::resolve::stream_target_patterns;
 use buck2_core::bzl::ImportPath;
+use buck2_core::configuration::compatibility::MaybeCompatible;
 use buck2_core::package::PackageLabel;
-use buck2_core::pattern::pattern_type::PatternType;
 use buck2_core::pattern::pattern_type::TargetPatternExtra;
 use buck2_core::pattern::PackageSpec;
 use buck2_core::pattern::ParsedPattern;
+use buck2_core::target::label::TargetLabel;
 use buck2_core::target::name::TargetName;
 use buck2_futures::spawn::spawn_cancellable;
 use buck2_interpreter::load_module::InterpreterCalculation;
 use buck2_interpreter::load_module::INTERPRETER_CALCULATION_IMPL;
 use buck2_interpreter::paths::package::PackageFilePath;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_node::nodes::configured_frontend::ConfiguredTargetNodeCalculation;
 use buck2_node::nodes::eval_result::EvaluationResult;
 use buck2_node::nodes::frontend::TargetGraphCalculation;
 use buck2_node::nodes::unconfigured::TargetNode;
+use buck2_node::target_calculation::ConfiguredTargetCalculation;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use dice::DiceComputations;
 use dice::DiceTransaction;
 use dupe::Dupe;
 use dupe::IterDupedExt;
 use futures::future::FutureExt;
-use futures::Stream;
 use futures::StreamExt;
 use gazebo::prelude::VecExt;
 use itertools::Either;
@@ -46,6 +48,8 @@ use itertools::Itertools;
 use starlark_map::small_set::SmallSet;
 use tokio::sync::Semaphore;
 
+use crate::commands::targets::export_manifest::inputs_fingerprint;
+use crate::commands::targets::export_manifest::IncrementalExport;
 use crate::commands::targets::fmt::Stats;
 use crate::commands::targets::fmt::TargetFormatter;
 use crate::commands::targets::fmt::TargetInfo;
@@ -65,6 +69,8 @@ pub(crate) async fn targets_streaming(
     imports: bool,
     fast_hash: Option<bool>, // None = no hashing
     threads: Option<usize>,
+    resolve_attributes: Option<Option<TargetLabel>>, // Some(target platform) = resolve selects
+    export: Option<Arc<IncrementalExport>>,          // Some = only output changed packages
 ) -> anyhow::Result<TargetsResponse> {
     struct Res {
         stats: Stats,           // Stats to merge in
@@ -76,11 +82,13 @@ pub(crate) async fn targets_streaming(
     let imported = Arc::new(Mutex::new(SmallSet::new()));
     let threads = Arc::new(Semaphore::new(threads.unwrap_or(Semaphore::MAX_PERMITS)));
 
-    let mut packages = stream_packages(&dice, parsed_patterns)
+    let mut packages = stream_target_patterns(&dice, parsed_patterns)
         .map(|x| {
             let formatter = formatter.dupe();
             let imported = imported.dupe();
             let threads = threads.dupe();
+            let resolve_attributes = resolve_attributes.dupe();
+            let export = export.dupe();
             let ctx = dice.dupe();
 
             spawn_cancellable(
@@ -94,10 +102,39 @@ pub(crate) async fn targets_streaming(
                                 stderr: None,
                                 stdout: String::new(),
                             };
+                            // Packages whose files did not change since the previous export
+                            // are neither loaded nor output.
+                            let inputs = match &export {
+                                Some(export) => {
+                                    let inputs = {
+                                        let _permit = threads.acquire().await.unwrap();
+                                        inputs_fingerprint(&ctx, package.dupe()).await
+                                    };
+                                    if let Ok(inputs) = &inputs {
+                                        if export.skip(&package, inputs) {
+                                            return anyhow::Ok(res);
+                                        }
+                                    }
+                                    // Errors are reported by loading the package.
+                                    inputs.ok()
+                                }
+                                None => None,
+                            };
                             let targets = {
                                 // This bit of code is the heavy CPU stuff, so guard it with the threads
                                 let _permit = threads.acquire().await.unwrap();
-                                load_targets(&ctx, package.dupe(), spec, cached, keep_going).await
+                                match load_targets(&ctx, package.dupe(), spec, cached, keep_going)
+                                    .await
+                                {
+                                    Ok((eval_result, targets, err)) => {
+                                        configure_targets(&ctx, &targets, &resolve_attributes)
+                                            .await
+                                            .map(|configured| {
+                                                (eval_result, targets, configured, err)
+                                            })
+                                    }
+                                    Err(e) => Err(e),
+                                }
                             };
                             let mut show_err = |err| {
                                 res.stats.errors += 1;
@@ -111,10 +148,12 @@ pub(crate) async fn targets_streaming(
                                 res.stderr = Some(stderr);
                             };
                             match targets {
-                                Ok((eval_result, targets, err)) => {
+                                Ok((eval_result, targets, configured, err)) => {
                                     if let Some(err) = err {
                                         show_err(&err);
-                                        formatter.separator(&mut res.stdout);
+                                        if !res.stdout.is_empty() {
+                                            formatter.separator(&mut res.stdout);
+                                        }
                                     }
                                     res.stats.success += 1;
                                     if imports {
@@ -130,7 +169,24 @@ pub(crate) async fn targets_streaming(
                                             .unwrap()
                                             .extend(eval_imports.iter().cloned());
                                     }
-                                    for (i, node) in targets.iter().enumerate() {
+                                    // Packages whose targets did not change since the
+                                    // previous export are not output.
+                                    let changed = match (&export, inputs) {
+                                        (Some(export), Some(inputs)) => export.record(
+                                            &package,
+                                            inputs,
+                                            eval_result.targets().values(),
+                                        ),
+                                        (Some(export), None) => {
+                                            export.record_error(&package);
+                                            true
+                                        }
+                                        (None, _) => true,
+                                    };
+                                    let targets = if changed { targets.as_slice() } else { &[] };
+                                    for (i, (node, configured_node)) in
+                                        targets.iter().zip(&configured).enumerate()
+                                    {
                                         res.stats.targets += 1;
                                         if imports || i != 0 {
                                             formatter.separator(&mut res.stdout);
@@ -142,12 +198,16 @@ pub(crate) async fn targets_streaming(
                                                     TargetHashes::compute_immediate_one(node, fast)
                                                 }),
                                                 super_package: eval_result.super_package(),
+                                                configured: configured_node.as_ref(),
                                             },
                                             &mut res.stdout,
                                         )
                                     }
                                 }
                                 Err(err) => {
+                                    if let Some(export) = &export {
+                                        export.record_error(&package);
+                                    }
                                     show_err(&err);
                                 }
                             }
@@ -233,6 +293,16 @@ pub(crate) async fn targets_streaming(
         }
     }
 
+    if let Some(export) = &export {
+        for package in export.removed() {
+            if needs_separator {
+                formatter.separator(&mut buffer);
+            }
+            needs_separator = true;
+            formatter.package_removed(&package, &mut buffer);
+        }
+    }
+
     formatter.end(&stats, &mut buffer);
     Ok(TargetsResponse {
         error_count: stats.errors,
@@ -240,32 +310,6 @@ pub(crate) async fn targets_streaming(
     })
 }
 
-/// Given the patterns, separate into those which have an explicit package, and those which are recursive
-fn stream_packages<T: PatternType>(
-    dice: &DiceTransaction,
-    patterns: Vec<ParsedPattern<T>>,
-) -> impl Stream<Item = anyhow::Result<(PackageLabel, PackageSpec<T>)>> {
-    let mut spec = ResolvedPattern::<T>::new();
-    let mut recursive_paths = Vec::new();
-
-    for pattern in patterns {
-        match pattern {
-            ParsedPattern::Target(package, target_name, extra) => {
-                spec.add_target(package.dupe(), target_name, extra);
-            }
-            ParsedPattern::Package(package) => {
-                spec.add_package(package.dupe());
-            }
-            ParsedPattern::Recursive(package) => {
-                recursive_paths.push(package);
-            }
-        }
-    }
-
-    futures::stream::iter(spec.specs.into_iter().map(Ok))
-        .chain(find_package_roots_stream(dice, recursive_paths).map(|x| Ok((x?, PackageSpec::All))))
-}
-
 #[derive(buck2_error::Error, Debug)]
 enum TargetsError {
     #[error(
@@ -326,6 +370,29 @@ async fn load_targets(
     }
 }
 
+/// Configure the targets for the target platform, if attributes should be resolved. Targets which
+/// are incompatible with the platform are `None`, like all targets when attributes are not resolved.
+async fn configure_targets(
+    dice: &DiceComputations,
+    targets: &[TargetNode],
+    resolve_attributes: &Option<Option<TargetLabel>>,
+) -> anyhow::Result<Vec<Option<ConfiguredTargetNode>>> {
+    let target_platform = match resolve_attributes {
+        Some(target_platform) => target_platform.as_ref(),
+        None => return Ok(targets.iter().map(|_| None).collect()),
+    };
+    futures::future::try_join_all(targets.iter().map(|node| async move {
+        let label = dice
+            .get_configured_target(node.label(), target_platform)
+            .await?;
+        match dice.get_configured_target_node(&label).await? {
+            MaybeCompatible::Compatible(node) => Ok(Some(node)),
+            MaybeCompatible::Incompatible(_) => Ok(None),
+        }
+    }))
+    .await
+}
+
 /// Return `None` if the PACKAGE file doesn't exist
 async fn package_imports(
     dice: &DiceComputations,
@@ -8,6 +8,7 @@
  */
 
 //! A very limited interface for writing dot files (see <http://www.graphviz.org/doc/info/lang.html>)
+//! and mermaid flowcharts (see <https://mermaid.js.org/syntax/flowchart.html>).
 //!
 //! Has a lot less features than <https://crates.io/crates/dot> or <https://crates.io/crates/tabbycat>,
 //! but it's easier for us to match buck1's output with this simple implementation.
@@ -86,6 +87,81 @@ pub trait DotDigraph<'a> {
         node: &Self::Node,
         f: F,
     ) -> anyhow::Result<()>;
+
+    /// The name of the cluster the node is drawn in, if any.
+    fn cluster(&self, _node: &Self::Node) -> Option<String> {
+        None
+    }
+}
+
+/// How node and edge statements are written in a given output format.
+trait GraphSyntax {
+    fn node<N: DotNode>(&mut self, node: &N) -> anyhow::Result<String>;
+    fn edge(&mut self, edge: &DotEdge) -> String;
+}
+
+/// The statements of a graph, grouped by cluster.
+#[derive(Default)]
+struct GraphStatements {
+    /// Statements for the nodes outside of any cluster, each node followed by its edges.
+    unclustered: Vec<String>,
+    /// Node statements of each cluster.
+    clusters: SmallMap<String, Vec<String>>,
+    /// Edges from the nodes in clusters, which are written after all the clusters.
+    cluster_edges: Vec<String>,
+}
+
+impl GraphStatements {
+    fn collect<'a, T: DotDigraph<'a>, S: GraphSyntax>(
+        graph: &'a T,
+        syntax: &mut S,
+    ) -> anyhow::Result<Self> {
+        let mut statements = Self::default();
+        graph.for_each_node(|node| {
+            let line = syntax.node(node)?;
+            let edges = match graph.cluster(node) {
+                None => {
+                    statements.unclustered.push(line);
+                    &mut statements.unclustered
+                }
+                Some(cluster) => {
+                    match statements.clusters.get_mut(&cluster) {
+                        Some(lines) => lines.push(line),
+                        None => {
+                            statements.clusters.insert(cluster, vec![line]);
+                        }
+                    }
+                    &mut statements.cluster_edges
+                }
+            };
+            graph.for_each_edge(node, |edge| {
+                edges.push(syntax.edge(edge));
+                Ok(())
+            })?;
+            Ok(())
+        })?;
+        Ok(statements)
+    }
+
+    fn write_dot<W: Write>(&self, name: &str, mut w: W) -> anyhow::Result<()> {
+        writeln!(w, "digraph {} {{", name)?;
+        for line in &self.unclustered {
+            writeln!(w, "  {}", line)?;
+        }
+        for (i, (cluster, lines)) in self.clusters.iter().enumerate() {
+            writeln!(w, "  subgraph cluster_{} {{", i)?;
+            writeln!(w, "    label={};", escape_id(cluster))?;
+            for line in lines {
+                writeln!(w, "    {}", line)?;
+            }
+            writeln!(w, "  }}")?;
+        }
+        for line in &self.cluster_edges {
+            writeln!(w, "  {}", line)?;
+        }
+        writeln!(w, "}}")?;
+        Ok(())
+    }
 }
 
 /// ids in dot format need to have the '"' escaped.
@@ -111,67 +187,342 @@ fn escape_id(value: &str) -> String {
     format!("\"{}\"", value.replace('"', "\\\""))
 }
 
+struct DotSyntax;
+
+impl GraphSyntax for DotSyntax {
+    fn node<N: DotNode>(&mut self, node: &N) -> anyhow::Result<String> {
+        Ok(format!("{} [{}];", escape_id(&node.id()), node.attrs()?))
+    }
+
+    fn edge(&mut self, edge: &DotEdge) -> String {
+        format!("{} -> {};", escape_id(edge.from), escape_id(edge.to))
+    }
+}
+
+/// Assigns sequential numbers to node ids, in order of first use.
+#[derive(Default)]
+struct NumericIds {
+    next_id: u32,
+    lookup_numeric_id: HashMap<String, u32>,
+}
+
+impl NumericIds {
+    fn get(&mut self, node_name: &str) -> u32 {
+        match self.lookup_numeric_id.entry(node_name.to_owned()) {
+            Vacant(entry) => {
+                self.next_id += 1;
+                entry.insert(self.next_id);
+                self.next_id
+            }
+            Occupied(entry) => *entry.get(),
+        }
+    }
+}
+
+struct DotCompactSyntax(NumericIds);
+
+impl GraphSyntax for DotCompactSyntax {
+    fn node<N: DotNode>(&mut self, node: &N) -> anyhow::Result<String> {
+        let node_name = escape_id(&node.id());
+        Ok(format!(
+            "{} [{},label={}];",
+            self.0.get(&node_name),
+            node.attrs()?,
+            node_name
+        ))
+    }
+
+    fn edge(&mut self, edge: &DotEdge) -> String {
+        format!(
+            "{} -> {};",
+            self.0.get(&escape_id(edge.from)),
+            self.0.get(&escape_id(edge.to))
+        )
+    }
+}
+
+/// Mermaid labels are quoted, quotes inside them are written as an entity code.
+fn escape_mermaid_label(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "#quot;"))
+}
+
+struct MermaidSyntax(NumericIds);
+
+impl GraphSyntax for MermaidSyntax {
+    fn node<N: DotNode>(&mut self, node: &N) -> anyhow::Result<String> {
+        let id = node.id();
+        let label = node.attrs()?.label.unwrap_or_else(|| id.clone());
+        Ok(format!(
+            "n{}[{}]",
+            self.0.get(&id),
+            escape_mermaid_label(&label)
+        ))
+    }
+
+    fn edge(&mut self, edge: &DotEdge) -> String {
+        format!("n{} --> n{}", self.0.get(edge.from), self.0.get(edge.to))
+    }
+}
+
 pub struct Dot {}
 
 impl Dot {
-    pub fn render<'a, T: DotDigraph<'a>, W: Write>(graph: &'a T, mut w: W) -> anyhow::Result<()> {
-        writeln!(w, "digraph {} {{", graph.name())?;
-        graph.for_each_node(|node| {
-            let attrs = node.attrs()?;
-            writeln!(w, "  {} [{}];", escape_id(&node.id()), attrs)?;
-            graph.for_each_edge(node, |edge| {
-                writeln!(w, "  {} -> {};", escape_id(edge.from), escape_id(edge.to))?;
-                Ok(())
-            })?;
-            Ok(())
-        })?;
-        writeln!(w, "}}")?;
-        Ok(())
+    pub fn render<'a, T: DotDigraph<'a>, W: Write>(graph: &'a T, w: W) -> anyhow::Result<()> {
+        GraphStatements::collect(graph, &mut DotSyntax)?.write_dot(graph.name(), w)
     }
 }
 
 pub struct DotCompact {}
 
 impl DotCompact {
-    pub fn render<'a, T: DotDigraph<'a>, W: Write>(graph: &'a T, mut w: W) -> anyhow::Result<()> {
-        writeln!(w, "digraph {} {{", graph.name())?;
-
-        let mut next_id: u32 = 0;
-        let mut lookup_numeric_id: HashMap<String, u32> = HashMap::new();
+    pub fn render<'a, T: DotDigraph<'a>, W: Write>(graph: &'a T, w: W) -> anyhow::Result<()> {
+        GraphStatements::collect(graph, &mut DotCompactSyntax(NumericIds::default()))?
+            .write_dot(graph.name(), w)
+    }
+}
 
-        let mut name_to_number = |node_name: &str| -> u32 {
-            match lookup_numeric_id.entry(node_name.to_owned()) {
-                Vacant(entry) => {
-                    next_id += 1;
-                    entry.insert(next_id);
-                    next_id
-                }
-                Occupied(entry) => *entry.get(),
-            }
-        };
+pub struct Mermaid {}
 
-        graph.for_each_node(|node| {
-            let attrs = node.attrs()?;
-            let node_name = &escape_id(&node.id());
+impl Mermaid {
+    pub fn render<'a, T: DotDigraph<'a>, W: Write>(graph: &'a T, mut w: W) -> anyhow::Result<()> {
+        let statements =
+            GraphStatements::collect(graph, &mut MermaidSyntax(NumericIds::default()))?;
+        writeln!(w, "flowchart TD")?;
+        for line in &statements.unclustered {
+            writeln!(w, "  {}", line)?;
+        }
+        for (i, (cluster, lines)) in statements.clusters.iter().enumerate() {
             writeln!(
                 w,
-                "  {} [{},label={}];",
-                name_to_number(node_name),
-                attrs,
-                escape_id(&node.id())
+                "  subgraph cluster_{} [{}]",
+                i,
+                escape_mermaid_label(cluster)
             )?;
-            graph.for_each_edge(node, |edge| {
-                writeln!(
-                    w,
-                    "  {} -> {};",
-                    name_to_number(&escape_id(edge.from)),
-                    name_to_number(&escape_id(edge.to))
-                )?;
-                Ok(())
-            })?;
+            for line in lines {
+                writeln!(w, "    {}", line)?;
+            }
+            writeln!(w, "  end")?;
+        }
+        for line in &statements.cluster_edges {
+            writeln!(w, "  {}", line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::dot::Dot;
+    use crate::dot::DotCompact;
+    use crate::dot::DotDigraph;
+    use crate::dot::DotEdge;
+    use crate::dot::DotNode;
+    use crate::dot::DotNodeAttrs;
+    use crate::dot::Mermaid;
+
+    struct TestNode {
+        id: &'static str,
+        cluster: Option<&'static str>,
+    }
+
+    impl DotNode for TestNode {
+        fn attrs(&self) -> anyhow::Result<DotNodeAttrs> {
+            Ok(DotNodeAttrs {
+                style: Some("filled".to_owned()),
+                ..DotNodeAttrs::default()
+            })
+        }
+
+        fn id(&self) -> String {
+            self.id.to_owned()
+        }
+    }
+
+    struct TestGraph {
+        nodes: Vec<TestNode>,
+        edges: Vec<(&'static str, &'static str)>,
+    }
+
+    impl<'a> DotDigraph<'a> for TestGraph {
+        type Node = TestNode;
+
+        fn name(&self) -> &str {
+            "test"
+        }
+
+        fn for_each_node<F: FnMut(&Self::Node) -> anyhow::Result<()>>(
+            &'a self,
+            f: F,
+        ) -> anyhow::Result<()> {
+            self.nodes.iter().try_for_each(f)
+        }
+
+        fn for_each_edge<F: FnMut(&DotEdge) -> anyhow::Result<()>>(
+            &'a self,
+            node: &Self::Node,
+            mut f: F,
+        ) -> anyhow::Result<()> {
+            for (from, to) in &self.edges {
+                if *from == node.id {
+                    f(&DotEdge { from, to })?;
+                }
+            }
             Ok(())
-        })?;
-        writeln!(w, "}}")?;
+        }
+
+        fn cluster(&self, node: &Self::Node) -> Option<String> {
+            node.cluster.map(str::to_owned)
+        }
+    }
+
+    /// `root//:a` depends on `root//lib:b` and `root//lib:c`, which depends on `root//lib:b`.
+    fn graph(clustered: bool) -> TestGraph {
+        let cluster = |cluster| if clustered { Some(cluster) } else { None };
+        TestGraph {
+            nodes: vec![
+                TestNode {
+                    id: "root//:a",
+                    cluster: cluster("root//"),
+                },
+                TestNode {
+                    id: "root//lib:b",
+                    cluster: cluster("root//lib"),
+                },
+                TestNode {
+                    id: "root//lib:c",
+                    cluster: cluster("root//lib"),
+                },
+            ],
+            edges: vec![
+                ("root//:a", "root//lib:b"),
+                ("root//:a", "root//lib:c"),
+                ("root//lib:c", "root//lib:b"),
+            ],
+        }
+    }
+
+    fn render(
+        render: impl FnOnce(&TestGraph, &mut Vec<u8>) -> anyhow::Result<()>,
+        graph: TestGraph,
+    ) -> anyhow::Result<String> {
+        let mut out = Vec::new();
+        render(&graph, &mut out)?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    #[test]
+    fn test_dot() -> anyhow::Result<()> {
+        assert_eq!(
+            indoc!(
+                r#"
+                digraph test {
+                  "root//:a" [style=filled];
+                  "root//:a" -> "root//lib:b";
+                  "root//:a" -> "root//lib:c";
+                  "root//lib:b" [style=filled];
+                  "root//lib:c" [style=filled];
+                  "root//lib:c" -> "root//lib:b";
+                }
+                "#
+            ),
+            render(|g, w| Dot::render(g, w), graph(false))?
+        );
+        assert_eq!(
+            indoc!(
+                r#"
+                digraph test {
+                  subgraph cluster_0 {
+                    label="root//";
+                    "root//:a" [style=filled];
+                  }
+                  subgraph cluster_1 {
+                    label="root//lib";
+                    "root//lib:b" [style=filled];
+                    "root//lib:c" [style=filled];
+                  }
+                  "root//:a" -> "root//lib:b";
+                  "root//:a" -> "root//lib:c";
+                  "root//lib:c" -> "root//lib:b";
+                }
+                "#
+            ),
+            render(|g, w| Dot::render(g, w), graph(true))?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_dot_compact() -> anyhow::Result<()> {
+        assert_eq!(
+            indoc!(
+                r#"
+                digraph test {
+                  1 [style=filled,label="root//:a"];
+                  1 -> 2;
+                  1 -> 3;
+                  2 [style=filled,label="root//lib:b"];
+                  3 [style=filled,label="root//lib:c"];
+                  3 -> 2;
+                }
+                "#
+            ),
+            render(|g, w| DotCompact::render(g, w), graph(false))?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_mermaid() -> anyhow::Result<()> {
+        assert_eq!(
+            indoc!(
+                r#"
+                flowchart TD
+                  n1["root//:a"]
+                  n1 --> n2
+                  n1 --> n3
+                  n2["root//lib:b"]
+                  n3["root//lib:c"]
+                  n3 --> n2
+                "#
+            ),
+            render(|g, w| Mermaid::render(g, w), graph(false))?
+        );
+        assert_eq!(
+            indoc!(
+                r#"
+                flowchart TD
+                  subgraph cluster_0 ["root//"]
+                    n1["root//:a"]
+                  end
+                  subgraph cluster_1 ["root//lib"]
+                    n2["root//lib:b"]
+                    n3["root//lib:c"]
+                  end
+                  n1 --> n2
+                  n1 --> n3
+                  n3 --> n2
+                "#
+            ),
+            render(|g, w| Mermaid::render(g, w), graph(true))?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_mermaid_escapes_labels() -> anyhow::Result<()> {
+        let graph = TestGraph {
+            nodes: vec![TestNode {
+                id: r#"root//:a "quoted""#,
+                cluster: None,
+            }],
+            edges: Vec::new(),
+        };
+        assert_eq!(
+            "flowchart TD\n  n1[\"root//:a #quot;quoted#quot;\"]\n",
+            render(|g, w| Mermaid::render(g, w), graph)?
+        );
         Ok(())
     }
 }
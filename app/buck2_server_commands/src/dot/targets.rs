@@ -7,6 +7,9 @@
  * of this source tree.
  */
 
+use std::collections::HashSet;
+use std::collections::VecDeque;
+
 use buck2_query::query::environment::QueryTarget;
 use buck2_query::query::environment::QueryTargets;
 use buck2_query::query::syntax::simple::eval::set::TargetSet;
@@ -20,10 +23,74 @@ use crate::dot::DotNodeAttrs;
 
 pub struct DotTargetGraphNode<'a, T: QueryTarget>(&'a T, &'a DotTargetGraph<T>);
 
+/// What the nodes of a graph are clustered by.
+#[derive(Debug, Clone, Copy)]
+pub enum DotCluster {
+    Package,
+    RuleType,
+}
+
 /// A simple adapter for creating a DotDiGraph for a TargetSet.
 pub struct DotTargetGraph<T: QueryTarget> {
     pub targets: TargetSet<T>,
     pub attributes: Option<RegexSet>,
+    pub cluster: Option<DotCluster>,
+}
+
+/// The package part of a target label, e.g. `cell//foo` for `cell//foo:bar (cfg)`.
+fn package_of(label: &str) -> &str {
+    let label = label.split_once(' ').map_or(label, |(label, _)| label);
+    label.rsplit_once(':').map_or(label, |(package, _)| package)
+}
+
+/// Restrict `targets` to the nodes within `max_depth` edges of the roots of the graph, the roots
+/// being the nodes no other node of the set depends on.
+pub fn limit_depth<T: QueryTarget>(targets: TargetSet<T>, max_depth: u32) -> TargetSet<T> {
+    let mut has_rdeps = HashSet::new();
+    for target in targets.iter() {
+        for dep in target.deps() {
+            if dep != target.node_ref() && targets.contains(dep) {
+                has_rdeps.insert(dep.clone());
+            }
+        }
+    }
+
+    let mut depths: Vec<Option<u32>> = targets
+        .iter()
+        .map(|t| (!has_rdeps.contains(t.node_ref())).then_some(0))
+        .collect();
+    let mut queue: VecDeque<usize> = (0..depths.len()).filter(|i| depths[*i].is_some()).collect();
+    let mut next_start = 0;
+    loop {
+        while let Some(index) = queue.pop_front() {
+            let depth = depths[index].unwrap_or_default();
+            for dep in targets.get_index(index).unwrap().deps() {
+                if let Some(dep_index) = targets.get_index_of(dep) {
+                    if depths[dep_index].is_none() {
+                        depths[dep_index] = Some(depth + 1);
+                        queue.push_back(dep_index);
+                    }
+                }
+            }
+        }
+        // Nodes only reachable through a cycle have no root, so start again from one of them.
+        match (next_start..depths.len()).find(|i| depths[*i].is_none()) {
+            Some(index) => {
+                depths[index] = Some(0);
+                queue.push_back(index);
+                next_start = index + 1;
+            }
+            None => break,
+        }
+    }
+
+    let mut limited = TargetSet::new();
+    for (target, depth) in targets.into_iter().zip(depths) {
+        if depth.map_or(false, |depth| depth <= max_depth) {
+            limited.insert(target);
+        }
+    }
+    limited
 }
 
 impl<'a, T: QueryTarget> DotDigraph<'a> for DotTargetGraph<T> {
@@ -59,6 +126,13 @@ impl<'a, T: QueryTarget> DotDigraph<'a> for DotTargetGraph<T> {
         }
         Ok(())
     }
+
+    fn cluster(&self, node: &Self::Node) -> Option<String> {
+        match self.cluster? {
+            DotCluster::Package => Some(package_of(&node.0.node_ref().to_string()).to_owned()),
+            DotCluster::RuleType => Some(node.0.rule_type().into_owned()),
+        }
+    }
 }
 
 impl<'a, T: QueryTarget> DotNode for DotTargetGraphNode<'a, T> {
@@ -94,3 +168,17 @@ impl<'a, T: QueryTarget> DotNode for DotTargetGraphNode<'a, T> {
         self.0.node_ref().to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::dot::targets::package_of;
+
+    #[test]
+    fn test_package_of() {
+        assert_eq!("root//foo/bar", package_of("root//foo/bar:baz"));
+        assert_eq!(
+            "root//foo",
+            package_of("root//foo:baz (cfg//:linux#0123456789abcdef)")
+        );
+    }
+}
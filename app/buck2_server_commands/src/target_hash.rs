@@ -432,6 +432,17 @@ impl TargetHashes {
         hasher.finish_u128()
     }
 
+    /// Hash of all the targets of a package, each hashed like `compute_immediate_one`.
+    pub fn compute_immediate_package<'a>(
+        nodes: impl IntoIterator<Item = &'a TargetNode>,
+    ) -> BuckTargetHash {
+        let mut hasher = TargetHashes::new_hasher(true);
+        for node in nodes {
+            TargetHashes::hash_node(node, &mut *hasher);
+        }
+        hasher.finish_u128()
+    }
+
     pub async fn compute<T: TargetHashingTargetNode, L: AsyncNodeLookup<T>>(
         dice: DiceTransaction,
         lookup: L,
@@ -17,6 +17,7 @@ use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::ops::Deref;
 use std::sync::Arc;
+use std::time::Instant;
 
 use allocative::Allocative;
 use async_condvar_fair::Condvar;
@@ -69,6 +70,17 @@ enum ConcurrencyHandlerError {
     ExitWhenDifferentState,
 }
 
+/// A command known to the `ConcurrencyHandler`, as reported by `buck2 status --queue`.
+#[derive(Clone, Debug)]
+pub struct QueuedCommand {
+    pub trace_id: TraceId,
+    pub argv: String,
+    /// Whether this command is waiting for commands with a different state to finish.
+    pub waiting: bool,
+    /// When this command entered its current state.
+    pub since: Instant,
+}
+
 #[derive(Clone, Dupe, Copy, Debug)]
 pub enum RunState {
     NestedSameState,
@@ -96,6 +108,10 @@ pub struct ConcurrencyHandler {
     dice: Arc<Dice>,
     /// Used to prevent commands (clean --stale) from running in parallel with dice commands
     exclusive_command_lock: Arc<ExclusiveCommandLock>,
+    /// Commands that have not yet been granted access to DICE. Kept outside of `data` so that it
+    /// can be updated from a drop guard.
+    #[allocative(skip)]
+    waiting_commands: Arc<parking_lot::Mutex<SmallMap<CommandId, QueuedCommand>>>,
 }
 
 #[derive(Allocative)]
@@ -130,6 +146,8 @@ struct CommandData {
     trace_id: TraceId,
     argv: Vec<String>,
     dispatcher: EventDispatcher,
+    #[allocative(skip)]
+    started: Instant,
 }
 
 impl CommandData {
@@ -142,6 +160,15 @@ impl CommandData {
         truncate(&cmd, 500)
     }
 
+    fn to_queued(&self, waiting: bool) -> QueuedCommand {
+        QueuedCommand {
+            trace_id: self.trace_id.dupe(),
+            argv: self.format_argv(),
+            waiting,
+            since: self.started,
+        }
+    }
+
     fn notify_tainted(&self) {
         self.dispatcher.instant_event(buck2_data::TagEvent {
             tags: vec!["concurrency-tainted".to_owned()],
@@ -318,9 +345,31 @@ impl ConcurrencyHandler {
             cond: Default::default(),
             dice,
             exclusive_command_lock: Arc::new(ExclusiveCommandLock::new()),
+            waiting_commands: Default::default(),
         }
     }
 
+    /// Commands currently running, followed by commands waiting to run. Each command is listed
+    /// once.
+    pub async fn command_queue(&self) -> Vec<QueuedCommand> {
+        // Commands move from `waiting_commands` to `active_commands` while `data` is locked, so
+        // hold it while reading both.
+        let data = self.data.lock().await;
+        let mut queue = data
+            .active_commands
+            .values()
+            .map(|command| command.to_queued(false))
+            .collect::<Vec<_>>();
+        queue.extend(
+            self.waiting_commands
+                .lock()
+                .iter()
+                .filter(|(id, _)| !data.active_commands.contains_key(*id))
+                .map(|(_, command)| command.clone()),
+        );
+        queue
+    }
+
     /// Enters a critical section that requires concurrent command synchronization,
     /// and runs the given `exec` function in the critical section.
     pub async fn enter<F, Fut, R>(
@@ -407,6 +456,15 @@ impl ConcurrencyHandler {
             trace_id: trace.dupe(),
             argv: sanitized_argv,
             dispatcher: event_dispatcher.dupe(),
+            started: Instant::now(),
+        };
+
+        self.waiting_commands
+            .lock()
+            .insert(command_id, command_data.to_queued(true));
+        let _waiting_guard = WaitingCommandGuard {
+            waiting_commands: &self.waiting_commands,
+            command_id,
         };
 
         let (transaction, tainted) = loop {
@@ -550,6 +608,9 @@ impl ConcurrencyHandler {
             data.previously_tainted = true;
         }
 
+        // The command stops waiting and becomes active under the same lock of `data`.
+        drop(_waiting_guard);
+
         // create the on exit drop handler, which will take care of notifying tasks.
         let drop_guard = OnExecExit::new(self.dupe(), command_id, command_data, data);
 
@@ -617,6 +678,19 @@ fn format_traces(
     trace_ids.iter().join(", ")
 }
 
+/// Removes a command from `waiting_commands` once it stops waiting, whether it was granted access
+/// to DICE or failed.
+struct WaitingCommandGuard<'a> {
+    waiting_commands: &'a parking_lot::Mutex<SmallMap<CommandId, QueuedCommand>>,
+    command_id: CommandId,
+}
+
+impl<'a> Drop for WaitingCommandGuard<'a> {
+    fn drop(&mut self) {
+        self.waiting_commands.lock().remove(&self.command_id);
+    }
+}
+
 /// Held to execute a command so that when the command is canceled, we properly remove its state
 /// from the handler so that it's no longer registered as a ongoing command.
 struct OnExecExit(Option<(ConcurrencyHandler, CommandId)>);
@@ -625,9 +699,10 @@ impl OnExecExit {
     pub fn new(
         handler: ConcurrencyHandler,
         command: CommandId,
-        data: CommandData,
+        mut data: CommandData,
         mut guard: MutexGuard<'_, ConcurrencyHandlerData>,
     ) -> Self {
+        data.started = Instant::now();
         guard.active_commands.insert(command, data);
         Self(Some((handler, command)))
     }
@@ -1053,6 +1128,100 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn command_queue_reports_waiting_commands() -> anyhow::Result<()> {
+        let dice = Dice::builder().build(DetectCycles::Enabled);
+
+        let concurrency = ConcurrencyHandler::new(dice.dupe());
+
+        let trace_running = TraceId::new();
+        let trace_waiting = TraceId::new();
+
+        let block = Arc::new(RwLock::new(()));
+        let blocked = block.write().await;
+
+        let barrier = Arc::new(Barrier::new(2));
+
+        let running = tokio::spawn({
+            let concurrency = concurrency.dupe();
+            let barrier = barrier.dupe();
+            let b = block.dupe();
+            let trace_running = trace_running.dupe();
+
+            async move {
+                concurrency
+                    .enter(
+                        EventDispatcher::null_sink_with_trace(trace_running),
+                        &TestDiceDataProvider,
+                        &NoChanges,
+                        |_| async move {
+                            barrier.wait().await;
+                            let _g = b.read().await;
+                        },
+                        false,
+                        Vec::new(),
+                        None,
+                        false,
+                        ExplicitCancellationContext::testing(),
+                    )
+                    .await
+            }
+        });
+
+        barrier.wait().await;
+
+        // The running command is no longer listed as waiting.
+        let queue = concurrency.command_queue().await;
+        assert_eq!(1, queue.len());
+        assert!(!queue[0].waiting);
+
+        let waiting = tokio::spawn({
+            let concurrency = concurrency.dupe();
+            let trace_waiting = trace_waiting.dupe();
+
+            async move {
+                concurrency
+                    .enter(
+                        EventDispatcher::null_sink_with_trace(trace_waiting),
+                        &TestDiceDataProvider,
+                        &CtxDifferent,
+                        |_| async move {},
+                        false,
+                        Vec::new(),
+                        None,
+                        false,
+                        ExplicitCancellationContext::testing(),
+                    )
+                    .await
+            }
+        });
+
+        let queue = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let queue = concurrency.command_queue().await;
+                if queue.iter().any(|c| c.waiting) {
+                    return queue;
+                }
+                tokio::task::yield_now().await;
+            }
+        })
+        .await?;
+
+        assert_eq!(2, queue.len());
+        assert!(!queue[0].waiting);
+        assert_eq!(queue[0].trace_id, trace_running);
+        assert!(queue[1].waiting);
+        assert_eq!(queue[1].trace_id, trace_waiting);
+
+        drop(blocked);
+        running.await??;
+        waiting.await??;
+
+        assert!(concurrency.waiting_commands.lock().is_empty());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn parallel_invocation_exit_when_different_state() -> anyhow::Result<()> {
         let dice = Dice::builder().build(DetectCycles::Enabled);
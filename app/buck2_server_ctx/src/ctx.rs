@@ -37,6 +37,13 @@ use crate::concurrency::DiceDataProvider;
 use crate::concurrency::DiceUpdater;
 use crate::stderr_output_guard::StderrOutputGuard;
 
+#[derive(buck2_error::Error, Debug)]
+enum DiceAccessError {
+    #[error("buck2 was invoked recursively from action `{0}`")]
+    #[buck2(user)]
+    NestedInvocationFromAction(String),
+}
+
 #[async_trait]
 pub trait ServerCommandContextTrait: Send + Sync {
     fn working_dir(&self) -> &ProjectRelativePath;
@@ -65,6 +72,12 @@ pub trait ServerCommandContextTrait: Send + Sync {
         ctx: &DiceComputations,
     ) -> anyhow::Result<HashMap<String, String>>;
 
+    /// The machine and environment the command runs in.
+    async fn environment_provenance(
+        &self,
+        ctx: &DiceComputations,
+    ) -> anyhow::Result<buck2_data::EnvironmentProvenance>;
+
     fn log_target_pattern(
         &self,
         providers_patterns: &[ParsedPattern<ConfiguredProvidersPatternExtra>],
@@ -80,6 +93,8 @@ pub struct DiceAccessor {
     pub data: Box<dyn DiceDataProvider>,
     pub setup: Box<dyn DiceUpdater>,
     pub is_nested_invocation: bool,
+    /// The action that invoked this command, for nested invocations.
+    pub parent_action: Option<String>,
     pub sanitized_argv: Vec<String>,
     pub exit_when_different_state: bool,
     pub build_signals: Box<dyn DeferredBuildSignals>,
@@ -131,6 +146,7 @@ impl ServerCommandDiceContext for dyn ServerCommandContextTrait + '_ {
             data,
             setup,
             is_nested_invocation,
+            parent_action,
             sanitized_argv,
             exit_when_different_state,
             build_signals,
@@ -150,12 +166,15 @@ impl ServerCommandDiceContext for dyn ServerCommandContextTrait + '_ {
 
                                 let request_metadata = self.request_metadata().await?;
                                 let config_metadata = self.config_metadata(&dice).await?;
+                                let environment_provenance =
+                                    self.environment_provenance(&dice).await?;
 
                                 events
                                     .span_async(
                                         CommandCriticalStart {
                                             metadata: config_metadata.clone(),
                                             dice_version: dice.equality_token().to_string(),
+                                            environment_provenance: Some(environment_provenance),
                                         },
                                         async move {
                                             let res = buck2_build_signals::scope(
@@ -197,7 +216,13 @@ impl ServerCommandDiceContext for dyn ServerCommandContextTrait + '_ {
                             exit_when_different_state,
                             self.cancellation_context(),
                         )
-                        .await,
+                        .await
+                        .map_err(|e| match parent_action {
+                            Some(action) => {
+                                e.context(DiceAccessError::NestedInvocationFromAction(action))
+                            }
+                            None => e,
+                        }),
                     DiceCriticalSectionEnd {},
                 )
             })
@@ -0,0 +1,524 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::io::Write;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use buck2_artifact::artifact::artifact_type::Artifact;
+use buck2_artifact::artifact::provide_outputs::ProvideActionKey;
+use buck2_build_api::actions::calculation::ActionCalculation;
+use buck2_build_api::analysis::calculation::RuleAnalysisCalculation;
+use buck2_build_api::analysis::AnalysisResult;
+use buck2_build_api::artifact_groups::ArtifactGroup;
+use buck2_build_api::artifact_groups::TransitiveSetProjectionKey;
+use buck2_build_api::deferred::calculation::DeferredCalculation;
+use buck2_cli_proto::ClientContext;
+use buck2_client_ctx::path_arg::PathArg;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::dice::data::HasIoProvider;
+use buck2_common::dice::file_ops::HasFileOps;
+use buck2_core::base_deferred_key::BaseDeferredKey;
+use buck2_core::cells::CellResolver;
+use buck2_core::configuration::compatibility::MaybeCompatible;
+use buck2_core::fs::fs_util;
+use buck2_core::package::PackageLabel;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::pattern::ParsedPattern;
+use buck2_core::target::label::TargetLabel;
+use buck2_core::target::name::TargetName;
+use buck2_interpreter::paths::path::OwnedStarlarkPath;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_node::nodes::configured_frontend::ConfiguredTargetNodeCalculation;
+use buck2_node::nodes::frontend::TargetGraphCalculation;
+use buck2_node::target_calculation::ConfiguredTargetCalculation;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::target_platform_from_client_context;
+use dice::DiceComputations;
+use dupe::Dupe;
+use starlark::analysis::find_unused_load_names;
+use starlark::analysis::remove_unused_loads;
+
+use crate::util::paths::starlark_files;
+use crate::StarlarkCommandCommonOptions;
+use crate::StarlarkOpaqueSubcommand;
+
+#[derive(Debug, buck2_error::Error)]
+#[buck2(user)]
+enum StarlarkFixError {
+    #[error("Expected a target label in unused deps file, got `{0}`")]
+    NotATarget(String),
+    #[error("Found {0} unused loads, rerun with `--apply` to remove them")]
+    UnusedLoads(usize),
+    #[error("Found {0} unused deps, which must be removed by hand")]
+    UnusedDeps(usize),
+    #[error(
+        "Found {0} unused loads, rerun with `--apply` to remove them, and {1} unused deps, which must be removed by hand"
+    )]
+    UnusedLoadsAndDeps(usize, usize),
+}
+
+/// Remove unused `load()` statements from Starlark files, and flag the unused deps of the
+/// targets of build files.
+///
+/// A dep is unused if, after analysis, no action of the target reads an output of the dep or of
+/// the targets it depends on, directly or through transitive sets. Only the deps written in the
+/// build file are flagged, and deps only used for their providers are flagged too, so check
+/// them before removing them.
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(name = "starlark-fix")]
+pub struct StarlarkFixCommand {
+    #[clap(flatten)]
+    common_opts: StarlarkCommandCommonOptions,
+
+    #[clap(value_name = "PATH", required = true)]
+    paths: Vec<PathArg>,
+
+    /// Rewrite the files in place rather than only reporting what would be fixed.
+    #[clap(long)]
+    apply: bool,
+
+    /// JSON file mapping targets to more deps they don't use, such as the output of dep file
+    /// based checks, e.g. `{"root//app:app": ["root//lib:unused"]}`. These deps are flagged in
+    /// addition to those found by analysis.
+    #[clap(long, value_name = "PATH")]
+    unused_deps: Option<PathArg>,
+}
+
+fn parse_target(
+    label: &str,
+    cell_resolver: &CellResolver,
+) -> anyhow::Result<(PackageLabel, TargetName)> {
+    match ParsedPattern::<TargetPatternExtra>::parse_precise(
+        label,
+        cell_resolver.root_cell(),
+        cell_resolver,
+    )? {
+        ParsedPattern::Target(package, name, TargetPatternExtra) => Ok((package, name)),
+        _ => Err(StarlarkFixError::NotATarget(label.to_owned()).into()),
+    }
+}
+
+/// The 1-based line of a build file where a dep is written, in any of the forms a label can take.
+fn dep_line(
+    content: &str,
+    package: PackageLabel,
+    dep: &(PackageLabel, TargetName),
+) -> Option<usize> {
+    let (dep_package, dep_name) = dep;
+    let mut forms = vec![
+        format!("{}:{}", dep_package, dep_name),
+        format!("//{}:{}", dep_package.cell_relative_path(), dep_name),
+    ];
+    if *dep_package == package {
+        forms.push(format!(":{}", dep_name));
+    }
+    if dep_package
+        .cell_relative_path()
+        .file_name()
+        .map(|f| f.as_str())
+        == Some(dep_name.as_ref().as_str())
+    {
+        forms.push(format!("//{}", dep_package.cell_relative_path()));
+    }
+    content
+        .lines()
+        .position(|line| {
+            forms.iter().any(|form| {
+                line.contains(&format!("\"{}\"", form)) || line.contains(&format!("'{}'", form))
+            })
+        })
+        .map(|line| line + 1)
+}
+
+/// The unused loads of a file, as messages, and the file without them if `apply` is set and
+/// there are any.
+fn fix_loads(
+    path: &str,
+    content: &str,
+    apply: bool,
+) -> anyhow::Result<(Vec<String>, Option<String>)> {
+    let unused_loads = find_unused_load_names(path, content).map_err(|e| e.into_anyhow())?;
+    let messages = unused_loads
+        .iter()
+        .map(|span| format!("{}: unused load of `{}`", span, span.source_span()))
+        .collect();
+    let fixed = if apply && !unused_loads.is_empty() {
+        remove_unused_loads(path, content).map_err(|e| e.into_anyhow())?
+    } else {
+        None
+    };
+    Ok((messages, fixed))
+}
+
+/// The targets whose outputs the actions of a target read, and the packages of the source
+/// files they read.
+#[derive(Default)]
+struct UsedInputs {
+    owners: HashSet<TargetLabel>,
+    packages: HashSet<PackageLabel>,
+}
+
+impl UsedInputs {
+    fn add_owner(&mut self, owner: Option<&BaseDeferredKey>) {
+        if let Some(BaseDeferredKey::TargetLabel(label)) = owner {
+            self.owners.insert(label.unconfigured().dupe());
+        }
+    }
+
+    fn add_artifact(&mut self, artifact: &Artifact) {
+        match artifact.get_source() {
+            Some(source) => {
+                self.packages.insert(source.get_path().package());
+            }
+            None => self.add_owner(artifact.owner()),
+        }
+    }
+
+    /// Whether an action reads an output of the target, or a source file of its package.
+    fn uses(&self, target: &TargetLabel) -> bool {
+        self.owners.contains(target) || self.packages.contains(&target.pkg())
+    }
+
+    /// Whether an action reads something of the dep or of the targets it depends on.
+    fn uses_dep(&self, dep: &ConfiguredTargetNode) -> bool {
+        let mut seen = HashSet::new();
+        let mut queue = vec![dep];
+        while let Some(node) = queue.pop() {
+            if !seen.insert(node.label()) {
+                continue;
+            }
+            if self.uses(node.label().unconfigured()) {
+                return true;
+            }
+            queue.extend(node.deps());
+        }
+        false
+    }
+
+    /// Collect what the actions of an analysis read, expanding transitive sets.
+    async fn of_analysis(
+        ctx: &DiceComputations,
+        analysis: &AnalysisResult,
+    ) -> anyhow::Result<Self> {
+        let action_keys: Vec<_> = analysis
+            .iter_deferreds()
+            .filter_map(|entry| provider::request_value::<ProvideActionKey>(entry.as_complex()))
+            .collect();
+        let actions =
+            futures::future::try_join_all(action_keys.iter().map(|key| ctx.get_action(&key.0)))
+                .await?;
+
+        let mut used = UsedInputs::default();
+        let mut projections = Vec::new();
+        for action in &actions {
+            for input in action.inputs()?.iter() {
+                used.add_group(input, &mut projections);
+            }
+        }
+        let mut seen = HashSet::new();
+        while let Some(projection) = projections.pop() {
+            if !seen.insert(projection.dupe()) {
+                continue;
+            }
+            used.add_owner(Some(projection.key.deferred_key().owner()));
+            let set = ctx.compute_deferred_data(&projection.key).await?;
+            for input in set
+                .as_transitive_set()
+                .get_projection_sub_inputs(projection.projection)?
+            {
+                used.add_group(&input, &mut projections);
+            }
+        }
+        Ok(used)
+    }
+
+    fn add_group(
+        &mut self,
+        group: &ArtifactGroup,
+        projections: &mut Vec<TransitiveSetProjectionKey>,
+    ) {
+        match group {
+            ArtifactGroup::Artifact(artifact) => self.add_artifact(artifact),
+            ArtifactGroup::TransitiveSetProjection(projection) => {
+                projections.push(projection.dupe())
+            }
+            ArtifactGroup::Promise(promise) => {
+                if let Some(artifact) = promise.get() {
+                    self.add_artifact(artifact);
+                }
+            }
+        }
+    }
+}
+
+/// The deps written in a build file which the targets of its package don't use, as
+/// `(target, dep)`.
+async fn detect_unused_deps(
+    ctx: &DiceComputations,
+    package: PackageLabel,
+    content: &str,
+    target_platform: Option<&TargetLabel>,
+) -> anyhow::Result<Vec<(String, (PackageLabel, TargetName))>> {
+    let targets: Vec<TargetLabel> = ctx
+        .get_interpreter_results(package.dupe())
+        .await?
+        .targets()
+        .keys()
+        .map(|name| TargetLabel::new(package.dupe(), name))
+        .collect();
+
+    let mut unused = Vec::new();
+    for target in targets {
+        let configured = ctx.get_configured_target(&target, target_platform).await?;
+        let node = match ctx.get_configured_target_node(&configured).await? {
+            MaybeCompatible::Compatible(node) => node,
+            MaybeCompatible::Incompatible(_) => continue,
+        };
+        let analysis = match ctx
+            .get_analysis_result(&configured)
+            .await
+            .with_context(|| format!("Analyzing `{}`", target))?
+        {
+            MaybeCompatible::Compatible(analysis) => analysis,
+            MaybeCompatible::Incompatible(_) => continue,
+        };
+        let used = UsedInputs::of_analysis(ctx, &analysis).await?;
+        for dep in node.target_deps() {
+            let label = dep.label().unconfigured();
+            let dep_label = (label.pkg(), label.name().to_owned());
+            if dep_line(content, package.dupe(), &dep_label).is_none() || used.uses_dep(dep) {
+                continue;
+            }
+            unused.push((target.to_string(), dep_label));
+        }
+    }
+    Ok(unused)
+}
+
+fn check_result(unused_loads: usize, unused_deps: usize) -> anyhow::Result<()> {
+    match (unused_loads, unused_deps) {
+        (0, 0) => Ok(()),
+        (loads, 0) => Err(StarlarkFixError::UnusedLoads(loads).into()),
+        (0, deps) => Err(StarlarkFixError::UnusedDeps(deps).into()),
+        (loads, deps) => Err(StarlarkFixError::UnusedLoadsAndDeps(loads, deps).into()),
+    }
+}
+
+#[async_trait]
+impl StarlarkOpaqueSubcommand for StarlarkFixCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, mut ctx| {
+                let target_platform =
+                    target_platform_from_client_context(&client_ctx, server_ctx, &mut ctx).await?;
+                let cell_resolver = ctx.get_cell_resolver().await?;
+                let fs = ctx.file_ops();
+                let io = ctx.global_data().get_io_provider();
+
+                let mut unused_deps: BTreeMap<PackageLabel, Vec<_>> = BTreeMap::new();
+                if let Some(path) = &self.unused_deps {
+                    let path = path.resolve(server_ctx.working_dir_abs());
+                    let deps: BTreeMap<String, Vec<String>> =
+                        serde_json::from_str(&fs_util::read_to_string(&path)?)
+                            .with_context(|| format!("Parsing `{}`", path.display()))?;
+                    for (target, deps) in deps {
+                        let (package, _name) = parse_target(&target, &cell_resolver)?;
+                        for dep in deps {
+                            unused_deps
+                                .entry(package.dupe())
+                                .or_default()
+                                .push((target.clone(), parse_target(&dep, &cell_resolver)?));
+                        }
+                    }
+                }
+
+                let mut stdout = stdout.as_writer();
+                let mut fixed = 0;
+                let mut unfixed = 0;
+                let mut deps_found = 0;
+                let files =
+                    starlark_files(&self.paths, server_ctx, &cell_resolver, &fs, &*io).await?;
+                for file in &files {
+                    let proj_path =
+                        cell_resolver.resolve_path(file.borrow().path().as_ref().as_ref())?;
+                    let path_str = proj_path.to_string();
+                    let content = io
+                        .read_file_if_exists(proj_path.clone())
+                        .await?
+                        .with_context(|| format!("File not found: `{}`", path_str))?;
+
+                    let (unused_loads, fixed_content) = fix_loads(&path_str, &content, self.apply)?;
+                    for message in &unused_loads {
+                        writeln!(stdout, "{}", message)?;
+                    }
+                    if let Some(fixed_content) = fixed_content {
+                        fs_util::write(
+                            server_ctx.project_root().resolve(&proj_path),
+                            fixed_content,
+                        )?;
+                    }
+                    if self.apply {
+                        fixed += unused_loads.len();
+                    } else {
+                        unfixed += unused_loads.len();
+                    }
+
+                    if let OwnedStarlarkPath::BuildFile(build_file) = file {
+                        let package = build_file.package();
+                        let mut deps = detect_unused_deps(
+                            &ctx,
+                            package.dupe(),
+                            &content,
+                            target_platform.as_ref(),
+                        )
+                        .await?;
+                        for dep in unused_deps.get(&package).into_iter().flatten() {
+                            if !deps.contains(dep) {
+                                deps.push(dep.clone());
+                            }
+                        }
+                        for (target, dep) in &deps {
+                            deps_found += 1;
+                            let location = match dep_line(&content, package.dupe(), dep) {
+                                Some(line) => format!("{}:{}", path_str, line),
+                                None => path_str.clone(),
+                            };
+                            writeln!(
+                                stdout,
+                                "{}: `{}` does not use dep `{}:{}`",
+                                location, target, dep.0, dep.1
+                            )?;
+                        }
+                    }
+                }
+
+                if fixed > 0 {
+                    writeln!(server_ctx.stderr()?, "Removed {} unused loads", fixed)?;
+                }
+                if unfixed == 0 && deps_found == 0 {
+                    writeln!(
+                        server_ctx.stderr()?,
+                        "Found nothing to fix in {} files",
+                        files.len()
+                    )?;
+                }
+                check_result(unfixed, deps_found)
+            })
+            .await
+    }
+
+    fn common_opts(&self) -> &StarlarkCommandCommonOptions {
+        &self.common_opts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dep(package: &str, name: &str) -> (PackageLabel, TargetName) {
+        (
+            PackageLabel::testing_new("root", package),
+            TargetName::unchecked_new(name),
+        )
+    }
+
+    #[test]
+    fn test_fix_loads() -> anyhow::Result<()> {
+        let content =
+            "load(\":defs.bzl\", \"unused\", \"used\")\nload(\":other.bzl\", \"gone\")\nused()\n";
+
+        let (messages, fixed) = fix_loads("app/BUCK", content, false)?;
+        assert_eq!(2, messages.len());
+        assert!(messages[0].starts_with("app/BUCK:1:"), "{}", messages[0]);
+        assert!(messages[0].contains("unused"), "{}", messages[0]);
+        assert!(messages[1].starts_with("app/BUCK:2:"), "{}", messages[1]);
+        assert!(messages[1].contains("gone"), "{}", messages[1]);
+        assert_eq!(None, fixed);
+
+        let (messages, fixed) = fix_loads("app/BUCK", content, true)?;
+        assert_eq!(2, messages.len());
+        assert_eq!(
+            Some("load(\":defs.bzl\",  \"used\")\n\nused()\n"),
+            fixed.as_deref()
+        );
+
+        let used = "load(\":defs.bzl\", \"used\")\nused()\n";
+        assert_eq!((Vec::new(), None), fix_loads("app/BUCK", used, true)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dep_line() {
+        let content = r#"rust_library(
+    name = "app",
+    deps = [
+        ":helper",
+        "//lib:lib",
+        "root//other:other_lib",
+        '//quoted:quoted',
+        "//short",
+    ],
+)
+"#;
+        let package = PackageLabel::testing_new("root", "app");
+        let line = |d| dep_line(content, package.dupe(), &d);
+        assert_eq!(Some(4), line(dep("app", "helper")));
+        assert_eq!(Some(5), line(dep("lib", "lib")));
+        assert_eq!(Some(6), line(dep("other", "other_lib")));
+        assert_eq!(Some(7), line(dep("quoted", "quoted")));
+        assert_eq!(Some(8), line(dep("short", "short")));
+        // `:helper` only refers to a target of the package of the build file.
+        assert_eq!(None, line(dep("lib", "helper")));
+        // Implicit deps, e.g. toolchains, aren't written in the build file.
+        assert_eq!(None, line(dep("toolchains", "rust")));
+    }
+
+    #[test]
+    fn test_used_inputs() {
+        let mut used = UsedInputs::default();
+        used.owners
+            .insert(TargetLabel::testing_parse("root//lib:built"));
+        used.packages
+            .insert(PackageLabel::testing_new("root", "headers"));
+
+        assert!(used.uses(&TargetLabel::testing_parse("root//lib:built")));
+        assert!(!used.uses(&TargetLabel::testing_parse("root//lib:other")));
+        assert!(used.uses(&TargetLabel::testing_parse("root//headers:any")));
+        assert!(!used.uses(&TargetLabel::testing_parse("root//unused:unused")));
+    }
+
+    #[test]
+    fn test_check_result_counts() {
+        assert!(check_result(0, 0).is_ok());
+        let message = |loads, deps| check_result(loads, deps).unwrap_err().to_string();
+        assert_eq!(
+            "Found 2 unused loads, rerun with `--apply` to remove them",
+            message(2, 0)
+        );
+        assert_eq!(
+            "Found 3 unused deps, which must be removed by hand",
+            message(0, 3)
+        );
+        assert_eq!(
+            "Found 2 unused loads, rerun with `--apply` to remove them, and 3 unused deps, which must be removed by hand",
+            message(2, 3)
+        );
+    }
+}
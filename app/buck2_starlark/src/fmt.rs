@@ -0,0 +1,106 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::path_arg::PathArg;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_path::AbsPathBuf;
+use starlark_syntax::format::format_starlark;
+use starlark_syntax::format::FormatOptions;
+
+#[derive(Debug, buck2_error::Error)]
+#[buck2(user)]
+enum StarlarkFmtError {
+    #[error("{0} file(s) are not formatted, run `buck2 starlark fmt` to format them")]
+    NotFormatted(usize),
+}
+
+/// Normalize the whitespace and quotes of BUCK and `.bzl` files.
+///
+/// Only whitespace and string delimiters are changed: trailing whitespace and extra blank lines
+/// are removed, and string literals use double quotes. Indentation, line breaks and the order of
+/// load symbols or list items are left as they are, so this does not replace buildifier.
+#[derive(Debug, clap::Parser)]
+#[clap(name = "starlark-fmt")]
+pub struct StarlarkFmtCommand {
+    /// Files to format, or directories to format the Starlark files of.
+    #[clap(value_name = "PATH", required = true)]
+    paths: Vec<PathArg>,
+
+    /// Don't write the files, list those which are not formatted and fail if there are any.
+    #[clap(long)]
+    check: bool,
+
+    /// Keep the quotes of string literals as they are.
+    #[clap(long)]
+    no_normalize_quotes: bool,
+}
+
+/// Whether a file found in a directory is a Starlark file.
+fn is_starlark_file(name: &str) -> bool {
+    matches!(
+        name,
+        "BUCK" | "BUCK.v2" | "TARGETS" | "TARGETS.v2" | "PACKAGE"
+    ) || name.ends_with(".bzl")
+        || name.ends_with(".bxl")
+}
+
+fn collect_files(path: AbsPathBuf, files: &mut Vec<AbsPathBuf>) -> anyhow::Result<()> {
+    if !fs_util::symlink_metadata(&path)?.is_dir() {
+        files.push(path);
+        return Ok(());
+    }
+    let mut entries = std::fs::read_dir(&path)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else { continue };
+        if name.starts_with('.') || name == "buck-out" {
+            continue;
+        }
+        if entry.file_type()?.is_dir() || is_starlark_file(name) {
+            collect_files(AbsPathBuf::new(entry.path())?, files)?;
+        }
+    }
+    Ok(())
+}
+
+impl StarlarkFmtCommand {
+    pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
+        let options = FormatOptions {
+            double_quotes: !self.no_normalize_quotes,
+        };
+        let mut files = Vec::new();
+        for path in &self.paths {
+            collect_files(path.resolve(&ctx.working_dir), &mut files)?;
+        }
+
+        let mut unformatted = 0;
+        for file in files {
+            let content = fs_util::read_to_string(&file)?;
+            let formatted = format_starlark(&file.display().to_string(), &content, &options)
+                .map_err(|e| e.into_anyhow())?;
+            if formatted == content {
+                continue;
+            }
+            unformatted += 1;
+            if self.check {
+                buck2_client_ctx::println!("{}", file.display())?;
+            } else {
+                fs_util::write(&file, formatted)?;
+            }
+        }
+
+        if self.check && unformatted != 0 {
+            return ExitResult::err(StarlarkFmtError::NotFormatted(unformatted).into());
+        }
+        ExitResult::success()
+    }
+}
@@ -29,11 +29,17 @@ use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
 
 use crate::debug::StarlarkDebugAttachCommand;
+use crate::fix::StarlarkFixCommand;
+use crate::fmt::StarlarkFmtCommand;
 use crate::lint::StarlarkLintCommand;
+use crate::rule_test::StarlarkRuleTestCommand;
 use crate::typecheck::StarlarkTypecheckCommand;
 
 mod debug;
+mod fix;
+mod fmt;
 mod lint;
+mod rule_test;
 pub mod server;
 mod typecheck;
 mod util;
@@ -44,6 +50,7 @@ pub enum StarlarkCommand {
     #[clap(flatten)]
     Opaque(StarlarkOpaqueCommand),
     DebugAttach(StarlarkDebugAttachCommand),
+    Fmt(StarlarkFmtCommand),
 }
 
 // Used for subcommands that follow `buck2 audit`'s "opaque" pattern where the command object is serialized
@@ -53,6 +60,8 @@ pub enum StarlarkCommand {
 pub enum StarlarkOpaqueCommand {
     Lint(StarlarkLintCommand),
     Typecheck(StarlarkTypecheckCommand),
+    RuleTest(StarlarkRuleTestCommand),
+    Fix(StarlarkFixCommand),
 }
 
 #[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize, Default)]
@@ -94,6 +103,8 @@ impl StarlarkOpaqueCommand {
         match self {
             Self::Lint(cmd) => cmd,
             Self::Typecheck(cmd) => cmd,
+            Self::RuleTest(cmd) => cmd,
+            Self::Fix(cmd) => cmd,
         }
     }
 }
@@ -146,6 +157,7 @@ impl StarlarkCommand {
         match self {
             StarlarkCommand::Opaque(cmd) => cmd.exec(matches, ctx),
             StarlarkCommand::DebugAttach(cmd) => cmd.exec(matches, ctx),
+            StarlarkCommand::Fmt(cmd) => cmd.exec(matches, ctx),
         }
     }
 
@@ -0,0 +1,228 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::io::Write;
+
+use async_trait::async_trait;
+use buck2_artifact::artifact::provide_outputs::ProvideActionKey;
+use buck2_build_api::actions::artifact::get_artifact_fs::GetArtifactFs;
+use buck2_build_api::actions::calculation::ActionCalculation;
+use buck2_build_api::analysis::calculation::RuleAnalysisCalculation;
+use buck2_build_api::interpreter::rule_defs::provider::json::ProvidersJson;
+use buck2_cli_proto::ClientContext;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::dice::file_ops::HasFileOps;
+use buck2_common::pattern::resolve::resolve_target_patterns;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::file_name::FileName;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::pattern::PackageSpec;
+use buck2_core::target::label::TargetLabel;
+use buck2_node::nodes::frontend::TargetGraphCalculation;
+use buck2_node::target_calculation::ConfiguredTargetCalculation;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use buck2_server_ctx::pattern::target_platform_from_client_context;
+use dupe::Dupe;
+use gazebo::prelude::*;
+use gazebo::variants::VariantName;
+
+use crate::StarlarkCommandCommonOptions;
+use crate::StarlarkOpaqueSubcommand;
+
+/// Directory, next to the `BUCK` file of the fixture targets, holding their golden files.
+const GOLDEN_DIR: &str = "goldens";
+
+#[derive(Debug, buck2_error::Error)]
+#[buck2(user)]
+enum RuleTestError {
+    #[error(
+        "{0} of {1} targets did not match their golden files. Rerun with `--update` to accept the changes."
+    )]
+    Mismatch(usize, usize),
+}
+
+/// Compare the analysis of fixture targets with golden files.
+///
+/// For each target, the providers (in the format of `buck2 audit providers --json`) and the
+/// actions it registers are compared with `goldens/<target>.json`, next to the `BUCK` file
+/// defining the target. Nothing is built.
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(name = "starlark-rule-test")]
+pub struct StarlarkRuleTestCommand {
+    #[clap(flatten)]
+    common_opts: StarlarkCommandCommonOptions,
+
+    #[clap(value_name = "TARGET_PATTERNS", required = true)]
+    patterns: Vec<String>,
+
+    /// Write the current analysis results to the golden files instead of comparing them.
+    #[clap(long)]
+    update: bool,
+}
+
+#[async_trait]
+impl StarlarkOpaqueSubcommand for StarlarkRuleTestCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, mut ctx| {
+                let cells = ctx.get_cell_resolver().await?;
+                let artifact_fs = ctx.get_artifact_fs().await?;
+                let target_platform =
+                    target_platform_from_client_context(&client_ctx, server_ctx, &mut ctx).await?;
+
+                let parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &mut ctx,
+                    &self
+                        .patterns
+                        .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
+                    server_ctx.working_dir(),
+                )
+                .await?;
+                let resolved_pattern =
+                    resolve_target_patterns(&cells, &parsed_patterns, &ctx.file_ops()).await?;
+
+                let mut stdout = stdout.as_writer();
+                let mut total = 0;
+                let mut mismatches = 0;
+
+                for (package, spec) in resolved_pattern.specs {
+                    let targets = match spec {
+                        PackageSpec::Targets(targets) => targets.into_map(|(t, _)| t),
+                        PackageSpec::All => ctx
+                            .get_interpreter_results(package.dupe())
+                            .await?
+                            .targets()
+                            .keys()
+                            .map(|t| t.to_owned())
+                            .collect(),
+                    };
+                    let golden_dir = server_ctx.project_root().resolve(
+                        &cells
+                            .resolve_package(package.dupe())?
+                            .join(ForwardRelativePath::unchecked_new(GOLDEN_DIR)),
+                    );
+
+                    for target in targets {
+                        let label = TargetLabel::new(package.dupe(), target.as_ref());
+                        let configured_target = ctx
+                            .get_configured_target(&label, target_platform.as_ref())
+                            .await?;
+                        let analysis = ctx
+                            .get_analysis_result(&configured_target)
+                            .await?
+                            .require_compatible()?;
+
+                        let action_keys: Vec<_> = analysis
+                            .iter_deferreds()
+                            .filter_map(|entry| {
+                                provider::request_value::<ProvideActionKey>(entry.as_complex())
+                            })
+                            .collect();
+                        let actions = futures::future::try_join_all(
+                            action_keys.iter().map(|key| ctx.get_action(&key.0)),
+                        )
+                        .await?;
+                        let actions = actions.try_map(|action| {
+                            anyhow::Ok(serde_json::json!({
+                                "kind": action.kind().variant_name(),
+                                "category": action.category().as_str(),
+                                "identifier": action.identifier(),
+                                "inputs": action.inputs()?.len(),
+                                "outputs": action
+                                    .outputs()?
+                                    .iter()
+                                    .map(|o| artifact_fs.resolve_build(o.get_path()).to_string())
+                                    .collect::<Vec<_>>(),
+                            }))
+                        })?;
+
+                        let providers = serde_json::to_value(ProvidersJson {
+                            collection: analysis.providers().provider_collection(),
+                            artifact_fs: &artifact_fs,
+                        })?;
+                        let mut snapshot = serde_json::to_string_pretty(&serde_json::json!({
+                            "providers": providers,
+                            "actions": actions,
+                        }))?;
+                        snapshot.push('\n');
+
+                        let golden_path =
+                            golden_dir.join(FileName::new(&format!("{}.json", target))?);
+                        let golden = fs_util::read_to_string_if_exists(&golden_path)?;
+                        total += 1;
+
+                        if golden.as_deref() == Some(snapshot.as_str()) {
+                            writeln!(stdout, "PASS {}", label)?;
+                        } else if self.update {
+                            fs_util::create_dir_all(&golden_dir)?;
+                            fs_util::write(&golden_path, &snapshot)?;
+                            writeln!(stdout, "UPDATED {} ({})", label, golden_path)?;
+                        } else {
+                            mismatches += 1;
+                            match golden {
+                                None => {
+                                    writeln!(stdout, "FAIL {}: missing {}", label, golden_path)?
+                                }
+                                Some(golden) => {
+                                    writeln!(
+                                        stdout,
+                                        "FAIL {}: differs from {}",
+                                        label, golden_path
+                                    )?;
+                                    write_first_difference(&mut stdout, &golden, &snapshot)?;
+                                }
+                            }
+                        }
+                    }
+                }
+
+                if mismatches > 0 {
+                    return Err(RuleTestError::Mismatch(mismatches, total).into());
+                }
+                Ok(())
+            })
+            .await
+    }
+
+    fn common_opts(&self) -> &StarlarkCommandCommonOptions {
+        &self.common_opts
+    }
+}
+
+/// Show the first line where the golden file and the current snapshot differ.
+fn write_first_difference(
+    out: &mut impl Write,
+    expected: &str,
+    actual: &str,
+) -> anyhow::Result<()> {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    for line in 1.. {
+        let (e, a) = (expected_lines.next(), actual_lines.next());
+        if e != a {
+            writeln!(out, "  line {}:", line)?;
+            writeln!(out, "  - {}", e.unwrap_or("<end of file>"))?;
+            writeln!(out, "  + {}", a.unwrap_or("<end of file>"))?;
+            break;
+        }
+        if e.is_none() {
+            break;
+        }
+    }
+    Ok(())
+}
@@ -27,6 +27,7 @@ use buck2_cli_proto::TestResponse;
 use buck2_common::dice::cells::HasCellResolver;
 use buck2_common::dice::file_ops::HasFileOps;
 use buck2_common::events::HasEvents;
+use buck2_common::invocation_paths::InvocationPaths;
 use buck2_common::legacy_configs::dice::HasLegacyConfigs;
 use buck2_common::liveliness_observer::LivelinessGuard;
 use buck2_common::liveliness_observer::LivelinessObserver;
@@ -38,6 +39,7 @@ use buck2_core::cells::CellResolver;
 use buck2_core::configuration::compatibility::MaybeCompatible;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_core::package::PackageLabel;
 use buck2_core::pattern::pattern_type::ConfiguredProvidersPatternExtra;
@@ -52,7 +54,11 @@ use buck2_events::dispatch::console_message;
 use buck2_events::dispatch::with_dispatcher_async;
 use buck2_events::errors::create_error_report;
 use buck2_futures::cancellation::CancellationContext;
+use buck2_node::attrs::fmt_context::AttrFmtContext;
+use buck2_node::attrs::inspect_options::AttrInspectOptions;
+use buck2_node::attrs::json::ToJsonWithContext;
 use buck2_node::load_patterns::MissingTargetBehavior;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
 use buck2_node::nodes::configured_frontend::ConfiguredTargetNodeCalculation;
 use buck2_node::nodes::eval_result::EvaluationResult;
 use buck2_node::nodes::frontend::TargetGraphCalculation;
@@ -88,11 +94,17 @@ use crate::downward_api::BuckTestDownwardApi;
 use crate::executor_launcher::ExecutorLaunch;
 use crate::executor_launcher::ExecutorLauncher;
 use crate::executor_launcher::OutOfProcessTestExecutor;
+use crate::impact::TestImpactFilter;
+use crate::label_expr::LabelExpr;
 use crate::local_resource_registry::LocalResourceRegistry;
 use crate::orchestrator::BuckTestOrchestrator;
 use crate::orchestrator::ExecutorMessage;
 use crate::session::TestSession;
 use crate::session::TestSessionOptions;
+use crate::test_history::external_test_history;
+use crate::test_history::SqliteTestHistory;
+use crate::test_history::TestHistory;
+use crate::test_history::TestId;
 use crate::translations::build_configured_target_handle;
 
 #[derive(Debug, Serialize)]
@@ -115,10 +127,18 @@ impl TestOutcome {
             // the client to delegate the exit code generation.
             return Ok(None);
         }
-        self.executor_report
+        let exit_code = self
+            .executor_report
             .exit_code
-            .context("Test executor did not provide an exit code")
-            .map(Some)
+            .context("Test executor did not provide an exit code")?;
+        // The executor fails the run if quarantined tests failed, but those must not fail the
+        // command. Any other failure of the executor is kept.
+        if exit_code == TESTS_FAILED_EXIT_CODE
+            && self.executor_report.statuses.only_quarantined_failures()
+        {
+            return Ok(Some(0));
+        }
+        Ok(Some(exit_code))
     }
 }
 
@@ -130,10 +150,10 @@ struct ExecutorReport {
 }
 
 impl ExecutorReport {
-    fn ingest(&mut self, status: &ExecutorMessage) {
+    fn ingest(&mut self, status: &ExecutorMessage, quarantined: bool) {
         match status {
             ExecutorMessage::TestResult(res) => {
-                self.statuses.ingest(res);
+                self.statuses.ingest(res, quarantined);
             }
             ExecutorMessage::ExitCode(exit_code) => {
                 self.exit_code = Some(*exit_code);
@@ -146,6 +166,18 @@ impl ExecutorReport {
 }
 
 const MAX_EXAMPLE_VALUES: u64 = 10;
+
+/// Number of recent runs of a test considered to decide whether it is flaky, unless overridden
+/// by `test.flaky_history_window`.
+const DEFAULT_FLAKY_HISTORY_WINDOW: u32 = 20;
+
+/// Number of times the history of a test must flip between passing and failing for it to be
+/// flaky, unless overridden by `test.flaky_min_flips`.
+const DEFAULT_FLAKY_MIN_FLIPS: u32 = 2;
+
+/// Exit code of the test executor when tests failed, as opposed to the executor itself failing.
+const TESTS_FAILED_EXIT_CODE: i32 = 32;
+
 struct CounterWithExamples {
     count: u64,
     max: u64,
@@ -186,9 +218,15 @@ struct TestStatuses {
     fatals: CounterWithExamples,
     listing_success: CounterWithExamples,
     listing_failed: CounterWithExamples,
+    /// Failures of known-flaky tests, which do not fail the command.
+    quarantined: CounterWithExamples,
 }
 impl TestStatuses {
-    fn ingest(&mut self, result: &TestResult) {
+    fn ingest(&mut self, result: &TestResult, quarantined: bool) {
+        if quarantined {
+            self.quarantined.add(&result.name);
+            return;
+        }
         match result.status {
             TestStatus::PASS => self.passed.add(&result.name),
             TestStatus::FAIL => self.failed.add(&result.name),
@@ -196,12 +234,20 @@ impl TestStatuses {
             TestStatus::OMITTED => self.skipped.add(&result.name),
             TestStatus::FATAL => self.fatals.add(&result.name),
             TestStatus::TIMEOUT => self.failed.add(&result.name),
+            TestStatus::MEMORY_LIMIT_EXCEEDED => self.failed.add(&result.name),
             TestStatus::UNKNOWN => {}
             TestStatus::RERUN => {}
             TestStatus::LISTING_SUCCESS => self.listing_success.add(&result.name),
             TestStatus::LISTING_FAILED => self.listing_failed.add(&result.name),
         }
     }
+
+    fn only_quarantined_failures(&self) -> bool {
+        self.quarantined.count > 0
+            && self.failed.count == 0
+            && self.fatals.count == 0
+            && self.listing_failed.count == 0
+    }
 }
 
 async fn test_command(
@@ -321,6 +367,59 @@ async fn test(
         .build_opts
         .as_ref()
         .expect("should have build options");
+    let test_history = if ctx
+        .parse_legacy_config_property(cell_resolver.root_cell(), "test", "quarantine_flaky_tests")
+        .await?
+        .unwrap_or(false)
+    {
+        match external_test_history() {
+            Some(history) => Some(history),
+            None => {
+                let window = ctx
+                    .parse_legacy_config_property(
+                        cell_resolver.root_cell(),
+                        "test",
+                        "flaky_history_window",
+                    )
+                    .await?
+                    .unwrap_or(DEFAULT_FLAKY_HISTORY_WINDOW);
+                let min_flips = ctx
+                    .parse_legacy_config_property(
+                        cell_resolver.root_cell(),
+                        "test",
+                        "flaky_min_flips",
+                    )
+                    .await?
+                    .unwrap_or(DEFAULT_FLAKY_MIN_FLIPS);
+                let path = server_ctx.project_root().resolve(
+                    &InvocationPaths::buck_out_dir_prefix()
+                        .join(server_ctx.isolation_prefix())
+                        .join(ForwardRelativePath::unchecked_new(
+                            "cache/test_history.sqlite",
+                        )),
+                );
+                // The history only quarantines failures, running the tests does not depend on it.
+                match SqliteTestHistory::open(path, window, min_flips).await {
+                    Ok(history) => Some(Arc::new(history) as Arc<dyn TestHistory>),
+                    Err(e) => {
+                        tracing::warn!(
+                            "Failed to open test history, not quarantining flaky tests: {:#}",
+                            e
+                        );
+                        None
+                    }
+                }
+            }
+        }
+    } else {
+        None
+    };
+    let impact_filter = request
+        .impact
+        .as_ref()
+        .map(|impact| TestImpactFilter::new(impact, &cell_resolver))
+        .transpose()?
+        .map(Arc::new);
     let test_outcome = test_targets(
         ctx,
         resolved_pattern,
@@ -331,7 +430,12 @@ async fn test(
             request.excluded_labels.clone(),
             request.always_exclude,
             request.build_filtered_targets,
+            (!request.label_expression.is_empty())
+                .then(|| LabelExpr::parse(&request.label_expression))
+                .transpose()?,
         )),
+        impact_filter.dupe(),
+        test_history,
         &*launcher,
         session,
         cell_resolver,
@@ -387,6 +491,13 @@ async fn test(
                 .listing_failed
                 .to_cli_proto_counter(),
         ),
+        quarantined: Some(
+            test_outcome
+                .executor_report
+                .statuses
+                .quarantined
+                .to_cli_proto_counter(),
+        ),
     };
 
     Ok(TestResponse {
@@ -396,6 +507,7 @@ async fn test(
         executor_stdout: test_outcome.executor_stdout,
         executor_stderr: test_outcome.executor_stderr,
         executor_info_messages: test_outcome.executor_report.info_messages,
+        impact_report: impact_filter.map(|f| f.report()),
     })
 }
 
@@ -405,6 +517,8 @@ async fn test_targets(
     global_target_platform: Option<TargetLabel>,
     external_runner_args: Vec<String>,
     label_filtering: Arc<TestLabelFiltering>,
+    impact_filter: Option<Arc<TestImpactFilter>>,
+    test_history: Option<Arc<dyn TestHistory>>,
     launcher: &dyn ExecutorLauncher,
     session: TestSession,
     cell_resolver: CellResolver,
@@ -447,7 +561,7 @@ async fn test_targets(
 
     let test_executor = Arc::new(test_executor) as Arc<dyn TestExecutor>;
 
-    let (test_status_sender, test_status_receiver) = mpsc::unbounded();
+    let (test_status_sender, mut test_status_receiver) = mpsc::unbounded();
 
     let test_server = tokio::spawn({
         let test_status_sender = test_status_sender.clone();
@@ -475,6 +589,7 @@ async fn test_targets(
                 let mut driver = TestDriver::new(TestDriverState {
                     ctx: &ctx,
                     label_filtering: &label_filtering,
+                    impact_filter: impact_filter.as_deref(),
                     global_target_platform: &global_target_platform,
                     session: &session,
                     test_executor: &test_executor,
@@ -510,13 +625,17 @@ async fn test_targets(
 
                 // Wait for the tests to finish running.
 
-                let test_statuses = test_status_receiver
-                    .try_fold(ExecutorReport::default(), |mut acc, result| {
-                        acc.ingest(&result);
-                        future::ready(Ok(acc))
-                    })
-                    .await
-                    .context("Did not receive all results from executor")?;
+                let test_statuses = async {
+                    let mut report = ExecutorReport::default();
+                    while let Some(message) = test_status_receiver.try_next().await? {
+                        let quarantined =
+                            record_test_history(test_history.as_deref(), &session, &message).await;
+                        report.ingest(&message, quarantined);
+                    }
+                    anyhow::Ok(report)
+                }
+                .await
+                .context("Did not receive all results from executor")?;
 
                 // Shutdown our server. This is technically not *required* since dropping it would shut it
                 // down implicitly, but let's do it anyway so we can collect any errors.
@@ -594,6 +713,7 @@ enum TestDriverTask {
 pub(crate) struct TestDriverState<'a, 'e> {
     ctx: &'a DiceComputations,
     label_filtering: &'a Arc<TestLabelFiltering>,
+    impact_filter: Option<&'a TestImpactFilter>,
     global_target_platform: &'a Option<TargetLabel>,
     session: &'a TestSession,
     test_executor: &'a Arc<dyn TestExecutor + 'e>,
@@ -769,6 +889,7 @@ impl<'a, 'e> TestDriver<'a, 'e> {
                 state.test_executor.dupe(),
                 state.session,
                 state.label_filtering.dupe(),
+                state.impact_filter,
                 state.cell_resolver,
                 state.working_dir_cell,
             )
@@ -781,6 +902,44 @@ impl<'a, 'e> TestDriver<'a, 'e> {
     }
 }
 
+/// Record a test result in the test history, and decide whether it is a failure of a known-flaky
+/// test which should be quarantined. Failures to access the history are not fatal.
+async fn record_test_history(
+    history: Option<&dyn TestHistory>,
+    session: &TestSession,
+    message: &ExecutorMessage,
+) -> bool {
+    let (Some(history), ExecutorMessage::TestResult(result)) = (history, message) else {
+        return false;
+    };
+    let passed = match result.status {
+        TestStatus::PASS => true,
+        TestStatus::FAIL | TestStatus::TIMEOUT => false,
+        _ => return false,
+    };
+    let res = async {
+        let test = TestId {
+            target: session
+                .get(result.target)?
+                .target()
+                .unconfigured()
+                .to_string(),
+            name: result.name.clone(),
+        };
+        let quarantined = !passed && history.is_flaky(&test).await?;
+        history.record(&test, passed).await?;
+        anyhow::Ok(quarantined)
+    }
+    .await;
+    match res {
+        Ok(quarantined) => quarantined,
+        Err(e) => {
+            tracing::warn!("Failed to update test history: {:#}", e);
+            false
+        }
+    }
+}
+
 struct SpecTargets {
     labels: Vec<(TargetName, ProvidersPatternExtra)>,
     /// Indicates whether this should be skipped if incompatible.
@@ -823,6 +982,7 @@ async fn test_target(
     test_executor: Arc<dyn TestExecutor + '_>,
     session: &TestSession,
     label_filtering: Arc<TestLabelFiltering>,
+    impact_filter: Option<&TestImpactFilter>,
     cell_resolver: &CellResolver,
     working_dir_cell: CellName,
 ) -> anyhow::Result<Option<ConfiguredProvidersLabel>> {
@@ -831,11 +991,33 @@ async fn test_target(
     // in v1: https://fb.workplace.com/groups/buckeng/posts/8520953297953210
     let frozen_providers = ctx.get_providers(&target).await?.require_compatible()?;
     let providers = frozen_providers.provider_collection();
-    build_artifacts(ctx, providers, &label_filtering).await?;
+    let node = if label_filtering.label_expression.is_some() || impact_filter.is_some() {
+        Some(
+            ctx.get_configured_target_node(target.target())
+                .await?
+                .require_compatible()?,
+        )
+    } else {
+        None
+    };
+    if let (Some(impact_filter), Some(node), Some(test_info)) = (
+        impact_filter,
+        &node,
+        <dyn TestProvider>::from_collection(providers),
+    ) {
+        if !impact_filter.check(node, test_info)? {
+            return Ok(None);
+        }
+    }
+    let rule_labels = match &node {
+        Some(node) if label_filtering.label_expression.is_some() => rule_labels(node)?,
+        _ => Vec::new(),
+    };
+    build_artifacts(ctx, providers, &label_filtering, &rule_labels).await?;
 
     let fut = match <dyn TestProvider>::from_collection(providers) {
         Some(test_info) => {
-            if skip_run_based_on_labels(test_info, &label_filtering) {
+            if skip_run_based_on_labels(test_info, &label_filtering, &rule_labels) {
                 return Ok(None);
             }
             run_tests(
@@ -861,33 +1043,62 @@ async fn test_target(
     fut.await
 }
 
+/// The `labels` attribute of the test rule, which label expressions are evaluated against in
+/// addition to the labels provided to the external runner.
+fn rule_labels(node: &ConfiguredTargetNode) -> anyhow::Result<Vec<String>> {
+    let labels = match node.get("labels", AttrInspectOptions::All) {
+        Some(attr) => attr.value.to_json(&AttrFmtContext::NO_CONTEXT)?,
+        None => return Ok(Vec::new()),
+    };
+    Ok(match labels {
+        serde_json::Value::Array(labels) => labels
+            .into_iter()
+            .filter_map(|l| match l {
+                serde_json::Value::String(l) => Some(l),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    })
+}
+
 fn skip_run_based_on_labels(
     provider: &dyn TestProvider,
     label_filtering: &TestLabelFiltering,
+    rule_labels: &[String],
 ) -> bool {
-    let target_labels = provider.labels();
+    let mut target_labels = provider.labels();
+    for label in rule_labels {
+        if !target_labels.contains(&label.as_str()) {
+            target_labels.push(label);
+        }
+    }
     label_filtering.is_excluded(target_labels)
 }
 
 fn skip_build_based_on_labels(
     provider: &dyn TestProvider,
     label_filtering: &TestLabelFiltering,
+    rule_labels: &[String],
 ) -> bool {
-    !label_filtering.build_filtered_targets && skip_run_based_on_labels(provider, label_filtering)
+    !label_filtering.build_filtered_targets
+        && skip_run_based_on_labels(provider, label_filtering, rule_labels)
 }
 
 async fn build_artifacts(
     ctx: &DiceComputations,
     providers: &FrozenProviderCollection,
     label_filtering: &TestLabelFiltering,
+    rule_labels: &[String],
 ) -> anyhow::Result<()> {
     fn get_artifacts_to_build(
         label_filtering: &TestLabelFiltering,
         providers: &FrozenProviderCollection,
+        rule_labels: &[String],
     ) -> anyhow::Result<IndexSet<ArtifactGroup>> {
         Ok(match <dyn TestProvider>::from_collection(providers) {
             Some(provider) => {
-                if skip_build_based_on_labels(provider, label_filtering) {
+                if skip_build_based_on_labels(provider, label_filtering, rule_labels) {
                     return Ok(indexset![]);
                 }
                 let mut artifact_visitor = SimpleCommandLineArtifactVisitor::new();
@@ -900,7 +1111,7 @@ async fn build_artifacts(
             }
         })
     }
-    let artifacts_to_build = get_artifacts_to_build(label_filtering, providers)?;
+    let artifacts_to_build = get_artifacts_to_build(label_filtering, providers, rule_labels)?;
     // build the test target first
     future::try_join_all(
         artifacts_to_build
@@ -953,10 +1164,17 @@ struct TestLabelFiltering {
     always_exclude: bool,
     /// Whether to build targets that are filtered out, but don't run it.
     build_filtered_targets: bool,
+    /// A boolean expression over labels (`--label-expr`). When present, a set of labels is excluded
+    /// exactly when it does not satisfy the expression, and the include/exclude lists are unused.
+    label_expression: Option<LabelExpr>,
 }
 
 impl TestLabelFiltering {
     fn is_excluded(&self, labels: Vec<&str>) -> bool {
+        if let Some(expr) = &self.label_expression {
+            return !expr.matches(&labels);
+        }
+
         let mut matched = self.included_labels.is_empty();
         for include_label in &self.included_labels {
             if let Some(include) = include_label.strip_prefix('!') {
@@ -989,12 +1207,14 @@ impl TestLabelFiltering {
         excluded_labels: Vec<String>,
         always_exclude: bool,
         build_filtered_targets: bool,
+        label_expression: Option<LabelExpr>,
     ) -> Self {
         Self {
             included_labels: included_labels.into_iter().collect(),
             excluded_labels: excluded_labels.into_iter().collect(),
             always_exclude,
             build_filtered_targets,
+            label_expression,
         }
     }
 }
@@ -1026,7 +1246,42 @@ fn post_process_test_executor(s: &str) -> anyhow::Result<PathBuf> {
 
 #[cfg(test)]
 mod tests {
+    use crate::command::ExecutorReport;
     use crate::command::TestLabelFiltering;
+    use crate::command::TestOutcome;
+    use crate::label_expr::LabelExpr;
+
+    fn outcome(exit_code: i32, failed: &[&str], quarantined: &[&str]) -> TestOutcome {
+        let mut executor_report = ExecutorReport {
+            exit_code: Some(exit_code),
+            ..Default::default()
+        };
+        for name in failed {
+            executor_report.statuses.failed.add(name);
+        }
+        for name in quarantined {
+            executor_report.statuses.quarantined.add(name);
+        }
+        TestOutcome {
+            errors: Vec::new(),
+            executor_report,
+            executor_stdout: String::new(),
+            executor_stderr: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_exit_code_with_quarantined_failures() {
+        assert_eq!(Some(0), outcome(32, &[], &["flaky"]).exit_code().unwrap());
+        assert_eq!(
+            Some(32),
+            outcome(32, &["broken"], &["flaky"]).exit_code().unwrap()
+        );
+        assert_eq!(Some(32), outcome(32, &["broken"], &[]).exit_code().unwrap());
+        // Failures of the executor itself are never hidden.
+        assert_eq!(Some(2), outcome(2, &[], &["flaky"]).exit_code().unwrap());
+        assert_eq!(Some(0), outcome(0, &[], &[]).exit_code().unwrap());
+    }
 
     #[test]
     fn only_include_labels_in_includes() {
@@ -1035,6 +1290,7 @@ mod tests {
             vec!["this_doesnt_affect_anything".to_owned()],
             false,
             false,
+            None,
         );
 
         assert!(!filter.is_excluded(vec!["include_me"]));
@@ -1049,6 +1305,7 @@ mod tests {
             vec!["not_me2".to_owned()],
             false,
             false,
+            None,
         );
 
         assert!(conflicting_filter.is_excluded(vec!["not_me1"]));
@@ -1058,6 +1315,7 @@ mod tests {
             vec!["include_me".to_owned()],
             false,
             false,
+            None,
         );
 
         assert!(!conflicting_filter.is_excluded(vec!["include_me"]));
@@ -1067,6 +1325,7 @@ mod tests {
             vec!["!include_me".to_owned()],
             false,
             false,
+            None,
         );
 
         assert!(!conflicting_filter.is_excluded(vec!["include_me"]));
@@ -1079,6 +1338,7 @@ mod tests {
             vec!["not_me2".to_owned()],
             true,
             false,
+            None,
         );
 
         assert!(!filter.is_excluded(vec!["include_me", "blah"]));
@@ -1090,8 +1350,26 @@ mod tests {
             vec!["include_me".to_owned()],
             true,
             false,
+            None,
         );
 
         assert!(conflicting_filter.is_excluded(vec!["include_me"]));
     }
+
+    #[test]
+    fn label_expression_overrides_lists() {
+        let filter = TestLabelFiltering::new(
+            Vec::new(),
+            Vec::new(),
+            false,
+            false,
+            Some(LabelExpr::parse("(unit | integration) & !flaky").unwrap()),
+        );
+
+        assert!(!filter.is_excluded(vec!["unit"]));
+        assert!(!filter.is_excluded(vec!["integration", "slow"]));
+        assert!(filter.is_excluded(vec!["unit", "flaky"]));
+        assert!(filter.is_excluded(vec!["e2e"]));
+        assert!(filter.is_excluded(vec![]));
+    }
 }
@@ -0,0 +1,353 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Test impact analysis: selecting only the tests which may be affected by a set of changed files.
+//!
+//! A test is affected if a changed file is an input or the build file of the test target or of
+//! any of its transitive dependencies, or if it is a source artifact the test provider refers to
+//! (e.g. data files passed to the test command). Changes to files whose effect cannot be
+//! attributed to targets, such as `.bzl` files, `PACKAGE` files or buckconfigs, select every
+//! test. A test is also affected if a changed file is among the dependencies recorded for it in
+//! earlier runs, which covers files it reads that the target graph does not know about.
+//!
+//! Whether a target is affected is computed once per target and shared by all tests, so the
+//! whole run visits the reverse-dependency closure of the changed files once rather than once per
+//! test.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use buck2_build_api::artifact_groups::ArtifactGroup;
+use buck2_build_api::interpreter::rule_defs::cmd_args::SimpleCommandLineArtifactVisitor;
+use buck2_build_api::interpreter::rule_defs::provider::test_provider::TestProvider;
+use buck2_cli_proto::test_response::TestImpactReport;
+use buck2_cli_proto::TestImpactRequest;
+use buck2_core::cells::cell_path::CellPath;
+use buck2_core::cells::CellResolver;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
+use buck2_core::target::label::TargetLabel;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
+use dupe::Dupe;
+use parking_lot::Mutex;
+
+pub(crate) struct TestImpactFilter {
+    revision: String,
+    changed_files: u64,
+    /// The changed files, as cell paths to compare with the inputs of targets.
+    changed: HashSet<CellPath>,
+    /// Tests with a changed file among their recorded dependencies.
+    recorded_impacted: HashSet<TargetLabel>,
+    /// Whether a changed file cannot be attributed to targets, in which case every test is
+    /// impacted.
+    all_impacted: bool,
+    /// Whether each target visited so far has a changed input in its transitive dependencies.
+    targets: Mutex<HashMap<ConfiguredTargetLabel, bool>>,
+    impacted: AtomicU64,
+    skipped: AtomicU64,
+}
+
+/// Whether changes to this file affect targets in ways the target graph does not record.
+fn is_unattributable(path: &ProjectRelativePath) -> bool {
+    match path.file_name() {
+        Some(name) => {
+            let name = name.as_str();
+            name.ends_with(".bzl")
+                || name.starts_with(".buckconfig")
+                || name == "PACKAGE"
+                || name == "PACKAGE.v2"
+        }
+        None => false,
+    }
+}
+
+/// Whether `root` or one of its transitive dependencies is directly impacted, recording the answer
+/// for every visited node in `memo` so that nodes shared with earlier queries are not visited
+/// again. The graph must be acyclic.
+fn is_transitively_impacted<K, N>(
+    memo: &mut HashMap<K, bool>,
+    root: N,
+    key: impl Fn(&N) -> K,
+    deps: impl Fn(&N) -> Vec<N>,
+    mut directly_impacted: impl FnMut(&N) -> anyhow::Result<bool>,
+) -> anyhow::Result<bool>
+where
+    K: Hash + Eq + Clone,
+{
+    enum Visit<N> {
+        Enter(N),
+        Exit(N),
+    }
+
+    let root_key = key(&root);
+    let mut entered = HashSet::new();
+    let mut stack = vec![Visit::Enter(root)];
+    while let Some(visit) = stack.pop() {
+        match visit {
+            Visit::Enter(node) => {
+                let node_key = key(&node);
+                if memo.contains_key(&node_key) || !entered.insert(node_key) {
+                    continue;
+                }
+                let node_deps = deps(&node);
+                stack.push(Visit::Exit(node));
+                stack.extend(node_deps.into_iter().map(Visit::Enter));
+            }
+            Visit::Exit(node) => {
+                // In an acyclic graph, every dependency has been exited, and so memoized, first.
+                let impacted = directly_impacted(&node)?
+                    || deps(&node)
+                        .iter()
+                        .any(|dep| memo.get(&key(dep)).copied().unwrap_or(false));
+                memo.insert(key(&node), impacted);
+            }
+        }
+    }
+    Ok(memo.get(&root_key).copied().unwrap_or(false))
+}
+
+impl TestImpactFilter {
+    pub(crate) fn new(
+        request: &TestImpactRequest,
+        cell_resolver: &CellResolver,
+    ) -> anyhow::Result<Self> {
+        let changed_files = request
+            .changed_files
+            .iter()
+            .map(|f| ProjectRelativePath::new(f).map(|f| f.to_buf()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let all_impacted = changed_files.iter().any(|f| is_unattributable(f));
+        let changed = changed_files
+            .iter()
+            .map(|f| cell_resolver.get_cell_path(f))
+            .collect::<anyhow::Result<HashSet<_>>>()?;
+
+        let mut recorded_impacted = HashSet::new();
+        for deps in &request.recorded_deps {
+            let mut is_impacted = false;
+            for file in &deps.files {
+                let file = cell_resolver.get_cell_path(ProjectRelativePath::new(file)?)?;
+                is_impacted |= changed.contains(&file);
+            }
+            if is_impacted {
+                recorded_impacted.insert(TargetLabel::parse(
+                    &deps.target,
+                    cell_resolver.root_cell(),
+                    cell_resolver,
+                )?);
+            }
+        }
+
+        Ok(Self {
+            revision: request.revision.clone(),
+            changed_files: changed_files.len() as u64,
+            changed,
+            recorded_impacted,
+            all_impacted,
+            targets: Mutex::new(HashMap::new()),
+            impacted: AtomicU64::new(0),
+            skipped: AtomicU64::new(0),
+        })
+    }
+
+    /// Decide whether the test defined by `node` may be affected by the change, and record the
+    /// decision for the report.
+    pub(crate) fn check(
+        &self,
+        node: &ConfiguredTargetNode,
+        provider: &dyn TestProvider,
+    ) -> anyhow::Result<bool> {
+        let impacted = self.all_impacted || self.is_impacted(node, provider)?;
+        if impacted {
+            self.impacted.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.skipped.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(impacted)
+    }
+
+    fn is_impacted(
+        &self,
+        node: &ConfiguredTargetNode,
+        provider: &dyn TestProvider,
+    ) -> anyhow::Result<bool> {
+        if self.changed.is_empty() {
+            return Ok(false);
+        }
+        if self.recorded_impacted.contains(node.label().unconfigured()) {
+            return Ok(true);
+        }
+
+        let mut visitor = SimpleCommandLineArtifactVisitor::new();
+        provider.visit_artifacts(&mut visitor)?;
+        for input in &visitor.inputs {
+            if let ArtifactGroup::Artifact(artifact) = input {
+                if let Some(source) = artifact.get_source() {
+                    if self.changed.contains(&source.get_path().to_cell_path()) {
+                        return Ok(true);
+                    }
+                }
+            }
+        }
+
+        is_transitively_impacted(
+            &mut self.targets.lock(),
+            node,
+            |node| node.label().dupe(),
+            |node| node.deps().collect(),
+            |node| {
+                Ok(self.changed.contains(&node.buildfile_path().path())
+                    || node.inputs().any(|input| self.changed.contains(&input)))
+            },
+        )
+    }
+
+    pub(crate) fn report(&self) -> TestImpactReport {
+        TestImpactReport {
+            revision: self.revision.clone(),
+            changed_files: self.changed_files,
+            impacted: self.impacted.load(Ordering::Relaxed),
+            skipped: self.skipped.load(Ordering::Relaxed),
+            all_impacted: self.all_impacted,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use buck2_cli_proto::test_impact_request::RecordedTestDeps;
+    use buck2_cli_proto::TestImpactRequest;
+    use buck2_core::cells::alias::NonEmptyCellAlias;
+    use buck2_core::cells::cell_root_path::CellRootPathBuf;
+    use buck2_core::cells::name::CellName;
+    use buck2_core::cells::CellResolver;
+    use buck2_core::fs::project_rel_path::ProjectRelativePath;
+    use buck2_core::target::label::TargetLabel;
+
+    use crate::impact::is_transitively_impacted;
+    use crate::impact::is_unattributable;
+    use crate::impact::TestImpactFilter;
+
+    /// `a -> b -> d`, `a -> c -> d`, `e -> c` and `f`.
+    fn deps(node: &&'static str) -> Vec<&'static str> {
+        match *node {
+            "a" => vec!["b", "c"],
+            "b" | "c" => vec!["d"],
+            "e" => vec!["c"],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Query each root in turn, returning the answers and the nodes checked directly.
+    fn query(changed: &str, roots: &[&'static str]) -> (Vec<bool>, Vec<&'static str>) {
+        let mut memo = HashMap::new();
+        let mut checked = Vec::new();
+        let impacted = roots
+            .iter()
+            .map(|root| {
+                is_transitively_impacted(
+                    &mut memo,
+                    *root,
+                    |node| *node,
+                    deps,
+                    |node| {
+                        checked.push(*node);
+                        Ok(*node == changed)
+                    },
+                )
+                .unwrap()
+            })
+            .collect();
+        checked.sort();
+        (impacted, checked)
+    }
+
+    #[test]
+    fn test_is_transitively_impacted() {
+        assert_eq!(
+            (vec![true, true, false], vec!["a", "b", "c", "d", "e", "f"]),
+            query("d", &["a", "e", "f"])
+        );
+        assert_eq!(
+            (vec![false, true, false], vec!["b", "c", "d", "e"]),
+            query("b", &["c", "b", "e"])
+        );
+        assert_eq!((vec![true], vec!["a", "b", "c", "d"]), query("a", &["a"]));
+    }
+
+    #[test]
+    fn test_visits_each_node_once() {
+        let (_, checked) = query("x", &["a", "e", "b", "d", "a"]);
+        assert_eq!(vec!["a", "b", "c", "d", "e"], checked);
+    }
+
+    fn cell_resolver() -> CellResolver {
+        let root = CellName::testing_new("root");
+        CellResolver::testing_with_names_and_paths_with_alias(&[(
+            root,
+            CellRootPathBuf::testing_new(""),
+            HashMap::from([(NonEmptyCellAlias::testing_new("root"), root)]),
+        )])
+    }
+
+    fn filter(changed_files: &[&str]) -> TestImpactFilter {
+        let request = TestImpactRequest {
+            revision: "main".to_owned(),
+            changed_files: changed_files.iter().map(|f| (*f).to_owned()).collect(),
+            recorded_deps: vec![
+                RecordedTestDeps {
+                    target: "root//foo:test".to_owned(),
+                    files: vec!["foo/data.txt".to_owned()],
+                },
+                RecordedTestDeps {
+                    target: "root//bar:test".to_owned(),
+                    files: vec!["bar/data.txt".to_owned(), "bar/other.txt".to_owned()],
+                },
+            ],
+        };
+        TestImpactFilter::new(&request, &cell_resolver()).unwrap()
+    }
+
+    #[test]
+    fn test_recorded_deps() {
+        let filter = filter(&["bar/other.txt", "baz/lib.rs"]);
+        assert!(!filter.all_impacted);
+        assert_eq!(
+            vec![&TargetLabel::testing_parse("root//bar:test")],
+            filter.recorded_impacted.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(2, filter.report().changed_files);
+    }
+
+    #[test]
+    fn test_all_impacted() {
+        assert!(filter(&["foo/lib.rs", "prelude/rules.bzl"]).all_impacted);
+        assert!(!filter(&["foo/lib.rs"]).all_impacted);
+    }
+
+    #[test]
+    fn test_is_unattributable() {
+        for path in [
+            "prelude/rust.bzl",
+            ".buckconfig",
+            ".buckconfig.local",
+            "foo/PACKAGE",
+        ] {
+            assert!(is_unattributable(ProjectRelativePath::new(path).unwrap()));
+        }
+        for path in ["foo/BUCK", "foo/lib.rs", "foo/bzl.txt"] {
+            assert!(!is_unattributable(ProjectRelativePath::new(path).unwrap()));
+        }
+    }
+}
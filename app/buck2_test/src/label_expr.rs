@@ -0,0 +1,180 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Boolean expressions over the labels of tests, such as `(unit | integration) & !flaky`.
+//!
+//! `!` binds tighter than `&`, which binds tighter than `|`. A label is any run of characters
+//! other than whitespace, parentheses and operators.
+
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+#[derive(Debug, buck2_error::Error)]
+#[buck2(user)]
+enum LabelExprError {
+    #[error("Invalid label expression `{0}`: expected a label, `!` or `(` at offset {1}")]
+    ExpectedLabel(String, usize),
+    #[error("Invalid label expression `{0}`: unexpected `{1}` at offset {2}")]
+    Unexpected(String, char, usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum LabelExpr {
+    Label(String),
+    Not(Box<LabelExpr>),
+    And(Box<LabelExpr>, Box<LabelExpr>),
+    Or(Box<LabelExpr>, Box<LabelExpr>),
+}
+
+fn is_label_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, '(' | ')' | '&' | '|' | '!')
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&mut self) -> Option<(usize, char)> {
+        while let Some((_, c)) = self.chars.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.chars.next();
+        }
+        self.chars.peek().copied()
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek().map(|(_, c)| c) == Some(expected) {
+            self.chars.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> anyhow::Result<LabelExpr> {
+        let mut expr = self.and()?;
+        while self.eat('|') {
+            expr = LabelExpr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> anyhow::Result<LabelExpr> {
+        let mut expr = self.unary()?;
+        while self.eat('&') {
+            expr = LabelExpr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> anyhow::Result<LabelExpr> {
+        match self.peek() {
+            Some((_, '!')) => {
+                self.chars.next();
+                Ok(LabelExpr::Not(Box::new(self.unary()?)))
+            }
+            Some((_, '(')) => {
+                self.chars.next();
+                let expr = self.or()?;
+                match self.peek() {
+                    Some((_, ')')) => {
+                        self.chars.next();
+                        Ok(expr)
+                    }
+                    _ => Err(self.unexpected()),
+                }
+            }
+            Some((start, c)) if is_label_char(c) => {
+                let mut end = start;
+                while let Some((i, c)) = self.chars.peek().copied() {
+                    if !is_label_char(c) {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    self.chars.next();
+                }
+                Ok(LabelExpr::Label(self.input[start..end].to_owned()))
+            }
+            Some((offset, _)) => {
+                Err(LabelExprError::ExpectedLabel(self.input.to_owned(), offset).into())
+            }
+            None => {
+                Err(LabelExprError::ExpectedLabel(self.input.to_owned(), self.input.len()).into())
+            }
+        }
+    }
+
+    fn unexpected(&mut self) -> anyhow::Error {
+        match self.peek() {
+            Some((offset, c)) => {
+                LabelExprError::Unexpected(self.input.to_owned(), c, offset).into()
+            }
+            None => LabelExprError::ExpectedLabel(self.input.to_owned(), self.input.len()).into(),
+        }
+    }
+}
+
+impl LabelExpr {
+    pub(crate) fn parse(input: &str) -> anyhow::Result<LabelExpr> {
+        let mut parser = Parser {
+            input,
+            chars: input.char_indices().peekable(),
+        };
+        let expr = parser.or()?;
+        if parser.peek().is_some() {
+            return Err(parser.unexpected());
+        }
+        Ok(expr)
+    }
+
+    pub(crate) fn matches(&self, labels: &[&str]) -> bool {
+        match self {
+            LabelExpr::Label(label) => labels.contains(&label.as_str()),
+            LabelExpr::Not(expr) => !expr.matches(labels),
+            LabelExpr::And(a, b) => a.matches(labels) && b.matches(labels),
+            LabelExpr::Or(a, b) => a.matches(labels) || b.matches(labels),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(expr: &str, labels: &[&str]) -> bool {
+        LabelExpr::parse(expr).unwrap().matches(labels)
+    }
+
+    #[test]
+    fn test_matches() {
+        let expr = "(unit | integration) & !flaky";
+        assert!(matches(expr, &["unit"]));
+        assert!(matches(expr, &["integration", "slow"]));
+        assert!(!matches(expr, &["unit", "flaky"]));
+        assert!(!matches(expr, &["e2e"]));
+
+        assert!(matches("a | b & c", &["a"]));
+        assert!(!matches("a | b & c", &["b"]));
+        assert!(matches("!!a", &["a"]));
+        assert!(matches("ci:skip-asan", &["ci:skip-asan"]));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(LabelExpr::parse("").is_err());
+        assert!(LabelExpr::parse("a &").is_err());
+        assert!(LabelExpr::parse("(a | b").is_err());
+        assert!(LabelExpr::parse("a b").is_err());
+        assert!(LabelExpr::parse("a)").is_err());
+    }
+}
@@ -17,11 +17,14 @@
 pub mod command;
 pub mod downward_api;
 pub mod executor_launcher;
+pub(crate) mod impact;
+pub(crate) mod label_expr;
 pub(crate) mod local_resource_api;
 pub(crate) mod local_resource_registry;
 pub(crate) mod local_resource_setup;
 pub mod orchestrator;
 pub mod session;
+pub mod test_history;
 pub(crate) mod tcp;
 pub mod translations;
 #[cfg(unix)]
@@ -9,6 +9,7 @@
 
 //! Implementation of the `TestOrchestrator` from `buck2_test_api`.
 
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::sync::Arc;
@@ -33,6 +34,7 @@ use buck2_build_api::interpreter::rule_defs::provider::builtin::external_runner_
 use buck2_build_api::interpreter::rule_defs::provider::builtin::external_runner_test_info::TestCommandMember;
 use buck2_common::dice::cells::HasCellResolver;
 use buck2_common::events::HasEvents;
+use buck2_common::legacy_configs::dice::HasLegacyConfigs;
 use buck2_common::liveliness_observer::LivelinessObserver;
 use buck2_common::local_resource_state::LocalResourceState;
 use buck2_core::cells::cell_root_path::CellRootPathBuf;
@@ -71,6 +73,7 @@ use buck2_execute::execute::command_executor::CommandExecutor;
 use buck2_execute::execute::environment_inheritance::EnvironmentInheritance;
 use buck2_execute::execute::kind::CommandExecutionKind;
 use buck2_execute::execute::manager::CommandExecutionManager;
+use buck2_execute::execute::merged_directory_cache::HasMergedDirectoryCache;
 use buck2_execute::execute::prepared::NoOpCommandOptionalExecutor;
 use buck2_execute::execute::prepared::PreparedCommand;
 use buck2_execute::execute::request::CommandExecutionInput;
@@ -104,6 +107,7 @@ use buck2_test_api::data::ExecutionStatus;
 use buck2_test_api::data::ExecutionStream;
 use buck2_test_api::data::ExecutorConfigOverride;
 use buck2_test_api::data::ExternalRunnerSpecValue;
+use buck2_test_api::data::LocalResourceType;
 use buck2_test_api::data::Output;
 use buck2_test_api::data::PrepareForLocalExecutionResult;
 use buck2_test_api::data::RequiredLocalResources;
@@ -221,6 +225,24 @@ impl<'a> BuckTestOrchestrator<'a> {
         let fs = self.dice.get_artifact_fs().await?;
 
         let test_info = self.get_test_info(&test_target).await?;
+        // The timeout declared by the rule takes precedence over a longer one requested by the
+        // test executor.
+        let timeout = match test_info.timeout() {
+            Some(declared) => timeout.min(declared),
+            None => timeout,
+        };
+        let mut required_local_resources = required_local_resources;
+        for name in test_info.required_local_resources() {
+            if !required_local_resources
+                .resources
+                .iter()
+                .any(|r| r.name == name)
+            {
+                required_local_resources.resources.push(LocalResourceType {
+                    name: name.to_owned(),
+                });
+            }
+        }
         let test_executor = self
             .get_test_executor(&test_target, &test_info, executor_override, &fs)
             .await?;
@@ -245,6 +267,7 @@ impl<'a> BuckTestOrchestrator<'a> {
         } = test_executable_expanded;
 
         let executor_preference = self.executor_preference(supports_re)?;
+        let env_allowlist_declared = test_info.env_allowlist().is_some();
 
         let required_resources = if test_executor.is_local_execution_possible(executor_preference) {
             let setup_local_resources_executor = self.get_local_executor(&fs).await?;
@@ -283,11 +306,18 @@ impl<'a> BuckTestOrchestrator<'a> {
                 Some(host_sharing_requirements),
                 Some(executor_preference),
                 required_resources,
+                test_env_inheritance(&test_info),
             )
             .await?;
 
         let (stdout, stderr, status, timing, execution_kind, outputs) = self
-            .execute_shared(&test_target, metadata, &test_executor, execution_request)
+            .execute_shared(
+                &test_target,
+                metadata,
+                &test_executor,
+                execution_request,
+                env_allowlist_declared,
+            )
             .await?;
 
         self.require_alive().await?;
@@ -327,6 +357,39 @@ impl<'a> BuckTestOrchestrator<'a> {
     }
 }
 
+/// Add the memory limit declared by a test, in MiB, to the platform properties of RE executors as
+/// `property`, so that the RE backend can enforce it. The timeout of a test doesn't need a property
+/// since it is the timeout of its action.
+fn with_test_memory_limit<'a>(
+    executor_config: &'a CommandExecutorConfig,
+    max_memory_bytes: Option<u64>,
+    property: Option<&str>,
+) -> Cow<'a, CommandExecutorConfig> {
+    let (max_memory_bytes, property) = match (max_memory_bytes, property) {
+        (Some(max_memory_bytes), Some(property)) => (max_memory_bytes, property),
+        _ => return Cow::Borrowed(executor_config),
+    };
+    if !matches!(executor_config.executor, Executor::RemoteEnabled { .. }) {
+        return Cow::Borrowed(executor_config);
+    }
+
+    let mut executor_config = executor_config.clone();
+    if let Executor::RemoteEnabled { re_properties, .. } = &mut executor_config.executor {
+        re_properties.properties = Arc::new(
+            re_properties
+                .properties
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .chain([(
+                    property.to_owned(),
+                    (max_memory_bytes / (1024 * 1024)).to_string(),
+                )])
+                .collect(),
+        );
+    }
+    Cow::Owned(executor_config)
+}
+
 struct PreparedLocalResourceSetupContext {
     pub target: ConfiguredTargetLabel,
     pub execution_request: CommandExecutionRequest,
@@ -476,6 +539,7 @@ impl<'a> TestOrchestrator for BuckTestOrchestrator<'a> {
                 None,
                 None,
                 vec![],
+                test_env_inheritance(&test_info),
             )
             .await?;
 
@@ -531,6 +595,7 @@ impl<'b> BuckTestOrchestrator<'b> {
         metadata: DisplayMetadata,
         executor: &CommandExecutor,
         request: CommandExecutionRequest,
+        env_allowlist_declared: bool,
     ) -> Result<
         (
             ExecutionStream,
@@ -563,6 +628,11 @@ impl<'b> BuckTestOrchestrator<'b> {
             action_key_suffix,
         };
 
+        let inherited_env: Vec<String> = request
+            .local_environment_inheritance()
+            .map(|env| env.values().map(|(key, _)| key.to_owned()).collect())
+            .unwrap_or_default();
+
         // For test execution, we currently do not do any cache queries
 
         let prepared_action = executor.prepare_action(&request, self.digest_config)?;
@@ -625,8 +695,14 @@ impl<'b> BuckTestOrchestrator<'b> {
                 self.events
                     .span_async(start, async move {
                         let result = command.await;
+                        let inherited_env = match result.report.status.execution_kind() {
+                            Some(CommandExecutionKind::Local { .. }) => inherited_env,
+                            _ => Vec::new(),
+                        };
                         let end = TestRunEnd {
                             suite: test_suite,
+                            inherited_env,
+                            env_allowlist_declared,
                             command_report: Some(
                                 result
                                     .report
@@ -814,13 +890,36 @@ impl<'b> BuckTestOrchestrator<'b> {
             None => test_info.default_executor().map(|o| &o.0),
         };
 
-        self.get_command_executor(
-            fs,
-            &node,
-            resolved_executor_override.as_ref().map(|a| &***a),
-        )
-        .await
-        .context("Error constructing CommandExecutor")
+        let executor_config = match resolved_executor_override.as_ref() {
+            Some(a) => &***a,
+            None => node
+                .execution_platform_resolution()
+                .executor_config()
+                .context("Error accessing executor config")?,
+        };
+        let memory_property = match test_info.max_memory_bytes() {
+            Some(_) => {
+                let cell_resolver = self.dice.get_cell_resolver().await?;
+                self.dice
+                    .get_legacy_config_property(
+                        cell_resolver.root_cell(),
+                        "test",
+                        "re_max_memory_property",
+                    )
+                    .await?
+                    .filter(|s| !s.is_empty())
+            }
+            None => None,
+        };
+        let executor_config = with_test_memory_limit(
+            executor_config,
+            test_info.max_memory_bytes(),
+            memory_property.as_deref(),
+        );
+
+        self.get_command_executor(fs, &node, Some(&executor_config))
+            .await
+            .context("Error constructing CommandExecutor")
     }
 
     async fn expand_test_executable(
@@ -907,6 +1006,7 @@ impl<'b> BuckTestOrchestrator<'b> {
         host_sharing_requirements: Option<HostSharingRequirements>,
         executor_preference: Option<ExecutorPreference>,
         required_local_resources: Vec<LocalResourceState>,
+        env_inheritance: EnvironmentInheritance,
     ) -> anyhow::Result<CommandExecutionRequest> {
         let mut inputs = Vec::with_capacity(cmd_inputs.len());
         for input in &cmd_inputs {
@@ -927,12 +1027,21 @@ impl<'b> BuckTestOrchestrator<'b> {
         let mut request = CommandExecutionRequest::new(
             vec![],
             cmd,
-            CommandExecutionPaths::new(inputs, outputs, fs, self.digest_config)?,
+            CommandExecutionPaths::new(
+                inputs,
+                outputs,
+                fs,
+                self.digest_config,
+                &self
+                    .dice
+                    .per_transaction_data()
+                    .get_merged_directory_cache(),
+            )?,
             env,
         );
         request = request
             .with_working_directory(cwd)
-            .with_local_environment_inheritance(EnvironmentInheritance::test_allowlist())
+            .with_local_environment_inheritance(env_inheritance)
             .with_disable_miniperf(true)
             .with_required_local_resources(required_local_resources)?;
         if let Some(timeout) = timeout {
@@ -1012,7 +1121,16 @@ impl<'b> BuckTestOrchestrator<'b> {
             .into_iter()
             .map(|group_values| CommandExecutionInput::Artifact(Box::new(group_values)))
             .collect();
-        let paths = CommandExecutionPaths::new(inputs, indexset![], fs, self.digest_config)?;
+        let paths = CommandExecutionPaths::new(
+            inputs,
+            indexset![],
+            fs,
+            self.digest_config,
+            &self
+                .dice
+                .per_transaction_data()
+                .get_merged_directory_cache(),
+        )?;
         let mut execution_request =
             CommandExecutionRequest::new(vec![], context.cmd, paths, Default::default());
         execution_request =
@@ -1279,6 +1397,14 @@ impl<'a> CommandLineBuilder for CommandLineBuilderFormatWrapper<'a> {
     }
 }
 
+/// The environment of the daemon that a test inherits when it runs locally.
+fn test_env_inheritance(test_info: &FrozenExternalRunnerTestInfo) -> EnvironmentInheritance {
+    match test_info.env_allowlist() {
+        Some(env_allowlist) => EnvironmentInheritance::test_allowlist_from(env_allowlist),
+        None => EnvironmentInheritance::test_allowlist(),
+    }
+}
+
 struct ExpandedTestExecutable {
     cwd: ProjectRelativePathBuf,
     cmd: Vec<String>,
@@ -1427,6 +1553,11 @@ mod tests {
     use buck2_core::cells::name::CellName;
     use buck2_core::cells::CellResolver;
     use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::execution_types::executor_config::CacheUploadBehavior;
+    use buck2_core::execution_types::executor_config::RePlatformFields;
+    use buck2_core::execution_types::executor_config::RemoteEnabledExecutor;
+    use buck2_core::execution_types::executor_config::RemoteExecutorOptions;
+    use buck2_core::execution_types::executor_config::RemoteExecutorUseCase;
     use buck2_core::fs::project::ProjectRootTemp;
     use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
     use buck2_events::dispatch::EventDispatcher;
@@ -1591,4 +1722,62 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_with_test_memory_limit() {
+        let remote = CommandExecutorConfig {
+            executor: Executor::RemoteEnabled {
+                executor: RemoteEnabledExecutor::Remote(RemoteExecutorOptions::default()),
+                re_properties: RePlatformFields {
+                    properties: Arc::new(
+                        [("platform".to_owned(), "linux".to_owned())]
+                            .into_iter()
+                            .collect(),
+                    ),
+                },
+                re_use_case: RemoteExecutorUseCase::buck2_default(),
+                re_action_key: None,
+                cache_upload_behavior: CacheUploadBehavior::Disabled,
+                remote_cache_enabled: true,
+                remote_dep_file_cache_enabled: false,
+                dependencies: vec![],
+            },
+            options: CommandExecutorConfig::testing_local().options,
+        };
+        let properties = |config: &CommandExecutorConfig| match &config.executor {
+            Executor::RemoteEnabled { re_properties, .. } => re_properties
+                .properties
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect::<Vec<_>>(),
+            Executor::Local(_) => Vec::new(),
+        };
+
+        // Only added when the property is configured.
+        assert_eq!(
+            with_test_memory_limit(&remote, Some(512 * 1024 * 1024), None).as_ref(),
+            &remote
+        );
+        assert_eq!(
+            with_test_memory_limit(&remote, None, Some("memory")).as_ref(),
+            &remote
+        );
+        assert_eq!(
+            properties(&with_test_memory_limit(
+                &remote,
+                Some(512 * 1024 * 1024),
+                Some("memory")
+            )),
+            vec![
+                ("memory".to_owned(), "512".to_owned()),
+                ("platform".to_owned(), "linux".to_owned()),
+            ]
+        );
+
+        let local = CommandExecutorConfig::testing_local();
+        assert_eq!(
+            with_test_memory_limit(&local, Some(512 * 1024 * 1024), Some("memory")).as_ref(),
+            &*local
+        );
+    }
 }
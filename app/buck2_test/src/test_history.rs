@@ -0,0 +1,326 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! History of test outcomes, used to quarantine known-flaky tests.
+//!
+//! When `test.quarantine_flaky_tests` is set, every pass and failure is recorded, and a failure of
+//! a test whose recent history flips between passing and failing is quarantined: it is reported
+//! separately and does not fail the test command. Consecutive failures ending the history are not
+//! counted as flips, so a test which started failing consistently, e.g. because of a regression
+//! or a local edit, is never quarantined.
+//!
+//! The history is kept in a local sqlite database under buck-out unless an external service was
+//! registered with [`init_external_test_history`].
+
+use std::sync::Arc;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use dupe::Dupe;
+use parking_lot::Mutex;
+use rusqlite::Connection;
+
+/// Identifies a test across runs.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TestId {
+    /// The unconfigured label of the test target.
+    pub target: String,
+    /// The name of the test case within the target.
+    pub name: String,
+}
+
+/// Storage for the pass/fail history of tests.
+#[async_trait]
+pub trait TestHistory: Send + Sync {
+    /// Record the outcome of a run of a test.
+    async fn record(&self, test: &TestId, passed: bool) -> anyhow::Result<()>;
+
+    /// Whether the test is known to be flaky, based on its history before the current run.
+    async fn is_flaky(&self, test: &TestId) -> anyhow::Result<bool>;
+}
+
+#[derive(Debug, buck2_error::Error)]
+enum TestHistoryError {
+    #[error("External test history is already initialized")]
+    AlreadyInitialized,
+}
+
+static EXTERNAL_TEST_HISTORY: OnceLock<Arc<dyn TestHistory>> = OnceLock::new();
+
+/// Use an external service instead of the local database to store test history.
+pub fn init_external_test_history(history: Arc<dyn TestHistory>) -> anyhow::Result<()> {
+    EXTERNAL_TEST_HISTORY
+        .set(history)
+        .map_err(|_| TestHistoryError::AlreadyInitialized.into())
+}
+
+pub(crate) fn external_test_history() -> Option<Arc<dyn TestHistory>> {
+    EXTERNAL_TEST_HISTORY.get().cloned()
+}
+
+/// Whether a history of outcomes (`true` for a pass), most recent first, shows a flaky test: once
+/// the most recent consecutive failures are dropped, it flips between passing and failing at least
+/// `min_flips` times.
+fn is_flaky_history(outcomes: &[bool], min_flips: u32) -> bool {
+    let Some(last_pass) = outcomes.iter().position(|passed| *passed) else {
+        return false;
+    };
+    let flips = outcomes[last_pass..]
+        .windows(2)
+        .filter(|pair| pair[0] != pair[1])
+        .count();
+    min_flips > 0 && flips >= min_flips as usize
+}
+
+/// Runs older than this are dropped from the local database, so that tests which are no longer run
+/// do not stay in it forever.
+const MAX_HISTORY_AGE_SECS: i64 = 30 * 24 * 60 * 60;
+
+/// How long to wait for the database when concurrent test commands are writing to it.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Test history in a local sqlite database, keeping the last `window` runs of each test.
+///
+/// Queries are blocking, so they run on a blocking thread.
+pub(crate) struct SqliteTestHistory {
+    connection: Arc<Mutex<Connection>>,
+    window: u32,
+    min_flips: u32,
+}
+
+impl SqliteTestHistory {
+    pub(crate) async fn open(
+        path: AbsNormPathBuf,
+        window: u32,
+        min_flips: u32,
+    ) -> anyhow::Result<Self> {
+        tokio::task::spawn_blocking(move || {
+            if let Some(dir) = path.parent() {
+                fs_util::create_dir_all(dir)?;
+            }
+            let connection = Connection::open(&path)
+                .with_context(|| format!("opening test history database `{}`", path))?;
+            Self::new(connection, window, min_flips)
+        })
+        .await?
+    }
+
+    fn new(connection: Connection, window: u32, min_flips: u32) -> anyhow::Result<Self> {
+        connection
+            .busy_timeout(BUSY_TIMEOUT)
+            .context("setting test history busy timeout")?;
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS test_history (
+                    target      TEXT NOT NULL,
+                    name        TEXT NOT NULL,
+                    passed      INTEGER NOT NULL,
+                    recorded_at INTEGER NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS test_history_test ON test_history (target, name);",
+            )
+            .context("creating test history table")?;
+        connection
+            .execute(
+                "DELETE FROM test_history WHERE recorded_at < CAST(strftime('%s', 'now') AS INTEGER) - ?",
+                rusqlite::params![MAX_HISTORY_AGE_SECS],
+            )
+            .context("pruning test history")?;
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+            window,
+            min_flips,
+        })
+    }
+
+    async fn with_connection<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&Connection) -> anyhow::Result<R> + Send + 'static,
+    ) -> anyhow::Result<R> {
+        let connection = self.connection.dupe();
+        tokio::task::spawn_blocking(move || f(&connection.lock())).await?
+    }
+}
+
+#[async_trait]
+impl TestHistory for SqliteTestHistory {
+    async fn record(&self, test: &TestId, passed: bool) -> anyhow::Result<()> {
+        let test = test.clone();
+        let window = self.window;
+        self.with_connection(move |connection| {
+            connection
+                .execute(
+                    "INSERT INTO test_history (target, name, passed, recorded_at) \
+                    VALUES (?, ?, ?, CAST(strftime('%s', 'now') AS INTEGER))",
+                    rusqlite::params![test.target, test.name, passed],
+                )
+                .with_context(|| format!("recording result of `{} {}`", test.target, test.name))?;
+            // Only the last `window` runs are ever read.
+            connection
+                .execute(
+                    "DELETE FROM test_history WHERE target = ?1 AND name = ?2 AND rowid NOT IN \
+                    (SELECT rowid FROM test_history WHERE target = ?1 AND name = ?2 \
+                    ORDER BY rowid DESC LIMIT ?3)",
+                    rusqlite::params![test.target, test.name, window],
+                )
+                .with_context(|| format!("pruning history of `{} {}`", test.target, test.name))?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn is_flaky(&self, test: &TestId) -> anyhow::Result<bool> {
+        let test = test.clone();
+        let window = self.window;
+        let outcomes = self
+            .with_connection(move |connection| {
+                let mut stmt = connection.prepare(
+                    "SELECT passed FROM test_history WHERE target = ? AND name = ? \
+                    ORDER BY rowid DESC LIMIT ?",
+                )?;
+                let outcomes = stmt
+                    .query_map(rusqlite::params![test.target, test.name, window], |row| {
+                        row.get::<_, bool>(0)
+                    })?
+                    .collect::<Result<Vec<_>, _>>()
+                    .with_context(|| {
+                        format!("reading history of `{} {}`", test.target, test.name)
+                    })?;
+                Ok(outcomes)
+            })
+            .await?;
+        Ok(is_flaky_history(&outcomes, self.min_flips))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+    use dupe::Dupe;
+    use rusqlite::Connection;
+
+    use crate::test_history::is_flaky_history;
+    use crate::test_history::SqliteTestHistory;
+    use crate::test_history::TestHistory;
+    use crate::test_history::TestId;
+
+    fn outcomes(s: &str) -> Vec<bool> {
+        // Written oldest first, like a timeline, but stored most recent first.
+        s.chars().rev().map(|c| c == 'P').collect()
+    }
+
+    #[test]
+    fn test_is_flaky_history() {
+        assert!(!is_flaky_history(&outcomes(""), 2));
+        assert!(!is_flaky_history(&outcomes("PPPP"), 2));
+        assert!(!is_flaky_history(&outcomes("FFFF"), 2));
+        // A regression keeps failing: the trailing failures are not flips.
+        assert!(!is_flaky_history(&outcomes("PPPF"), 2));
+        assert!(!is_flaky_history(&outcomes("PPPFFF"), 2));
+        // A regression which was fixed.
+        assert!(!is_flaky_history(&outcomes("PPFF"), 2));
+        assert!(!is_flaky_history(&outcomes("FFPP"), 2));
+        assert!(is_flaky_history(&outcomes("PFP"), 2));
+        assert!(is_flaky_history(&outcomes("PFPPFF"), 2));
+        assert!(!is_flaky_history(&outcomes("PFP"), 3));
+        assert!(is_flaky_history(&outcomes("PFPFP"), 3));
+        assert!(!is_flaky_history(&outcomes("PFP"), 0));
+    }
+
+    fn test_id(name: &str) -> TestId {
+        TestId {
+            target: "root//:test".to_owned(),
+            name: name.to_owned(),
+        }
+    }
+
+    fn count_rows(history: &SqliteTestHistory) -> anyhow::Result<u32> {
+        Ok(history
+            .connection
+            .lock()
+            .query_row("SELECT COUNT(*) FROM test_history", [], |row| row.get(0))?)
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_history() -> anyhow::Result<()> {
+        let history = SqliteTestHistory::new(Connection::open_in_memory()?, 4, 2)?;
+        let test = test_id("case");
+        assert!(!history.is_flaky(&test).await?);
+
+        history.record(&test, true).await?;
+        history.record(&test, true).await?;
+        history.record(&test, false).await?;
+        // A new failure is not a flake.
+        assert!(!history.is_flaky(&test).await?);
+
+        history.record(&test, true).await?;
+        assert!(history.is_flaky(&test).await?);
+        history.record(&test, false).await?;
+        assert!(history.is_flaky(&test).await?);
+
+        // The flips fall out of the window.
+        for _ in 0..3 {
+            history.record(&test, true).await?;
+        }
+        assert!(!history.is_flaky(&test).await?);
+
+        let other = TestId {
+            target: "root//:test".to_owned(),
+            name: "other".to_owned(),
+        };
+        assert!(!history.is_flaky(&other).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_history_pruning() -> anyhow::Result<()> {
+        let history = SqliteTestHistory::new(Connection::open_in_memory()?, 3, 2)?;
+        for _ in 0..10 {
+            history.record(&test_id("a"), true).await?;
+            history.record(&test_id("b"), false).await?;
+        }
+        assert_eq!(6, count_rows(&history)?);
+
+        let connection = Arc::into_inner(history.connection).unwrap().into_inner();
+        connection.execute(
+            "INSERT INTO test_history (target, name, passed, recorded_at) VALUES ('root//:old', 'case', 1, 0)",
+            [],
+        )?;
+        let history = SqliteTestHistory::new(connection, 3, 2)?;
+        assert_eq!(6, count_rows(&history)?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_history_waits_for_concurrent_writer() -> anyhow::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let path = AbsNormPathBuf::new(tempdir.path().join("cache/test_history.sqlite"))?;
+        let writer = SqliteTestHistory::open(path.clone(), 4, 2).await?;
+        let history = SqliteTestHistory::open(path, 4, 2).await?;
+
+        writer.connection.lock().execute_batch("BEGIN EXCLUSIVE")?;
+        let connection = writer.connection.dupe();
+        let commit = tokio::task::spawn_blocking(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            connection.lock().execute_batch("COMMIT")
+        });
+        // Blocks until the other transaction commits, rather than failing with `SQLITE_BUSY`.
+        history.record(&test_id("case"), true).await?;
+        commit.await??;
+        assert_eq!(1, count_rows(&writer)?);
+        Ok(())
+    }
+}
@@ -213,6 +213,7 @@ impl TryFrom<i32> for TestStatus {
             buck2_test_proto::TestStatus::Rerun => TestStatus::RERUN,
             buck2_test_proto::TestStatus::ListingSuccess => TestStatus::LISTING_SUCCESS,
             buck2_test_proto::TestStatus::ListingFailed => TestStatus::LISTING_FAILED,
+            buck2_test_proto::TestStatus::MemoryLimitExceeded => TestStatus::MEMORY_LIMIT_EXCEEDED,
         })
     }
 }
@@ -232,6 +233,7 @@ impl TryInto<i32> for TestStatus {
             TestStatus::RERUN => buck2_test_proto::TestStatus::Rerun,
             TestStatus::LISTING_SUCCESS => buck2_test_proto::TestStatus::ListingSuccess,
             TestStatus::LISTING_FAILED => buck2_test_proto::TestStatus::ListingFailed,
+            TestStatus::MEMORY_LIMIT_EXCEEDED => buck2_test_proto::TestStatus::MemoryLimitExceeded,
         } as i32)
     }
 }
@@ -298,6 +300,9 @@ impl TryFrom<buck2_test_proto::ExternalRunnerSpec> for ExternalRunnerSpec {
             contacts,
             oncall,
             working_dir_cell,
+            timeout,
+            max_memory_bytes,
+            required_local_resources,
         } = s;
 
         Ok(Self {
@@ -317,6 +322,12 @@ impl TryFrom<buck2_test_proto::ExternalRunnerSpec> for ExternalRunnerSpec {
             contacts,
             oncall,
             working_dir_cell: CellName::unchecked_new(&working_dir_cell)?,
+            timeout: timeout
+                .map(convert::to_std_duration)
+                .transpose()
+                .context("Invalid `timeout`")?,
+            max_memory_bytes,
+            required_local_resources,
         })
     }
 }
@@ -334,6 +345,9 @@ impl TryInto<buck2_test_proto::ExternalRunnerSpec> for ExternalRunnerSpec {
             contacts,
             oncall,
             working_dir_cell,
+            timeout,
+            max_memory_bytes,
+            required_local_resources,
         } = self;
         Ok(buck2_test_proto::ExternalRunnerSpec {
             target: Some(target.try_into().context("Invalid `target`")?),
@@ -349,6 +363,9 @@ impl TryInto<buck2_test_proto::ExternalRunnerSpec> for ExternalRunnerSpec {
             contacts,
             oncall,
             working_dir_cell: working_dir_cell.as_str().to_owned(),
+            timeout: timeout.map(|t| t.try_into()).transpose()?,
+            max_memory_bytes,
+            required_local_resources,
         })
     }
 }
@@ -873,6 +890,9 @@ mod tests {
             contacts: vec!["contact1".to_owned(), "contact2".to_owned()],
             oncall: Some("contact1".to_owned()),
             working_dir_cell: CellName::testing_new("qux"),
+            timeout: Some(Duration::from_millis(1500)),
+            max_memory_bytes: Some(512 * 1024 * 1024),
+            required_local_resources: vec!["db".to_owned()],
         };
         assert_roundtrips::<buck2_test_proto::ExternalRunnerSpec, ExternalRunnerSpec>(&test_spec);
     }
@@ -112,6 +112,9 @@ pub enum TestStatus {
     RERUN,
     LISTING_SUCCESS,
     LISTING_FAILED,
+    // The test exceeded the memory limit it declared. Only reported by test executors which
+    // enforce the limit.
+    MEMORY_LIMIT_EXCEEDED,
 }
 
 /// The set of information about a test rule that is passed to the test executor
@@ -135,6 +138,15 @@ pub struct ExternalRunnerSpec {
     pub oncall: Option<String>,
     /// Cell of current working directory for test command.
     pub working_dir_cell: CellName,
+    /// Maximum duration of a run of the test declared by the rule. Runs are timed out after this
+    /// duration even if the executor requests a longer timeout.
+    pub timeout: Option<Duration>,
+    /// Maximum memory a run of the test may use, declared by the rule, for the executor to enforce
+    /// when running locally.
+    pub max_memory_bytes: Option<u64>,
+    /// Local resources which are set up for every run of the test, in addition to those the
+    /// executor requires.
+    pub required_local_resources: Vec<String>,
 }
 
 /// Command line argument or environment variable value
@@ -15,6 +15,7 @@ use buck2_test_api::data::DisplayMetadata;
 use buck2_test_api::data::ExecuteResponse;
 use buck2_test_api::data::ExecutionResult2;
 use buck2_test_api::data::ExecutionStatus;
+use buck2_test_api::data::ExecutionStream;
 use buck2_test_api::data::ExternalRunnerSpec;
 use buck2_test_api::data::ExternalRunnerSpecValue;
 use buck2_test_api::data::RequiredLocalResources;
@@ -75,6 +76,7 @@ impl Buck2TestRunner {
                     spec.target.cell, spec.target.package, spec.target.target
                 );
                 let target_handle = spec.target.handle.to_owned();
+                let max_memory_bytes = spec.max_memory_bytes;
 
                 let execution_response = self
                     .execute_test_from_spec(spec)
@@ -86,7 +88,8 @@ impl Buck2TestRunner {
                     ExecuteResponse::Cancelled => return TestStatus::OMITTED,
                 };
 
-                let test_result = get_test_result(name, target_handle, execution_result);
+                let test_result =
+                    get_test_result(name, target_handle, execution_result, max_memory_bytes);
                 let test_status = test_result.status.clone();
 
                 self.report_test_result(test_result)
@@ -131,9 +134,9 @@ impl Buck2TestRunner {
             format: None,
         });
 
-        let command = spec
-            .command
+        let command = memory_limit_prefix(spec.max_memory_bytes)
             .into_iter()
+            .chain(spec.command)
             .map(|spec_value| ArgValue {
                 content: ArgValueContent::ExternalRunnerSpecValue(spec_value),
                 format: None,
@@ -195,23 +198,96 @@ impl Buck2TestRunner {
     }
 }
 
+/// Exit code of [`MEMORY_LIMIT_SCRIPT`] when it killed the test for exceeding its memory limit.
+const MEMORY_LIMIT_EXIT_CODE: i32 = 199;
+/// Printed to stderr by [`MEMORY_LIMIT_SCRIPT`] when it killed the test, so that a test exiting
+/// with [`MEMORY_LIMIT_EXIT_CODE`] itself is not mistaken for one exceeding its limit.
+const MEMORY_LIMIT_MESSAGE: &str =
+    "buck2_test_runner: killed the test for exceeding its memory limit";
+
+/// Runs the command given as arguments after the limit in KiB, and kills it if its resident memory
+/// goes above the limit. Polls with `ps`, so that it works on any Unix without privileges.
+const MEMORY_LIMIT_SCRIPT: &str = r#"limit_kib="$1"
+shift
+# Asynchronous commands get their stdin from /dev/null unless it is duplicated.
+exec 3<&0
+"$@" <&3 3<&- &
+pid=$!
+trap 'kill -TERM "$pid" 2>/dev/null' HUP INT TERM
+while :; do
+  rss_kib=$(ps -o rss= -p "$pid" 2>/dev/null | tr -d ' ')
+  [ -n "$rss_kib" ] || break
+  if [ "$rss_kib" -gt "$limit_kib" ]; then
+    kill -KILL "$pid" 2>/dev/null
+    echo "$MEMORY_LIMIT_MESSAGE ($limit_kib KiB)" >&2
+    exit "$MEMORY_LIMIT_EXIT_CODE"
+  fi
+  sleep 0.1
+done
+wait "$pid"
+"#;
+
+/// Arguments to prepend to the command of a test to enforce its memory limit with
+/// [`MEMORY_LIMIT_SCRIPT`]. Memory limits are not enforced on Windows.
+fn memory_limit_prefix(max_memory_bytes: Option<u64>) -> Vec<ExternalRunnerSpecValue> {
+    let max_memory_bytes = match max_memory_bytes {
+        Some(max_memory_bytes) if cfg!(unix) => max_memory_bytes,
+        _ => return Vec::new(),
+    };
+    let script = format!(
+        "MEMORY_LIMIT_MESSAGE='{}'\nMEMORY_LIMIT_EXIT_CODE={}\n{}",
+        MEMORY_LIMIT_MESSAGE, MEMORY_LIMIT_EXIT_CODE, MEMORY_LIMIT_SCRIPT
+    );
+    [
+        "/bin/sh".to_owned(),
+        "-c".to_owned(),
+        script,
+        "buck2_test_runner_memory_limit".to_owned(),
+        (max_memory_bytes / 1024).to_string(),
+    ]
+    .into_iter()
+    .map(ExternalRunnerSpecValue::Verbatim)
+    .collect()
+}
+
+/// Whether the test was killed by [`MEMORY_LIMIT_SCRIPT`].
+fn exceeded_memory_limit(exitcode: i32, stderr: &ExecutionStream) -> bool {
+    let ExecutionStream::Inline(stderr) = stderr;
+    exitcode == MEMORY_LIMIT_EXIT_CODE
+        && String::from_utf8_lossy(stderr).contains(MEMORY_LIMIT_MESSAGE)
+}
+
 fn get_test_result(
     name: String,
     target: ConfiguredTargetHandle,
     execution_result: ExecutionResult2,
+    max_memory_bytes: Option<u64>,
 ) -> TestResult {
     let status = match execution_result.status {
         ExecutionStatus::Finished { exitcode } => match exitcode {
             0 => TestStatus::PASS,
+            exitcode
+                if max_memory_bytes.is_some()
+                    && exceeded_memory_limit(exitcode, &execution_result.stderr) =>
+            {
+                TestStatus::MEMORY_LIMIT_EXCEEDED
+            }
             _ => TestStatus::FAIL,
         },
         ExecutionStatus::TimedOut { .. } => TestStatus::TIMEOUT,
     };
+    let msg = match (&status, max_memory_bytes) {
+        (TestStatus::MEMORY_LIMIT_EXCEEDED, Some(max_memory_bytes)) => Some(format!(
+            "Exceeded the memory limit of {} MiB",
+            max_memory_bytes / (1024 * 1024)
+        )),
+        _ => None,
+    };
     TestResult {
         target,
         name,
         status,
-        msg: None,
+        msg,
         duration: Some(execution_result.execution_time),
         details: format!(
             "---- STDOUT ----\n{:?}\n---- STDERR ----\n{:?}\n",
@@ -234,3 +310,126 @@ impl RunVerdict {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use std::time::SystemTime;
+
+    use buck2_test_api::data::ConfiguredTargetHandle;
+    use buck2_test_api::data::ExecutionResult2;
+    use buck2_test_api::data::ExecutionStatus;
+    use buck2_test_api::data::ExecutionStream;
+    use buck2_test_api::data::ExternalRunnerSpecValue;
+    use buck2_test_api::data::TestStatus;
+
+    use crate::runner::get_test_result;
+    use crate::runner::memory_limit_prefix;
+    use crate::runner::MEMORY_LIMIT_EXIT_CODE;
+    use crate::runner::MEMORY_LIMIT_MESSAGE;
+
+    fn result(status: ExecutionStatus, stderr: &str, max_memory_bytes: Option<u64>) -> TestStatus {
+        let result = ExecutionResult2 {
+            status,
+            stdout: ExecutionStream::Inline(Vec::new()),
+            stderr: ExecutionStream::Inline(stderr.as_bytes().to_vec()),
+            outputs: HashMap::new(),
+            start_time: SystemTime::UNIX_EPOCH,
+            execution_time: Duration::from_secs(1),
+            execution_details: Default::default(),
+        };
+        get_test_result(
+            "test".to_owned(),
+            ConfiguredTargetHandle::from(0),
+            result,
+            max_memory_bytes,
+        )
+        .status
+    }
+
+    fn status(status: ExecutionStatus) -> TestStatus {
+        result(status, "", None)
+    }
+
+    #[test]
+    fn test_get_test_result_status() {
+        assert_eq!(
+            TestStatus::PASS,
+            status(ExecutionStatus::Finished { exitcode: 0 })
+        );
+        assert_eq!(
+            TestStatus::FAIL,
+            status(ExecutionStatus::Finished { exitcode: 1 })
+        );
+        // Crashes are failures: only the tests killed for exceeding their limit are reported as
+        // such.
+        for exitcode in [134, 137, 139] {
+            assert_eq!(
+                TestStatus::FAIL,
+                result(ExecutionStatus::Finished { exitcode }, "", Some(1 << 20))
+            );
+        }
+        assert_eq!(
+            TestStatus::TIMEOUT,
+            status(ExecutionStatus::TimedOut {
+                duration: Duration::from_secs(1)
+            })
+        );
+    }
+
+    #[test]
+    fn test_get_test_result_memory_limit_exceeded() {
+        let killed = ExecutionStatus::Finished {
+            exitcode: MEMORY_LIMIT_EXIT_CODE,
+        };
+        assert_eq!(
+            TestStatus::MEMORY_LIMIT_EXCEEDED,
+            result(killed.clone(), MEMORY_LIMIT_MESSAGE, Some(1 << 20))
+        );
+        // A test exiting with the same code by itself.
+        assert_eq!(TestStatus::FAIL, result(killed.clone(), "", Some(1 << 20)));
+        assert_eq!(TestStatus::FAIL, result(killed, MEMORY_LIMIT_MESSAGE, None));
+    }
+
+    /// Runs `command` the way the test executor would with a memory limit, and returns the status
+    /// of the test.
+    #[cfg(unix)]
+    fn run_with_memory_limit(command: &[&str], max_memory_bytes: u64) -> TestStatus {
+        let args: Vec<String> = memory_limit_prefix(Some(max_memory_bytes))
+            .into_iter()
+            .map(|arg| match arg {
+                ExternalRunnerSpecValue::Verbatim(arg) => arg,
+                arg => panic!("Unexpected argument: {:?}", arg),
+            })
+            .chain(command.iter().map(|arg| (*arg).to_owned()))
+            .collect();
+        let output = std::process::Command::new(&args[0])
+            .args(&args[1..])
+            .output()
+            .unwrap();
+        result(
+            ExecutionStatus::Finished {
+                exitcode: output.status.code().unwrap(),
+            },
+            &String::from_utf8_lossy(&output.stderr),
+            Some(max_memory_bytes),
+        )
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_memory_limit_enforced() {
+        let limit = 64 * 1024 * 1024;
+        // Doubles a string until it is killed.
+        assert_eq!(
+            TestStatus::MEMORY_LIMIT_EXCEEDED,
+            run_with_memory_limit(&["awk", "BEGIN { s = \"x\"; while (1) s = s s }"], limit)
+        );
+        assert_eq!(TestStatus::PASS, run_with_memory_limit(&["true"], limit));
+        assert_eq!(
+            TestStatus::FAIL,
+            run_with_memory_limit(&["sh", "-c", "exit 3"], limit)
+        );
+    }
+}
@@ -33,3 +33,30 @@ impl UnixSystemStats {
         None
     }
 }
+
+pub struct MemoryStats {
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+}
+
+impl MemoryStats {
+    #[cfg(target_os = "linux")]
+    pub fn get() -> Option<Self> {
+        let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+        // Values are in kB, e.g. `MemAvailable:   12345678 kB`.
+        let field = |name: &str| -> Option<u64> {
+            let line = meminfo.lines().find(|l| l.starts_with(name))?;
+            let kb = line[name.len()..].trim().trim_end_matches("kB").trim();
+            Some(kb.parse::<u64>().ok()? * 1024)
+        };
+        Some(Self {
+            total_bytes: field("MemTotal:")?,
+            available_bytes: field("MemAvailable:")?,
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn get() -> Option<Self> {
+        None
+    }
+}
@@ -16,7 +16,12 @@
 
 #![feature(once_cell_try)]
 
+use std::collections::HashMap;
 use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fmt;
+use std::path::Path;
+use std::path::PathBuf;
 use std::thread;
 use std::time::Duration;
 use std::time::Instant;
@@ -39,11 +44,88 @@ pub(crate) mod winapi_process;
 pub const BUCK2_WRAPPER_ENV_VAR: &str = "BUCK2_WRAPPER";
 pub const BUCK_WRAPPER_UUID_ENV_VAR: &str = "BUCK_WRAPPER_UUID";
 
+/// Isolation dir of buck2 processes which do not set one.
+const DEFAULT_ISOLATION_DIR: &str = "v2";
+
+/// What a buck2 process does, guessed from its command line.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ProcessRole {
+    Daemon,
+    Forkserver,
+    /// Background processes of clients, such as event log uploads, which are not commands.
+    Helper,
+    Client,
+}
+
+impl ProcessRole {
+    fn of(cmd: &[String]) -> ProcessRole {
+        let args = cmd.get(1..).unwrap_or_default();
+        if args.first().map(String::as_str) == Some("forkserver") {
+            ProcessRole::Forkserver
+        } else if args.windows(2).any(|w| {
+            // The daemon is started with `daemon --dont-daemonize` on Windows, and with
+            // `daemon <startup config JSON>` elsewhere.
+            w[0] == "daemon" && (w[1] == "--dont-daemonize" || w[1].starts_with('{'))
+        }) {
+            ProcessRole::Daemon
+        } else if args.iter().any(|arg| arg == "persist-event-logs") {
+            ProcessRole::Helper
+        } else {
+            ProcessRole::Client
+        }
+    }
+}
+
+impl fmt::Display for ProcessRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcessRole::Daemon => write!(f, "daemon"),
+            ProcessRole::Forkserver => write!(f, "forkserver"),
+            ProcessRole::Helper => write!(f, "helper"),
+            ProcessRole::Client => write!(f, "client"),
+        }
+    }
+}
+
 /// Because `sysinfo::Process` is not `Clone`.
 struct ProcessInfo {
     pid: u32,
+    parent: Option<u32>,
     name: String,
     cmd: Vec<String>,
+    role: ProcessRole,
+    /// The project root for daemons, otherwise the working directory. Empty if unknown.
+    root: PathBuf,
+    isolation_dir: String,
+}
+
+/// The isolation dir of a process, from its command line or environment.
+fn isolation_dir(cmd: &[String], environ: &[String]) -> String {
+    let mut args = cmd.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--isolation-dir" {
+            if let Some(dir) = args.next() {
+                return dir.clone();
+            }
+        } else if let Some(dir) = arg.strip_prefix("--isolation-dir=") {
+            return dir.to_owned();
+        }
+    }
+    environ
+        .iter()
+        .find_map(|var| var.strip_prefix("BUCK_ISOLATION_DIR="))
+        .unwrap_or(DEFAULT_ISOLATION_DIR)
+        .to_owned()
+}
+
+/// Daemons run in `buck-out` (unless on Eden), so their project root is the parent of
+/// `buck-out`.
+fn process_root(cwd: &Path) -> PathBuf {
+    cwd.ancestors()
+        .find(|dir| dir.file_name() == Some(OsStr::new("buck-out")))
+        .and_then(Path::parent)
+        .unwrap_or(cwd)
+        .to_owned()
 }
 
 /// Find all buck2 processes in the system.
@@ -67,19 +149,99 @@ fn find_buck2_processes(who_is_asking: WhoIsAsking) -> Vec<ProcessInfo> {
         if is_buck2_exe(process.exe(), who_is_asking) && !current_parents.contains(pid) {
             buck2_processes.push(ProcessInfo {
                 pid: pid.as_u32(),
+                parent: process.parent().map(|p| p.as_u32()),
                 name: process.name().to_owned(),
                 cmd: process.cmd().to_vec(),
+                role: ProcessRole::of(process.cmd()),
+                root: process_root(process.cwd()),
+                isolation_dir: isolation_dir(process.cmd(), process.environ()),
             });
         }
     }
 
+    // Forkservers are started by daemons without an isolation dir, and belong to their daemon.
+    let daemons: HashMap<u32, (PathBuf, String)> = buck2_processes
+        .iter()
+        .filter(|p| p.role == ProcessRole::Daemon)
+        .map(|p| (p.pid, (p.root.clone(), p.isolation_dir.clone())))
+        .collect();
+    for process in &mut buck2_processes {
+        if process.role == ProcessRole::Forkserver {
+            if let Some((root, isolation_dir)) = process.parent.and_then(|p| daemons.get(&p)) {
+                process.root = root.clone();
+                process.isolation_dir = isolation_dir.clone();
+            }
+        }
+    }
+
     buck2_processes
 }
 
+/// Which processes `killall` kills.
+#[derive(Default, Debug)]
+pub struct KillallOptions {
+    /// Only kill processes running for this directory, or below it.
+    pub repo: Option<PathBuf>,
+    /// Only kill processes with this isolation dir.
+    pub isolation_dir: Option<String>,
+    /// Also kill daemons which are running a command, and their clients.
+    pub force: bool,
+    /// Only print the processes which would be killed.
+    pub dry_run: bool,
+}
+
+impl KillallOptions {
+    fn selects(&self, process: &ProcessInfo) -> bool {
+        if let Some(repo) = &self.repo {
+            if process.root.as_os_str().is_empty() || !process.root.starts_with(repo) {
+                return false;
+            }
+        }
+        if let Some(isolation_dir) = &self.isolation_dir {
+            if process.isolation_dir != *isolation_dir {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Whether a client belongs to a daemon: it runs in the project of the daemon, with the same
+/// isolation dir.
+fn is_client_of(client: &ProcessInfo, daemon: &ProcessInfo) -> bool {
+    client.isolation_dir == daemon.isolation_dir
+        && !daemon.root.as_os_str().is_empty()
+        && client.root.starts_with(&daemon.root)
+}
+
 /// Kills all running Buck2 processes, except this process's hierarchy. Returns whether it
 /// succeeded without errors.
 pub fn killall(who_is_asking: WhoIsAsking, write: impl Fn(String)) -> bool {
-    let buck2_processes = find_buck2_processes(who_is_asking);
+    killall_with_options(
+        who_is_asking,
+        &KillallOptions {
+            force: true,
+            ..KillallOptions::default()
+        },
+        write,
+    )
+}
+
+/// Kills the running Buck2 processes selected by `options`, except this process's hierarchy.
+///
+/// Unless `options.force` is set, daemons which are running a command are not killed, nor are
+/// their clients and forkservers. A daemon is considered to run a command when a buck2 client is
+/// running in its project with the same isolation dir. Returns whether it succeeded without errors
+/// and without skipping daemons.
+pub fn killall_with_options(
+    who_is_asking: WhoIsAsking,
+    options: &KillallOptions,
+    write: impl Fn(String),
+) -> bool {
+    let buck2_processes: Vec<ProcessInfo> = find_buck2_processes(who_is_asking)
+        .into_iter()
+        .filter(|p| options.selects(p))
+        .collect();
 
     if buck2_processes.is_empty() {
         write("No buck2 processes found".to_owned());
@@ -122,6 +284,80 @@ pub fn killall(who_is_asking: WhoIsAsking, write: impl Fn(String)) -> bool {
 
     let mut printer = Printer { write, ok: true };
 
+    // Daemons running a command, and processes belonging to them, are kept.
+
+    let mut kept = HashSet::new();
+    if !options.force {
+        for daemon in buck2_processes
+            .iter()
+            .filter(|p| p.role == ProcessRole::Daemon)
+        {
+            let clients: Vec<&ProcessInfo> = buck2_processes
+                .iter()
+                .filter(|p| p.role == ProcessRole::Client && is_client_of(p, daemon))
+                .collect();
+            if clients.is_empty() {
+                continue;
+            }
+            (printer.write)(format!(
+                "Not killing busy daemon ({}) in `{}` with isolation dir `{}`, running: {}. \
+                Use `--force` to kill it anyway.",
+                daemon.pid,
+                daemon.root.display(),
+                daemon.isolation_dir,
+                clients
+                    .iter()
+                    .map(|c| shlex::join(c.cmd.iter().map(|s| s.as_str())))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ));
+            printer.ok = false;
+            kept.insert(daemon.pid);
+            for process in &buck2_processes {
+                let belongs = match process.role {
+                    ProcessRole::Daemon => false,
+                    ProcessRole::Forkserver => process.parent == Some(daemon.pid),
+                    ProcessRole::Helper | ProcessRole::Client => is_client_of(process, daemon),
+                };
+                if belongs {
+                    kept.insert(process.pid);
+                }
+            }
+        }
+    }
+
+    let buck2_processes: Vec<ProcessInfo> = buck2_processes
+        .into_iter()
+        .filter(|p| !kept.contains(&p.pid))
+        .collect();
+
+    if buck2_processes.is_empty() {
+        return printer.ok;
+    }
+
+    (printer.write)(format!(
+        "{} {} buck2 processes:",
+        if options.dry_run {
+            "Would kill"
+        } else {
+            "Killing"
+        },
+        buck2_processes.len()
+    ));
+    for process in &buck2_processes {
+        (printer.write)(format!(
+            "  {} {} ({}) in `{}` with isolation dir `{}`",
+            process.role,
+            process.name,
+            process.pid,
+            process.root.display(),
+            process.isolation_dir,
+        ));
+    }
+    if options.dry_run {
+        return printer.ok;
+    }
+
     // Send a kill signal and collect the processes that are still alive.
 
     let mut processes_still_alive: Vec<(ProcessInfo, _)> = Vec::new();
@@ -165,3 +401,70 @@ pub fn killall(who_is_asking: WhoIsAsking, write: impl Fn(String)) -> bool {
 
     printer.ok
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::path::PathBuf;
+
+    use crate::isolation_dir;
+    use crate::process_root;
+    use crate::ProcessRole;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| (*s).to_owned()).collect()
+    }
+
+    #[test]
+    fn test_process_role() {
+        assert_eq!(
+            ProcessRole::Daemon,
+            ProcessRole::of(&args(&["buck2", "--isolation-dir", "v2", "daemon", "{}"]))
+        );
+        assert_eq!(
+            ProcessRole::Daemon,
+            ProcessRole::of(&args(&["buck2", "daemon", "--dont-daemonize", "{}"]))
+        );
+        assert_eq!(
+            ProcessRole::Forkserver,
+            ProcessRole::of(&args(&["buck2", "forkserver", "--fd", "3"]))
+        );
+        assert_eq!(
+            ProcessRole::Helper,
+            ProcessRole::of(&args(&["buck2", "debug", "persist-event-logs"]))
+        );
+        assert_eq!(
+            ProcessRole::Client,
+            ProcessRole::of(&args(&["buck2", "build", "//daemon:daemon"]))
+        );
+    }
+
+    #[test]
+    fn test_isolation_dir() {
+        assert_eq!("v2", isolation_dir(&args(&["buck2", "build"]), &[]));
+        assert_eq!(
+            "x",
+            isolation_dir(&args(&["buck2", "--isolation-dir", "x", "build"]), &[])
+        );
+        assert_eq!(
+            "y",
+            isolation_dir(&args(&["buck2", "--isolation-dir=y", "build"]), &[])
+        );
+        assert_eq!(
+            "z",
+            isolation_dir(&args(&["buck2", "build"]), &args(&["BUCK_ISOLATION_DIR=z"]))
+        );
+    }
+
+    #[test]
+    fn test_process_root() {
+        assert_eq!(
+            PathBuf::from("/repo"),
+            process_root(Path::new("/repo/buck-out/v2"))
+        );
+        assert_eq!(
+            PathBuf::from("/repo/sub"),
+            process_root(Path::new("/repo/sub"))
+        );
+    }
+}
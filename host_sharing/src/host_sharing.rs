@@ -8,6 +8,7 @@
  */
 
 use std::fmt;
+use std::str::FromStr;
 
 use allocative::Allocative;
 use anyhow::Context;
@@ -95,11 +96,46 @@ impl Default for HostSharingRequirements {
     }
 }
 
+/// The resource a command is mostly bound by. When the broker has a budget of IO permits, IO-bound
+/// commands (e.g. links or copies) are scheduled against it rather than against the machine
+/// permits, so that they don't wait behind a storm of CPU-bound commands (e.g. compiles), and vice
+/// versa.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Allocative)]
+pub enum ResourceClass {
+    Cpu,
+    Io,
+}
+
+impl fmt::Display for ResourceClass {
+    fn fmt(&self, w: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cpu => write!(w, "cpu"),
+            Self::Io => write!(w, "io"),
+        }
+    }
+}
+
+impl FromStr for ResourceClass {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "cpu" => Ok(Self::Cpu),
+            "io" => Ok(Self::Io),
+            _ => Err(anyhow::anyhow!(
+                "Invalid resource class `{}`, expected `cpu` or `io`",
+                s
+            )),
+        }
+    }
+}
+
 /// A guard for all permits and resources acquired for a HostSharingBroker.acquire request.
 /// Keeps the data structures received from semaphores after acquiring.
 /// Semaphores are held until this struct is dropped.
 pub struct HostSharingGuard {
     _run_guard: SharedSemaphoreReleaser,
+    _io_guard: Option<SharedSemaphoreReleaser>,
     _name_guard: Option<SharedSemaphoreReleaser>,
 }
 
@@ -107,6 +143,9 @@ pub struct HostSharingGuard {
 pub struct HostSharingBroker {
     permits: SharedSemaphore,
     num_machine_permits: usize,
+    /// The budget of IO-bound commands, if they are scheduled separately.
+    io_permits: Option<(SharedSemaphore, usize)>,
+    fair: bool,
     named_semaphores: NamedSemaphores,
 }
 
@@ -129,56 +168,144 @@ impl HostSharingBroker {
     // If a test requires Permits(4) permits but the machine only has 3 permits then we cap the
     // test's required permits to 3. Otherwise the test would never be allowed to run.
     pub fn requested_permits(&self, weight_class: &WeightClass) -> RequestedPermits {
+        self.requested_permits_for_class(weight_class, ResourceClass::Cpu)
+    }
+
+    /// Like `requested_permits`, but relative to the budget of the given resource class.
+    pub fn requested_permits_for_class(
+        &self,
+        weight_class: &WeightClass,
+        resource_class: ResourceClass,
+    ) -> RequestedPermits {
+        let (_, num_permits) = self.pool(resource_class);
         let count = match weight_class {
             WeightClass::Permits(required_permits) => *required_permits,
             WeightClass::Percentage(percentage) => {
                 let percentage: usize = percentage.into_value().into();
-                (self.num_machine_permits * percentage).div_ceil(100)
+                (num_permits * percentage).div_ceil(100)
             }
         };
 
         RequestedPermits {
             count,
-            cap: self.num_machine_permits,
+            cap: num_permits,
         }
     }
 
     pub fn new(host_sharing_strategy: HostSharingStrategy, num_machine_permits: usize) -> Self {
-        let permits = match host_sharing_strategy {
-            HostSharingStrategy::Fifo => SharedSemaphore::new(true, num_machine_permits),
-            HostSharingStrategy::SmallerTasksFirst => {
-                SharedSemaphore::new(false, num_machine_permits)
-            }
+        let fair = match host_sharing_strategy {
+            HostSharingStrategy::Fifo => true,
+            HostSharingStrategy::SmallerTasksFirst => false,
         };
 
         Self {
-            permits,
+            permits: SharedSemaphore::new(fair, num_machine_permits),
             num_machine_permits,
+            io_permits: None,
+            fair,
             named_semaphores: NamedSemaphores::new(),
         }
     }
 
+    /// Schedule IO-bound commands against a separate budget of `num_io_permits` permits. A budget
+    /// of zero permits disables this, and IO-bound commands share the machine permits.
+    pub fn with_io_permits(mut self, num_io_permits: usize) -> Self {
+        self.io_permits = if num_io_permits == 0 {
+            None
+        } else {
+            Some((
+                SharedSemaphore::new(self.fair, num_io_permits),
+                num_io_permits,
+            ))
+        };
+        self
+    }
+
     pub fn num_machine_permits(&self) -> usize {
         self.num_machine_permits
     }
 
+    /// Number of permits in the budget of this resource class.
+    pub fn num_permits(&self, resource_class: ResourceClass) -> usize {
+        self.pool(resource_class).1
+    }
+
+    /// Whether IO-bound commands have their own budget.
+    pub fn has_io_permits(&self) -> bool {
+        self.io_permits.is_some()
+    }
+
+    /// Take up to `count` single permits from the budget of this resource class, without
+    /// waiting: only the permits which are free right now are taken.
+    pub fn try_reserve(
+        &self,
+        resource_class: ResourceClass,
+        count: usize,
+    ) -> Vec<HostSharingGuard> {
+        let (permits, _) = self.pool(resource_class);
+        let mut guards = Vec::new();
+        while guards.len() < count {
+            let Some(_run_guard) = permits.try_acquire(1) else {
+                break;
+            };
+            guards.push(HostSharingGuard {
+                _run_guard,
+                _io_guard: None,
+                _name_guard: None,
+            });
+        }
+        guards
+    }
+
+    fn pool(&self, resource_class: ResourceClass) -> (&SharedSemaphore, usize) {
+        match (resource_class, &self.io_permits) {
+            (ResourceClass::Io, Some((io_permits, num_io_permits))) => {
+                (io_permits, *num_io_permits)
+            }
+            _ => (&self.permits, self.num_machine_permits),
+        }
+    }
+
     pub async fn acquire(
         &self,
         host_sharing_requirements: &HostSharingRequirements,
     ) -> HostSharingGuard {
+        self.acquire_for_class(host_sharing_requirements, ResourceClass::Cpu)
+            .await
+    }
+
+    /// Acquire the permits of a command from the budget of its resource class.
+    pub async fn acquire_for_class(
+        &self,
+        host_sharing_requirements: &HostSharingRequirements,
+        resource_class: ResourceClass,
+    ) -> HostSharingGuard {
+        let (permits, _) = self.pool(resource_class);
         match host_sharing_requirements {
             HostSharingRequirements::Shared(weight_class) => {
-                let permits = self.requested_permits(weight_class).into_count();
-                let _run_guard = self.permits.acquire(permits).await;
+                let count = self
+                    .requested_permits_for_class(weight_class, resource_class)
+                    .into_count();
+                let _run_guard = permits.acquire(count).await;
                 HostSharingGuard {
                     _run_guard,
+                    _io_guard: None,
                     _name_guard: None,
                 }
             }
             HostSharingRequirements::ExclusiveAccess => {
+                // Exclusive access excludes commands of every class, so it takes both budgets,
+                // always in the same order.
                 let _run_guard = self.permits.acquire(self.num_machine_permits).await;
+                let _io_guard = match &self.io_permits {
+                    Some((io_permits, num_io_permits)) => {
+                        Some(io_permits.acquire(*num_io_permits).await)
+                    }
+                    None => None,
+                };
                 HostSharingGuard {
                     _run_guard,
+                    _io_guard,
                     _name_guard: None,
                 }
             }
@@ -189,10 +316,13 @@ impl HostSharingBroker {
                 // for the previous run on this identifier to finish.
                 let run_semaphore = self.named_semaphores.get(identifier);
                 let _name_guard = Some(run_semaphore.acquire(SINGLE_RUN).await);
-                let permits = self.requested_permits(weight_class).into_count();
-                let _run_guard = self.permits.acquire(permits).await;
+                let count = self
+                    .requested_permits_for_class(weight_class, resource_class)
+                    .into_count();
+                let _run_guard = permits.acquire(count).await;
                 HostSharingGuard {
                     _run_guard,
+                    _io_guard: None,
                     _name_guard,
                 }
             }
@@ -267,4 +397,67 @@ mod tests {
             10,
         );
     }
+
+    #[test]
+    fn test_io_permits() {
+        let broker =
+            HostSharingBroker::new(HostSharingStrategy::SmallerTasksFirst, 8).with_io_permits(2);
+
+        assert_eq!(
+            broker
+                .requested_permits_for_class(&WeightClass::Permits(4), ResourceClass::Cpu)
+                .into_count(),
+            4,
+        );
+        assert_eq!(
+            broker
+                .requested_permits_for_class(&WeightClass::Permits(4), ResourceClass::Io)
+                .into_count(),
+            2,
+        );
+        assert_eq!(
+            broker
+                .requested_permits_for_class(
+                    &WeightClass::Percentage(WeightPercentage { value: 50 }),
+                    ResourceClass::Io
+                )
+                .into_count(),
+            1,
+        );
+
+        // The budgets are independent: using up the machine permits leaves the IO permits.
+        let _cpu = broker.pool(ResourceClass::Cpu).0.try_acquire(8).unwrap();
+        assert!(broker.pool(ResourceClass::Io).0.try_acquire(2).is_some());
+    }
+
+    #[test]
+    fn test_io_permits_disabled() {
+        let broker =
+            HostSharingBroker::new(HostSharingStrategy::SmallerTasksFirst, 8).with_io_permits(0);
+
+        assert_eq!(
+            broker
+                .requested_permits_for_class(&WeightClass::Permits(4), ResourceClass::Io)
+                .into_count(),
+            4,
+        );
+        let _cpu = broker.pool(ResourceClass::Cpu).0.try_acquire(8).unwrap();
+        assert!(broker.pool(ResourceClass::Io).0.try_acquire(1).is_none());
+    }
+
+    #[test]
+    fn test_try_reserve() {
+        let broker =
+            HostSharingBroker::new(HostSharingStrategy::SmallerTasksFirst, 4).with_io_permits(2);
+
+        let running = broker.pool(ResourceClass::Cpu).0.try_acquire(3).unwrap();
+        // Only the free permits are taken.
+        let reserved = broker.try_reserve(ResourceClass::Cpu, 2);
+        assert_eq!(reserved.len(), 1);
+        assert!(broker.try_reserve(ResourceClass::Cpu, 1).is_empty());
+
+        drop(running);
+        assert_eq!(broker.try_reserve(ResourceClass::Cpu, 5).len(), 3);
+        assert_eq!(broker.try_reserve(ResourceClass::Io, 5).len(), 2);
+    }
 }
@@ -0,0 +1,45 @@
+# Copyright (c) Meta Platforms, Inc. and affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+# The attribute to add to a rule using `deterministic_archive`, e.g.
+# `attrs = {"_deterministic_archive": DETERMINISTIC_ARCHIVE_TOOL_ATTR, ...}`.
+DETERMINISTIC_ARCHIVE_TOOL_ATTR = attrs.default_only(attrs.exec_dep(
+    providers = [RunInfo],
+    default = "prelude//deterministic_archive/tools:deterministic_archive",
+))
+
+def deterministic_archive(
+        ctx: AnalysisContext,
+        tool: Dependency,
+        output: Artifact,
+        srcs_dir: Artifact,
+        format: str = "tar",
+        mtime: int = 0,
+        identifier: [str, None] = None) -> Artifact:
+    """
+    Archive the directory `srcs_dir` into `output` (one of `tar`, `tar.gz` or
+    `zip`) so that the archive only depends on the paths, contents and
+    executable bits of the files: entries are sorted, mtimes are set to `mtime`,
+    permissions are normalized to 0644/0755 and owners are dropped.
+
+    `tool` is the dependency from `DETERMINISTIC_ARCHIVE_TOOL_ATTR`.
+    """
+    ctx.actions.run(
+        cmd_args([
+            tool[RunInfo],
+            "--format",
+            format,
+            "--mtime",
+            str(mtime),
+            "--output",
+            output.as_output(),
+            srcs_dir,
+        ]),
+        category = "deterministic_archive",
+        identifier = identifier,
+    )
+    return output
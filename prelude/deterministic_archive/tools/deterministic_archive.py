@@ -0,0 +1,126 @@
+#!/usr/bin/env python3
+# Copyright (c) Meta Platforms, Inc. and affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+"""
+Archive a directory so that the archive only depends on the paths, contents and
+executable bits of the files in it: entries are sorted by path, mtimes are set
+to a fixed value, permissions are normalized to 0644/0755 and owners are
+dropped.
+
+Usage: deterministic_archive.py --format tar.gz --output out.tar.gz src_dir
+"""
+
+import argparse
+import gzip
+import io
+import os
+import stat
+import tarfile
+import zipfile
+from typing import Iterator, Tuple
+
+FORMATS = ["tar", "tar.gz", "zip"]
+
+# The earliest date a zip file can represent.
+ZIP_DATE_TIME = (1980, 1, 1, 0, 0, 0)
+
+
+def _normalized_mode(is_dir: bool, mode: int) -> int:
+    if is_dir or mode & stat.S_IXUSR:
+        return 0o755
+    return 0o644
+
+
+def _walk(src_dir: str) -> Iterator[Tuple[str, str]]:
+    """
+    Yields `(archive path, filesystem path)` for every entry under `src_dir`,
+    sorted by archive path. Symlinks to directories are not followed.
+    """
+    entries = []
+    for root, dirs, files in os.walk(src_dir):
+        for name in dirs + files:
+            path = os.path.join(root, name)
+            arcname = os.path.relpath(path, src_dir).replace(os.sep, "/")
+            entries.append((arcname, path))
+    entries.sort()
+    return iter(entries)
+
+
+def _normalize_tarinfo(info: tarfile.TarInfo, mtime: int) -> tarfile.TarInfo:
+    info.mtime = mtime
+    info.uid = 0
+    info.gid = 0
+    info.uname = ""
+    info.gname = ""
+    if info.issym():
+        info.mode = 0o777
+    else:
+        info.mode = _normalized_mode(info.isdir(), info.mode)
+    return info
+
+
+def write_tar(src_dir: str, fileobj: io.IOBase, mtime: int) -> None:
+    with tarfile.open(fileobj=fileobj, mode="w", format=tarfile.GNU_FORMAT) as tar:
+        for arcname, path in _walk(src_dir):
+            info = _normalize_tarinfo(tar.gettarinfo(path, arcname), mtime)
+            if info.isfile():
+                with open(path, "rb") as f:
+                    tar.addfile(info, f)
+            else:
+                tar.addfile(info)
+
+
+def write_zip(src_dir: str, fileobj: io.IOBase) -> None:
+    with zipfile.ZipFile(fileobj, mode="w", compression=zipfile.ZIP_DEFLATED) as zip:
+        for arcname, path in _walk(src_dir):
+            st = os.stat(path)
+            if stat.S_ISDIR(st.st_mode):
+                info = zipfile.ZipInfo(arcname + "/", date_time=ZIP_DATE_TIME)
+                info.external_attr = (stat.S_IFDIR | 0o755) << 16
+                zip.writestr(info, b"")
+            else:
+                info = zipfile.ZipInfo(arcname, date_time=ZIP_DATE_TIME)
+                info.external_attr = (
+                    stat.S_IFREG | _normalized_mode(False, st.st_mode)
+                ) << 16
+                info.compress_type = zipfile.ZIP_DEFLATED
+                with open(path, "rb") as f:
+                    zip.writestr(info, f.read())
+
+
+def write_archive(src_dir: str, output: str, format: str, mtime: int) -> None:
+    with open(output, "wb") as out:
+        if format == "tar":
+            write_tar(src_dir, out, mtime)
+        elif format == "tar.gz":
+            # The gzip header embeds a file name and an mtime too.
+            with gzip.GzipFile(filename="", mode="wb", fileobj=out, mtime=mtime) as gz:
+                write_tar(src_dir, gz, mtime)
+        elif format == "zip":
+            write_zip(src_dir, out)
+        else:
+            raise ValueError("Unknown archive format: {}".format(format))
+
+
+def main() -> None:
+    parser = argparse.ArgumentParser(description=__doc__)
+    parser.add_argument("--format", choices=FORMATS, default="tar")
+    parser.add_argument("--output", required=True)
+    parser.add_argument(
+        "--mtime",
+        type=int,
+        default=0,
+        help="The mtime of every entry, in seconds since the epoch (not used for zip)",
+    )
+    parser.add_argument("src_dir")
+    args = parser.parse_args()
+    write_archive(args.src_dir, args.output, args.format, args.mtime)
+
+
+if __name__ == "__main__":
+    main()
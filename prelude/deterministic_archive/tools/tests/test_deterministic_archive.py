@@ -0,0 +1,93 @@
+#!/usr/bin/env python3
+# Copyright (c) Meta Platforms, Inc. and affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+import io
+import os
+import tarfile
+import tempfile
+import unittest
+import zipfile
+
+from deterministic_archive.tools.deterministic_archive import write_archive
+
+
+def _make_tree(root: str, files, mtime: int, umask_mode: int) -> None:
+    """
+    Create `files` (a list of `(path, content, executable)`) under `root`, in
+    the given order, with the given mtime and extra permission bits.
+    """
+    for path, content, executable in files:
+        full = os.path.join(root, path)
+        os.makedirs(os.path.dirname(full), exist_ok=True)
+        with open(full, "w") as f:
+            f.write(content)
+        os.chmod(full, (0o755 if executable else 0o644) | umask_mode)
+    for dirpath, dirnames, filenames in os.walk(root):
+        for name in dirnames + filenames:
+            os.utime(os.path.join(dirpath, name), (mtime, mtime))
+
+
+FILES = [
+    ("b/z.txt", "z", False),
+    ("a.sh", "#!/bin/sh", True),
+    ("b/y.txt", "y", False),
+]
+
+
+class TestDeterministicArchive(unittest.TestCase):
+    def _archive(self, files, mtime: int, umask_mode: int, format: str) -> bytes:
+        with tempfile.TemporaryDirectory() as tmp:
+            src = os.path.join(tmp, "src")
+            os.mkdir(src)
+            _make_tree(src, files, mtime, umask_mode)
+            output = os.path.join(tmp, "out")
+            write_archive(src, output, format, 0)
+            with open(output, "rb") as f:
+                return f.read()
+
+    def test_byte_identical(self):
+        for format in ["tar", "tar.gz", "zip"]:
+            with self.subTest(format=format):
+                first = self._archive(FILES, 1000000000, 0, format)
+                second = self._archive(
+                    list(reversed(FILES)), 1700000000, 0o020, format
+                )
+                self.assertEqual(first, second)
+
+    def test_content_changes_archive(self):
+        changed = [(p, c + "!", x) for (p, c, x) in FILES]
+        self.assertNotEqual(
+            self._archive(FILES, 0, 0, "tar"), self._archive(changed, 0, 0, "tar")
+        )
+
+    def test_tar_entries(self):
+        data = self._archive(FILES, 1000000000, 0o020, "tar")
+        with tarfile.open(fileobj=io.BytesIO(data)) as tar:
+            members = tar.getmembers()
+        self.assertEqual(
+            ["a.sh", "b", "b/y.txt", "b/z.txt"], [m.name for m in members]
+        )
+        modes = {m.name: m.mode for m in members}
+        self.assertEqual(0o755, modes["a.sh"])
+        self.assertEqual(0o755, modes["b"])
+        self.assertEqual(0o644, modes["b/y.txt"])
+        for m in members:
+            self.assertEqual(0, m.mtime)
+            self.assertEqual((0, 0, "", ""), (m.uid, m.gid, m.uname, m.gname))
+
+    def test_zip_entries(self):
+        data = self._archive(FILES, 1000000000, 0, "zip")
+        with zipfile.ZipFile(io.BytesIO(data)) as zip:
+            infos = zip.infolist()
+            self.assertEqual("#!/bin/sh", zip.read("a.sh").decode())
+        self.assertEqual(
+            ["a.sh", "b/", "b/y.txt", "b/z.txt"], [i.filename for i in infos]
+        )
+        modes = {i.filename: (i.external_attr >> 16) & 0o777 for i in infos}
+        self.assertEqual(0o755, modes["a.sh"])
+        self.assertEqual(0o644, modes["b/z.txt"])
@@ -0,0 +1,58 @@
+# Copyright (c) Meta Platforms, Inc. and affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+"""
+Host tools declared in the `[system_toolchains]` section of the root
+`.buckconfig`, and probed by Buck2 for their path, version and fingerprint.
+"""
+
+SystemToolchainInfo = provider(fields = {
+    "fingerprint": provider_field(str),
+    "path": provider_field(str),
+    "version": provider_field(str),
+})
+
+def _system_toolchain_impl(ctx: AnalysisContext) -> list[Provider]:
+    # Actions using the tool take the fingerprint as an input, so they run again
+    # when the tool changes, instead of hitting the cache.
+    fingerprint = ctx.actions.write("fingerprint", ctx.attrs.fingerprint)
+    return [
+        DefaultInfo(),
+        RunInfo(args = cmd_args(ctx.attrs.path).hidden(fingerprint)),
+        SystemToolchainInfo(
+            fingerprint = ctx.attrs.fingerprint,
+            path = ctx.attrs.path,
+            version = ctx.attrs.version,
+        ),
+    ]
+
+_system_toolchain = rule(
+    impl = _system_toolchain_impl,
+    attrs = {
+        "fingerprint": attrs.string(),
+        "path": attrs.string(),
+        "version": attrs.string(),
+    },
+)
+
+def system_toolchain(name, toolchain = None, **kwargs):
+    """
+    Declares a target for the system toolchain `toolchain` (defaulting to
+    `name`), providing `RunInfo` and `SystemToolchainInfo`.
+    """
+    toolchain = toolchain or name
+    path = read_root_config("system_toolchain_probes", toolchain + ".path")
+    if path == None:
+        fail("System toolchain `{}` is not declared in the `[system_toolchains]` section of the root `.buckconfig`".format(toolchain))
+
+    _system_toolchain(
+        name = name,
+        path = path,
+        version = read_root_config("system_toolchain_probes", toolchain + ".version", ""),
+        fingerprint = read_root_config("system_toolchain_probes", toolchain + ".fingerprint", ""),
+        **kwargs
+    )
@@ -0,0 +1,40 @@
+# Copyright (c) Meta Platforms, Inc. and affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+"""
+Toolchains downloaded from the URLs declared in the `[toolchain_downloads]`
+section of the root `.buckconfig`, and locked by `buck2 fetch-toolchains`.
+"""
+
+def toolchain_download(name, download = None, executable = False, **kwargs):
+    """
+    Declares an `http_file` for the toolchain download `download` (defaulting
+    to `name`), with the URL and hashes recorded in `toolchains.lock`.
+
+    The file is only downloaded when an action needs it.
+    """
+    download = download or name
+    declared_url = read_root_config("toolchain_downloads", download)
+    if declared_url == None:
+        fail("Toolchain download `{}` is not declared in the `[toolchain_downloads]` section of the root `.buckconfig`".format(download))
+
+    url = read_root_config("toolchain_lock", download + ".url")
+    sha1 = read_root_config("toolchain_lock", download + ".sha1")
+    sha256 = read_root_config("toolchain_lock", download + ".sha256")
+    if url == None or sha1 == None or sha256 == None:
+        fail("Toolchain download `{}` is not locked, run `buck2 fetch-toolchains`".format(download))
+    if url != declared_url:
+        fail("The URL of toolchain download `{}` changed since it was locked, run `buck2 fetch-toolchains`".format(download))
+
+    native.http_file(
+        name = name,
+        urls = [url],
+        sha1 = sha1,
+        sha256 = sha256,
+        executable = executable,
+        **kwargs
+    )
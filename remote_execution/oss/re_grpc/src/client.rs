@@ -745,6 +745,39 @@ impl REClient {
         })
     }
 
+    /// The Remote Execution API has no way to set the TTL of a blob: instead, servers extend the
+    /// lifetime of the blobs `FindMissingBlobs` finds, by as much as they choose, so the requested
+    /// TTL is ignored.
+    pub async fn extend_digest_ttl(
+        &self,
+        metadata: RemoteExecutionMetadata,
+        request: ExtendDigestsTtlRequest,
+    ) -> anyhow::Result<()> {
+        let mut cas_client = self.grpc_clients.cas_client.clone();
+
+        for digest_chunk in request.digests.chunks(100) {
+            let missing_blobs = cas_client
+                .find_missing_blobs(with_internal_metadata(
+                    FindMissingBlobsRequest {
+                        instance_name: self.instance_name.as_str().to_owned(),
+                        blob_digests: digest_chunk.map(|b| tdigest_to(b.clone())),
+                    },
+                    metadata.clone(),
+                ))
+                .await
+                .context("Failed to extend the TTL of blobs")?;
+            let resp: FindMissingBlobsResponse = missing_blobs.into_inner();
+            if let Some(missing) = resp.missing_blob_digests.into_iter().next() {
+                return Err(anyhow::anyhow!(
+                    "Cannot extend the TTL of blob `{}`, it is not in the CAS",
+                    tdigest_from(missing)
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn get_execution_client(&self) -> &Self {
         self
     }
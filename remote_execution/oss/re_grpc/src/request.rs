@@ -73,6 +73,14 @@ pub struct GetDigestsTtlRequest {
     pub _dot_dot: (),
 }
 
+#[derive(Default)]
+pub struct ExtendDigestsTtlRequest {
+    pub digests: Vec<TDigest>,
+    /// In seconds.
+    pub ttl: i64,
+    pub _dot_dot: (),
+}
+
 #[derive(Clone, Default)]
 pub struct ExecuteRequest {
     pub action_digest: TDigest,
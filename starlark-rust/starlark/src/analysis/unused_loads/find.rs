@@ -20,6 +20,7 @@ use std::collections::HashMap;
 use anyhow::Context;
 use dupe::Dupe;
 use starlark_syntax::codemap::CodeMap;
+use starlark_syntax::codemap::FileSpan;
 use starlark_syntax::codemap::FileSpanRef;
 use starlark_syntax::codemap::Spanned;
 use starlark_syntax::slice_vec_ext::SliceExt;
@@ -128,7 +129,6 @@ pub(crate) fn find_unused_loads(
 
     for top in top_level_stmts(&module_scopes.cst) {
         top.visit_ident(|ident| {
-            println!("visit ident: {:?}", ident);
             let ResolvedIdent::Slot(Slot::Module(_), binding_id) = ident
                 .payload
                 .context("ident is not resolved (internal error)")?
@@ -179,3 +179,13 @@ pub(crate) fn find_unused_loads(
 
     Ok(((*codemap).dupe(), unused))
 }
+
+/// Find the names of `load` statements which are not used, as the spans of the local names.
+pub fn find_unused_load_names(name: &str, program: &str) -> crate::Result<Vec<FileSpan>> {
+    let (codemap, unused_loads) = find_unused_loads(name, program)?;
+    Ok(unused_loads
+        .iter()
+        .flat_map(|load| &load.unused_args)
+        .map(|arg| codemap.file_span(arg.local.span))
+        .collect())
+}
@@ -22,6 +22,7 @@ use std::fmt::Write;
 use starlark_syntax::golden_test_template::golden_test_template;
 use starlark_syntax::span_display::span_display;
 
+use crate::analysis::unused_loads::find::find_unused_load_names;
 use crate::analysis::unused_loads::find::find_unused_loads;
 
 fn test_unused_loads(name: &str, program: &str) {
@@ -129,3 +130,27 @@ load("foo",
 "#,
     );
 }
+
+#[test]
+fn test_find_unused_load_names() {
+    let names = find_unused_load_names(
+        "names.bzl",
+        "load(\"foo\", \"x\", \"y\", z = \"w\")\nload(\"bar\", \"v\")\nprint(x)\n",
+    )
+    .unwrap();
+    let names: Vec<_> = names
+        .iter()
+        .map(|span| {
+            (
+                span.resolve_span().begin.line,
+                span.source_span().to_owned(),
+            )
+        })
+        .collect();
+    assert_eq!(3, names.len());
+    assert_eq!(0, names[0].0);
+    assert!(names[0].1.contains('y'), "{:?}", names);
+    assert_eq!((0, "z".to_owned()), names[1]);
+    assert_eq!(1, names[2].0);
+    assert!(names[2].1.contains('v'), "{:?}", names);
+}
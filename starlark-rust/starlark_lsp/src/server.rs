@@ -43,6 +43,7 @@ use lsp_types::notification::DidOpenTextDocument;
 use lsp_types::notification::LogMessage;
 use lsp_types::notification::PublishDiagnostics;
 use lsp_types::request::Completion;
+use lsp_types::request::Formatting;
 use lsp_types::request::GotoDefinition;
 use lsp_types::request::HoverRequest;
 use lsp_types::CompletionItem;
@@ -55,6 +56,7 @@ use lsp_types::Diagnostic;
 use lsp_types::DidChangeTextDocumentParams;
 use lsp_types::DidCloseTextDocumentParams;
 use lsp_types::DidOpenTextDocumentParams;
+use lsp_types::DocumentFormattingParams;
 use lsp_types::Documentation;
 use lsp_types::GotoDefinitionParams;
 use lsp_types::GotoDefinitionResponse;
@@ -95,6 +97,8 @@ use starlark::docs::DocMember;
 use starlark::docs::DocModule;
 use starlark::syntax::AstModule;
 use starlark_syntax::codemap::ResolvedPos;
+use starlark_syntax::format::format_starlark;
+use starlark_syntax::format::FormatOptions;
 use starlark_syntax::syntax::ast::AstPayload;
 use starlark_syntax::syntax::ast::LoadArgP;
 use starlark_syntax::syntax::module::AstModuleFields;
@@ -367,6 +371,17 @@ pub trait LspContext {
         let _unused = (document_uri, kind, current_value, workspace_root);
         Ok(Vec::new())
     }
+
+    /// Format the contents of a file, returning `None` if it should be left as it is.
+    fn format_file(&self, uri: &LspUrl, content: &str) -> anyhow::Result<Option<String>> {
+        let formatted = format_starlark(
+            &uri.path().to_string_lossy(),
+            content,
+            &FormatOptions::default(),
+        )
+        .map_err(|e| e.into_anyhow())?;
+        Ok(Some(formatted))
+    }
 }
 
 /// Errors when [`LspContext::resolve_load()`] cannot resolve a given path.
@@ -391,6 +406,9 @@ pub(crate) struct Backend<T: LspContext> {
     /// The `AstModule` from the last time that a file was opened / changed and parsed successfully.
     /// Entries are evicted when the file is closed.
     pub(crate) last_valid_parse: RwLock<HashMap<LspUrl, Arc<LspModule>>>,
+    /// The latest contents of the open files, which are formatted as they are, even when they
+    /// don't parse. Entries are evicted when the file is closed.
+    open_files: RwLock<HashMap<LspUrl, String>>,
 }
 
 /// The logic implementations of stuff
@@ -428,6 +446,7 @@ impl<T: LspContext> Backend<T> {
                 ..Default::default()
             }),
             hover_provider: Some(HoverProviderCapability::Simple(true)),
+            document_formatting_provider: Some(OneOf::Left(true)),
             ..ServerCapabilities::default()
         }
     }
@@ -452,7 +471,11 @@ impl<T: LspContext> Backend<T> {
     }
 
     fn validate(&self, uri: Url, version: Option<i64>, text: String) -> anyhow::Result<()> {
-        let uri = uri.try_into()?;
+        let uri: LspUrl = uri.try_into()?;
+        self.open_files
+            .write()
+            .unwrap()
+            .insert(uri.clone(), text.clone());
         let eval_result = self.context.parse_file_with_contents(&uri, text);
         if let Some(ast) = eval_result.ast {
             let module = Arc::new(LspModule::new(ast));
@@ -486,6 +509,10 @@ impl<T: LspContext> Backend<T> {
             let mut last_valid_parse = self.last_valid_parse.write().unwrap();
             last_valid_parse.remove(&params.text_document.uri.clone().try_into()?);
         }
+        self.open_files
+            .write()
+            .unwrap()
+            .remove(&params.text_document.uri.clone().try_into()?);
         self.publish_diagnostics(params.text_document.uri, Vec::new(), None);
         Ok(())
     }
@@ -526,6 +553,34 @@ impl<T: LspContext> Backend<T> {
         self.send_response(new_response(id, self.hover_info(params, initialize_params)));
     }
 
+    /// Format a whole open file, replacing its contents with a single edit.
+    fn formatting(&self, id: RequestId, params: DocumentFormattingParams) {
+        self.send_response(new_response(id, self.format_document(params)));
+    }
+
+    fn format_document(
+        &self,
+        params: DocumentFormattingParams,
+    ) -> anyhow::Result<Option<Vec<TextEdit>>> {
+        let uri = params.text_document.uri.try_into()?;
+        let Some(content) = self.open_files.read().unwrap().get(&uri).cloned() else {
+            return Ok(None);
+        };
+        let formatted = match self.context.format_file(&uri, &content)? {
+            Some(formatted) if formatted != content => formatted,
+            _ => return Ok(None),
+        };
+        let last_line = content.rsplit('\n').next().unwrap_or_default();
+        let end = Position::new(
+            content.matches('\n').count() as u32,
+            last_line.encode_utf16().count() as u32,
+        );
+        Ok(Some(vec![TextEdit::new(
+            Range::new(Position::new(0, 0), end),
+            formatted,
+        )]))
+    }
+
     /// Get the file contents of a starlark: URI.
     fn get_starlark_file_contents(&self, id: RequestId, params: StarlarkFileContentsParams) {
         let response: anyhow::Result<_> = match params.uri {
@@ -1246,6 +1301,8 @@ impl<T: LspContext> Backend<T> {
                         self.completion(req.id, params, &initialize_params);
                     } else if let Some(params) = as_request::<HoverRequest>(&req) {
                         self.hover(req.id, params, &initialize_params);
+                    } else if let Some(params) = as_request::<Formatting>(&req) {
+                        self.formatting(req.id, params);
                     } else if self.connection.handle_shutdown(&req)? {
                         return Ok(());
                     }
@@ -1309,6 +1366,7 @@ pub fn server_with_connection<T: LspContext>(
         connection,
         context,
         last_valid_parse: RwLock::default(),
+        open_files: RwLock::default(),
     }
     .main_loop(initialization_params)?;
 
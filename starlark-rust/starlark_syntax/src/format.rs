@@ -0,0 +1,200 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Normalization of the whitespace and quotes of Starlark files: string literals use double
+//! quotes, lines have no trailing whitespace, there is at most one blank line in a row, and files
+//! end with a single newline.
+//!
+//! This is not a full formatter like buildifier: indentation, line breaks, and the order of
+//! load symbols or list items are left as they are.
+//!
+//! Formatting only changes whitespace and string delimiters, which is checked by comparing the
+//! tokens of the file before and after formatting, so it never changes what a file means.
+
+use std::collections::HashSet;
+
+use crate::codemap::CodeMap;
+use crate::dialect::Dialect;
+use crate::lexer::Lexer;
+use crate::lexer::Token;
+
+#[derive(Debug, thiserror::Error)]
+enum FormatError {
+    #[error("Formatting `{0}` would change its tokens (internal error)")]
+    TokensChanged(String),
+}
+
+/// Options of [`format_starlark`].
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    /// Use double quotes for string literals which don't contain double quotes.
+    pub double_quotes: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            double_quotes: true,
+        }
+    }
+}
+
+fn lex(filename: &str, content: &str) -> crate::Result<Vec<(usize, Token, usize)>> {
+    let codemap = CodeMap::new(filename.to_owned(), content.to_owned());
+    Lexer::new(content, &Dialect::Extended, codemap)
+        .map(|lexeme| lexeme.map_err(|e| e.into_error()))
+        .collect()
+}
+
+/// The tokens which formatting must not change.
+fn significant_tokens(tokens: &[(usize, Token, usize)]) -> Vec<Token> {
+    let mut significant: Vec<Token> = Vec::new();
+    for (_, token, _) in tokens {
+        match token {
+            Token::Tabs => {}
+            Token::Newline if significant.last() == Some(&Token::Newline) => {}
+            Token::Comment(comment) => {
+                significant.push(Token::Comment(comment.trim_end().to_owned()))
+            }
+            token => significant.push(token.clone()),
+        }
+    }
+    significant
+}
+
+/// A single quoted string literal, `'abc'`, as `"abc"`, unless it contains double quotes.
+fn double_quoted(literal: &str) -> Option<String> {
+    let (prefix, rest) = match literal.strip_prefix('r') {
+        Some(rest) => ("r", rest),
+        None => ("", literal),
+    };
+    let (quote, double) = if rest.starts_with("'''") {
+        ("'''", "\"\"\"")
+    } else if rest.starts_with('\'') {
+        ("'", "\"")
+    } else {
+        return None;
+    };
+    let body = rest.strip_prefix(quote)?.strip_suffix(quote)?;
+    if body.contains('"') {
+        return None;
+    }
+    Some(format!("{}{}{}{}", prefix, double, body, double))
+}
+
+/// Format a Starlark file. Fails if the file can't be lexed.
+pub fn format_starlark(
+    filename: &str,
+    content: &str,
+    options: &FormatOptions,
+) -> crate::Result<String> {
+    let tokens = lex(filename, content)?;
+
+    // Lines which end inside a string literal, and so must be kept as they are.
+    let mut lines_in_strings = HashSet::new();
+    let mut replacements = Vec::new();
+    let mut line = 0;
+    let mut pos = 0;
+    for (start, token, end) in &tokens {
+        if !matches!(token, Token::String(_) | Token::FString(_)) {
+            continue;
+        }
+        line += content[pos..*start].matches('\n').count();
+        pos = *start;
+        let literal = &content[*start..*end];
+        lines_in_strings.extend(line..line + literal.matches('\n').count());
+        if options.double_quotes && matches!(token, Token::String(_)) {
+            if let Some(literal) = double_quoted(literal) {
+                replacements.push((*start, *end, literal));
+            }
+        }
+    }
+
+    // Replacements don't add or remove newlines, so lines keep their numbers.
+    let mut text = String::with_capacity(content.len());
+    let mut pos = 0;
+    for (start, end, literal) in replacements {
+        text.push_str(&content[pos..start]);
+        text.push_str(&literal);
+        pos = end;
+    }
+    text.push_str(&content[pos..]);
+
+    let mut formatted = String::with_capacity(text.len());
+    let mut pending_blank_line = false;
+    for (i, line) in text.split('\n').enumerate() {
+        let in_string = lines_in_strings.contains(&i);
+        let line = if in_string { line } else { line.trim_end() };
+        if line.is_empty() && !in_string {
+            // Blank lines at the start of the file are dropped.
+            pending_blank_line = !formatted.is_empty();
+            continue;
+        }
+        if pending_blank_line {
+            formatted.push('\n');
+            pending_blank_line = false;
+        }
+        formatted.push_str(line);
+        formatted.push('\n');
+    }
+
+    if significant_tokens(&lex(filename, &formatted)?) != significant_tokens(&tokens) {
+        return Err(crate::Error::new_other(FormatError::TokensChanged(
+            filename.to_owned(),
+        )));
+    }
+    Ok(formatted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(content: &str) -> String {
+        format_starlark("test.bzl", content, &FormatOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_whitespace() {
+        assert_eq!(
+            "x = 1\n\ndef f():\n    return x\n",
+            format("\n\nx = 1   \n\n\n\ndef f():\t\n    return x")
+        );
+        assert_eq!("", format("\n\n"));
+    }
+
+    #[test]
+    fn test_quotes() {
+        assert_eq!(
+            "x = \"a\"\ny = 'b\"'\nz = r\"c\\d\"\n",
+            format("x = 'a'\ny = 'b\"'\nz = r'c\\d'\n")
+        );
+        let preserve = FormatOptions {
+            double_quotes: false,
+        };
+        assert_eq!(
+            "x = 'a'\n",
+            format_starlark("test.bzl", "x = 'a'\n", &preserve).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_multiline_strings_kept() {
+        let content = "x = '''\na  \n\n\nb'''\n";
+        assert_eq!("x = \"\"\"\na  \n\n\nb\"\"\"\n", format(content));
+    }
+}
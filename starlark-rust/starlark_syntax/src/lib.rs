@@ -38,6 +38,7 @@ pub mod dot_format_parser;
 pub mod error;
 pub mod eval_exception;
 pub mod fast_string;
+pub mod format;
 pub mod frame;
 pub mod golden_test_template;
 pub mod lexer;
@@ -103,12 +103,14 @@ const manualSidebar = [
           'users/build_observability/interactive_console',
           'users/build_observability/logging',
           'users/build_observability/build_report',
+          'users/build_observability/graph_budgets',
           isInternal() ? 'users/build_observability/observability' : [],
           isInternal() ? 'users/build_observability/scuba' : [],
           isInternal() ? 'users/build_observability/ods' : [],
         ],
       },
       'users/remote_execution',
+      'users/audit_providers_json',
       {
         type: 'category',
         label: 'Queries',